@@ -0,0 +1,2 @@
+-- Banned users can't sign in, and their tokens are rejected
+ALTER TABLE app.user ADD COLUMN banned_at timestamptz;
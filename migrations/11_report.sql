@@ -0,0 +1,18 @@
+-- Articles and comments reported by users, for moderators to look at
+CREATE TABLE app.report
+(
+    report_id bigserial PRIMARY KEY,
+    reporter_user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    article_id uuid NOT NULL REFERENCES app.article (article_id) ON DELETE CASCADE,
+    -- Set when a comment on the article was reported, rather than the article itself
+    comment_id bigint REFERENCES app.article_comment (comment_id) ON DELETE CASCADE,
+    reason text NOT NULL,
+    resolved_at timestamptz,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz
+);
+
+SELECT app.trigger_updated_at('app."report"');
+
+CREATE INDEX ON app.report (created_at);
@@ -0,0 +1,9 @@
+-- Articles saved for later. Unlike favorites, these are only visible to the user themselves.
+CREATE TABLE app.bookmark
+(
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    article_id uuid NOT NULL REFERENCES app.article (article_id) ON DELETE CASCADE,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, article_id)
+);
@@ -0,0 +1,10 @@
+-- Users whose articles and comments are hidden from the blocking user
+CREATE TABLE app.block
+(
+    blocking_user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    blocked_user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    CONSTRAINT user_cannot_block_self CHECK (blocking_user_id != blocked_user_id),
+    PRIMARY KEY (blocking_user_id, blocked_user_id)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE app.comment_like
+(
+    comment_id bigint NOT NULL REFERENCES app.article_comment (comment_id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (comment_id, user_id)
+);
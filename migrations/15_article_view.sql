@@ -0,0 +1,17 @@
+-- Who viewed which article on which day, so that a view is only counted once per viewer and day.
+-- Views of earlier days are purged, while their counts are kept in `article_view_count`.
+CREATE TABLE app.article_view
+(
+    article_id uuid NOT NULL REFERENCES app.article (article_id) ON DELETE CASCADE,
+    -- `user:<user id>` or `ip:<address>`
+    viewer text NOT NULL,
+    day date NOT NULL,
+    PRIMARY KEY (article_id, viewer, day)
+);
+
+-- Kept apart from `article`, so that counting views doesn't touch its `updated_at`
+CREATE TABLE app.article_view_count
+(
+    article_id uuid PRIMARY KEY REFERENCES app.article (article_id) ON DELETE CASCADE,
+    views_count bigint NOT NULL
+);
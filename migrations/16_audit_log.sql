@@ -0,0 +1,48 @@
+CREATE TYPE app.audit_action AS ENUM (
+    'login',
+    'failed_login',
+    'password_change',
+    'email_change',
+    'article_delete',
+    'user_ban',
+    'user_unban',
+    'comment_delete'
+);
+
+-- Security-relevant actions, for admins to look into.
+-- The user isn't a foreign key, so that entries outlive deleted users.
+CREATE TABLE app.audit_log
+(
+    audit_id bigserial PRIMARY KEY,
+    -- Who did it, when known
+    user_id uuid,
+    action app.audit_action NOT NULL,
+    -- What it was done to, e.g. a slug, a username or the email address of a failed login
+    target text,
+    ip_address text,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON app.audit_log (created_at);
+CREATE INDEX ON app.audit_log (user_id, created_at);
+
+-- The log is append-only
+CREATE FUNCTION app.reject_audit_log_change()
+    returns trigger as
+$$
+BEGIN
+    RAISE EXCEPTION 'app.audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE
+    ON app.audit_log
+    FOR EACH ROW
+EXECUTE FUNCTION app.reject_audit_log_change();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE
+    ON app.audit_log
+    FOR EACH STATEMENT
+EXECUTE FUNCTION app.reject_audit_log_change();
@@ -0,0 +1,22 @@
+-- One session per sign-in, lasting as long as its refresh token keeps being exchanged
+CREATE TABLE app.session
+(
+    session_id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    -- The User-Agent of the sign-in
+    device text,
+    expires_at timestamptz NOT NULL,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    last_seen_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON app.session (user_id);
+
+-- Refresh tokens from before sessions belong to none, so those users sign in again
+DELETE FROM app.refresh_token;
+
+ALTER TABLE app.refresh_token
+    ADD COLUMN session_id uuid NOT NULL REFERENCES app.session (session_id) ON DELETE CASCADE;
+
+CREATE INDEX ON app.refresh_token (session_id);
@@ -0,0 +1,16 @@
+CREATE TYPE app.data_export_status AS ENUM ('pending', 'running', 'ready', 'failed');
+
+-- Archives of a user's data, compiled in the background when asked for
+CREATE TABLE app.data_export
+(
+    data_export_id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    status app.data_export_status NOT NULL DEFAULT 'pending',
+    -- Where the archive can be downloaded from, once ready
+    url text,
+    requested_at timestamptz NOT NULL DEFAULT now(),
+    finished_at timestamptz
+);
+
+CREATE INDEX ON app.data_export (user_id, requested_at);
+CREATE INDEX ON app.data_export (requested_at) WHERE status = 'pending';
@@ -0,0 +1,9 @@
+-- Usernames that users have renamed themselves from, so that links to their old profiles keep working
+CREATE TABLE app.username_history
+(
+    username text COLLATE "case_insensitive" NOT NULL,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    changed_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON app.username_history (username, changed_at);
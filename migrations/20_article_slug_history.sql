@@ -0,0 +1,9 @@
+-- Slugs that articles had before their title was edited, so that links to them keep working
+CREATE TABLE app.article_slug_history
+(
+    slug text NOT NULL,
+    article_id uuid NOT NULL REFERENCES app.article (article_id) ON DELETE CASCADE,
+    changed_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON app.article_slug_history (slug, changed_at);
@@ -0,0 +1,13 @@
+-- Comments with their author and likes, the projection shared by every query returning a comment
+CREATE VIEW app.comment_view AS
+SELECT
+    comment.comment_id,
+    comment.article_id,
+    comment.created_at,
+    comment.updated_at,
+    comment.body,
+    author.user_id author_id,
+    author.username author_username,
+    (SELECT count(*) FROM app.comment_like l WHERE l.comment_id = comment.comment_id) likes_count
+FROM app.article_comment comment
+INNER JOIN app."user" author USING (user_id);
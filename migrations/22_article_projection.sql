@@ -0,0 +1,22 @@
+-- Articles with their author and counts, the projection shared by every query returning an article.
+-- Whether the current user favorited an article is left to those queries.
+-- (`article_view` is already taken by the views of articles.)
+CREATE VIEW app.article_projection AS
+SELECT
+    article.article_id,
+    article.slug,
+    article.title,
+    article.description,
+    article.body,
+    article.tag_list,
+    article.created_at,
+    article.updated_at,
+    (SELECT count(*) FROM app.article_favorite fav WHERE fav.article_id = article.article_id) favorites_count,
+    COALESCE(
+        (SELECT views_count FROM app.article_view_count WHERE article_id = article.article_id),
+        0
+    ) views_count,
+    author.user_id author_id,
+    author.username author_username
+FROM app.article article
+INNER JOIN app."user" author USING (user_id);
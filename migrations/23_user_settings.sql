@@ -0,0 +1,7 @@
+-- Preferences of users who saved them. Keys missing from `settings` get their defaults when read.
+CREATE TABLE app.user_settings
+(
+    user_id uuid PRIMARY KEY REFERENCES app.user (user_id) ON DELETE CASCADE,
+    settings jsonb NOT NULL,
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,24 @@
+-- Unlisted articles are left out of every listing, and shared by a link with a random token.
+-- Public articles have no token.
+ALTER TABLE app.article ADD COLUMN share_token text UNIQUE;
+
+CREATE OR REPLACE VIEW app.article_projection AS
+SELECT
+    article.article_id,
+    article.slug,
+    article.title,
+    article.description,
+    article.body,
+    article.tag_list,
+    article.created_at,
+    article.updated_at,
+    (SELECT count(*) FROM app.article_favorite fav WHERE fav.article_id = article.article_id) favorites_count,
+    COALESCE(
+        (SELECT views_count FROM app.article_view_count WHERE article_id = article.article_id),
+        0
+    ) views_count,
+    author.user_id author_id,
+    author.username author_username,
+    article.share_token
+FROM app.article article
+INNER JOIN app."user" author USING (user_id);
@@ -0,0 +1,15 @@
+-- The articles in the feed of each user, written when articles are created and authors followed,
+-- so that reading a feed doesn't join the follows of the reader. Only used with `--materialized-feeds`.
+CREATE TABLE app.feed_entry
+(
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    article_id uuid NOT NULL REFERENCES app.article (article_id) ON DELETE CASCADE,
+    -- the author and creation time of the article, for unfollowing and for reading a feed in order
+    author_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+
+    PRIMARY KEY (user_id, article_id)
+);
+
+CREATE INDEX feed_entry_user_created_at ON app.feed_entry (user_id, created_at DESC);
+CREATE INDEX feed_entry_user_author ON app.feed_entry (user_id, author_id);
@@ -0,0 +1,33 @@
+-- The users mentioned with `@username` in a comment
+CREATE TABLE app.comment_mention
+(
+    comment_id bigint NOT NULL REFERENCES app.article_comment (comment_id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX comment_mention_user_id ON app.comment_mention (user_id);
+
+ALTER TYPE app.notification_kind ADD VALUE 'mentioned';
+
+-- New columns of a view have to come last
+CREATE OR REPLACE VIEW app.comment_view AS
+SELECT
+    comment.comment_id,
+    comment.article_id,
+    comment.created_at,
+    comment.updated_at,
+    comment.body,
+    author.user_id author_id,
+    author.username author_username,
+    (SELECT count(*) FROM app.comment_like l WHERE l.comment_id = comment.comment_id) likes_count,
+    ARRAY(
+        SELECT mentioned.username
+        FROM app.comment_mention m
+        INNER JOIN app."user" mentioned USING (user_id)
+        WHERE m.comment_id = comment.comment_id
+        ORDER BY mentioned.username
+    ) mentions
+FROM app.article_comment comment
+INNER JOIN app."user" author USING (user_id);
@@ -0,0 +1,7 @@
+-- The archive is kept with its export, downloaded only by its signed in user,
+-- rather than in public blob storage
+ALTER TABLE app.data_export DROP COLUMN url;
+ALTER TABLE app.data_export ADD COLUMN archive bytea;
+
+-- Exports made ready before had their archive elsewhere, so asking again compiles a new one
+UPDATE app.data_export SET status = 'failed' WHERE status = 'ready';
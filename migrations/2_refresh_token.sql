@@ -0,0 +1,16 @@
+-- Refresh tokens are opaque secrets handed out at sign-in. Only their hash is stored,
+-- so a leaked database dump can't be used to mint new access tokens.
+CREATE TABLE app.refresh_token
+(
+    refresh_token_id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    token_hash text UNIQUE NOT NULL,
+    expires_at timestamptz NOT NULL,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz
+);
+
+SELECT app.trigger_updated_at('app."refresh_token"');
+
+CREATE INDEX ON app.refresh_token (user_id);
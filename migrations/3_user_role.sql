@@ -0,0 +1,3 @@
+CREATE TYPE app.user_role AS ENUM ('user', 'moderator', 'admin');
+
+ALTER TABLE app.user ADD COLUMN role app.user_role NOT NULL DEFAULT 'user';
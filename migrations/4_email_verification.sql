@@ -0,0 +1,18 @@
+-- Users that signed up before email verification was introduced are considered verified,
+-- hence the default is flipped after adding the column.
+ALTER TABLE app.user ADD COLUMN email_verified boolean NOT NULL DEFAULT true;
+ALTER TABLE app.user ALTER COLUMN email_verified SET DEFAULT false;
+
+CREATE TABLE app.email_verification
+(
+    token_hash text PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    expires_at timestamptz NOT NULL,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz
+);
+
+SELECT app.trigger_updated_at('app."email_verification"');
+
+CREATE INDEX ON app.email_verification (user_id);
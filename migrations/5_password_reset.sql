@@ -0,0 +1,13 @@
+CREATE TABLE app.password_reset
+(
+    token_hash text PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    expires_at timestamptz NOT NULL,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz
+);
+
+SELECT app.trigger_updated_at('app."password_reset"');
+
+CREATE INDEX ON app.password_reset (user_id);
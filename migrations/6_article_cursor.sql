@@ -0,0 +1,2 @@
+-- Keyset pagination of articles, newest first
+CREATE INDEX ON app.article (created_at DESC, slug DESC);
@@ -0,0 +1,21 @@
+CREATE TYPE app.notification_kind AS ENUM ('followed', 'favorited', 'commented');
+
+CREATE TABLE app.notification
+(
+    notification_id bigserial PRIMARY KEY,
+    -- The user who gets notified
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    -- The user who did something
+    actor_user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+    kind app.notification_kind NOT NULL,
+    -- The article that was favorited or commented
+    article_id uuid REFERENCES app.article (article_id) ON DELETE CASCADE,
+    read_at timestamptz,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz
+);
+
+SELECT app.trigger_updated_at('app."notification"');
+
+CREATE INDEX ON app.notification (user_id, created_at);
@@ -0,0 +1,11 @@
+-- Failed logins, for locking out password guessing
+CREATE TABLE app.login_attempt
+(
+    login_attempt_id bigserial PRIMARY KEY,
+    email text COLLATE "case_insensitive" NOT NULL,
+    ip_address text,
+    attempted_at timestamptz NOT NULL
+);
+
+CREATE INDEX ON app.login_attempt (email, attempted_at);
+CREATE INDEX ON app.login_attempt (ip_address, attempted_at);
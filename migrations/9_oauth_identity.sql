@@ -0,0 +1,13 @@
+-- Identities at external OAuth providers that users sign in with
+CREATE TABLE app.oauth_identity
+(
+    provider text NOT NULL,
+    subject text NOT NULL,
+    user_id uuid NOT NULL REFERENCES app.user (user_id) ON DELETE CASCADE,
+
+    created_at timestamptz NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (provider, subject)
+);
+
+CREATE INDEX ON app.oauth_identity (user_id);
@@ -1,20 +1,174 @@
+use crate::blob_storage::BlobStorage;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::comment_events::CommentBroadcaster;
 use crate::config::Config;
+use crate::email::Mailer;
+use crate::events::EventBus;
+use crate::feed_cache::FeedCacheStore;
+use crate::live_config::LiveConfig;
+use crate::metrics::Metrics;
+use crate::oauth::OAuthProviders;
+use crate::retry::{RetryPolicy, RetryTransient, Retrying};
+use crate::revocation::TokenRevocationStore;
+use crate::tenant::Tenants;
+use crate::views::ViewBuffer;
 
+use realworld_domain::article::feed_cache::FeedPage;
+use realworld_domain::article::repo::ArticleView;
+use realworld_domain::article::tag::TagRules;
+use realworld_domain::article::{Article, Slug};
+use realworld_domain::comment::Comment;
+use realworld_domain::error::RwResult;
+use realworld_domain::event::Event;
+use realworld_domain::user::email::EmailMessage;
+use realworld_domain::user::jwt_keys::JwtKeys;
+use realworld_domain::user::oauth::OAuthIdentity;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::UserId;
+use realworld_domain::GetConfig;
+
+use futures::stream::BoxStream;
 use std::sync::Arc;
 use time::OffsetDateTime;
 
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("either the `postgres` or the `sqlite` feature must be enabled");
+
+///
+/// The storage backend: Postgres by default, SQLite with the `sqlite` feature.
+/// Query durations are only recorded for Postgres.
+///
+#[cfg(not(feature = "sqlite"))]
+pub mod backend {
+    pub use realworld_db::{
+        transient_failure, Db, GetDb, PoolConfig, PoolStatus, SchemaStatus, TransientFailure,
+    };
+
+    pub type UserRepo = realworld_db::user::PgUserRepo;
+    pub type RefreshTokenRepo = realworld_db::refresh_token::PgRefreshTokenRepo;
+    pub type SessionRepo = realworld_db::session::PgSessionRepo;
+    pub type EmailVerificationRepo = realworld_db::email_verification::PgEmailVerificationRepo;
+    pub type PasswordResetRepo = realworld_db::password_reset::PgPasswordResetRepo;
+    pub type LoginAttemptRepo = realworld_db::login_attempt::PgLoginAttemptRepo;
+    pub type BanRepo = realworld_db::ban::PgBanRepo;
+    pub type ArticleRepo = realworld_db::article::PgArticleRepo;
+    pub type BookmarkRepo = realworld_db::bookmark::PgBookmarkRepo;
+    pub type FeedRepo = realworld_db::feed::PgFeedRepo;
+    pub type ViewRepo = realworld_db::view::PgViewRepo;
+    pub type CommentRepo = realworld_db::comment::PgCommentRepo;
+    pub type DataExportRepo = realworld_db::data_export::PgDataExportRepo;
+    pub type NotificationRepo = realworld_db::notification::PgNotificationRepo;
+    pub type ReportRepo = realworld_db::report::PgReportRepo;
+    pub type RecommendationRepo = realworld_db::recommendation::PgRecommendationRepo;
+    pub type SettingsRepo = realworld_db::settings::PgSettingsRepo;
+    pub type StatsRepo = realworld_db::stats::PgStatsRepo;
+    pub type AuditLogRepo = realworld_db::audit::PgAuditLogRepo;
+}
+
+#[cfg(feature = "sqlite")]
+pub mod backend {
+    pub use realworld_db_sqlite::{
+        transient_failure, Db, GetDb, PoolConfig, PoolStatus, SchemaStatus, TransientFailure,
+    };
+
+    pub type UserRepo = realworld_db_sqlite::user::SqliteUserRepo;
+    pub type RefreshTokenRepo = realworld_db_sqlite::refresh_token::SqliteRefreshTokenRepo;
+    pub type SessionRepo = realworld_db_sqlite::session::SqliteSessionRepo;
+    pub type EmailVerificationRepo =
+        realworld_db_sqlite::email_verification::SqliteEmailVerificationRepo;
+    pub type PasswordResetRepo = realworld_db_sqlite::password_reset::SqlitePasswordResetRepo;
+    pub type LoginAttemptRepo = realworld_db_sqlite::login_attempt::SqliteLoginAttemptRepo;
+    pub type BanRepo = realworld_db_sqlite::ban::SqliteBanRepo;
+    pub type ArticleRepo = realworld_db_sqlite::article::SqliteArticleRepo;
+    pub type BookmarkRepo = realworld_db_sqlite::bookmark::SqliteBookmarkRepo;
+    pub type FeedRepo = realworld_db_sqlite::feed::SqliteFeedRepo;
+    pub type ViewRepo = realworld_db_sqlite::view::SqliteViewRepo;
+    pub type CommentRepo = realworld_db_sqlite::comment::SqliteCommentRepo;
+    pub type DataExportRepo = realworld_db_sqlite::data_export::SqliteDataExportRepo;
+    pub type NotificationRepo = realworld_db_sqlite::notification::SqliteNotificationRepo;
+    pub type ReportRepo = realworld_db_sqlite::report::SqliteReportRepo;
+    pub type RecommendationRepo = realworld_db_sqlite::recommendation::SqliteRecommendationRepo;
+    pub type SettingsRepo = realworld_db_sqlite::settings::SqliteSettingsRepo;
+    pub type StatsRepo = realworld_db_sqlite::stats::SqliteStatsRepo;
+    pub type AuditLogRepo = realworld_db_sqlite::audit::SqliteAuditLogRepo;
+}
+
 #[derive(Clone)]
 pub struct App {
     pub config: Arc<Config>,
-    pub db: realworld_db::Db,
+    /// The settings that can change while serving, shared with the apps of tenants
+    pub live_config: LiveConfig,
+    pub jwt_keys: JwtKeys,
+    /// What the domain reads through [GetConfig], normally [ConfigSettings]
+    pub domain_config: Arc<dyn GetConfig + Send + Sync>,
+    pub db: backend::Db,
+    pub mailer: Mailer,
+    pub oauth: OAuthProviders,
+    pub blob_storage: BlobStorage,
+    pub feed_cache: FeedCacheStore,
+    pub revoked_tokens: TokenRevocationStore,
+    pub comment_events: CommentBroadcaster,
+    pub events: EventBus,
+    pub metrics: Metrics,
+    pub views: ViewBuffer,
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    pub retry_policy: RetryPolicy,
+    /// Empty in the apps of the tenants themselves
+    pub tenants: Tenants,
+    /// Used instead of the backend, see [crate::AppBuilder]
+    #[cfg(feature = "dyn-repos")]
+    pub custom_repos: CustomRepos,
+}
+
+///
+/// Repositories replacing those of the backend, when given.
+///
+#[cfg(feature = "dyn-repos")]
+#[derive(Clone, Default)]
+pub struct CustomRepos {
+    pub user: Option<Arc<dyn realworld_domain::user::repo::UserRepoImpl<App> + Send + Sync>>,
+    pub article:
+        Option<Arc<dyn realworld_domain::article::repo::ArticleRepoImpl<App> + Send + Sync>>,
+    pub comment:
+        Option<Arc<dyn realworld_domain::comment::repo::CommentRepoImpl<App> + Send + Sync>>,
 }
 
 // Implement the leaf dependency from realworld_db for the App.
 // `<Impl<T> as GetDb>::get_db` will delegate in its implementation
 // back to the 'native' implementation for `T`.
 // So here we make the circle complete:
-impl realworld_db::GetDb for App {
-    fn get_db(&self) -> &realworld_db::Db {
+#[cfg(not(feature = "sqlite"))]
+impl backend::GetDb for App {
+    fn get_db(&self) -> realworld_db::DbRef<'_> {
+        realworld_db::DbRef::observed_by(&self.db, self)
+    }
+}
+
+// Slow queries are logged within the span of the request they were part of
+#[cfg(not(feature = "sqlite"))]
+impl realworld_db::QueryObserver for App {
+    fn observe_query(
+        &self,
+        caller: &'static std::panic::Location<'static>,
+        elapsed: std::time::Duration,
+    ) {
+        let query = format!("{}:{}", caller.file(), caller.line());
+        self.metrics.record_db_query(&query, elapsed);
+
+        if elapsed.as_millis() >= u128::from(self.config.slow_query_threshold_ms) {
+            self.metrics.record_slow_db_query(&query);
+            tracing::warn!(
+                query,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "slow database query"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl backend::GetDb for App {
+    fn get_db(&self) -> &backend::Db {
         &self.db
     }
 }
@@ -25,20 +179,467 @@ impl realworld_domain::System for App {
     }
 }
 
-impl realworld_domain::GetConfig for App {
-    fn get_jwt_signing_key(&self) -> &hmac::Hmac<sha2::Sha384> {
-        &self.config.jwt_signing_key.0
+///
+/// The [GetConfig] of the domain, taken from the [Config].
+///
+pub struct ConfigSettings {
+    config: Arc<Config>,
+    tag_rules: TagRules,
+}
+
+impl ConfigSettings {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            tag_rules: config.tag_rules(),
+            config,
+        }
     }
 }
 
-impl realworld_domain::user::repo::DelegateUserRepo<Self> for App {
-    type Target = realworld_db::user::PgUserRepo;
+impl GetConfig for ConfigSettings {
+    fn require_email_verification(&self) -> bool {
+        self.config.require_email_verification
+    }
+
+    fn max_failed_logins(&self) -> u32 {
+        self.config.max_failed_logins
+    }
+
+    fn failed_login_window(&self) -> time::Duration {
+        time::Duration::seconds(self.config.failed_login_window_secs as i64)
+    }
+
+    fn tag_rules(&self) -> &TagRules {
+        &self.tag_rules
+    }
+
+    fn words_per_minute(&self) -> u32 {
+        self.config.words_per_minute
+    }
+
+    fn trending_window(&self) -> time::Duration {
+        time::Duration::hours(self.config.trending_window_hours as i64)
+    }
+
+    fn max_avatar_bytes(&self) -> usize {
+        self.config.max_avatar_bytes
+    }
+
+    fn max_article_body_bytes(&self) -> usize {
+        self.config.max_article_body_bytes
+    }
+
+    fn reserved_usernames(&self) -> &[String] {
+        &self.config.reserved_usernames
+    }
+
+    fn site_url(&self) -> &str {
+        &self.config.site_url
+    }
+
+    fn account_deletion_mode(&self) -> realworld_domain::user::repo::DeletionMode {
+        use crate::config::AccountDeletion;
+        use realworld_domain::user::repo::DeletionMode;
+
+        match self.config.account_deletion {
+            AccountDeletion::Delete => DeletionMode::Delete,
+            AccountDeletion::Anonymize => DeletionMode::Anonymize,
+        }
+    }
+
+    fn page_sizes(&self) -> realworld_domain::pagination::PageSizes {
+        self.config.page_sizes()
+    }
+
+    fn materialized_feeds(&self) -> bool {
+        self.config.materialized_feeds
+    }
+
+    fn plus_addressing(&self) -> realworld_domain::user::email::PlusAddressing {
+        use crate::config::PlusAddressing;
+        use realworld_domain::user::email;
+
+        match self.config.plus_addressing {
+            PlusAddressing::Keep => email::PlusAddressing::Keep,
+            PlusAddressing::Strip => email::PlusAddressing::Strip,
+        }
+    }
+
+    fn password_algorithm(&self) -> realworld_domain::user::password::PasswordAlgorithm {
+        use crate::config::PasswordAlgorithm;
+        use realworld_domain::user::password;
+
+        match self.config.password_algorithm {
+            PasswordAlgorithm::Argon2id => password::PasswordAlgorithm::Argon2id,
+            PasswordAlgorithm::Bcrypt => password::PasswordAlgorithm::Bcrypt,
+            PasswordAlgorithm::Scrypt => password::PasswordAlgorithm::Scrypt,
+        }
+    }
+
+    fn sanitize_mode(&self) -> realworld_domain::sanitize::SanitizeMode {
+        use crate::config::HtmlSanitizing;
+        use realworld_domain::sanitize::SanitizeMode;
+
+        match self.config.html_sanitizing {
+            HtmlSanitizing::Strip => SanitizeMode::Strip,
+            HtmlSanitizing::Escape => SanitizeMode::Escape,
+            HtmlSanitizing::Markdown => SanitizeMode::Markdown,
+        }
+    }
+
+    fn slug_strategy(&self) -> realworld_domain::article::slugify::SlugStrategy {
+        use crate::config::SlugStrategy;
+        use realworld_domain::article::slugify;
+
+        match self.config.slug_strategy {
+            SlugStrategy::Transliterate => slugify::SlugStrategy::Transliterate,
+            SlugStrategy::Unicode => slugify::SlugStrategy::Unicode,
+        }
+    }
 }
 
-impl realworld_domain::article::repo::DelegateArticleRepo<Self> for App {
-    type Target = realworld_db::article::PgArticleRepo;
+impl GetConfig for App {
+    fn require_email_verification(&self) -> bool {
+        self.domain_config.require_email_verification()
+    }
+
+    fn max_failed_logins(&self) -> u32 {
+        self.domain_config.max_failed_logins()
+    }
+
+    fn failed_login_window(&self) -> time::Duration {
+        self.domain_config.failed_login_window()
+    }
+
+    fn tag_rules(&self) -> &TagRules {
+        self.domain_config.tag_rules()
+    }
+
+    fn words_per_minute(&self) -> u32 {
+        self.domain_config.words_per_minute()
+    }
+
+    fn trending_window(&self) -> time::Duration {
+        self.domain_config.trending_window()
+    }
+
+    fn max_avatar_bytes(&self) -> usize {
+        self.domain_config.max_avatar_bytes()
+    }
+
+    fn max_article_body_bytes(&self) -> usize {
+        self.domain_config.max_article_body_bytes()
+    }
+
+    fn reserved_usernames(&self) -> &[String] {
+        self.domain_config.reserved_usernames()
+    }
+
+    fn site_url(&self) -> &str {
+        self.domain_config.site_url()
+    }
+
+    fn account_deletion_mode(&self) -> realworld_domain::user::repo::DeletionMode {
+        self.domain_config.account_deletion_mode()
+    }
+
+    fn page_sizes(&self) -> realworld_domain::pagination::PageSizes {
+        self.domain_config.page_sizes()
+    }
+
+    fn materialized_feeds(&self) -> bool {
+        self.domain_config.materialized_feeds()
+    }
+
+    fn plus_addressing(&self) -> realworld_domain::user::email::PlusAddressing {
+        self.domain_config.plus_addressing()
+    }
+
+    fn password_algorithm(&self) -> realworld_domain::user::password::PasswordAlgorithm {
+        self.domain_config.password_algorithm()
+    }
+
+    fn sanitize_mode(&self) -> realworld_domain::sanitize::SanitizeMode {
+        self.domain_config.sanitize_mode()
+    }
+
+    fn slug_strategy(&self) -> realworld_domain::article::slugify::SlugStrategy {
+        self.domain_config.slug_strategy()
+    }
 }
 
-impl realworld_domain::comment::repo::DelegateCommentRepo<Self> for App {
-    type Target = realworld_db::comment::PgCommentRepo;
+impl realworld_domain::admin::ReloadSettings for App {
+    fn reload_settings(&self) -> RwResult<()> {
+        // An internal error, whose reason is only logged, since the config file may hold secrets
+        Ok(self.live_config.reload()?)
+    }
 }
+
+impl realworld_domain::user::jwt_keys::JwtKeyProvider for App {
+    fn jwt_keys(&self) -> &JwtKeys {
+        &self.jwt_keys
+    }
+}
+
+impl realworld_domain::user::oauth::OAuthProvider for App {
+    fn oauth_authorize_url(&self, provider: &str, state: &str) -> RwResult<String> {
+        self.oauth.authorize_url(provider, state)
+    }
+
+    async fn fetch_oauth_identity(&self, provider: &str, code: &str) -> RwResult<OAuthIdentity> {
+        self.oauth.fetch_identity(provider, code).await
+    }
+}
+
+impl realworld_domain::user::avatar::BlobStorage for App {
+    async fn put_blob(&self, key: &str, content_type: &str, data: Vec<u8>) -> RwResult<String> {
+        self.blob_storage.put(key, content_type, data).await
+    }
+}
+
+impl realworld_domain::article::markdown::RenderMarkdown for App {
+    fn render_markdown(&self, markdown: &str) -> String {
+        crate::markdown::render(markdown)
+    }
+}
+
+impl realworld_domain::RecordMetrics for App {
+    fn record_auth_failure(&self) {
+        self.metrics.record_auth_failure();
+    }
+}
+
+impl RetryTransient for App {
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn record_retry(&self, operation: &'static str, failure: backend::TransientFailure) {
+        self.metrics.record_db_retry(operation, failure.as_str());
+    }
+}
+
+impl realworld_domain::EmailSender for App {
+    async fn send_email(&self, message: EmailMessage) -> RwResult<()> {
+        self.mailer.send(message).await
+    }
+}
+
+impl realworld_domain::event::DomainEvents for App {
+    fn publish(&self, event: Event) {
+        self.events.publish(&event)
+    }
+}
+
+impl realworld_domain::user::revocation::TokenRevocationList for App {
+    async fn revoke_token(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        self.revoked_tokens.revoke(token_hash, expires_at).await
+    }
+
+    async fn is_token_revoked(&self, token_hash: &OpaqueTokenHash) -> RwResult<bool> {
+        self.revoked_tokens.is_revoked(token_hash).await
+    }
+}
+
+impl realworld_domain::article::feed_cache::FeedCache for App {
+    async fn get_cached_feed(&self, user_id: UserId, page: FeedPage) -> Option<Vec<Article>> {
+        self.feed_cache.get(user_id, page).await
+    }
+
+    async fn cache_feed(&self, user_id: UserId, page: FeedPage, articles: Vec<Article>) {
+        self.feed_cache.insert(user_id, page, articles).await
+    }
+
+    async fn invalidate_feed(&self, user_id: UserId) {
+        self.feed_cache.invalidate(user_id).await
+    }
+
+    async fn invalidate_all_feeds(&self) {
+        self.feed_cache.clear().await
+    }
+}
+
+impl realworld_domain::article::views::RecordView for App {
+    fn record_view(&self, view: ArticleView) {
+        self.views.push(view)
+    }
+}
+
+impl realworld_domain::comment::events::CommentEvents for App {
+    fn publish_comment(&self, article_slug: &Slug, comment: &Comment) {
+        self.comment_events.publish(article_slug, comment)
+    }
+
+    fn subscribe_comments(&self, article_slug: &Slug) -> BoxStream<'static, Comment> {
+        self.comment_events.subscribe(article_slug)
+    }
+}
+
+///
+/// Delegates a repository trait of the [App] to the backend, retried on transient failures.
+///
+/// With the `dyn-repos` feature the repository is a trait object, instead of being part of the type.
+/// This keeps the repositories from being instantiated for every function that uses them,
+/// at the cost of a virtual call and a boxed future per repository method.
+/// A repository named by `custom` can then be replaced, see [CustomRepos].
+///
+macro_rules! delegate_repo {
+    ($($module:ident)::+, $delegate:ident, $repo_impl:ident, $repo:ident $(, custom = $custom:ident)?) => {
+        #[cfg(not(feature = "dyn-repos"))]
+        impl $($module::)+$delegate<Self> for App {
+            type Target = Retrying<backend::$repo>;
+        }
+
+        #[cfg(feature = "dyn-repos")]
+        impl AsRef<dyn $($module::)+$repo_impl<Self> + Sync> for App {
+            fn as_ref(&self) -> &(dyn $($module::)+$repo_impl<Self> + Sync) {
+                $(
+                    if let Some(repo) = &self.custom_repos.$custom {
+                        return &**repo;
+                    }
+                )?
+                &Retrying(backend::$repo {})
+            }
+        }
+    };
+}
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateUserRepo,
+    UserRepoImpl,
+    UserRepo,
+    custom = user
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateRefreshTokenRepo,
+    RefreshTokenRepoImpl,
+    RefreshTokenRepo
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateSessionRepo,
+    SessionRepoImpl,
+    SessionRepo
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateEmailVerificationRepo,
+    EmailVerificationRepoImpl,
+    EmailVerificationRepo
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegatePasswordResetRepo,
+    PasswordResetRepoImpl,
+    PasswordResetRepo
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateLoginAttemptRepo,
+    LoginAttemptRepoImpl,
+    LoginAttemptRepo
+);
+
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateBanRepo,
+    BanRepoImpl,
+    BanRepo
+);
+
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateArticleRepo,
+    ArticleRepoImpl,
+    ArticleRepo,
+    custom = article
+);
+
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateBookmarkRepo,
+    BookmarkRepoImpl,
+    BookmarkRepo
+);
+
+delegate_repo!(
+    realworld_domain::article::feed,
+    DelegateFeedRepo,
+    FeedRepoImpl,
+    FeedRepo
+);
+
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateViewRepo,
+    ViewRepoImpl,
+    ViewRepo
+);
+
+delegate_repo!(
+    realworld_domain::comment::repo,
+    DelegateCommentRepo,
+    CommentRepoImpl,
+    CommentRepo,
+    custom = comment
+);
+
+delegate_repo!(
+    realworld_domain::data_export,
+    DelegateDataExportRepo,
+    DataExportRepoImpl,
+    DataExportRepo
+);
+
+delegate_repo!(
+    realworld_domain::notification::repo,
+    DelegateNotificationRepo,
+    NotificationRepoImpl,
+    NotificationRepo
+);
+
+delegate_repo!(
+    realworld_domain::report::repo,
+    DelegateReportRepo,
+    ReportRepoImpl,
+    ReportRepo
+);
+
+delegate_repo!(
+    realworld_domain::recommendation,
+    DelegateRecommendationRepo,
+    RecommendationRepoImpl,
+    RecommendationRepo
+);
+
+delegate_repo!(
+    realworld_domain::settings,
+    DelegateSettingsRepo,
+    SettingsRepoImpl,
+    SettingsRepo
+);
+
+delegate_repo!(
+    realworld_domain::stats,
+    DelegateStatsRepo,
+    StatsRepoImpl,
+    StatsRepo
+);
+
+delegate_repo!(
+    realworld_domain::audit,
+    DelegateAuditLog,
+    AuditLogImpl,
+    AuditLogRepo
+);
@@ -0,0 +1,256 @@
+use crate::config::Config;
+
+use realworld_domain::error::RwResult;
+
+use anyhow::Context;
+use std::path::PathBuf;
+
+///
+/// Stores uploaded files.
+///
+/// Files are written to `upload_dir` on local disk, which has to be served at `upload_base_url`,
+/// e.g. by a reverse proxy. With the `s3` feature and a configured bucket, they're put in the bucket.
+///
+#[derive(Clone)]
+pub enum BlobStorage {
+    Local(LocalStorage),
+    #[cfg(feature = "s3")]
+    S3(Box<S3Storage>),
+}
+
+#[derive(Clone)]
+pub struct LocalStorage {
+    dir: PathBuf,
+    base_url: String,
+}
+
+impl BlobStorage {
+    pub fn from_config(config: &Config) -> Self {
+        #[cfg(feature = "s3")]
+        if let (Some(bucket), Some(access_key_id), Some(secret_access_key)) = (
+            &config.s3_bucket,
+            &config.s3_access_key_id,
+            &config.s3_secret_access_key,
+        ) {
+            let endpoint = config.s3_endpoint.trim_end_matches('/');
+
+            return Self::S3(Box::new(S3Storage {
+                bucket_url: format!("{endpoint}/{bucket}"),
+                public_url: config
+                    .s3_public_url
+                    .as_deref()
+                    .map_or_else(|| format!("{endpoint}/{bucket}"), ToString::to_string),
+                region: config.s3_region.clone(),
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+            }));
+        }
+
+        Self::Local(LocalStorage {
+            dir: config.upload_dir.clone(),
+            base_url: config.upload_base_url.clone(),
+        })
+    }
+
+    /// Store `data` under `key`, returning its public URL
+    pub async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> RwResult<String> {
+        let url = match self {
+            Self::Local(local) => {
+                let _ = content_type;
+                local.put(key, data).await?
+            }
+            #[cfg(feature = "s3")]
+            Self::S3(s3) => s3.put(key, content_type, data).await?,
+        };
+
+        Ok(url)
+    }
+}
+
+impl LocalStorage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> anyhow::Result<String> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(format!("{}/{key}", self.base_url.trim_end_matches('/')))
+    }
+}
+
+///
+/// An S3 bucket, or anything else that speaks the S3 API over HTTPS.
+///
+/// Objects are addressed path-style, `<endpoint>/<bucket>/<key>`,
+/// and requests are signed with AWS Signature Version 4.
+///
+#[cfg(feature = "s3")]
+#[derive(Clone)]
+pub struct S3Storage {
+    bucket_url: String,
+    public_url: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3Storage {
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> anyhow::Result<String> {
+        use sha2::Digest;
+
+        let uri: hyper::Uri = format!("{}/{key}", self.bucket_url)
+            .parse()
+            .context("invalid s3 object url")?;
+        let authority = uri.authority().context("s3 url has no host")?.as_str();
+        let payload_hash = hex(&sha2::Sha256::digest(&data));
+        let amz_date = amz_date(time::OffsetDateTime::now_utc());
+        let authorization =
+            self.authorization("PUT", authority, uri.path(), &payload_hash, &amz_date);
+
+        let request = hyper::Request::put(&uri)
+            .header(hyper::header::AUTHORIZATION, authorization)
+            .header(hyper::header::CONTENT_TYPE, content_type)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .body(data)
+            .context("invalid s3 request")?;
+        let (status, body) = crate::https::send(request).await?;
+
+        if !status.is_success() {
+            anyhow::bail!(
+                "s3 responded with {status}: {}",
+                String::from_utf8_lossy(&body)
+            );
+        }
+
+        Ok(format!("{}/{key}", self.public_url.trim_end_matches('/')))
+    }
+
+    /// The `Authorization` header of a request without a query string
+    fn authorization(
+        &self,
+        method: &str,
+        authority: &str,
+        path: &str,
+        payload_hash: &str,
+        amz_date: &str,
+    ) -> String {
+        use hmac::Mac;
+        use sha2::Digest;
+
+        const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let canonical_request = format!(
+            "{method}\n{path}\n\nhost:{authority}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{SIGNED_HEADERS}\n{payload_hash}"
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&sha2::Sha256::digest(canonical_request))
+        );
+
+        let mut mac = HmacSha256::new_from_slice(&signing_key(
+            &self.secret_access_key,
+            date,
+            &self.region,
+            "s3",
+        ))
+        .expect("HMAC can take a key of any size");
+        mac.update(string_to_sign.as_bytes());
+        let signature = hex(&mac.finalize().into_bytes());
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, Signature={signature}",
+            self.access_key_id
+        )
+    }
+}
+
+#[cfg(feature = "s3")]
+type HmacSha256 = hmac::Hmac<sha2::Sha256>;
+
+/// The key that requests to `service` in `region` are signed with on `date` (`YYYYMMDD`)
+#[cfg(feature = "s3")]
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    use hmac::Mac;
+
+    [date, region, service, "aws4_request"].into_iter().fold(
+        format!("AWS4{secret_access_key}").into_bytes(),
+        |key, data| {
+            let mut mac =
+                HmacSha256::new_from_slice(&key).expect("HMAC can take a key of any size");
+            mac.update(data.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        },
+    )
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'`
+#[cfg(feature = "s3")]
+fn amz_date(time: time::OffsetDateTime) -> String {
+    let time = time.to_offset(time::UtcOffset::UTC);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+#[cfg(feature = "s3")]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_storage_should_write_file_under_key() {
+        let dir = std::env::temp_dir().join(format!("realworld-{}", uuid::Uuid::new_v4()));
+        let storage = BlobStorage::Local(LocalStorage {
+            dir: dir.clone(),
+            base_url: "https://example.com/uploads/".to_string(),
+        });
+
+        let url = storage
+            .put("avatars/1/image.png", "image/png", b"png".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!("https://example.com/uploads/avatars/1/image.png", url);
+        assert_eq!(
+            b"png".to_vec(),
+            tokio::fs::read(dir.join("avatars/1/image.png"))
+                .await
+                .unwrap()
+        );
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn signing_key_should_match_aws_example() {
+        // From the AWS documentation of Signature Version 4
+        assert_eq!(
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d",
+            hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            ))
+        );
+    }
+}
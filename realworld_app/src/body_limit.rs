@@ -0,0 +1,82 @@
+use realworld_domain::error::RwError;
+
+use axum::extract::{Request, State};
+use axum::http::header::CONTENT_LENGTH;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+///
+/// Reject requests that announce a body larger than `max_bytes`, before the body is read.
+///
+/// Bodies without a `Content-Length` are cut off at the same size by
+/// `axum::extract::DefaultBodyLimit` while they're being read.
+///
+pub async fn reject_large_bodies(
+    State(max_bytes): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let content_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    match content_length {
+        Some(length) if length > max_bytes => {
+            RwError::PayloadTooLarge { max_bytes }.into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::{Request, StatusCode};
+
+    fn test_router() -> axum::Router {
+        axum::Router::new()
+            .route("/", axum::routing::post(|body: String| async { body }))
+            .layer(axum::middleware::from_fn_with_state(4, reject_large_bodies))
+    }
+
+    #[tokio::test]
+    async fn should_reject_body_larger_than_max() {
+        let (status, body) = request(
+            test_router(),
+            Request::post("/")
+                .header(CONTENT_LENGTH, 5)
+                .body("12345".into())
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+        assert_eq!(
+            serde_json::json!({
+                "code": "PAYLOAD_TOO_LARGE",
+                "message": "request body is larger than 4 bytes",
+                "errors": { "body": ["is larger than 4 bytes"] },
+            }),
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_body_within_max() {
+        let (status, body) = request(
+            test_router(),
+            Request::post("/")
+                .header(CONTENT_LENGTH, 4)
+                .body("1234".into())
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("1234", body);
+    }
+}
@@ -0,0 +1,140 @@
+//!
+//! Building the API router for another server to serve, with parts of the app replaced.
+//!
+//! The router is the same as `serve` serves, but without tenants or gRPC.
+//! Its background tasks, like creating notifications, run on the current Tokio runtime.
+//!
+//! Replacing repositories needs the `dyn-repos` feature, since they're otherwise part of the type of the [App].
+//! The rest of the repositories still use the database.
+//!
+
+use crate::app::backend;
+use crate::config::Config;
+use crate::live_config::{LiveConfig, LiveSettings};
+use crate::{link_app, metrics, router, spawn_background_tasks, state};
+
+#[cfg(feature = "dyn-repos")]
+use crate::app::{App, CustomRepos};
+
+use axum::Router;
+use realworld_domain::GetConfig;
+use std::sync::Arc;
+
+type ExtendRouter = Box<dyn FnOnce(Router) -> Router + Send>;
+
+pub struct AppBuilder {
+    config: Config,
+    db: Option<backend::Db>,
+    domain_config: Option<Arc<dyn GetConfig + Send + Sync>>,
+    /// In the order they were given
+    extend_api: Vec<ExtendRouter>,
+    #[cfg(feature = "dyn-repos")]
+    custom_repos: CustomRepos,
+}
+
+impl AppBuilder {
+    /// `config` is parsed like the command line of the app, e.g. by `Config::try_parse_from`
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            db: None,
+            domain_config: None,
+            extend_api: vec![],
+            #[cfg(feature = "dyn-repos")]
+            custom_repos: CustomRepos::default(),
+        }
+    }
+
+    /// Use this database, instead of connecting to the `database_url` of the config
+    pub fn db(mut self, db: backend::Db) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// What the domain reads through [GetConfig], instead of taking it from the config
+    pub fn get_config(mut self, get_config: impl GetConfig + Send + Sync + 'static) -> Self {
+        self.domain_config = Some(Arc::new(get_config));
+        self
+    }
+
+    ///
+    /// Adds routes or layers to the routes under `/api`, with `/api` left out of their paths.
+    ///
+    /// The routes are handled like those of the app, with rate limits, timeouts and formatted errors.
+    /// Handlers get the app as `Extension<Impl<App>>`. A layer only wraps the routes added before it,
+    /// which includes those of the app.
+    ///
+    pub fn routes(mut self, extend: impl FnOnce(Router) -> Router + Send + 'static) -> Self {
+        self.extend_api.push(Box::new(extend));
+        self
+    }
+
+    #[cfg(feature = "dyn-repos")]
+    pub fn user_repo(
+        mut self,
+        repo: impl realworld_domain::user::repo::UserRepoImpl<App> + Send + Sync,
+    ) -> Self {
+        self.custom_repos.user = Some(Arc::new(repo));
+        self
+    }
+
+    #[cfg(feature = "dyn-repos")]
+    pub fn article_repo(
+        mut self,
+        repo: impl realworld_domain::article::repo::ArticleRepoImpl<App> + Send + Sync,
+    ) -> Self {
+        self.custom_repos.article = Some(Arc::new(repo));
+        self
+    }
+
+    #[cfg(feature = "dyn-repos")]
+    pub fn comment_repo(
+        mut self,
+        repo: impl realworld_domain::comment::repo::CommentRepoImpl<App> + Send + Sync,
+    ) -> Self {
+        self.custom_repos.comment = Some(Arc::new(repo));
+        self
+    }
+
+    /// The router of the API, with the app injected, serving requests under `/api`
+    pub async fn build(self) -> anyhow::Result<Router> {
+        self.config.validate()?;
+
+        let shared = state::SharedState::connect(&self.config).await?;
+        let db = match self.db {
+            Some(db) => db,
+            None => {
+                backend::Db::init(
+                    &self.config.database_url,
+                    &self.config.db_pool_config(),
+                    self.config.migrate_on_start,
+                )
+                .await?
+            }
+        };
+        let metrics = metrics::Metrics::new();
+        // Not reloaded, the embedding server owns the configuration
+        let live_config = LiveConfig::new(LiveSettings::from_config(&self.config));
+
+        let (mut app, notification_receiver) = link_app(
+            Arc::new(self.config),
+            live_config,
+            db,
+            &shared,
+            metrics.clone(),
+        )?;
+        if let Some(domain_config) = self.domain_config {
+            app.domain_config = domain_config;
+        }
+        #[cfg(feature = "dyn-repos")]
+        {
+            app.custom_repos = self.custom_repos;
+        }
+
+        spawn_background_tasks(app.clone(), notification_receiver);
+        let extend_api = self.extend_api;
+        router(app, &shared, metrics, |api| {
+            extend_api.into_iter().fold(api, |api, extend| extend(api))
+        })
+    }
+}
@@ -0,0 +1,443 @@
+//!
+//! `Cache-Control` and `ETag` of public content, like article listings and profiles,
+//! so that browsers and shared caches can keep what anonymous users fetch.
+//!
+//! Every group of routes has a policy of its own, see [CacheControlLayer].
+//!
+
+use crate::config::Config;
+use crate::tenant::TenantFrom;
+
+use axum::body::{Body, Bytes};
+use axum::extract::Request;
+use axum::http::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Bytes of the hash of a response body in its ETag, twice as many hex digits
+const ETAG_HASH_BYTES: usize = 16;
+
+/// Longer response bodies get no ETag, rather than being held in memory to be hashed
+const MAX_ETAG_BODY_BYTES: usize = 1024 * 1024;
+
+/// Sent for requests with credentials, which only the client may keep
+const PRIVATE: HeaderValue = HeaderValue::from_static("private, no-cache");
+
+///
+/// How long the responses of a group of routes may be cached, `None` when they aren't.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CachePolicy {
+    max_age: Option<Duration>,
+    /// A request header that responses depend on, besides `Authorization`
+    vary: Option<HeaderName>,
+}
+
+impl CachePolicy {
+    /// 0 disables caching
+    pub fn max_age_secs(secs: u64) -> Self {
+        Self {
+            max_age: (secs > 0).then(|| Duration::from_secs(secs)),
+            vary: None,
+        }
+    }
+
+    pub fn articles(config: &Config) -> Self {
+        Self::max_age_secs(config.cache_articles_max_age_secs).vary_on_tenant(config)
+    }
+
+    pub fn profiles(config: &Config) -> Self {
+        Self::max_age_secs(config.cache_profiles_max_age_secs).vary_on_tenant(config)
+    }
+
+    /// Shared caches key responses by URL, which doesn't tell tenants named by a header apart
+    fn vary_on_tenant(self, config: &Config) -> Self {
+        match config.tenant_from {
+            TenantFrom::Header if !config.tenants.is_empty() => {
+                self.vary_on(config.tenant_header.clone())
+            }
+            _ => self,
+        }
+    }
+
+    pub fn vary_on(self, header: HeaderName) -> Self {
+        Self {
+            vary: Some(header),
+            ..self
+        }
+    }
+}
+
+///
+/// Tower layer adding caching headers to successful `GET` and `HEAD` responses.
+///
+/// Requests with an `Authorization` header, valid or not, get `private, no-cache`, so that
+/// what depends on the user, like `favorited` or `following`, is never kept by shared caches.
+/// Other requests get `public` with the `max-age` of the policy.
+/// Either way, JSON responses get an ETag, and `304 Not Modified` when it matches `If-None-Match`,
+/// unless they are longer than [MAX_ETAG_BODY_BYTES].
+/// Responses that already have a `Cache-Control` or an `ETag`, like streams or articles, keep theirs.
+///
+#[derive(Clone, Debug)]
+pub struct CacheControlLayer {
+    policy: CachePolicy,
+}
+
+impl CacheControlLayer {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> tower::Layer<S> for CacheControlLayer {
+    type Service = CacheControlService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheControlService {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CacheControlService<S> {
+    inner: S,
+    policy: CachePolicy,
+}
+
+impl<S> tower::Service<Request> for CacheControlService<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // Call the instance that was driven to readiness, leaving a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let Some(max_age) = self.policy.max_age else {
+            return Box::pin(inner.call(request));
+        };
+        if !matches!(*request.method(), Method::GET | Method::HEAD) {
+            return Box::pin(inner.call(request));
+        }
+
+        let cache_control = if request.headers().contains_key(AUTHORIZATION) {
+            PRIVATE
+        } else {
+            HeaderValue::from_str(&format!("public, max-age={}", max_age.as_secs())).unwrap()
+        };
+        let if_none_match = request.headers().get(IF_NONE_MATCH).cloned();
+        let vary = self.policy.vary.clone();
+
+        Box::pin(async move {
+            let mut response = inner.call(request).await?;
+            if !response.status().is_success() {
+                return Ok(response);
+            }
+
+            let headers = response.headers_mut();
+            headers.append(VARY, HeaderValue::from_static("authorization"));
+            if let Some(vary) = vary {
+                headers.append(VARY, HeaderValue::from(vary));
+            }
+            if !headers.contains_key(CACHE_CONTROL) {
+                headers.insert(CACHE_CONTROL, cache_control);
+            }
+            if headers.contains_key(ETAG) || !is_json(&response) {
+                return Ok(response);
+            }
+
+            Ok(with_etag(response, if_none_match).await)
+        })
+    }
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// The response with an ETag of its body, or just its headers if the client has this version
+async fn with_etag(response: Response, if_none_match: Option<HeaderValue>) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match read_body(body, MAX_ETAG_BODY_BYTES).await {
+        Ok(Ok(bytes)) => bytes,
+        Ok(Err(body)) => return Response::from_parts(parts, body),
+        Err(error) => {
+            tracing::error!("failed to read response body: {error}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = HeaderValue::from_str(&etag_of(&bytes)).unwrap();
+
+    let not_modified = if_none_match
+        .as_ref()
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        });
+
+    parts.headers.insert(ETAG, etag);
+    if not_modified {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(CONTENT_TYPE);
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// The body, if it's at most `limit` bytes. Otherwise a body of what was read and the rest.
+async fn read_body(body: Body, limit: usize) -> Result<Result<Bytes, Body>, axum::Error> {
+    let mut stream = body.into_data_stream();
+    let mut chunks = vec![];
+    let mut length = 0;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        length += chunk.len();
+        chunks.push(chunk);
+        if length > limit {
+            let read = futures::stream::iter(chunks.into_iter().map(Ok));
+            return Ok(Err(Body::from_stream(read.chain(stream))));
+        }
+    }
+
+    Ok(Ok(chunks.concat().into()))
+}
+
+/// A quoted hex hash of the body
+fn etag_of(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    let digest = Sha256::digest(bytes);
+    let mut etag = digest[..ETAG_HASH_BYTES]
+        .iter()
+        .fold(String::from("\""), |mut output, byte| {
+            let _ = write!(output, "{byte:02x}");
+            output
+        });
+    etag.push('"');
+    etag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn test_router(policy: CachePolicy) -> axum::Router {
+        axum::Router::new()
+            .route(
+                "/json",
+                get(|| async { axum::Json(serde_json::json!({ "articles": [] })) })
+                    .post(|| async { axum::Json(serde_json::json!({ "articles": [] })) }),
+            )
+            .route("/text", get(|| async { "text" }))
+            .route(
+                "/long",
+                get(|| async { axum::Json("a".repeat(MAX_ETAG_BODY_BYTES)) }),
+            )
+            .route(
+                "/missing",
+                get(|| async { StatusCode::NOT_FOUND.into_response() }),
+            )
+            .route(
+                "/own",
+                get(|| async {
+                    (
+                        [(CACHE_CONTROL, "no-cache")],
+                        axum::Json(serde_json::json!({})),
+                    )
+                }),
+            )
+            .layer(CacheControlLayer::new(policy))
+    }
+
+    async fn respond(policy: CachePolicy, request: Request) -> Response {
+        test_router(policy).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn anonymous_responses_should_be_public() {
+        let response = respond(
+            CachePolicy::max_age_secs(60),
+            Request::get("/json").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("public, max-age=60", response.headers()[CACHE_CONTROL]);
+        assert_eq!("authorization", response.headers()[VARY]);
+        assert!(response.headers().contains_key(ETAG));
+    }
+
+    #[tokio::test]
+    async fn authenticated_responses_should_never_be_public() {
+        let response = respond(
+            CachePolicy::max_age_secs(60),
+            Request::get("/json")
+                .header(AUTHORIZATION, "Token whatever")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!("private, no-cache", response.headers()[CACHE_CONTROL]);
+        assert_eq!("authorization", response.headers()[VARY]);
+    }
+
+    #[tokio::test]
+    async fn responses_should_vary_on_header_of_policy() {
+        let response = respond(
+            CachePolicy::max_age_secs(60).vary_on(HeaderName::from_static("x-tenant")),
+            Request::get("/json").empty_body(),
+        )
+        .await;
+
+        assert_eq!(
+            vec!["authorization", "x-tenant"],
+            response.headers().get_all(VARY).iter().collect::<Vec<_>>()
+        );
+    }
+
+    fn test_config(args: &[&str]) -> Config {
+        use clap::Parser;
+
+        let config_args = [
+            "realworld",
+            "--database-url=unused",
+            "--jwt-signing-key=secret",
+        ];
+        Config::try_parse_from(config_args.iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn policy_should_vary_on_tenant_header_with_tenants() {
+        assert_eq!(None, CachePolicy::articles(&test_config(&[])).vary);
+        assert_eq!(
+            Some(HeaderName::from_static("x-tenant")),
+            CachePolicy::articles(&test_config(&["--tenants=acme,globex"])).vary
+        );
+        assert_eq!(
+            None,
+            CachePolicy::profiles(&test_config(&["--tenants=acme", "--tenant-from=subdomain"]))
+                .vary
+        );
+    }
+
+    #[tokio::test]
+    async fn long_response_should_be_sent_whole_without_etag() {
+        let response = respond(
+            CachePolicy::max_age_secs(60),
+            Request::get("/long").empty_body(),
+        )
+        .await;
+
+        assert!(!response.headers().contains_key(ETAG));
+        assert_eq!(
+            MAX_ETAG_BODY_BYTES + 2,
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[tokio::test]
+    async fn matching_etag_should_not_be_modified() {
+        let policy = CachePolicy::max_age_secs(60);
+        let response = respond(policy.clone(), Request::get("/json").empty_body()).await;
+        let etag = response.headers()[ETAG].clone();
+
+        let response = respond(
+            policy.clone(),
+            Request::get("/json")
+                .header(IF_NONE_MATCH, etag.clone())
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+        assert_eq!(etag, response.headers()[ETAG]);
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let (status, body) = request(
+            test_router(policy),
+            Request::get("/json")
+                .header(IF_NONE_MATCH, "\"other\"")
+                .empty_body(),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(r#"{"articles":[]}"#, body);
+    }
+
+    #[tokio::test]
+    async fn only_successful_reads_should_get_headers() {
+        let policy = CachePolicy::max_age_secs(60);
+
+        let response = respond(policy.clone(), Request::get("/missing").empty_body()).await;
+        assert!(!response.headers().contains_key(CACHE_CONTROL));
+
+        let response = respond(policy.clone(), Request::post("/json").empty_body()).await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(!response.headers().contains_key(CACHE_CONTROL));
+
+        let response = respond(policy, Request::get("/text").empty_body()).await;
+        assert_eq!("public, max-age=60", response.headers()[CACHE_CONTROL]);
+        assert!(!response.headers().contains_key(ETAG));
+    }
+
+    #[tokio::test]
+    async fn own_cache_control_should_be_kept() {
+        let response = respond(
+            CachePolicy::max_age_secs(60),
+            Request::get("/own").empty_body(),
+        )
+        .await;
+
+        assert_eq!("no-cache", response.headers()[CACHE_CONTROL]);
+    }
+
+    #[tokio::test]
+    async fn disabled_policy_should_add_no_headers() {
+        let response = respond(
+            CachePolicy::max_age_secs(0),
+            Request::get("/json").empty_body(),
+        )
+        .await;
+
+        assert!(!response.headers().contains_key(CACHE_CONTROL));
+        assert!(!response.headers().contains_key(ETAG));
+    }
+}
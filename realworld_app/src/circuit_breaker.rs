@@ -0,0 +1,195 @@
+//!
+//! Rejecting requests up front while the database is unavailable,
+//! instead of letting them pile up waiting for connections.
+//!
+//! Requests fail when the database is unavailable, or when they time out, both giving
+//! `503 Service Unavailable`. After `circuit_breaker_failures` such failures in a row,
+//! the circuit opens, and requests are rejected with a `Retry-After` for `circuit_breaker_open_secs`.
+//! Then one request is let through to try the database again, and the circuit closes if it succeeds.
+//!
+//! Every app has its own circuit breaker, since every tenant has its own database.
+//!
+
+use crate::app::App;
+use crate::config::Config;
+
+use realworld_domain::error::RwError;
+
+use axum::extract::{Extension, Request};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use entrait::Impl;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// One request is trying the database
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+pub struct CircuitBreaker {
+    /// 0 when disabled
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_for,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.circuit_breaker_failures,
+            Duration::from_secs(config.circuit_breaker_open_secs),
+        )
+    }
+
+    /// `Err` with the time until a request may be let through, if the circuit is open
+    fn admit(&self, now: Instant) -> Result<(), Duration> {
+        if self.failure_threshold == 0 {
+            return Ok(());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now < until => Err(until - now),
+            // The trial request may never finish, so in time another one is let through
+            State::HalfOpen { since } if now < since + self.open_for => {
+                Err(since + self.open_for - now)
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, failed: bool, now: Instant) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        *state = match (&*state, failed) {
+            (_, false) => State::Closed { failures: 0 },
+            (State::Closed { failures }, true) if failures + 1 < self.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            // Requests admitted before the circuit opened don't keep it open longer
+            (State::Open { until }, true) => State::Open { until: *until },
+            (_, true) => {
+                tracing::warn!(
+                    "database unavailable, rejecting requests for {:?}",
+                    self.open_for
+                );
+                State::Open {
+                    until: now + self.open_for,
+                }
+            }
+        };
+    }
+}
+
+pub async fn short_circuit(
+    Extension(app): Extension<Impl<App>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let breaker = &app.circuit_breaker;
+    if let Err(retry_after) = breaker.admit(Instant::now()) {
+        return RwError::ServiceUnavailable {
+            retry_after: Some(retry_after),
+        }
+        .into_response();
+    }
+
+    let response = next.run(request).await;
+    breaker.record(
+        response.status() == StatusCode::SERVICE_UNAVAILABLE,
+        Instant::now(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPEN_FOR: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn circuit_should_open_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, OPEN_FOR);
+        let now = Instant::now();
+
+        breaker.record(true, now);
+        breaker.record(true, now);
+        breaker.record(false, now);
+        breaker.record(true, now);
+        breaker.record(true, now);
+        assert_eq!(Ok(()), breaker.admit(now));
+
+        breaker.record(true, now);
+        assert_eq!(Err(OPEN_FOR), breaker.admit(now));
+        assert_eq!(
+            Err(Duration::from_secs(10)),
+            breaker.admit(now + Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn one_request_should_try_database_when_open_circuit_expires() {
+        let breaker = CircuitBreaker::new(1, OPEN_FOR);
+        let now = Instant::now();
+        breaker.record(true, now);
+
+        let later = now + OPEN_FOR;
+        assert_eq!(Ok(()), breaker.admit(later));
+        assert_eq!(Err(OPEN_FOR), breaker.admit(later));
+
+        breaker.record(false, later);
+        assert_eq!(Ok(()), breaker.admit(later));
+    }
+
+    #[test]
+    fn failed_trial_should_open_circuit_again() {
+        let breaker = CircuitBreaker::new(1, OPEN_FOR);
+        let now = Instant::now();
+        breaker.record(true, now);
+
+        let later = now + OPEN_FOR;
+        assert_eq!(Ok(()), breaker.admit(later));
+        breaker.record(true, later);
+
+        assert_eq!(Err(OPEN_FOR), breaker.admit(later));
+    }
+
+    #[test]
+    fn disabled_circuit_should_never_open() {
+        let breaker = CircuitBreaker::new(0, OPEN_FOR);
+        let now = Instant::now();
+        for _ in 0..10 {
+            breaker.record(true, now);
+        }
+
+        assert_eq!(Ok(()), breaker.admit(now));
+    }
+}
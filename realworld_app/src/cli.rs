@@ -0,0 +1,415 @@
+//!
+//! The command line of the app. Serves the API unless another command is given.
+//!
+//! All commands take the same configuration, mostly from the environment.
+//!
+
+use crate::app::{backend, App};
+use crate::config::Config;
+use crate::doctor;
+use crate::tenant;
+
+use anyhow::Context;
+use entrait::Impl;
+use realworld_domain::admin::{CreateUserWithRole, MintToken};
+use realworld_domain::article::feed::BackfillFeeds;
+use realworld_domain::seed::{Seed, SeedOptions};
+use realworld_domain::user::import::{ExportedUser, ImportUsers};
+use realworld_domain::user::role::Role;
+use realworld_domain::user::{NewUser, UserId};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Parser)]
+pub struct Cli {
+    #[clap(flatten)]
+    pub config: Config,
+
+    /// Run the command against the database of this tenant, see `tenants`.
+    /// Without it, `migrate` runs against every database.
+    #[clap(long, global = true)]
+    pub tenant: Option<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Default)]
+#[cfg_attr(test, derive(Debug))]
+pub enum Command {
+    /// Serve the API. This is the default.
+    #[default]
+    Serve,
+
+    /// Run, revert or list database migrations
+    #[clap(subcommand)]
+    Migrate(MigrateCommand),
+
+    /// Create a user with a role, e.g. the first admin
+    CreateAdmin(CreateAdminArgs),
+
+    /// Print an access token for a user
+    GenJwt { user_id: uuid::Uuid },
+
+    /// Fill the database with made-up content, the same for the same seed
+    Seed(SeedArgs),
+
+    /// Import users exported from another RealWorld backend, with their password hashes
+    ImportUsers(ImportUsersArgs),
+
+    /// Write the materialized feed of every user from the current follows,
+    /// before switching on `materialized_feeds`
+    BackfillFeeds,
+
+    /// Check the configuration, JWT keys, databases and ports, and print a JSON report.
+    /// Fails when any check fails.
+    Doctor,
+}
+
+#[derive(clap::Subcommand)]
+#[cfg_attr(test, derive(Debug))]
+pub enum MigrateCommand {
+    /// Run the migrations that haven't been run yet
+    Run {
+        /// Only list the migrations that would be run
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// List the migrations of this build, and whether they have been run
+    Status,
+
+    /// Revert the migrations newer than a version
+    Revert {
+        /// Version of the migration to revert to, e.g. 12 for `12_bookmark.sql`
+        target: i64,
+    },
+}
+
+#[derive(clap::Args)]
+#[cfg_attr(test, derive(Debug))]
+pub struct CreateAdminArgs {
+    #[clap(long)]
+    pub username: String,
+
+    #[clap(long)]
+    pub email: String,
+
+    /// Preferably given by `ADMIN_PASSWORD`, to keep it out of the shell history
+    #[clap(long, env = "ADMIN_PASSWORD", hide_env_values = true)]
+    pub password: String,
+
+    /// Role of the user: user, moderator or admin
+    #[clap(long, default_value = "admin")]
+    pub role: Role,
+}
+
+#[derive(clap::Args)]
+#[cfg_attr(test, derive(Debug))]
+pub struct SeedArgs {
+    #[clap(long, default_value_t = 10)]
+    pub users: usize,
+
+    #[clap(long, default_value_t = 3)]
+    pub follows_per_user: usize,
+
+    #[clap(long, default_value_t = 3)]
+    pub articles_per_user: usize,
+
+    #[clap(long, default_value_t = 5)]
+    pub favorites_per_user: usize,
+
+    #[clap(long, default_value_t = 2)]
+    pub comments_per_article: usize,
+
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(clap::Args)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ImportUsersArgs {
+    /// A JSON array of users, or a CSV file with a header,
+    /// with the `username`, `email` and `password_hash` of each user.
+    /// Hashes can be Argon2, bcrypt or scrypt hashes.
+    pub file: PathBuf,
+
+    /// Format of the file, when its extension isn't `.json` or `.csv`
+    #[clap(long, value_enum)]
+    pub format: Option<ImportFormat>,
+
+    /// Users inserted at a time
+    #[clap(long, default_value_t = 500)]
+    pub batch_size: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum ImportFormat {
+    Json,
+    Csv,
+}
+
+pub async fn migrate(
+    config: &Config,
+    tenant: Option<&str>,
+    command: MigrateCommand,
+) -> anyhow::Result<()> {
+    let databases = match tenant {
+        Some(tenant) => vec![(tenant.to_string(), database_url(config, Some(tenant))?)],
+        None => std::iter::once(("default".to_string(), config.database_url.clone()))
+            .chain(tenant::tenant_database_urls(config))
+            .collect(),
+    };
+
+    for (name, url) in databases {
+        // Connecting with `Db::init` would run the migrations before they could be reverted
+        let db = backend::Db::connect(&url, &config.db_pool_config()).await?;
+
+        match command {
+            MigrateCommand::Run { dry_run: false } => db.migrate().await?,
+            MigrateCommand::Run { dry_run: true } => {
+                let status = db.schema_status().await?;
+                println!("{name}: {} to run", status.pending().count());
+                for migration in status.pending() {
+                    println!("  {} {}", migration.version, migration.description);
+                }
+            }
+            MigrateCommand::Status => print_schema_status(&name, &db.schema_status().await?),
+            MigrateCommand::Revert { target } => db.revert_migrations(target).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Every migration of the database, and whether it has been run
+fn print_schema_status(name: &str, status: &backend::SchemaStatus) {
+    println!("{name}:");
+    for migration in &status.migrations {
+        let state = if migration.applied {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!("  {state} {} {}", migration.version, migration.description);
+    }
+    for version in &status.unknown_versions {
+        println!("  applied {version}, unknown to this build");
+    }
+}
+
+/// The database of the tenant, or `database_url` without one
+pub fn database_url(config: &Config, tenant: Option<&str>) -> anyhow::Result<String> {
+    let Some(tenant) = tenant else {
+        return Ok(config.database_url.clone());
+    };
+
+    tenant::tenant_database_urls(config)
+        .into_iter()
+        .find(|(name, _)| name == tenant)
+        .map(|(_, url)| url)
+        .ok_or_else(|| anyhow::anyhow!("unknown tenant {tenant}, see `tenants`"))
+}
+
+pub async fn create_admin(app: &Impl<App>, args: CreateAdminArgs) -> anyhow::Result<()> {
+    let user = app
+        .create_user_with_role(
+            NewUser {
+                username: args.username,
+                email: args.email,
+                password: args.password.into(),
+            },
+            args.role,
+        )
+        .await?;
+
+    println!("{}", user.user_id.into_id());
+    Ok(())
+}
+
+pub async fn gen_jwt(app: &Impl<App>, user_id: uuid::Uuid) -> anyhow::Result<()> {
+    println!("{}", app.mint_token(UserId(user_id)).await?);
+    Ok(())
+}
+
+pub async fn seed(app: &Impl<App>, args: SeedArgs) -> anyhow::Result<()> {
+    let seeded = app
+        .seed(SeedOptions {
+            users: args.users,
+            follows_per_user: args.follows_per_user,
+            articles_per_user: args.articles_per_user,
+            favorites_per_user: args.favorites_per_user,
+            comments_per_article: args.comments_per_article,
+            seed: args.seed,
+        })
+        .await?;
+
+    println!(
+        "{} users, {} follows, {} articles, {} favorites and {} comments",
+        seeded.users, seeded.follows, seeded.articles, seeded.favorites, seeded.comments
+    );
+    Ok(())
+}
+
+pub async fn import_users(app: &Impl<App>, args: ImportUsersArgs) -> anyhow::Result<()> {
+    let users = read_users(&args.file, args.format)?;
+    let report = app.import_users(users, args.batch_size).await?;
+
+    for conflict in &report.conflicts {
+        println!(
+            "user {} ({}) not imported: {}",
+            conflict.index + 1,
+            conflict.username,
+            conflict.error
+        );
+    }
+    println!(
+        "{} users imported, {} not imported",
+        report.imported,
+        report.conflicts.len()
+    );
+    Ok(())
+}
+
+pub async fn backfill_feeds(app: &Impl<App>) -> anyhow::Result<()> {
+    let entries = app.backfill_feeds().await?;
+
+    println!("{entries} feed entries written");
+    Ok(())
+}
+
+pub async fn doctor(config: &Config) -> anyhow::Result<()> {
+    let report = doctor::diagnose(config).await;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if !report.ok {
+        anyhow::bail!("some checks failed");
+    }
+    Ok(())
+}
+
+fn read_users(path: &Path, format: Option<ImportFormat>) -> anyhow::Result<Vec<ExportedUser>> {
+    let format = format
+        .or_else(|| match path.extension()?.to_str()? {
+            "json" => Some(ImportFormat::Json),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None,
+        })
+        .context("unknown file format, see `--format`")?;
+    let file = std::fs::File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+
+    parse_users(std::io::BufReader::new(file), format)
+}
+
+fn parse_users(
+    reader: impl std::io::Read,
+    format: ImportFormat,
+) -> anyhow::Result<Vec<ExportedUser>> {
+    match format {
+        ImportFormat::Json => serde_json::from_reader(reader).context("invalid JSON"),
+        ImportFormat::Csv => csv::Reader::from_reader(reader)
+            .deserialize()
+            .enumerate()
+            .map(|(index, user)| user.with_context(|| format!("invalid CSV record {}", index + 1)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_matches::*;
+    use clap::Parser;
+
+    fn parse(args: &[&str]) -> Cli {
+        let config_args = [
+            "realworld",
+            "--database-url=postgres://localhost/realworld",
+            "--jwt-signing-key=secret",
+        ];
+        Cli::try_parse_from(config_args.iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn commands_should_be_parsed() {
+        assert_matches!(parse(&[]).command, None);
+        assert_matches!(
+            parse(&["migrate", "revert", "12"]).command,
+            Some(Command::Migrate(MigrateCommand::Revert { target: 12 }))
+        );
+        assert_matches!(
+            parse(&["migrate", "run", "--dry-run"]).command,
+            Some(Command::Migrate(MigrateCommand::Run { dry_run: true }))
+        );
+        assert_matches!(
+            parse(&[
+                "create-admin",
+                "--username=root",
+                "--email=root@example.com",
+                "--password=secret",
+                "--role=moderator"
+            ])
+            .command,
+            Some(Command::CreateAdmin(CreateAdminArgs { role: Role::Moderator, username, .. })) if username == "root"
+        );
+        assert_matches!(
+            parse(&["seed", "--users=3", "--seed=42"]).command,
+            Some(Command::Seed(SeedArgs {
+                users: 3,
+                articles_per_user: 3,
+                seed: 42,
+                ..
+            }))
+        );
+        assert_matches!(
+            parse(&["import-users", "users.csv", "--batch-size=100"]).command,
+            Some(Command::ImportUsers(ImportUsersArgs { format: None, batch_size: 100, file })) if file == Path::new("users.csv")
+        );
+        assert_matches!(parse(&["doctor"]).command, Some(Command::Doctor));
+        assert_matches!(
+            parse(&["backfill-feeds"]).command,
+            Some(Command::BackfillFeeds)
+        );
+    }
+
+    #[test]
+    fn users_should_be_parsed_from_json_and_csv() {
+        let json = r#"[
+            {"username": "jake", "email": "jake@jake.jake", "password_hash": "$2b$10$hash"},
+            {"username": "jane", "email": "jane@jane.jane", "passwordHash": "$argon2id$hash"}
+        ]"#;
+        let csv = "username,email,password_hash\n\
+            jake,jake@jake.jake,$2b$10$hash\n\
+            jane,jane@jane.jane,$argon2id$hash\n";
+
+        for users in [
+            parse_users(json.as_bytes(), ImportFormat::Json).unwrap(),
+            parse_users(csv.as_bytes(), ImportFormat::Csv).unwrap(),
+        ] {
+            let users: Vec<(&str, &str, &str)> = users
+                .iter()
+                .map(|user| {
+                    (
+                        user.username.as_str(),
+                        user.email.as_str(),
+                        user.password_hash.as_str(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![
+                    ("jake", "jake@jake.jake", "$2b$10$hash"),
+                    ("jane", "jane@jane.jane", "$argon2id$hash")
+                ],
+                users
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_csv_record_should_be_pointed_out() {
+        let csv = "username,email,password_hash\njake,jake@jake.jake,hash\njane\n";
+        let error = parse_users(csv.as_bytes(), ImportFormat::Csv).unwrap_err();
+
+        assert_eq!("invalid CSV record 2", error.to_string());
+    }
+}
@@ -0,0 +1,115 @@
+use realworld_domain::article::Slug;
+use realworld_domain::comment::Comment;
+
+use futures::stream::{BoxStream, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// How many comments a slow subscriber may fall behind before it starts missing some
+const CHANNEL_CAPACITY: usize = 64;
+
+///
+/// Fans out new comments to the clients watching each article,
+/// through `realworld_domain::comment::events::CommentEvents`.
+///
+/// An article gets a channel when it's first watched, and loses it
+/// when a comment is published after the last watcher has left.
+/// Only watchers connected to the same instance get notified.
+///
+#[derive(Clone, Default)]
+pub struct CommentBroadcaster {
+    channels: Arc<Mutex<HashMap<Slug, broadcast::Sender<Comment>>>>,
+}
+
+impl CommentBroadcaster {
+    pub fn publish(&self, article_slug: &Slug, comment: &Comment) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(article_slug) {
+            // Sending only fails when there are no receivers left
+            if sender.send(comment.clone()).is_err() {
+                channels.remove(article_slug);
+            }
+        }
+    }
+
+    pub fn subscribe(&self, article_slug: &Slug) -> BoxStream<'static, Comment> {
+        let mut receiver = self
+            .channels
+            .lock()
+            .unwrap()
+            .entry(article_slug.clone())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe();
+
+        async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(comment) => yield comment,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+        .boxed()
+    }
+
+    #[cfg(test)]
+    fn watched_articles(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_comment(id: i64) -> Comment {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "createdAt": "2019-10-12T07:20:50.52Z",
+            "updatedAt": "2019-10-12T07:20:50.52Z",
+            "body": "body",
+            "author": {
+                "username": "author",
+                "bio": "bio",
+                "image": null,
+                "following": false
+            },
+            "likesCount": 0,
+            "liked": false
+        }))
+        .unwrap()
+    }
+
+    fn id(comment: Comment) -> i64 {
+        serde_json::to_value(comment).unwrap()["id"]
+            .as_i64()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn subscribers_should_only_get_comments_on_their_article() {
+        let broadcaster = CommentBroadcaster::default();
+        let mut a = broadcaster.subscribe(&Slug::from("a"));
+        let mut b = broadcaster.subscribe(&Slug::from("b"));
+
+        broadcaster.publish(&Slug::from("a"), &test_comment(1));
+        broadcaster.publish(&Slug::from("b"), &test_comment(2));
+        broadcaster.publish(&Slug::from("c"), &test_comment(3));
+
+        assert_eq!(Some(1), a.next().await.map(id));
+        assert_eq!(Some(2), b.next().await.map(id));
+    }
+
+    #[tokio::test]
+    async fn channel_should_be_dropped_after_last_subscriber() {
+        let broadcaster = CommentBroadcaster::default();
+        let subscription = broadcaster.subscribe(&Slug::from("a"));
+        assert_eq!(1, broadcaster.watched_articles());
+
+        drop(subscription);
+        broadcaster.publish(&Slug::from("a"), &test_comment(1));
+        assert_eq!(0, broadcaster.watched_articles());
+    }
+}
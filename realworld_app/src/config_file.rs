@@ -0,0 +1,444 @@
+//!
+//! The optional TOML file given by `--config`, with sections for the database, authentication,
+//! HTTP, pagination, logging and scheduled jobs:
+//!
+//! ```toml
+//! [db]
+//! url = "postgres://localhost/realworld"
+//! max_connections = 20
+//!
+//! [auth]
+//! jwt_signing_key = "secret"
+//!
+//! [http]
+//! cors_allowed_origins = ["https://example.com"]
+//!
+//! [pagination]
+//! max_size = 50
+//!
+//! [logging]
+//! format = "pretty"
+//! request_log = "status"
+//! request_log_routes = ["/api/users/login=off"]
+//!
+//! [jobs]
+//! purge_refresh_tokens = "0 3 * * *"
+//! ```
+//!
+//! Its values replace the built-in defaults of [Config](crate::config::Config).
+//! Some of them can be changed while serving, by reloading the file, see `live_config.rs`.
+//! Secrets read from files, environment variables and command line arguments
+//! in turn take precedence over the file.
+//!
+
+use crate::secrets::{self, SecretProvider};
+
+use anyhow::Context;
+use clap::{CommandFactory, FromArgMatches};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    db: DbSection,
+    #[serde(default)]
+    auth: AuthSection,
+    #[serde(default)]
+    http: HttpSection,
+    #[serde(default)]
+    pagination: PaginationSection,
+    #[serde(default)]
+    logging: LoggingSection,
+    #[serde(default)]
+    jobs: JobsSection,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DbSection {
+    url: Option<String>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    acquire_timeout_secs: Option<u64>,
+    idle_timeout_secs: Option<u64>,
+    statement_cache_capacity: Option<usize>,
+    schema: Option<String>,
+    migrate_on_start: Option<bool>,
+    slow_query_threshold_ms: Option<u64>,
+    circuit_breaker_failures: Option<u32>,
+    circuit_breaker_open_secs: Option<u64>,
+    retry_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    retry_max_delay_ms: Option<u64>,
+    materialized_feeds: Option<bool>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct AuthSection {
+    jwt_signing_key: Option<String>,
+    jwt_algorithm: Option<String>,
+    jwt_key_id: Option<String>,
+    jwt_verification_keys: Option<Vec<String>>,
+    require_email_verification: Option<bool>,
+    max_failed_logins: Option<u32>,
+    failed_login_window_secs: Option<u64>,
+    reserved_usernames: Option<Vec<String>>,
+    plus_addressing: Option<String>,
+    password_algorithm: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct HttpSection {
+    max_body_bytes: Option<usize>,
+    max_avatar_bytes: Option<usize>,
+    max_article_body_bytes: Option<usize>,
+    cors_allowed_origins: Option<Vec<String>>,
+    cors_allowed_methods: Option<Vec<String>>,
+    cors_allowed_headers: Option<Vec<String>>,
+    cors_allow_credentials: Option<bool>,
+    cors_max_age_secs: Option<u64>,
+    rate_limit_ip_per_minute: Option<u32>,
+    rate_limit_ip_burst: Option<u32>,
+    rate_limit_user_per_minute: Option<u32>,
+    rate_limit_user_burst: Option<u32>,
+    site_url: Option<String>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+    cache_articles_max_age_secs: Option<u64>,
+    cache_profiles_max_age_secs: Option<u64>,
+    error_format: Option<String>,
+    maintenance_mode: Option<bool>,
+    feature_flags: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct PaginationSection {
+    default_size: Option<u32>,
+    max_size: Option<u32>,
+    max_offset: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct LoggingSection {
+    format: Option<String>,
+    filter: Option<String>,
+    request_log: Option<String>,
+    request_log_sample_rate: Option<f64>,
+    request_log_routes: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct JobsSection {
+    purge_refresh_tokens: Option<String>,
+    purge_login_attempts: Option<String>,
+    purge_article_views: Option<String>,
+    compile_data_exports: Option<String>,
+}
+
+/// Values of arguments, by argument id
+#[derive(Default)]
+struct Defaults(Vec<(&'static str, Vec<String>)>);
+
+impl Defaults {
+    fn value(&mut self, id: &'static str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.0.push((id, vec![value.to_string()]));
+        }
+    }
+
+    fn values(&mut self, id: &'static str, values: Option<Vec<String>>) {
+        if let Some(values) = values {
+            self.0.push((id, values));
+        }
+    }
+}
+
+impl ConfigFile {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    fn defaults(self) -> Defaults {
+        let Self {
+            db,
+            auth,
+            http,
+            pagination,
+            logging,
+            jobs,
+        } = self;
+        let mut defaults = Defaults::default();
+
+        defaults.value("database_url", db.url);
+        defaults.value("db_min_connections", db.min_connections);
+        defaults.value("db_max_connections", db.max_connections);
+        defaults.value("db_acquire_timeout_secs", db.acquire_timeout_secs);
+        defaults.value("db_idle_timeout_secs", db.idle_timeout_secs);
+        defaults.value("db_statement_cache_capacity", db.statement_cache_capacity);
+        defaults.value("db_schema", db.schema);
+        defaults.value("migrate_on_start", db.migrate_on_start);
+        defaults.value("slow_query_threshold_ms", db.slow_query_threshold_ms);
+        defaults.value("circuit_breaker_failures", db.circuit_breaker_failures);
+        defaults.value("circuit_breaker_open_secs", db.circuit_breaker_open_secs);
+        defaults.value("db_retry_attempts", db.retry_attempts);
+        defaults.value("db_retry_base_delay_ms", db.retry_base_delay_ms);
+        defaults.value("db_retry_max_delay_ms", db.retry_max_delay_ms);
+        defaults.value("materialized_feeds", db.materialized_feeds);
+
+        defaults.value("jwt_signing_key", auth.jwt_signing_key);
+        defaults.value("jwt_algorithm", auth.jwt_algorithm);
+        defaults.value("jwt_key_id", auth.jwt_key_id);
+        defaults.values("jwt_verification_keys", auth.jwt_verification_keys);
+        defaults.value(
+            "require_email_verification",
+            auth.require_email_verification,
+        );
+        defaults.value("max_failed_logins", auth.max_failed_logins);
+        defaults.value("failed_login_window_secs", auth.failed_login_window_secs);
+        defaults.values("reserved_usernames", auth.reserved_usernames);
+        defaults.value("plus_addressing", auth.plus_addressing);
+        defaults.value("password_algorithm", auth.password_algorithm);
+
+        defaults.value("max_body_bytes", http.max_body_bytes);
+        defaults.value("max_avatar_bytes", http.max_avatar_bytes);
+        defaults.value("max_article_body_bytes", http.max_article_body_bytes);
+        defaults.values("cors_allowed_origins", http.cors_allowed_origins);
+        defaults.values("cors_allowed_methods", http.cors_allowed_methods);
+        defaults.values("cors_allowed_headers", http.cors_allowed_headers);
+        defaults.value("cors_allow_credentials", http.cors_allow_credentials);
+        defaults.value("cors_max_age_secs", http.cors_max_age_secs);
+        defaults.value("rate_limit_ip_per_minute", http.rate_limit_ip_per_minute);
+        defaults.value("rate_limit_ip_burst", http.rate_limit_ip_burst);
+        defaults.value(
+            "rate_limit_user_per_minute",
+            http.rate_limit_user_per_minute,
+        );
+        defaults.value("rate_limit_user_burst", http.rate_limit_user_burst);
+        defaults.value("site_url", http.site_url);
+        defaults.value("read_timeout_ms", http.read_timeout_ms);
+        defaults.value("write_timeout_ms", http.write_timeout_ms);
+        defaults.value(
+            "cache_articles_max_age_secs",
+            http.cache_articles_max_age_secs,
+        );
+        defaults.value(
+            "cache_profiles_max_age_secs",
+            http.cache_profiles_max_age_secs,
+        );
+        defaults.value("error_format", http.error_format);
+        defaults.value("maintenance_mode", http.maintenance_mode);
+        defaults.values("feature_flags", http.feature_flags);
+
+        defaults.value("default_page_size", pagination.default_size);
+        defaults.value("max_page_size", pagination.max_size);
+        defaults.value("max_page_offset", pagination.max_offset);
+
+        defaults.value("log_format", logging.format);
+        defaults.value("log_filter", logging.filter);
+        defaults.value("request_log", logging.request_log);
+        defaults.value("request_log_sample_rate", logging.request_log_sample_rate);
+        defaults.values("request_log_routes", logging.request_log_routes);
+
+        defaults.value("purge_refresh_tokens_schedule", jobs.purge_refresh_tokens);
+        defaults.value("purge_login_attempts_schedule", jobs.purge_login_attempts);
+        defaults.value("purge_article_views_schedule", jobs.purge_article_views);
+        defaults.value("compile_data_exports_schedule", jobs.compile_data_exports);
+
+        defaults
+    }
+}
+
+/// Like [clap::Parser::parse], with the values of the config file, if any,
+/// and secrets read from files as defaults
+pub fn parse<P: CommandFactory + FromArgMatches>() -> anyhow::Result<P> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    // Only looking for the path, the required arguments may be in the file
+    let path = P::command()
+        .ignore_errors(true)
+        .get_matches_from(&args)
+        .get_one::<PathBuf>("config_file")
+        .cloned();
+    let file = match path {
+        Some(path) => ConfigFile::read(&path)?,
+        None => ConfigFile::default(),
+    };
+
+    parse_with(args, file, &[&secrets::FileSecrets])
+}
+
+fn parse_with<P: CommandFactory + FromArgMatches>(
+    args: Vec<OsString>,
+    file: ConfigFile,
+    secret_providers: &[&dyn SecretProvider],
+) -> anyhow::Result<P> {
+    let mut defaults = file.defaults();
+    let command = P::command();
+
+    for id in secrets::SECRETS {
+        // Not compiled in
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_id() == id) else {
+            continue;
+        };
+        let Some(env_name) = arg.get_env().and_then(|env| env.to_str()) else {
+            continue;
+        };
+
+        for provider in secret_providers {
+            if let Some(secret) = provider.secret(env_name)? {
+                let values = match arg.get_value_delimiter() {
+                    Some(delimiter) => secret.split(delimiter).map(str::to_string).collect(),
+                    None => vec![secret],
+                };
+                defaults.values(id, Some(values));
+                break;
+            }
+        }
+    }
+
+    // Later defaults of the same argument replace earlier ones.
+    // Secrets are kept out of `--help`.
+    let command = defaults
+        .0
+        .into_iter()
+        .fold(command, |command, (id, values)| {
+            command.mut_arg(id, |arg| {
+                arg.default_values(values)
+                    .required(false)
+                    .hide_default_value(secrets::SECRETS.contains(&id))
+            })
+        });
+
+    Ok(P::from_arg_matches(&command.get_matches_from(args)).unwrap_or_else(|error| error.exit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+
+    struct TestSecrets(&'static [(&'static str, &'static str)]);
+
+    impl SecretProvider for TestSecrets {
+        fn secret(&self, env_name: &str) -> anyhow::Result<Option<String>> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(name, _)| *name == env_name)
+                .map(|(_, secret)| secret.to_string()))
+        }
+    }
+
+    fn parse_with_secrets(args: &[&str], toml: &str, secrets: TestSecrets) -> Cli {
+        parse_with(
+            std::iter::once("realworld")
+                .chain(args.iter().copied())
+                .map(OsString::from)
+                .collect(),
+            toml::from_str(toml).unwrap(),
+            &[&secrets],
+        )
+        .unwrap()
+    }
+
+    fn parse(args: &[&str], toml: &str) -> Cli {
+        parse_with_secrets(args, toml, TestSecrets(&[]))
+    }
+
+    #[test]
+    fn file_should_replace_defaults() {
+        let cli = parse(
+            &[],
+            r#"
+            [db]
+            url = "postgres://localhost/realworld"
+            max_connections = 20
+            migrate_on_start = false
+
+            [auth]
+            jwt_signing_key = "secret"
+            require_email_verification = false
+            max_failed_logins = 3
+            plus_addressing = "strip"
+
+            [http]
+            cors_allowed_origins = ["https://a.example.com", "https://b.example.com"]
+
+            [pagination]
+            max_size = 50
+            "#,
+        );
+
+        assert_eq!(Some(20), cli.config.db_max_connections);
+        assert!(!cli.config.migrate_on_start);
+        assert!(!cli.config.require_email_verification);
+        assert_eq!(3, cli.config.max_failed_logins);
+        assert!(matches!(
+            cli.config.plus_addressing,
+            crate::config::PlusAddressing::Strip
+        ));
+        assert_eq!(
+            vec!["https://a.example.com", "https://b.example.com"],
+            cli.config.cors_allowed_origins
+        );
+        assert_eq!(50, cli.config.max_page_size);
+        assert_eq!(30, cli.config.db_acquire_timeout_secs);
+    }
+
+    #[test]
+    fn arguments_should_take_precedence_over_file() {
+        let cli = parse(
+            &[
+                "--database-url=postgres://localhost/other",
+                "--db-max-connections=5",
+            ],
+            r#"
+            [db]
+            url = "postgres://localhost/realworld"
+            max_connections = 20
+
+            [auth]
+            jwt_signing_key = "secret"
+            "#,
+        );
+
+        assert_eq!("postgres://localhost/other", cli.config.database_url);
+        assert_eq!(Some(5), cli.config.db_max_connections);
+    }
+
+    #[test]
+    fn secrets_should_take_precedence_over_file() {
+        let cli = parse_with_secrets(
+            &["--database-url=postgres://localhost/realworld"],
+            r#"
+            [auth]
+            jwt_signing_key = "from-file"
+            "#,
+            TestSecrets(&[
+                ("JWT_SIGNING_KEY", "from-secret"),
+                ("JWT_VERIFICATION_KEYS", "0:HS256:old,1:HS384:older"),
+            ]),
+        );
+
+        assert_eq!("from-secret", cli.config.jwt_signing_key);
+        assert_eq!(2, cli.config.jwt_verification_keys.len());
+    }
+
+    #[test]
+    fn unknown_keys_should_be_rejected() {
+        let error = toml::from_str::<ConfigFile>("[db]\nmax_conections = 20")
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("max_conections"));
+    }
+}
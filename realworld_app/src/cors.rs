@@ -0,0 +1,129 @@
+use crate::config::Config;
+
+use anyhow::Context;
+use axum::http::HeaderValue;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+///
+/// CORS headers, so that browsers let frontends on other origins call the API.
+///
+/// Access tokens are sent in the `Authorization` header, not in cookies,
+/// so by default any origin is allowed.
+///
+pub fn layer(config: &Config) -> anyhow::Result<CorsLayer> {
+    let allow_origin = if config
+        .cors_allowed_origins
+        .iter()
+        .any(|origin| origin == "*")
+    {
+        if config.cors_allow_credentials {
+            anyhow::bail!("cors_allow_credentials can't be combined with `*` origins");
+        }
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .cors_allowed_origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .with_context(|| format!("invalid CORS origin: {origin}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(config.cors_allowed_methods.clone())
+        .allow_headers(config.cors_allowed_headers.clone())
+        .allow_credentials(config.cors_allow_credentials)
+        .expose_headers([crate::routes::TOTAL_COUNT])
+        .max_age(Duration::from_secs(config.cors_max_age_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::header::*;
+    use axum::http::{Method, Request, StatusCode};
+    use clap::Parser;
+    use tower::ServiceExt;
+
+    fn test_config(args: &[&str]) -> Config {
+        Config::try_parse_from(
+            ["realworld", "--database-url=db", "--jwt-signing-key=key"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+    }
+
+    fn test_router(config: &Config) -> axum::Router {
+        axum::Router::new()
+            .route("/articles", axum::routing::post(|| async {}))
+            .layer(layer(config).unwrap())
+    }
+
+    fn preflight(origin: &str) -> Request<axum::body::Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/articles")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, "authorization,content-type")
+            .empty_body()
+    }
+
+    #[tokio::test]
+    async fn preflight_should_allow_any_origin_by_default() {
+        let response = test_router(&test_config(&[]))
+            .oneshot(preflight("https://example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("*", response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]);
+        assert_eq!("3600", response.headers()[ACCESS_CONTROL_MAX_AGE]);
+        assert!(response.headers()[ACCESS_CONTROL_ALLOW_METHODS]
+            .to_str()
+            .unwrap()
+            .contains("POST"));
+        assert!(response.headers()[ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap()
+            .contains("authorization"));
+    }
+
+    #[tokio::test]
+    async fn preflight_should_only_allow_configured_origins() {
+        let config = test_config(&[
+            "--cors-allowed-origins=https://example.com",
+            "--cors-allow-credentials",
+        ]);
+
+        let allowed = test_router(&config)
+            .oneshot(preflight("https://example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            "https://example.com",
+            allowed.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]
+        );
+        assert_eq!("true", allowed.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS]);
+
+        let denied = test_router(&config)
+            .oneshot(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert!(denied.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[test]
+    fn credentials_should_not_be_allowed_for_any_origin() {
+        assert!(layer(&test_config(&["--cors-allow-credentials"])).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//!
+//! Checks of the environment the app is about to run in, for CI and container entrypoints:
+//! whether the configuration is valid, the JWT keys work, the databases are reachable
+//! and migrated, and the ports are free.
+//!
+//! Every check is run even when an earlier one fails, so that one run shows every problem.
+//!
+
+use crate::app::backend;
+use crate::config::Config;
+use crate::tenant;
+
+use jwt::{SigningAlgorithm, VerifyingAlgorithm};
+use realworld_domain::user::jwt_keys::JwtAlgorithm;
+use std::net::SocketAddr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but probably not as intended
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+
+    fn from_result(name: impl Into<String>, result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self::new(name, CheckStatus::Ok, detail),
+            Err(error) => Self::new(name, CheckStatus::Failed, format!("{error:#}")),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DoctorReport {
+    /// Whether no check failed. Warnings don't count.
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    fn new(checks: Vec<Check>) -> Self {
+        Self {
+            ok: checks
+                .iter()
+                .all(|check| check.status != CheckStatus::Failed),
+            checks,
+        }
+    }
+}
+
+/// Run every check
+pub async fn diagnose(config: &Config) -> DoctorReport {
+    let mut checks = vec![check_config(config), check_jwt_keys(config)];
+
+    let databases = std::iter::once(("default".to_string(), config.database_url.clone()))
+        .chain(tenant::tenant_database_urls(config));
+    for (name, url) in databases {
+        checks.extend(check_database(config, &name, &url).await);
+    }
+
+    for addr in listen_addrs(config) {
+        checks.push(check_port(addr).await);
+    }
+
+    DoctorReport::new(checks)
+}
+
+fn check_config(config: &Config) -> Check {
+    Check::from_result("config", config.validate().map(|()| "valid".to_string()))
+}
+
+///
+/// Sign a token with the signing key and verify it again,
+/// and warn about keys shorter than the output of their hash function.
+///
+fn check_jwt_keys(config: &Config) -> Check {
+    let keys = config.jwt_keys();
+    let (key_id, key) = keys.signing_key();
+    let (header, claims) = ("doctor", "doctor");
+
+    let verified = key.sign(header, claims).is_ok_and(|signature| {
+        keys.verification_keys(Some(key_id))
+            .any(|key| key.verify(header, claims, &signature).unwrap_or(false))
+    });
+    if !verified {
+        return Check::new(
+            "jwt_keys",
+            CheckStatus::Failed,
+            "a token signed with jwt_signing_key could not be verified",
+        );
+    }
+
+    let min_length = match config.jwt_algorithm {
+        JwtAlgorithm::Hs256 => 32,
+        JwtAlgorithm::Hs384 => 48,
+        JwtAlgorithm::Hs512 => 64,
+    };
+    if config.jwt_signing_key.len() < min_length {
+        return Check::new(
+            "jwt_keys",
+            CheckStatus::Warning,
+            format!(
+                "jwt_signing_key is {} bytes, {:?} keys should be at least {min_length}",
+                config.jwt_signing_key.len(),
+                config.jwt_algorithm
+            ),
+        );
+    }
+
+    Check::new(
+        "jwt_keys",
+        CheckStatus::Ok,
+        format!("signing with key {key_id}"),
+    )
+}
+
+/// Connect to the database, and compare its schema to the migrations of this build
+async fn check_database(config: &Config, name: &str, url: &str) -> Vec<Check> {
+    let db = match backend::Db::connect(url, &config.db_pool_config()).await {
+        Ok(db) => db,
+        Err(error) => return vec![Check::from_result(format!("database {name}"), Err(error))],
+    };
+    let connected = Check::new(format!("database {name}"), CheckStatus::Ok, "connected");
+
+    let migrations_name = format!("migrations {name}");
+    let migrations = match db.schema_status().await {
+        Err(error) => Check::from_result(migrations_name, Err(error)),
+        Ok(status) => {
+            let pending = status.pending().count();
+            match status.verify() {
+                Ok(()) => Check::new(migrations_name, CheckStatus::Ok, "up to date"),
+                // They'll be run on startup
+                Err(_) if status.unknown_versions.is_empty() && config.migrate_on_start => {
+                    Check::new(
+                        migrations_name,
+                        CheckStatus::Warning,
+                        format!("{pending} to run on startup"),
+                    )
+                }
+                Err(error) => Check::from_result(migrations_name, Err(error)),
+            }
+        }
+    };
+
+    vec![connected, migrations]
+}
+
+/// The addresses that `serve` listens on
+#[cfg_attr(not(feature = "grpc"), allow(unused_variables, unused_mut))]
+fn listen_addrs(config: &Config) -> Vec<SocketAddr> {
+    let mut addrs = vec![crate::HTTP_LISTEN_ADDR];
+    #[cfg(feature = "grpc")]
+    addrs.extend(config.grpc_listen_addr);
+    addrs
+}
+
+async fn check_port(addr: SocketAddr) -> Check {
+    Check::from_result(
+        format!("port {}", addr.port()),
+        tokio::net::TcpListener::bind(addr)
+            .await
+            .map(|_| format!("{addr} is free"))
+            .map_err(|error| anyhow::anyhow!("can't listen on {addr}: {error}")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clap::Parser;
+
+    fn test_config(args: &[&str]) -> Config {
+        let config_args = [
+            "realworld",
+            "--database-url=postgres://localhost/realworld",
+            "--jwt-signing-key=0123456789abcdef0123456789abcdef0123456789abcdef",
+        ];
+        Config::try_parse_from(config_args.iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn jwt_keys_should_be_checked() {
+        assert_eq!(CheckStatus::Ok, check_jwt_keys(&test_config(&[])).status);
+
+        let check = check_jwt_keys(&test_config(&["--jwt-algorithm=HS512"]));
+        assert_eq!(CheckStatus::Warning, check.status);
+        assert_eq!(
+            "jwt_signing_key is 48 bytes, Hs512 keys should be at least 64",
+            check.detail
+        );
+    }
+
+    #[test]
+    fn invalid_config_should_fail() {
+        let check = check_config(&test_config(&[
+            "--default-page-size=50",
+            "--max-page-size=20",
+        ]));
+
+        assert_eq!(CheckStatus::Failed, check.status);
+        assert!(check
+            .detail
+            .contains("default_page_size (50) must be between 1 and max_page_size (20)"));
+    }
+
+    #[tokio::test]
+    async fn taken_port_should_fail() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        assert_eq!(CheckStatus::Failed, check_port(addr).await.status);
+        drop(listener);
+        assert_eq!(CheckStatus::Ok, check_port(addr).await.status);
+    }
+
+    #[test]
+    fn report_should_fail_only_on_failed_checks() {
+        let report = DoctorReport::new(vec![
+            Check::new("a", CheckStatus::Ok, ""),
+            Check::new("b", CheckStatus::Warning, ""),
+        ]);
+        assert!(report.ok);
+        assert_eq!(
+            serde_json::json!({
+                "ok": true,
+                "checks": [
+                    { "name": "a", "status": "ok", "detail": "" },
+                    { "name": "b", "status": "warning", "detail": "" },
+                ]
+            }),
+            serde_json::to_value(&report).unwrap()
+        );
+
+        let report = DoctorReport::new(vec![Check::new("a", CheckStatus::Failed, "")]);
+        assert!(!report.ok);
+    }
+}
@@ -0,0 +1,189 @@
+//!
+//! Signing in, refreshing, sessions and resetting passwords.
+//!
+
+use super::*;
+
+/// The refresh token of a new sign-in
+async fn sign_in(server: &TestServer, user: &TestUser, password: &str) -> Value {
+    let (status, mut body) = server.login(&user.email, password).await;
+    assert_eq!(StatusCode::OK, status, "{body}");
+    body["user"]["refreshToken"].take()
+}
+
+/// Signed in again, on a new session
+async fn other_device(server: &TestServer, user: &TestUser) -> TestUser {
+    let (status, body) = server.login(&user.email, PASSWORD).await;
+    assert_eq!(StatusCode::OK, status, "{body}");
+    TestUser {
+        token: body["user"]["token"].as_str().unwrap().to_string(),
+        ..user.clone()
+    }
+}
+
+async fn current_user_status(server: &TestServer, user: &TestUser) -> StatusCode {
+    server
+        .request(Method::GET, "/api/user", Some(user), None)
+        .await
+        .0
+}
+
+async fn refresh(server: &TestServer, refresh_token: &Value) -> StatusCode {
+    server
+        .request(
+            Method::POST,
+            "/api/users/refresh",
+            None,
+            Some(json!({ "user": { "refreshToken": refresh_token } })),
+        )
+        .await
+        .0
+}
+
+#[tokio::test]
+async fn login_should_require_verified_email() {
+    let server = TestServer::start().await;
+    let user = server.register_unverified("jake").await;
+
+    let (status, body) = server.login(&user.email, PASSWORD).await;
+    assert_ne!(StatusCode::OK, status);
+    assert!(body["errors"]["email"].is_array(), "{body}");
+
+    server.verify_email(&user.email).await;
+    let (status, body) = server.login(&user.email, PASSWORD).await;
+    assert_eq!(StatusCode::OK, status, "{body}");
+}
+
+#[tokio::test]
+async fn password_reset_should_end_sessions() {
+    let server = TestServer::start().await;
+    let user = server.register("jake").await;
+    let refresh_token = sign_in(&server, &user, PASSWORD).await;
+
+    server
+        .ok(
+            Method::POST,
+            "/api/users/forgot-password",
+            None,
+            Some(json!({ "user": { "email": user.email } })),
+        )
+        .await;
+    let email = server.last_email_to(&user.email);
+    let reset_token = email
+        .split_once(": ")
+        .and_then(|(_, rest)| rest.lines().next())
+        .unwrap();
+
+    server
+        .ok(
+            Method::POST,
+            "/api/users/reset-password",
+            None,
+            Some(json!({
+                "user": { "resetToken": reset_token, "password": "new password" }
+            })),
+        )
+        .await;
+
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        refresh(&server, &refresh_token).await
+    );
+    let new_refresh_token = sign_in(&server, &user, "new password").await;
+    assert_eq!(StatusCode::OK, refresh(&server, &new_refresh_token).await);
+}
+
+#[tokio::test]
+async fn revoked_session_should_end_its_access_token() {
+    let server = TestServer::start().await;
+    let user = server.register("jake").await;
+    let other = other_device(&server, &user).await;
+
+    let sessions = server
+        .ok(Method::GET, "/api/user/sessions", Some(&user), None)
+        .await;
+    // Most recently seen first
+    let other_session_id = sessions["sessions"][0]["id"].as_str().unwrap();
+    server
+        .ok(
+            Method::DELETE,
+            &format!("/api/user/sessions/{other_session_id}"),
+            Some(&user),
+            None,
+        )
+        .await;
+
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        current_user_status(&server, &other).await
+    );
+    assert_eq!(StatusCode::OK, current_user_status(&server, &user).await);
+}
+
+#[tokio::test]
+async fn password_change_should_end_access_tokens_of_other_devices() {
+    let server = TestServer::start().await;
+    let user = server.register("jake").await;
+    let other = other_device(&server, &user).await;
+
+    let body = server
+        .ok(
+            Method::PUT,
+            "/api/user/password",
+            Some(&user),
+            Some(json!({
+                "user": { "currentPassword": PASSWORD, "newPassword": "new password" }
+            })),
+        )
+        .await;
+    let user = TestUser {
+        token: body["user"]["token"].as_str().unwrap().to_string(),
+        ..user
+    };
+
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        current_user_status(&server, &other).await
+    );
+    assert_eq!(StatusCode::OK, current_user_status(&server, &user).await);
+}
+
+#[tokio::test]
+async fn user_update_should_not_change_password() {
+    let server = TestServer::start().await;
+    let user = server.register("jake").await;
+
+    let (status, body) = server
+        .request(
+            Method::PUT,
+            "/api/user",
+            Some(&user),
+            Some(json!({ "user": { "password": "new password" } })),
+        )
+        .await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert!(body["errors"]["password"].is_array(), "{body}");
+    sign_in(&server, &user, PASSWORD).await;
+}
+
+#[tokio::test]
+async fn anonymized_account_should_end_access_tokens_of_other_devices() {
+    let server = TestServer::start_with(&["--account-deletion=anonymize"], &[]).await;
+    let user = server.register("jake").await;
+    let other = other_device(&server, &user).await;
+
+    server
+        .ok(
+            Method::DELETE,
+            "/api/user",
+            Some(&user),
+            Some(json!({ "user": { "password": PASSWORD } })),
+        )
+        .await;
+
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        current_user_status(&server, &other).await
+    );
+}
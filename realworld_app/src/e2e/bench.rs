@@ -0,0 +1,89 @@
+//!
+//! Request latency, for comparing the static and the `dyn-repos` wiring of the repositories.
+//!
+//! Ignored by default, since it only makes sense in a release build:
+//! `cargo test --release -p realworld-app e2e::bench -- --ignored --nocapture`,
+//! with and without `--features dyn-repos`. [bench-dyn-repos.sh](../../../scripts/bench-dyn-repos.sh)
+//! runs both, along with the incremental build times.
+//!
+
+use super::*;
+
+use std::time::{Duration, Instant};
+
+const WARMUP: usize = 50;
+const ITERATIONS: usize = 500;
+
+/// Prints the median and 99th percentile of the time it takes `request` to answer
+async fn measure<F, Fut>(name: &str, mut request: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    for _ in 0..WARMUP {
+        request().await;
+    }
+
+    let mut durations: Vec<Duration> = Vec::with_capacity(ITERATIONS);
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        request().await;
+        durations.push(start.elapsed());
+    }
+    durations.sort();
+
+    println!(
+        "{name:<20} median {:>8.1?}  p99 {:>8.1?}",
+        durations[ITERATIONS / 2],
+        durations[ITERATIONS * 99 / 100]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn request_latency() {
+    let server = TestServer::start_with(
+        &[
+            "--rate-limit-ip-per-minute=0",
+            "--rate-limit-user-per-minute=0",
+        ],
+        &[],
+    )
+    .await;
+    let author = server.register("author").await;
+    let reader = server.register("reader").await;
+    let mut slugs = vec![];
+    for i in 0..20 {
+        let article = server
+            .create_article(&author, &format!("Article {i}"), &["bench"])
+            .await;
+        slugs.push(article["slug"].as_str().unwrap().to_string());
+    }
+    server.follow(&reader, "author").await;
+
+    println!(
+        "repositories: {}",
+        if cfg!(feature = "dyn-repos") {
+            "dyn"
+        } else {
+            "static"
+        }
+    );
+    measure("get article", || async {
+        server.get_article(Some(&reader), &slugs[0]).await;
+    })
+    .await;
+    measure("list articles", || async {
+        server.list_articles(Some(&reader), "tag=bench").await;
+    })
+    .await;
+    measure("feed", || async {
+        server.feed(&reader).await;
+    })
+    .await;
+    measure("favorite toggle", || async {
+        server.favorite(&reader, &slugs[1]).await;
+        server.unfavorite(&reader, &slugs[1]).await;
+    })
+    .await;
+}
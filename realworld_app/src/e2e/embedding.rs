@@ -0,0 +1,168 @@
+//!
+//! The router of [crate::AppBuilder], as another server would embed it.
+//!
+
+use super::*;
+use crate::app::{App, ConfigSettings};
+use crate::AppBuilder;
+
+use axum::Extension;
+use entrait::Impl;
+
+use realworld_domain::article::slugify::SlugStrategy;
+use realworld_domain::article::tag::TagRules;
+use realworld_domain::pagination::PageSizes;
+use realworld_domain::sanitize::SanitizeMode;
+use realworld_domain::user::auth::Token;
+use realworld_domain::user::email::PlusAddressing;
+use realworld_domain::user::password::PasswordAlgorithm;
+use realworld_domain::user::repo::DeletionMode;
+use realworld_domain::GetConfig;
+
+/// The settings of the config, but with room for tiny articles only
+struct TinyArticles(ConfigSettings);
+
+impl GetConfig for TinyArticles {
+    fn require_email_verification(&self) -> bool {
+        self.0.require_email_verification()
+    }
+
+    fn account_deletion_mode(&self) -> DeletionMode {
+        self.0.account_deletion_mode()
+    }
+
+    fn max_failed_logins(&self) -> u32 {
+        self.0.max_failed_logins()
+    }
+
+    fn failed_login_window(&self) -> time::Duration {
+        self.0.failed_login_window()
+    }
+
+    fn tag_rules(&self) -> &TagRules {
+        self.0.tag_rules()
+    }
+
+    fn words_per_minute(&self) -> u32 {
+        self.0.words_per_minute()
+    }
+
+    fn trending_window(&self) -> time::Duration {
+        self.0.trending_window()
+    }
+
+    fn max_avatar_bytes(&self) -> usize {
+        self.0.max_avatar_bytes()
+    }
+
+    fn max_article_body_bytes(&self) -> usize {
+        30
+    }
+
+    fn site_url(&self) -> &str {
+        self.0.site_url()
+    }
+
+    fn sanitize_mode(&self) -> SanitizeMode {
+        self.0.sanitize_mode()
+    }
+
+    fn slug_strategy(&self) -> SlugStrategy {
+        self.0.slug_strategy()
+    }
+
+    fn reserved_usernames(&self) -> &[String] {
+        self.0.reserved_usernames()
+    }
+
+    fn page_sizes(&self) -> PageSizes {
+        self.0.page_sizes()
+    }
+
+    fn materialized_feeds(&self) -> bool {
+        self.0.materialized_feeds()
+    }
+
+    fn plus_addressing(&self) -> PlusAddressing {
+        self.0.plus_addressing()
+    }
+
+    fn password_algorithm(&self) -> PasswordAlgorithm {
+        self.0.password_algorithm()
+    }
+}
+
+/// Validated, unlike the config of [TestServer::start]
+fn test_config() -> config::Config {
+    let database_url = if cfg!(feature = "sqlite") {
+        "--database-url=sqlite::memory:"
+    } else {
+        "--database-url=postgres://unused"
+    };
+    config::Config::try_parse_from(["realworld", database_url, "--jwt-signing-key=e2e"]).unwrap()
+}
+
+async fn start(builder: AppBuilder) -> TestServer {
+    TestServer {
+        router: builder.db(create_test_db().await).build().await.unwrap(),
+        headers: HeaderMap::new(),
+        // The builder sends emails by its own mailer
+        outbox: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn built_router_should_use_the_given_config() {
+    let settings = TinyArticles(ConfigSettings::new(Arc::new(test_config())));
+    let server = start(AppBuilder::new(test_config()).get_config(settings)).await;
+    // The builder's emails don't reach the outbox, and these users don't log in
+    let author = server.register_unverified("jake").await;
+
+    // Too large by 3 bytes
+    let (status, _) = server
+        .request(
+            Method::POST,
+            "/api/articles",
+            Some(&author),
+            Some(json!({
+                "article": {
+                    "title": "Dragons",
+                    "description": "About dragons",
+                    "body": "All there is to say about dragons",
+                    "tagList": [],
+                }
+            })),
+        )
+        .await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+
+    // All there is to say about Tiny
+    server.create_article(&author, "Tiny", &[]).await;
+}
+
+#[tokio::test]
+async fn added_routes_should_be_handled_like_those_of_the_app() {
+    async fn site_url(Extension(app): Extension<Impl<App>>, _: Token) -> String {
+        app.config.site_url.clone()
+    }
+
+    let server = start(
+        AppBuilder::new(test_config())
+            .routes(|api| api.route("/site-url", axum::routing::get(site_url))),
+    )
+    .await;
+    let user = server.register_unverified("jake").await;
+
+    assert_eq!(
+        (StatusCode::OK, json!("http://localhost:8080")),
+        server
+            .request(Method::GET, "/api/site-url", Some(&user), None)
+            .await
+    );
+    // Through the error handling of the app
+    let (status, body) = server
+        .request(Method::GET, "/api/site-url", None, None)
+        .await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+    assert!(body["errors"].is_object(), "{body}");
+}
@@ -0,0 +1,416 @@
+//!
+//! End-to-end tests of the whole app, in the spirit of the Postman collection of the RealWorld spec.
+//!
+//! Every test gets a [TestServer] with its own database, serving the same router as `serve`,
+//! and talks to it through the typed helpers below instead of hand-written requests.
+//!
+
+mod accounts;
+mod bench;
+mod embedding;
+mod postman;
+mod tenants;
+mod unlisted;
+
+use crate::app::backend;
+use crate::email::Mailer;
+use crate::live_config::{LiveConfig, LiveSettings};
+use crate::{config, link_app, link_tenant_apps, metrics, router, state};
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use clap::Parser;
+use realworld_domain::user::email::EmailMessage;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+pub const PASSWORD: &str = "password";
+
+#[derive(Clone)]
+pub struct TestServer {
+    router: axum::Router,
+    /// Sent with every request
+    headers: HeaderMap,
+    /// The emails sent by the default app
+    outbox: Arc<Mutex<Vec<EmailMessage>>>,
+}
+
+/// A registered user, signed in
+#[derive(Clone)]
+pub struct TestUser {
+    pub username: String,
+    pub email: String,
+    pub token: String,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        Self::start_with_tenants(&[]).await
+    }
+
+    /// In multi-tenant mode when there are tenants, see [TestServer::with_tenant]
+    pub async fn start_with_tenants(tenants: &[&str]) -> Self {
+        Self::start_with(&[], tenants).await
+    }
+
+    /// `args` are added to the command line of the app
+    pub async fn start_with(args: &[&str], tenants: &[&str]) -> Self {
+        let config = config::Config::try_parse_from(
+            [
+                "realworld",
+                "--database-url=unused",
+                "--jwt-signing-key=e2e",
+            ]
+            .iter()
+            .chain(args),
+        )
+        .unwrap();
+        let shared = state::SharedState::default();
+        let metrics = metrics::Metrics::new();
+        let live_config = LiveConfig::new(LiveSettings::from_config(&config));
+
+        let (mut app, notification_receiver) = link_app(
+            Arc::new(config),
+            live_config,
+            create_test_db().await,
+            &shared,
+            metrics.clone(),
+        )
+        .unwrap();
+        let outbox = Arc::new(Mutex::new(vec![]));
+        app.mailer = Mailer::Outbox(outbox.clone());
+
+        let mut tenant_dbs = vec![];
+        for tenant in tenants {
+            tenant_dbs.push((tenant.to_string(), create_test_db().await));
+        }
+        let tenant_notification_receivers =
+            link_tenant_apps(&mut app, tenant_dbs, &shared).unwrap();
+
+        for (app, notification_receiver) in std::iter::once((app.clone(), notification_receiver))
+            .chain(tenant_notification_receivers)
+        {
+            tokio::spawn(crate::events::handle_events(app, notification_receiver));
+        }
+
+        Self {
+            router: router(app, &shared, metrics, std::convert::identity).unwrap(),
+            headers: HeaderMap::new(),
+            outbox,
+        }
+    }
+
+    /// The same server, with requests for a tenant
+    pub fn with_tenant(&self, tenant: &str) -> Self {
+        let mut server = self.clone();
+        server
+            .headers
+            .insert("x-tenant", HeaderValue::from_str(tenant).unwrap());
+        server
+    }
+
+    /// The body of the latest email sent to the address
+    pub fn last_email_to(&self, email: &str) -> String {
+        let outbox = self.outbox.lock().unwrap();
+        let message = outbox
+            .iter()
+            .rev()
+            .find(|message| message.to.as_ref() == email)
+            .unwrap_or_else(|| panic!("no email to {email}"));
+        message.body.clone()
+    }
+
+    /// The status and JSON body of the response.
+    /// `Value::Null` if there's no body, and a string if it isn't JSON, like most errors.
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        user: Option<&TestUser>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(path);
+        request.headers_mut().unwrap().extend(self.headers.clone());
+        if let Some(user) = user {
+            request = request.header(header::AUTHORIZATION, format!("Token {}", user.token));
+        }
+        let mut request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(serde_json::to_vec(&body).unwrap())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        // As if served by `axum::serve`, for rate limiting
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+        };
+
+        (status, body)
+    }
+
+    /// A response that must be `200 OK`
+    async fn ok(
+        &self,
+        method: Method,
+        path: &str,
+        user: Option<&TestUser>,
+        body: Option<Value>,
+    ) -> Value {
+        let (status, body) = self.request(method.clone(), path, user, body).await;
+        assert_eq!(StatusCode::OK, status, "{method} {path}: {body}");
+        body
+    }
+
+    /// A new user, with a verified email address
+    pub async fn register(&self, username: &str) -> TestUser {
+        let user = self.register_unverified(username).await;
+        self.verify_email(&user.email).await;
+        user
+    }
+
+    /// A new user, who can't log in before verifying the email address
+    pub async fn register_unverified(&self, username: &str) -> TestUser {
+        let email = format!("{username}@example.com");
+        let body = self
+            .ok(
+                Method::POST,
+                "/api/users",
+                None,
+                Some(json!({
+                    "user": { "username": username, "email": email, "password": PASSWORD }
+                })),
+            )
+            .await;
+
+        TestUser {
+            username: username.to_string(),
+            email,
+            token: body["user"]["token"].as_str().unwrap().to_string(),
+        }
+    }
+
+    /// Verify the address with the code last emailed to it
+    pub async fn verify_email(&self, email: &str) {
+        let body = self.last_email_to(email);
+        let (_, verification_token) = body.rsplit_once(' ').unwrap();
+        self.ok(
+            Method::POST,
+            "/api/users/verify",
+            None,
+            Some(json!({ "user": { "verificationToken": verification_token } })),
+        )
+        .await;
+    }
+
+    pub async fn login(&self, email: &str, password: &str) -> (StatusCode, Value) {
+        self.request(
+            Method::POST,
+            "/api/users/login",
+            None,
+            Some(json!({ "user": { "email": email, "password": password } })),
+        )
+        .await
+    }
+
+    pub async fn current_user(&self, user: &TestUser) -> Value {
+        self.ok(Method::GET, "/api/user", Some(user), None).await["user"].take()
+    }
+
+    pub async fn update_user(&self, user: &TestUser, update: Value) -> Value {
+        self.ok(
+            Method::PUT,
+            "/api/user",
+            Some(user),
+            Some(json!({ "user": update })),
+        )
+        .await["user"]
+            .take()
+    }
+
+    pub async fn create_article(&self, user: &TestUser, title: &str, tags: &[&str]) -> Value {
+        self.ok(
+            Method::POST,
+            "/api/articles",
+            Some(user),
+            Some(json!({
+                "article": {
+                    "title": title,
+                    "description": format!("About {title}"),
+                    "body": format!("All there is to say about {title}"),
+                    "tagList": tags,
+                }
+            })),
+        )
+        .await["article"]
+            .take()
+    }
+
+    pub async fn get_article(&self, user: Option<&TestUser>, slug: &str) -> (StatusCode, Value) {
+        let (status, mut body) = self
+            .request(Method::GET, &format!("/api/articles/{slug}"), user, None)
+            .await;
+        (status, body["article"].take())
+    }
+
+    pub async fn update_article(&self, user: &TestUser, slug: &str, update: Value) -> Value {
+        self.ok(
+            Method::PUT,
+            &format!("/api/articles/{slug}"),
+            Some(user),
+            Some(json!({ "article": update })),
+        )
+        .await["article"]
+            .take()
+    }
+
+    pub async fn delete_article(&self, user: &TestUser, slug: &str) -> StatusCode {
+        self.request(
+            Method::DELETE,
+            &format!("/api/articles/{slug}"),
+            Some(user),
+            None,
+        )
+        .await
+        .0
+    }
+
+    /// `query` is the query string, e.g. `tag=rust`
+    pub async fn list_articles(&self, user: Option<&TestUser>, query: &str) -> Vec<Value> {
+        articles(
+            self.ok(Method::GET, &format!("/api/articles?{query}"), user, None)
+                .await,
+        )
+    }
+
+    pub async fn feed(&self, user: &TestUser) -> Vec<Value> {
+        articles(
+            self.ok(Method::GET, "/api/articles/feed", Some(user), None)
+                .await,
+        )
+    }
+
+    pub async fn favorite(&self, user: &TestUser, slug: &str) -> Value {
+        self.ok(
+            Method::POST,
+            &format!("/api/articles/{slug}/favorite"),
+            Some(user),
+            None,
+        )
+        .await["article"]
+            .take()
+    }
+
+    pub async fn unfavorite(&self, user: &TestUser, slug: &str) -> Value {
+        self.ok(
+            Method::DELETE,
+            &format!("/api/articles/{slug}/favorite"),
+            Some(user),
+            None,
+        )
+        .await["article"]
+            .take()
+    }
+
+    pub async fn add_comment(&self, user: &TestUser, slug: &str, body: &str) -> Value {
+        self.ok(
+            Method::POST,
+            &format!("/api/articles/{slug}/comments"),
+            Some(user),
+            Some(json!({ "comment": { "body": body } })),
+        )
+        .await["comment"]
+            .take()
+    }
+
+    pub async fn comments(&self, user: Option<&TestUser>, slug: &str) -> Vec<Value> {
+        match self
+            .ok(
+                Method::GET,
+                &format!("/api/articles/{slug}/comments"),
+                user,
+                None,
+            )
+            .await["comments"]
+            .take()
+        {
+            Value::Array(comments) => comments,
+            other => panic!("not a list of comments: {other}"),
+        }
+    }
+
+    pub async fn delete_comment(&self, user: &TestUser, slug: &str, comment_id: i64) -> StatusCode {
+        self.request(
+            Method::DELETE,
+            &format!("/api/articles/{slug}/comments/{comment_id}"),
+            Some(user),
+            None,
+        )
+        .await
+        .0
+    }
+
+    pub async fn profile(&self, user: Option<&TestUser>, username: &str) -> Value {
+        self.ok(
+            Method::GET,
+            &format!("/api/profiles/{username}"),
+            user,
+            None,
+        )
+        .await["profile"]
+            .take()
+    }
+
+    pub async fn follow(&self, user: &TestUser, username: &str) -> Value {
+        self.ok(
+            Method::POST,
+            &format!("/api/profiles/{username}/follow"),
+            Some(user),
+            None,
+        )
+        .await["profile"]
+            .take()
+    }
+
+    pub async fn unfollow(&self, user: &TestUser, username: &str) -> Value {
+        self.ok(
+            Method::DELETE,
+            &format!("/api/profiles/{username}/follow"),
+            Some(user),
+            None,
+        )
+        .await["profile"]
+            .take()
+    }
+}
+
+async fn create_test_db() -> backend::Db {
+    #[cfg(not(feature = "sqlite"))]
+    let db = realworld_db::create_test_db().await;
+    #[cfg(feature = "sqlite")]
+    let db = realworld_db_sqlite::create_test_db().await;
+
+    db.into_inner()
+}
+
+fn articles(mut body: Value) -> Vec<Value> {
+    match body["articles"].take() {
+        Value::Array(articles) => articles,
+        other => panic!("not a list of articles: {other}"),
+    }
+}
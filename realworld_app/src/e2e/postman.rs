@@ -0,0 +1,190 @@
+//!
+//! The folders of the Postman collection: auth, articles, favorites, comments and profiles.
+//!
+
+use super::*;
+
+#[tokio::test]
+async fn auth() {
+    let server = TestServer::start().await;
+    let user = server.register("jake").await;
+
+    let (status, body) = server.login(&user.email, PASSWORD).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!("jake", body["user"]["username"]);
+    assert_eq!("jake@example.com", body["user"]["email"]);
+    assert!(body["user"]["token"].is_string());
+
+    let (status, _) = server.login(&user.email, "wrong").await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+    let current = server.current_user(&user).await;
+    assert_eq!("jake", current["username"]);
+    assert_eq!("", current["bio"]);
+
+    let updated = server
+        .update_user(&user, json!({ "bio": "I work at statefarm" }))
+        .await;
+    assert_eq!("I work at statefarm", updated["bio"]);
+    assert_eq!(
+        "I work at statefarm",
+        server.current_user(&user).await["bio"]
+    );
+
+    let (status, _) = server.request(Method::GET, "/api/user", None, None).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+}
+
+#[tokio::test]
+async fn articles() {
+    let server = TestServer::start().await;
+    let author = server.register("jake").await;
+
+    let article = server
+        .create_article(
+            &author,
+            "How to train your dragon",
+            &["dragons", "training"],
+        )
+        .await;
+    let slug = article["slug"].as_str().unwrap().to_string();
+    assert_eq!("How to train your dragon", article["title"]);
+    assert_eq!(json!(["dragons", "training"]), article["tagList"]);
+    assert_eq!("jake", article["author"]["username"]);
+    assert_eq!(false, article["favorited"]);
+    assert_eq!(0, article["favoritesCount"]);
+
+    let (status, fetched) = server.get_article(None, &slug).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(article["title"], fetched["title"]);
+    // Views are counted in the background
+    assert_eq!(0, fetched["viewsCount"]);
+
+    let all = server.list_articles(None, "").await;
+    assert_eq!(1, all.len());
+    assert_eq!(slug, all[0]["slug"]);
+    assert_eq!(1, server.list_articles(None, "author=jake").await.len());
+    assert_eq!(1, server.list_articles(None, "tag=dragons").await.len());
+    assert!(server.list_articles(None, "tag=cooking").await.is_empty());
+
+    let updated = server
+        .update_article(&author, &slug, json!({ "body": "With two hands" }))
+        .await;
+    assert_eq!("With two hands", updated["body"]);
+    assert_eq!(article["title"], updated["title"]);
+
+    let other = server.register("other").await;
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        server.delete_article(&other, &slug).await
+    );
+    assert_eq!(StatusCode::OK, server.delete_article(&author, &slug).await);
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        server.get_article(None, &slug).await.0
+    );
+}
+
+#[tokio::test]
+async fn favorites() {
+    let server = TestServer::start().await;
+    let author = server.register("jake").await;
+    let reader = server.register("celeb").await;
+    let slug = server
+        .create_article(&author, "How to train your dragon", &[])
+        .await["slug"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let favorited = server.favorite(&reader, &slug).await;
+    assert_eq!(true, favorited["favorited"]);
+    assert_eq!(1, favorited["favoritesCount"]);
+
+    let by_favorite = server.list_articles(Some(&reader), "favorited=celeb").await;
+    assert_eq!(1, by_favorite.len());
+    assert_eq!(true, by_favorite[0]["favorited"]);
+    assert_eq!(
+        false,
+        server.list_articles(Some(&author), "").await[0]["favorited"]
+    );
+
+    let unfavorited = server.unfavorite(&reader, &slug).await;
+    assert_eq!(false, unfavorited["favorited"]);
+    assert_eq!(0, unfavorited["favoritesCount"]);
+    assert!(server
+        .list_articles(None, "favorited=celeb")
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn comments() {
+    let server = TestServer::start().await;
+    let author = server.register("jake").await;
+    let commenter = server.register("celeb").await;
+    let slug = server
+        .create_article(&author, "How to train your dragon", &[])
+        .await["slug"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let comment = server
+        .add_comment(&commenter, &slug, "Thank you so much!")
+        .await;
+    assert_eq!("Thank you so much!", comment["body"]);
+    assert_eq!("celeb", comment["author"]["username"]);
+
+    let comments = server.comments(None, &slug).await;
+    assert_eq!(1, comments.len());
+    assert_eq!(comment["id"], comments[0]["id"]);
+
+    let comment_id = comment["id"].as_i64().unwrap();
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        server.delete_comment(&author, &slug, comment_id).await
+    );
+    assert_eq!(
+        StatusCode::OK,
+        server.delete_comment(&commenter, &slug, comment_id).await
+    );
+    assert!(server.comments(None, &slug).await.is_empty());
+}
+
+#[tokio::test]
+async fn profiles() {
+    let server = TestServer::start().await;
+    let fan = server.register("jake").await;
+    let celeb = server.register("celeb").await;
+    server
+        .create_article(&celeb, "How to train your dragon", &[])
+        .await;
+
+    let profile = server.profile(None, "celeb").await;
+    assert_eq!("celeb", profile["username"]);
+    assert_eq!(false, profile["following"]);
+    assert!(server.feed(&fan).await.is_empty());
+
+    assert_eq!(
+        true,
+        server.follow(&fan, &celeb.username).await["following"]
+    );
+    assert_eq!(true, server.profile(Some(&fan), "celeb").await["following"]);
+
+    let feed = server.feed(&fan).await;
+    assert_eq!(1, feed.len());
+    assert_eq!("celeb", feed[0]["author"]["username"]);
+    assert_eq!(true, feed[0]["author"]["following"]);
+
+    assert_eq!(
+        false,
+        server.unfollow(&fan, &celeb.username).await["following"]
+    );
+    assert!(server.feed(&fan).await.is_empty());
+
+    let (status, _) = server
+        .request(Method::GET, "/api/profiles/nobody", None, None)
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
@@ -0,0 +1,41 @@
+//!
+//! Multi-tenant mode, where every tenant has a database of its own.
+//!
+
+use super::*;
+
+#[tokio::test]
+async fn tenants_should_not_see_each_others_content() {
+    let server = TestServer::start_with_tenants(&["acme", "globex"]).await;
+    let acme = server.with_tenant("acme");
+    let globex = server.with_tenant("globex");
+
+    let author = acme.register("jake").await;
+    acme.create_article(&author, "How to train your dragon", &[])
+        .await;
+
+    assert_eq!(1, acme.list_articles(None, "").await.len());
+    assert!(globex.list_articles(None, "").await.is_empty());
+
+    // The username is only taken within acme
+    globex.register("jake").await;
+}
+
+#[tokio::test]
+async fn requests_should_name_known_tenant() {
+    let server = TestServer::start_with_tenants(&["acme"]).await;
+
+    let (status, _) = server
+        .request(Method::GET, "/api/articles", None, None)
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+
+    let (status, _) = server
+        .with_tenant("initech")
+        .request(Method::GET, "/api/articles", None, None)
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+
+    let (status, _) = server.request(Method::GET, "/ready", None, None).await;
+    assert_eq!(StatusCode::OK, status);
+}
@@ -0,0 +1,82 @@
+//!
+//! Unlisted articles, which only their author reaches by slug.
+//!
+
+use super::*;
+
+/// The slug of a new unlisted article by `author`
+async fn create_unlisted_article(server: &TestServer, author: &TestUser) -> String {
+    let article = server
+        .ok(
+            Method::POST,
+            "/api/articles",
+            Some(author),
+            Some(json!({
+                "article": {
+                    "title": "Between us",
+                    "description": "Not for everyone",
+                    "body": "Only for those with the link",
+                    "tagList": [],
+                    "visibility": "unlisted",
+                }
+            })),
+        )
+        .await;
+    article["article"]["slug"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn routes_under_slug_of_unlisted_article_should_not_find_it_for_others() {
+    let server = TestServer::start().await;
+    let author = server.register("jake").await;
+    let other = server.register("jane").await;
+    let slug = create_unlisted_article(&server, &author).await;
+    server.add_comment(&author, &slug, "First").await;
+
+    for (method, path, body) in [
+        (Method::GET, format!("/api/articles/{slug}/comments"), None),
+        (
+            Method::GET,
+            format!("/api/articles/{slug}/comments/stream"),
+            None,
+        ),
+        (
+            Method::GET,
+            format!("/api/articles/{slug}/favoriters"),
+            None,
+        ),
+        (Method::POST, format!("/api/articles/{slug}/favorite"), None),
+        (Method::POST, format!("/api/articles/{slug}/bookmark"), None),
+        (
+            Method::POST,
+            format!("/api/articles/{slug}/comments"),
+            Some(json!({ "comment": { "body": "Found it" } })),
+        ),
+    ] {
+        for user in [None, Some(&other)] {
+            let (status, _) = server
+                .request(method.clone(), &path, user, body.clone())
+                .await;
+            let expected = if user.is_none() && method == Method::POST {
+                StatusCode::UNAUTHORIZED
+            } else {
+                StatusCode::NOT_FOUND
+            };
+            assert_eq!(expected, status, "{method} {path}");
+        }
+    }
+
+    let batch = server
+        .ok(
+            Method::POST,
+            "/api/articles/favorites/batch",
+            Some(&other),
+            Some(json!({ "slugs": [slug], "favorited": true })),
+        )
+        .await;
+    assert_eq!("notFound", batch["results"][0]["status"]);
+
+    // The author still reaches it, and nothing was added by others
+    assert_eq!(1, server.comments(Some(&author), &slug).await.len());
+    assert_eq!(1, server.favorite(&author, &slug).await["favoritesCount"]);
+}
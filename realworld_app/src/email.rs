@@ -0,0 +1,83 @@
+use crate::config::Config;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::email::EmailMessage;
+
+///
+/// Delivers outgoing emails.
+///
+/// Without the `smtp` feature (or without a configured SMTP server),
+/// emails are written to the log, which is handy during development.
+///
+#[derive(Clone)]
+pub enum Mailer {
+    Log,
+    /// Keeps the emails for tests to read
+    #[cfg(test)]
+    Outbox(std::sync::Arc<std::sync::Mutex<Vec<EmailMessage>>>),
+    #[cfg(feature = "smtp")]
+    Smtp(Box<SmtpMailer>),
+}
+
+#[cfg(feature = "smtp")]
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    from: lettre::message::Mailbox,
+}
+
+impl Mailer {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        #[cfg(feature = "smtp")]
+        if let Some(smtp_url) = &config.smtp_url {
+            use anyhow::Context;
+
+            return Ok(Self::Smtp(Box::new(SmtpMailer {
+                transport: lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::from_url(smtp_url)
+                    .context("invalid smtp_url")?
+                    .build(),
+                from: config.email_from.parse().context("invalid email_from")?,
+            })));
+        }
+
+        let _ = config;
+        Ok(Self::Log)
+    }
+
+    pub async fn send(&self, message: EmailMessage) -> RwResult<()> {
+        match self {
+            Self::Log => {
+                tracing::info!(
+                    to = message.to.as_ref(),
+                    subject = message.subject,
+                    "{}",
+                    message.body
+                );
+                Ok(())
+            }
+            #[cfg(test)]
+            Self::Outbox(outbox) => {
+                outbox.lock().unwrap().push(message);
+                Ok(())
+            }
+            #[cfg(feature = "smtp")]
+            Self::Smtp(smtp) => {
+                use anyhow::Context;
+                use lettre::AsyncTransport;
+
+                let email = lettre::Message::builder()
+                    .from(smtp.from.clone())
+                    .to(message.to.as_ref().parse().context("invalid recipient")?)
+                    .subject(message.subject)
+                    .body(message.body)
+                    .context("failed to build email")?;
+
+                smtp.transport
+                    .send(email)
+                    .await
+                    .context("failed to send email")?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//!
+//! Error responses are written by `RwError::into_response`, in the format configured by `error_format`.
+//! This middleware tells it the format, and the path of the request for the `instance` of RFC 7807.
+//!
+
+use crate::config::ErrorResponseFormat;
+
+use realworld_domain::error::{with_error_format, ErrorFormat};
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+impl From<ErrorResponseFormat> for ErrorFormat {
+    fn from(format: ErrorResponseFormat) -> Self {
+        match format {
+            ErrorResponseFormat::Realworld => ErrorFormat::RealWorld,
+            ErrorResponseFormat::Problem => ErrorFormat::Problem,
+        }
+    }
+}
+
+pub async fn format_errors(
+    State(format): State<ErrorFormat>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let instance = request.uri().path().to_string();
+    with_error_format(format, instance, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use realworld_domain::error::RwError;
+
+    use axum::http::header::CONTENT_TYPE;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn test_router(format: ErrorFormat) -> axum::Router {
+        axum::Router::new()
+            .route(
+                "/api/articles/:slug",
+                get(|| async { Err::<(), _>(RwError::ArticleNotFound) }),
+            )
+            .layer(axum::middleware::from_fn_with_state(format, format_errors))
+    }
+
+    #[tokio::test]
+    async fn problem_format_should_have_path_as_instance() {
+        let response = test_router(ErrorFormat::Problem)
+            .oneshot(axum::http::Request::get("/api/articles/missing").empty_body())
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert_eq!("application/problem+json", response.headers()[CONTENT_TYPE]);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("/api/articles/missing", body["instance"]);
+        assert_eq!("ARTICLE_NOT_FOUND", body["code"]);
+    }
+
+    #[tokio::test]
+    async fn realworld_format_should_be_plain_json() {
+        let (status, body) = request_json::<serde_json::Value>(
+            test_router(ErrorFormat::RealWorld),
+            axum::http::Request::get("/api/articles/missing").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        assert_eq!(
+            serde_json::json!({
+                "code": "ARTICLE_NOT_FOUND",
+                "message": "article not found",
+                "errors": {},
+            }),
+            body
+        );
+    }
+}
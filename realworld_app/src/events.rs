@@ -0,0 +1,167 @@
+use realworld_domain::article::feed::MaterializeFeed;
+use realworld_domain::event::Event;
+use realworld_domain::notification::NotifyOnEvent;
+
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+///
+/// Reacts to the events published through `realworld_domain::event::DomainEvents`.
+///
+/// Subscribers are called synchronously by the publishing request,
+/// so anything slow should be spawned as a separate task.
+///
+pub trait EventSubscriber: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+///
+/// In-process registry of event subscribers.
+///
+/// Every subscriber gets every event, in the order the subscribers were added.
+///
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Vec<Box<dyn EventSubscriber>>>,
+}
+
+impl EventBus {
+    pub fn new(subscribers: Vec<Box<dyn EventSubscriber>>) -> Self {
+        Self {
+            subscribers: Arc::new(subscribers),
+        }
+    }
+
+    pub fn publish(&self, event: &Event) {
+        for subscriber in self.subscribers.iter() {
+            subscriber.on_event(event);
+        }
+    }
+}
+
+/// Logs every event, as JSON
+pub struct LogEvents;
+
+impl EventSubscriber for LogEvents {
+    fn on_event(&self, event: &Event) {
+        match serde_json::to_string(event) {
+            Ok(json) => tracing::info!(event = %json, "domain event"),
+            Err(error) => tracing::warn!(?error, "unserializable domain event"),
+        }
+    }
+}
+
+/// Sends every event to a channel, to be handled by a background task
+pub struct ChannelEvents(mpsc::UnboundedSender<Event>);
+
+impl ChannelEvents {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Event>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self(sender), receiver)
+    }
+}
+
+impl EventSubscriber for ChannelEvents {
+    fn on_event(&self, event: &Event) {
+        // The receiver is only gone when the process is shutting down
+        let _ = self.0.send(event.clone());
+    }
+}
+
+///
+/// Creates notifications and writes materialized feeds from the events received from `ChannelEvents`,
+/// until the channel closes.
+///
+/// A failure is logged and otherwise ignored, since the action that caused it already happened.
+///
+pub async fn handle_events(
+    deps: impl NotifyOnEvent + MaterializeFeed,
+    mut events: mpsc::UnboundedReceiver<Event>,
+) {
+    while let Some(event) = events.recv().await {
+        if let Err(error) = deps.notify_on_event(&event).await {
+            tracing::warn!(?error, ?event, "could not create notification");
+        }
+        if let Err(error) = deps.materialize_feed(&event).await {
+            tracing::warn!(?error, ?event, "could not update feeds");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realworld_domain::article::feed::MaterializeFeedMock;
+    use realworld_domain::article::Slug;
+    use realworld_domain::comment::CommentId;
+    use realworld_domain::error::RwError;
+    use realworld_domain::notification::NotifyOnEventMock;
+
+    use std::sync::Mutex;
+    use unimock::*;
+
+    struct Recorder(Arc<Mutex<Vec<String>>>, &'static str);
+
+    impl EventSubscriber for Recorder {
+        fn on_event(&self, event: &Event) {
+            if let Event::ArticleCreated { slug, .. } = event {
+                self.0.lock().unwrap().push(format!("{}:{slug}", self.1));
+            }
+        }
+    }
+
+    #[test]
+    fn every_subscriber_should_get_every_event_in_order() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let bus = EventBus::new(vec![
+            Box::new(Recorder(received.clone(), "first")),
+            Box::new(LogEvents),
+            Box::new(Recorder(received.clone(), "second")),
+        ]);
+
+        for slug in ["a", "b"] {
+            bus.publish(&Event::ArticleCreated {
+                author_id: uuid::Uuid::new_v4(),
+                slug: Slug::from(slug),
+            });
+        }
+
+        assert_eq!(
+            ["first:a", "second:a", "first:b", "second:b"],
+            received.lock().unwrap().as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn events_should_be_handled_from_channel_until_it_closes() {
+        let (channel, receiver) = ChannelEvents::new();
+        let deps = Unimock::new((
+            NotifyOnEventMock
+                .next_call(matching!((event) if matches!(event, Event::UserFollowed { .. })))
+                .returns(Err(RwError::ProfileNotFound)),
+            MaterializeFeedMock
+                .next_call(matching!((event) if matches!(event, Event::UserFollowed { .. })))
+                .returns(Ok(())),
+            NotifyOnEventMock
+                .next_call(matching!((event) if matches!(event, Event::CommentDeleted { .. })))
+                .returns(Ok(())),
+            MaterializeFeedMock
+                .next_call(matching!((event) if matches!(event, Event::CommentDeleted { .. })))
+                .returns(Ok(())),
+        ));
+
+        channel.on_event(&Event::UserFollowed {
+            follower_id: uuid::Uuid::new_v4(),
+            username: "followed".to_string(),
+            following: true,
+        });
+        channel.on_event(&Event::CommentDeleted {
+            user_id: uuid::Uuid::new_v4(),
+            article_slug: Slug::from("slug"),
+            comment_id: CommentId(1),
+        });
+        drop(channel);
+
+        handle_events(deps.clone(), receiver).await;
+    }
+}
@@ -0,0 +1,285 @@
+use realworld_domain::error::RwResult;
+use realworld_domain::export::{ArticleExport, ExportFormat, UserExport};
+
+use axum::body::{Body, Bytes};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::{Stream, TryStreamExt};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+/// Response of `GET /api/articles/:slug/export`
+pub fn article_response(export: ArticleExport, format: ExportFormat) -> RwResult<Response> {
+    match format {
+        ExportFormat::Json => Ok(Json(export).into_response()),
+        ExportFormat::Markdown => {
+            let mut archive = MarkdownArchive::new();
+            archive.start_file(&format!("{}.md", file_name(export.article.slug.as_str())))?;
+            archive.write(&export.article.to_markdown(&export.comments))?;
+
+            Ok(zip_response(
+                export.article.slug.as_str(),
+                Body::from(archive.finish()?),
+            ))
+        }
+    }
+}
+
+///
+/// Response of `GET /api/user/export`.
+///
+/// The body is written while it's being read from the database,
+/// so a database error after the first chunk aborts the response instead of yielding an error status.
+///
+pub fn user_response(export: UserExport, format: ExportFormat) -> Response {
+    match format {
+        ExportFormat::Json => (
+            [(CONTENT_TYPE, "application/json")],
+            Body::from_stream(user_json(export)),
+        )
+            .into_response(),
+        ExportFormat::Markdown => {
+            let name = export.username.clone();
+            zip_response(&name, Body::from_stream(user_markdown(export)))
+        }
+    }
+}
+
+fn zip_response(name: &str, body: Body) -> Response {
+    (
+        [
+            (CONTENT_TYPE, "application/zip".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="{}.zip""#, file_name(name)),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// `{"username": .., "articles": [..], "comments": [..]}`, one entry at a time
+fn user_json(export: UserExport) -> impl Stream<Item = RwResult<Bytes>> {
+    let UserExport {
+        username,
+        mut articles,
+        mut comments,
+    } = export;
+
+    async_stream::try_stream! {
+        let username = to_json(&username)?;
+        yield Bytes::from(format!(r#"{{"username":{username},"articles":["#));
+
+        let mut separator = "";
+        while let Some(article) = articles.try_next().await? {
+            let article = to_json(&article)?;
+            yield Bytes::from(format!("{separator}{article}"));
+            separator = ",";
+        }
+
+        yield Bytes::from_static(br#"],"comments":["#);
+
+        let mut separator = "";
+        while let Some(comment) = comments.try_next().await? {
+            let comment = to_json(&comment)?;
+            yield Bytes::from(format!("{separator}{comment}"));
+            separator = ",";
+        }
+
+        yield Bytes::from_static(b"]}");
+    }
+}
+
+/// One Markdown file per article, and all comments in `comments.md`
+fn user_markdown(export: UserExport) -> impl Stream<Item = RwResult<Bytes>> {
+    let UserExport {
+        mut articles,
+        mut comments,
+        ..
+    } = export;
+
+    async_stream::try_stream! {
+        let mut archive = MarkdownArchive::new();
+
+        while let Some(article) = articles.try_next().await? {
+            archive.start_file(&format!("articles/{}.md", file_name(article.slug.as_str())))?;
+            archive.write(&article.to_markdown(&[]))?;
+            yield archive.take_written();
+        }
+
+        archive.start_file("comments.md")?;
+        while let Some(comment) = comments.try_next().await? {
+            archive.write(&comment.to_markdown())?;
+            yield archive.take_written();
+        }
+
+        yield archive.finish()?;
+    }
+}
+
+fn to_json(value: &impl serde::Serialize) -> RwResult<String> {
+    Ok(serde_json::to_string(value).map_err(anyhow::Error::from)?)
+}
+
+/// Slugs and usernames, restricted to characters that are safe in file names and headers
+fn file_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+///
+/// A zip archive that is written without seeking,
+/// so that its bytes can be sent as soon as each file is written.
+///
+struct MarkdownArchive {
+    zip: ZipWriter<StreamWriter<SharedBuffer>>,
+    written: SharedBuffer,
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        Bytes::from(std::mem::take(&mut *self.0.lock().unwrap()))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MarkdownArchive {
+    fn new() -> Self {
+        let written = SharedBuffer::default();
+        Self {
+            zip: ZipWriter::new_stream(written.clone()),
+            written,
+        }
+    }
+
+    fn start_file(&mut self, name: &str) -> RwResult<()> {
+        self.zip
+            .start_file(
+                name,
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+            )
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    fn write(&mut self, markdown: &str) -> RwResult<()> {
+        self.zip
+            .write_all(markdown.as_bytes())
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    /// The bytes written since last time
+    fn take_written(&mut self) -> Bytes {
+        self.written.take()
+    }
+
+    fn finish(self) -> RwResult<Bytes> {
+        self.zip.finish().map_err(anyhow::Error::from)?;
+        Ok(self.written.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realworld_domain::article::Slug;
+    use realworld_domain::export::{ExportedArticle, ExportedComment};
+    use realworld_domain::timestamp::Timestamptz;
+
+    use futures::StreamExt;
+    use std::io::Read;
+
+    fn test_article(slug: &str) -> ExportedArticle {
+        ExportedArticle {
+            slug: Slug::from(slug),
+            title: "title".to_string(),
+            description: "desc".to_string(),
+            body: format!("body of {slug}"),
+            tag_list: vec![],
+            author: "author".to_string(),
+            created_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            updated_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            favorites_count: 0,
+        }
+    }
+
+    fn test_comment(body: &str) -> ExportedComment {
+        ExportedComment {
+            article_slug: Slug::from("a"),
+            author: "author".to_string(),
+            body: body.to_string(),
+            created_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            updated_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+        }
+    }
+
+    fn test_export() -> UserExport {
+        UserExport {
+            username: "author".to_string(),
+            articles: futures::stream::iter([Ok(test_article("a")), Ok(test_article("b"))]).boxed(),
+            comments: futures::stream::iter([Ok(test_comment("1")), Ok(test_comment("2"))]).boxed(),
+        }
+    }
+
+    async fn collect(stream: impl Stream<Item = RwResult<Bytes>>) -> Vec<u8> {
+        stream
+            .try_fold(vec![], |mut bytes, chunk| async move {
+                bytes.extend_from_slice(&chunk);
+                Ok(bytes)
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn user_json_should_be_one_document() {
+        let json: serde_json::Value =
+            serde_json::from_slice(&collect(user_json(test_export())).await).unwrap();
+
+        assert_eq!("author", json["username"]);
+        assert_eq!("a", json["articles"][0]["slug"]);
+        assert_eq!("b", json["articles"][1]["slug"]);
+        assert_eq!("2", json["comments"][1]["body"]);
+    }
+
+    #[tokio::test]
+    async fn user_markdown_should_be_a_readable_zip() {
+        let bytes = collect(user_markdown(test_export())).await;
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+
+        assert_eq!(
+            ["articles/a.md", "articles/b.md", "comments.md"],
+            archive.file_names().collect::<Vec<_>>().as_slice()
+        );
+
+        let mut comments = String::new();
+        archive
+            .by_name("comments.md")
+            .unwrap()
+            .read_to_string(&mut comments)
+            .unwrap();
+        assert_eq!(
+            "## On a, 1970-01-01T00:00:00Z\n\n1\n\n## On a, 1970-01-01T00:00:00Z\n\n2\n\n",
+            comments
+        );
+    }
+}
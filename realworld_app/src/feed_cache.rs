@@ -0,0 +1,223 @@
+use crate::config::Config;
+use crate::state::SharedState;
+
+#[cfg(feature = "redis")]
+use realworld_domain::article::feed_cache::FeedCache;
+use realworld_domain::article::feed_cache::FeedPage;
+use realworld_domain::article::Article;
+use realworld_domain::user::UserId;
+
+use lru::LruCache;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+///
+/// Stores the feeds cached by `realworld_domain::article::feed_cache::FeedCache`.
+///
+/// Feeds are kept in memory, unless Redis is configured (requires the `redis` feature).
+///
+#[derive(Clone)]
+pub enum FeedCacheStore {
+    Disabled,
+    Memory(MemoryFeedCache),
+    #[cfg(feature = "redis")]
+    Redis(realworld_redis::Redis),
+}
+
+impl FeedCacheStore {
+    pub fn new(config: &Config, shared: &SharedState) -> Self {
+        let ttl = Duration::from_secs(config.feed_cache_ttl_secs);
+
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &shared.redis {
+            return Self::Redis(redis.clone().with_feed_ttl(ttl));
+        }
+
+        let _ = shared;
+        match NonZeroUsize::new(config.feed_cache_capacity) {
+            Some(capacity) => Self::Memory(MemoryFeedCache::new(capacity, ttl)),
+            None => Self::Disabled,
+        }
+    }
+
+    pub async fn get(&self, user_id: UserId, page: FeedPage) -> Option<Vec<Article>> {
+        match self {
+            Self::Disabled => None,
+            Self::Memory(memory) => memory.get(user_id, page),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.get_cached_feed(user_id, page).await,
+        }
+    }
+
+    pub async fn insert(&self, user_id: UserId, page: FeedPage, articles: Vec<Article>) {
+        match self {
+            Self::Disabled => {}
+            Self::Memory(memory) => memory.insert(user_id, page, articles),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.cache_feed(user_id, page, articles).await,
+        }
+    }
+
+    pub async fn invalidate(&self, user_id: UserId) {
+        match self {
+            Self::Disabled => {}
+            Self::Memory(memory) => memory.invalidate(user_id),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.invalidate_feed(user_id).await,
+        }
+    }
+
+    pub async fn clear(&self) {
+        match self {
+            Self::Disabled => {}
+            Self::Memory(memory) => memory.clear(),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.invalidate_all_feeds().await,
+        }
+    }
+}
+
+///
+/// Least recently used feeds, per process.
+///
+/// A feed that's being read from the database while it's invalidated
+/// may be cached in its old state, but only until it expires.
+///
+#[derive(Clone)]
+pub struct MemoryFeedCache {
+    ttl: Duration,
+    feeds: Arc<Mutex<LruCache<Uuid, HashMap<FeedPage, CachedFeed>>>>,
+}
+
+struct CachedFeed {
+    cached_at: Instant,
+    articles: Vec<Article>,
+}
+
+impl MemoryFeedCache {
+    /// Keep the feeds of at most `capacity` users
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            ttl,
+            feeds: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    fn get(&self, UserId(user_id): UserId, page: FeedPage) -> Option<Vec<Article>> {
+        let mut feeds = self.feeds.lock().unwrap();
+        let cached = feeds.get(&user_id)?.get(&page)?;
+
+        if cached.cached_at.elapsed() < self.ttl {
+            Some(cached.articles.clone())
+        } else {
+            None
+        }
+    }
+
+    fn insert(&self, UserId(user_id): UserId, page: FeedPage, articles: Vec<Article>) {
+        let mut feeds = self.feeds.lock().unwrap();
+        feeds.get_or_insert_mut(user_id, HashMap::new).insert(
+            page,
+            CachedFeed {
+                cached_at: Instant::now(),
+                articles,
+            },
+        );
+    }
+
+    fn invalidate(&self, UserId(user_id): UserId) {
+        self.feeds.lock().unwrap().pop(&user_id);
+    }
+
+    fn clear(&self) {
+        self.feeds.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_article(slug: &str) -> Article {
+        serde_json::from_value(serde_json::json!({
+            "id": "0000000000000000000001",
+            "slug": slug,
+            "title": "title",
+            "description": "desc",
+            "body": "body",
+            "tag_list": [],
+            "created_at": "2019-10-12T07:20:50.52Z",
+            "updated_at": "2019-10-12T07:20:50.52Z",
+            "favorited": false,
+            "favorites_count": 0,
+            "views_count": 0,
+            "word_count": 1,
+            "reading_time_minutes": 1,
+            "author": {
+                "username": "author",
+                "bio": "bio",
+                "image": null,
+                "following": true
+            }
+        }))
+        .unwrap()
+    }
+
+    fn cached_len(cache: &MemoryFeedCache, user_id: UserId, page: FeedPage) -> Option<usize> {
+        cache.get(user_id, page).map(|articles| articles.len())
+    }
+
+    #[test]
+    fn memory_cache_should_keep_pages_until_invalidated() {
+        let cache = MemoryFeedCache::new(NonZeroUsize::new(10).unwrap(), Duration::from_secs(60));
+        let user = UserId(Uuid::new_v4());
+        let other_user = UserId(Uuid::new_v4());
+        let second_page = FeedPage {
+            limit: Some(1),
+            offset: Some(1),
+        };
+
+        cache.insert(user, FeedPage::default(), vec![test_article("a")]);
+        cache.insert(user, second_page, vec![]);
+        cache.insert(other_user, FeedPage::default(), vec![test_article("b")]);
+
+        assert_eq!(Some(1), cached_len(&cache, user, FeedPage::default()));
+        assert_eq!(Some(0), cached_len(&cache, user, second_page));
+        assert_eq!(None, cached_len(&cache, other_user, second_page));
+
+        cache.invalidate(user);
+        assert_eq!(None, cached_len(&cache, user, FeedPage::default()));
+        assert_eq!(None, cached_len(&cache, user, second_page));
+        assert_eq!(Some(1), cached_len(&cache, other_user, FeedPage::default()));
+
+        cache.clear();
+        assert_eq!(None, cached_len(&cache, other_user, FeedPage::default()));
+    }
+
+    #[test]
+    fn memory_cache_should_evict_least_recently_used_feed() {
+        let cache = MemoryFeedCache::new(NonZeroUsize::new(2).unwrap(), Duration::from_secs(60));
+        let users: Vec<_> = (0..3).map(|_| UserId(Uuid::new_v4())).collect();
+
+        cache.insert(users[0], FeedPage::default(), vec![]);
+        cache.insert(users[1], FeedPage::default(), vec![]);
+        cache.get(users[0], FeedPage::default());
+        cache.insert(users[2], FeedPage::default(), vec![]);
+
+        assert_eq!(Some(0), cached_len(&cache, users[0], FeedPage::default()));
+        assert_eq!(None, cached_len(&cache, users[1], FeedPage::default()));
+        assert_eq!(Some(0), cached_len(&cache, users[2], FeedPage::default()));
+    }
+
+    #[test]
+    fn memory_cache_should_expire_feeds() {
+        let cache = MemoryFeedCache::new(NonZeroUsize::new(10).unwrap(), Duration::ZERO);
+        let user = UserId(Uuid::new_v4());
+
+        cache.insert(user, FeedPage::default(), vec![test_article("a")]);
+        assert_eq!(None, cached_len(&cache, user, FeedPage::default()));
+    }
+}
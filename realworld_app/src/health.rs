@@ -0,0 +1,80 @@
+//!
+//! `GET /ready` for load balancers and orchestrators, which should only route requests
+//! to an instance that can reach its database.
+//!
+
+use crate::app::backend;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Json;
+
+pub fn router(db: backend::Db) -> axum::Router {
+    axum::Router::new()
+        .route(
+            "/ready",
+            get(|State(db): State<backend::Db>| async move { readiness(db.pool_status().await) }),
+        )
+        .with_state(db)
+}
+
+/// Unavailable when no database connection could be used
+fn readiness(pool: backend::PoolStatus) -> (StatusCode, Json<serde_json::Value>) {
+    let status = if pool.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(serde_json::json!({
+            "database": {
+                "healthy": pool.healthy,
+                "connections": pool.connections,
+                "idleConnections": pool.idle_connections,
+                "maxConnections": pool.max_connections,
+            }
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_status(healthy: bool) -> backend::PoolStatus {
+        backend::PoolStatus {
+            healthy,
+            connections: 3,
+            idle_connections: 1,
+            max_connections: 50,
+        }
+    }
+
+    #[test]
+    fn healthy_pool_should_be_ready() {
+        let (status, Json(body)) = readiness(pool_status(true));
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            serde_json::json!({
+                "database": {
+                    "healthy": true,
+                    "connections": 3,
+                    "idleConnections": 1,
+                    "maxConnections": 50,
+                }
+            }),
+            body
+        );
+    }
+
+    #[test]
+    fn unhealthy_pool_should_not_be_ready() {
+        let (status, _) = readiness(pool_status(false));
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+    }
+}
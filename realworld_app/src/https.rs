@@ -0,0 +1,55 @@
+//!
+//! A minimal HTTPS client, for talking to OAuth providers and S3.
+//!
+
+use anyhow::Context;
+use axum::body::Bytes;
+
+///
+/// Send one request over a new HTTPS connection.
+///
+/// Sign-ins and uploads are rare enough that connections aren't worth keeping around.
+///
+pub async fn send<B: Into<Bytes>>(
+    request: hyper::Request<B>,
+) -> anyhow::Result<(hyper::StatusCode, Bytes)> {
+    use http_body_util::{BodyExt, Full};
+    use hyper::header::{HOST, USER_AGENT};
+
+    let (mut parts, body) = request.into_parts();
+    let authority = parts.uri.authority().context("url has no host")?.clone();
+    let host = authority.host().to_string();
+    let port = authority.port_u16().unwrap_or(443);
+
+    // The request line only has the path, the host goes in its own header
+    parts.uri = parts
+        .uri
+        .path_and_query()
+        .map_or("/", |path| path.as_str())
+        .parse()?;
+    parts.headers.insert(HOST, authority.as_str().parse()?);
+    // Required by GitHub
+    parts.headers.insert(USER_AGENT, "realworld-app".parse()?);
+
+    let tcp = tokio::net::TcpStream::connect((host.as_str(), port)).await?;
+    let tls =
+        tokio_native_tls::TlsConnector::from(tokio_native_tls::native_tls::TlsConnector::new()?)
+            .connect(&host, tcp)
+            .await?;
+
+    let (mut sender, connection) =
+        hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(tls)).await?;
+    tokio::spawn(async move {
+        if let Err(error) = connection.await {
+            tracing::warn!(%error, %host, "HTTPS connection failed");
+        }
+    });
+
+    let response = sender
+        .send_request(hyper::Request::from_parts(parts, Full::new(body.into())))
+        .await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+
+    Ok((status, body))
+}
@@ -0,0 +1,47 @@
+use realworld_domain::schedule::{Job, RunDueJobs, Schedule, Scheduler};
+use realworld_domain::System;
+
+///
+/// Runs the scheduled maintenance jobs of one app until none of them will ever be due.
+///
+/// Every instance of the app runs them; the jobs only delete what's no longer needed,
+/// so it doesn't matter that they run more than once.
+///
+pub async fn run_scheduled_jobs(deps: impl RunDueJobs + System, jobs: Vec<(Job, Schedule)>) {
+    let mut scheduler = Scheduler::new(jobs, deps.get_current_time());
+
+    while let Some(next) = deps.run_due_jobs(&mut scheduler).await {
+        let wait = next - deps.get_current_time();
+        // A negative wait, when running late, fails to convert
+        tokio::time::sleep(wait.try_into().unwrap_or_default()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realworld_domain::schedule::RunDueJobsMock;
+    use realworld_domain::SystemMock;
+
+    use time::OffsetDateTime;
+    use unimock::*;
+
+    #[tokio::test]
+    async fn jobs_should_run_until_never_due() {
+        let deps = Unimock::new((
+            SystemMock::get_current_time
+                .each_call(matching!())
+                .returns(OffsetDateTime::now_utc()),
+            RunDueJobsMock
+                .next_call(matching!(_))
+                .returns(Some(OffsetDateTime::UNIX_EPOCH)),
+            RunDueJobsMock.next_call(matching!(_)).returns(None),
+        ));
+
+        run_scheduled_jobs(
+            deps,
+            vec![(Job::PurgeRefreshTokens, "@daily".parse().unwrap())],
+        )
+        .await;
+    }
+}
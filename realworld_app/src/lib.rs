@@ -0,0 +1,267 @@
+#![cfg_attr(feature = "use-associated-future", feature(type_alias_impl_trait))]
+//!
+//! The RealWorld app, as served by the `realworld-app` binary.
+//!
+//! To embed the API in another server, build a router with [AppBuilder].
+//!
+
+pub mod app;
+mod blob_storage;
+mod body_limit;
+mod builder;
+mod cache_control;
+mod circuit_breaker;
+pub mod cli;
+mod comment_events;
+pub mod config;
+pub mod config_file;
+mod cors;
+mod doctor;
+mod email;
+mod error_format;
+mod events;
+mod export;
+mod feed_cache;
+mod health;
+#[cfg(any(feature = "oauth", feature = "s3"))]
+mod https;
+mod jobs;
+mod live_config;
+pub mod logging;
+mod markdown;
+mod metrics;
+mod oauth;
+#[cfg(feature = "otel")]
+pub mod otel;
+mod rate_limit;
+mod request_log;
+mod retry;
+mod revocation;
+mod routes;
+mod secrets;
+mod state;
+mod tenant;
+mod timeout;
+#[cfg(feature = "tls")]
+mod tls;
+mod views;
+
+pub use builder::AppBuilder;
+
+use anyhow::Context;
+use entrait::Impl;
+use live_config::{LiveConfig, LiveSettings};
+use realworld_domain::event::Event;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tower::ServiceBuilder;
+
+/// Where the API is served over HTTP
+const HTTP_LISTEN_ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 8080));
+
+#[cfg(test)]
+mod e2e;
+#[cfg(test)]
+mod test_util;
+
+/// The app of a command other than serving, which only uses its traits
+pub async fn init_command_app(
+    config: config::Config,
+    tenant: Option<&str>,
+) -> anyhow::Result<Impl<app::App>> {
+    let shared = state::SharedState::connect(&config).await?;
+    let url = cli::database_url(&config, tenant)?;
+    let db =
+        app::backend::Db::init(&url, &config.db_pool_config(), config.migrate_on_start).await?;
+    let live_config = LiveConfig::new(LiveSettings::from_config(&config));
+    let (app, _) = link_app(
+        Arc::new(config),
+        live_config,
+        db,
+        &shared,
+        metrics::Metrics::new(),
+    )?;
+    Ok(app)
+}
+
+/// The app, and the receiving end of the events that notifications are created from
+async fn init_app(
+    config: config::Config,
+    live_config: LiveConfig,
+    shared: &state::SharedState,
+    metrics: metrics::Metrics,
+) -> anyhow::Result<(Impl<app::App>, UnboundedReceiver<Event>)> {
+    let db = app::backend::Db::init(
+        &config.database_url,
+        &config.db_pool_config(),
+        config.migrate_on_start,
+    )
+    .await?;
+    link_app(Arc::new(config), live_config, db, shared, metrics)
+}
+
+fn link_app(
+    config: Arc<config::Config>,
+    live_config: LiveConfig,
+    db: app::backend::Db,
+    shared: &state::SharedState,
+    metrics: metrics::Metrics,
+) -> anyhow::Result<(Impl<app::App>, UnboundedReceiver<Event>)> {
+    let mailer = email::Mailer::from_config(&config)?;
+    let feed_cache = feed_cache::FeedCacheStore::new(&config, shared);
+    let revoked_tokens = revocation::TokenRevocationStore::new(shared);
+    let (notification_events, notification_receiver) = events::ChannelEvents::new();
+
+    // "link" the application by using the Impl type.
+    // All trait implementations are for that type.
+    let app = Impl::new(app::App {
+        jwt_keys: config.jwt_keys(),
+        domain_config: Arc::new(app::ConfigSettings::new(config.clone())),
+        oauth: oauth::OAuthProviders::from_config(&config),
+        blob_storage: blob_storage::BlobStorage::from_config(&config),
+        circuit_breaker: Arc::new(circuit_breaker::CircuitBreaker::from_config(&config)),
+        retry_policy: retry::RetryPolicy::from_config(&config),
+        config,
+        live_config,
+        db,
+        mailer,
+        feed_cache,
+        revoked_tokens,
+        comment_events: comment_events::CommentBroadcaster::default(),
+        events: events::EventBus::new(vec![
+            Box::new(events::LogEvents),
+            Box::new(notification_events),
+        ]),
+        metrics,
+        views: views::ViewBuffer::default(),
+        tenants: tenant::Tenants::default(),
+        #[cfg(feature = "dyn-repos")]
+        custom_repos: Default::default(),
+    });
+
+    Ok((app, notification_receiver))
+}
+
+/// Links an app for each tenant, and lets `app` hand requests over to them
+fn link_tenant_apps(
+    app: &mut Impl<app::App>,
+    tenant_dbs: Vec<(String, app::backend::Db)>,
+    shared: &state::SharedState,
+) -> anyhow::Result<Vec<(Impl<app::App>, UnboundedReceiver<Event>)>> {
+    let mut apps = HashMap::new();
+    let mut notification_receivers = vec![];
+
+    for (tenant, db) in tenant_dbs {
+        let (mut tenant_app, receiver) = link_app(
+            app.config.clone(),
+            app.live_config.clone(),
+            db,
+            shared,
+            app.metrics.clone(),
+        )?;
+        tenant_app.mailer = app.mailer.clone();
+        notification_receivers.push((tenant_app.clone(), receiver));
+        apps.insert(tenant, tenant_app);
+    }
+
+    app.tenants = tenant::Tenants::from_config(&app.config, apps);
+    Ok(notification_receivers)
+}
+
+pub async fn serve(config: config::Config) -> anyhow::Result<()> {
+    let shared = state::SharedState::connect(&config).await?;
+    let metrics = metrics::Metrics::new();
+    let live_config = LiveConfig::new(LiveSettings::from_config(&config))
+        .reloaded_by(|| Ok(config_file::parse::<cli::Cli>()?.config));
+    tokio::spawn(live_config::reload_on_sighup(live_config.clone()));
+    let (mut app, notification_receiver) =
+        init_app(config, live_config, &shared, metrics.clone()).await?;
+    let tenant_dbs = tenant::init_tenant_dbs(&app.config).await?;
+    let tenant_notification_receivers = link_tenant_apps(&mut app, tenant_dbs, &shared)?;
+
+    for (app, notification_receiver) in
+        std::iter::once((app.clone(), notification_receiver)).chain(tenant_notification_receivers)
+    {
+        spawn_background_tasks(app, notification_receiver);
+    }
+
+    // Tenants aren't selected over gRPC, it serves the default app
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = app.config.grpc_listen_addr {
+        let app = app.clone();
+        tokio::spawn(async move {
+            if let Err(error) = realworld_grpc::serve(app, addr).await {
+                tracing::error!("error running gRPC server: {error}");
+            }
+        });
+    }
+
+    #[cfg(feature = "tls")]
+    let tls_files = tls::TlsFiles::from_config(&app.config);
+    let router = router(app, &shared, metrics, std::convert::identity)?;
+
+    #[cfg(feature = "tls")]
+    if let Some(tls_files) = tls_files {
+        return tls::serve(router, HTTP_LISTEN_ADDR, tls_files).await;
+    }
+
+    let listener = tokio::net::TcpListener::bind(HTTP_LISTEN_ADDR)
+        .await
+        .unwrap();
+
+    // The peer address is used for rate limiting
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .context("error running HTTP server")?;
+
+    Ok(())
+}
+
+///
+/// Notifications are created and materialized feeds written in the background,
+/// so that the requests causing them don't wait.
+/// Maintenance jobs and counting views of articles happen in the background too.
+///
+fn spawn_background_tasks(app: Impl<app::App>, notification_receiver: UnboundedReceiver<Event>) {
+    tokio::spawn(jobs::run_scheduled_jobs(
+        app.clone(),
+        app.config.scheduled_jobs(),
+    ));
+    tokio::spawn(views::count_views(
+        app.clone(),
+        app.views.clone(),
+        Duration::from_secs(app.config.view_flush_interval_secs),
+    ));
+    tokio::spawn(events::handle_events(app, notification_receiver));
+}
+
+/// Every route, with the app injected
+fn router(
+    app: Impl<app::App>,
+    shared: &state::SharedState,
+    metrics: metrics::Metrics,
+    extend_api: impl FnOnce(axum::Router) -> axum::Router,
+) -> anyhow::Result<axum::Router> {
+    let router = routes::api_router(&app.config, &app.live_config, shared, extend_api)?
+        .merge(metrics::router(metrics.clone()))
+        .merge(health::router(app.db.clone()))
+        .layer(
+            ServiceBuilder::new()
+                // Inject the app into the axum context
+                .layer(axum::extract::Extension(app))
+                // Request counts and latencies, see `GET /metrics`
+                .layer(axum::middleware::from_fn_with_state(
+                    metrics,
+                    metrics::track_requests,
+                )),
+        );
+
+    // Request ids and logging. Use `RUST_LOG=tower_http=debug` for more detail
+    Ok(logging::with_request_tracing(router))
+}
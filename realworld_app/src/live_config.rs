@@ -0,0 +1,249 @@
+//!
+//! Settings that can be changed while serving, without a restart:
+//! `log_filter`, the rate limits, `feature_flags` and `maintenance_mode`.
+//!
+//! They're read again from the command line, the environment and the config file on `SIGHUP`,
+//! or when an admin calls `POST /api/admin/config/reload`. Since the environment and arguments
+//! of a running process don't change, it's the config file that changes them in practice.
+//! Other settings keep the values they were started with.
+//!
+//! If the reloaded configuration is invalid, the current settings stay in use.
+//!
+
+use crate::config::Config;
+use crate::logging;
+use crate::rate_limit::Quotas;
+
+use realworld_domain::error::RwError;
+
+use arc_swap::{ArcSwap, Guard};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// The current values of the reloadable settings
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LiveSettings {
+    /// `None` leaves the log levels to `RUST_LOG`
+    pub log_filter: Option<String>,
+    pub rate_limits: Quotas,
+    pub feature_flags: BTreeSet<String>,
+    pub maintenance_mode: bool,
+}
+
+impl LiveSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            log_filter: config.log_filter.clone(),
+            rate_limits: Quotas::from_config(config),
+            feature_flags: config.feature_flags.iter().cloned().collect(),
+            maintenance_mode: config.maintenance_mode,
+        }
+    }
+}
+
+type LoadConfig = dyn Fn() -> anyhow::Result<Config> + Send + Sync;
+
+///
+/// Shared handle to the [LiveSettings], which requests read without locking.
+///
+#[derive(Clone)]
+pub struct LiveConfig {
+    settings: Arc<ArcSwap<LiveSettings>>,
+    /// Where reloaded configuration comes from. Without it, the settings stay as they are.
+    load_config: Option<Arc<LoadConfig>>,
+}
+
+impl LiveConfig {
+    pub fn new(settings: LiveSettings) -> Self {
+        Self {
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            load_config: None,
+        }
+    }
+
+    /// Reload the configuration with `load_config`
+    pub fn reloaded_by(
+        self,
+        load_config: impl Fn() -> anyhow::Result<Config> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            load_config: Some(Arc::new(load_config)),
+            ..self
+        }
+    }
+
+    pub fn load(&self) -> Guard<Arc<LiveSettings>> {
+        self.settings.load()
+    }
+
+    /// Load the configuration again, and take its settings into use if it's valid
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let Some(load_config) = &self.load_config else {
+            anyhow::bail!("the settings of this app can't be reloaded");
+        };
+        let config = load_config()?;
+        config.validate()?;
+
+        self.apply(LiveSettings::from_config(&config))
+    }
+
+    fn apply(&self, settings: LiveSettings) -> anyhow::Result<()> {
+        // Logged before the log filter changes, which might leave it out
+        tracing::info!(
+            maintenance_mode = settings.maintenance_mode,
+            feature_flags = ?settings.feature_flags,
+            "reloading settings"
+        );
+        if settings.log_filter != self.load().log_filter {
+            logging::set_filter(settings.log_filter.as_deref())?;
+        }
+
+        self.settings.store(Arc::new(settings));
+        Ok(())
+    }
+}
+
+/// Reload the settings on every `SIGHUP`
+pub async fn reload_on_sighup(live_config: LiveConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            tracing::error!("settings won't be reloaded, can't listen for SIGHUP: {error}");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        if let Err(error) = live_config.reload() {
+            tracing::error!("{error:#}, keeping the current settings");
+        }
+    }
+}
+
+///
+/// In maintenance mode, reject API requests that make changes.
+///
+/// Admin requests are let through, so that maintenance mode can be left
+/// with `POST /api/admin/config/reload`.
+///
+pub async fn reject_in_maintenance(
+    State(live_config): State<LiveConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let changes = !(request.method().is_safe() || request.uri().path().starts_with("/admin/"));
+    if changes && live_config.load().maintenance_mode {
+        return RwError::ServiceUnavailable { retry_after: None }.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use clap::Parser;
+    use std::sync::Mutex;
+
+    /// Valid for the database of the build, since reloaded configuration is validated
+    #[cfg(not(feature = "sqlite"))]
+    const DATABASE_URL: &str = "--database-url=postgres://localhost/realworld";
+    #[cfg(feature = "sqlite")]
+    const DATABASE_URL: &str = "--database-url=sqlite://realworld.db";
+
+    fn test_config(args: &[&str]) -> Config {
+        let config_args = ["realworld", DATABASE_URL, "--jwt-signing-key=secret"];
+        Config::try_parse_from(config_args.iter().chain(args)).unwrap()
+    }
+
+    /// Reloaded from the arguments in `args`, which the test can change
+    fn reloaded_from(args: &Arc<Mutex<Vec<&'static str>>>) -> LiveConfig {
+        let args = args.clone();
+        LiveConfig::new(LiveSettings::from_config(&test_config(&[])))
+            .reloaded_by(move || Ok(test_config(&args.lock().unwrap())))
+    }
+
+    #[test]
+    fn reload_should_take_new_settings_into_use() {
+        let args = Arc::new(Mutex::new(vec![]));
+        let live_config = reloaded_from(&args);
+        assert!(!live_config.load().maintenance_mode);
+
+        *args.lock().unwrap() = vec![
+            "--maintenance-mode",
+            "--feature-flags=comments,bookmarks",
+            "--rate-limit-ip-per-minute=0",
+        ];
+        live_config.reload().unwrap();
+
+        let after = live_config.load();
+        assert!(after.maintenance_mode);
+        assert_eq!(
+            BTreeSet::from(["bookmarks".to_string(), "comments".to_string()]),
+            after.feature_flags
+        );
+        assert!(after.rate_limits.ip.is_none());
+        assert!(after.rate_limits.user.is_some());
+    }
+
+    #[test]
+    fn invalid_config_should_keep_current_settings() {
+        let args = Arc::new(Mutex::new(vec![]));
+        let live_config = reloaded_from(&args);
+
+        *args.lock().unwrap() = vec!["--maintenance-mode", "--rate-limit-ip-burst=0"];
+        let error = live_config.reload().unwrap_err();
+
+        assert!(error.to_string().contains("rate_limit_ip_burst"));
+        assert!(!live_config.load().maintenance_mode);
+    }
+
+    #[test]
+    fn fixed_settings_should_not_be_reloaded() {
+        let live_config = LiveConfig::new(LiveSettings::default());
+
+        assert!(live_config.reload().is_err());
+    }
+
+    #[tokio::test]
+    async fn maintenance_mode_should_reject_changes() {
+        let live_config = LiveConfig::new(LiveSettings {
+            maintenance_mode: true,
+            ..Default::default()
+        });
+        let router = axum::Router::new()
+            .route("/articles", get(|| async {}).post(|| async {}))
+            .route("/admin/config/reload", post(|| async {}))
+            .layer(axum::middleware::from_fn_with_state(
+                live_config.clone(),
+                reject_in_maintenance,
+            ));
+
+        for (sent, expected) in [
+            (
+                axum::http::Request::get("/articles").empty_body(),
+                StatusCode::OK,
+            ),
+            (
+                axum::http::Request::post("/articles").empty_body(),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                axum::http::Request::post("/admin/config/reload").empty_body(),
+                StatusCode::OK,
+            ),
+        ] {
+            let (status, _) = request(router.clone(), sent).await;
+            assert_eq!(expected, status);
+        }
+    }
+}
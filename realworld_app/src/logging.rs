@@ -0,0 +1,153 @@
+use crate::config::{Config, LogFormat};
+
+use anyhow::Context;
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::OnceLock;
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Instrument, Level};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+/// Replaces the filter of the subscriber set up by [init]
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+pub fn init(config: &Config) -> anyhow::Result<()> {
+    let format = match config.log_format {
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().pretty().boxed(),
+    };
+    let (filter, handle) = reload::Layer::new(env_filter(config.log_filter.as_deref())?);
+    let _ = FILTER.set(handle);
+    let registry = tracing_subscriber::registry().with(filter).with(format);
+
+    #[cfg(feature = "otel")]
+    let registry = registry.with(crate::otel::layer(config)?);
+
+    registry.init();
+    Ok(())
+}
+
+/// Log levels by `filter` if given, or else by `RUST_LOG`
+fn env_filter(filter: Option<&str>) -> anyhow::Result<EnvFilter> {
+    Ok(match filter {
+        Some(filter) => EnvFilter::try_new(filter).context("invalid log_filter")?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    })
+}
+
+/// Change the log levels, like `log_filter` does on startup. Does nothing before [init].
+pub fn set_filter(filter: Option<&str>) -> anyhow::Result<()> {
+    let filter = env_filter(filter)?;
+    if let Some(handle) = FILTER.get() {
+        handle.reload(filter)?;
+    }
+    Ok(())
+}
+
+///
+/// Give every request an `x-request-id`, unless the client already sent one,
+/// and run it inside a span carrying that id. The id is echoed in the response.
+///
+/// The span has an empty `user_id` field, filled in when the request is authenticated.
+///
+pub fn with_request_tracing(router: axum::Router) -> axum::Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(make_request_span)
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::x_request_id()),
+    )
+}
+
+fn make_request_span(request: &Request<Body>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+        user_id = tracing::field::Empty,
+    );
+
+    #[cfg(feature = "otel")]
+    crate::otel::set_parent(&span, request.headers());
+
+    span
+}
+
+///
+/// Run the handler of the request inside a span named by its route, like `GET /api/articles/:slug`.
+/// Routes are only known after routing, so this is a route layer.
+///
+pub async fn handler_span(request: axum::extract::Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "handler",
+        otel.name = format!("{} {route}", request.method()),
+        route,
+    );
+
+    next.run(request).instrument(span).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn test_router() -> axum::Router {
+        with_request_tracing(axum::Router::new().route("/", get(|| async {})))
+    }
+
+    #[tokio::test]
+    async fn should_generate_request_id() {
+        let response = test_router()
+            .oneshot(Request::get("/").empty_body())
+            .await
+            .unwrap();
+
+        let request_id = response.headers().get("x-request-id").unwrap();
+        assert!(uuid::Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_propagate_client_request_id() {
+        let response = test_router()
+            .oneshot(
+                Request::get("/")
+                    .header("x-request-id", "client-id")
+                    .empty_body(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!("client-id", response.headers()["x-request-id"]);
+    }
+}
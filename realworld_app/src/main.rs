@@ -1,46 +1,48 @@
 #![cfg_attr(feature = "use-associated-future", feature(type_alias_impl_trait))]
 
-mod app;
-mod config;
-mod routes;
-
-use anyhow::Context;
-use clap::Parser;
-use entrait::Impl;
-use std::sync::Arc;
-use tower::ServiceBuilder;
-
-#[cfg(test)]
-mod test_util;
+use realworld_app::{cli, config_file, init_command_app, logging, serve};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
-    env_logger::init();
-
-    let config = config::Config::parse();
-    let db = realworld_db::Db::init(&config.database_url).await?;
-
-    // "link" the application by using the Impl type.
-    // All trait implementations are for that type.
-    let app = Impl::new(app::App {
-        config: Arc::new(config),
-        db,
-    });
-
-    let router = routes::api_router().layer(
-        ServiceBuilder::new()
-            // Inject the app into the axum context
-            .layer(axum::extract::Extension(app))
-            // Enables logging. Use `RUST_LOG=tower_http=debug`
-            .layer(tower_http::trace::TraceLayer::new_for_http()),
-    );
-
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await.unwrap();
-
-    axum::serve(listener, router)
-        .await
-        .context("error running HTTP server")?;
 
-    Ok(())
+    let cli: cli::Cli = config_file::parse()?;
+    logging::init(&cli.config)?;
+    let command = cli.command.unwrap_or_default();
+    // The doctor reports invalid configuration along with its other checks
+    if !matches!(command, cli::Command::Doctor) {
+        cli.config.validate()?;
+    }
+
+    let result = match command {
+        cli::Command::Doctor => cli::doctor(&cli.config).await,
+        cli::Command::Serve => serve(cli.config).await,
+        cli::Command::Migrate(command) => {
+            cli::migrate(&cli.config, cli.tenant.as_deref(), command).await
+        }
+        cli::Command::CreateAdmin(args) => {
+            let app = init_command_app(cli.config, cli.tenant.as_deref()).await?;
+            cli::create_admin(&app, args).await
+        }
+        cli::Command::GenJwt { user_id } => {
+            let app = init_command_app(cli.config, cli.tenant.as_deref()).await?;
+            cli::gen_jwt(&app, user_id).await
+        }
+        cli::Command::Seed(args) => {
+            let app = init_command_app(cli.config, cli.tenant.as_deref()).await?;
+            cli::seed(&app, args).await
+        }
+        cli::Command::ImportUsers(args) => {
+            let app = init_command_app(cli.config, cli.tenant.as_deref()).await?;
+            cli::import_users(&app, args).await
+        }
+        cli::Command::BackfillFeeds => {
+            let app = init_command_app(cli.config, cli.tenant.as_deref()).await?;
+            cli::backfill_feeds(&app).await
+        }
+    };
+
+    #[cfg(feature = "otel")]
+    realworld_app::otel::shutdown();
+    result
 }
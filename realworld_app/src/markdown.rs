@@ -0,0 +1,354 @@
+//!
+//! Renders the commonly used subset of Markdown to HTML:
+//! headings, paragraphs, emphasis, code, links, images, lists, blockquotes and rules.
+//!
+//! All text is escaped, so raw HTML in the Markdown ends up as text,
+//! and links only get through with a safe scheme. The output can be embedded without further sanitizing.
+//!
+
+use std::fmt::Write;
+
+/// Blockquotes nest at most this deep, further `>` are kept as text.
+/// Each level is a recursion, so a body of `>` mustn't overflow the stack.
+const MAX_QUOTE_DEPTH: usize = 16;
+
+pub fn render(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut html = String::new();
+    render_blocks(&lines, 0, &mut html);
+    html
+}
+
+fn render_blocks(lines: &[&str], depth: usize, html: &mut String) {
+    let quotes = depth < MAX_QUOTE_DEPTH;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            i += 1;
+        } else if let Some(info) = trimmed.strip_prefix("```") {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with("```"))
+                .map_or(lines.len(), |end| i + 1 + end);
+            let language: String = info
+                .trim()
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                .collect();
+
+            if language.is_empty() {
+                html.push_str("<pre><code>");
+            } else {
+                let _ = write!(html, "<pre><code class=\"language-{language}\">");
+            }
+            for line in &lines[i + 1..end] {
+                html.push_str(&escape(line));
+                html.push('\n');
+            }
+            html.push_str("</code></pre>\n");
+            i = end + 1;
+        } else if let Some((level, text)) = heading(trimmed) {
+            let _ = writeln!(html, "<h{level}>{}</h{level}>", inline(text));
+            i += 1;
+        } else if is_rule(trimmed) {
+            html.push_str("<hr>\n");
+            i += 1;
+        } else if quotes && trimmed.starts_with('>') {
+            let end = block_end(lines, i, |line| line.trim_start().starts_with('>'));
+            let quoted: Vec<&str> = lines[i..end]
+                .iter()
+                .map(|line| {
+                    let line = &line.trim_start()[1..];
+                    line.strip_prefix(' ').unwrap_or(line)
+                })
+                .collect();
+
+            html.push_str("<blockquote>\n");
+            render_blocks(&quoted, depth + 1, html);
+            html.push_str("</blockquote>\n");
+            i = end;
+        } else if let Some(ordered) = list_item(trimmed).map(|(ordered, _)| ordered) {
+            let tag = if ordered { "ol" } else { "ul" };
+            let _ = writeln!(html, "<{tag}>");
+
+            while i < lines.len() {
+                let Some((true, text)) = list_item(lines[i].trim_start())
+                    .map(|(item_ordered, text)| (item_ordered == ordered, text))
+                else {
+                    break;
+                };
+
+                // Indented lines continue the item
+                let end = block_end(lines, i + 1, |line| {
+                    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+                });
+                let item: Vec<&str> = std::iter::once(text)
+                    .chain(lines[i + 1..end].iter().map(|line| line.trim()))
+                    .collect();
+
+                let _ = writeln!(html, "<li>{}</li>", inline(&item.join("\n")));
+                i = end;
+            }
+
+            let _ = writeln!(html, "</{tag}>");
+        } else {
+            let end = block_end(lines, i, |line| {
+                let trimmed = line.trim_start();
+                !trimmed.is_empty()
+                    && !trimmed.starts_with("```")
+                    && (!quotes || !trimmed.starts_with('>'))
+                    && heading(trimmed).is_none()
+                    && list_item(trimmed).is_none()
+                    && !is_rule(trimmed)
+            })
+            // The first line is never part of another block
+            .max(i + 1);
+            let paragraph: Vec<&str> = lines[i..end].iter().map(|line| line.trim()).collect();
+
+            let _ = writeln!(html, "<p>{}</p>", inline(&paragraph.join("\n")));
+            i = end;
+        }
+    }
+}
+
+/// The index of the first line from `start` that doesn't belong to the block
+fn block_end(lines: &[&str], start: usize, belongs: impl Fn(&str) -> bool) -> usize {
+    lines[start..]
+        .iter()
+        .position(|line| !belongs(line))
+        .map_or(lines.len(), |end| start + end)
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let text = &line[level..];
+    if text.is_empty() {
+        Some((level, text))
+    } else if text.starts_with(' ') {
+        Some((level, text.trim().trim_end_matches('#').trim_end()))
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    line.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|c| line.replace(c, "").is_empty())
+}
+
+/// Whether the line is an item of an ordered list, and its text
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some((false, text.trim()));
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits) {
+        if let Some(text) = line[digits..].strip_prefix(". ") {
+            return Some((true, text.trim()));
+        }
+    }
+    None
+}
+
+fn inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        let mut consumed = c.len_utf8();
+
+        match c {
+            '\\' => match rest[1..].chars().next() {
+                Some(escaped) if escaped.is_ascii_punctuation() => {
+                    html.push_str(&escape(&rest[1..2]));
+                    consumed = 2;
+                }
+                _ => html.push('\\'),
+            },
+            '`' => match rest[1..].find('`') {
+                Some(end) => {
+                    let _ = write!(html, "<code>{}</code>", escape(&rest[1..end + 1]));
+                    consumed = end + 2;
+                }
+                None => html.push('`'),
+            },
+            '*' | '_' if !(c == '_' && previous.is_some_and(char::is_alphanumeric)) => {
+                let strong = rest[1..].starts_with(c);
+                let delimiter = if strong { &rest[..2] } else { &rest[..1] };
+
+                match emphasized(&rest[delimiter.len()..], delimiter) {
+                    Some(inner) => {
+                        let tag = if strong { "strong" } else { "em" };
+                        let _ = write!(html, "<{tag}>{}</{tag}>", inline(inner));
+                        consumed = inner.len() + 2 * delimiter.len();
+                    }
+                    None => {
+                        html.push_str(delimiter);
+                        consumed = delimiter.len();
+                    }
+                }
+            }
+            '!' if rest.starts_with("![") => match link(&rest[1..]) {
+                Some((alt, url, len)) if is_safe_url(url) => {
+                    let _ = write!(
+                        html,
+                        "<img src=\"{}\" alt=\"{}\">",
+                        escape(url),
+                        escape(alt)
+                    );
+                    consumed = len + 1;
+                }
+                _ => html.push('!'),
+            },
+            '[' => match link(rest) {
+                Some((text, url, len)) if is_safe_url(url) => {
+                    let _ = write!(
+                        html,
+                        "<a href=\"{}\" rel=\"nofollow\">{}</a>",
+                        escape(url),
+                        inline(text)
+                    );
+                    consumed = len;
+                }
+                _ => html.push('['),
+            },
+            _ => html.push_str(&escape(&rest[..consumed])),
+        }
+
+        previous = rest[..consumed].chars().last();
+        rest = &rest[consumed..];
+    }
+
+    html
+}
+
+/// The emphasized text up to the closing `delimiter`, if any
+fn emphasized<'t>(text: &'t str, delimiter: &str) -> Option<&'t str> {
+    let end = text.find(delimiter)?;
+    let inner = &text[..end];
+    let flanked = !inner.is_empty()
+        && !inner.starts_with(char::is_whitespace)
+        && !inner.ends_with(char::is_whitespace);
+
+    flanked.then_some(inner)
+}
+
+/// `[text](url)` at the start of `text`: the text, the url and the length of the whole link
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let text_end = text.find(']')?;
+    let url_part = text[text_end + 1..].strip_prefix('(')?;
+    let url_end = url_part.find(')')?;
+
+    Some((
+        &text[1..text_end],
+        url_part[..url_end].trim(),
+        text_end + 2 + url_end + 1,
+    ))
+}
+
+/// Only web and mail links, or relative ones, so that `javascript:` and the like can't get through
+fn is_safe_url(url: &str) -> bool {
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            ["http", "https", "mailto"].contains(&scheme.as_str())
+        }
+        _ => !url.is_empty(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_render_blocks() {
+        assert_eq!(
+            "<h1>Title</h1>\n\
+             <p>Some <strong>bold</strong> and <em>emphasized</em> text,\n\
+             with <code>code</code> and a <a href=\"https://example.com\" rel=\"nofollow\">link</a>.</p>\n\
+             <ul>\n<li>one</li>\n<li>two</li>\n</ul>\n\
+             <ol>\n<li>first</li>\n</ol>\n\
+             <blockquote>\n<p>quoted</p>\n</blockquote>\n\
+             <hr>\n\
+             <pre><code class=\"language-rust\">let a = &amp;b;\n</code></pre>\n",
+            render(
+                "# Title\n\n\
+                 Some **bold** and *emphasized* text,\n\
+                 with `code` and a [link](https://example.com).\n\n\
+                 - one\n\
+                 - two\n\n\
+                 1. first\n\n\
+                 > quoted\n\n\
+                 ---\n\n\
+                 ```rust\n\
+                 let a = &b;\n\
+                 ```\n"
+            )
+        );
+    }
+
+    #[test]
+    fn should_not_pass_html_through() {
+        assert_eq!(
+            "<p>&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;</p>\n",
+            render("<script>alert('hi')</script>")
+        );
+        assert_eq!(
+            "<p>[click](javascript:alert(1))</p>\n",
+            render("[click](javascript:alert(1))")
+        );
+        assert_eq!(
+            "<p><a href=\"/a&quot;onclick=&quot;x\" rel=\"nofollow\">x</a></p>\n",
+            render("[x](/a\"onclick=\"x)")
+        );
+    }
+
+    #[test]
+    fn deeply_nested_quotes_should_be_kept_as_text() {
+        let html = render(&">".repeat(200 * 1024));
+
+        assert_eq!(MAX_QUOTE_DEPTH, html.matches("<blockquote>").count());
+        assert!(html.contains("<p>&gt;&gt;&gt;"));
+    }
+
+    #[test]
+    fn underscores_within_words_should_not_emphasize() {
+        assert_eq!(
+            "<p>snake_case_name and <em>this</em></p>\n",
+            render("snake_case_name and _this_")
+        );
+    }
+}
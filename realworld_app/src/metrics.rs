@@ -0,0 +1,229 @@
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::routing::get;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+///
+/// Prometheus metrics of the app, rendered by `GET /metrics`.
+///
+#[derive(Clone)]
+pub struct Metrics(Arc<Inner>);
+
+struct Inner {
+    registry: prometheus::Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_query_duration: HistogramVec,
+    db_slow_queries: IntCounterVec,
+    db_retries: IntCounterVec,
+    auth_failures: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = prometheus::Registry::new_custom(Some("realworld".to_string()), None)
+            .expect("valid prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of HTTP requests"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Latency of HTTP requests"),
+            &["method", "route"],
+        )
+        .unwrap();
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "Duration of database queries")
+                .buckets(vec![
+                    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+                ]),
+            &["query"],
+        )
+        .unwrap();
+        let db_slow_queries = IntCounterVec::new(
+            Opts::new(
+                "db_slow_queries_total",
+                "Number of database queries slower than the slow query threshold",
+            ),
+            &["query"],
+        )
+        .unwrap();
+        let db_retries = IntCounterVec::new(
+            Opts::new(
+                "db_retries_total",
+                "Number of database operations retried after a transient failure",
+            ),
+            &["operation", "failure"],
+        )
+        .unwrap();
+        let auth_failures = IntCounter::new(
+            "auth_failures_total",
+            "Number of tokens that could not be verified",
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_query_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_slow_queries.clone()))
+            .unwrap();
+        registry.register(Box::new(db_retries.clone())).unwrap();
+        registry.register(Box::new(auth_failures.clone())).unwrap();
+
+        Self(Arc::new(Inner {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_query_duration,
+            db_slow_queries,
+            db_retries,
+            auth_failures,
+        }))
+    }
+
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.0
+            .http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.0
+            .http_request_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// `query` is where the query was run from, which identifies the repository method.
+    /// Only the Postgres backend reports queries.
+    #[cfg_attr(feature = "sqlite", allow(dead_code))]
+    pub fn record_db_query(&self, query: &str, elapsed: Duration) {
+        self.0
+            .db_query_duration
+            .with_label_values(&[query])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    #[cfg_attr(feature = "sqlite", allow(dead_code))]
+    pub fn record_slow_db_query(&self, query: &str) {
+        self.0.db_slow_queries.with_label_values(&[query]).inc();
+    }
+
+    /// `operation` is the repository method, `failure` what made it fail before it was retried
+    pub fn record_db_retry(&self, operation: &str, failure: &str) {
+        self.0
+            .db_retries
+            .with_label_values(&[operation, failure])
+            .inc();
+    }
+
+    pub fn record_auth_failure(&self) {
+        self.0.auth_failures.inc();
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = vec![];
+        prometheus::TextEncoder::new()
+            .encode(&self.0.registry.gather(), &mut buffer)
+            .expect("writing to a Vec should not fail");
+        String::from_utf8(buffer).expect("metrics should be UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn router(metrics: Metrics) -> axum::Router {
+    axum::Router::new()
+        .route(
+            "/metrics",
+            get(|State(metrics): State<Metrics>| async move { metrics.render() }),
+        )
+        .with_state(metrics)
+}
+
+///
+/// Middleware recording the count and latency of requests.
+///
+/// Requests are labeled by the route pattern rather than the path,
+/// so that e.g. article slugs don't blow up the number of series.
+///
+pub async fn track_requests(
+    State(metrics): State<Metrics>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started_at = Instant::now();
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    metrics.record_request(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        started_at.elapsed(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::{Request, StatusCode};
+
+    #[tokio::test]
+    async fn should_render_recorded_metrics() {
+        let metrics = Metrics::new();
+        let router = axum::Router::new()
+            .route("/articles/:slug", get(|| async {}))
+            .layer(axum::middleware::from_fn_with_state(
+                metrics.clone(),
+                track_requests,
+            ))
+            .merge(router(metrics.clone()));
+
+        request(router.clone(), Request::get("/articles/foo").empty_body()).await;
+        metrics.record_auth_failure();
+        metrics.record_db_query("realworld_db/src/article.rs:26", Duration::from_millis(3));
+        metrics.record_slow_db_query("realworld_db/src/article.rs:26");
+        metrics.record_db_retry("insert_favorite", "serialization");
+
+        let (status, body) = request(router, Request::get("/metrics").empty_body()).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.contains(
+            r#"realworld_http_requests_total{method="GET",route="/articles/:slug",status="200"} 1"#
+        ));
+        assert!(body.contains("realworld_auth_failures_total 1"));
+        assert!(body.contains(
+            r#"realworld_db_query_duration_seconds_count{query="realworld_db/src/article.rs:26"} 1"#
+        ));
+        assert!(body.contains(
+            r#"realworld_db_slow_queries_total{query="realworld_db/src/article.rs:26"} 1"#
+        ));
+        assert!(body.contains(
+            r#"realworld_db_retries_total{failure="serialization",operation="insert_favorite"} 1"#
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::config::Config;
+
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::user::oauth::OAuthIdentity;
+
+#[cfg(feature = "oauth")]
+use anyhow::Context;
+
+///
+/// The OAuth providers that users can sign in with, by name.
+///
+/// Requires the `oauth` feature. Each provider is enabled by configuring its client id and secret,
+/// along with `oauth_redirect_base_url`.
+///
+#[derive(Clone, Default)]
+pub struct OAuthProviders {
+    #[cfg(feature = "oauth")]
+    clients: Vec<OAuthClient>,
+}
+
+impl OAuthProviders {
+    pub fn from_config(config: &Config) -> Self {
+        #[cfg(feature = "oauth")]
+        if let Some(base_url) = &config.oauth_redirect_base_url {
+            let configured = [
+                (
+                    ProviderKind::GitHub,
+                    &config.github_client_id,
+                    &config.github_client_secret,
+                ),
+                (
+                    ProviderKind::Google,
+                    &config.google_client_id,
+                    &config.google_client_secret,
+                ),
+            ];
+
+            return Self {
+                clients: configured
+                    .into_iter()
+                    .filter_map(|(kind, client_id, client_secret)| {
+                        Some(OAuthClient::new(
+                            kind,
+                            client_id.as_deref()?,
+                            client_secret.as_deref()?,
+                            base_url,
+                        ))
+                    })
+                    .collect(),
+            };
+        }
+
+        let _ = config;
+        Self::default()
+    }
+
+    pub fn authorize_url(&self, provider: &str, state: &str) -> RwResult<String> {
+        #[cfg(feature = "oauth")]
+        if let Some(client) = self.client(provider) {
+            return Ok(client.authorize_url(state));
+        }
+
+        let _ = (provider, state);
+        Err(RwError::OAuthProviderNotFound)
+    }
+
+    pub async fn fetch_identity(&self, provider: &str, code: &str) -> RwResult<OAuthIdentity> {
+        #[cfg(feature = "oauth")]
+        if let Some(client) = self.client(provider) {
+            return client.fetch_identity(code).await;
+        }
+
+        let _ = (provider, code);
+        Err(RwError::OAuthProviderNotFound)
+    }
+
+    #[cfg(feature = "oauth")]
+    fn client(&self, provider: &str) -> Option<&OAuthClient> {
+        self.clients
+            .iter()
+            .find(|client| client.kind.name() == provider)
+    }
+}
+
+#[cfg(feature = "oauth")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ProviderKind {
+    GitHub,
+    Google,
+}
+
+#[cfg(feature = "oauth")]
+impl ProviderKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::GitHub => "github",
+            Self::Google => "google",
+        }
+    }
+
+    fn authorize_endpoint(self) -> &'static str {
+        match self {
+            Self::GitHub => "https://github.com/login/oauth/authorize",
+            Self::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    fn token_endpoint(self) -> &'static str {
+        match self {
+            Self::GitHub => "https://github.com/login/oauth/access_token",
+            Self::Google => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    fn scope(self) -> &'static str {
+        match self {
+            Self::GitHub => "read:user user:email",
+            Self::Google => "openid email profile",
+        }
+    }
+}
+
+#[cfg(feature = "oauth")]
+#[derive(Clone)]
+struct OAuthClient {
+    kind: ProviderKind,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+}
+
+#[cfg(feature = "oauth")]
+impl OAuthClient {
+    fn new(
+        kind: ProviderKind,
+        client_id: &str,
+        client_secret: &str,
+        redirect_base_url: &str,
+    ) -> Self {
+        Self {
+            kind,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            redirect_uri: format!(
+                "{}/{}/callback",
+                redirect_base_url.trim_end_matches('/'),
+                kind.name()
+            ),
+        }
+    }
+
+    fn authorize_url(&self, state: &str) -> String {
+        let query = serde_urlencoded::to_string([
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", &self.redirect_uri),
+            ("response_type", "code"),
+            ("scope", self.kind.scope()),
+            ("state", state),
+        ])
+        .expect("strings should be url encodable");
+
+        format!("{}?{query}", self.kind.authorize_endpoint())
+    }
+
+    async fn fetch_identity(&self, code: &str) -> RwResult<OAuthIdentity> {
+        let access_token = self.exchange_code(code).await?;
+
+        match self.kind {
+            ProviderKind::GitHub => github_identity(
+                get_json("https://api.github.com/user", &access_token).await?,
+                get_json("https://api.github.com/user/emails", &access_token).await?,
+            ),
+            ProviderKind::Google => google_identity(
+                get_json(
+                    "https://openidconnect.googleapis.com/v1/userinfo",
+                    &access_token,
+                )
+                .await?,
+            ),
+        }
+    }
+
+    /// Codes that the provider doesn't accept, e.g. because they were already used, are unauthorized
+    async fn exchange_code(&self, code: &str) -> RwResult<String> {
+        let form = serde_urlencoded::to_string([
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", &self.client_secret),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("grant_type", "authorization_code"),
+        ])
+        .expect("strings should be url encodable");
+
+        let request = hyper::Request::post(self.kind.token_endpoint())
+            .header(hyper::header::ACCEPT, "application/json")
+            .header(
+                hyper::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(form)
+            .context("invalid token request")?;
+        let (status, body) = crate::https::send(request).await?;
+
+        #[derive(serde::Deserialize, Default)]
+        struct TokenResponse {
+            access_token: Option<String>,
+        }
+
+        match serde_json::from_slice::<TokenResponse>(&body).unwrap_or_default() {
+            TokenResponse {
+                access_token: Some(access_token),
+            } if status.is_success() => Ok(access_token),
+            _ => Err(RwError::Unauthorized),
+        }
+    }
+}
+
+#[cfg(feature = "oauth")]
+#[derive(serde::Deserialize)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+}
+
+#[cfg(feature = "oauth")]
+#[derive(serde::Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+#[cfg(feature = "oauth")]
+fn github_identity(user: GitHubUser, emails: Vec<GitHubEmail>) -> RwResult<OAuthIdentity> {
+    let email = emails
+        .into_iter()
+        .find(|email| email.primary)
+        .context("GitHub user has no primary email address")?;
+
+    Ok(OAuthIdentity {
+        provider: ProviderKind::GitHub.name().to_string(),
+        subject: user.id.to_string(),
+        email: email.email.parse()?,
+        email_verified: email.verified,
+        username: user.login,
+    })
+}
+
+#[cfg(feature = "oauth")]
+#[derive(serde::Deserialize)]
+struct GoogleUserInfo {
+    sub: String,
+    email: String,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+#[cfg(feature = "oauth")]
+fn google_identity(info: GoogleUserInfo) -> RwResult<OAuthIdentity> {
+    // Google accounts have no username, so the start of the email address will have to do
+    let username = info.email.split('@').next().unwrap_or_default().to_string();
+
+    Ok(OAuthIdentity {
+        provider: ProviderKind::Google.name().to_string(),
+        subject: info.sub,
+        email: info.email.parse()?,
+        email_verified: info.email_verified,
+        username,
+    })
+}
+
+#[cfg(feature = "oauth")]
+async fn get_json<T: serde::de::DeserializeOwned>(url: &str, access_token: &str) -> RwResult<T> {
+    let request = hyper::Request::get(url)
+        .header(hyper::header::ACCEPT, "application/json")
+        .header(
+            hyper::header::AUTHORIZATION,
+            format!("Bearer {access_token}"),
+        )
+        .body(String::new())
+        .context("invalid request")?;
+    let (status, body) = crate::https::send(request).await?;
+
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("{url} responded with {status}").into());
+    }
+
+    Ok(serde_json::from_slice(&body).with_context(|| format!("unexpected response from {url}"))?)
+}
+
+#[cfg(all(test, feature = "oauth"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorize_url_should_have_callback_and_state() {
+        let client = OAuthClient::new(
+            ProviderKind::GitHub,
+            "id",
+            "secret",
+            "https://example.com/api/users/oauth/",
+        );
+
+        assert_eq!(
+            "https://github.com/login/oauth/authorize?client_id=id\
+            &redirect_uri=https%3A%2F%2Fexample.com%2Fapi%2Fusers%2Foauth%2Fgithub%2Fcallback\
+            &response_type=code&scope=read%3Auser+user%3Aemail&state=st%26te",
+            client.authorize_url("st&te")
+        );
+    }
+
+    #[test]
+    fn github_identity_should_have_primary_email() {
+        let identity = github_identity(
+            serde_json::from_value(serde_json::json!({ "id": 1, "login": "octocat" })).unwrap(),
+            serde_json::from_value(serde_json::json!([
+                { "email": "other@example.com", "primary": false, "verified": true },
+                { "email": "octocat@example.com", "primary": true, "verified": false },
+            ]))
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!("1", identity.subject);
+        assert_eq!("octocat", identity.username);
+        assert_eq!("octocat@example.com", identity.email.as_ref());
+        assert!(!identity.email_verified);
+    }
+
+    #[test]
+    fn unconfigured_provider_should_not_be_found() {
+        let providers = OAuthProviders {
+            clients: vec![OAuthClient::new(
+                ProviderKind::Google,
+                "id",
+                "secret",
+                "https://example.com",
+            )],
+        };
+
+        assert!(providers.authorize_url("google", "state").is_ok());
+        assert!(matches!(
+            providers.authorize_url("github", "state"),
+            Err(RwError::OAuthProviderNotFound)
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+//!
+//! Distributed tracing with OpenTelemetry, when `otlp_endpoint` is configured.
+//!
+//! Requests continue the trace of a W3C `traceparent` header, if they have one.
+//! The spans of requests, handlers and repository methods are exported through OTLP,
+//! so that a trace shows how much of a request was spent in the database.
+//!
+
+use crate::config::Config;
+
+use anyhow::Context;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// The layer exporting spans, when there's somewhere to export them to
+pub fn layer<S>(
+    config: &Config,
+) -> anyhow::Result<Option<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .context("could not create OTLP exporter")?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            config.otel_service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer("realworld");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Export the spans that haven't been exported yet
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Make `span` a child of the span in the `traceparent` header, if any
+pub fn set_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    span.set_parent(parent);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn span_should_continue_trace_of_traceparent() {
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            set_parent(&span, &headers);
+
+            assert_eq!(
+                "4bf92f3577b34da6a3ce929d0e0e4736",
+                span.context().span().span_context().trace_id().to_string()
+            );
+        });
+    }
+}
@@ -0,0 +1,515 @@
+use crate::config::Config;
+use crate::live_config::LiveConfig;
+use crate::state::SharedState;
+
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::user::auth::{Authenticate, Token};
+
+use axum::extract::{ConnectInfo, Request};
+use axum::response::{IntoResponse, Response};
+use headers::authorization::Authorization;
+use headers::HeaderMapExt;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Above this number of tracked clients, buckets that have filled up again are forgotten.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Pruning visits every bucket, so it's done at most this often.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Clients tracked at most. Beyond it, the least recently seen half is forgotten,
+/// even with buckets that haven't filled up again.
+const MAX_BUCKETS: usize = 100_000;
+
+///
+/// Sustained rate and burst size of a token bucket.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quota {
+    per_second: f64,
+    burst: f64,
+}
+
+impl Quota {
+    /// `None` when the limit is disabled.
+    pub fn per_minute(per_minute: u32, burst: u32) -> Option<Self> {
+        if per_minute == 0 {
+            return None;
+        }
+
+        Some(Self {
+            per_second: f64::from(per_minute) / 60.0,
+            burst: f64::from(burst.max(1)),
+        })
+    }
+}
+
+/// The quotas of clients by IP address and of users, `None` when disabled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quotas {
+    pub ip: Option<Quota>,
+    pub user: Option<Quota>,
+}
+
+impl Quotas {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ip: Quota::per_minute(config.rate_limit_ip_per_minute, config.rate_limit_ip_burst),
+            user: Quota::per_minute(
+                config.rate_limit_user_per_minute,
+                config.rate_limit_user_burst,
+            ),
+        }
+    }
+
+    fn of(&self, key: &RateLimitKey) -> Option<Quota> {
+        match key {
+            RateLimitKey::User(_) => self.user,
+            RateLimitKey::Ip(_) => self.ip,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+enum RateLimitKey {
+    User(uuid::Uuid),
+    Ip(IpAddr),
+}
+
+impl std::fmt::Display for RateLimitKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(user_id) => write!(f, "user:{user_id}"),
+            Self::Ip(ip) => write!(f, "ip:{ip}"),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, quota: &Quota, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * quota.per_second).min(quota.burst);
+        self.updated_at = now;
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_key: HashMap<RateLimitKey, Bucket>,
+    last_pruned: Option<Instant>,
+}
+
+impl Buckets {
+    /// Forget buckets that have filled up again, or whose quota is disabled
+    fn prune(&mut self, quotas: &Quotas, now: Instant) {
+        if self.by_key.len() <= PRUNE_THRESHOLD
+            || self.last_pruned.is_some_and(|last_pruned| {
+                now.saturating_duration_since(last_pruned) < PRUNE_INTERVAL
+            })
+        {
+            return;
+        }
+
+        self.last_pruned = Some(now);
+        self.by_key.retain(|key, bucket| {
+            quotas.of(key).is_some_and(|quota| {
+                bucket.refill(&quota, now);
+                bucket.tokens < quota.burst
+            })
+        });
+    }
+
+    /// Make room for a new client by forgetting the least recently seen half
+    fn evict(&mut self) {
+        if self.by_key.len() < MAX_BUCKETS {
+            return;
+        }
+
+        let mut updated_at: Vec<Instant> = self
+            .by_key
+            .values()
+            .map(|bucket| bucket.updated_at)
+            .collect();
+        let middle = updated_at.len() / 2;
+        let (_, &mut cutoff, _) = updated_at.select_nth_unstable(middle);
+        self.by_key.retain(|_, bucket| bucket.updated_at > cutoff);
+    }
+}
+
+///
+/// Token buckets for every client seen recently.
+///
+/// Authenticated requests are keyed by user, other requests by IP address.
+///
+/// The buckets are kept in Redis when it's configured, so that they're shared between instances.
+/// If Redis fails, the buckets of this instance are used instead.
+///
+/// The quotas are those of the current [LiveConfig], so that they can be changed while serving.
+///
+pub struct RateLimiter {
+    live_config: LiveConfig,
+    buckets: Mutex<Buckets>,
+    #[cfg(feature = "redis")]
+    redis: Option<realworld_redis::Redis>,
+}
+
+impl RateLimiter {
+    pub fn new(live_config: LiveConfig) -> Self {
+        Self {
+            live_config,
+            buckets: Default::default(),
+            #[cfg(feature = "redis")]
+            redis: None,
+        }
+    }
+
+    pub fn with_shared_state(live_config: LiveConfig, shared: &SharedState) -> Self {
+        let limiter = Self::new(live_config);
+
+        #[cfg(feature = "redis")]
+        let limiter = Self {
+            redis: shared.redis.clone(),
+            ..limiter
+        };
+
+        let _ = shared;
+        limiter
+    }
+
+    async fn check(&self, key: RateLimitKey, now: Instant) -> RwResult<()> {
+        let quotas = self.live_config.load().rate_limits;
+        let Some(quota) = quotas.of(&key) else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            match redis
+                .take_rate_limit_token(&key.to_string(), quota.per_second, quota.burst)
+                .await
+            {
+                Ok(None) => return Ok(()),
+                Ok(Some(retry_after)) => return Err(RwError::TooManyRequests { retry_after }),
+                Err(error) => tracing::warn!("rate limiter failure: {error:#}"),
+            }
+        }
+
+        self.take_local_token(key, quota, &quotas, now)
+    }
+
+    fn take_local_token(
+        &self,
+        key: RateLimitKey,
+        quota: Quota,
+        quotas: &Quotas,
+        now: Instant,
+    ) -> RwResult<()> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        buckets.prune(quotas, now);
+        if !buckets.by_key.contains_key(&key) {
+            buckets.evict();
+        }
+
+        let bucket = buckets.by_key.entry(key).or_insert(Bucket {
+            tokens: quota.burst,
+            updated_at: now,
+        });
+        bucket.refill(&quota, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(RwError::TooManyRequests {
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / quota.per_second),
+            })
+        }
+    }
+}
+
+///
+/// Tower layer rejecting clients that exceed their quota.
+///
+/// The user is identified with the `D` found in the request extensions,
+/// so the layer has to be applied inside the layer that injects the app.
+/// Requests with an invalid token are limited by IP address,
+/// the token itself is rejected later by the route.
+///
+pub struct RateLimitLayer<D> {
+    limiter: Arc<RateLimiter>,
+    deps: std::marker::PhantomData<fn() -> D>,
+}
+
+impl<D> RateLimitLayer<D> {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self {
+            limiter: Arc::new(limiter),
+            deps: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<D> Clone for RateLimitLayer<D> {
+    fn clone(&self) -> Self {
+        Self {
+            limiter: self.limiter.clone(),
+            deps: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<S, D> tower::Layer<S> for RateLimitLayer<D> {
+    type Service = RateLimitService<S, D>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+pub struct RateLimitService<S, D> {
+    inner: S,
+    layer: RateLimitLayer<D>,
+}
+
+impl<S: Clone, D> Clone for RateLimitService<S, D> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, D> RateLimitService<S, D>
+where
+    D: Authenticate + Clone + Send + Sync + 'static,
+{
+    fn key(request: &Request) -> impl Future<Output = Option<RateLimitKey>> + Send {
+        // Extract everything up front, the request itself can't be held across an await
+        let deps = request.extensions().get::<D>().cloned();
+        let token = request.headers().typed_get::<Authorization<Token>>();
+        let ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        async move {
+            let user_id = match (deps, token) {
+                (Some(deps), Some(Authorization(token))) => deps.authenticate(token).await.ok(),
+                _ => None,
+            };
+
+            match user_id {
+                Some(user_id) => Some(RateLimitKey::User(user_id.into_id())),
+                None => ip.map(RateLimitKey::Ip),
+            }
+        }
+    }
+}
+
+impl<S, D> tower::Service<Request> for RateLimitService<S, D>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    D: Authenticate + Clone + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let limiter = self.layer.limiter.clone();
+        let key = Self::key(&request);
+
+        // Call the instance that was driven to readiness, leaving a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if let Some(key) = key.await {
+                if let Err(error) = limiter.check(key, Instant::now()).await {
+                    return Ok(error.into_response());
+                }
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::live_config::LiveSettings;
+    use crate::test_util::*;
+
+    use axum::http::header::RETRY_AFTER;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use unimock::*;
+
+    fn limiter(ip: Option<Quota>, user: Option<Quota>) -> RateLimiter {
+        RateLimiter::new(LiveConfig::new(LiveSettings {
+            rate_limits: Quotas { ip, user },
+            ..Default::default()
+        }))
+    }
+
+    fn ip_key() -> RateLimitKey {
+        RateLimitKey::Ip([127, 0, 0, 1].into())
+    }
+
+    #[tokio::test]
+    async fn bucket_should_allow_burst_then_refill() {
+        let limiter = limiter(Quota::per_minute(60, 2), None);
+        let now = Instant::now();
+
+        assert!(limiter.check(ip_key(), now).await.is_ok());
+        assert!(limiter.check(ip_key(), now).await.is_ok());
+        assert_matches::assert_matches!(
+            limiter.check(ip_key(), now).await,
+            Err(RwError::TooManyRequests { retry_after }) if retry_after == Duration::from_secs(1)
+        );
+
+        assert!(limiter
+            .check(ip_key(), now + Duration::from_secs(1))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn clients_should_have_separate_buckets() {
+        let limiter = limiter(Quota::per_minute(60, 1), None);
+        let now = Instant::now();
+
+        assert!(limiter.check(ip_key(), now).await.is_ok());
+        assert!(limiter
+            .check(RateLimitKey::Ip([10, 0, 0, 1].into()), now)
+            .await
+            .is_ok());
+        assert!(limiter.check(ip_key(), now).await.is_err());
+    }
+
+    fn user_key(n: u128) -> RateLimitKey {
+        RateLimitKey::User(uuid::Uuid::from_u128(n))
+    }
+
+    /// Buckets of `count` clients, filled up again at `now`
+    fn full_buckets(count: usize, now: Instant) -> impl Iterator<Item = (RateLimitKey, Bucket)> {
+        (0..count as u128).map(move |n| {
+            let bucket = Bucket {
+                tokens: 1.0,
+                updated_at: now,
+            };
+            (user_key(n), bucket)
+        })
+    }
+
+    #[test]
+    fn buckets_should_be_pruned_at_most_once_per_interval() {
+        let quotas = Quotas {
+            ip: None,
+            user: Quota::per_minute(60, 1),
+        };
+        let now = Instant::now();
+        let mut buckets = Buckets::default();
+
+        buckets
+            .by_key
+            .extend(full_buckets(PRUNE_THRESHOLD + 1, now));
+        buckets.prune(&quotas, now);
+        assert!(buckets.by_key.is_empty());
+
+        buckets
+            .by_key
+            .extend(full_buckets(PRUNE_THRESHOLD + 1, now));
+        buckets.prune(&quotas, now + PRUNE_INTERVAL / 2);
+        assert_eq!(PRUNE_THRESHOLD + 1, buckets.by_key.len());
+
+        buckets.prune(&quotas, now + PRUNE_INTERVAL);
+        assert!(buckets.by_key.is_empty());
+    }
+
+    #[test]
+    fn least_recently_seen_clients_should_be_evicted_beyond_max_buckets() {
+        let now = Instant::now();
+        let mut buckets = Buckets::default();
+        buckets.by_key.extend((0..MAX_BUCKETS as u128).map(|n| {
+            let bucket = Bucket {
+                tokens: 0.0,
+                updated_at: now + Duration::from_micros(n as u64),
+            };
+            (user_key(n), bucket)
+        }));
+
+        buckets.evict();
+
+        assert_eq!(MAX_BUCKETS / 2 - 1, buckets.by_key.len());
+        assert!(!buckets.by_key.contains_key(&user_key(0)));
+        assert!(buckets
+            .by_key
+            .contains_key(&user_key(MAX_BUCKETS as u128 - 1)));
+    }
+
+    #[tokio::test]
+    async fn disabled_quota_should_not_limit() {
+        let limiter = limiter(None, Quota::per_minute(0, 10));
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            assert!(limiter.check(ip_key(), now).await.is_ok());
+            assert!(limiter
+                .check(RateLimitKey::User(uuid::Uuid::nil()), now)
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn exceeded_quota_should_give_429() {
+        let router = axum::Router::new()
+            .route("/", get(|| async {}))
+            .layer(RateLimitLayer::<Unimock>::new(limiter(
+                Quota::per_minute(1, 1),
+                None,
+            )));
+
+        let connected_request = || {
+            let mut request = axum::http::Request::get("/").empty_body();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+            request
+        };
+
+        let response = tower::ServiceExt::oneshot(router.clone(), connected_request())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = tower::ServiceExt::oneshot(router, connected_request())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert!(response.headers().contains_key(RETRY_AFTER));
+    }
+}
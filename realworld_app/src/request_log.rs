@@ -0,0 +1,342 @@
+//!
+//! Structured logs of API requests, for following what clients send without a debugger:
+//! one `request_log` event per request, with its method, route, status and latency,
+//! and with the `body` verbosity, its JSON body.
+//!
+//! Bodies are redacted before they're logged. Fields are recognised by their names as serialized
+//! by serde, like `password`, `currentPassword` or `refreshToken`, so that new DTOs are covered
+//! without being listed here.
+//!
+
+use crate::config::{Config, RequestLogVerbosity};
+
+use realworld_domain::error::RwError;
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Logged in place of the values of redacted fields
+const REDACTED: &str = "[redacted]";
+
+/// Parts of the names of fields that are redacted, compared regardless of case
+const SENSITIVE_NAMES: &[&str] = &["password", "token", "secret"];
+
+/// Which requests are logged, and how verbosely
+pub struct RequestLog {
+    verbosity: RequestLogVerbosity,
+    sample_rate: f64,
+    routes: HashMap<String, RequestLogVerbosity>,
+    /// Bodies are read into memory to be logged, at most this much
+    max_body_bytes: usize,
+}
+
+impl RequestLog {
+    pub fn from_config(config: &Config) -> Arc<Self> {
+        Arc::new(Self {
+            verbosity: config.request_log,
+            sample_rate: config.request_log_sample_rate,
+            routes: config
+                .request_log_routes
+                .iter()
+                .map(|route| (route.route.clone(), route.verbosity))
+                .collect(),
+            max_body_bytes: config.max_body_bytes,
+        })
+    }
+
+    fn verbosity_of(&self, route: &str) -> RequestLogVerbosity {
+        self.routes.get(route).copied().unwrap_or(self.verbosity)
+    }
+
+    fn sampled(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+}
+
+///
+/// Log the request when it's sampled, after it's been handled.
+///
+/// Routes are only known after routing, so this is a route layer.
+///
+pub async fn log_request(
+    State(log): State<Arc<RequestLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let verbosity = log.verbosity_of(&route);
+    if verbosity == RequestLogVerbosity::Off || !log.sampled() {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    let (request, body) = if verbosity == RequestLogVerbosity::Body && is_json(&request) {
+        let (parts, body) = request.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, log.max_body_bytes).await else {
+            return RwError::PayloadTooLarge {
+                max_bytes: log.max_body_bytes,
+            }
+            .into_response();
+        };
+        let redacted = redacted(&bytes);
+        (
+            Request::from_parts(parts, Body::from(bytes)),
+            Some(redacted),
+        )
+    } else {
+        (request, None)
+    };
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    tracing::info!(
+        target: "request_log",
+        method = %method,
+        route,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_millis() as u64,
+        body = body.as_deref(),
+    );
+    response
+}
+
+fn is_json(request: &Request) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// The body as JSON, with the values of sensitive fields replaced, at any depth
+fn redacted(bytes: &[u8]) -> String {
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(bytes) else {
+        return "[invalid JSON]".to_string();
+    };
+    redact(&mut value);
+    value.to_string()
+}
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if is_sensitive(name) {
+                    *value = serde_json::Value::from(REDACTED);
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SENSITIVE_NAMES
+        .iter()
+        .any(|sensitive| name.contains(sensitive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::StatusCode;
+    use std::sync::Mutex;
+
+    /// The log output of a test, one JSON object per line
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn events(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    fn test_router(log: RequestLog) -> axum::Router {
+        axum::Router::new()
+            .route(
+                "/users/:username",
+                axum::routing::post(|body: String| async { body }),
+            )
+            .route(
+                "/login",
+                axum::routing::post(|| async { StatusCode::UNAUTHORIZED }),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                Arc::new(log),
+                log_request,
+            ))
+    }
+
+    fn test_log(verbosity: RequestLogVerbosity) -> RequestLog {
+        RequestLog {
+            verbosity,
+            sample_rate: 1.0,
+            routes: HashMap::new(),
+            max_body_bytes: 1000,
+        }
+    }
+
+    /// The events logged while handling the requests
+    async fn logged(
+        log: RequestLog,
+        requests: Vec<axum::http::Request<Body>>,
+    ) -> Vec<serde_json::Value> {
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let router = test_router(log);
+        for request in requests {
+            crate::test_util::request(router.clone(), request).await;
+        }
+        output.events()
+    }
+
+    fn user_body() -> serde_json::Value {
+        serde_json::json!({
+            "user": {
+                "username": "jake",
+                "password": "s3cr3t",
+                "refreshToken": "t0k3n",
+                "devices": [{ "name": "phone", "pushToken": "t0k3n" }],
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn status_should_be_logged_with_route() {
+        let events = logged(
+            test_log(RequestLogVerbosity::Status),
+            vec![axum::http::Request::post("/login").with_json_body(user_body())],
+        )
+        .await;
+
+        assert_eq!(1, events.len());
+        assert_eq!("request_log", events[0]["target"]);
+        assert_eq!("POST", events[0]["method"]);
+        assert_eq!("/login", events[0]["route"]);
+        assert_eq!(401, events[0]["status"]);
+        assert!(events[0]["latency_ms"].is_u64());
+        assert!(events[0].get("body").is_none());
+    }
+
+    #[tokio::test]
+    async fn bodies_should_be_logged_redacted() {
+        let events = logged(
+            test_log(RequestLogVerbosity::Body),
+            vec![axum::http::Request::post("/users/jake").with_json_body(user_body())],
+        )
+        .await;
+
+        let body: serde_json::Value =
+            serde_json::from_str(events[0]["body"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "user": {
+                    "username": "jake",
+                    "password": "[redacted]",
+                    "refreshToken": "[redacted]",
+                    "devices": [{ "name": "phone", "pushToken": "[redacted]" }],
+                }
+            }),
+            body
+        );
+        assert_eq!("/users/:username", events[0]["route"]);
+    }
+
+    #[tokio::test]
+    async fn logged_bodies_should_still_reach_the_handler() {
+        let (status, body) = request(
+            test_router(test_log(RequestLogVerbosity::Body)),
+            axum::http::Request::post("/users/jake").with_json_body(user_body()),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            user_body(),
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_should_override_verbosity() {
+        let log = RequestLog {
+            routes: HashMap::from([
+                ("/login".to_string(), RequestLogVerbosity::Off),
+                ("/users/:username".to_string(), RequestLogVerbosity::Body),
+            ]),
+            ..test_log(RequestLogVerbosity::Status)
+        };
+        let events = logged(
+            log,
+            vec![
+                axum::http::Request::post("/login").with_json_body(user_body()),
+                axum::http::Request::post("/users/jake").with_json_body(user_body()),
+            ],
+        )
+        .await;
+
+        assert_eq!(1, events.len());
+        assert_eq!("/users/:username", events[0]["route"]);
+        assert!(events[0]["body"].is_string());
+    }
+
+    #[tokio::test]
+    async fn unsampled_requests_should_not_be_logged() {
+        let log = RequestLog {
+            sample_rate: 0.0,
+            ..test_log(RequestLogVerbosity::Body)
+        };
+        let events = logged(
+            log,
+            vec![axum::http::Request::post("/login").with_json_body(user_body())],
+        )
+        .await;
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn invalid_json_should_not_be_logged() {
+        assert_eq!("[invalid JSON]", redacted(b"password=s3cr3t"));
+        assert_eq!(r#"["a",1]"#, redacted(br#"["a", 1]"#));
+    }
+}
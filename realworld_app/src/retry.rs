@@ -0,0 +1,707 @@
+//!
+//! Retrying database operations that fail transiently.
+//!
+//! A repository method is one transaction, so when it fails on a serialization failure or
+//! a deadlock, which the database rolled back, the whole method can be run again.
+//! [Retrying] wraps the repositories of the backend, so that every method is retried
+//! up to `db_retry_attempts` times, waiting a jittered, exponentially growing time in between.
+//!
+//! A connection may be lost after the database committed, so only methods marked
+//! `#[read_only]` are retried on a lost connection. Running a write again could insert twice,
+//! or find a single use token already taken.
+//!
+//! Methods returning streams aren't retried, since part of the stream may already have been read.
+//!
+//! Every retried method runs in a `repo` span, which is how repository calls show up in traces.
+//!
+
+use crate::app::backend::{transient_failure, TransientFailure};
+use crate::config::Config;
+
+use realworld_domain::article::feed::FeedRepoImpl;
+use realworld_domain::article::repo::{
+    Article, ArticleRepoImpl, ArticleUpdate, ArticleView, BookmarkRepoImpl, Filter, Pagination,
+    ViewRepoImpl,
+};
+use realworld_domain::article::{ArticleId, Slug};
+use realworld_domain::audit::{AuditFilter, AuditLogImpl, AuditRecord, NewAuditEntry};
+use realworld_domain::comment::repo::{ArticleComment, Comment, CommentRepoImpl, ListOptions};
+use realworld_domain::comment::CommentId;
+use realworld_domain::data_export::{DataExportRecord, DataExportRepoImpl};
+use realworld_domain::error::RwResult;
+use realworld_domain::notification::repo::{Notification, NotificationRepoImpl};
+use realworld_domain::notification::NotificationKind;
+use realworld_domain::recommendation::{RecommendationRepoImpl, Signals};
+use realworld_domain::report::repo::{Report, ReportRepoImpl};
+use realworld_domain::settings::{SettingsRepoImpl, UserSettings};
+use realworld_domain::stats::{StatsRepoImpl, UserStats};
+use realworld_domain::timestamp::Timestamptz;
+use realworld_domain::user::email::Email;
+use realworld_domain::user::oauth::OAuthIdentity;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::password::PasswordHash;
+use realworld_domain::user::repo::{
+    BanRepoImpl, Banned, Created, Credentials, DeletionMode, EmailVerificationRepoImpl,
+    FollowStats, Following, ImportedUser, LoginAttemptRepoImpl, PasswordResetRepoImpl,
+    PastUsernames, RefreshTokenRepoImpl, Session, SessionRepoImpl, User, UserRepoImpl, UserUpdate,
+};
+use realworld_domain::user::role::Role;
+use realworld_domain::user::UserId;
+
+use entrait::Impl;
+use futures::stream::BoxStream;
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::Instrument;
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Including the first attempt
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            attempts: config.db_retry_attempts,
+            base_delay: Duration::from_millis(config.db_retry_base_delay_ms),
+            max_delay: Duration::from_millis(config.db_retry_max_delay_ms),
+        }
+    }
+
+    /// The wait before retry number `retry`, counting from 0:
+    /// a random part of `base_delay * 2^retry`, but no more than `max_delay`
+    fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << retry.min(16))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::random::<f64>())
+    }
+}
+
+///
+/// What [Retrying] needs of the app.
+///
+pub trait RetryTransient: Send + Sync + 'static {
+    fn retry_policy(&self) -> &RetryPolicy;
+
+    /// `operation` failed with `failure`, and is about to be retried
+    fn record_retry(&self, operation: &'static str, failure: TransientFailure);
+}
+
+///
+/// The repository `R`, with its methods retried on transient failures.
+///
+#[cfg(not(feature = "dyn-repos"))]
+pub struct Retrying<R>(std::marker::PhantomData<R>);
+
+///
+/// The repository `R`, with its methods retried on transient failures.
+///
+/// The app hands out references to this as trait objects, so it holds a value of `R`.
+///
+#[cfg(feature = "dyn-repos")]
+pub struct Retrying<R>(pub R);
+
+async fn retry<T, F, Fut, O>(
+    deps: &T,
+    operation: &'static str,
+    read_only: bool,
+    mut attempt: F,
+) -> RwResult<O>
+where
+    T: RetryTransient,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = RwResult<O>>,
+{
+    let policy = deps.retry_policy();
+    let mut retries = 0;
+    loop {
+        let error = match attempt().await {
+            Ok(output) => return Ok(output),
+            Err(error) => error,
+        };
+
+        let Some(failure) = transient_failure(&error) else {
+            return Err(error);
+        };
+        if failure == TransientFailure::Connection && !read_only {
+            return Err(error);
+        }
+        if retries + 1 >= policy.attempts {
+            return Err(error);
+        }
+
+        let delay = policy.backoff(retries);
+        tracing::warn!(
+            operation,
+            failure = failure.as_str(),
+            retry = retries + 1,
+            delay_ms = delay.as_millis() as u64,
+            "retrying database operation: {error}"
+        );
+        deps.record_retry(operation, failure);
+        tokio::time::sleep(delay).await;
+        retries += 1;
+    }
+}
+
+/// Spans all attempts of a repository method, so that traces show the time spent in the database
+fn repo_span(repo: &'static str, method: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        "repo",
+        otel.name = format!("{}::{method}", repo.trim_end_matches("Impl")),
+        otel.kind = "client",
+        db.operation = method,
+    )
+}
+
+/// Whether a method given to [retrying] is marked `#[read_only]`
+macro_rules! read_only {
+    () => {
+        false
+    };
+    (read_only) => {
+        true
+    };
+}
+
+/// Implements a repository trait for [Retrying], given the signatures of its methods,
+/// streaming methods last. Arguments are cloned for every attempt.
+/// Methods marked `#[read_only]` are retried on a lost connection too.
+macro_rules! retrying {
+    ($repo:ident {
+        $($(#[$read_only:ident])? async fn $method:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*
+        $(fn $stream:ident($($stream_arg:ident: $stream_ty:ty),*) -> $stream_ret:ty;)*
+    }) => {
+        #[cfg(not(feature = "dyn-repos"))]
+        impl<T, R> $repo<T> for Retrying<R>
+        where
+            T: RetryTransient,
+            R: $repo<T>,
+        {
+            $(
+                async fn $method(deps: &Impl<T>, $($arg: $ty),*) -> $ret {
+                    retry(&**deps, stringify!($method), read_only!($($read_only)?), || {
+                        R::$method(deps, $(Clone::clone(&$arg)),*)
+                    })
+                    .instrument(repo_span(stringify!($repo), stringify!($method)))
+                    .await
+                }
+            )*
+
+            $(
+                fn $stream(deps: &Impl<T>, $($stream_arg: $stream_ty),*) -> $stream_ret {
+                    R::$stream(deps, $($stream_arg),*)
+                }
+            )*
+        }
+
+        #[cfg(feature = "dyn-repos")]
+        #[async_trait::async_trait]
+        impl<T, R> $repo<T> for Retrying<R>
+        where
+            T: RetryTransient,
+            R: $repo<T> + Sync,
+        {
+            $(
+                async fn $method(&self, deps: &Impl<T>, $($arg: $ty),*) -> $ret {
+                    retry(&**deps, stringify!($method), read_only!($($read_only)?), || {
+                        self.0.$method(deps, $(Clone::clone(&$arg)),*)
+                    })
+                    .instrument(repo_span(stringify!($repo), stringify!($method)))
+                    .await
+                }
+            )*
+
+            $(
+                fn $stream(&self, deps: &Impl<T>, $($stream_arg: $stream_ty),*) -> $stream_ret {
+                    self.0.$stream(deps, $($stream_arg),*)
+                }
+            )*
+        }
+    };
+}
+
+retrying!(UserRepoImpl {
+    async fn insert_user(
+        username: &str,
+        email: &Email,
+        password_hash: PasswordHash
+    ) -> RwResult<(User, Credentials)>;
+    async fn insert_users(users: &[ImportedUser]) -> RwResult<Vec<String>>;
+    #[read_only]
+    async fn find_user_credentials_by_id(user_id: UserId) -> RwResult<Option<(User, Credentials)>>;
+    #[read_only]
+    async fn find_user_credentials_by_email(
+        email: &Email
+    ) -> RwResult<Option<(User, Credentials)>>;
+    async fn find_or_create_by_oauth_identity(
+        identity: &OAuthIdentity
+    ) -> RwResult<(User, Credentials, Created)>;
+    #[read_only]
+    async fn find_user_by_username(
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        past_usernames: PastUsernames
+    ) -> RwResult<Option<(User, Following, FollowStats)>>;
+    async fn update_user(
+        current_user_id: UserId,
+        update: UserUpdate<'_>
+    ) -> RwResult<(User, Credentials)>;
+    async fn set_user_role(user_id: UserId, role: Role) -> RwResult<()>;
+    async fn delete_user(user_id: UserId, mode: DeletionMode) -> RwResult<()>;
+    async fn insert_follow(current_user_id: UserId, username: &str) -> RwResult<()>;
+    async fn delete_follow(current_user_id: UserId, username: &str) -> RwResult<()>;
+    async fn insert_block(current_user_id: UserId, username: &str) -> RwResult<()>;
+    async fn delete_block(current_user_id: UserId, username: &str) -> RwResult<()>;
+    #[read_only]
+    async fn list_followers(
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        pagination: Pagination
+    ) -> RwResult<Vec<(User, Following)>>;
+    #[read_only]
+    async fn list_following(
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        pagination: Pagination
+    ) -> RwResult<Vec<(User, Following)>>;
+    #[read_only]
+    async fn search_users(
+        current_user: UserId<Option<Uuid>>,
+        query: &str,
+        pagination: Pagination
+    ) -> RwResult<Vec<(User, Following)>>;
+    #[read_only]
+    async fn select_users_by_ids(
+        current_user: UserId<Option<Uuid>>,
+        user_ids: &[Uuid]
+    ) -> RwResult<Vec<(User, Following)>>;
+    #[read_only]
+    async fn list_users(pagination: Pagination) -> RwResult<Vec<(User, Credentials, Banned)>>;
+    fn stream_usernames() -> BoxStream<'static, RwResult<String>>;
+});
+
+retrying!(BanRepoImpl {
+    async fn set_user_banned(user_id: UserId, banned: Banned) -> RwResult<()>;
+    #[read_only]
+    async fn is_user_banned(user_id: UserId) -> RwResult<bool>;
+});
+
+retrying!(LoginAttemptRepoImpl {
+    async fn insert_failed_login(
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        attempted_at: OffsetDateTime
+    ) -> RwResult<()>;
+    #[read_only]
+    async fn count_failed_logins(
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        since: OffsetDateTime
+    ) -> RwResult<i64>;
+    async fn delete_failed_logins(email: &Email) -> RwResult<()>;
+    async fn delete_failed_logins_before(before: OffsetDateTime) -> RwResult<u64>;
+});
+
+retrying!(RefreshTokenRepoImpl {
+    async fn insert_refresh_token(
+        user_id: UserId,
+        session_id: Uuid,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime
+    ) -> RwResult<()>;
+    async fn take_refresh_token(
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime
+    ) -> RwResult<Option<(UserId, Uuid)>>;
+    async fn delete_expired_refresh_tokens(now: OffsetDateTime) -> RwResult<u64>;
+});
+
+retrying!(SessionRepoImpl {
+    async fn insert_session(
+        user_id: UserId,
+        device: Option<&str>,
+        expires_at: OffsetDateTime
+    ) -> RwResult<Uuid>;
+    async fn touch_session(session_id: Uuid, expires_at: OffsetDateTime) -> RwResult<bool>;
+    #[read_only]
+    async fn session_exists(session_id: Uuid, now: OffsetDateTime) -> RwResult<bool>;
+    #[read_only]
+    async fn select_sessions(user_id: UserId, now: OffsetDateTime) -> RwResult<Vec<Session>>;
+    async fn delete_session(user_id: UserId, session_id: Uuid) -> RwResult<()>;
+    async fn delete_user_sessions(user_id: UserId) -> RwResult<u64>;
+    async fn delete_expired_sessions(now: OffsetDateTime) -> RwResult<u64>;
+});
+
+retrying!(EmailVerificationRepoImpl {
+    async fn insert_email_verification(
+        user_id: UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime
+    ) -> RwResult<()>;
+    async fn take_email_verification(
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime
+    ) -> RwResult<Option<UserId>>;
+});
+
+retrying!(PasswordResetRepoImpl {
+    async fn insert_password_reset(
+        user_id: UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime
+    ) -> RwResult<()>;
+    async fn take_password_reset(
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime
+    ) -> RwResult<Option<UserId>>;
+});
+
+retrying!(ArticleRepoImpl {
+    #[read_only]
+    async fn select_articles(
+        current_user: UserId<Option<Uuid>>,
+        filter: Filter<'_>
+    ) -> RwResult<Vec<Article>>;
+    #[read_only]
+    async fn fetch_article_id(slug: &Slug) -> RwResult<ArticleId>;
+    #[read_only]
+    async fn find_article_slug(article_id: ArticleId) -> RwResult<Option<Slug>>;
+    #[read_only]
+    async fn find_renamed_article_slug(previous_slug: &Slug) -> RwResult<Option<Slug>>;
+    async fn insert_article(
+        user_id: UserId,
+        slug: &Slug,
+        title: &str,
+        description: &str,
+        body: &str,
+        tag_list: &[String],
+        share_token: Option<&str>
+    ) -> RwResult<Article>;
+    async fn update_article(user_id: UserId, slug: &Slug, up: ArticleUpdate<'_>) -> RwResult<()>;
+    async fn delete_article(user_id: UserId, slug: &Slug) -> RwResult<()>;
+    async fn delete_any_article(slug: &Slug) -> RwResult<()>;
+    async fn insert_favorite(user_id: UserId, slug: &Slug) -> RwResult<()>;
+    async fn delete_favorite(user_id: UserId, slug: &Slug) -> RwResult<()>;
+    async fn set_favorites_bulk(
+        user_id: UserId,
+        slugs: &[Slug],
+        favorited: bool
+    ) -> RwResult<Vec<Slug>>;
+    #[read_only]
+    async fn list_favoriting_users(
+        current_user: UserId<Option<Uuid>>,
+        slug: &Slug,
+        pagination: Pagination
+    ) -> RwResult<Vec<(User, Following)>>;
+    #[read_only]
+    async fn select_trending_articles(
+        current_user: UserId<Option<Uuid>>,
+        since: OffsetDateTime,
+        pagination: Pagination
+    ) -> RwResult<Vec<Article>>;
+    #[read_only]
+    async fn select_recommended_articles(
+        current_user: UserId,
+        pagination: Pagination
+    ) -> RwResult<Vec<Article>>;
+    fn stream_articles_by_author(author: UserId) -> BoxStream<'static, RwResult<Article>>;
+    fn stream_article_slugs() -> BoxStream<'static, RwResult<(Slug, Timestamptz)>>;
+});
+
+retrying!(BookmarkRepoImpl {
+    async fn insert_bookmark(user_id: UserId, slug: &Slug) -> RwResult<()>;
+    async fn delete_bookmark(user_id: UserId, slug: &Slug) -> RwResult<()>;
+});
+
+retrying!(FeedRepoImpl {
+    async fn fan_out_article(slug: &Slug) -> RwResult<u64>;
+    async fn insert_author_into_feed(follower: UserId, username: &str) -> RwResult<()>;
+    async fn delete_author_from_feed(follower: UserId, username: &str) -> RwResult<()>;
+    async fn rebuild_feeds() -> RwResult<u64>;
+});
+
+retrying!(ViewRepoImpl {
+    async fn insert_views(views: &[ArticleView]) -> RwResult<u64>;
+    async fn delete_views_before(day: time::Date) -> RwResult<u64>;
+});
+
+retrying!(CommentRepoImpl {
+    #[read_only]
+    async fn list_comments(
+        current_user: UserId<Option<Uuid>>,
+        article_id: ArticleId,
+        options: ListOptions
+    ) -> RwResult<Vec<Comment>>;
+    #[read_only]
+    async fn count_comments(
+        current_user: UserId<Option<Uuid>>,
+        article_id: ArticleId
+    ) -> RwResult<i64>;
+    #[read_only]
+    async fn find_comment(
+        current_user: UserId<Option<Uuid>>,
+        article_slug: &Slug,
+        comment_id: CommentId
+    ) -> RwResult<Comment>;
+    async fn insert_comment(
+        current_user: UserId,
+        article_slug: &Slug,
+        body: &str,
+        mentions: &[String]
+    ) -> RwResult<Comment>;
+    async fn delete_comment(
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId
+    ) -> RwResult<()>;
+    async fn insert_like(current_user: UserId, article_slug: &Slug, comment_id: CommentId) -> RwResult<()>;
+    async fn delete_like(current_user: UserId, article_slug: &Slug, comment_id: CommentId) -> RwResult<()>;
+    async fn delete_any_comment(article_slug: &Slug, comment_id: CommentId) -> RwResult<()>;
+    fn stream_comments_by_author(author: UserId) -> BoxStream<'static, RwResult<ArticleComment>>;
+});
+
+retrying!(DataExportRepoImpl {
+    async fn insert_data_export(user_id: UserId) -> RwResult<DataExportRecord>;
+    #[read_only]
+    async fn find_latest_data_export(user_id: UserId) -> RwResult<Option<DataExportRecord>>;
+    async fn claim_pending_data_exports(limit: i64) -> RwResult<Vec<DataExportRecord>>;
+    async fn finish_data_export(data_export_id: Uuid, archive: Option<&[u8]>) -> RwResult<()>;
+    #[read_only]
+    async fn find_data_export_archive(data_export_id: Uuid) -> RwResult<Option<Vec<u8>>>;
+    async fn purge_data_export_archives(finished_before: OffsetDateTime) -> RwResult<u64>;
+    #[read_only]
+    async fn select_favorited_slugs(user_id: UserId) -> RwResult<Vec<String>>;
+    #[read_only]
+    async fn select_followed_usernames(user_id: UserId) -> RwResult<Vec<String>>;
+});
+
+retrying!(NotificationRepoImpl {
+    async fn insert_follow_notification(actor: UserId, username: &str) -> RwResult<()>;
+    async fn insert_article_notification(
+        actor: UserId,
+        kind: NotificationKind,
+        article_slug: &Slug
+    ) -> RwResult<()>;
+    async fn insert_mention_notification(
+        actor: UserId,
+        username: &str,
+        article_slug: &Slug
+    ) -> RwResult<()>;
+    #[read_only]
+    async fn list_notifications(
+        user_id: UserId,
+        unread_only: bool,
+        limit: i64,
+        offset: i64
+    ) -> RwResult<Vec<Notification>>;
+    async fn mark_notification_read(user_id: UserId, notification_id: i64) -> RwResult<()>;
+});
+
+retrying!(ReportRepoImpl {
+    async fn insert_report(
+        reporter: UserId,
+        article_slug: &Slug,
+        comment_id: Option<CommentId>,
+        reason: &str
+    ) -> RwResult<i64>;
+    #[read_only]
+    async fn list_reports(resolved: Option<bool>, pagination: Pagination) -> RwResult<Vec<Report>>;
+    async fn resolve_report(report_id: i64) -> RwResult<()>;
+});
+
+retrying!(RecommendationRepoImpl {
+    #[read_only]
+    async fn select_follow_candidates(
+        user_id: UserId,
+        limit: i64
+    ) -> RwResult<Vec<(User, Signals)>>;
+});
+
+retrying!(SettingsRepoImpl {
+    #[read_only]
+    async fn find_user_settings(user_id: UserId) -> RwResult<Option<UserSettings>>;
+    async fn save_user_settings(user_id: UserId, settings: &UserSettings) -> RwResult<()>;
+});
+
+retrying!(StatsRepoImpl {
+    #[read_only]
+    async fn select_user_stats(user_id: UserId) -> RwResult<UserStats>;
+});
+
+retrying!(AuditLogImpl {
+    async fn record_audit(entry: NewAuditEntry<'_>) -> RwResult<()>;
+    #[read_only]
+    async fn select_audit_entries(filter: AuditFilter) -> RwResult<Vec<AuditRecord>>;
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use realworld_domain::error::RwError;
+    use realworld_domain::user::repo::BanRepo;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    struct TestDeps {
+        policy: RetryPolicy,
+        /// Attempts that fail before one succeeds
+        failing_attempts: AtomicU32,
+        error: fn() -> RwError,
+        attempts: AtomicU32,
+        retries: Mutex<Vec<(&'static str, TransientFailure)>>,
+    }
+
+    impl TestDeps {
+        fn new(failing_attempts: u32, error: fn() -> RwError) -> Impl<Self> {
+            Impl::new(Self {
+                policy: RetryPolicy {
+                    attempts: 3,
+                    base_delay: Duration::ZERO,
+                    max_delay: Duration::ZERO,
+                },
+                failing_attempts: AtomicU32::new(failing_attempts),
+                error,
+                attempts: AtomicU32::new(0),
+                retries: Mutex::new(vec![]),
+            })
+        }
+    }
+
+    impl RetryTransient for TestDeps {
+        fn retry_policy(&self) -> &RetryPolicy {
+            &self.policy
+        }
+
+        fn record_retry(&self, operation: &'static str, failure: TransientFailure) {
+            self.retries.lock().unwrap().push((operation, failure));
+        }
+    }
+
+    /// Fails the first `failing_attempts` attempts
+    trait Attempt {
+        fn attempt(&self) -> RwResult<bool>;
+    }
+
+    impl Attempt for Impl<TestDeps> {
+        fn attempt(&self) -> RwResult<bool> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            match self
+                .failing_attempts
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            {
+                Ok(_) => Err((self.error)()),
+                Err(_) => Ok(true),
+            }
+        }
+    }
+
+    struct FlakyBanRepo;
+
+    #[cfg_attr(not(feature = "dyn-repos"), entrait::entrait)]
+    #[cfg_attr(feature = "dyn-repos", entrait::entrait(ref), async_trait::async_trait)]
+    impl BanRepoImpl for FlakyBanRepo {
+        async fn set_user_banned(deps: &impl Attempt, _: UserId, _: Banned) -> RwResult<()> {
+            deps.attempt().map(|_| ())
+        }
+
+        async fn is_user_banned(deps: &impl Attempt, _: UserId) -> RwResult<bool> {
+            deps.attempt()
+        }
+    }
+
+    #[cfg(not(feature = "dyn-repos"))]
+    impl realworld_domain::user::repo::DelegateBanRepo<Self> for TestDeps {
+        type Target = Retrying<FlakyBanRepo>;
+    }
+
+    #[cfg(feature = "dyn-repos")]
+    impl AsRef<dyn BanRepoImpl<Self> + Sync> for TestDeps {
+        fn as_ref(&self) -> &(dyn BanRepoImpl<Self> + Sync) {
+            &Retrying(FlakyBanRepo)
+        }
+    }
+
+    fn connection_reset() -> RwError {
+        RwError::DatabaseUnavailable(
+            sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()).into(),
+        )
+    }
+
+    #[tokio::test]
+    async fn transient_failure_should_be_retried() {
+        let deps = TestDeps::new(2, connection_reset);
+
+        assert!(deps.is_user_banned(UserId(Uuid::nil())).await.unwrap());
+        assert_eq!(3, deps.attempts.load(Ordering::SeqCst));
+        assert_eq!(
+            vec![
+                ("is_user_banned", TransientFailure::Connection),
+                ("is_user_banned", TransientFailure::Connection)
+            ],
+            *deps.retries.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_should_give_up_after_attempts() {
+        let deps = TestDeps::new(3, connection_reset);
+
+        assert!(matches!(
+            deps.is_user_banned(UserId(Uuid::nil())).await,
+            Err(RwError::DatabaseUnavailable(_))
+        ));
+        assert_eq!(3, deps.attempts.load(Ordering::SeqCst));
+        assert_eq!(2, deps.retries.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn write_should_not_be_retried_on_lost_connection() {
+        let deps = TestDeps::new(1, connection_reset);
+
+        assert!(matches!(
+            deps.set_user_banned(UserId(Uuid::nil()), Banned(true))
+                .await,
+            Err(RwError::DatabaseUnavailable(_))
+        ));
+        assert_eq!(1, deps.attempts.load(Ordering::SeqCst));
+        assert!(deps.retries.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn other_errors_should_not_be_retried() {
+        let deps = TestDeps::new(1, || RwError::ProfileNotFound);
+
+        assert!(matches!(
+            deps.is_user_banned(UserId(Uuid::nil())).await,
+            Err(RwError::ProfileNotFound)
+        ));
+        assert_eq!(1, deps.attempts.load(Ordering::SeqCst));
+        assert!(deps.retries.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn backoff_should_grow_exponentially_up_to_max_delay() {
+        let policy = RetryPolicy {
+            attempts: 10,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(100),
+        };
+
+        for _ in 0..100 {
+            assert!(policy.backoff(0) <= Duration::from_millis(10));
+            assert!(policy.backoff(2) <= Duration::from_millis(40));
+            assert!(policy.backoff(10) <= Duration::from_millis(100));
+        }
+    }
+}
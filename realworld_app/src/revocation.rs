@@ -0,0 +1,111 @@
+use crate::state::SharedState;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+#[cfg(feature = "redis")]
+use realworld_domain::user::revocation::TokenRevocationList;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+
+///
+/// Stores the tokens revoked through `realworld_domain::user::revocation::TokenRevocationList`.
+///
+/// In memory, a revoked token only stops working on the instance that revoked it,
+/// so Redis should be configured when running more than one instance.
+///
+#[derive(Clone)]
+pub enum TokenRevocationStore {
+    Memory(MemoryRevocationList),
+    #[cfg(feature = "redis")]
+    Redis(realworld_redis::Redis),
+}
+
+impl TokenRevocationStore {
+    pub fn new(shared: &SharedState) -> Self {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &shared.redis {
+            return Self::Redis(redis.clone());
+        }
+
+        let _ = shared;
+        Self::Memory(Default::default())
+    }
+
+    pub async fn revoke(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        match self {
+            Self::Memory(memory) => {
+                memory.revoke(token_hash, expires_at, OffsetDateTime::now_utc());
+                Ok(())
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.revoke_token(token_hash, expires_at).await,
+        }
+    }
+
+    pub async fn is_revoked(&self, token_hash: &OpaqueTokenHash) -> RwResult<bool> {
+        match self {
+            Self::Memory(memory) => Ok(memory.is_revoked(token_hash, OffsetDateTime::now_utc())),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.is_token_revoked(token_hash).await,
+        }
+    }
+}
+
+/// Revoked tokens with their expiry, forgotten once they've expired
+#[derive(Clone, Default)]
+pub struct MemoryRevocationList(Arc<Mutex<HashMap<String, OffsetDateTime>>>);
+
+impl MemoryRevocationList {
+    fn revoke(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) {
+        let mut revoked = self.0.lock().unwrap();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.insert(token_hash.0.clone(), expires_at);
+    }
+
+    fn is_revoked(&self, token_hash: &OpaqueTokenHash, now: OffsetDateTime) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&token_hash.0)
+            .is_some_and(|expires_at| *expires_at > now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use time::Duration;
+
+    #[test]
+    fn revoked_tokens_should_be_forgotten_after_expiry() {
+        let list = MemoryRevocationList::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        let token = OpaqueTokenHash("a".to_string());
+
+        list.revoke(&token, now + Duration::hours(1), now);
+        assert!(list.is_revoked(&token, now));
+        assert!(!list.is_revoked(&OpaqueTokenHash("b".to_string()), now));
+
+        let later = now + Duration::hours(2);
+        assert!(!list.is_revoked(&token, later));
+
+        list.revoke(
+            &OpaqueTokenHash("b".to_string()),
+            later + Duration::hours(1),
+            later,
+        );
+        assert_eq!(1, list.0.lock().unwrap().len());
+    }
+}
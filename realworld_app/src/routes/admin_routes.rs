@@ -0,0 +1,182 @@
+use realworld_domain::admin;
+use realworld_domain::article::Slug;
+use realworld_domain::audit::{AuditEntry, AuditLogQuery};
+use realworld_domain::comment::CommentId;
+use realworld_domain::error::RwResult;
+use realworld_domain::pagination::Pagination;
+use realworld_domain::user::auth::Token;
+
+use axum::extract::{Extension, Path, Query};
+use axum::routing::{delete, get, post};
+use axum::Json;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MultipleUsersBody {
+    users: Vec<admin::AdminUser>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct AuditLogBody {
+    entries: Vec<AuditEntry>,
+}
+
+///
+/// Moderation endpoints under `/admin`, only for admins.
+///
+pub struct AdminRoutes<D>(std::marker::PhantomData<D>);
+
+impl<D> AdminRoutes<D>
+where
+    D: admin::Api + Sized + Clone + Send + Sync + 'static,
+{
+    pub fn router() -> axum::Router {
+        axum::Router::new()
+            .route("/admin/users", get(Self::list_users))
+            .route(
+                "/admin/users/:username/ban",
+                post(Self::ban_user).delete(Self::unban_user),
+            )
+            .route("/admin/articles/:slug", delete(Self::delete_article))
+            .route(
+                "/admin/articles/:slug/comments/:comment_id",
+                delete(Self::delete_comment),
+            )
+            .route("/admin/audit-log", get(Self::list_audit_log))
+            .route("/admin/config/reload", post(Self::reload_config))
+    }
+
+    async fn reload_config(Extension(deps): Extension<D>, token: Token) -> RwResult<()> {
+        deps.reload_config(token).await
+    }
+
+    async fn list_users(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(pagination): Query<Pagination>,
+    ) -> RwResult<Json<MultipleUsersBody>> {
+        Ok(Json(MultipleUsersBody {
+            users: deps.list_users(token, pagination).await?,
+        }))
+    }
+
+    async fn ban_user(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(username): Path<String>,
+    ) -> RwResult<()> {
+        deps.set_user_banned(token, &username, true).await
+    }
+
+    async fn unban_user(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(username): Path<String>,
+    ) -> RwResult<()> {
+        deps.set_user_banned(token, &username, false).await
+    }
+
+    async fn delete_article(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(slug): Path<Slug>,
+    ) -> RwResult<()> {
+        deps.delete_article(token, &slug).await
+    }
+
+    async fn delete_comment(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path((slug, comment_id)): Path<(Slug, CommentId)>,
+    ) -> RwResult<()> {
+        deps.delete_comment(token, &slug, comment_id).await
+    }
+
+    async fn list_audit_log(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(query): Query<AuditLogQuery>,
+    ) -> RwResult<Json<AuditLogBody>> {
+        Ok(Json(AuditLogBody {
+            entries: deps.list_audit_log(token, query).await?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use realworld_domain::error::RwError;
+
+    use axum::http::{Request, StatusCode};
+    use unimock::*;
+
+    fn test_router(deps: Unimock) -> axum::Router {
+        AdminRoutes::<Unimock>::router().layer(Extension(deps))
+    }
+
+    #[tokio::test]
+    async fn unban_should_unset_banned() {
+        let deps = Unimock::new(
+            admin::api::mock::set_user_banned
+                .next_call(matching!(_, "spammer", false))
+                .returns(Ok(())),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::delete("/admin/users/spammer/ban")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn audit_log_should_be_queried_by_user_and_time() {
+        let deps = Unimock::new(
+            admin::api::mock::list_audit_log
+                .next_call(matching! {
+                    (_, query) if query.username.as_deref() == Some("jake")
+                        && query.since.is_some()
+                        && query.until.is_none()
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request(
+            test_router(deps.clone()),
+            Request::get("/admin/audit-log?username=jake&since=2024-01-01T00:00:00Z")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            r#"{"entries":[]}"#,
+            String::from_utf8(body.to_vec()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_comment_should_take_slug_and_id() {
+        let deps = Unimock::new(
+            admin::api::mock::delete_comment
+                .next_call(matching!(_, "slug", CommentId(42)))
+                .returns(Err(RwError::Forbidden)),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::delete("/admin/articles/slug/comments/42")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+}
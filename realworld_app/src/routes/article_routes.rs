@@ -1,21 +1,69 @@
+use super::dto;
+use super::sparse::{Fields, FieldsQuery, Sparse};
+
 use realworld_domain::article;
 use realworld_domain::comment;
-use realworld_domain::error::RwResult;
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::export::{ExportArticle, ExportQuery};
+use realworld_domain::pagination::Pagination;
+use realworld_domain::rss::{GlobalRssFeed, RssFeed};
 use realworld_domain::user::auth::Token;
+use realworld_domain::user::profile::Profile;
 
-use axum::extract::{Extension, Path, Query};
+use axum::extract::{ConnectInfo, Extension, Path, Query, RawQuery};
+use axum::handler::Handler;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{delete, get, post};
 use axum::Json;
+use futures::{Stream, StreamExt};
+use headers::{HeaderMapExt, IfMatch, IfNoneMatch};
+use std::net::SocketAddr;
+use tower_http::compression::CompressionLayer;
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
-struct ArticleBody<T = article::Article> {
+struct ArticleBody<T = dto::Article> {
     article: T,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 // Just trying this out to avoid the tautology of `ArticleBody<Article>`
-struct MultipleArticlesBody {
-    articles: Vec<article::Article>,
+#[serde(rename_all = "camelCase")]
+struct MultipleArticlesBody<T = dto::Article> {
+    articles: Vec<T>,
+    /// Pass as `after` to get the next page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<article::cursor::ArticleCursor>,
+}
+
+/// Articles of a listing with the fields asked for in `?fields=`
+type SparseArticlesBody = MultipleArticlesBody<Sparse<dto::Article>>;
+
+impl SparseArticlesBody {
+    fn new(
+        articles: Vec<article::Article>,
+        next_cursor: Option<article::cursor::ArticleCursor>,
+        fields: &Fields,
+    ) -> Self {
+        Self {
+            articles: articles
+                .into_iter()
+                .map(|article| fields.apply(dto::Article::from(article)))
+                .collect(),
+            next_cursor,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct FavoriteResultsBody {
+    results: Vec<article::FavoriteResult>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct MultipleProfilesBody {
+    profiles: Vec<Profile>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -23,7 +71,7 @@ struct CommentBody<T = comment::Comment> {
     comment: T,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 struct MultipleCommentsBody {
     comments: Vec<comment::Comment>,
 }
@@ -37,13 +85,17 @@ pub struct ArticleRoutes<D>(std::marker::PhantomData<D>);
 
 impl<D: Sized + Clone + Send + Sync + 'static> ArticleRoutes<D>
 where
-    D: article::Api + comment::Api,
+    D: article::Api + comment::Api + ExportArticle + GlobalRssFeed,
 {
     pub fn router() -> axum::Router {
         axum::Router::new().nest(
             "/articles",
             axum::Router::new()
-                .route("/", get(Self::list_articles).post(Self::create_article))
+                .route(
+                    "/",
+                    get(Self::list_articles.layer(CompressionLayer::new()))
+                        .post(Self::create_article),
+                )
                 .route(
                     "/:slug",
                     get(Self::get_article)
@@ -54,12 +106,33 @@ where
                     "/:slug/favorite",
                     post(Self::favorite_article).delete(Self::unfavorite_article),
                 )
-                .route("/feed", get(Self::feed_articles))
+                .route("/:slug/favoriters", get(Self::list_favoriters))
+                .route("/favorites/batch", post(Self::set_favorites))
+                .route(
+                    "/:slug/bookmark",
+                    post(Self::bookmark_article).delete(Self::unbookmark_article),
+                )
+                .route("/:slug/export", get(Self::export_article))
+                .route(
+                    "/feed",
+                    get(Self::feed_articles.layer(CompressionLayer::new())),
+                )
+                .route("/feed.rss", get(Self::global_rss_feed))
+                .route("/id/:id", get(Self::get_article_by_id))
+                .route("/shared/:token", get(Self::get_shared_article))
+                .route("/trending", get(Self::trending_articles))
+                .route("/recommended", get(Self::recommended_articles))
+                .route("/bookmarked", get(Self::bookmarked_articles))
                 .route(
                     "/:slug/comments",
                     get(Self::list_comments).post(Self::add_comment),
                 )
-                .route("/:slug/comments/:comment_id", delete(Self::delete_comment)),
+                .route("/:slug/comments/stream", get(Self::stream_comments))
+                .route("/:slug/comments/:comment_id", delete(Self::delete_comment))
+                .route(
+                    "/:slug/comments/:comment_id/like",
+                    post(Self::like_comment).delete(Self::unlike_comment),
+                ),
         )
     }
 
@@ -67,57 +140,175 @@ where
         Extension(deps): Extension<D>,
         token: Option<Token>,
         Query(query): Query<article::ListArticlesQuery>,
-    ) -> RwResult<Json<MultipleArticlesBody>> {
-        Ok(Json(MultipleArticlesBody {
-            articles: deps.list_articles(token, query).await?,
-        }))
+        Query(fields): Query<FieldsQuery>,
+    ) -> RwResult<Json<SparseArticlesBody>> {
+        let fields = Fields::parse::<dto::Article>(fields)?;
+        let list = deps.list_articles(token, query).await?;
+        Ok(Json(SparseArticlesBody::new(
+            list.articles,
+            list.next_cursor,
+            &fields,
+        )))
     }
 
     async fn feed_articles(
         Extension(deps): Extension<D>,
         token: Token,
         Query(query): Query<article::FeedArticlesQuery>,
-    ) -> RwResult<Json<MultipleArticlesBody>> {
-        Ok(Json(MultipleArticlesBody {
-            articles: deps.feed_articles(token, query).await?,
-        }))
+        Query(fields): Query<FieldsQuery>,
+    ) -> RwResult<Json<SparseArticlesBody>> {
+        let fields = Fields::parse::<dto::Article>(fields)?;
+        let list = deps.feed_articles(token, query).await?;
+        Ok(Json(SparseArticlesBody::new(
+            list.articles,
+            list.next_cursor,
+            &fields,
+        )))
+    }
+
+    async fn global_rss_feed(Extension(deps): Extension<D>) -> RwResult<RssFeed> {
+        deps.global_rss_feed().await
+    }
+
+    async fn trending_articles(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Query(pagination): Query<Pagination>,
+        Query(fields): Query<FieldsQuery>,
+    ) -> RwResult<Json<SparseArticlesBody>> {
+        let fields = Fields::parse::<dto::Article>(fields)?;
+        Ok(Json(SparseArticlesBody::new(
+            deps.list_trending_articles(token, pagination).await?,
+            None,
+            &fields,
+        )))
+    }
+
+    async fn recommended_articles(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(pagination): Query<Pagination>,
+        Query(fields): Query<FieldsQuery>,
+    ) -> RwResult<Json<SparseArticlesBody>> {
+        let fields = Fields::parse::<dto::Article>(fields)?;
+        Ok(Json(SparseArticlesBody::new(
+            deps.list_recommended_articles(token, pagination).await?,
+            None,
+            &fields,
+        )))
+    }
+
+    async fn bookmarked_articles(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(pagination): Query<Pagination>,
+        Query(fields): Query<FieldsQuery>,
+    ) -> RwResult<Json<SparseArticlesBody>> {
+        let fields = Fields::parse::<dto::Article>(fields)?;
+        Ok(Json(SparseArticlesBody::new(
+            deps.list_bookmarked_articles(token, pagination).await?,
+            None,
+            &fields,
+        )))
     }
 
     async fn get_article(
         Extension(deps): Extension<D>,
         token: Option<Token>,
-        Path(slug): Path<String>,
-    ) -> RwResult<Json<ArticleBody>> {
-        Ok(Json(ArticleBody {
-            article: deps.fetch_article(token, &slug).await?,
-        }))
+        Path(slug): Path<article::Slug>,
+        Query(query): Query<article::FetchArticleQuery>,
+        RawQuery(raw_query): RawQuery,
+        headers: HeaderMap,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+    ) -> RwResult<Response> {
+        let ip_address = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        let article = match deps.fetch_article(token, &slug, query, ip_address).await {
+            // The title, and thereby the slug, may have been edited since the client got it
+            Err(RwError::ArticleNotFound) => {
+                let current_slug = deps
+                    .find_renamed_article(&slug)
+                    .await?
+                    .ok_or(RwError::ArticleNotFound)?;
+                let mut location = super::api_path(&["articles", current_slug.as_str()]);
+                if let Some(raw_query) = raw_query {
+                    location = format!("{location}?{raw_query}");
+                }
+                return Ok(Redirect::permanent(&location).into_response());
+            }
+            result => result?,
+        };
+
+        Ok(article_response(article, &headers))
+    }
+
+    async fn get_article_by_id(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(id): Path<String>,
+        Query(query): Query<article::FetchArticleQuery>,
+        headers: HeaderMap,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+    ) -> RwResult<Response> {
+        let ip_address = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        let article = deps
+            .fetch_article_by_id(token, &id, query, ip_address)
+            .await?;
+
+        Ok(article_response(article, &headers))
+    }
+
+    /// An unlisted article, by the token of its share link
+    async fn get_shared_article(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(share_token): Path<String>,
+        Query(query): Query<article::FetchArticleQuery>,
+        headers: HeaderMap,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+    ) -> RwResult<Response> {
+        let ip_address = connect_info.map(|ConnectInfo(addr)| addr.ip());
+        let article = deps
+            .fetch_shared_article(token, &share_token, query, ip_address)
+            .await?;
+
+        Ok(article_response(article, &headers))
     }
 
     async fn create_article(
         Extension(deps): Extension<D>,
         token: Token,
         Json(body): Json<ArticleBody<article::ArticleCreate>>,
-    ) -> RwResult<Json<ArticleBody<article::Article>>> {
+    ) -> RwResult<Json<ArticleBody>> {
         Ok(Json(ArticleBody {
-            article: deps.create_article(token, body.article).await?,
+            article: deps.create_article(token, body.article).await?.into(),
         }))
     }
 
     async fn update_article(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
+        Path(slug): Path<article::Slug>,
+        headers: HeaderMap,
         Json(body): Json<ArticleBody<article::ArticleUpdate>>,
-    ) -> RwResult<Json<ArticleBody>> {
-        Ok(Json(ArticleBody {
-            article: deps.update_article(token, &slug, body.article).await?,
-        }))
+    ) -> RwResult<Response> {
+        let article = deps
+            .update_article(token, &slug, body.article, headers.typed_get::<IfMatch>())
+            .await?;
+        let etag = article.etag();
+
+        Ok(with_etag(
+            Json(ArticleBody {
+                article: dto::Article::from(article),
+            })
+            .into_response(),
+            etag,
+        ))
     }
 
     async fn delete_article(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
+        Path(slug): Path<article::Slug>,
     ) -> RwResult<()> {
         deps.delete_article(token, &slug).await?;
         Ok(())
@@ -126,37 +317,91 @@ where
     async fn favorite_article(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
+        Path(slug): Path<article::Slug>,
     ) -> RwResult<Json<ArticleBody>> {
         Ok(Json(ArticleBody {
-            article: deps.favorite_article(token, &slug, true).await?,
+            article: deps.favorite_article(token, &slug, true).await?.into(),
+        }))
+    }
+
+    async fn set_favorites(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Json(batch): Json<article::FavoritesBatch>,
+    ) -> RwResult<Json<FavoriteResultsBody>> {
+        Ok(Json(FavoriteResultsBody {
+            results: deps.set_favorites(token, batch).await?,
         }))
     }
 
     async fn unfavorite_article(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
+        Path(slug): Path<article::Slug>,
     ) -> RwResult<Json<ArticleBody>> {
         Ok(Json(ArticleBody {
-            article: deps.favorite_article(token, &slug, false).await?,
+            article: deps.favorite_article(token, &slug, false).await?.into(),
         }))
     }
 
-    async fn list_comments(
+    async fn bookmark_article(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(slug): Path<article::Slug>,
+    ) -> RwResult<Json<ArticleBody>> {
+        Ok(Json(ArticleBody {
+            article: deps.bookmark_article(token, &slug, true).await?.into(),
+        }))
+    }
+
+    async fn unbookmark_article(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(slug): Path<article::Slug>,
+    ) -> RwResult<Json<ArticleBody>> {
+        Ok(Json(ArticleBody {
+            article: deps.bookmark_article(token, &slug, false).await?.into(),
+        }))
+    }
+
+    async fn list_favoriters(
         Extension(deps): Extension<D>,
         token: Option<Token>,
-        Path(slug): Path<String>,
-    ) -> RwResult<Json<MultipleCommentsBody>> {
-        Ok(Json(MultipleCommentsBody {
-            comments: deps.list_comments(token, &slug).await?,
+        Path(slug): Path<article::Slug>,
+        Query(pagination): Query<Pagination>,
+    ) -> RwResult<Json<MultipleProfilesBody>> {
+        Ok(Json(MultipleProfilesBody {
+            profiles: deps.list_favoriters(token, &slug, pagination).await?,
         }))
     }
 
+    async fn export_article(
+        Extension(deps): Extension<D>,
+        Path(slug): Path<article::Slug>,
+        Query(query): Query<ExportQuery>,
+    ) -> RwResult<Response> {
+        crate::export::article_response(deps.export_article(&slug).await?, query.format)
+    }
+
+    async fn list_comments(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(slug): Path<article::Slug>,
+        Query(query): Query<comment::ListCommentsQuery>,
+    ) -> RwResult<impl IntoResponse> {
+        let list = deps.list_comments(token, &slug, query).await?;
+        Ok((
+            [(super::TOTAL_COUNT, list.total_count)],
+            Json(MultipleCommentsBody {
+                comments: list.comments,
+            }),
+        ))
+    }
+
     async fn add_comment(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
+        Path(slug): Path<article::Slug>,
         Json(CommentBody { comment }): Json<CommentBody<AddComment>>,
     ) -> RwResult<Json<CommentBody>> {
         Ok(Json(CommentBody {
@@ -164,29 +409,112 @@ where
         }))
     }
 
+    /// Server-sent `comment` events, one for each comment posted from now on
+    async fn stream_comments(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(slug): Path<article::Slug>,
+    ) -> RwResult<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+        let comments = deps.watch_comments(token, &slug).await?;
+
+        Ok(Sse::new(comments.map(|comment| {
+            Event::default()
+                .event("comment")
+                .json_data(CommentBody { comment })
+        }))
+        .keep_alive(KeepAlive::default()))
+    }
+
     async fn delete_comment(
         Extension(deps): Extension<D>,
         token: Token,
-        Path(slug): Path<String>,
-        Path(comment_id): Path<i64>,
+        Path((slug, comment_id)): Path<(article::Slug, comment::CommentId)>,
     ) -> RwResult<()> {
         deps.delete_comment(token, &slug, comment_id).await?;
         Ok(())
     }
+
+    async fn like_comment(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path((slug, comment_id)): Path<(article::Slug, comment::CommentId)>,
+    ) -> RwResult<Json<CommentBody>> {
+        Ok(Json(CommentBody {
+            comment: deps.like_comment(token, &slug, comment_id, true).await?,
+        }))
+    }
+
+    async fn unlike_comment(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path((slug, comment_id)): Path<(article::Slug, comment::CommentId)>,
+    ) -> RwResult<Json<CommentBody>> {
+        Ok(Json(CommentBody {
+            comment: deps.like_comment(token, &slug, comment_id, false).await?,
+        }))
+    }
+}
+
+/// The article with its ETag, or just the ETag if the client has this version
+fn article_response(article: article::Article, headers: &HeaderMap) -> Response {
+    let etag = article.etag();
+    let response = match headers.typed_get::<IfNoneMatch>() {
+        Some(if_none_match) if !if_none_match.precondition_passes(&etag) => {
+            StatusCode::NOT_MODIFIED.into_response()
+        }
+        _ => Json(ArticleBody {
+            article: dto::Article::from(article),
+        })
+        .into_response(),
+    };
+    with_etag(response, etag)
+}
+
+fn with_etag(mut response: Response, etag: headers::ETag) -> Response {
+    response.headers_mut().typed_insert(etag);
+    response
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_util::*;
+    use realworld_domain::article::Slug;
+    use realworld_domain::comment::CommentId;
+    use realworld_domain::error::RwError;
 
-    use axum::http::{Request, StatusCode};
+    use axum::http::Request;
     use unimock::*;
 
     fn test_router(deps: Unimock) -> axum::Router {
         ArticleRoutes::<Unimock>::router().layer(Extension(deps))
     }
 
+    fn test_article() -> article::Article {
+        serde_json::from_value(serde_json::json!({
+            "id": "0000000000000000000001",
+            "slug": "slug",
+            "title": "title",
+            "description": "description",
+            "body": "body",
+            "tag_list": [],
+            "created_at": "2019-10-12T07:20:50.52Z",
+            "updated_at": "2019-10-12T07:20:50.52Z",
+            "favorited": false,
+            "favorites_count": 0,
+            "views_count": 0,
+            "word_count": 1,
+            "reading_time_minutes": 1,
+            "author": {
+                "username": "author",
+                "bio": "bio",
+                "image": null,
+                "following": false
+            }
+        }))
+        .unwrap()
+    }
+
     #[tokio::test]
     async fn list_articles_should_accept_no_auth() {
         let deps = Unimock::new(
@@ -194,7 +522,7 @@ mod tests {
                 .next_call(matching! {
                     (None, query) if query == &article::ListArticlesQuery::default()
                 })
-                .returns(Ok(vec![])),
+                .returns(Ok(Default::default())),
         );
 
         let (status, body) = request_json::<MultipleArticlesBody>(
@@ -207,4 +535,585 @@ mod tests {
         assert_eq!(StatusCode::OK, status);
         assert!(body.articles.is_empty());
     }
+
+    #[tokio::test]
+    async fn list_articles_should_be_compressed_when_accepted() {
+        use tower::ServiceExt;
+
+        let deps = Unimock::new(
+            article::api::mock::list_articles
+                .each_call(matching!(_, _))
+                .answers(&|_, _, _| {
+                    Ok(article::ArticleList {
+                        articles: vec![test_article(); 10],
+                        next_cursor: None,
+                    })
+                }),
+        );
+
+        for encoding in ["gzip", "br"] {
+            let response = test_router(deps.clone())
+                .oneshot(
+                    Request::get("/articles")
+                        .header(axum::http::header::ACCEPT_ENCODING, encoding)
+                        .empty_body(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(
+                encoding,
+                response.headers()[axum::http::header::CONTENT_ENCODING]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn list_articles_should_only_have_the_given_fields() {
+        let deps = Unimock::new(
+            article::api::mock::list_articles
+                .next_call(matching! {
+                    (None, query) if query == &article::ListArticlesQuery::default()
+                })
+                .returns(Ok(article::ArticleList {
+                    articles: vec![test_article(); 2],
+                    next_cursor: None,
+                })),
+        );
+
+        let (status, body) = request_json::<serde_json::Value>(
+            test_router(deps.clone()),
+            Request::get("/articles?fields=slug,title,author").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        for article in body["articles"].as_array().unwrap() {
+            let mut names: Vec<&String> = article.as_object().unwrap().keys().collect();
+            names.sort();
+            assert_eq!(vec!["author", "slug", "title"], names);
+            assert_eq!("author", article["author"]["username"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_articles_should_reject_unknown_fields() {
+        let deps = Unimock::new(());
+        let (status, body) = request_json::<serde_json::Value>(
+            test_router(deps.clone()),
+            Request::get("/articles?fields=slug,tag_list").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        assert_eq!("VALIDATION_FAILED", body["code"]);
+    }
+
+    fn test_etag() -> axum::http::HeaderValue {
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(test_article().etag());
+        headers[axum::http::header::ETAG].clone()
+    }
+
+    #[tokio::test]
+    async fn get_article_should_not_be_modified_when_etag_matches() {
+        use tower::ServiceExt;
+
+        let deps = Unimock::new(
+            article::api::mock::fetch_article
+                .each_call(matching!(None, "slug", _, None))
+                .answers(&|_, _, _, _, _| Ok(test_article())),
+        );
+
+        let response = test_router(deps.clone())
+            .oneshot(
+                Request::get("/articles/slug")
+                    .header(axum::http::header::IF_NONE_MATCH, test_etag())
+                    .empty_body(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+        assert_eq!(test_etag(), response.headers()[axum::http::header::ETAG]);
+
+        let response = test_router(deps.clone())
+            .oneshot(
+                Request::get("/articles/slug")
+                    .header(axum::http::header::IF_NONE_MATCH, "\"other\"")
+                    .empty_body(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(test_etag(), response.headers()[axum::http::header::ETAG]);
+    }
+
+    #[tokio::test]
+    async fn renamed_article_should_redirect_to_current_slug() {
+        use tower::ServiceExt;
+
+        let deps = Unimock::new((
+            article::api::mock::fetch_article
+                .next_call(matching!(None, "old-slug", _, None))
+                .returns(Err(RwError::ArticleNotFound)),
+            article::api::mock::find_renamed_article
+                .next_call(matching!("old-slug"))
+                .returns(Ok(Some(Slug::from("new-slug")))),
+            article::api::mock::fetch_article
+                .next_call(matching!(None, "unknown", _, None))
+                .returns(Err(RwError::ArticleNotFound)),
+            article::api::mock::find_renamed_article
+                .next_call(matching!("unknown"))
+                .returns(Ok(None)),
+        ));
+
+        let response = test_router(deps.clone())
+            .oneshot(Request::get("/articles/old-slug?format=html").empty_body())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::PERMANENT_REDIRECT, response.status());
+        assert_eq!(
+            "/api/articles/new-slug?format=html",
+            response.headers()[axum::http::header::LOCATION]
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::get("/articles/unknown").empty_body(),
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[tokio::test]
+    async fn shared_article_should_be_fetched_by_its_token() {
+        let deps = Unimock::new(
+            article::api::mock::fetch_shared_article
+                .next_call(matching!(None, "share-token", _, None))
+                .answers(&|_, _, _, _, _| Ok(test_article())),
+        );
+
+        let (status, body) = request_json::<ArticleBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/shared/share-token").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(Slug::from("slug"), body.article.slug);
+    }
+
+    #[tokio::test]
+    async fn get_article_should_accept_html_format() {
+        use realworld_domain::article::markdown::BodyFormat;
+
+        let deps = Unimock::new(
+            article::api::mock::fetch_article
+                .next_call(matching!(
+                    None,
+                    "slug",
+                    article::FetchArticleQuery {
+                        format: BodyFormat::Html
+                    },
+                    _
+                ))
+                .answers(&|_, _, _, _, _| Ok(test_article())),
+        );
+
+        let (status, _) = request_json::<ArticleBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/slug?format=html").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn favorites_batch_should_report_result_per_slug() {
+        use article::{FavoriteResult, FavoriteStatus};
+
+        let deps = Unimock::new(
+            article::api::mock::set_favorites
+                .next_call(matching!(
+                    (_, batch) if batch.slugs == [Slug::from("a"), Slug::from("unknown")] && batch.favorited
+                ))
+                .returns(Ok(vec![
+                    FavoriteResult {
+                        slug: Slug::from("a"),
+                        status: FavoriteStatus::Favorited,
+                    },
+                    FavoriteResult {
+                        slug: Slug::from("unknown"),
+                        status: FavoriteStatus::NotFound,
+                    },
+                ])),
+        );
+
+        let (status, body) = request(
+            test_router(deps.clone()),
+            Request::post("/articles/favorites/batch")
+                .header(axum::http::header::AUTHORIZATION, "Token t")
+                .with_json_body(serde_json::json!({
+                    "slugs": ["a", "unknown"],
+                    "favorited": true
+                })),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            serde_json::json!({
+                "results": [
+                    { "slug": "a", "status": "favorited" },
+                    { "slug": "unknown", "status": "notFound" }
+                ]
+            }),
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn update_article_should_pass_if_match_on() {
+        let deps = Unimock::new(
+            article::api::mock::update_article
+                .next_call(matching!((_, "slug", _, Some(if_match)) if *if_match == IfMatch::from(test_article().etag())))
+                .answers(&|_, _, _, _, _| Ok(test_article())),
+        );
+
+        let (status, _) = request_json::<ArticleBody>(
+            test_router(deps.clone()),
+            Request::put("/articles/slug")
+                .header(axum::http::header::AUTHORIZATION, "Token t")
+                .header(axum::http::header::IF_MATCH, test_etag())
+                .with_json_body(serde_json::json!({ "article": {} })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    fn test_cursor() -> article::cursor::ArticleCursor {
+        article::cursor::ArticleCursor {
+            created_at: realworld_domain::timestamp::Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            slug: Slug::from("slug"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_articles_should_pass_cursor_on() {
+        let deps = Unimock::new(
+            article::api::mock::list_articles
+                .next_call(matching! {
+                    (None, query) if query == &serde_json::from_value::<article::ListArticlesQuery>(
+                        serde_json::json!({ "after": test_cursor() })
+                    ).unwrap()
+                })
+                .returns(Ok(article::ArticleList {
+                    articles: vec![],
+                    next_cursor: Some(test_cursor()),
+                })),
+        );
+        let encoded = serde_json::to_value(test_cursor()).unwrap();
+
+        let (status, body) = request_json::<serde_json::Value>(
+            test_router(deps.clone()),
+            Request::get(format!("/articles?after={}", encoded.as_str().unwrap())).empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(encoded, body["nextCursor"]);
+    }
+
+    #[tokio::test]
+    async fn list_articles_should_reject_malformed_cursor() {
+        let deps = Unimock::new(());
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::get("/articles?after=nope").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+    }
+
+    #[tokio::test]
+    async fn list_favoriters_should_accept_pagination_query() {
+        let deps = Unimock::new(
+            article::api::mock::list_favoriters
+                .next_call(matching! {
+                    (None, slug, Pagination { limit: Some(5), offset: Some(10) }) if slug.as_str() == "slug"
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request_json::<MultipleProfilesBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/slug/favoriters?limit=5&offset=10").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.profiles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn trending_articles_should_not_be_taken_for_a_slug() {
+        let deps = Unimock::new(
+            article::api::mock::list_trending_articles
+                .next_call(matching! {
+                    (None, Pagination { limit: Some(3), offset: None })
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request_json::<MultipleArticlesBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/trending?limit=3").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn recommended_articles_should_require_authentication() {
+        let deps = Unimock::new(
+            article::api::mock::list_recommended_articles
+                .next_call(matching! {
+                    (_, Pagination { limit: None, offset: Some(20) })
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::get("/articles/recommended").empty_body(),
+        )
+        .await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let (status, body) = request_json::<MultipleArticlesBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/recommended?offset=20")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn global_rss_feed_should_not_be_taken_for_a_slug() {
+        use realworld_domain::rss::GlobalRssFeedMock;
+        use tower::ServiceExt;
+
+        let deps = Unimock::new(
+            GlobalRssFeedMock
+                .next_call(matching!())
+                .answers(&|_| Ok(RssFeed("<rss/>".to_string()))),
+        );
+
+        let response = test_router(deps.clone())
+            .oneshot(Request::get("/articles/feed.rss").empty_body())
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "application/rss+xml; charset=utf-8",
+            response.headers()[axum::http::header::CONTENT_TYPE]
+        );
+    }
+
+    #[tokio::test]
+    async fn feed_should_not_be_taken_for_a_slug() {
+        let deps = Unimock::new(
+            article::api::mock::feed_articles
+                .next_call(matching!(_, _))
+                .returns(Ok(article::ArticleList::default())),
+        );
+
+        let (status, body) = request_json::<MultipleArticlesBody>(
+            test_router(deps.clone()),
+            Request::get("/articles/feed")
+                .header(axum::http::header::AUTHORIZATION, "Token t")
+                .empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn articles_titled_like_routes_should_be_fetched_by_their_slug() {
+        use realworld_domain::article::slugify::{slugify, RESERVED_SLUGS};
+
+        let deps = Unimock::new(
+            article::api::mock::fetch_article
+                .each_call(matching!(None, _, _, None))
+                .answers(&|_, _, slug, _, _| {
+                    Ok(article::Article {
+                        slug: slug.clone(),
+                        ..test_article()
+                    })
+                }),
+        );
+
+        for title in RESERVED_SLUGS {
+            let slug = slugify(Default::default(), title);
+            assert_ne!(title, &slug.as_str());
+
+            let (status, body) = request_json::<ArticleBody>(
+                test_router(deps.clone()),
+                Request::get(format!("/articles/{slug}")).empty_body(),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(StatusCode::OK, status, "{title}");
+            assert_eq!(slug, body.article.slug);
+        }
+    }
+
+    #[tokio::test]
+    async fn bookmarked_articles_should_require_auth() {
+        let deps = Unimock::new(());
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::get("/articles/bookmarked").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+    }
+
+    #[tokio::test]
+    async fn list_comments_should_accept_pagination_query() {
+        use tower::ServiceExt;
+
+        let deps = Unimock::new(
+            comment::api::mock::list_comments
+                .next_call(matching! {
+                    (None, "slug", query) if query == &serde_json::from_value::<comment::ListCommentsQuery>(
+                        serde_json::json!({ "limit": 5, "offset": 10, "order": "desc" })
+                    ).unwrap()
+                })
+                .returns(Ok(comment::CommentList {
+                    comments: vec![],
+                    total_count: 12,
+                })),
+        );
+
+        let response = test_router(deps.clone())
+            .oneshot(
+                Request::get("/articles/slug/comments?limit=5&offset=10&order=desc").empty_body(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("12", response.headers()[crate::routes::TOTAL_COUNT]);
+    }
+
+    #[tokio::test]
+    async fn export_article_should_respond_with_zip_when_asking_for_markdown() {
+        use realworld_domain::export::*;
+        use realworld_domain::timestamp::Timestamptz;
+
+        let deps = Unimock::new(ExportArticleMock.next_call(matching!("slug")).answers(
+            &|_, slug| {
+                Ok(ArticleExport {
+                    article: ExportedArticle {
+                        slug: slug.clone(),
+                        title: "title".to_string(),
+                        description: "desc".to_string(),
+                        body: "body".to_string(),
+                        tag_list: vec![],
+                        author: "author".to_string(),
+                        created_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+                        updated_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+                        favorites_count: 0,
+                    },
+                    comments: vec![],
+                })
+            },
+        ));
+
+        let (status, body) = request(
+            test_router(deps.clone()),
+            Request::get("/articles/slug/export?format=markdown").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.starts_with(b"PK"));
+    }
+
+    #[tokio::test]
+    async fn like_comment_should_take_slug_and_comment_id() {
+        let deps = Unimock::new(
+            comment::api::mock::like_comment
+                .next_call(matching!(_, "slug", CommentId(42), true))
+                .returns(Err(RwError::ArticleNotFound)),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::post("/articles/slug/comments/42/like")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[tokio::test]
+    async fn stream_comments_should_send_events() {
+        let deps = Unimock::new(
+            comment::api::mock::watch_comments
+                .next_call(matching!(None, "slug"))
+                .answers(&|_, _, _| {
+                    let comment: comment::Comment = serde_json::from_value(serde_json::json!({
+                        "id": 1,
+                        "createdAt": "2019-10-12T07:20:50.52Z",
+                        "updatedAt": "2019-10-12T07:20:50.52Z",
+                        "body": "body",
+                        "author": {
+                            "username": "author",
+                            "bio": "bio",
+                            "image": null,
+                            "following": false
+                        },
+                        "likesCount": 0,
+                        "liked": false
+                    }))
+                    .unwrap();
+                    Ok(futures::stream::iter([comment]).boxed())
+                }),
+        );
+
+        let (status, body) = request(
+            test_router(deps.clone()),
+            Request::get("/articles/slug/comments/stream").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.starts_with("event: comment\ndata: {\"comment\":{\"id\":1,"));
+    }
 }
@@ -0,0 +1,155 @@
+//!
+//! The JSON form of domain types in the API, so the wire format can change without the domain.
+//!
+
+use super::sparse::Fieldset;
+
+use realworld_domain::article::{self, Slug, Visibility};
+use realworld_domain::timestamp::Timestamptz;
+use realworld_domain::user::profile::Profile;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+// The Realworld spec doesn't mention this as an API convention, it just finally shows up
+// when you're looking at the spec for the Article object and see `tagList` as a field name.
+#[serde(rename_all = "camelCase")]
+pub struct Article {
+    pub id: String,
+    pub slug: Slug,
+    pub title: String,
+    pub description: String,
+    /// Left out of summaries, like the counts that depend on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    pub tag_list: Vec<String>,
+    pub created_at: Timestamptz,
+    pub updated_at: Timestamptz,
+    pub favorited: bool,
+    pub favorites_count: i64,
+    pub views_count: i64,
+    pub author: Profile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reading_time_minutes: Option<u32>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Of the share link of an unlisted article, for its author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_token: Option<String>,
+}
+
+impl From<article::Article> for Article {
+    fn from(article: article::Article) -> Self {
+        Self {
+            id: article.id,
+            slug: article.slug,
+            title: article.title,
+            description: article.description,
+            body: article.body,
+            tag_list: article.tag_list,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+            favorited: article.favorited,
+            favorites_count: article.favorites_count,
+            views_count: article.views_count,
+            author: article.author,
+            word_count: article.word_count,
+            reading_time_minutes: article.reading_time_minutes,
+            visibility: article.visibility,
+            share_token: article.share_token,
+        }
+    }
+}
+
+impl Fieldset for Article {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "slug",
+        "title",
+        "description",
+        "body",
+        "tagList",
+        "createdAt",
+        "updatedAt",
+        "favorited",
+        "favoritesCount",
+        "viewsCount",
+        "author",
+        "wordCount",
+        "readingTimeMinutes",
+        "visibility",
+        "shareToken",
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_should_leave_out_body_and_counts() {
+        let timestamp = Timestamptz(time::OffsetDateTime::UNIX_EPOCH);
+        let article = Article::from(article::Article {
+            id: "0000000000000000000001".to_string(),
+            slug: Slug::from("slug"),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            body: None,
+            tag_list: vec!["tag".to_string()],
+            created_at: timestamp.clone(),
+            updated_at: timestamp,
+            favorited: false,
+            favorites_count: 2,
+            views_count: 3,
+            author: Profile::default(),
+            word_count: None,
+            reading_time_minutes: None,
+            visibility: Visibility::Public,
+            share_token: None,
+        });
+
+        let json = serde_json::to_value(article).unwrap();
+        assert_eq!(Some("description"), json["description"].as_str());
+        assert_eq!(Some(2), json["favoritesCount"].as_i64());
+        assert_eq!(Some("tag"), json["tagList"][0].as_str());
+        assert!(json.get("body").is_none());
+        assert!(json.get("wordCount").is_none());
+        assert!(json.get("readingTimeMinutes").is_none());
+        assert_eq!(Some("public"), json["visibility"].as_str());
+        assert!(json.get("shareToken").is_none());
+    }
+
+    #[test]
+    fn fieldset_should_name_every_field() {
+        let article = Article::from(article::Article {
+            id: "0000000000000000000001".to_string(),
+            slug: Slug::from("slug"),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            body: Some("body".to_string()),
+            tag_list: vec![],
+            created_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            updated_at: Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            favorited: false,
+            favorites_count: 0,
+            views_count: 0,
+            author: Profile::default(),
+            word_count: Some(1),
+            reading_time_minutes: Some(1),
+            visibility: Visibility::Unlisted,
+            share_token: Some("token".to_string()),
+        });
+
+        let json = serde_json::to_value(article).unwrap();
+        let mut names: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut fields = Article::FIELDS.to_vec();
+        names.sort();
+        fields.sort();
+        assert_eq!(fields, names);
+    }
+}
@@ -0,0 +1,64 @@
+//!
+//! `GET /api/errors` documents the `code` that error responses may have, along with its HTTP status.
+//! It's generated from [ErrorCode], so it's never out of date.
+//!
+
+use realworld_domain::error::ErrorCode;
+
+use axum::routing::get;
+use axum::Json;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorCodesBody {
+    error_codes: Vec<ErrorCodeDoc>,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorCodeDoc {
+    code: ErrorCode,
+    status: u16,
+    description: &'static str,
+}
+
+pub fn router() -> axum::Router {
+    axum::Router::new().route("/errors", get(|| async { error_codes() }))
+}
+
+fn error_codes() -> Json<ErrorCodesBody> {
+    Json(ErrorCodesBody {
+        error_codes: ErrorCode::ALL
+            .into_iter()
+            .map(|code| ErrorCodeDoc {
+                code,
+                status: code.status_code().as_u16(),
+                description: code.description(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::{Request, StatusCode};
+
+    #[tokio::test]
+    async fn should_list_every_error_code_with_its_status() {
+        let (status, body) =
+            request_json::<serde_json::Value>(router(), Request::get("/errors").empty_body())
+                .await
+                .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        let error_codes = body["errorCodes"].as_array().unwrap();
+        assert_eq!(ErrorCode::ALL.len(), error_codes.len());
+        assert!(error_codes.contains(&serde_json::json!({
+            "code": "ARTICLE_NOT_FOUND",
+            "status": 404,
+            "description": "No article has the slug",
+        })));
+    }
+}
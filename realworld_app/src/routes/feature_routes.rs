@@ -0,0 +1,32 @@
+//!
+//! `GET /api/features` lists the `feature_flags` that are switched on, for clients to show or hide
+//! features by. They can be switched while serving, see `live_config.rs`.
+//!
+
+use crate::app::App;
+
+use axum::extract::Extension;
+use axum::routing::get;
+use axum::Json;
+use entrait::Impl;
+
+#[derive(serde::Serialize)]
+struct FeaturesBody {
+    features: Vec<String>,
+}
+
+pub fn router() -> axum::Router {
+    axum::Router::new().route("/features", get(list_features))
+}
+
+async fn list_features(Extension(app): Extension<Impl<App>>) -> Json<FeaturesBody> {
+    Json(FeaturesBody {
+        features: app
+            .live_config
+            .load()
+            .feature_flags
+            .iter()
+            .cloned()
+            .collect(),
+    })
+}
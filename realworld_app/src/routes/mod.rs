@@ -1,19 +1,113 @@
+mod admin_routes;
 mod article_routes;
+mod dto;
+mod error_routes;
+mod feature_routes;
+mod notification_routes;
 mod profile_routes;
+mod report_routes;
+mod sitemap_routes;
+mod sparse;
 mod user_routes;
 
 use crate::app::App;
+use crate::body_limit;
+use crate::cache_control::{CacheControlLayer, CachePolicy};
+use crate::circuit_breaker;
+use crate::config::Config;
+use crate::cors;
+use crate::error_format;
+use crate::live_config::{self, LiveConfig};
+use crate::logging;
+use crate::rate_limit::{RateLimitLayer, RateLimiter};
+use crate::request_log::{self, RequestLog};
+use crate::state::SharedState;
+use crate::tenant;
+use crate::timeout::{self, TimeoutBudgets};
 
+use axum::extract::DefaultBodyLimit;
+use axum::http::HeaderName;
 use axum::routing::Router;
 use entrait::Impl;
 
-/// Axum API router for the real app.
-pub fn api_router() -> axum::Router {
-    Router::new().nest(
-        "/api",
-        Router::new()
-            .merge(user_routes::UserRoutes::<Impl<App>>::router())
-            .merge(profile_routes::ProfileRoutes::<Impl<App>>::router())
-            .merge(article_routes::ArticleRoutes::<Impl<App>>::router()),
-    )
+/// Items of a paginated listing on all pages
+pub const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Path of an API resource, e.g. for a `Location` header, with each segment percent-encoded
+fn api_path(segments: &[&str]) -> String {
+    let mut url = url::Url::parse("http://localhost/api").unwrap();
+    url.path_segments_mut().unwrap().extend(segments);
+    url.path().to_string()
+}
+
+///
+/// Axum API router for the real app, with what `extend` adds to the routes under `/api`,
+/// e.g. routes of an embedding server, see [crate::AppBuilder::routes].
+///
+/// Those get the same handling as the routes of the app: they're rate limited, time out,
+/// run for the tenant of the request and have their errors formatted.
+/// The app is available to handlers as `Extension<Impl<App>>`.
+///
+pub fn api_router(
+    config: &Config,
+    live_config: &LiveConfig,
+    shared: &SharedState,
+    extend: impl FnOnce(Router) -> Router,
+) -> anyhow::Result<axum::Router> {
+    let api = Router::new()
+        .merge(user_routes::UserRoutes::<Impl<App>>::router())
+        .merge(
+            profile_routes::ProfileRoutes::<Impl<App>>::router()
+                .layer(CacheControlLayer::new(CachePolicy::profiles(config))),
+        )
+        .merge(
+            article_routes::ArticleRoutes::<Impl<App>>::router()
+                .layer(CacheControlLayer::new(CachePolicy::articles(config))),
+        )
+        .merge(notification_routes::NotificationRoutes::<Impl<App>>::router())
+        .merge(admin_routes::AdminRoutes::<Impl<App>>::router())
+        .merge(report_routes::ReportRoutes::<Impl<App>>::router())
+        .merge(error_routes::router())
+        .merge(feature_routes::router());
+
+    Ok(Router::new()
+        .nest(
+            "/api",
+            extend(api)
+                .route_layer(axum::middleware::from_fn(logging::handler_span))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    RequestLog::from_config(config),
+                    request_log::log_request,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    TimeoutBudgets::from_config(config),
+                    timeout::enforce_timeout,
+                ))
+                // Outside of the timeout, so that timeouts count as failures
+                .layer(axum::middleware::from_fn(circuit_breaker::short_circuit))
+                .layer(axum::middleware::from_fn_with_state(
+                    live_config.clone(),
+                    live_config::reject_in_maintenance,
+                ))
+                .layer(RateLimitLayer::<Impl<App>>::new(
+                    RateLimiter::with_shared_state(live_config.clone(), shared),
+                ))
+                .layer(axum::middleware::from_fn(tenant::select_tenant)),
+        )
+        .merge(
+            sitemap_routes::SitemapRoutes::<Impl<App>>::router()
+                .layer(axum::middleware::from_fn(tenant::select_tenant)),
+        )
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .layer(axum::middleware::from_fn_with_state(
+            config.max_body_bytes,
+            body_limit::reject_large_bodies,
+        ))
+        // Outside of every layer that can respond with an error
+        .layer(axum::middleware::from_fn_with_state(
+            config.error_format.into(),
+            error_format::format_errors,
+        ))
+        // Outermost, so that preflight requests aren't rate limited
+        .layer(cors::layer(config)?))
 }
@@ -0,0 +1,104 @@
+use realworld_domain::error::RwResult;
+use realworld_domain::notification;
+use realworld_domain::user::auth::Token;
+
+use axum::extract::{Extension, Path, Query};
+use axum::routing::{get, post};
+use axum::Json;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MultipleNotificationsBody {
+    notifications: Vec<notification::Notification>,
+}
+
+pub struct NotificationRoutes<D>(std::marker::PhantomData<D>);
+
+impl<D> NotificationRoutes<D>
+where
+    D: notification::Api + Sized + Clone + Send + Sync + 'static,
+{
+    pub fn router() -> axum::Router {
+        axum::Router::new()
+            .route("/notifications", get(Self::list_notifications))
+            .route(
+                "/notifications/:id/read",
+                post(Self::mark_notification_read),
+            )
+    }
+
+    async fn list_notifications(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(query): Query<notification::ListNotificationsQuery>,
+    ) -> RwResult<Json<MultipleNotificationsBody>> {
+        Ok(Json(MultipleNotificationsBody {
+            notifications: deps.list_notifications(token, query).await?,
+        }))
+    }
+
+    async fn mark_notification_read(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(notification_id): Path<i64>,
+    ) -> RwResult<()> {
+        deps.mark_notification_read(token, notification_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use realworld_domain::error::RwError;
+
+    use axum::http::{Request, StatusCode};
+    use unimock::*;
+
+    fn test_router(deps: Unimock) -> axum::Router {
+        NotificationRoutes::<Unimock>::router().layer(Extension(deps))
+    }
+
+    #[tokio::test]
+    async fn list_notifications_should_accept_unread_only_query() {
+        let deps = Unimock::new(
+            notification::api::mock::list_notifications
+                .next_call(matching! {
+                    (_, query) if query == &serde_json::from_value::<notification::ListNotificationsQuery>(
+                        serde_json::json!({ "limit": 5, "unreadOnly": true })
+                    ).unwrap()
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request_json::<MultipleNotificationsBody>(
+            test_router(deps.clone()),
+            Request::get("/notifications?limit=5&unreadOnly=true")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.notifications.is_empty());
+    }
+
+    #[tokio::test]
+    async fn mark_unknown_notification_read_should_be_not_found() {
+        let deps = Unimock::new(
+            notification::api::mock::mark_notification_read
+                .next_call(matching!(_, 42))
+                .returns(Err(RwError::NotificationNotFound)),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::post("/notifications/42/read")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+}
@@ -1,8 +1,13 @@
 use realworld_domain::error::RwResult;
+use realworld_domain::pagination::Pagination;
+use realworld_domain::recommendation::SuggestProfiles;
+use realworld_domain::rss::{AuthorRssFeed, RssFeed};
 use realworld_domain::user;
 use realworld_domain::user::auth::Token;
 
-use axum::extract::{Extension, Path};
+use axum::extract::{Extension, Path, Query};
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::Json;
 
@@ -11,31 +16,91 @@ struct ProfileBody {
     profile: user::profile::Profile,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct MultipleProfilesBody {
+    profiles: Vec<user::profile::Profile>,
+}
+
 pub struct ProfileRoutes<D>(std::marker::PhantomData<D>);
 
 impl<D> ProfileRoutes<D>
 where
-    D: user::FetchProfile + user::Follow + Sized + Clone + Send + Sync + 'static,
+    D: user::FetchProfile
+        + user::SearchProfiles
+        + user::Follow
+        + user::Block
+        + user::ListFollowers
+        + user::ListFollowing
+        + AuthorRssFeed
+        + SuggestProfiles
+        + Sized
+        + Clone
+        + Send
+        + Sync
+        + 'static,
 {
     pub fn router() -> axum::Router {
         axum::Router::new()
+            .route("/profiles", get(Self::search_profiles))
+            // Takes precedence over a user named `suggestions`, which is a reserved username
+            .route("/profiles/suggestions", get(Self::suggest_profiles))
             .route("/profiles/:username", get(Self::get_user_profile))
             .route(
                 "/profiles/:username/follow",
                 post(Self::follow_user).delete(Self::unfollow_user),
             )
+            .route(
+                "/profiles/:username/block",
+                post(Self::block_user).delete(Self::unblock_user),
+            )
+            .route("/profiles/:username/followers", get(Self::list_followers))
+            .route("/profiles/:username/following", get(Self::list_following))
+            .route("/profiles/:username/feed.rss", get(Self::author_rss_feed))
     }
 
     async fn get_user_profile(
         Extension(deps): Extension<D>,
         token: Option<Token>,
         Path(username): Path<String>,
-    ) -> RwResult<Json<ProfileBody>> {
-        Ok(Json(ProfileBody {
-            profile: deps.fetch_profile(token, &username).await?,
+    ) -> RwResult<Response> {
+        let profile = deps.fetch_profile(token, &username).await?;
+        let canonical = (profile.username.to_lowercase() != username.to_lowercase())
+            .then(|| canonical_link(&profile.username));
+
+        let mut response = Json(ProfileBody { profile }).into_response();
+        // The user was found by a past username, so point the client to the current one
+        if let Some(link) = canonical {
+            response.headers_mut().insert(header::LINK, link);
+        }
+        Ok(response)
+    }
+
+    async fn search_profiles(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Query(query): Query<user::SearchProfilesQuery>,
+    ) -> RwResult<Json<MultipleProfilesBody>> {
+        Ok(Json(MultipleProfilesBody {
+            profiles: deps.search_profiles(token, query).await?,
+        }))
+    }
+
+    async fn suggest_profiles(
+        Extension(deps): Extension<D>,
+        token: Token,
+    ) -> RwResult<Json<MultipleProfilesBody>> {
+        Ok(Json(MultipleProfilesBody {
+            profiles: deps.suggest_profiles(token).await?,
         }))
     }
 
+    async fn author_rss_feed(
+        Extension(deps): Extension<D>,
+        Path(username): Path<String>,
+    ) -> RwResult<RssFeed> {
+        deps.author_rss_feed(&username).await
+    }
+
     async fn follow_user(
         Extension(deps): Extension<D>,
         token: Token,
@@ -55,4 +120,202 @@ where
             profile: deps.follow(token, &username, false).await?,
         }))
     }
+
+    async fn block_user(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(username): Path<String>,
+    ) -> RwResult<Json<ProfileBody>> {
+        Ok(Json(ProfileBody {
+            profile: deps.block(token, &username, true).await?,
+        }))
+    }
+
+    async fn unblock_user(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(username): Path<String>,
+    ) -> RwResult<Json<ProfileBody>> {
+        Ok(Json(ProfileBody {
+            profile: deps.block(token, &username, false).await?,
+        }))
+    }
+
+    async fn list_followers(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(username): Path<String>,
+        Query(pagination): Query<Pagination>,
+    ) -> RwResult<Json<MultipleProfilesBody>> {
+        Ok(Json(MultipleProfilesBody {
+            profiles: deps.list_followers(token, &username, pagination).await?,
+        }))
+    }
+
+    async fn list_following(
+        Extension(deps): Extension<D>,
+        token: Option<Token>,
+        Path(username): Path<String>,
+        Query(pagination): Query<Pagination>,
+    ) -> RwResult<Json<MultipleProfilesBody>> {
+        Ok(Json(MultipleProfilesBody {
+            profiles: deps.list_following(token, &username, pagination).await?,
+        }))
+    }
+}
+
+fn canonical_link(username: &str) -> HeaderValue {
+    let path = super::api_path(&["profiles", username]);
+    HeaderValue::from_str(&format!("<{path}>; rel=\"canonical\"")).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use realworld_domain::error::RwError;
+
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use unimock::*;
+
+    fn test_router(deps: Unimock) -> axum::Router {
+        ProfileRoutes::<Unimock>::router().layer(Extension(deps))
+    }
+
+    #[tokio::test]
+    async fn list_following_should_accept_pagination_query() {
+        let deps = Unimock::new(
+            user::ListFollowingMock
+                .next_call(matching!(
+                    None,
+                    "someone",
+                    Pagination {
+                        limit: Some(5),
+                        offset: Some(10)
+                    }
+                ))
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request_json::<MultipleProfilesBody>(
+            test_router(deps.clone()),
+            Request::get("/profiles/someone/following?limit=5&offset=10").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.profiles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_should_accept_query_and_pagination() {
+        let deps = Unimock::new(
+            user::SearchProfilesMock
+                .next_call(matching!(
+                    (
+                        None,
+                        user::SearchProfilesQuery {
+                            query,
+                            limit: Some(5),
+                            offset: None
+                        }
+                    ) if query == "jake smith"
+                ))
+                .returns(Ok(vec![test_profile("jake")])),
+        );
+
+        let (status, body) = request_json::<MultipleProfilesBody>(
+            test_router(deps.clone()),
+            Request::get("/profiles?query=jake+smith&limit=5").empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("jake", body.profiles[0].username);
+    }
+
+    #[tokio::test]
+    async fn suggestions_should_not_be_taken_for_a_profile() {
+        use realworld_domain::recommendation::SuggestProfilesMock;
+
+        let deps = Unimock::new(
+            SuggestProfilesMock
+                .next_call(matching!(_))
+                .returns(Ok(vec![test_profile("author")])),
+        );
+
+        let (status, body) = request_json::<MultipleProfilesBody>(
+            test_router(deps.clone()),
+            Request::get("/profiles/suggestions")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("author", body.profiles[0].username);
+    }
+
+    fn test_profile(username: &str) -> user::profile::Profile {
+        user::profile::Profile {
+            username: username.to_string(),
+            bio: String::new(),
+            image: None,
+            following: false,
+            followers_count: None,
+            following_count: None,
+            follows_you: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn profile_found_by_past_username_should_link_to_canonical_profile() {
+        let deps = Unimock::new((
+            user::FetchProfileMock
+                .next_call(matching!(None, "jake"))
+                .returns(Ok(test_profile("jacob"))),
+            user::FetchProfileMock
+                .next_call(matching!(None, "Jacob"))
+                .returns(Ok(test_profile("jacob"))),
+        ));
+
+        let response = test_router(deps.clone())
+            .oneshot(Request::get("/profiles/jake").empty_body())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "</api/profiles/jacob>; rel=\"canonical\"",
+            response.headers()[header::LINK]
+        );
+
+        let response = test_router(deps.clone())
+            .oneshot(Request::get("/profiles/Jacob").empty_body())
+            .await
+            .unwrap();
+        assert!(response.headers().get(header::LINK).is_none());
+    }
+
+    #[tokio::test]
+    async fn unblocking_unknown_user_should_not_be_found() {
+        let deps = Unimock::new(
+            user::BlockMock
+                .next_call(matching!(_, "unknown", false))
+                .returns(Err(RwError::ProfileNotFound)),
+        );
+
+        let (status, _) = request(
+            test_router(deps.clone()),
+            Request::delete("/profiles/unknown/block")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
 }
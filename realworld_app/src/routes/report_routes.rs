@@ -0,0 +1,150 @@
+use realworld_domain::article::Slug;
+use realworld_domain::comment::CommentId;
+use realworld_domain::error::RwResult;
+use realworld_domain::report;
+use realworld_domain::user::auth::Token;
+
+use axum::extract::{Extension, Path, Query};
+use axum::routing::{get, post};
+use axum::Json;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ReportBody<T = report::Report> {
+    report: T,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MultipleReportsBody {
+    reports: Vec<report::Report>,
+}
+
+///
+/// Reporting articles and comments, and handling the reports under `/admin`.
+///
+pub struct ReportRoutes<D>(std::marker::PhantomData<D>);
+
+impl<D> ReportRoutes<D>
+where
+    D: report::Api + Sized + Clone + Send + Sync + 'static,
+{
+    pub fn router() -> axum::Router {
+        axum::Router::new()
+            .route("/articles/:slug/report", post(Self::report_article))
+            .route(
+                "/articles/:slug/comments/:comment_id/report",
+                post(Self::report_comment),
+            )
+            .route("/admin/reports", get(Self::list_reports))
+            .route("/admin/reports/:id/resolve", post(Self::resolve_report))
+    }
+
+    async fn report_article(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(slug): Path<Slug>,
+        Json(body): Json<ReportBody<report::NewReport>>,
+    ) -> RwResult<()> {
+        deps.report_article(token, &slug, body.report).await
+    }
+
+    async fn report_comment(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path((slug, comment_id)): Path<(Slug, CommentId)>,
+        Json(body): Json<ReportBody<report::NewReport>>,
+    ) -> RwResult<()> {
+        deps.report_comment(token, &slug, comment_id, body.report)
+            .await
+    }
+
+    async fn list_reports(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Query(query): Query<report::ListReportsQuery>,
+    ) -> RwResult<Json<MultipleReportsBody>> {
+        Ok(Json(MultipleReportsBody {
+            reports: deps.list_reports(token, query).await?,
+        }))
+    }
+
+    async fn resolve_report(
+        Extension(deps): Extension<D>,
+        token: Token,
+        Path(report_id): Path<i64>,
+    ) -> RwResult<()> {
+        deps.resolve_report(token, report_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use realworld_domain::error::RwError;
+
+    use axum::http::{Request, StatusCode};
+    use unimock::*;
+
+    fn test_router(deps: Unimock) -> axum::Router {
+        ReportRoutes::<Unimock>::router().layer(Extension(deps))
+    }
+
+    #[tokio::test]
+    async fn blank_reason_should_be_unprocessable() {
+        let deps = Unimock::new(
+            report::api::mock::report_comment
+                .next_call(
+                    matching!((_, "slug", CommentId(42), report) if report.reason.is_empty()),
+                )
+                .returns(Err(RwError::ReportReasonMissing)),
+        );
+
+        let (status, body) = request_json::<serde_json::Value>(
+            test_router(deps.clone()),
+            Request::post("/articles/slug/comments/42/report")
+                .header("Authorization", "Token 123")
+                .with_json_body(ReportBody {
+                    report: report::NewReport {
+                        reason: "".to_string(),
+                    },
+                }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        assert_eq!(
+            serde_json::json!({
+                "code": "VALIDATION_FAILED",
+                "message": "a reason is required",
+                "errors": { "reason": ["can't be blank"] },
+            }),
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn list_reports_should_accept_resolved_query() {
+        let deps = Unimock::new(
+            report::api::mock::list_reports
+                .next_call(matching! {
+                    (_, query) if query == &serde_json::from_value::<report::ListReportsQuery>(
+                        serde_json::json!({ "resolved": false })
+                    ).unwrap()
+                })
+                .returns(Ok(vec![])),
+        );
+
+        let (status, body) = request_json::<MultipleReportsBody>(
+            test_router(deps.clone()),
+            Request::get("/admin/reports?resolved=false")
+                .header("Authorization", "Token 123")
+                .empty_body(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.reports.is_empty());
+    }
+}
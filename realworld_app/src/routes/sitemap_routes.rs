@@ -0,0 +1,61 @@
+use realworld_domain::sitemap::{Sitemap, SitemapXml};
+
+use axum::extract::Extension;
+use axum::routing::get;
+
+/// Routes outside of `/api`, at the root of the site
+pub struct SitemapRoutes<D>(std::marker::PhantomData<D>);
+
+impl<D> SitemapRoutes<D>
+where
+    D: Sitemap + Sized + Clone + Send + Sync + 'static,
+{
+    pub fn router() -> axum::Router {
+        axum::Router::new().route("/sitemap.xml", get(Self::sitemap))
+    }
+
+    async fn sitemap(Extension(deps): Extension<D>) -> SitemapXml {
+        deps.sitemap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use realworld_domain::sitemap::SitemapMock;
+
+    use axum::http::{header, Request, StatusCode};
+    use futures::StreamExt;
+    use tower::ServiceExt;
+    use unimock::*;
+
+    fn test_router(deps: Unimock) -> axum::Router {
+        SitemapRoutes::<Unimock>::router().layer(Extension(deps))
+    }
+
+    #[tokio::test]
+    async fn sitemap_should_be_xml_of_all_chunks() {
+        let deps = Unimock::new(SitemapMock.next_call(matching!()).answers(&|_| {
+            SitemapXml(
+                futures::stream::iter([Ok("<urlset>".to_string()), Ok("</urlset>".to_string())])
+                    .boxed(),
+            )
+        }));
+
+        let response = test_router(deps.clone())
+            .oneshot(Request::get("/sitemap.xml").empty_body())
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "application/xml; charset=utf-8",
+            response.headers()[header::CONTENT_TYPE]
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!("<urlset></urlset>", body);
+    }
+}
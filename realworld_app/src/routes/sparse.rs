@@ -0,0 +1,144 @@
+//!
+//! Sparse fieldsets, like in JSON:API: `?fields=slug,title,author` on a listing
+//! leaves out all other fields of each listed item, for clients that want smaller responses.
+//!
+//! Items are serialized as usual and then cut down, so there's only one DTO per kind of item.
+//! Fields are given by their names in the response, e.g. `tagList`, and a nested object like
+//! `author` is kept whole.
+//!
+
+use realworld_domain::error::{RwError, RwResult};
+
+use serde::ser::Error;
+use std::sync::Arc;
+
+/// An item whose fields can be asked for
+pub trait Fieldset: serde::Serialize {
+    /// The names of all the fields of the item in a response
+    const FIELDS: &'static [&'static str];
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct FieldsQuery {
+    /// Comma separated
+    fields: Option<String>,
+}
+
+/// The fields to keep, or all of them
+#[derive(Clone, Default)]
+pub struct Fields(Option<Arc<[String]>>);
+
+impl Fields {
+    pub fn parse<T: Fieldset>(query: FieldsQuery) -> RwResult<Self> {
+        let Some(fields) = query.fields else {
+            return Ok(Self(None));
+        };
+        let fields = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                if T::FIELDS.contains(&name) {
+                    Ok(name.to_string())
+                } else {
+                    Err(RwError::UnknownField(name.to_string()))
+                }
+            })
+            .collect::<RwResult<_>>()?;
+
+        Ok(Self(Some(fields)))
+    }
+
+    pub fn apply<T: Fieldset>(&self, item: T) -> Sparse<T> {
+        Sparse {
+            item,
+            fields: self.clone(),
+        }
+    }
+}
+
+/// Serializes `T` with only the given fields
+pub struct Sparse<T> {
+    item: T,
+    fields: Fields,
+}
+
+impl<T: Fieldset> serde::Serialize for Sparse<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields.0 else {
+            return self.item.serialize(serializer);
+        };
+        let mut value = serde_json::to_value(&self.item).map_err(S::Error::custom)?;
+        if let Some(object) = value.as_object_mut() {
+            object.retain(|name, _| fields.iter().any(|field| field == name));
+        }
+        value.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Item {
+        slug: &'static str,
+        tag_list: Vec<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        body: Option<&'static str>,
+    }
+
+    impl Fieldset for Item {
+        const FIELDS: &'static [&'static str] = &["slug", "tagList", "body"];
+    }
+
+    fn item() -> Item {
+        Item {
+            slug: "slug",
+            tag_list: vec!["tag"],
+            body: None,
+        }
+    }
+
+    fn sparse(fields: Option<&str>) -> RwResult<serde_json::Value> {
+        let fields = Fields::parse::<Item>(FieldsQuery {
+            fields: fields.map(str::to_string),
+        })?;
+        Ok(serde_json::to_value(fields.apply(item())).unwrap())
+    }
+
+    #[test]
+    fn no_fields_should_keep_all_of_them() {
+        assert_eq!(
+            serde_json::json!({ "slug": "slug", "tagList": ["tag"] }),
+            sparse(None).unwrap()
+        );
+    }
+
+    #[test]
+    fn only_the_given_fields_should_be_kept() {
+        assert_eq!(
+            serde_json::json!({ "tagList": ["tag"] }),
+            sparse(Some("tagList")).unwrap()
+        );
+        assert_eq!(
+            serde_json::json!({ "slug": "slug", "tagList": ["tag"] }),
+            sparse(Some(" slug, tagList,,")).unwrap()
+        );
+        // Fields that are left out anyway stay out
+        assert_eq!(
+            serde_json::json!({ "slug": "slug" }),
+            sparse(Some("slug,body")).unwrap()
+        );
+        assert_eq!(serde_json::json!({}), sparse(Some("")).unwrap());
+    }
+
+    #[test]
+    fn unknown_fields_should_be_rejected() {
+        assert!(matches!(
+            sparse(Some("slug,tag_list")),
+            Err(RwError::UnknownField(name)) if name == "tag_list"
+        ));
+    }
+}
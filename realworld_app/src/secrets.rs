@@ -0,0 +1,83 @@
+//!
+//! Secret settings, like `JWT_SIGNING_KEY` and `DATABASE_URL`, can be read from files
+//! the way Docker and Kubernetes mount secrets, e.g. `JWT_SIGNING_KEY_FILE=/run/secrets/jwt`.
+//!
+//! A secret from a file takes precedence over the config file,
+//! but not over the environment variable or argument itself.
+//! Other sources of secrets, like Vault, can be added as a [SecretProvider].
+//!
+
+use anyhow::Context;
+use std::ffi::OsString;
+
+/// Settings that may hold secret material, by argument id.
+/// Those that aren't compiled in are skipped.
+pub const SECRETS: &[&str] = &[
+    "database_url",
+    "jwt_signing_key",
+    "jwt_verification_keys",
+    "smtp_url",
+    "github_client_secret",
+    "google_client_secret",
+    "s3_secret_access_key",
+    "redis_url",
+    "tenant_database_url",
+];
+
+pub trait SecretProvider {
+    /// The value of the secret named by its environment variable, e.g. `JWT_SIGNING_KEY`,
+    /// if this provider has it
+    fn secret(&self, env_name: &str) -> anyhow::Result<Option<String>>;
+}
+
+/// Reads secrets from the files named by `<NAME>_FILE` environment variables
+pub struct FileSecrets;
+
+impl SecretProvider for FileSecrets {
+    fn secret(&self, env_name: &str) -> anyhow::Result<Option<String>> {
+        read_secret_file(env_name, std::env::var_os(format!("{env_name}_FILE")))
+    }
+}
+
+fn read_secret_file(env_name: &str, path: Option<OsString>) -> anyhow::Result<Option<String>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let secret = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "could not read {env_name}_FILE {}",
+            std::path::Path::new(&path).display()
+        )
+    })?;
+
+    // Files tend to end with a newline, which isn't part of the secret
+    Ok(Some(secret.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_newline_should_not_be_part_of_secret() {
+        let path = std::env::temp_dir().join(format!("secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "s3cr3t\n").unwrap();
+
+        let secret = read_secret_file("JWT_SIGNING_KEY", Some(path.clone().into()));
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(Some("s3cr3t".to_string()), secret.unwrap());
+    }
+
+    #[test]
+    fn missing_file_should_be_an_error() {
+        let error = read_secret_file("JWT_SIGNING_KEY", Some("/nonexistent/secret".into()))
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            "could not read JWT_SIGNING_KEY_FILE /nonexistent/secret",
+            error
+        );
+    }
+}
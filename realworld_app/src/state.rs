@@ -0,0 +1,26 @@
+use crate::config::Config;
+
+///
+/// Connections to services that keep state shared between instances of the app.
+///
+/// Without any (the default), every instance keeps its own state in memory.
+///
+#[derive(Clone, Default)]
+pub struct SharedState {
+    #[cfg(feature = "redis")]
+    pub redis: Option<realworld_redis::Redis>,
+}
+
+impl SharedState {
+    pub async fn connect(config: &Config) -> anyhow::Result<Self> {
+        #[cfg(feature = "redis")]
+        if let Some(redis_url) = &config.redis_url {
+            return Ok(Self {
+                redis: Some(realworld_redis::Redis::connect(redis_url).await?),
+            });
+        }
+
+        let _ = config;
+        Ok(Self::default())
+    }
+}
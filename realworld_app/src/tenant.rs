@@ -0,0 +1,195 @@
+//!
+//! Optional multi-tenant mode, serving several separate communities from one deployment.
+//!
+//! Every tenant has a database of its own, given by `tenant_database_url`,
+//! and an [App] of its own using that database. The tenant of each API request is found
+//! by a [ResolveTenant], and the request is then handled by the app of that tenant.
+//!
+//! The queries name the `app` schema explicitly, so tenants are separated
+//! by database rather than by schema.
+//!
+//! With no `tenants` configured, which is the default, none of this is in play.
+//!
+
+use crate::app::{backend, App};
+use crate::config::Config;
+
+use axum::extract::{Extension, Request};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderName, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use entrait::Impl;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum TenantFrom {
+    /// A header naming the tenant, see `tenant_header`
+    Header,
+    /// The first label of the host name, e.g. `acme` in `acme.example.com`
+    Subdomain,
+}
+
+///
+/// Finds the tenant that a request is for.
+///
+pub trait ResolveTenant: Send + Sync {
+    fn resolve_tenant(&self, parts: &Parts) -> Option<String>;
+}
+
+pub struct TenantResolver {
+    pub from: TenantFrom,
+    pub header: HeaderName,
+}
+
+impl ResolveTenant for TenantResolver {
+    fn resolve_tenant(&self, parts: &Parts) -> Option<String> {
+        match self.from {
+            TenantFrom::Header => parts
+                .headers
+                .get(&self.header)?
+                .to_str()
+                .ok()
+                .map(str::to_string),
+            TenantFrom::Subdomain => {
+                let host = parts.headers.get(header::HOST)?.to_str().ok()?;
+                let (subdomain, _) = host.split_once('.')?;
+                Some(subdomain.to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+///
+/// The apps of the tenants. Empty in single-tenant mode.
+///
+#[derive(Clone, Default)]
+pub struct Tenants {
+    apps: Arc<HashMap<String, Impl<App>>>,
+    resolver: Option<Arc<dyn ResolveTenant>>,
+}
+
+impl Tenants {
+    pub fn new(resolver: impl ResolveTenant + 'static, apps: HashMap<String, Impl<App>>) -> Self {
+        Self {
+            apps: Arc::new(apps),
+            resolver: Some(Arc::new(resolver)),
+        }
+    }
+
+    pub fn from_config(config: &Config, apps: HashMap<String, Impl<App>>) -> Self {
+        if apps.is_empty() {
+            return Self::default();
+        }
+
+        Self::new(
+            TenantResolver {
+                from: config.tenant_from,
+                header: config.tenant_header.clone(),
+            },
+            apps,
+        )
+    }
+
+    /// The app of the tenant of the request, if it names a known tenant
+    fn resolve(&self, parts: &Parts) -> Option<&Impl<App>> {
+        let tenant = self.resolver.as_ref()?.resolve_tenant(parts)?;
+        self.apps.get(&tenant)
+    }
+}
+
+/// The database url of each configured tenant
+pub fn tenant_database_urls(config: &Config) -> Vec<(String, String)> {
+    let Some(url) = &config.tenant_database_url else {
+        return vec![];
+    };
+
+    config
+        .tenants
+        .iter()
+        .map(|tenant| (tenant.clone(), url.replace("{tenant}", tenant)))
+        .collect()
+}
+
+/// Connects to, and migrates unless disabled, the database of every tenant
+pub async fn init_tenant_dbs(config: &Config) -> anyhow::Result<Vec<(String, backend::Db)>> {
+    let mut dbs = vec![];
+    for (tenant, url) in tenant_database_urls(config) {
+        let db = backend::Db::init(&url, &config.db_pool_config(), config.migrate_on_start)
+            .await
+            .map_err(|error| error.context(format!("database of tenant {tenant}")))?;
+        dbs.push((tenant, db));
+    }
+    Ok(dbs)
+}
+
+///
+/// Lets the app of the request's tenant handle the request, in place of the app of the router.
+/// Requests that don't name a known tenant are rejected.
+///
+pub async fn select_tenant(
+    Extension(app): Extension<Impl<App>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if app.tenants.apps.is_empty() {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let Some(tenant_app) = app.tenants.resolve(&parts).cloned() else {
+        return (StatusCode::NOT_FOUND, "unknown tenant").into_response();
+    };
+
+    parts.extensions.insert(tenant_app);
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(name: &str, value: &str) -> Parts {
+        axum::http::Request::get("/api/articles")
+            .header(name, value)
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    #[test]
+    fn tenant_should_be_resolved_from_header() {
+        let resolver = TenantResolver {
+            from: TenantFrom::Header,
+            header: HeaderName::from_static("x-tenant"),
+        };
+
+        assert_eq!(
+            Some("acme".to_string()),
+            resolver.resolve_tenant(&parts("x-tenant", "acme"))
+        );
+        assert_eq!(
+            None,
+            resolver.resolve_tenant(&parts("host", "acme.example.com"))
+        );
+    }
+
+    #[test]
+    fn tenant_should_be_resolved_from_subdomain() {
+        let resolver = TenantResolver {
+            from: TenantFrom::Subdomain,
+            header: HeaderName::from_static("x-tenant"),
+        };
+
+        assert_eq!(
+            Some("acme".to_string()),
+            resolver.resolve_tenant(&parts("host", "ACME.example.com:8080"))
+        );
+        assert_eq!(
+            None,
+            resolver.resolve_tenant(&parts("host", "localhost:8080"))
+        );
+    }
+}
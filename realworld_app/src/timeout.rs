@@ -0,0 +1,106 @@
+//!
+//! Time budgets of requests, until the response starts. Reading requests get a shorter budget
+//! than writing ones. Streamed response bodies, like exports and the sitemap, aren't limited.
+//!
+
+use crate::config::Config;
+
+use realworld_domain::error::RwError;
+
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimeoutBudgets {
+    read: Option<Duration>,
+    write: Option<Duration>,
+}
+
+impl TimeoutBudgets {
+    pub fn from_config(config: &Config) -> Self {
+        let budget = |ms| (ms > 0).then(|| Duration::from_millis(ms));
+        Self {
+            read: budget(config.read_timeout_ms),
+            write: budget(config.write_timeout_ms),
+        }
+    }
+
+    fn for_method(&self, method: &Method) -> Option<Duration> {
+        if method == Method::GET || method == Method::HEAD {
+            self.read
+        } else {
+            self.write
+        }
+    }
+}
+
+/// Requests over budget get `503 Service Unavailable`, and are cancelled
+pub async fn enforce_timeout(
+    State(budgets): State<TimeoutBudgets>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(budget) = budgets.for_method(request.method()) else {
+        return next.run(request).await;
+    };
+
+    match tokio::time::timeout(budget, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("request timed out after {budget:?}");
+            RwError::ServiceUnavailable { retry_after: None }.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    use axum::http::StatusCode;
+    use axum::routing::get;
+
+    fn test_router() -> axum::Router {
+        axum::Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+                .post(|| async {}),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                TimeoutBudgets {
+                    read: Some(Duration::from_millis(10)),
+                    write: None,
+                },
+                enforce_timeout,
+            ))
+    }
+
+    #[tokio::test]
+    async fn slow_read_should_time_out() {
+        let (status, _) = request(
+            test_router(),
+            axum::http::Request::get("/slow").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+    }
+
+    #[tokio::test]
+    async fn writes_should_have_their_own_budget() {
+        let (status, _) = request(
+            test_router(),
+            axum::http::Request::post("/slow").empty_body(),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+    }
+}
@@ -0,0 +1,147 @@
+//!
+//! Serving HTTPS directly, with the `tls` feature, when `tls_cert_path` and `tls_key_path` are set.
+//!
+//! The certificate and key are read again on `SIGHUP`, so that a renewed certificate
+//! is taken into use without a restart. Established connections keep the certificate they got.
+//! If the files can't be read, the previous certificate stays in use.
+//!
+
+use crate::config::Config;
+
+use anyhow::Context;
+use axum_server::tls_rustls::RustlsConfig;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// PEM files of the certificate chain and its private key
+#[derive(Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    /// `None` unless both files are configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            cert: config.tls_cert_path.clone()?,
+            key: config.tls_key_path.clone()?,
+        })
+    }
+
+    async fn load(&self) -> anyhow::Result<RustlsConfig> {
+        RustlsConfig::from_pem_file(&self.cert, &self.key)
+            .await
+            .with_context(|| self.describe())
+    }
+
+    async fn reload(&self, config: &RustlsConfig) -> anyhow::Result<()> {
+        config
+            .reload_from_pem_file(&self.cert, &self.key)
+            .await
+            .with_context(|| self.describe())
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "could not load TLS certificate {} with key {}",
+            self.cert.display(),
+            self.key.display()
+        )
+    }
+}
+
+/// Serves HTTPS on `addr` until the server fails
+pub async fn serve(router: axum::Router, addr: SocketAddr, files: TlsFiles) -> anyhow::Result<()> {
+    // The one crypto provider compiled in, but rustls wants it to be explicit
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let config = files.load().await?;
+    tokio::spawn(reload_on_sighup(config.clone(), files));
+
+    // The peer address is used for rate limiting
+    axum_server::bind_rustls(addr, config)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .context("error running HTTPS server")
+}
+
+async fn reload_on_sighup(config: RustlsConfig, files: TlsFiles) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            tracing::error!("TLS certificates won't be reloaded, can't listen for SIGHUP: {error}");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        match files.reload(&config).await {
+            Ok(()) => tracing::info!("reloaded TLS certificate {}", files.cert.display()),
+            Err(error) => tracing::error!("{error:#}, keeping the previous certificate"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files of a new self-signed certificate, removed when dropped
+    struct TestFiles(TlsFiles);
+
+    impl TestFiles {
+        fn new() -> Self {
+            let certified = rcgen::generate_simple_self_signed(["localhost".to_string()]).unwrap();
+            let dir = std::env::temp_dir();
+            let id = uuid::Uuid::new_v4();
+            let files = TlsFiles {
+                cert: dir.join(format!("cert-{id}.pem")),
+                key: dir.join(format!("key-{id}.pem")),
+            };
+            std::fs::write(&files.cert, certified.cert.pem()).unwrap();
+            std::fs::write(&files.key, certified.key_pair.serialize_pem()).unwrap();
+            Self(files)
+        }
+    }
+
+    impl Drop for TestFiles {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0.cert);
+            let _ = std::fs::remove_file(&self.0.key);
+        }
+    }
+
+    #[tokio::test]
+    async fn renewed_certificate_should_be_reloaded() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let files = TestFiles::new();
+        let config = files.0.load().await.unwrap();
+        let before = config.get_inner();
+
+        let renewed = TestFiles::new();
+        std::fs::copy(&renewed.0.cert, &files.0.cert).unwrap();
+        std::fs::copy(&renewed.0.key, &files.0.key).unwrap();
+        files.0.reload(&config).await.unwrap();
+
+        assert!(!std::sync::Arc::ptr_eq(&before, &config.get_inner()));
+    }
+
+    #[tokio::test]
+    async fn broken_certificate_should_keep_previous() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let files = TestFiles::new();
+        let config = files.0.load().await.unwrap();
+        let before = config.get_inner();
+
+        std::fs::write(&files.0.key, "not a key").unwrap();
+        let error = files.0.reload(&config).await.unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("could not load TLS certificate"));
+        assert!(std::sync::Arc::ptr_eq(&before, &config.get_inner()));
+    }
+}
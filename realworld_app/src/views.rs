@@ -0,0 +1,77 @@
+use realworld_domain::article::repo::{ArticleView, ViewRepo};
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Views beyond this many are dropped while waiting to be counted,
+/// so that a slow database can't make the buffer grow without bounds
+const MAX_PENDING_VIEWS: usize = 100_000;
+
+///
+/// Views recorded by `realworld_domain::article::views::RecordView`, waiting to be counted.
+///
+/// Each view is only kept once, so repeated views between two flushes don't reach the database.
+///
+#[derive(Clone, Default)]
+pub struct ViewBuffer(Arc<Mutex<HashSet<ArticleView>>>);
+
+impl ViewBuffer {
+    pub fn push(&self, view: ArticleView) {
+        let mut pending = self.0.lock().unwrap();
+        if pending.len() < MAX_PENDING_VIEWS {
+            pending.insert(view);
+        }
+    }
+
+    pub fn take(&self) -> Vec<ArticleView> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Counts the views of the buffer in batches, one batch every `interval`
+pub async fn count_views(deps: impl ViewRepo, buffer: ViewBuffer, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+
+        let views = buffer.take();
+        if views.is_empty() {
+            continue;
+        }
+        if let Err(error) = deps.insert_views(&views).await {
+            tracing::warn!(?error, views = views.len(), "could not count article views");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realworld_domain::article::repo::Viewer;
+    use realworld_domain::article::Slug;
+    use realworld_domain::user::UserId;
+
+    fn view(slug: &str) -> ArticleView {
+        ArticleView {
+            slug: Slug::from(slug),
+            viewer: Viewer::User(UserId(uuid::Uuid::from_u128(1))),
+            day: time::Date::from_calendar_date(2024, time::Month::May, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn repeated_views_should_be_taken_once() {
+        let buffer = ViewBuffer::default();
+        buffer.push(view("a"));
+        buffer.push(view("a"));
+        buffer.push(view("b"));
+
+        let mut slugs: Vec<_> = buffer.take().into_iter().map(|view| view.slug.0).collect();
+        slugs.sort();
+        assert_eq!(vec!["a", "b"], slugs);
+        assert!(buffer.take().is_empty());
+    }
+}
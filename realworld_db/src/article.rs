@@ -3,17 +3,22 @@ use crate::GetDb;
 use crate::OnConstraint;
 
 use realworld_domain::article::repo::*;
+use realworld_domain::article::{ArticleId, Slug};
 use realworld_domain::error::{RwError, RwResult};
 use realworld_domain::timestamp::Timestamptz;
+use realworld_domain::user::repo::{Following, User};
+use realworld_domain::user::role::Role;
 use realworld_domain::user::UserId;
 
 use entrait::*;
-use futures::TryStreamExt;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use uuid::Uuid;
 
 pub struct PgArticleRepo;
 
-#[entrait]
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
 impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
     pub async fn select_articles(
         deps: &impl GetDb,
@@ -25,65 +30,108 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
             // language=PostgreSQL
             r#"
             SELECT
-                slug,
-                title,
-                description,
-                body,
-                tag_list,
-                article.created_at "created_at: Timestamptz",
-                article.updated_at "updated_at: Timestamptz",
+                article.article_id "article_id!: ArticleId",
+                article.slug "slug!: Slug",
+                article.title "title!",
+                -- summaries leave out the body, and cut the description
+                CASE
+                    WHEN $14 AND char_length(description) > $15 THEN left(description, $15 - 1) || '…'
+                    ELSE description
+                END "description!",
+                CASE WHEN $14 THEN '' ELSE body END "body!",
+                article.tag_list "tag_list!",
+                article.created_at "created_at!: Timestamptz",
+                article.updated_at "updated_at!: Timestamptz",
                 EXISTS(
-                    SELECT 1 FROM app.article_favorite WHERE user_id = $1
+                    SELECT 1 FROM article_favorite fav
+                    WHERE fav.article_id = article.article_id AND fav.user_id = $1
                 ) "favorited!",
-                COALESCE(
-                    (SELECT count(*) FROM app.article_favorite fav WHERE fav.article_id = article.article_id),
-                    0
-                ) "favorites_count!",
-                author.username author_username,
-                author.bio author_bio,
-                author.image author_image,
-                EXISTS(
-                    SELECT 1 FROM app.follow WHERE followed_user_id = author.user_id AND following_user_id = $1
-                ) "following_author!"
-            FROM app.article
-            INNER JOIN app.user author USING (user_id)
+                article.favorites_count "favorites_count!",
+                article.views_count "views_count!",
+                article.author_id "author_id!",
+                article.author_username "author_username!",
+                article.share_token
+            FROM article_projection article
             WHERE (
                 $2::text IS NULL OR slug = $2
             ) AND (
-                $3::text IS NULL OR tag_list @> array[$3]
+                $16::text IS NULL OR article.share_token = $16
+            ) AND (
+                -- unlisted articles are only found by their slug or their token
+                article.share_token IS NULL OR $2::text IS NOT NULL OR $16::text IS NOT NULL
+            ) AND (
+                cardinality($3::text[]) = 0 OR tag_list && $3
             ) AND (
-                $4::text IS NULL OR author.username = $4
+                tag_list @> $11::text[]
+            ) AND (
+                NOT tag_list && $12::text[]
+            ) AND (
+                $4::text IS NULL OR article.author_username = $4
             ) AND (
                 $5::text IS NULL OR EXISTS(
                     SELECT 1
-                    FROM app.article_favorite
+                    FROM article_favorite
                     WHERE
-                        user_id = (SELECT user_id FROM app.user WHERE username = $5)
+                        user_id = (SELECT user_id FROM "user" WHERE username = $5)
                     AND
                         article_id = article.article_id
                 )
             ) AND (
                 $6::uuid IS NULL OR EXISTS(
                     SELECT 1
-                    FROM app.follow
+                    FROM follow
                     WHERE
                         following_user_id = $6
                     AND
-                        followed_user_id = author.user_id
+                        followed_user_id = article.author_id
+                )
+            ) AND (
+                $17::uuid IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM feed_entry
+                    WHERE
+                        user_id = $17
+                    AND
+                        article_id = article.article_id
+                )
+            ) AND (
+                -- blocked authors are left out of listings, but a single article can still be fetched
+                $2::text IS NOT NULL OR NOT EXISTS(
+                    SELECT 1 FROM block WHERE blocking_user_id = $1 AND blocked_user_id = article.author_id
+                )
+            ) AND (
+                $13::uuid IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM bookmark
+                    WHERE
+                        user_id = $13
+                    AND
+                        article_id = article.article_id
                 )
+            ) AND (
+                $9::timestamptz IS NULL OR (article.created_at, slug) < ($9, $10::text)
             )
-            ORDER BY article.created_at DESC
+            ORDER BY article.created_at DESC, slug DESC
             LIMIT $7
             OFFSET $8
             "#,
             current_user.0,
-            filter.slug,
-            filter.tag,
+            filter.slug.map(Slug::as_str),
+            filter.any_tag,
             filter.author,
             filter.favorited_by,
             filter.followed_by.map(UserId::into_id),
-            filter.limit.unwrap_or(20),
-            filter.offset.unwrap_or(0)
+            filter.limit.unwrap_or(DEFAULT_LIMIT),
+            filter.offset.unwrap_or(0),
+            filter.after.map(|cursor| cursor.created_at.0),
+            filter.after.map(|cursor| cursor.slug.as_str()),
+            filter.all_tags,
+            filter.excluded_tags,
+            filter.bookmarked_by.map(UserId::into_id),
+            filter.summary,
+            SUMMARY_DESCRIPTION_LENGTH,
+            filter.share_token,
+            filter.in_feed_of.map(UserId::into_id)
         )
         .fetch(&deps.get_db().pg_pool)
         .try_collect::<Vec<_>>()
@@ -93,10 +141,10 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
         Ok(articles)
     }
 
-    pub async fn fetch_article_id(deps: &impl GetDb, slug: &str) -> RwResult<Uuid> {
+    pub async fn fetch_article_id(deps: &impl GetDb, Slug(slug): &Slug) -> RwResult<ArticleId> {
         sqlx::query_scalar!(
             // language=PostgreSQL
-            "SELECT article_id FROM app.article WHERE slug = $1",
+            r#"SELECT article_id "article_id: ArticleId" FROM article WHERE slug = $1"#,
             slug,
         )
         .fetch_optional(&deps.get_db().pg_pool)
@@ -105,65 +153,119 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
         .ok_or(RwError::ArticleNotFound)
     }
 
+    pub async fn find_article_slug(
+        deps: &impl GetDb,
+        ArticleId(article_id): ArticleId,
+    ) -> RwResult<Option<Slug>> {
+        sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"SELECT slug "slug: Slug" FROM article WHERE article_id = $1"#,
+            article_id,
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn find_renamed_article_slug(
+        deps: &impl GetDb,
+        Slug(previous_slug): &Slug,
+    ) -> RwResult<Option<Slug>> {
+        sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            SELECT article.slug "slug: Slug"
+            FROM article_slug_history history
+            INNER JOIN article USING (article_id)
+            WHERE history.slug = $1
+            ORDER BY history.changed_at DESC
+            LIMIT 1
+            "#,
+            previous_slug,
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn insert_article(
         deps: &impl GetDb,
         UserId(user_id): UserId,
-        slug: &str,
+        Slug(slug): &Slug,
         title: &str,
         description: &str,
         body: &str,
         tag_list: &[String],
+        share_token: Option<&str>,
     ) -> RwResult<Article> {
-        let article = sqlx::query_as!(
-            Article,
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        let article_id = sqlx::query_scalar!(
             // language=PostgreSQL
             r#"
-            WITH inserted_article AS (
-                INSERT INTO app.article (user_id, slug, title, description, body, tag_list)
-                VALUES ($1, $2, $3, $4, $5, $6)
-                RETURNING
-                    slug,
-                    title,
-                    description,
-                    body,
-                    tag_list,
-                    -- This is how you can override the inferred type of a column.
-                    created_at "created_at: Timestamptz",
-                    updated_at "updated_at: Timestamptz"
-            )
-            SELECT
-                inserted_article.*,
-                false "favorited!",
-                0::int8 "favorites_count!",
-                username author_username,
-                bio author_bio,
-                image author_image,
-                -- user is forbidden to follow themselves
-                false "following_author!"
-            FROM inserted_article
-            INNER JOIN app.user ON user_id = $1
+            INSERT INTO article (user_id, slug, title, description, body, tag_list, share_token)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING article_id
             "#,
             user_id,
             slug,
             title,
             description,
             body,
-            tag_list
+            tag_list,
+            share_token
         )
-        .fetch_one(&deps.get_db().pg_pool)
+        .fetch_one(&mut *tx)
         .await
         .to_rw_err()
         .on_constraint("article_slug_key", |_| {
             RwError::DuplicateArticleSlug(slug.to_string())
         })?;
 
+        // Selected after the insert, since a query doesn't see the rows inserted by its own `WITH`
+        let article = sqlx::query_as!(
+            Article,
+            // language=PostgreSQL
+            r#"
+            SELECT
+                article.article_id "article_id!: ArticleId",
+                article.slug "slug!: Slug",
+                article.title "title!",
+                article.description "description!",
+                article.body "body!",
+                article.tag_list "tag_list!",
+                -- This is how you can override the inferred type of a column.
+                article.created_at "created_at!: Timestamptz",
+                article.updated_at "updated_at!: Timestamptz",
+                EXISTS(
+                    SELECT 1 FROM article_favorite fav
+                    WHERE fav.article_id = article.article_id AND fav.user_id = $1
+                ) "favorited!",
+                article.favorites_count "favorites_count!",
+                article.views_count "views_count!",
+                article.author_id "author_id!",
+                article.author_username "author_username!",
+                article.share_token
+            FROM article_projection article
+            WHERE article.article_id = $2
+            "#,
+            user_id,
+            article_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
         Ok(article)
     }
 
     pub async fn update_article(
         deps: &impl GetDb,
         UserId(user_id): UserId,
-        slug: &str,
+        Slug(slug): &Slug,
         up: ArticleUpdate<'_>,
     ) -> RwResult<()> {
         let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
@@ -171,7 +273,7 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
         let article_meta = sqlx::query!(
             // This locks the `article` row for the duration of the transaction so we're
             // not interleaving this with other possible updates.
-            "SELECT article_id, user_id FROM app.article WHERE slug = $1 FOR UPDATE",
+            "SELECT article_id, user_id, updated_at FROM article WHERE slug = $1 FOR UPDATE",
             slug
         )
         .fetch_optional(&mut *tx)
@@ -183,21 +285,52 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
             return Err(RwError::Forbidden);
         }
 
+        if up
+            .expected_updated_at
+            .is_some_and(|expected| expected.0 != article_meta.updated_at)
+        {
+            return Err(RwError::PreconditionFailed);
+        }
+
+        if up.slug.is_some_and(|new_slug| new_slug.as_str() != slug) {
+            sqlx::query!(
+                // language=PostgreSQL
+                r#"
+                INSERT INTO article_slug_history (slug, article_id)
+                VALUES ($1, $2)
+                "#,
+                slug,
+                article_meta.article_id
+            )
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+        }
+
         sqlx::query!(
             // language=PostgreSQL
             r#"
-            UPDATE app.article
+            UPDATE article
             SET
                 slug = COALESCE($1, slug),
                 title = COALESCE($2, title),
                 description = COALESCE($3, description),
-                body = COALESCE($4, body)
-            WHERE article_id = $5
+                body = COALESCE($4, body),
+                tag_list = COALESCE($5, tag_list),
+                share_token = CASE
+                    WHEN NOT $6 THEN share_token
+                    WHEN $7::text IS NULL THEN NULL
+                    ELSE COALESCE(share_token, $7)
+                END
+            WHERE article_id = $8
             "#,
-            up.slug,
+            up.slug.map(Slug::as_str),
             up.title,
             up.description,
             up.body,
+            up.tag_list,
+            up.share_token.is_some(),
+            up.share_token.flatten(),
             article_meta.article_id
         )
         .execute(&mut *tx)
@@ -213,7 +346,7 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
     pub async fn delete_article(
         deps: &impl GetDb,
         UserId(user_id): UserId,
-        slug: &str,
+        Slug(slug): &Slug,
     ) -> RwResult<()> {
         let result = sqlx::query!(
             // I like to use raw strings for most queries mainly because CLion doesn't try
@@ -221,13 +354,13 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
             // language=PostgreSQL
             r#"
             WITH deleted_article AS (
-                DELETE from app.article
+                DELETE from article
                 WHERE slug = $1 AND user_id = $2
                 RETURNING 1
             )
             SELECT
                 -- This will be `true` if the article existed before we deleted it.
-                EXISTS(SELECT 1 FROM app.article WHERE slug = $1) "existed!",
+                EXISTS(SELECT 1 FROM article WHERE slug = $1) "existed!",
                 -- This will only be `true` if we actually deleted the article.
                 EXISTS(SELECT 1 FROM deleted_article) "deleted!"
             "#,
@@ -247,18 +380,35 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
         }
     }
 
+    pub async fn delete_any_article(deps: &impl GetDb, Slug(slug): &Slug) -> RwResult<()> {
+        let result = sqlx::query!(
+            // language=PostgreSQL
+            "DELETE FROM article WHERE slug = $1",
+            slug
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::ArticleNotFound)
+        }
+    }
+
     pub async fn insert_favorite(
         deps: &impl GetDb,
         UserId(user_id): UserId,
-        slug: &str,
+        Slug(slug): &Slug,
     ) -> RwResult<()> {
         sqlx::query_scalar!(
             r#"
             WITH selected_article AS (
-                SELECT article_id FROM app.article WHERE slug = $1
+                SELECT article_id FROM article WHERE slug = $1
             ),
             inserterted_favorite AS (
-                INSERT INTO app.article_favorite(article_id, user_id)
+                INSERT INTO article_favorite(article_id, user_id)
                     SELECT article_id, $2 FROM selected_article
                 -- if the article is already favorited
                 ON CONFLICT DO NOTHING
@@ -279,15 +429,15 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
     pub async fn delete_favorite(
         deps: &impl GetDb,
         UserId(user_id): UserId,
-        slug: &str,
+        Slug(slug): &Slug,
     ) -> RwResult<()> {
         sqlx::query_scalar!(
             r#"
             WITH selected_article AS (
-                SELECT article_id FROM app.article WHERE slug = $1
+                SELECT article_id FROM article WHERE slug = $1
             ),
             deleted_favorite AS (
-                DELETE FROM app.article_favorite
+                DELETE FROM article_favorite
                 WHERE article_id = (SELECT article_id from selected_article)
                 AND user_id = $2
             )
@@ -303,6 +453,304 @@ impl realworld_domain::article::repo::ArticleRepoImpl for PgArticleRepo {
 
         Ok(())
     }
+
+    pub async fn set_favorites_bulk(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slugs: &[Slug],
+        favorited: bool,
+    ) -> RwResult<Vec<Slug>> {
+        let pg_pool = &deps.get_db().pg_pool;
+        let slugs: Vec<&str> = slugs.iter().map(Slug::as_str).collect();
+        if favorited {
+            sqlx::query_scalar!(
+                r#"
+                WITH selected_article AS (
+                    -- unlisted articles of others are not found by their slug
+                    SELECT article_id, slug FROM article
+                    WHERE slug = ANY($1) AND (share_token IS NULL OR user_id = $2)
+                ),
+                inserted_favorite AS (
+                    INSERT INTO article_favorite(article_id, user_id)
+                        SELECT article_id, $2 FROM selected_article
+                    -- if the article is already favorited
+                    ON CONFLICT DO NOTHING
+                )
+                SELECT slug "slug!: Slug" FROM selected_article
+                "#,
+                &slugs as &[&str],
+                user_id
+            )
+            .fetch(pg_pool)
+            .try_collect()
+            .await
+            .to_rw_err()
+        } else {
+            sqlx::query_scalar!(
+                r#"
+                WITH selected_article AS (
+                    -- unlisted articles of others are not found by their slug
+                    SELECT article_id, slug FROM article
+                    WHERE slug = ANY($1) AND (share_token IS NULL OR user_id = $2)
+                ),
+                deleted_favorite AS (
+                    DELETE FROM article_favorite
+                    WHERE article_id IN (SELECT article_id FROM selected_article)
+                    AND user_id = $2
+                )
+                SELECT slug "slug!: Slug" FROM selected_article
+                "#,
+                &slugs as &[&str],
+                user_id
+            )
+            .fetch(pg_pool)
+            .try_collect()
+            .await
+            .to_rw_err()
+        }
+    }
+
+    pub async fn list_favoriting_users(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        Slug(slug): &Slug,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                "user".user_id,
+                username,
+                bio,
+                image,
+                role "role: Role",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = "user".user_id AND following_user_id = $2
+                ) "following!"
+            FROM article
+            INNER JOIN article_favorite fav USING (article_id)
+            INNER JOIN "user" ON "user".user_id = fav.user_id
+            WHERE slug = $1
+            ORDER BY fav.created_at DESC, username
+            LIMIT $3
+            OFFSET $4
+            "#,
+            slug,
+            current_user.0,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Following(record.following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn select_trending_articles(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        since: time::OffsetDateTime,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let articles: Vec<Article> = sqlx::query_as!(
+            Article,
+            // language=PostgreSQL
+            r#"
+            WITH activity AS (
+                SELECT article_id, count(*) score
+                FROM (
+                    SELECT article_id FROM article_favorite WHERE created_at >= $2
+                    UNION ALL
+                    SELECT article_id FROM article_comment WHERE created_at >= $2
+                ) recent
+                GROUP BY article_id
+            )
+            SELECT
+                article.article_id "article_id!: ArticleId",
+                article.slug "slug!: Slug",
+                article.title "title!",
+                article.description "description!",
+                article.body "body!",
+                article.tag_list "tag_list!",
+                article.created_at "created_at!: Timestamptz",
+                article.updated_at "updated_at!: Timestamptz",
+                EXISTS(
+                    SELECT 1 FROM article_favorite fav
+                    WHERE fav.article_id = article.article_id AND fav.user_id = $1
+                ) "favorited!",
+                article.favorites_count "favorites_count!",
+                article.views_count "views_count!",
+                article.author_id "author_id!",
+                article.author_username "author_username!",
+                article.share_token
+            FROM activity
+            INNER JOIN article_projection article USING (article_id)
+            WHERE article.share_token IS NULL AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = $1 AND blocked_user_id = article.author_id
+            )
+            ORDER BY activity.score DESC, article.created_at DESC, article.slug DESC
+            LIMIT $3
+            OFFSET $4
+            "#,
+            current_user.0,
+            since,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(articles)
+    }
+
+    pub async fn select_recommended_articles(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let articles: Vec<Article> = sqlx::query_as!(
+            Article,
+            // language=PostgreSQL
+            r#"
+            WITH own_favorite AS (
+                SELECT article_id FROM article_favorite WHERE user_id = $1
+            ),
+            -- users favoriting the same articles, and how many
+            similar_user AS (
+                SELECT user_id, count(*) overlap
+                FROM article_favorite
+                WHERE article_id IN (SELECT article_id FROM own_favorite) AND user_id <> $1
+                GROUP BY user_id
+            ),
+            recommendation AS (
+                SELECT article_id, sum(similar_user.overlap) score
+                FROM article_favorite
+                INNER JOIN similar_user USING (user_id)
+                WHERE article_id NOT IN (SELECT article_id FROM own_favorite)
+                GROUP BY article_id
+            )
+            SELECT
+                article.article_id "article_id!: ArticleId",
+                article.slug "slug!: Slug",
+                article.title "title!",
+                article.description "description!",
+                article.body "body!",
+                article.tag_list "tag_list!",
+                article.created_at "created_at!: Timestamptz",
+                article.updated_at "updated_at!: Timestamptz",
+                EXISTS(
+                    SELECT 1 FROM article_favorite fav
+                    WHERE fav.article_id = article.article_id AND fav.user_id = $1
+                ) "favorited!",
+                article.favorites_count "favorites_count!",
+                article.views_count "views_count!",
+                article.author_id "author_id!",
+                article.author_username "author_username!",
+                article.share_token
+            FROM recommendation
+            INNER JOIN article_projection article USING (article_id)
+            WHERE article.author_id <> $1 AND article.share_token IS NULL AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = $1 AND blocked_user_id = article.author_id
+            )
+            ORDER BY recommendation.score DESC, article.created_at DESC, article.slug DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            user_id,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(articles)
+    }
+
+    pub fn stream_articles_by_author(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> BoxStream<'static, RwResult<Article>> {
+        // The stream outlives `deps`, so it gets its own handle to the pool
+        let pg_pool = deps.get_db().pg_pool.clone();
+
+        async_stream::try_stream! {
+            let mut articles = sqlx::query_as!(
+                Article,
+                // language=PostgreSQL
+                r#"
+                SELECT
+                    article.article_id "article_id!: ArticleId",
+                    article.slug "slug!: Slug",
+                    article.title "title!",
+                    article.description "description!",
+                    article.body "body!",
+                    article.tag_list "tag_list!",
+                    article.created_at "created_at!: Timestamptz",
+                    article.updated_at "updated_at!: Timestamptz",
+                    EXISTS(
+                        SELECT 1 FROM article_favorite fav
+                        WHERE fav.article_id = article.article_id AND fav.user_id = $1
+                    ) "favorited!",
+                    article.favorites_count "favorites_count!",
+                    article.views_count "views_count!",
+                    article.author_id "author_id!",
+                    article.author_username "author_username!",
+                    article.share_token
+                FROM article_projection article
+                WHERE article.author_id = $1
+                ORDER BY article.created_at, article.article_id
+                "#,
+                user_id
+            )
+            .fetch(&pg_pool);
+
+            while let Some(article) = articles.try_next().await.to_rw_err()? {
+                yield article;
+            }
+        }
+        .boxed()
+    }
+
+    pub fn stream_article_slugs(
+        deps: &impl GetDb,
+    ) -> BoxStream<'static, RwResult<(Slug, Timestamptz)>> {
+        let pg_pool = deps.get_db().pg_pool.clone();
+
+        async_stream::try_stream! {
+            let mut records = sqlx::query!(
+                r#"
+                SELECT slug "slug: Slug", updated_at "updated_at: Timestamptz"
+                FROM article
+                WHERE share_token IS NULL
+                ORDER BY created_at, article_id
+                "#
+            )
+            .fetch(&pg_pool);
+
+            while let Some(record) = records.try_next().await.to_rw_err()? {
+                yield (record.slug, record.updated_at);
+            }
+        }
+        .boxed()
+    }
 }
 
 #[cfg(test)]
@@ -313,6 +761,7 @@ mod tests {
     use user_db_test::InsertTestUser;
 
     use realworld_domain::iter_util::Single;
+    use realworld_domain::user::repo::UserRepo;
 
     use assert_matches::*;
 
@@ -341,7 +790,7 @@ mod tests {
             .into_iter()
             .single_or_none()
             .unwrap()
-            .map(|article| article.slug)
+            .map(|article| article.slug.0)
     }
 
     #[tokio::test]
@@ -352,11 +801,12 @@ mod tests {
         let inserted_article = db
             .insert_article(
                 user.user_id,
-                "slug",
+                &Slug::from("slug"),
                 "title",
                 "desc",
                 "body",
                 &["tag".to_string()],
+                None,
             )
             .await?;
 
@@ -364,14 +814,14 @@ mod tests {
             .select_single_with_user(
                 user.user_id.some(),
                 Filter {
-                    slug: Some("slug"),
+                    slug: Some(&Slug::from("slug")),
                     ..Default::default()
                 },
             )
             .await;
         assert_eq!(fetched_article, inserted_article);
 
-        assert_eq!(inserted_article.slug, "slug");
+        assert_eq!(inserted_article.slug, Slug::from("slug"));
         assert_eq!(inserted_article.title, "title");
         assert_eq!(inserted_article.description, "desc");
         assert_eq!(inserted_article.body, "body");
@@ -379,22 +829,23 @@ mod tests {
 
         assert_eq!(inserted_article.created_at.0, inserted_article.updated_at.0);
 
-        assert_eq!(inserted_article.favorited, false);
+        assert!(!inserted_article.favorited);
         assert_eq!(inserted_article.favorites_count, 0);
 
+        assert_eq!(inserted_article.author_id, user.user_id.0);
         assert_eq!(inserted_article.author_username, user.username);
-        assert_eq!(inserted_article.author_bio, user.bio);
-        assert_eq!(inserted_article.author_image, user.image);
-        assert_eq!(inserted_article.following_author, false);
 
         db.update_article(
             user.user_id,
-            "slug",
+            &Slug::from("slug"),
             ArticleUpdate {
-                slug: Some("slug2"),
+                slug: Some(&Slug::from("slug2")),
                 title: Some("title2"),
                 description: Some("desc2"),
                 body: Some("body2"),
+                tag_list: Some(&["tag2".to_string()]),
+                share_token: None,
+                expected_updated_at: None,
             },
         )
         .await?;
@@ -403,24 +854,26 @@ mod tests {
             .select_single_with_user(
                 user.user_id.some(),
                 Filter {
-                    slug: Some("slug2"),
+                    slug: Some(&Slug::from("slug2")),
                     ..Default::default()
                 },
             )
             .await;
 
-        assert_eq!(modified_article.slug, "slug2");
+        assert_eq!(modified_article.slug, Slug::from("slug2"));
         assert_eq!(modified_article.title, "title2");
         assert_eq!(modified_article.description, "desc2");
         assert_eq!(modified_article.body, "body2");
+        assert_eq!(modified_article.tag_list, &["tag2".to_string()]);
 
-        db.delete_article(user.user_id, "slug2").await?;
+        db.delete_article(user.user_id, &Slug::from("slug2"))
+            .await?;
 
         assert!(db
             .select_articles(
                 UserId(None),
                 Filter {
-                    slug: Some("slug2"),
+                    slug: Some(&Slug::from("slug2")),
                     ..Default::default()
                 }
             )
@@ -431,88 +884,272 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn should_filter_articles() -> RwResult<()> {
+    async fn renamed_article_should_be_found_by_previous_slugs() -> RwResult<()> {
         let db = create_test_db().await;
-        let (user1, _) = db.insert_test_user(Default::default()).await?;
-        let (user2, _) = db.insert_test_user(user_db_test::other_user()).await?;
-
-        db.insert_article(
-            user1.user_id,
-            "slug1",
-            "title1",
-            "desc1",
-            "body1",
-            &["tag1".to_string()],
-        )
-        .await?;
-
-        db.insert_article(
-            user2.user_id,
-            "slug2",
-            "title2",
-            "desc2",
-            "body2",
-            &["tag2".to_string()],
-        )
-        .await?;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        for (slug, new_slug) in [("slug", "slug2"), ("slug2", "slug3")] {
+            db.update_article(
+                user.user_id,
+                &Slug::from(slug),
+                ArticleUpdate {
+                    slug: Some(&Slug::from(new_slug)),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        }
 
+        let slug3 = Some(Slug::from("slug3"));
+        assert_eq!(slug3, db.find_article_slug(article.article_id).await?);
         assert_eq!(
-            Some("slug1"),
-            db.select_single_slug_or_none(Filter {
-                slug: Some("slug1"),
-                ..Default::default()
-            })
-            .await
-            .as_deref()
+            slug3,
+            db.find_renamed_article_slug(&Slug::from("slug")).await?
         );
-
         assert_eq!(
-            Some("slug1"),
-            db.select_single_slug_or_none(Filter {
-                tag: Some("tag1"),
-                ..Default::default()
-            })
-            .await
-            .as_deref()
+            slug3,
+            db.find_renamed_article_slug(&Slug::from("slug2")).await?
         );
-
         assert_eq!(
-            Some("slug1"),
-            db.select_single_slug_or_none(Filter {
-                author: Some(&user1.username),
-                ..Default::default()
-            })
-            .await
-            .as_deref(),
+            None,
+            db.find_renamed_article_slug(&Slug::from("slug3")).await?
         );
 
+        db.delete_article(user.user_id, &Slug::from("slug3"))
+            .await?;
+        assert_eq!(None, db.find_article_slug(article.article_id).await?);
         assert_eq!(
             None,
-            db.select_single_slug_or_none(Filter {
-                favorited_by: Some(&user1.username),
-                ..Default::default()
-            })
-            .await
-            .as_deref(),
+            db.find_renamed_article_slug(&Slug::from("slug")).await?
         );
+        Ok(())
+    }
 
-        db.insert_favorite(user1.user_id, "slug1").await?;
+    #[tokio::test]
+    async fn should_filter_articles_by_tags() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
 
-        assert_eq!(
-            Some("slug1"),
-            db.select_single_slug_or_none(Filter {
-                favorited_by: Some(&user1.username),
-                ..Default::default()
-            })
-            .await
-            .as_deref()
-        );
+        fn tags(tags: &[&str]) -> Vec<String> {
+            tags.iter().map(|tag| tag.to_string()).collect()
+        }
 
-        assert_eq!(
-            None,
-            db.select_single_slug_or_none(Filter {
-                followed_by: Some(user1.user_id),
-                ..Default::default()
+        async fn slugs(db: &impl ArticleRepo, filter: Filter<'_>) -> Vec<String> {
+            let mut slugs: Vec<String> = db
+                .select_articles(UserId(None), filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect();
+            slugs.sort();
+            slugs
+        }
+
+        for (slug, tag_list) in [
+            ("a", tags(&["rust", "web"])),
+            ("b", tags(&["rust"])),
+            ("c", tags(&["web", "python"])),
+        ] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &tag_list,
+                None,
+            )
+            .await?;
+        }
+
+        let rust = tags(&["rust"]);
+        let web = tags(&["web"]);
+        let python = tags(&["python"]);
+        let rust_or_python = tags(&["rust", "python"]);
+        let rust_and_web = tags(&["rust", "web"]);
+
+        assert_eq!(tags(&["a", "b", "c"]), slugs(&db, Default::default()).await);
+        assert_eq!(
+            tags(&["a", "b", "c"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &rust_or_python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["c"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    all_tags: &rust_and_web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["b"]),
+            slugs(
+                &db,
+                Filter {
+                    excluded_tags: &web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["b"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &rust,
+                    excluded_tags: &web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &web,
+                    all_tags: &rust,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    all_tags: &web,
+                    excluded_tags: &python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_filter_articles() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user1, _) = db.insert_test_user(Default::default()).await?;
+        let (user2, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        db.insert_article(
+            user1.user_id,
+            &Slug::from("slug1"),
+            "title1",
+            "desc1",
+            "body1",
+            &["tag1".to_string()],
+            None,
+        )
+        .await?;
+
+        db.insert_article(
+            user2.user_id,
+            &Slug::from("slug2"),
+            "title2",
+            "desc2",
+            "body2",
+            &["tag2".to_string()],
+            None,
+        )
+        .await?;
+
+        assert_eq!(
+            Some("slug1"),
+            db.select_single_slug_or_none(Filter {
+                slug: Some(&Slug::from("slug1")),
+                ..Default::default()
+            })
+            .await
+            .as_deref()
+        );
+
+        assert_eq!(
+            Some("slug1"),
+            db.select_single_slug_or_none(Filter {
+                any_tag: &["tag1".to_string()],
+                ..Default::default()
+            })
+            .await
+            .as_deref()
+        );
+
+        assert_eq!(
+            Some("slug1"),
+            db.select_single_slug_or_none(Filter {
+                author: Some(&user1.username),
+                ..Default::default()
+            })
+            .await
+            .as_deref(),
+        );
+
+        assert_eq!(
+            None,
+            db.select_single_slug_or_none(Filter {
+                favorited_by: Some(&user1.username),
+                ..Default::default()
+            })
+            .await
+            .as_deref(),
+        );
+
+        db.insert_favorite(user1.user_id, &Slug::from("slug1"))
+            .await?;
+
+        assert_eq!(
+            Some("slug1"),
+            db.select_single_slug_or_none(Filter {
+                favorited_by: Some(&user1.username),
+                ..Default::default()
+            })
+            .await
+            .as_deref()
+        );
+
+        assert_eq!(
+            None,
+            db.select_single_slug_or_none(Filter {
+                followed_by: Some(user1.user_id),
+                ..Default::default()
             })
             .await
             .as_deref()
@@ -541,20 +1178,684 @@ mod tests {
 
         db.insert_article(
             user.user_id,
-            "slug",
+            &Slug::from("slug"),
             "title",
             "desc",
             "body",
             &["tag".to_string()],
+            None,
         )
         .await?;
 
         let error = db
-            .update_article(UserId(Uuid::new_v4()), "slug", Default::default())
+            .update_article(
+                UserId(Uuid::new_v4()),
+                &Slug::from("slug"),
+                Default::default(),
+            )
             .await
             .expect_err("Should error");
         assert_matches!(error, RwError::Forbidden);
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn unlisted_articles_should_only_be_found_by_slug_or_token() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        for (slug, share_token) in [("public", None), ("unlisted", Some("token"))] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                share_token,
+            )
+            .await?;
+        }
+
+        let listed = db
+            .select_articles(user.user_id.some(), Default::default())
+            .await?;
+        assert_eq!(
+            vec!["public"],
+            listed
+                .iter()
+                .map(|article| article.slug.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let unlisted_slug = Slug::from("unlisted");
+        let by_slug = db
+            .select_single_with_user(
+                UserId(None),
+                Filter {
+                    slug: Some(&unlisted_slug),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert_eq!(Some("token"), by_slug.share_token.as_deref());
+
+        let by_token = db
+            .select_single_with_user(
+                UserId(None),
+                Filter {
+                    share_token: Some("token"),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert_eq!(unlisted_slug, by_token.slug);
+
+        assert!(db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    share_token: Some("other"),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unlisted_article_should_keep_its_token_until_made_public() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let slug = Slug::from("slug");
+
+        db.insert_article(user.user_id, &slug, "title", "desc", "body", &[], None)
+            .await?;
+
+        for (share_token, expected) in [
+            (Some(Some("first")), Some("first")),
+            (Some(Some("second")), Some("first")),
+            (None, Some("first")),
+            (Some(None), None),
+            (Some(Some("second")), Some("second")),
+        ] {
+            db.update_article(
+                user.user_id,
+                &slug,
+                ArticleUpdate {
+                    share_token,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            let article = db
+                .select_single_with_user(
+                    UserId(None),
+                    Filter {
+                        slug: Some(&slug),
+                        ..Default::default()
+                    },
+                )
+                .await;
+            assert_eq!(expected, article.share_token.as_deref());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn updating_article_changed_since_fetched_should_fail() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        let update = || ArticleUpdate {
+            body: Some("body2"),
+            expected_updated_at: Some(&article.updated_at),
+            ..Default::default()
+        };
+
+        db.update_article(user.user_id, &Slug::from("slug"), update())
+            .await?;
+        assert_matches!(
+            db.update_article(user.user_id, &Slug::from("slug"), update())
+                .await,
+            Err(RwError::PreconditionFailed)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_any_article_should_ignore_owner() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &["tag".to_string()],
+            None,
+        )
+        .await?;
+
+        assert_matches!(
+            db.delete_article(UserId(Uuid::new_v4()), &Slug::from("slug"))
+                .await,
+            Err(RwError::Forbidden)
+        );
+        db.delete_any_article(&Slug::from("slug")).await?;
+        assert_matches!(
+            db.delete_any_article(&Slug::from("slug")).await,
+            Err(RwError::ArticleNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_articles_by_author_should_only_yield_own_articles() -> RwResult<()> {
+        use futures::TryStreamExt;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other_user, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        for (author, slug) in [(&user, "first"), (&other_user, "other"), (&user, "second")] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(user.user_id, &Slug::from("first"))
+            .await?;
+
+        let articles: Vec<_> = db
+            .stream_articles_by_author(user.user_id)
+            .try_collect()
+            .await?;
+
+        assert_eq!(
+            articles
+                .iter()
+                .map(|article| (article.slug.as_str(), article.favorites_count))
+                .collect::<Vec<_>>(),
+            [("first", 1), ("second", 0)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_article_slugs_should_yield_all_articles_oldest_first() -> RwResult<()> {
+        use futures::TryStreamExt;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other_user, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        for (author, slug) in [(&user, "first"), (&other_user, "second")] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+
+        let slugs: Vec<_> = db
+            .stream_article_slugs()
+            .map_ok(|(slug, _)| slug.0)
+            .try_collect()
+            .await?;
+
+        assert_eq!(vec!["first", "second"], slugs);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn favorited_should_only_be_set_for_favorited_articles() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (fan, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        for slug in ["a", "b", "c"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(fan.user_id, &Slug::from("a")).await?;
+        db.insert_favorite(fan.user_id, &Slug::from("c")).await?;
+        db.insert_favorite(author.user_id, &Slug::from("b")).await?;
+
+        let mut favorited: Vec<_> = db
+            .select_articles(fan.user_id.some(), Filter::default())
+            .await?
+            .into_iter()
+            .map(|article| (article.slug.0, article.favorited, article.favorites_count))
+            .collect();
+        favorited.sort();
+        assert_eq!(
+            vec![
+                ("a".to_string(), true, 1),
+                ("b".to_string(), false, 1),
+                ("c".to_string(), true, 1)
+            ],
+            favorited
+        );
+
+        let single = db
+            .select_single_with_user(
+                fan.user_id.some(),
+                Filter {
+                    slug: Some(&Slug::from("b")),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(!single.favorited);
+
+        let single = db
+            .select_single_with_user(
+                author.user_id.some(),
+                Filter {
+                    slug: Some(&Slug::from("b")),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(single.favorited);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_list_favoriting_users() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (fan, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_favorite(author.user_id, &Slug::from("slug"))
+            .await?;
+        db.insert_favorite(fan.user_id, &Slug::from("slug")).await?;
+        db.insert_follow(author.user_id, &fan.username).await?;
+
+        let users = db
+            .list_favoriting_users(
+                author.user_id.some(),
+                &Slug::from("slug"),
+                Pagination::default(),
+            )
+            .await?;
+        assert_eq!(
+            vec![
+                (fan.username.as_str(), true),
+                (author.username.as_str(), false)
+            ],
+            users
+                .iter()
+                .map(|(user, Following(following))| (user.username.as_str(), *following))
+                .collect::<Vec<_>>()
+        );
+
+        let second_page = db
+            .list_favoriting_users(
+                UserId(None),
+                &Slug::from("slug"),
+                Pagination {
+                    limit: Some(1),
+                    offset: Some(1),
+                },
+            )
+            .await?;
+        assert_eq!(1, second_page.len());
+        assert_eq!(author.username, second_page[0].0.username);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_favorites_should_report_existing_slugs() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (fan, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        for slug in ["a", "b"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(fan.user_id, &Slug::from("a")).await?;
+
+        let slugs = |slugs: &[&str]| slugs.iter().map(|s| Slug::from(*s)).collect::<Vec<_>>();
+        let mut found = db
+            .set_favorites_bulk(fan.user_id, &slugs(&["a", "b", "unknown"]), true)
+            .await?;
+        found.sort();
+        assert_eq!(slugs(&["a", "b"]), found);
+        for slug in ["a", "b"] {
+            let users = db
+                .list_favoriting_users(UserId(None), &Slug::from(slug), Pagination::default())
+                .await?;
+            assert_eq!(1, users.len());
+        }
+
+        let mut found = db
+            .set_favorites_bulk(fan.user_id, &slugs(&["a", "b", "unknown"]), false)
+            .await?;
+        found.sort();
+        assert_eq!(slugs(&["a", "b"]), found);
+        assert!(db
+            .list_favoriting_users(UserId(None), &Slug::from("b"), Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn summaries_should_leave_out_body_and_cut_description() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let long_description = "é".repeat(300);
+        db.insert_article(
+            user.user_id,
+            &Slug::from("long"),
+            "title",
+            &long_description,
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("short"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        let (long_slug, short_slug) = (Slug::from("long"), Slug::from("short"));
+        let summaries = |slug| Filter {
+            slug: Some(slug),
+            summary: true,
+            ..Default::default()
+        };
+        let long = db
+            .select_articles(UserId(None), summaries(&long_slug))
+            .await?
+            .remove(0);
+        assert_eq!("", long.body);
+        assert_eq!(
+            SUMMARY_DESCRIPTION_LENGTH as usize,
+            long.description.chars().count()
+        );
+        assert!(long.description.ends_with("é…"));
+
+        let short = db
+            .select_articles(UserId(None), summaries(&short_slug))
+            .await?
+            .remove(0);
+        assert_eq!(
+            ("", "desc"),
+            (short.body.as_str(), short.description.as_str())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_continue_after_cursor() -> RwResult<()> {
+        use realworld_domain::article::cursor::ArticleCursor;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        for slug in ["a", "b", "c"] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+
+        async fn slugs(db: &impl ArticleRepo, filter: Filter<'_>) -> RwResult<Vec<String>> {
+            Ok(db
+                .select_articles(UserId(None), filter)
+                .await?
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect())
+        }
+
+        let first_page = db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    limit: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(
+            vec!["c", "b"],
+            first_page
+                .iter()
+                .map(|article| article.slug.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let cursor = ArticleCursor::from(first_page.last().unwrap());
+        assert_eq!(
+            vec!["a"],
+            slugs(
+                &db,
+                Filter {
+                    limit: Some(2),
+                    after: Some(&cursor),
+                    ..Default::default()
+                }
+            )
+            .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn trending_should_rank_by_recent_activity() -> RwResult<()> {
+        use realworld_domain::comment::repo::CommentRepo;
+
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        for slug in ["quiet", "liked", "discussed"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(reader.user_id, &Slug::from("liked"))
+            .await?;
+        db.insert_favorite(author.user_id, &Slug::from("discussed"))
+            .await?;
+        db.insert_comment(reader.user_id, &Slug::from("discussed"), "first", &[])
+            .await?;
+        db.insert_comment(reader.user_id, &Slug::from("discussed"), "second", &[])
+            .await?;
+
+        let trending = db
+            .select_trending_articles(
+                reader.user_id.some(),
+                time::OffsetDateTime::now_utc() - time::Duration::hours(1),
+                Pagination::default(),
+            )
+            .await?;
+        assert_eq!(
+            vec![("discussed", false), ("liked", true)],
+            trending
+                .iter()
+                .map(|article| (article.slug.as_str(), article.favorited))
+                .collect::<Vec<_>>()
+        );
+
+        // A window starting in the future has no activity
+        assert!(db
+            .select_trending_articles(
+                UserId(None),
+                time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+                Pagination::default(),
+            )
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recommended_should_be_favorited_by_users_with_common_favorites() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (me, _) = db.insert_test_user(user_db_test::named_user("me")).await?;
+        let (twin, _) = db
+            .insert_test_user(user_db_test::named_user("twin"))
+            .await?;
+        let (other, _) = db
+            .insert_test_user(user_db_test::named_user("other"))
+            .await?;
+        let (author, _) = db
+            .insert_test_user(user_db_test::named_user("author"))
+            .await?;
+
+        for slug in ["a", "b", "x", "y", "z"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_article(
+            me.user_id,
+            &Slug::from("mine"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        for (user, slugs) in [
+            (&me, &["a", "b"][..]),
+            (&twin, &["a", "b", "x", "y", "mine"][..]),
+            (&other, &["a", "y", "z"][..]),
+        ] {
+            for slug in slugs {
+                db.insert_favorite(user.user_id, &Slug::from(*slug)).await?;
+            }
+        }
+
+        async fn slugs(
+            db: &impl ArticleRepo,
+            user_id: UserId,
+            pagination: Pagination,
+        ) -> RwResult<Vec<String>> {
+            Ok(db
+                .select_recommended_articles(user_id, pagination)
+                .await?
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect())
+        }
+
+        assert_eq!(
+            vec!["y", "x", "z"],
+            slugs(&db, me.user_id, Pagination::default()).await?
+        );
+        assert_eq!(
+            vec!["x"],
+            slugs(
+                &db,
+                me.user_id,
+                Pagination {
+                    limit: Some(1),
+                    offset: Some(1)
+                }
+            )
+            .await?
+        );
+        assert!(slugs(&db, author.user_id, Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
 }
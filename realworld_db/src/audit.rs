@@ -0,0 +1,170 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::audit::{AuditAction, AuditFilter, AuditRecord, NewAuditEntry};
+use realworld_domain::error::RwResult;
+
+use entrait::*;
+use futures::TryStreamExt;
+
+pub struct PgAuditLogRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::audit::AuditLogImpl for PgAuditLogRepo {
+    pub async fn record_audit(deps: &impl GetDb, entry: NewAuditEntry<'_>) -> RwResult<()> {
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO audit_log (user_id, action, target, ip_address)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            entry.user_id.0,
+            entry.action as AuditAction,
+            entry.target,
+            entry.ip_address.map(|ip_address| ip_address.to_string())
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn select_audit_entries(
+        deps: &impl GetDb,
+        filter: AuditFilter,
+    ) -> RwResult<Vec<AuditRecord>> {
+        sqlx::query_as!(
+            AuditRecord,
+            // language=PostgreSQL
+            r#"
+            SELECT
+                audit_id,
+                audit_log.user_id,
+                username "username?",
+                action "action: AuditAction",
+                target,
+                ip_address,
+                audit_log.created_at
+            FROM audit_log
+            LEFT JOIN "user" USING (user_id)
+            WHERE ($1::uuid IS NULL OR audit_log.user_id = $1)
+                AND ($2::timestamptz IS NULL OR audit_log.created_at >= $2)
+                AND ($3::timestamptz IS NULL OR audit_log.created_at < $3)
+            ORDER BY audit_log.created_at DESC, audit_id DESC
+            LIMIT $4
+            OFFSET $5
+            "#,
+            filter.user_id.map(|user_id| user_id.0),
+            filter.since,
+            filter.until,
+            filter.pagination.limit(),
+            filter.pagination.offset()
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::audit::*;
+    use realworld_domain::error::*;
+    use realworld_domain::user::UserId;
+
+    use std::net::IpAddr;
+
+    #[tokio::test]
+    async fn audit_log_should_be_filtered_by_user_and_time() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        db.record_audit(NewAuditEntry {
+            user_id: user.user_id.some(),
+            action: AuditAction::Login,
+            target: None,
+            ip_address: Some(IpAddr::from([10, 0, 0, 1])),
+        })
+        .await?;
+        db.record_audit(NewAuditEntry {
+            user_id: other.user_id.some(),
+            action: AuditAction::PasswordChange,
+            target: None,
+            ip_address: None,
+        })
+        .await?;
+        db.record_audit(NewAuditEntry {
+            user_id: UserId(None),
+            action: AuditAction::FailedLogin,
+            target: Some("nobody@example.com"),
+            ip_address: None,
+        })
+        .await?;
+
+        let all = db.select_audit_entries(AuditFilter::default()).await?;
+        assert_eq!(
+            vec![
+                AuditAction::FailedLogin,
+                AuditAction::PasswordChange,
+                AuditAction::Login
+            ],
+            all.iter().map(|entry| entry.action).collect::<Vec<_>>()
+        );
+        assert_eq!(None, all[0].username);
+        assert_eq!(Some("nobody@example.com"), all[0].target.as_deref());
+
+        let by_user = db
+            .select_audit_entries(AuditFilter {
+                user_id: Some(user.user_id),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(1, by_user.len());
+        assert_eq!(Some(&user.username), by_user[0].username.as_ref());
+        assert_eq!(Some("10.0.0.1"), by_user[0].ip_address.as_deref());
+
+        let before = db
+            .select_audit_entries(AuditFilter {
+                until: Some(all[2].created_at),
+                ..Default::default()
+            })
+            .await?;
+        assert!(before.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn audit_log_should_be_append_only() -> RwResult<()> {
+        let db = create_test_db().await;
+        db.record_audit(NewAuditEntry {
+            user_id: UserId(None),
+            action: AuditAction::FailedLogin,
+            target: Some("nobody@example.com"),
+            ip_address: None,
+        })
+        .await?;
+
+        let pg_pool = &db.pg_pool;
+        assert!(sqlx::query("UPDATE audit_log SET target = NULL")
+            .execute(pg_pool)
+            .await
+            .is_err());
+        assert!(sqlx::query("DELETE FROM audit_log")
+            .execute(pg_pool)
+            .await
+            .is_err());
+        assert_eq!(
+            1,
+            db.select_audit_entries(AuditFilter::default()).await?.len()
+        );
+
+        Ok(())
+    }
+}
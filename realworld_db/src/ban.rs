@@ -0,0 +1,101 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::error::*;
+use realworld_domain::user::repo::Banned;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+
+pub struct PgBanRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::BanRepoImpl for PgBanRepo {
+    pub async fn set_user_banned(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        Banned(banned): Banned,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE "user"
+            SET banned_at = CASE WHEN $2 THEN COALESCE(banned_at, now()) END
+            WHERE user_id = $1
+            "#,
+            user_id,
+            banned
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn is_user_banned(deps: &impl GetDb, UserId(user_id): UserId) -> RwResult<bool> {
+        let banned = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM "user" WHERE user_id = $1 AND banned_at IS NOT NULL) "banned!""#,
+            user_id
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(banned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::repo::{BanRepo, Banned, UserRepo};
+
+    #[tokio::test]
+    async fn banned_users_should_be_listed_as_banned() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        db.set_user_banned(user.user_id, Banned(true)).await?;
+        assert!(db.is_user_banned(user.user_id).await?);
+        assert!(!db.is_user_banned(other.user_id).await?);
+
+        let banned: Vec<_> = db
+            .list_users(Default::default())
+            .await?
+            .into_iter()
+            .map(|(user, _, banned)| (user.username, banned))
+            .collect();
+        assert_eq!(
+            vec![
+                (user.username.clone(), Banned(true)),
+                (other.username, Banned(false))
+            ],
+            banned
+        );
+
+        db.set_user_banned(user.user_id, Banned(false)).await?;
+        assert!(!db.is_user_banned(user.user_id).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn banned_users_should_not_be_streamed() -> RwResult<()> {
+        use futures::TryStreamExt;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        db.set_user_banned(user.user_id, Banned(true)).await?;
+
+        let usernames: Vec<String> = db.stream_usernames().try_collect().await?;
+        assert_eq!(vec![other.username], usernames);
+
+        Ok(())
+    }
+}
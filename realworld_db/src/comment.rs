@@ -1,45 +1,62 @@
 use crate::{DbResultExt, GetDb};
 
-use realworld_domain::comment::repo::Comment;
+use realworld_domain::article::{ArticleId, Slug};
+use realworld_domain::comment::repo::{ArticleComment, Comment, ListOptions, SortDirection};
+use realworld_domain::comment::CommentId;
 use realworld_domain::error::*;
 use realworld_domain::user::UserId;
 
-use futures::TryStreamExt;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use uuid::Uuid;
 
 use entrait::*;
 
 pub struct PgCommentRepo;
 
-#[entrait]
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
 impl realworld_domain::comment::repo::CommentRepoImpl for PgCommentRepo {
     pub async fn list_comments(
         deps: &impl GetDb,
         current_user: UserId<Option<Uuid>>,
-        article_id: Uuid,
+        ArticleId(article_id): ArticleId,
+        options: ListOptions,
     ) -> RwResult<Vec<Comment>> {
         let comments = sqlx::query_as!(
-        Comment,
-        r#"
-        SELECT
-            comment_id,
-            comment.created_at,
-            comment.updated_at,
-            comment.body,
-            author.username author_username,
-            author.bio author_bio,
-            author.image author_image,
-            exists(
-                SELECT 1 FROM app.follow WHERE followed_user_id = author.user_id AND following_user_id = $1
-            ) "following_author!"
-        FROM app.article_comment comment
-        INNER JOIN app.user author using (user_id)
-        WHERE article_id = $2
-        ORDER by created_at
-        "#,
-        current_user.0,
-        article_id
-    )
+            Comment,
+            r#"
+            SELECT
+                comment.comment_id "comment_id!: CommentId",
+                comment.created_at "created_at!",
+                comment.updated_at "updated_at!",
+                comment.body "body!",
+                comment.author_id "author_id!",
+                comment.author_username "author_username!",
+                comment.likes_count "likes_count!",
+                exists(
+                    SELECT 1 FROM comment_like l WHERE l.comment_id = comment.comment_id AND l.user_id = $1
+                ) "liked!",
+                comment.mentions "mentions!"
+            FROM comment_view comment
+            WHERE article_id = $2 AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = $1 AND blocked_user_id = comment.author_id
+            )
+            ORDER BY
+                -- `comment_id` breaks ties between comments created in the same transaction
+                CASE WHEN $3 THEN comment.created_at END DESC,
+                CASE WHEN $3 THEN comment.comment_id END DESC,
+                comment.created_at,
+                comment.comment_id
+            LIMIT $4
+            OFFSET $5
+            "#,
+            current_user.0,
+            article_id,
+            options.direction == SortDirection::Desc,
+            options.limit,
+            options.offset.unwrap_or(0)
+        )
         .fetch(&deps.get_db().pg_pool)
         .try_collect()
         .await
@@ -48,68 +65,156 @@ impl realworld_domain::comment::repo::CommentRepoImpl for PgCommentRepo {
         Ok(comments)
     }
 
+    pub async fn count_comments(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        ArticleId(article_id): ArticleId,
+    ) -> RwResult<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT count(*) "count!"
+            FROM comment_view comment
+            WHERE article_id = $2 AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = $1 AND blocked_user_id = comment.author_id
+            )
+            "#,
+            current_user.0,
+            article_id
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn find_comment(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        Slug(article_slug): &Slug,
+        CommentId(comment_id): CommentId,
+    ) -> RwResult<Comment> {
+        sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT
+                comment.comment_id "comment_id!: CommentId",
+                comment.created_at "created_at!",
+                comment.updated_at "updated_at!",
+                comment.body "body!",
+                comment.author_id "author_id!",
+                comment.author_username "author_username!",
+                comment.likes_count "likes_count!",
+                exists(
+                    SELECT 1 FROM comment_like l WHERE l.comment_id = comment.comment_id AND l.user_id = $1
+                ) "liked!",
+                comment.mentions "mentions!"
+            FROM comment_view comment
+            INNER JOIN article USING (article_id)
+            WHERE comment.comment_id = $2 AND slug = $3
+            "#,
+            current_user.0,
+            comment_id,
+            article_slug
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)
+    }
+
     pub async fn insert_comment(
         deps: &impl GetDb,
         current_user: UserId,
-        article_slug: &str,
+        Slug(article_slug): &Slug,
         body: &str,
+        mentions: &[String],
     ) -> RwResult<Comment> {
-        let comment = sqlx::query_as!(
-            Comment,
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        let comment_id = sqlx::query_scalar!(
             r#"
-            WITH inserted_comment AS (
-                INSERT INTO app.article_comment (article_id, user_id, body)
-                    SELECT article_id, $1, $2
-                    FROM app.article
-                    WHERE slug = $3
-                RETURNING comment_id, created_at, updated_at, body
-            )
-            SELECT
-                comment_id,
-                comment.created_at,
-                comment.updated_at,
-                body,
-                author.username author_username,
-                author.bio author_bio,
-                author.image author_image,
-                false "following_author!"
-            FROM inserted_comment comment
-            INNER JOIN app.user author ON user_id = $1
+            INSERT INTO article_comment (article_id, user_id, body)
+                SELECT article_id, $1, $2
+                FROM article
+                WHERE slug = $3
+            RETURNING comment_id
             "#,
             current_user.0,
             body,
             article_slug,
         )
-        .fetch_optional(&deps.get_db().pg_pool)
+        .fetch_optional(&mut *tx)
         .await
         .to_rw_err()?
         .ok_or(RwError::ArticleNotFound)?;
 
+        // Mentions of users that don't exist aren't inserted
+        sqlx::query!(
+            r#"
+            INSERT INTO comment_mention (comment_id, user_id)
+                SELECT $1, user_id
+                FROM "user"
+                WHERE username = ANY($2)
+            "#,
+            comment_id,
+            mentions
+        )
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        // Selected after the insert, since a query doesn't see the rows inserted by its own `WITH`
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT
+                comment.comment_id "comment_id!: CommentId",
+                comment.created_at "created_at!",
+                comment.updated_at "updated_at!",
+                comment.body "body!",
+                comment.author_id "author_id!",
+                comment.author_username "author_username!",
+                comment.likes_count "likes_count!",
+                exists(
+                    SELECT 1 FROM comment_like l WHERE l.comment_id = comment.comment_id AND l.user_id = $1
+                ) "liked!",
+                comment.mentions "mentions!"
+            FROM comment_view comment
+            WHERE comment.comment_id = $2
+            "#,
+            current_user.0,
+            comment_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
         Ok(comment)
     }
 
     pub async fn delete_comment(
         deps: &impl GetDb,
         current_user: UserId,
-        article_slug: &str,
-        comment_id: i64,
+        Slug(article_slug): &Slug,
+        CommentId(comment_id): CommentId,
     ) -> RwResult<()> {
         let result = sqlx::query!(
             r#"
             WITH deleted_comment AS (
-                DELETE FROM app.article_comment
+                DELETE FROM article_comment
                 WHERE
                     comment_id = $1
                 AND
-                    article_id IN (SELECT article_id FROM app.article WHERE slug = $2)
+                    article_id IN (SELECT article_id FROM article WHERE slug = $2)
                 AND
                     user_id = $3
                 RETURNING 1
             )
             SELECT
                 EXISTS(
-                    SELECT 1 FROM app.article_comment
-                    INNER JOIN app.article USING (article_id)
+                    SELECT 1 FROM article_comment
+                    INNER JOIN article USING (article_id)
                     WHERE comment_id = $1 AND slug = $2
                 ) "existed!",
                 EXISTS(SELECT 1 FROM deleted_comment) "deleted!"
@@ -130,6 +235,152 @@ impl realworld_domain::comment::repo::CommentRepoImpl for PgCommentRepo {
             Err(RwError::ArticleNotFound)
         }
     }
+
+    pub async fn insert_like(
+        deps: &impl GetDb,
+        current_user: UserId,
+        Slug(article_slug): &Slug,
+        CommentId(comment_id): CommentId,
+    ) -> RwResult<()> {
+        sqlx::query_scalar!(
+            r#"
+            WITH selected_comment AS (
+                SELECT comment_id
+                FROM article_comment
+                INNER JOIN article USING (article_id)
+                WHERE comment_id = $1 AND slug = $2
+            ),
+            inserted_like AS (
+                INSERT INTO comment_like (comment_id, user_id)
+                    SELECT comment_id, $3 FROM selected_comment
+                -- if the comment is already liked
+                ON CONFLICT DO NOTHING
+            )
+            SELECT comment_id FROM selected_comment
+            "#,
+            comment_id,
+            article_slug,
+            current_user.0
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        Ok(())
+    }
+
+    pub async fn delete_like(
+        deps: &impl GetDb,
+        current_user: UserId,
+        Slug(article_slug): &Slug,
+        CommentId(comment_id): CommentId,
+    ) -> RwResult<()> {
+        sqlx::query_scalar!(
+            r#"
+            WITH selected_comment AS (
+                SELECT comment_id
+                FROM article_comment
+                INNER JOIN article USING (article_id)
+                WHERE comment_id = $1 AND slug = $2
+            ),
+            deleted_like AS (
+                DELETE FROM comment_like
+                WHERE comment_id = (SELECT comment_id FROM selected_comment)
+                AND user_id = $3
+            )
+            SELECT comment_id FROM selected_comment
+            "#,
+            comment_id,
+            article_slug,
+            current_user.0
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        Ok(())
+    }
+
+    pub async fn delete_any_comment(
+        deps: &impl GetDb,
+        Slug(article_slug): &Slug,
+        CommentId(comment_id): CommentId,
+    ) -> RwResult<()> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM article_comment
+            WHERE
+                comment_id = $1
+            AND
+                article_id IN (SELECT article_id FROM article WHERE slug = $2)
+            "#,
+            comment_id,
+            article_slug
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::ArticleNotFound)
+        }
+    }
+
+    pub fn stream_comments_by_author(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> BoxStream<'static, RwResult<ArticleComment>> {
+        // The stream outlives `deps`, so it gets its own handle to the pool
+        let pg_pool = deps.get_db().pg_pool.clone();
+
+        async_stream::try_stream! {
+            let mut rows = sqlx::query!(
+                r#"
+                SELECT
+                    article.slug "article_slug: Slug",
+                    comment.comment_id "comment_id!: CommentId",
+                    comment.created_at "created_at!",
+                    comment.updated_at "updated_at!",
+                    comment.body "body!",
+                    comment.author_id "author_id!",
+                    comment.author_username "author_username!",
+                    comment.likes_count "likes_count!",
+                    exists(
+                        SELECT 1 FROM comment_like l WHERE l.comment_id = comment.comment_id AND l.user_id = $1
+                    ) "liked!",
+                    comment.mentions "mentions!"
+                FROM comment_view comment
+                INNER JOIN article USING (article_id)
+                WHERE comment.author_id = $1
+                ORDER BY comment.created_at, comment.comment_id
+                "#,
+                user_id
+            )
+            .fetch(&pg_pool);
+
+            while let Some(row) = rows.try_next().await.to_rw_err()? {
+                yield ArticleComment {
+                    article_slug: row.article_slug,
+                    comment: Comment {
+                        comment_id: row.comment_id,
+                        created_at: row.created_at,
+                        updated_at: row.updated_at,
+                        body: row.body,
+                        author_id: row.author_id,
+                        author_username: row.author_username,
+                        likes_count: row.likes_count,
+                        liked: row.liked,
+                        mentions: row.mentions,
+                    },
+                };
+            }
+        }
+        .boxed()
+    }
 }
 
 #[cfg(test)]
@@ -145,11 +396,12 @@ mod tests {
     async fn insert_test_article(deps: &impl ArticleRepo, current_user: UserId) -> RwResult<()> {
         deps.insert_article(
             current_user,
-            "slug",
+            &Slug::from("slug"),
             "title",
             "desc",
             "body",
             &["tag".to_string()],
+            None,
         )
         .await?;
         Ok(())
@@ -160,29 +412,221 @@ mod tests {
         let db = create_test_db().await;
         let (user, _) = db.insert_test_user(Default::default()).await?;
         insert_test_article(&db, user.user_id).await?;
-        let article_id = db.fetch_article_id("slug").await?;
+        let article_id = db.fetch_article_id(&Slug::from("slug")).await?;
 
-        let inserted_comment = db.insert_comment(user.user_id, "slug", "body").await?;
+        let inserted_comment = db
+            .insert_comment(user.user_id, &Slug::from("slug"), "body", &[])
+            .await?;
 
         assert_eq!(
-            db.list_comments(user.user_id.some(), article_id).await?,
-            &[inserted_comment.clone()]
+            db.find_comment(
+                user.user_id.some(),
+                &Slug::from("slug"),
+                inserted_comment.comment_id
+            )
+            .await?,
+            inserted_comment
         );
-
         assert_eq!(
-            db.list_comments(user.user_id.some(), Uuid::new_v4())
+            db.list_comments(user.user_id.some(), article_id, Default::default())
                 .await?,
+            std::slice::from_ref(&inserted_comment)
+        );
+
+        assert_eq!(
+            db.list_comments(
+                user.user_id.some(),
+                ArticleId(Uuid::new_v4()),
+                Default::default()
+            )
+            .await?,
             &[]
         );
 
-        db.delete_comment(user.user_id, "slug", inserted_comment.comment_id)
+        assert_matches::assert_matches!(
+            db.delete_comment(
+                UserId(Uuid::new_v4()),
+                &Slug::from("slug"),
+                inserted_comment.comment_id
+            )
+            .await,
+            Err(RwError::Forbidden)
+        );
+
+        db.delete_any_comment(&Slug::from("slug"), inserted_comment.comment_id)
             .await?;
 
         assert_eq!(
-            db.list_comments(user.user_id.some(), article_id).await?,
+            db.list_comments(user.user_id.some(), article_id, Default::default())
+                .await?,
             &[]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn list_comments_should_paginate_and_order() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        insert_test_article(&db, user.user_id).await?;
+        let article_id = db.fetch_article_id(&Slug::from("slug")).await?;
+
+        for body in ["1", "2", "3"] {
+            db.insert_comment(user.user_id, &Slug::from("slug"), body, &[])
+                .await?;
+        }
+
+        async fn bodies(
+            db: &impl CommentRepo,
+            article_id: ArticleId,
+            options: ListOptions,
+        ) -> RwResult<Vec<String>> {
+            Ok(db
+                .list_comments(UserId(None), article_id, options)
+                .await?
+                .into_iter()
+                .map(|comment| comment.body)
+                .collect())
+        }
+
+        assert_eq!(
+            bodies(&db, article_id, Default::default()).await?,
+            ["1", "2", "3"]
+        );
+        assert_eq!(
+            bodies(
+                &db,
+                article_id,
+                ListOptions {
+                    direction: SortDirection::Desc,
+                    ..Default::default()
+                }
+            )
+            .await?,
+            ["3", "2", "1"]
+        );
+        assert_eq!(
+            bodies(
+                &db,
+                article_id,
+                ListOptions {
+                    limit: Some(1),
+                    offset: Some(1),
+                    direction: SortDirection::Desc
+                }
+            )
+            .await?,
+            ["2"]
+        );
+        assert_eq!(3, db.count_comments(UserId(None), article_id).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mentions_of_existing_users_should_be_stored() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        db.insert_test_user(user_db_test::named_user("bob")).await?;
+        db.insert_test_user(user_db_test::named_user("carol"))
+            .await?;
+        insert_test_article(&db, user.user_id).await?;
+
+        let comment = db
+            .insert_comment(
+                user.user_id,
+                &Slug::from("slug"),
+                "@carol @nobody @bob",
+                &["carol".to_string(), "nobody".to_string(), "bob".to_string()],
+            )
+            .await?;
+        assert_eq!(vec!["bob", "carol"], comment.mentions);
+        assert_eq!(
+            db.find_comment(UserId(None), &Slug::from("slug"), comment.comment_id)
+                .await?
+                .mentions,
+            comment.mentions
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_comments_by_author_should_include_article_slug() -> RwResult<()> {
+        use futures::TryStreamExt;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other_user, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        insert_test_article(&db, user.user_id).await?;
+
+        db.insert_comment(user.user_id, &Slug::from("slug"), "mine", &[])
+            .await?;
+        db.insert_comment(other_user.user_id, &Slug::from("slug"), "theirs", &[])
+            .await?;
+
+        let comments: Vec<_> = db
+            .stream_comments_by_author(user.user_id)
+            .try_collect()
+            .await?;
+
+        assert_eq!(
+            comments
+                .iter()
+                .map(|comment| (comment.article_slug.as_str(), comment.comment.body.as_str()))
+                .collect::<Vec<_>>(),
+            [("slug", "mine")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn likes_should_be_counted_and_relative_to_current_user() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other_user, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        insert_test_article(&db, user.user_id).await?;
+
+        let comment_id = db
+            .insert_comment(user.user_id, &Slug::from("slug"), "body", &[])
+            .await?
+            .comment_id;
+        db.insert_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        db.insert_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        db.insert_like(other_user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+
+        let comment = db
+            .find_comment(user.user_id.some(), &Slug::from("slug"), comment_id)
+            .await?;
+        assert_eq!((2, true), (comment.likes_count, comment.liked));
+
+        db.delete_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        let comment = db
+            .find_comment(user.user_id.some(), &Slug::from("slug"), comment_id)
+            .await?;
+        assert_eq!((1, false), (comment.likes_count, comment.liked));
+
+        assert_matches::assert_matches!(
+            db.insert_like(user.user_id, &Slug::from("other-slug"), comment_id)
+                .await,
+            Err(RwError::ArticleNotFound)
+        );
+        assert_matches::assert_matches!(
+            db.find_comment(
+                UserId(None),
+                &Slug::from("slug"),
+                CommentId(comment_id.0 + 1)
+            )
+            .await,
+            Err(RwError::ArticleNotFound)
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,290 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::data_export::{DataExportRecord, DataExportStatus};
+use realworld_domain::error::RwResult;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use futures::TryStreamExt;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct PgDataExportRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::data_export::DataExportRepoImpl for PgDataExportRepo {
+    pub async fn insert_data_export(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<DataExportRecord> {
+        sqlx::query_as!(
+            DataExportRecord,
+            // language=PostgreSQL
+            r#"
+            INSERT INTO data_export (user_id)
+            VALUES ($1)
+            RETURNING data_export_id, user_id, status "status: DataExportStatus", requested_at, finished_at
+            "#,
+            user_id
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn find_latest_data_export(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<Option<DataExportRecord>> {
+        sqlx::query_as!(
+            DataExportRecord,
+            // language=PostgreSQL
+            r#"
+            SELECT data_export_id, user_id, status "status: DataExportStatus", requested_at, finished_at
+            FROM data_export
+            WHERE user_id = $1
+            ORDER BY requested_at DESC
+            LIMIT 1
+            "#,
+            user_id
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn claim_pending_data_exports(
+        deps: &impl GetDb,
+        limit: i64,
+    ) -> RwResult<Vec<DataExportRecord>> {
+        sqlx::query_as!(
+            DataExportRecord,
+            // language=PostgreSQL
+            r#"
+            UPDATE data_export
+            SET status = 'running'
+            WHERE data_export_id IN (
+                SELECT data_export_id
+                FROM data_export
+                WHERE status = 'pending'
+                ORDER BY requested_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING data_export_id, user_id, status "status: DataExportStatus", requested_at, finished_at
+            "#,
+            limit
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn finish_data_export(
+        deps: &impl GetDb,
+        data_export_id: Uuid,
+        archive: Option<&[u8]>,
+    ) -> RwResult<()> {
+        let status = if archive.is_some() {
+            DataExportStatus::Ready
+        } else {
+            DataExportStatus::Failed
+        };
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            UPDATE data_export
+            SET status = $2, archive = $3, finished_at = now()
+            WHERE data_export_id = $1
+            "#,
+            data_export_id,
+            status as DataExportStatus,
+            archive
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn find_data_export_archive(
+        deps: &impl GetDb,
+        data_export_id: Uuid,
+    ) -> RwResult<Option<Vec<u8>>> {
+        let archive = sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            SELECT archive
+            FROM data_export
+            WHERE data_export_id = $1
+            "#,
+            data_export_id
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(archive.flatten())
+    }
+
+    pub async fn purge_data_export_archives(
+        deps: &impl GetDb,
+        finished_before: OffsetDateTime,
+    ) -> RwResult<u64> {
+        let result = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            UPDATE data_export
+            SET archive = NULL
+            WHERE archive IS NOT NULL AND finished_at < $1
+            "#,
+            finished_before
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn select_favorited_slugs(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<Vec<String>> {
+        sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            SELECT article.slug
+            FROM article_favorite
+            INNER JOIN article USING (article_id)
+            WHERE article_favorite.user_id = $1
+            ORDER BY article_favorite.created_at
+            "#,
+            user_id
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn select_followed_usernames(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<Vec<String>> {
+        sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            SELECT "user".username
+            FROM follow
+            INNER JOIN "user" ON "user".user_id = follow.followed_user_id
+            WHERE follow.following_user_id = $1
+            ORDER BY follow.created_at
+            "#,
+            user_id
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::article::Slug;
+    use realworld_domain::data_export::DataExportRepo;
+    use realworld_domain::user::repo::UserRepo;
+
+    #[tokio::test]
+    async fn data_export_lifecycle() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        assert_eq!(None, db.find_latest_data_export(user.user_id).await?);
+        let export = db.insert_data_export(user.user_id).await?;
+        assert_eq!(DataExportStatus::Pending, export.status);
+
+        let claimed = db.claim_pending_data_exports(10).await?;
+        assert!(claimed
+            .iter()
+            .any(|claimed| claimed.data_export_id == export.data_export_id
+                && claimed.status == DataExportStatus::Running));
+        assert!(!db
+            .claim_pending_data_exports(10)
+            .await?
+            .iter()
+            .any(|claimed| claimed.data_export_id == export.data_export_id));
+
+        db.finish_data_export(export.data_export_id, Some(b"{}"))
+            .await?;
+        let latest = db.find_latest_data_export(user.user_id).await?.unwrap();
+        assert_eq!(DataExportStatus::Ready, latest.status);
+        assert!(latest.finished_at.is_some());
+        assert_eq!(
+            Some(b"{}".to_vec()),
+            db.find_data_export_archive(export.data_export_id).await?
+        );
+        assert_eq!(None, db.find_latest_data_export(other.user_id).await?);
+
+        let finished_at = latest.finished_at.unwrap();
+        assert_eq!(0, db.purge_data_export_archives(finished_at).await?);
+        assert!(db
+            .find_data_export_archive(export.data_export_id)
+            .await?
+            .is_some());
+        db.purge_data_export_archives(finished_at + time::Duration::seconds(1))
+            .await?;
+        assert_eq!(
+            None,
+            db.find_data_export_archive(export.data_export_id).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_select_favorites_and_follows() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+        db.insert_article(
+            other.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_favorite(user.user_id, &Slug::from("slug"))
+            .await?;
+        db.insert_follow(user.user_id, &other.username).await?;
+
+        assert_eq!(
+            vec!["slug".to_string()],
+            db.select_favorited_slugs(user.user_id).await?
+        );
+        assert_eq!(
+            vec![other.username.clone()],
+            db.select_followed_usernames(user.user_id).await?
+        );
+        assert!(db.select_favorited_slugs(other.user_id).await?.is_empty());
+        assert!(db
+            .select_followed_usernames(other.user_id)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
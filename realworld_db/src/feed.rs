@@ -0,0 +1,202 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::Slug;
+use realworld_domain::error::*;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+
+pub struct PgFeedRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::article::feed::FeedRepoImpl for PgFeedRepo {
+    pub async fn fan_out_article(deps: &impl GetDb, Slug(slug): &Slug) -> RwResult<u64> {
+        let count = sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            WITH selected_article AS (
+                SELECT article_id, user_id, created_at FROM article WHERE slug = $1
+            ),
+            inserted_entry AS (
+                INSERT INTO feed_entry (user_id, article_id, author_id, created_at)
+                    SELECT
+                        follow.following_user_id,
+                        selected_article.article_id,
+                        selected_article.user_id,
+                        selected_article.created_at
+                    FROM selected_article
+                    INNER JOIN follow ON follow.followed_user_id = selected_article.user_id
+                -- if the article was already fanned out
+                ON CONFLICT DO NOTHING
+                RETURNING user_id
+            )
+            SELECT (SELECT count(*) FROM inserted_entry) "count!" FROM selected_article
+            "#,
+            slug
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        Ok(count as u64)
+    }
+
+    pub async fn insert_author_into_feed(
+        deps: &impl GetDb,
+        UserId(follower_id): UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO feed_entry (user_id, article_id, author_id, created_at)
+                SELECT $1, article.article_id, article.user_id, article.created_at
+                FROM article
+                INNER JOIN "user" author USING (user_id)
+                WHERE author.username = $2
+            ON CONFLICT DO NOTHING
+            "#,
+            follower_id,
+            username
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn delete_author_from_feed(
+        deps: &impl GetDb,
+        UserId(follower_id): UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            DELETE FROM feed_entry
+            WHERE user_id = $1
+            AND author_id = (SELECT user_id FROM "user" WHERE username = $2)
+            "#,
+            follower_id,
+            username
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn rebuild_feeds(deps: &impl GetDb) -> RwResult<u64> {
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        sqlx::query!("DELETE FROM feed_entry")
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+        let result = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO feed_entry (user_id, article_id, author_id, created_at)
+                SELECT follow.following_user_id, article.article_id, article.user_id, article.created_at
+                FROM follow
+                INNER JOIN article ON article.user_id = follow.followed_user_id
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::article::feed::FeedRepo;
+    use realworld_domain::article::repo::{ArticleRepo, Filter};
+    use realworld_domain::article::Slug;
+    use realworld_domain::error::{RwError, RwResult};
+    use realworld_domain::user::repo::UserRepo;
+    use realworld_domain::user::UserId;
+
+    use assert_matches::*;
+
+    async fn feed_slugs(db: &impl ArticleRepo, user_id: UserId) -> RwResult<Vec<String>> {
+        Ok(db
+            .select_articles(
+                user_id.some(),
+                Filter {
+                    in_feed_of: Some(user_id),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .map(|article| article.slug.0)
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn feeds_should_follow_articles_and_follows() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (follower, _) = db.insert_test_user(other_user()).await?;
+
+        db.insert_article(
+            author.user_id,
+            &Slug::from("before"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_follow(follower.user_id, &author.username).await?;
+        db.insert_author_into_feed(follower.user_id, &author.username)
+            .await?;
+        assert_eq!(vec!["before"], feed_slugs(&db, follower.user_id).await?);
+
+        db.insert_article(
+            author.user_id,
+            &Slug::from("after"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        assert_eq!(1, db.fan_out_article(&Slug::from("after")).await?);
+        assert_eq!(0, db.fan_out_article(&Slug::from("after")).await?);
+        assert_eq!(
+            vec!["after", "before"],
+            feed_slugs(&db, follower.user_id).await?
+        );
+        assert!(feed_slugs(&db, author.user_id).await?.is_empty());
+
+        db.delete_author_from_feed(follower.user_id, &author.username)
+            .await?;
+        assert!(feed_slugs(&db, follower.user_id).await?.is_empty());
+
+        assert_eq!(2, db.rebuild_feeds().await?);
+        assert_eq!(
+            vec!["after", "before"],
+            feed_slugs(&db, follower.user_id).await?
+        );
+
+        assert_matches!(
+            db.fan_out_article(&Slug::from("unknown")).await,
+            Err(RwError::ArticleNotFound)
+        );
+        Ok(())
+    }
+}
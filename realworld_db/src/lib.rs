@@ -4,35 +4,405 @@ use realworld_domain::error::{RwError, RwResult};
 
 use anyhow::Context;
 use entrait::entrait_export as entrait;
-use sqlx::error::DatabaseError;
-use sqlx::PgPool;
+use futures::future::BoxFuture;
+use sqlx::error::{BoxDynError, DatabaseError};
+use sqlx::migrate::{Migrate, Migration, MigrationSource, MigrationType, Migrator};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::borrow::Cow;
+use std::panic::Location;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 pub mod article;
+pub mod audit;
+pub mod ban;
+pub mod bookmark;
 pub mod comment;
+pub mod data_export;
+pub mod email_verification;
+pub mod feed;
+pub mod login_attempt;
+pub mod notification;
+pub mod password_reset;
+pub mod recommendation;
+pub mod refresh_token;
+pub mod report;
+pub mod session;
+pub mod settings;
+pub mod stats;
 pub mod user;
+pub mod view;
+
+/// The schema of the tables, unless another one is configured
+pub const DEFAULT_SCHEMA: &str = "app";
 
 #[derive(Clone)]
 pub struct Db {
     pub pg_pool: PgPool,
+    schema: String,
+}
+
+///
+/// Sizing and timeouts of the connection pool, and the schema its connections use.
+///
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// Connections that are kept open even when idle
+    pub min_connections: u32,
+    pub max_connections: u32,
+    /// How long to wait for a free connection before a query fails
+    pub acquire_timeout: Duration,
+    /// Idle connections above `min_connections` are closed after this long
+    pub idle_timeout: Option<Duration>,
+    /// Prepared statements cached per connection. 0 disables the cache.
+    pub statement_cache_capacity: usize,
+    /// Schema of the tables, which connections find them in by their `search_path`
+    pub schema: String,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_connections: 0,
+            max_connections: 50,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            statement_cache_capacity: 100,
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+}
+
+///
+/// Connections of the pool at one point in time.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PoolStatus {
+    /// Whether a connection could be acquired and used
+    pub healthy: bool,
+    /// Open connections, idle or in use
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub max_connections: u32,
+}
+
+///
+/// A migration of this build, and whether the database has run it.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+///
+/// How the schema of the database compares to the migrations of this build.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaStatus {
+    /// Every migration of this build, oldest first
+    pub migrations: Vec<MigrationStatus>,
+    /// Migrations the database has run that this build doesn't know, i.e. of a newer build
+    pub unknown_versions: Vec<i64>,
+}
+
+impl SchemaStatus {
+    fn new(migrator: &Migrator, applied_versions: &[i64]) -> Self {
+        let migrations: Vec<_> = migrator
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied_versions.contains(&migration.version),
+            })
+            .collect();
+        let unknown_versions = applied_versions
+            .iter()
+            .copied()
+            .filter(|version| !migrations.iter().any(|known| known.version == *version))
+            .collect();
+
+        Self {
+            migrations,
+            unknown_versions,
+        }
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus> {
+        self.migrations
+            .iter()
+            .filter(|migration| !migration.applied)
+    }
+
+    /// Fails unless the database has run exactly the migrations of this build
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if let Some(version) = self.unknown_versions.first() {
+            anyhow::bail!(
+                "the database has run migration {version}, which this build doesn't know; is the build outdated?"
+            );
+        }
+        if let Some(migration) = self.pending().next() {
+            anyhow::bail!(
+                "migration {} ({}) hasn't been run, see the `migrate` command",
+                migration.version,
+                migration.description
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Db {
-    pub async fn init(url: &str) -> anyhow::Result<Self> {
+    ///
+    /// Connect, and run the migrations that haven't been run yet if `migrate`.
+    /// Fails unless the database then has the schema this build expects.
+    ///
+    pub async fn init(url: &str, config: &PoolConfig, migrate: bool) -> anyhow::Result<Self> {
+        let db = Self::connect(url, config).await?;
+        if migrate {
+            db.migrate().await?;
+        }
+        db.schema_status().await?.verify()?;
+        Ok(db)
+    }
+
+    pub async fn connect(url: &str, config: &PoolConfig) -> anyhow::Result<Self> {
+        let options = sqlx::postgres::PgConnectOptions::from_str(url)
+            .context("invalid database_url")?
+            .statement_cache_capacity(config.statement_cache_capacity);
+
+        // `public` for the extensions and collations created there
+        let set_search_path = format!("SET search_path TO {}, public", config.schema);
+
         let pg_pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(50)
-            .connect(url)
+            .min_connections(config.min_connections)
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .after_connect(move |connection, _| {
+                let set_search_path = set_search_path.clone();
+                Box::pin(async move {
+                    connection.execute(set_search_path.as_str()).await?;
+                    Ok(())
+                })
+            })
+            .connect_with(options)
             .await
             .context("could not connect to database_url")?;
 
-        sqlx::migrate!("../migrations").run(&pg_pool).await?;
+        Ok(Db {
+            pg_pool,
+            schema: config.schema.clone(),
+        })
+    }
+
+    async fn migrator(&self) -> anyhow::Result<Migrator> {
+        Ok(Migrator::new(SchemaMigrations(&self.schema)).await?)
+    }
+
+    ///
+    /// A connection of its own for running migrations, which finds sqlx' table of them
+    /// first in its `search_path`.
+    ///
+    /// The default schema has always had the table in `public`. Other schemas are created
+    /// up front to have it, so that deployments sharing a database don't mix up their migrations.
+    ///
+    async fn migration_connection(&self) -> anyhow::Result<PgConnection> {
+        let mut connection = PgConnection::connect_with(&self.pg_pool.connect_options()).await?;
+
+        let search_path = if self.schema == DEFAULT_SCHEMA {
+            "public".to_string()
+        } else {
+            connection
+                .execute(format!("CREATE SCHEMA IF NOT EXISTS {}", self.schema).as_str())
+                .await?;
+            format!("{}, public", self.schema)
+        };
+        connection
+            .execute(format!("SET search_path TO {search_path}").as_str())
+            .await?;
+
+        Ok(connection)
+    }
+
+    /// Run the migrations that haven't been run yet
+    pub async fn migrate(&self) -> anyhow::Result<()> {
+        let mut connection = self.migration_connection().await?;
+        self.migrator().await?.run(&mut connection).await?;
+        connection.close().await?;
+        Ok(())
+    }
+
+    /// Which migrations of this build the database has run, creating sqlx' table of them if missing
+    pub async fn schema_status(&self) -> anyhow::Result<SchemaStatus> {
+        let mut connection = self.migration_connection().await?;
+        connection.ensure_migrations_table().await?;
+        let applied_versions: Vec<i64> = connection
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|applied| applied.version)
+            .collect();
+        connection.close().await?;
+
+        Ok(SchemaStatus::new(
+            &self.migrator().await?,
+            &applied_versions,
+        ))
+    }
+
+    ///
+    /// Revert the migrations newer than `target`, newest first.
+    ///
+    /// Only migrations with a `.down.sql` script can be reverted,
+    /// so this fails without reverting anything if any of them lacks one.
+    ///
+    pub async fn revert_migrations(&self, target: i64) -> anyhow::Result<()> {
+        let migrator = self.migrator().await?;
+
+        if let Some(migration) = migrator.iter().find(|migration| {
+            migration.version > target && migration.migration_type == MigrationType::Simple
+        }) {
+            anyhow::bail!(
+                "migration {} ({}) can't be reverted",
+                migration.version,
+                migration.description
+            );
+        }
+
+        let mut connection = self.migration_connection().await?;
+        migrator.undo(&mut connection, target).await?;
+        connection.close().await?;
+        Ok(())
+    }
+
+    /// Checks that a connection can be acquired within the acquire timeout, and that it responds
+    pub async fn pool_status(&self) -> PoolStatus {
+        let healthy = sqlx::query("SELECT 1").execute(&self.pg_pool).await.is_ok();
+
+        PoolStatus {
+            healthy,
+            connections: self.pg_pool.size(),
+            idle_connections: self.pg_pool.num_idle() as u32,
+            max_connections: self.pg_pool.options().get_max_connections(),
+        }
+    }
+}
+
+///
+/// The migrations, written for the `app` schema, in the schema of the [Db].
+///
+/// They keep the checksums of the scripts as written, so that a migration is the same
+/// whatever the schema it was run in.
+///
+#[derive(Debug)]
+struct SchemaMigrations<'s>(&'s str);
+
+impl<'s> MigrationSource<'s> for SchemaMigrations<'s> {
+    fn resolve(self) -> BoxFuture<'s, Result<Vec<Migration>, BoxDynError>> {
+        let migrations = sqlx::migrate!("../migrations")
+            .iter()
+            .map(|migration| Migration {
+                sql: Cow::Owned(in_schema(&migration.sql, self.0)),
+                ..migration.clone()
+            })
+            .collect();
 
-        Ok(Db { pg_pool })
+        Box::pin(async move { Ok(migrations) })
     }
 }
 
-#[entrait(pub GetDb)]
-fn get_db(db: &Db) -> &Db {
-    db
+///
+/// A script of the `app` schema, for `schema`.
+///
+/// The schema, and the collations in the `search_path`, may already exist
+/// when the database is shared.
+///
+fn in_schema(sql: &str, schema: &str) -> String {
+    sql.replace(
+        "CREATE SCHEMA app;",
+        &format!("CREATE SCHEMA IF NOT EXISTS {schema};"),
+    )
+    .replace("CREATE COLLATION ", "CREATE COLLATION IF NOT EXISTS ")
+    .replace("app.", &format!("{schema}."))
+}
+
+///
+/// Access to the [Db].
+///
+/// The returned [DbRef] knows where `get_db()` was called from, which identifies the repository
+/// method running the query, because the location is passed through every implementation.
+///
+#[entrait]
+pub trait GetDb {
+    #[track_caller]
+    fn get_db(&self) -> DbRef<'_>;
+}
+
+impl GetDb for Db {
+    fn get_db(&self) -> DbRef<'_> {
+        DbRef::new(self)
+    }
+}
+
+///
+/// Receives the duration of database queries.
+///
+pub trait QueryObserver: Send + Sync {
+    /// `caller` is where the query was run from, like `realworld_db/src/article.rs:26:39`
+    fn observe_query(&self, caller: &'static Location<'static>, elapsed: Duration);
+}
+
+///
+/// Reference to the [Db], reporting to an optional [QueryObserver] when dropped.
+///
+/// Repositories access the pool with `&deps.get_db().pg_pool` inside the statement
+/// that runs the query, so the time from `get_db()` until the end of that statement
+/// is the duration of the query.
+///
+pub struct DbRef<'a> {
+    db: &'a Db,
+    observer: Option<&'a dyn QueryObserver>,
+    caller: &'static Location<'static>,
+    started_at: Instant,
+}
+
+impl<'a> DbRef<'a> {
+    #[track_caller]
+    pub fn new(db: &'a Db) -> Self {
+        Self {
+            db,
+            observer: None,
+            caller: Location::caller(),
+            started_at: Instant::now(),
+        }
+    }
+
+    #[track_caller]
+    pub fn observed_by(db: &'a Db, observer: &'a dyn QueryObserver) -> Self {
+        Self {
+            observer: Some(observer),
+            ..Self::new(db)
+        }
+    }
+}
+
+impl std::ops::Deref for DbRef<'_> {
+    type Target = Db;
+
+    fn deref(&self) -> &Db {
+        self.db
+    }
+}
+
+impl Drop for DbRef<'_> {
+    fn drop(&mut self) {
+        if let Some(observer) = self.observer {
+            observer.observe_query(self.caller, self.started_at.elapsed());
+        }
+    }
 }
 
 trait DbResultExt<T> {
@@ -41,7 +411,50 @@ trait DbResultExt<T> {
 
 impl<T> DbResultExt<T> for Result<T, sqlx::Error> {
     fn to_rw_err(self) -> RwResult<T> {
-        self.map_err(|sqlx_error| RwError::Anyhow(sqlx_error.into()))
+        self.map_err(|sqlx_error| match sqlx_error {
+            // The database itself, rather than the query, is the problem
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::WorkerCrashed => RwError::DatabaseUnavailable(sqlx_error.into()),
+            sqlx_error => RwError::Anyhow(sqlx_error.into()),
+        })
+    }
+}
+
+///
+/// A failure that may well not happen again if the operation is retried.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransientFailure {
+    /// The transaction conflicted with a concurrent one
+    Serialization,
+    /// The connection was lost during the operation
+    Connection,
+}
+
+impl TransientFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Serialization => "serialization",
+            Self::Connection => "connection",
+        }
+    }
+}
+
+/// Whether `error` is a transient failure of the database, and which one
+pub fn transient_failure(error: &RwError) -> Option<TransientFailure> {
+    let (RwError::DatabaseUnavailable(error) | RwError::Anyhow(error)) = error else {
+        return None;
+    };
+
+    match error.downcast_ref::<sqlx::Error>()? {
+        sqlx::Error::Io(_) => Some(TransientFailure::Connection),
+        // serialization_failure and deadlock_detected: the transaction was rolled back
+        sqlx::Error::Database(dbe) => matches!(dbe.code().as_deref(), Some("40001" | "40P01"))
+            .then_some(TransientFailure::Serialization),
+        _ => None,
     }
 }
 
@@ -70,28 +483,202 @@ impl<T> OnConstraint<T> for Result<T, RwError> {
     }
 }
 
-#[cfg(test)]
-impl realworld_domain::user::repo::DelegateUserRepo<Self> for Db {
-    type Target = user::PgUserRepo;
+///
+/// Delegates a repository trait of [Db] to its implementation in this crate,
+/// through a trait object with the `dyn-repos` feature.
+///
+macro_rules! delegate_repo {
+    ($($module:ident)::+, $delegate:ident, $repo_impl:ident, $repo:path) => {
+        #[cfg(not(feature = "dyn-repos"))]
+        impl $($module::)+$delegate<Self> for Db {
+            type Target = $repo;
+        }
+
+        #[cfg(feature = "dyn-repos")]
+        impl AsRef<dyn $($module::)+$repo_impl<Self> + Sync> for Db {
+            fn as_ref(&self) -> &(dyn $($module::)+$repo_impl<Self> + Sync) {
+                &$repo
+            }
+        }
+    };
 }
 
 #[cfg(test)]
-impl realworld_domain::article::repo::DelegateArticleRepo<Self> for Db {
-    type Target = article::PgArticleRepo;
-}
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateUserRepo,
+    UserRepoImpl,
+    user::PgUserRepo
+);
 
 #[cfg(test)]
-impl realworld_domain::comment::repo::DelegateCommentRepo<Self> for Db {
-    type Target = comment::PgCommentRepo;
-}
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateRefreshTokenRepo,
+    RefreshTokenRepoImpl,
+    refresh_token::PgRefreshTokenRepo
+);
 
 #[cfg(test)]
-async fn create_test_db() -> entrait::Impl<Db> {
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateSessionRepo,
+    SessionRepoImpl,
+    session::PgSessionRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateEmailVerificationRepo,
+    EmailVerificationRepoImpl,
+    email_verification::PgEmailVerificationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateLoginAttemptRepo,
+    LoginAttemptRepoImpl,
+    login_attempt::PgLoginAttemptRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegatePasswordResetRepo,
+    PasswordResetRepoImpl,
+    password_reset::PgPasswordResetRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateArticleRepo,
+    ArticleRepoImpl,
+    article::PgArticleRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateBookmarkRepo,
+    BookmarkRepoImpl,
+    bookmark::PgBookmarkRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::feed,
+    DelegateFeedRepo,
+    FeedRepoImpl,
+    feed::PgFeedRepo
+);
+
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateViewRepo,
+    ViewRepoImpl,
+    view::PgViewRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::comment::repo,
+    DelegateCommentRepo,
+    CommentRepoImpl,
+    comment::PgCommentRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::data_export,
+    DelegateDataExportRepo,
+    DataExportRepoImpl,
+    data_export::PgDataExportRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::notification::repo,
+    DelegateNotificationRepo,
+    NotificationRepoImpl,
+    notification::PgNotificationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::recommendation,
+    DelegateRecommendationRepo,
+    RecommendationRepoImpl,
+    recommendation::PgRecommendationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::settings,
+    DelegateSettingsRepo,
+    SettingsRepoImpl,
+    settings::PgSettingsRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::stats,
+    DelegateStatsRepo,
+    StatsRepoImpl,
+    stats::PgStatsRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateBanRepo,
+    BanRepoImpl,
+    ban::PgBanRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::report::repo,
+    DelegateReportRepo,
+    ReportRepoImpl,
+    report::PgReportRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::audit,
+    DelegateAuditLog,
+    AuditLogImpl,
+    audit::PgAuditLogRepo
+);
+
+///
+/// A migrated database of its own for the current test, named after the thread running it.
+/// A test may create several, which are numbered.
+///
+#[cfg(any(test, feature = "testing"))]
+pub async fn create_test_db() -> entrait::Impl<Db> {
+    create_test_db_in_schema(DEFAULT_SCHEMA).await
+}
+
+/// Like [create_test_db], with the tables in another schema
+#[cfg(any(test, feature = "testing"))]
+pub async fn create_test_db_in_schema(schema: &str) -> entrait::Impl<Db> {
     use sha2::Digest;
     use sqlx::Connection;
 
+    thread_local! {
+        static CREATED: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+    }
+    let number = CREATED.replace(CREATED.get() + 1);
+
     let mut hasher = sha2::Sha256::new();
     hasher.update(std::thread::current().name().unwrap().as_bytes());
+    if number > 0 {
+        hasher.update(number.to_be_bytes());
+    }
     let thread_hash = hex::encode(hasher.finalize());
     let db_name = &thread_hash[0..24];
 
@@ -108,21 +695,22 @@ async fn create_test_db() -> entrait::Impl<Db> {
         .await
         .expect("failed creating test database");
 
-    url.set_path(&db_name);
+    url.set_path(db_name);
 
-    let pg_pool = sqlx::PgPool::connect(url.as_str())
+    let config = PoolConfig {
+        schema: schema.to_string(),
+        ..Default::default()
+    };
+    let db = Db::connect(url.as_str(), &config)
         .await
         .expect("Failed to connect to database");
 
-    sqlx::migrate!("../migrations")
-        .run(&pg_pool)
-        .await
-        .expect("Failed to migrate");
+    db.migrate().await.expect("Failed to migrate");
 
-    entrait::Impl::new(Db { pg_pool })
+    entrait::Impl::new(db)
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 fn database_server_url() -> url::Url {
     // (re)load the .env file
     dotenv::dotenv().ok();
@@ -138,3 +726,184 @@ fn database_server_url() -> url::Url {
 
     url
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use realworld_domain::article::Slug;
+
+    use std::sync::Mutex;
+
+    struct ObservedDb {
+        db: Db,
+        callers: Mutex<Vec<&'static Location<'static>>>,
+    }
+
+    impl QueryObserver for ObservedDb {
+        fn observe_query(&self, caller: &'static Location<'static>, _: Duration) {
+            self.callers.lock().unwrap().push(caller);
+        }
+    }
+
+    impl GetDb for ObservedDb {
+        fn get_db(&self) -> DbRef<'_> {
+            DbRef::observed_by(&self.db, self)
+        }
+    }
+
+    #[test]
+    fn connection_errors_should_be_database_unavailable() {
+        assert!(matches!(
+            Err::<(), _>(sqlx::Error::PoolTimedOut).to_rw_err(),
+            Err(RwError::DatabaseUnavailable(_))
+        ));
+        assert!(matches!(
+            Err::<(), _>(sqlx::Error::RowNotFound).to_rw_err(),
+            Err(RwError::Anyhow(_))
+        ));
+    }
+
+    #[test]
+    fn reset_connections_should_be_transient() {
+        let reset = Err::<(), _>(sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()))
+            .to_rw_err()
+            .unwrap_err();
+        let not_found = Err::<(), _>(sqlx::Error::RowNotFound)
+            .to_rw_err()
+            .unwrap_err();
+
+        assert_eq!(
+            Some(TransientFailure::Connection),
+            transient_failure(&reset)
+        );
+        assert_eq!(None, transient_failure(&not_found));
+        assert_eq!(None, transient_failure(&RwError::ArticleNotFound));
+    }
+
+    #[tokio::test]
+    async fn query_should_be_observed_with_location_of_caller() {
+        let pg_pool = PgPool::connect_lazy(database_server_url().as_str()).unwrap();
+        let deps = entrait::Impl::new(ObservedDb {
+            db: Db {
+                pg_pool,
+                schema: DEFAULT_SCHEMA.to_string(),
+            },
+            callers: Mutex::new(vec![]),
+        });
+
+        let line = line!() + 1;
+        drop(deps.get_db());
+
+        let callers = deps.callers.lock().unwrap();
+        assert_eq!(1, callers.len());
+        assert_eq!((file!(), line), (callers[0].file(), callers[0].line()));
+    }
+
+    #[tokio::test]
+    async fn schema_should_only_be_verified_with_the_migrations_of_this_build() {
+        let db = create_test_db().await;
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(None, status.pending().next());
+        status.verify().unwrap();
+
+        let latest = status.migrations.last().unwrap().version;
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+            .bind(latest)
+            .execute(&db.pg_pool)
+            .await
+            .unwrap();
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(
+            vec![latest],
+            status
+                .pending()
+                .map(|migration| migration.version)
+                .collect::<Vec<_>>()
+        );
+        assert!(status.verify().is_err());
+
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES ($1, 'future', true, ''::bytea, 0), (9999, 'from a newer build', true, ''::bytea, 0)",
+        )
+        .bind(latest)
+        .execute(&db.pg_pool)
+        .await
+        .unwrap();
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(vec![9999], status.unknown_versions);
+        assert!(status
+            .verify()
+            .unwrap_err()
+            .to_string()
+            .contains("doesn't know"));
+    }
+
+    #[test]
+    fn scripts_should_be_moved_to_schema() {
+        assert_eq!(
+            "CREATE SCHEMA IF NOT EXISTS realworld;\nCREATE TABLE realworld.user ();",
+            in_schema("CREATE SCHEMA app;\nCREATE TABLE app.user ();", "realworld")
+        );
+    }
+
+    #[tokio::test]
+    async fn tables_should_be_in_the_configured_schema() -> RwResult<()> {
+        use crate::user::tests::InsertTestUser;
+        use realworld_domain::article::repo::ArticleRepo;
+        use realworld_domain::user::UserId;
+
+        let db = create_test_db_in_schema("realworld").await;
+        db.schema_status().await.unwrap().verify().unwrap();
+
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        let articles = db.select_articles(UserId(None), Default::default()).await?;
+        assert_eq!(1, articles.len());
+
+        let schemas: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT table_schema::text FROM information_schema.tables WHERE table_name = 'article'",
+        )
+        .fetch_all(&db.pg_pool)
+        .await
+        .unwrap();
+        assert_eq!(vec!["realworld".to_string()], schemas);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrations_without_down_script_should_not_be_reverted() {
+        let db = create_test_db().await;
+
+        let error = db.revert_migrations(0).await.unwrap_err();
+        assert!(error.to_string().contains("can't be reverted"));
+
+        let latest = sqlx::migrate!("../migrations")
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap();
+        db.revert_migrations(latest).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pool_status_should_be_healthy() {
+        let db = create_test_db().await;
+        let status = db.pool_status().await;
+
+        assert!(status.healthy);
+        assert!(status.connections >= 1);
+        assert!(status.idle_connections <= status.connections);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::DbResultExt;
+use crate::GetDb;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use time::OffsetDateTime;
+
+pub struct PgPasswordResetRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::PasswordResetRepoImpl for PgPasswordResetRepo {
+    pub async fn insert_password_reset(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO password_reset (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            user_id,
+            token_hash.as_ref(),
+            expires_at
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn take_password_reset(
+        deps: &impl GetDb,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<UserId>> {
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        let record = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            DELETE FROM password_reset
+            WHERE token_hash = $1
+            RETURNING user_id, expires_at > $2 "valid!"
+            "#,
+            token_hash.as_ref(),
+            now
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        if let Some(record) = &record {
+            sqlx::query!(
+                // language=PostgreSQL
+                "DELETE FROM password_reset WHERE user_id = $1",
+                record.user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+        }
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(record
+            .filter(|record| record.valid)
+            .map(|record| UserId(record.user_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::opaque_token::OpaqueToken;
+    use realworld_domain::user::repo::PasswordResetRepo;
+
+    #[tokio::test]
+    async fn taking_reset_token_should_discard_other_tokens() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let first = OpaqueToken::generate().hash();
+        let second = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+
+        for hash in [&first, &second] {
+            db.insert_password_reset(user.user_id, hash, now + time::Duration::hours(1))
+                .await?;
+        }
+
+        assert_eq!(
+            Some(user.user_id),
+            db.take_password_reset(&second, now).await?
+        );
+        assert_eq!(None, db.take_password_reset(&second, now).await?);
+        assert_eq!(None, db.take_password_reset(&first, now).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_reset_token_should_not_be_valid() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let hash = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+
+        db.insert_password_reset(user.user_id, &hash, now - time::Duration::seconds(1))
+            .await?;
+
+        assert_eq!(None, db.take_password_reset(&hash, now).await?);
+        Ok(())
+    }
+}
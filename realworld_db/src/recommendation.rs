@@ -0,0 +1,176 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::error::*;
+use realworld_domain::recommendation::Signals;
+use realworld_domain::user::repo::User;
+use realworld_domain::user::role::Role;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+
+pub struct PgRecommendationRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::recommendation::RecommendationRepoImpl for PgRecommendationRepo {
+    pub async fn select_follow_candidates(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        limit: i64,
+    ) -> RwResult<Vec<(User, Signals)>> {
+        let records = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            WITH signal AS (
+                SELECT followed_user_id user_id, true via_follow
+                FROM follow
+                WHERE following_user_id IN (
+                    SELECT followed_user_id FROM follow WHERE following_user_id = $1
+                )
+                UNION ALL
+                SELECT article.user_id, false
+                FROM article_favorite fav
+                INNER JOIN article USING (article_id)
+                WHERE fav.user_id = $1
+            )
+            SELECT
+                candidate.user_id,
+                candidate.username,
+                candidate.bio,
+                candidate.image,
+                candidate.role "role: Role",
+                count(*) FILTER (WHERE via_follow) "followed_by_followees!",
+                count(*) FILTER (WHERE NOT via_follow) "favorited_articles!"
+            FROM signal
+            INNER JOIN "user" candidate USING (user_id)
+            WHERE candidate.user_id <> $1
+            AND candidate.banned_at IS NULL
+            AND EXISTS(SELECT 1 FROM article WHERE article.user_id = candidate.user_id)
+            AND NOT EXISTS(
+                SELECT 1 FROM follow
+                WHERE following_user_id = $1 AND followed_user_id = candidate.user_id
+            )
+            AND NOT EXISTS(
+                SELECT 1 FROM block
+                WHERE blocking_user_id = $1 AND blocked_user_id = candidate.user_id
+            )
+            GROUP BY candidate.user_id
+            ORDER BY count(*) DESC, candidate.username
+            LIMIT $2
+            "#,
+            user_id,
+            limit
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Signals {
+                        followed_by_followees: record.followed_by_followees,
+                        favorited_articles: record.favorited_articles,
+                    },
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{named_user, InsertTestUser};
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::article::Slug;
+    use realworld_domain::error::RwResult;
+    use realworld_domain::recommendation::{RecommendationRepo, Signals};
+    use realworld_domain::user::repo::UserRepo;
+    use realworld_domain::user::UserId;
+
+    use std::collections::HashMap;
+
+    async fn candidates(
+        db: &impl RecommendationRepo,
+        user_id: UserId,
+        limit: i64,
+    ) -> RwResult<Vec<(String, Signals)>> {
+        Ok(db
+            .select_follow_candidates(user_id, limit)
+            .await?
+            .into_iter()
+            .map(|(user, signals)| (user.username, signals))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn candidates_should_be_unfollowed_authors_with_signals() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (me, _) = db.insert_test_user(named_user("me")).await?;
+        let mut user_ids = HashMap::new();
+        for username in ["followee", "popular", "favorite", "silent", "blocked"] {
+            let (user, _) = db.insert_test_user(named_user(username)).await?;
+            user_ids.insert(username, user.user_id);
+        }
+        for (username, slug) in [
+            ("followee", "followee-1"),
+            ("popular", "popular-1"),
+            ("favorite", "favorite-1"),
+            ("favorite", "favorite-2"),
+            ("blocked", "blocked-1"),
+        ] {
+            db.insert_article(
+                user_ids[username],
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+
+        db.insert_follow(me.user_id, "followee").await?;
+        for username in ["popular", "silent", "blocked", "me"] {
+            db.insert_follow(user_ids["followee"], username).await?;
+        }
+        for slug in ["favorite-1", "favorite-2", "followee-1"] {
+            db.insert_favorite(me.user_id, &Slug::from(slug)).await?;
+        }
+        db.insert_block(me.user_id, "blocked").await?;
+
+        assert_eq!(
+            vec![
+                (
+                    "favorite".to_string(),
+                    Signals {
+                        followed_by_followees: 0,
+                        favorited_articles: 2
+                    }
+                ),
+                (
+                    "popular".to_string(),
+                    Signals {
+                        followed_by_followees: 1,
+                        favorited_articles: 0
+                    }
+                )
+            ],
+            candidates(&db, me.user_id, 10).await?
+        );
+        assert_eq!(1, candidates(&db, me.user_id, 1).await?.len());
+
+        Ok(())
+    }
+}
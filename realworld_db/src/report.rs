@@ -0,0 +1,198 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::Slug;
+use realworld_domain::comment::CommentId;
+use realworld_domain::error::*;
+use realworld_domain::pagination::Pagination;
+use realworld_domain::report::repo::Report;
+use realworld_domain::user::UserId;
+
+use futures::TryStreamExt;
+
+use entrait::*;
+
+pub struct PgReportRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::report::repo::ReportRepoImpl for PgReportRepo {
+    pub async fn insert_report(
+        deps: &impl GetDb,
+        UserId(reporter): UserId,
+        Slug(article_slug): &Slug,
+        comment_id: Option<CommentId>,
+        reason: &str,
+    ) -> RwResult<i64> {
+        let record = sqlx::query!(
+            r#"
+            INSERT INTO report (reporter_user_id, article_id, comment_id, reason)
+                SELECT $1, article_id, $3, $4
+                FROM article
+                WHERE slug = $2 AND (
+                    $3::bigint IS NULL
+                    OR EXISTS(
+                        SELECT 1 FROM article_comment
+                        WHERE comment_id = $3 AND article_comment.article_id = article.article_id
+                    )
+                )
+            RETURNING report_id
+            "#,
+            reporter,
+            article_slug,
+            comment_id.map(|CommentId(comment_id)| comment_id),
+            reason
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        Ok(record.report_id)
+    }
+
+    pub async fn list_reports(
+        deps: &impl GetDb,
+        resolved: Option<bool>,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Report>> {
+        sqlx::query_as!(
+            Report,
+            r#"
+            SELECT
+                report_id,
+                reporter.username reporter_username,
+                article.slug "article_slug: Slug",
+                comment_id "comment_id: CommentId",
+                reason,
+                report.created_at,
+                resolved_at
+            FROM report
+            INNER JOIN "user" reporter ON reporter.user_id = report.reporter_user_id
+            INNER JOIN article USING (article_id)
+            WHERE $1::boolean IS NULL OR (resolved_at IS NOT NULL) = $1
+            ORDER BY report.created_at DESC, report_id DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            resolved,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn resolve_report(deps: &impl GetDb, report_id: i64) -> RwResult<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE report
+            SET resolved_at = coalesce(resolved_at, now())
+            WHERE report_id = $1
+            "#,
+            report_id
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::ReportNotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::article::Slug;
+    use realworld_domain::comment::repo::CommentRepo;
+    use realworld_domain::error::*;
+    use realworld_domain::report::repo::ReportRepo;
+
+    use assert_matches::*;
+
+    #[tokio::test]
+    async fn report_lifecycle() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(other_user()).await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("a"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("b"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        let comment = db
+            .insert_comment(author.user_id, &Slug::from("a"), "spam", &[])
+            .await?;
+
+        assert_matches!(
+            db.insert_report(
+                reader.user_id,
+                &Slug::from("b"),
+                Some(comment.comment_id),
+                "spam"
+            )
+            .await,
+            Err(RwError::ArticleNotFound)
+        );
+        let article_report = db
+            .insert_report(reader.user_id, &Slug::from("b"), None, "rude")
+            .await?;
+        db.insert_report(
+            reader.user_id,
+            &Slug::from("a"),
+            Some(comment.comment_id),
+            "spam",
+        )
+        .await?;
+
+        db.resolve_report(article_report).await?;
+        assert_matches!(
+            db.resolve_report(article_report + 100).await,
+            Err(RwError::ReportNotFound)
+        );
+
+        let reports = db.list_reports(None, Default::default()).await?;
+        assert_eq!(2, reports.len());
+        assert_eq!(reader.username, reports[0].reporter_username);
+
+        let unresolved = db.list_reports(Some(false), Default::default()).await?;
+        assert_eq!(
+            vec![("a", Some(comment.comment_id))],
+            unresolved
+                .iter()
+                .map(|report| (report.article_slug.as_str(), report.comment_id))
+                .collect::<Vec<_>>()
+        );
+
+        let resolved = db.list_reports(Some(true), Default::default()).await?;
+        assert_eq!(
+            vec![article_report],
+            resolved.iter().map(|r| r.report_id).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::user::repo::Session;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use futures::TryStreamExt;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct PgSessionRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::SessionRepoImpl for PgSessionRepo {
+    pub async fn insert_session(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        device: Option<&str>,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<Uuid> {
+        let record = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO session (user_id, device, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING session_id
+            "#,
+            user_id,
+            device,
+            expires_at
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(record.session_id)
+    }
+
+    pub async fn touch_session(
+        deps: &impl GetDb,
+        session_id: Uuid,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<bool> {
+        let result = sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            UPDATE session
+            SET last_seen_at = now(), expires_at = $2
+            WHERE session_id = $1
+            "#,
+            session_id,
+            expires_at
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn session_exists(
+        deps: &impl GetDb,
+        session_id: Uuid,
+        now: OffsetDateTime,
+    ) -> RwResult<bool> {
+        let exists = sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM session WHERE session_id = $1 AND expires_at > $2
+            ) AS "exists!"
+            "#,
+            session_id,
+            now
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(exists)
+    }
+
+    pub async fn select_sessions(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        now: OffsetDateTime,
+    ) -> RwResult<Vec<Session>> {
+        sqlx::query_as!(
+            Session,
+            // language=PostgreSQL
+            r#"
+            SELECT session_id, device, created_at, last_seen_at
+            FROM session
+            WHERE user_id = $1 AND expires_at > $2
+            ORDER BY last_seen_at DESC, session_id
+            "#,
+            user_id,
+            now
+        )
+        .fetch(&deps.get_db().pg_pool)
+        .try_collect()
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn delete_session(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        session_id: Uuid,
+    ) -> RwResult<()> {
+        // The refresh token goes with it, by `ON DELETE CASCADE`
+        let result = sqlx::query!(
+            "DELETE FROM session WHERE session_id = $1 AND user_id = $2",
+            session_id,
+            user_id
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::SessionNotFound)
+        }
+    }
+
+    pub async fn delete_user_sessions(deps: &impl GetDb, UserId(user_id): UserId) -> RwResult<u64> {
+        let result = sqlx::query!("DELETE FROM session WHERE user_id = $1", user_id)
+            .execute(&deps.get_db().pg_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_expired_sessions(deps: &impl GetDb, now: OffsetDateTime) -> RwResult<u64> {
+        let result = sqlx::query!("DELETE FROM session WHERE expires_at <= $1", now)
+            .execute(&deps.get_db().pg_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::error::{RwError, RwResult};
+    use realworld_domain::user::opaque_token::OpaqueToken;
+    use realworld_domain::user::repo::{RefreshTokenRepo, SessionRepo};
+
+    use assert_matches::*;
+
+    #[tokio::test]
+    async fn only_valid_sessions_should_be_listed() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let now = time::OffsetDateTime::now_utc();
+
+        let valid = db
+            .insert_session(
+                user.user_id,
+                Some("curl/8.0"),
+                now + time::Duration::days(1),
+            )
+            .await?;
+        db.insert_session(user.user_id, None, now - time::Duration::seconds(1))
+            .await?;
+
+        let sessions = db.select_sessions(user.user_id, now).await?;
+        assert_eq!(1, sessions.len());
+        assert_eq!(valid, sessions[0].session_id);
+        assert_eq!(Some("curl/8.0"), sessions[0].device.as_deref());
+
+        assert_eq!(1, db.delete_expired_sessions(now).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deleted_session_should_take_its_refresh_token() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+        let now = time::OffsetDateTime::now_utc();
+        let expires_at = now + time::Duration::days(1);
+        let session_id = db.insert_session(user.user_id, None, expires_at).await?;
+        let hash = OpaqueToken::generate().hash();
+        db.insert_refresh_token(user.user_id, session_id, &hash, expires_at)
+            .await?;
+
+        assert_matches!(
+            db.delete_session(other.user_id, session_id).await,
+            Err(RwError::SessionNotFound)
+        );
+        assert!(db.session_exists(session_id, now).await?);
+        db.delete_session(user.user_id, session_id).await?;
+
+        assert_eq!(None, db.take_refresh_token(&hash, now).await?);
+        assert!(!db.touch_session(session_id, expires_at).await?);
+        assert!(!db.session_exists(session_id, now).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_sessions_of_user_should_be_deleted() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+        let now = time::OffsetDateTime::now_utc();
+        let expires_at = now + time::Duration::days(1);
+        db.insert_session(user.user_id, None, expires_at).await?;
+        db.insert_session(user.user_id, None, expires_at).await?;
+        db.insert_session(other.user_id, None, expires_at).await?;
+
+        assert_eq!(2, db.delete_user_sessions(user.user_id).await?);
+        assert!(db.select_sessions(user.user_id, now).await?.is_empty());
+        assert_eq!(1, db.select_sessions(other.user_id, now).await?.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::error::*;
+use realworld_domain::settings::UserSettings;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use sqlx::types::Json;
+
+pub struct PgSettingsRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::settings::SettingsRepoImpl for PgSettingsRepo {
+    pub async fn find_user_settings(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<Option<UserSettings>> {
+        let settings = sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"SELECT settings "settings: Json<UserSettings>" FROM user_settings WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_optional(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(settings.map(|Json(settings)| settings))
+    }
+
+    pub async fn save_user_settings(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        settings: &UserSettings,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO user_settings (user_id, settings)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET settings = EXCLUDED.settings, updated_at = now()
+            "#,
+            user_id,
+            Json(settings) as _
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::settings::{SettingsRepo, UserSettings};
+
+    #[tokio::test]
+    async fn saved_settings_should_replace_earlier_ones() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        assert_eq!(None, db.find_user_settings(user.user_id).await?);
+
+        for locale in ["de", "nb-NO"] {
+            let settings = UserSettings {
+                locale: locale.to_string(),
+                ..Default::default()
+            };
+            db.save_user_settings(user.user_id, &settings).await?;
+            assert_eq!(Some(settings), db.find_user_settings(user.user_id).await?);
+        }
+        assert_eq!(None, db.find_user_settings(other.user_id).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn settings_saved_without_a_field_should_get_its_default() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        sqlx::query!(
+            r#"INSERT INTO user_settings (user_id, settings) VALUES ($1, '{"locale": "sv"}')"#,
+            user.user_id.0
+        )
+        .execute(&db.pg_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            Some(UserSettings {
+                locale: "sv".to_string(),
+                ..Default::default()
+            }),
+            db.find_user_settings(user.user_id).await?
+        );
+
+        Ok(())
+    }
+}
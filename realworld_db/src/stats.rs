@@ -0,0 +1,109 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::error::*;
+use realworld_domain::stats::UserStats;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+
+pub struct PgStatsRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::stats::StatsRepoImpl for PgStatsRepo {
+    pub async fn select_user_stats(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<UserStats> {
+        let stats = sqlx::query_as!(
+            UserStats,
+            // language=PostgreSQL
+            r#"
+            SELECT
+                (SELECT count(*) FROM article WHERE user_id = $1) "articles_count!",
+                (
+                    SELECT count(*)
+                    FROM article_favorite
+                    INNER JOIN article USING (article_id)
+                    WHERE article.user_id = $1
+                ) "favorites_count!",
+                (SELECT count(*) FROM follow WHERE followed_user_id = $1) "followers_count!",
+                (SELECT count(*) FROM article_comment WHERE user_id = $1) "comments_count!"
+            "#,
+            user_id
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::article::Slug;
+    use realworld_domain::comment::repo::CommentRepo;
+    use realworld_domain::error::RwResult;
+    use realworld_domain::stats::{StatsRepo, UserStats};
+    use realworld_domain::user::repo::UserRepo;
+
+    #[tokio::test]
+    async fn stats_should_count_per_author() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(other_user()).await?;
+
+        db.insert_article(
+            author.user_id,
+            &Slug::from("a"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("b"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_favorite(author.user_id, &Slug::from("a")).await?;
+        db.insert_favorite(reader.user_id, &Slug::from("a")).await?;
+        db.insert_favorite(reader.user_id, &Slug::from("b")).await?;
+        db.insert_follow(reader.user_id, &author.username).await?;
+        db.insert_comment(reader.user_id, &Slug::from("a"), "nice", &[])
+            .await?;
+
+        assert_eq!(
+            UserStats {
+                articles_count: 2,
+                favorites_count: 3,
+                followers_count: 1,
+                comments_count: 0,
+            },
+            db.select_user_stats(author.user_id).await?
+        );
+        assert_eq!(
+            UserStats {
+                articles_count: 0,
+                favorites_count: 0,
+                followers_count: 0,
+                comments_count: 1,
+            },
+            db.select_user_stats(reader.user_id).await?
+        );
+
+        Ok(())
+    }
+}
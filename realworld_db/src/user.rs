@@ -3,16 +3,22 @@ use crate::GetDb;
 use crate::OnConstraint;
 
 use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::pagination::Pagination;
 use realworld_domain::user::email::Email;
+use realworld_domain::user::oauth::OAuthIdentity;
 use realworld_domain::user::password::PasswordHash;
 use realworld_domain::user::repo::*;
+use realworld_domain::user::role::Role;
 use realworld_domain::user::UserId;
 
 use entrait::*;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 
 pub struct PgUserRepo;
 
-#[entrait]
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
 impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
     pub async fn insert_user(
         deps: &impl GetDb,
@@ -22,8 +28,8 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
     ) -> RwResult<(User, Credentials)> {
         let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
 
-        let id = sqlx::query_scalar!(
-            r#"INSERT INTO app.user (username, email, password_hash) VALUES ($1, $2, $3) RETURNING user_id"#,
+        let record = sqlx::query!(
+            r#"INSERT INTO "user" (username, email, password_hash) VALUES ($1, $2, $3) RETURNING user_id, role "role: Role", email_verified"#,
             username,
             email.as_ref(),
             password_hash.0
@@ -38,24 +44,51 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
 
         Ok((
             User {
-                user_id: UserId(id),
+                user_id: UserId(record.user_id),
                 username: username.to_string(),
                 bio: "".to_string(),
                 image: None,
+                role: record.role,
             },
             Credentials {
                 email: email.clone(),
                 password_hash,
+                email_verified: record.email_verified,
             },
         ))
     }
 
+    pub async fn insert_users(deps: &impl GetDb, users: &[ImportedUser]) -> RwResult<Vec<String>> {
+        let usernames: Vec<&str> = users.iter().map(|user| user.username.as_str()).collect();
+        let emails: Vec<&str> = users.iter().map(|user| user.email.as_ref()).collect();
+        let password_hashes: Vec<&str> = users
+            .iter()
+            .map(|user| user.password_hash.as_ref())
+            .collect();
+
+        sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO "user" (username, email, password_hash)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+            ON CONFLICT DO NOTHING
+            RETURNING username::text "username!"
+            "#,
+            &usernames as &[&str],
+            &emails as &[&str],
+            &password_hashes as &[&str]
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+    }
+
     pub async fn find_user_credentials_by_id(
         deps: &impl GetDb,
         UserId(user_id): UserId,
     ) -> RwResult<Option<(User, Credentials)>> {
         let record = sqlx::query!(
-            r#"SELECT user_id, email, username, password_hash, bio, image FROM app.user WHERE user_id = $1"#,
+            r#"SELECT user_id, email, username, password_hash, bio, image, role "role: Role", email_verified FROM "user" WHERE user_id = $1"#,
             user_id
         )
         .fetch_optional(&deps.get_db().pg_pool)
@@ -69,10 +102,12 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
                     username: record.username,
                     bio: record.bio,
                     image: record.image,
+                    role: record.role,
                 },
                 Credentials {
                     email: Email::valid(record.email),
                     password_hash: record.password_hash.into(),
+                    email_verified: record.email_verified,
                 },
             )
         }))
@@ -83,7 +118,7 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
         email: &Email,
     ) -> RwResult<Option<(User, Credentials)>> {
         let record = sqlx::query!(
-            r#"SELECT user_id, email, username, password_hash, bio, image FROM app.user WHERE email = $1"#,
+            r#"SELECT user_id, email, username, password_hash, bio, image, role "role: Role", email_verified FROM "user" WHERE email = $1"#,
             email.as_ref()
         )
         .fetch_optional(&deps.get_db().pg_pool)
@@ -97,20 +132,129 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
                     username: record.username,
                     bio: record.bio,
                     image: record.image,
+                    role: record.role,
                 },
                 Credentials {
                     email: Email::valid(record.email),
                     password_hash: record.password_hash.into(),
+                    email_verified: record.email_verified,
                 },
             )
         }))
     }
 
+    pub async fn find_or_create_by_oauth_identity(
+        deps: &impl GetDb,
+        identity: &OAuthIdentity,
+    ) -> RwResult<(User, Credentials, Created)> {
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        let linked_user_id = sqlx::query_scalar!(
+            "SELECT user_id FROM oauth_identity WHERE provider = $1 AND subject = $2",
+            identity.provider,
+            identity.subject
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        let (user_id, created) = match linked_user_id {
+            Some(user_id) => (user_id, false),
+            None => {
+                let user_id_with_email = sqlx::query_scalar!(
+                    r#"SELECT user_id FROM "user" WHERE email = $1"#,
+                    identity.email.as_ref()
+                )
+                .fetch_optional(&mut *tx)
+                .await
+                .to_rw_err()?;
+
+                let (user_id, created) = match user_id_with_email {
+                    Some(user_id) if identity.email_verified => (user_id, false),
+                    // Linking to an address the user might not own would let them take over the account
+                    Some(_) => return Err(RwError::EmailTaken),
+                    None => {
+                        let username_taken = sqlx::query_scalar!(
+                            r#"SELECT EXISTS (SELECT 1 FROM "user" WHERE username = $1) "exists!""#,
+                            identity.username
+                        )
+                        .fetch_one(&mut *tx)
+                        .await
+                        .to_rw_err()?;
+                        let username = if username_taken {
+                            format!(
+                                "{}-{}",
+                                identity.username,
+                                &uuid::Uuid::new_v4().simple().to_string()[..6]
+                            )
+                        } else {
+                            identity.username.clone()
+                        };
+
+                        let user_id = sqlx::query_scalar!(
+                            // An empty password hash matches no password
+                            r#"INSERT INTO "user" (username, email, password_hash, email_verified) VALUES ($1, $2, '', $3) RETURNING user_id"#,
+                            username,
+                            identity.email.as_ref(),
+                            identity.email_verified
+                        )
+                        .fetch_one(&mut *tx)
+                        .await
+                        .to_rw_err()
+                        .on_constraint("user_username_key", |_| RwError::UsernameTaken)
+                        .on_constraint("user_email_key", |_| RwError::EmailTaken)?;
+
+                        (user_id, true)
+                    }
+                };
+
+                sqlx::query!(
+                    "INSERT INTO oauth_identity (provider, subject, user_id) VALUES ($1, $2, $3)",
+                    identity.provider,
+                    identity.subject,
+                    user_id
+                )
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+
+                (user_id, created)
+            }
+        };
+
+        let record = sqlx::query!(
+            r#"SELECT user_id, email, username, password_hash, bio, image, role "role: Role", email_verified FROM "user" WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok((
+            User {
+                user_id: UserId(record.user_id),
+                username: record.username,
+                bio: record.bio,
+                image: record.image,
+                role: record.role,
+            },
+            Credentials {
+                email: Email::valid(record.email),
+                password_hash: record.password_hash.into(),
+                email_verified: record.email_verified,
+            },
+            Created(created),
+        ))
+    }
+
     pub async fn find_user_by_username(
         deps: &impl GetDb,
         current_user: UserId<Option<uuid::Uuid>>,
         username: &str,
-    ) -> RwResult<Option<(User, Following)>> {
+        PastUsernames(past_usernames): PastUsernames,
+    ) -> RwResult<Option<(User, Following, FollowStats)>> {
         let record = sqlx::query!(
             r#"
             SELECT
@@ -118,15 +262,35 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
                 username,
                 bio,
                 image,
+                role "role: Role",
                 EXISTS(
-                    SELECT 1 FROM app.follow
+                    SELECT 1 FROM follow
                     WHERE followed_user_id = "user".user_id AND following_user_id = $2
-                ) "following!"
-            FROM app.user
-            WHERE username = $1
+                ) "following!",
+                (
+                    SELECT count(*) FROM follow WHERE followed_user_id = "user".user_id
+                ) "followers_count!",
+                (
+                    SELECT count(*) FROM follow WHERE following_user_id = "user".user_id
+                ) "following_count!",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = $2 AND following_user_id = "user".user_id
+                ) "follows_you!"
+            FROM "user"
+            WHERE user_id = COALESCE(
+                (SELECT user_id FROM "user" WHERE username = $1),
+                (
+                    SELECT user_id FROM username_history
+                    WHERE $3 AND username = $1
+                    ORDER BY changed_at DESC
+                    LIMIT 1
+                )
+            )
             "#,
             username,
-            current_user.0
+            current_user.0,
+            past_usernames
         )
         .fetch_optional(&deps.get_db().pg_pool)
         .await
@@ -139,8 +303,14 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
                     username: record.username,
                     bio: record.bio,
                     image: record.image,
+                    role: record.role,
                 },
                 Following(record.following),
+                FollowStats {
+                    followers_count: record.followers_count,
+                    following_count: record.following_count,
+                    follows_you: record.follows_you,
+                },
             )
         }))
     }
@@ -150,17 +320,37 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
         current_user_id: UserId,
         update: UserUpdate<'_>,
     ) -> RwResult<(User, Credentials)> {
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        // Renaming only by case is no new name
+        sqlx::query!(
+            // language=PostgreSQL
+            r#"
+            INSERT INTO username_history (username, user_id)
+                SELECT username, user_id
+                FROM "user"
+                WHERE user_id = $1 AND username <> $2
+            "#,
+            current_user_id.0,
+            update.username
+        )
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
         let record = sqlx::query!(
             // language=PostgreSQL
             r#"
-            UPDATE app.user SET
+            UPDATE "user" SET
+                -- a new email address has to be verified again
+                email_verified = email_verified AND ($1::text IS NULL OR $1 = email),
                 email = COALESCE($1, email),
                 username = COALESCE($2, username),
                 password_hash = COALESCE($3, password_hash),
                 bio = COALESCE($4, bio),
                 image = COALESCE($5, image)
             WHERE user_id = $6
-            RETURNING username, bio, image, email, password_hash
+            RETURNING username, bio, image, role "role: Role", email, password_hash, email_verified
             "#,
             update.email,
             update.username,
@@ -169,26 +359,155 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
             update.image,
             current_user_id.0
         )
-        .fetch_one(&deps.get_db().pg_pool)
+        .fetch_one(&mut *tx)
         .await
         .to_rw_err()
         .on_constraint("user_username_key", |_| RwError::UsernameTaken)
         .on_constraint("user_email_key", |_| RwError::EmailTaken)?;
 
+        tx.commit().await.to_rw_err()?;
+
         Ok((
             User {
                 user_id: current_user_id,
                 username: record.username,
                 bio: record.bio,
                 image: record.image,
+                role: record.role,
             },
             Credentials {
                 email: Email::valid(record.email),
                 password_hash: record.password_hash.into(),
+                email_verified: record.email_verified,
             },
         ))
     }
 
+    pub async fn set_user_role(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        role: Role,
+    ) -> RwResult<()> {
+        sqlx::query!(
+            r#"UPDATE "user" SET role = $2 WHERE user_id = $1"#,
+            user_id,
+            role as Role
+        )
+        .execute(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn delete_user(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        mode: DeletionMode,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().pg_pool.begin().await.to_rw_err()?;
+
+        let result = match mode {
+            // Everything else references the user with `ON DELETE CASCADE`
+            DeletionMode::Delete => {
+                sqlx::query!(r#"DELETE FROM "user" WHERE user_id = $1"#, user_id)
+                    .execute(&mut *tx)
+                    .await
+            }
+            DeletionMode::Anonymize => {
+                sqlx::query!("DELETE FROM article_favorite WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!("DELETE FROM comment_like WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!("DELETE FROM bookmark WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!(
+                    "DELETE FROM block WHERE blocking_user_id = $1 OR blocked_user_id = $1",
+                    user_id
+                )
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+                sqlx::query!(
+                    "DELETE FROM follow WHERE followed_user_id = $1 OR following_user_id = $1",
+                    user_id
+                )
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+                sqlx::query!(
+                    "DELETE FROM notification WHERE user_id = $1 OR actor_user_id = $1",
+                    user_id
+                )
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+                // Refresh tokens go with their sessions
+                sqlx::query!("DELETE FROM session WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!("DELETE FROM email_verification WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!("DELETE FROM password_reset WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                sqlx::query!("DELETE FROM oauth_identity WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                // Archives have the email address and everything else of the user in them
+                sqlx::query!("DELETE FROM data_export WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+                // Old profile links shouldn't lead to the anonymized user
+                sqlx::query!("DELETE FROM username_history WHERE user_id = $1", user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+
+                sqlx::query!(
+                    // language=PostgreSQL
+                    r#"
+                    UPDATE "user"
+                    SET
+                        username = $2,
+                        email = $3,
+                        bio = '',
+                        image = NULL,
+                        -- Not a valid hash, so no password matches it
+                        password_hash = '',
+                        role = 'user',
+                        email_verified = false
+                    WHERE user_id = $1
+                    "#,
+                    user_id,
+                    format!("deleted-{}", user_id.simple()),
+                    format!("{}@deleted.invalid", user_id.simple())
+                )
+                .execute(&mut *tx)
+                .await
+            }
+        }
+        .to_rw_err()?;
+
+        if result.rows_affected() == 0 {
+            return Err(RwError::CurrentUserDoesNotExist);
+        }
+
+        tx.commit().await.to_rw_err()
+    }
+
     pub async fn insert_follow(
         deps: &impl GetDb,
         current_user_id: UserId,
@@ -200,10 +519,10 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
                 SELECT
                     $1::uuid AS following,
                     user_id as followed
-                FROM app.user
+                FROM "user"
                 WHERE username = $2
             ), insertion AS (
-                INSERT INTO app.follow (following_user_id, followed_user_id)
+                INSERT INTO follow (following_user_id, followed_user_id)
                     SELECT following, followed FROM id_pair
                 ON CONFLICT DO NOTHING
                 RETURNING 1
@@ -236,10 +555,10 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
         let result = sqlx::query!(
             r#"
             WITH other_user AS (
-                SELECT user_id FROM app.user WHERE username = $2
+                SELECT user_id FROM "user" WHERE username = $2
             ),
             deleted_follow AS (
-                DELETE from app.follow
+                DELETE from follow
                 WHERE following_user_id = $1
                 AND followed_user_id = (SELECT user_id FROM other_user)
                 RETURNING 1
@@ -264,6 +583,342 @@ impl realworld_domain::user::repo::UserRepoImpl for PgUserRepo {
             Ok(())
         }
     }
+
+    pub async fn insert_block(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let user_exists = sqlx::query_scalar!(
+            r#"
+            WITH blocked_user AS (
+                SELECT user_id FROM "user" WHERE username = $2
+            ), insertion AS (
+                INSERT INTO block (blocking_user_id, blocked_user_id)
+                    SELECT $1, user_id FROM blocked_user
+                ON CONFLICT DO NOTHING
+            )
+            SELECT EXISTS(SELECT 1 FROM blocked_user) "user_exists!"
+            "#,
+            current_user_id.0,
+            username
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()
+        .on_constraint("block_blocking_user_id_fkey", |_| RwError::ProfileNotFound)
+        .on_constraint("user_cannot_block_self", |_| RwError::Forbidden)?;
+
+        if !user_exists {
+            Err(RwError::ProfileNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn delete_block(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let user_exists = sqlx::query_scalar!(
+            r#"
+            WITH blocked_user AS (
+                SELECT user_id FROM "user" WHERE username = $2
+            ), deletion AS (
+                DELETE FROM block
+                WHERE blocking_user_id = $1
+                AND blocked_user_id = (SELECT user_id FROM blocked_user)
+            )
+            SELECT EXISTS(SELECT 1 FROM blocked_user) "user_exists!"
+            "#,
+            current_user_id.0,
+            username
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        if !user_exists {
+            Err(RwError::ProfileNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn list_followers(
+        deps: &impl GetDb,
+        current_user: UserId<Option<uuid::Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                listed.user_id,
+                listed.username,
+                listed.bio,
+                listed.image,
+                listed.role "role: Role",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = listed.user_id AND following_user_id = $2
+                ) "following!"
+            FROM "user"
+            INNER JOIN follow ON follow.followed_user_id = "user".user_id
+            INNER JOIN "user" listed ON listed.user_id = follow.following_user_id
+            WHERE "user".username = $1
+            ORDER BY follow.created_at DESC, listed.username
+            LIMIT $3
+            OFFSET $4
+            "#,
+            username,
+            current_user.0,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Following(record.following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn list_following(
+        deps: &impl GetDb,
+        current_user: UserId<Option<uuid::Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                listed.user_id,
+                listed.username,
+                listed.bio,
+                listed.image,
+                listed.role "role: Role",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = listed.user_id AND following_user_id = $2
+                ) "following!"
+            FROM "user"
+            INNER JOIN follow ON follow.following_user_id = "user".user_id
+            INNER JOIN "user" listed ON listed.user_id = follow.followed_user_id
+            WHERE "user".username = $1
+            ORDER BY follow.created_at DESC, listed.username
+            LIMIT $3
+            OFFSET $4
+            "#,
+            username,
+            current_user.0,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Following(record.following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn search_users(
+        deps: &impl GetDb,
+        current_user: UserId<Option<uuid::Uuid>>,
+        query: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                user_id,
+                username,
+                bio,
+                image,
+                role "role: Role",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = "user".user_id AND following_user_id = $1
+                ) "following!"
+            FROM "user"
+            WHERE banned_at IS NULL AND (
+                -- `position` rather than `ILIKE`, so that `%` and `_` are searched for as they are.
+                -- The case insensitive collation of usernames doesn't support searching within them.
+                position(lower($2) IN lower(username COLLATE "default")) > 0
+                OR position(lower($2) IN lower(bio)) > 0
+            )
+            ORDER BY position(lower($2) IN lower(username COLLATE "default")) <> 1, username
+            LIMIT $3
+            OFFSET $4
+            "#,
+            current_user.0,
+            query,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Following(record.following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn select_users_by_ids(
+        deps: &impl GetDb,
+        current_user: UserId<Option<uuid::Uuid>>,
+        user_ids: &[uuid::Uuid],
+    ) -> RwResult<Vec<(User, Following)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                user_id,
+                username,
+                bio,
+                image,
+                role "role: Role",
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = "user".user_id AND following_user_id = $2
+                ) "following!"
+            FROM "user"
+            WHERE user_id = ANY($1)
+            "#,
+            user_ids,
+            current_user.0
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Following(record.following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn list_users(
+        deps: &impl GetDb,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Credentials, Banned)>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                user_id,
+                email,
+                username,
+                password_hash,
+                bio,
+                image,
+                role "role: Role",
+                email_verified,
+                banned_at IS NOT NULL "banned!"
+            FROM "user"
+            ORDER BY created_at, username
+            LIMIT $1
+            OFFSET $2
+            "#,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                (
+                    User {
+                        user_id: UserId(record.user_id),
+                        username: record.username,
+                        bio: record.bio,
+                        image: record.image,
+                        role: record.role,
+                    },
+                    Credentials {
+                        email: Email::valid(record.email),
+                        password_hash: record.password_hash.into(),
+                        email_verified: record.email_verified,
+                    },
+                    Banned(record.banned),
+                )
+            })
+            .collect())
+    }
+
+    pub fn stream_usernames(deps: &impl GetDb) -> BoxStream<'static, RwResult<String>> {
+        let pg_pool = deps.get_db().pg_pool.clone();
+
+        async_stream::try_stream! {
+            let mut records = sqlx::query!(
+                r#"
+                SELECT username
+                FROM "user"
+                WHERE banned_at IS NULL
+                ORDER BY created_at, username
+                "#
+            )
+            .fetch(&pg_pool);
+
+            while let Some(record) = records.try_next().await.to_rw_err()? {
+                yield record.username;
+            }
+        }
+        .boxed()
+    }
 }
 
 #[cfg(test)]
@@ -271,6 +926,8 @@ pub mod tests {
     use super::*;
     use crate::create_test_db;
 
+    use realworld_domain::article::Slug;
+
     use assert_matches::*;
 
     pub struct TestNewUser {
@@ -297,6 +954,15 @@ pub mod tests {
         }
     }
 
+    /// A user with the username as email address too
+    pub fn named_user(username: &'static str) -> TestNewUser {
+        TestNewUser {
+            username,
+            email: username,
+            password_hash: "hash",
+        }
+    }
+
     #[entrait(pub InsertTestUser, unimock = false)]
     pub async fn insert_test_user(
         db: &impl realworld_domain::user::repo::UserRepo,
@@ -327,6 +993,47 @@ pub mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn inserting_users_should_skip_taken_ones() -> RwResult<()> {
+        let db = create_test_db().await;
+        db.insert_test_user(TestNewUser::default()).await?;
+
+        let imported = |username: &str, email: &str| ImportedUser {
+            username: username.to_string(),
+            email: email.parse().unwrap(),
+            password_hash: "hash".into(),
+        };
+        let inserted = db
+            .insert_users(&[
+                imported("username", "new_email"),
+                imported("new_username", "email"),
+                imported("imported", "imported_email"),
+                imported("IMPORTED", "other_imported_email"),
+            ])
+            .await?;
+
+        assert_eq!(vec!["imported".to_string()], inserted);
+        let (user, credentials) = db
+            .find_user_credentials_by_email(&"imported_email".parse().unwrap())
+            .await?
+            .unwrap();
+        assert_eq!("imported", user.username);
+        assert_eq!("hash", credentials.password_hash.as_ref());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_set_user_role() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+
+        db.set_user_role(user.user_id, Role::Admin).await?;
+
+        let (fetched_user, _) = db.find_user_credentials_by_id(user.user_id).await?.unwrap();
+        assert_eq!(Role::Admin, fetched_user.role);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_fail_to_create_two_users_with_the_same_username() -> RwResult<()> {
         let db = create_test_db().await;
@@ -338,6 +1045,15 @@ pub mod tests {
             .expect_err("should error");
 
         assert_matches!(error, RwError::UsernameTaken);
+
+        let error = db
+            .insert_test_user(TestNewUser {
+                username: "USERNAME",
+                ..other_user()
+            })
+            .await
+            .expect_err("should error regardless of case");
+        assert_matches!(error, RwError::UsernameTaken);
         Ok(())
     }
 
@@ -383,6 +1099,45 @@ pub mod tests {
 
         assert_eq!("newmail", updated_credentials.email.as_ref());
         assert_eq!("newhash", updated_credentials.password_hash.0);
+        assert!(!updated_credentials.email_verified);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn past_usernames_should_only_resolve_when_asked_for() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let rename = |username| UserUpdate {
+            username: Some(username),
+            ..UserUpdate::default()
+        };
+        db.update_user(user.user_id, rename("Username")).await?;
+        db.update_user(user.user_id, rename("renamed")).await?;
+
+        assert!(db
+            .find_user_by_username(UserId(None), "username", PastUsernames(false))
+            .await?
+            .is_none());
+        let (found, _, _) = db
+            .find_user_by_username(UserId(None), "username", PastUsernames(true))
+            .await?
+            .unwrap();
+        assert_eq!(user.user_id, found.user_id);
+        assert_eq!("renamed", found.username);
+
+        // The current holder of a name wins over its past holders
+        let (other, _) = db
+            .insert_test_user(TestNewUser {
+                username: "username",
+                ..other_user()
+            })
+            .await?;
+        let (found, _, _) = db
+            .find_user_by_username(UserId(None), "username", PastUsernames(true))
+            .await?
+            .unwrap();
+        assert_eq!(other.user_id, found.user_id);
+
         Ok(())
     }
 
@@ -437,10 +1192,32 @@ pub mod tests {
         db.insert_follow(user1.user_id, &user2.username).await?;
 
         assert_matches!(
-            db.find_user_by_username(user1.user_id.some(), &user2.username)
+            db.find_user_by_username(user1.user_id.some(), &user2.username, PastUsernames(false))
                 .await?
                 .unwrap(),
-            (_, Following(true))
+            (
+                _,
+                Following(true),
+                FollowStats {
+                    followers_count: 1,
+                    following_count: 0,
+                    follows_you: false
+                }
+            )
+        );
+        assert_matches!(
+            db.find_user_by_username(user2.user_id.some(), &user1.username, PastUsernames(false))
+                .await?
+                .unwrap(),
+            (
+                _,
+                Following(false),
+                FollowStats {
+                    followers_count: 0,
+                    following_count: 1,
+                    follows_you: true
+                }
+            )
         );
 
         // Idempotent
@@ -464,10 +1241,17 @@ pub mod tests {
         db.delete_follow(user1.user_id, &user2.username).await?;
 
         assert_matches!(
-            db.find_user_by_username(user1.user_id.some(), &user2.username)
+            db.find_user_by_username(user1.user_id.some(), &user2.username, PastUsernames(false))
                 .await?
                 .unwrap(),
-            (_, Following(false))
+            (
+                _,
+                Following(false),
+                FollowStats {
+                    followers_count: 0,
+                    ..
+                }
+            )
         );
         Ok(())
     }
@@ -484,4 +1268,341 @@ pub mod tests {
         assert_matches!(err, RwError::Anyhow(_));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn should_list_followers_and_following() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (user2, _) = db.insert_test_user(other_user()).await?;
+        let (user3, _) = db
+            .insert_test_user(TestNewUser {
+                username: "username3",
+                email: "email3",
+                password_hash: "hash3",
+            })
+            .await?;
+
+        db.insert_follow(user2.user_id, &user.username).await?;
+        db.insert_follow(user3.user_id, &user.username).await?;
+        db.insert_follow(user.user_id, &user3.username).await?;
+
+        fn usernames(users: Vec<(User, Following)>) -> Vec<(String, bool)> {
+            users
+                .into_iter()
+                .map(|(user, Following(following))| (user.username, following))
+                .collect()
+        }
+
+        assert_eq!(
+            vec![
+                ("username3".to_string(), true),
+                ("username2".to_string(), false)
+            ],
+            usernames(
+                db.list_followers(user.user_id.some(), "username", Pagination::default())
+                    .await?
+            )
+        );
+        assert_eq!(
+            vec![("username2".to_string(), false)],
+            usernames(
+                db.list_followers(
+                    UserId(None),
+                    "username",
+                    Pagination {
+                        limit: Some(1),
+                        offset: Some(1),
+                    }
+                )
+                .await?
+            )
+        );
+        assert_eq!(
+            vec![("username".to_string(), false)],
+            usernames(
+                db.list_following(UserId(None), "username3", Pagination::default())
+                    .await?
+            )
+        );
+
+        let mut selected = usernames(
+            db.select_users_by_ids(user2.user_id.some(), &[user.user_id.0, user3.user_id.0])
+                .await?,
+        );
+        selected.sort();
+        assert_eq!(
+            vec![
+                ("username".to_string(), true),
+                ("username3".to_string(), false)
+            ],
+            selected
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_search_users_by_username_and_bio() -> RwResult<()> {
+        use realworld_domain::user::repo::BanRepo;
+
+        let db = create_test_db().await;
+        let mut users = vec![];
+        for username in ["jake", "bigjake", "bob", "jakebanned", "alice"] {
+            let (user, _) = db.insert_test_user(named_user(username)).await?;
+            users.push(user);
+        }
+        let (jake, bob, banned) = (&users[0], &users[2], &users[3]);
+        db.update_user(
+            bob.user_id,
+            UserUpdate {
+                bio: Some("Knows Jake"),
+                ..Default::default()
+            },
+        )
+        .await?;
+        db.insert_follow(bob.user_id, &jake.username).await?;
+        db.set_user_banned(banned.user_id, Banned(true)).await?;
+
+        fn usernames(users: Vec<(User, Following)>) -> Vec<(String, bool)> {
+            users
+                .into_iter()
+                .map(|(user, Following(following))| (user.username, following))
+                .collect()
+        }
+
+        assert_eq!(
+            vec![
+                ("jake".to_string(), true),
+                ("bigjake".to_string(), false),
+                ("bob".to_string(), false)
+            ],
+            usernames(
+                db.search_users(bob.user_id.some(), "JAK", Pagination::default())
+                    .await?
+            )
+        );
+        assert_eq!(
+            vec![("bigjake".to_string(), false)],
+            usernames(
+                db.search_users(
+                    UserId(None),
+                    "jak",
+                    Pagination {
+                        limit: Some(1),
+                        offset: Some(1)
+                    }
+                )
+                .await?
+            )
+        );
+        assert!(db
+            .search_users(UserId(None), "%", Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_delete_or_anonymize_user() -> RwResult<()> {
+        use realworld_domain::article::repo::{ArticleRepo, Filter};
+        use realworld_domain::data_export::DataExportRepo;
+
+        let db = create_test_db().await;
+        let (deleted, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (anonymized, _) = db.insert_test_user(other_user()).await?;
+
+        for (user, slug) in [(&deleted, "deleted"), (&anonymized, "anonymized")] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(anonymized.user_id, &Slug::from("deleted"))
+            .await?;
+        db.insert_follow(anonymized.user_id, &deleted.username)
+            .await?;
+        for user in [&deleted, &anonymized] {
+            db.insert_data_export(user.user_id).await?;
+        }
+
+        db.delete_user(deleted.user_id, DeletionMode::Delete)
+            .await?;
+        assert!(db
+            .find_user_credentials_by_id(deleted.user_id)
+            .await?
+            .is_none());
+        assert_matches!(
+            db.fetch_article_id(&Slug::from("deleted")).await,
+            Err(RwError::ArticleNotFound)
+        );
+        assert_eq!(None, db.find_latest_data_export(deleted.user_id).await?);
+
+        db.delete_user(anonymized.user_id, DeletionMode::Anonymize)
+            .await?;
+        let (user, credentials) = db
+            .find_user_credentials_by_id(anonymized.user_id)
+            .await?
+            .unwrap();
+        assert!(user.username.starts_with("deleted-"));
+        assert!(credentials.email.as_ref().ends_with("@deleted.invalid"));
+        assert_eq!(None, db.find_latest_data_export(anonymized.user_id).await?);
+        assert_eq!(
+            vec![user.username.clone()],
+            db.select_articles(
+                UserId(None),
+                Filter {
+                    slug: Some(&Slug::from("anonymized")),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .map(|article| article.author_username)
+            .collect::<Vec<_>>()
+        );
+
+        assert_matches!(
+            db.delete_user(deleted.user_id, DeletionMode::Delete).await,
+            Err(RwError::CurrentUserDoesNotExist)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_find_or_create_user_by_oauth_identity() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (existing, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let identity = |subject: &str, email: &str, email_verified| OAuthIdentity {
+            provider: "github".to_string(),
+            subject: subject.to_string(),
+            email: email.parse().unwrap(),
+            email_verified,
+            username: "username".to_string(),
+        };
+
+        let (created, credentials, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("1", "new", true))
+            .await?;
+        assert!(was_created);
+        assert!(created.username.starts_with("username-"));
+        assert!(credentials.email_verified);
+        assert_eq!("", credentials.password_hash.as_ref());
+
+        let (found, _, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("1", "changed", true))
+            .await?;
+        assert!(!was_created);
+        assert_eq!(created.user_id, found.user_id);
+
+        let (linked, _, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("2", "email", true))
+            .await?;
+        assert!(!was_created);
+        assert_eq!(existing.user_id, linked.user_id);
+
+        assert_matches!(
+            db.find_or_create_by_oauth_identity(&identity("3", "email", false))
+                .await,
+            Err(RwError::EmailTaken)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocked_authors_should_be_hidden_from_listings() -> RwResult<()> {
+        use realworld_domain::article::repo::{ArticleRepo, Filter};
+        use realworld_domain::comment::repo::CommentRepo;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (troll, _) = db.insert_test_user(other_user()).await?;
+
+        db.insert_article(
+            user.user_id,
+            &Slug::from("mine"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            troll.user_id,
+            &Slug::from("theirs"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_comment(troll.user_id, &Slug::from("mine"), "first!", &[])
+            .await?;
+
+        db.insert_block(user.user_id, &troll.username).await?;
+        db.insert_block(user.user_id, &troll.username).await?;
+
+        let slugs = |articles: Vec<realworld_domain::article::repo::Article>| -> Vec<String> {
+            articles.into_iter().map(|article| article.slug.0).collect()
+        };
+        let article_id = db.fetch_article_id(&Slug::from("mine")).await?;
+
+        assert_eq!(
+            vec!["mine"],
+            slugs(
+                db.select_articles(user.user_id.some(), Filter::default())
+                    .await?
+            )
+        );
+        assert!(db
+            .list_comments(user.user_id.some(), article_id, Default::default())
+            .await?
+            .is_empty());
+        // The article itself can still be found
+        assert_eq!(
+            vec!["theirs"],
+            slugs(
+                db.select_articles(
+                    user.user_id.some(),
+                    Filter {
+                        slug: Some(&Slug::from("theirs")),
+                        ..Default::default()
+                    }
+                )
+                .await?
+            )
+        );
+        // Others still see everything
+        assert_eq!(
+            2,
+            db.select_articles(UserId(None), Filter::default())
+                .await?
+                .len()
+        );
+
+        assert_matches!(
+            db.insert_block(user.user_id, &user.username).await,
+            Err(RwError::Forbidden)
+        );
+
+        db.delete_block(user.user_id, &troll.username).await?;
+        assert_eq!(
+            1,
+            db.list_comments(user.user_id.some(), article_id, Default::default())
+                .await?
+                .len()
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,130 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::repo::ArticleView;
+use realworld_domain::error::RwResult;
+
+use entrait::*;
+
+pub struct PgViewRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::article::repo::ViewRepoImpl for PgViewRepo {
+    pub async fn insert_views(deps: &impl GetDb, views: &[ArticleView]) -> RwResult<u64> {
+        let slugs: Vec<&str> = views.iter().map(|view| view.slug.as_str()).collect();
+        let viewers: Vec<String> = views.iter().map(|view| view.viewer.to_string()).collect();
+        let days: Vec<time::Date> = views.iter().map(|view| view.day).collect();
+
+        // The whole batch in one statement, with one update of each viewed article's count
+        let count = sqlx::query_scalar!(
+            // language=PostgreSQL
+            r#"
+            WITH inserted_view AS (
+                INSERT INTO article_view (article_id, viewer, day)
+                    SELECT article_id, view.viewer, view.day
+                    FROM unnest($1::text[], $2::text[], $3::date[]) view (slug, viewer, day)
+                    INNER JOIN article USING (slug)
+                -- if the viewer has already viewed the article that day
+                ON CONFLICT DO NOTHING
+                RETURNING article_id
+            ),
+            counted_view AS (
+                INSERT INTO article_view_count (article_id, views_count)
+                    SELECT article_id, count(*) FROM inserted_view GROUP BY article_id
+                ON CONFLICT (article_id) DO UPDATE
+                SET views_count = article_view_count.views_count + excluded.views_count
+            )
+            SELECT count(*) "count!" FROM inserted_view
+            "#,
+            &slugs as &[&str],
+            &viewers,
+            &days
+        )
+        .fetch_one(&deps.get_db().pg_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(count as u64)
+    }
+
+    pub async fn delete_views_before(deps: &impl GetDb, day: time::Date) -> RwResult<u64> {
+        let result = sqlx::query!("DELETE FROM article_view WHERE day < $1", day)
+            .execute(&deps.get_db().pg_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::article::repo::{ArticleRepo, ArticleView, Filter, ViewRepo, Viewer};
+    use realworld_domain::article::Slug;
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::UserId;
+
+    use std::net::IpAddr;
+    use time::Duration;
+
+    #[tokio::test]
+    async fn views_should_count_once_per_viewer_and_day() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        let today = time::OffsetDateTime::now_utc().date();
+        let view = |viewer, day| ArticleView {
+            slug: Slug::from("slug"),
+            viewer,
+            day,
+        };
+        let anonymous = Viewer::Ip(IpAddr::from([10, 0, 0, 1]));
+
+        assert_eq!(
+            2,
+            db.insert_views(&[
+                view(Viewer::User(user.user_id), today),
+                view(anonymous, today),
+                ArticleView {
+                    slug: Slug::from("deleted"),
+                    ..view(anonymous, today)
+                },
+            ])
+            .await?
+        );
+        assert_eq!(
+            1,
+            db.insert_views(&[
+                view(anonymous, today),
+                view(anonymous, today - Duration::days(1)),
+            ])
+            .await?
+        );
+
+        assert_eq!(1, db.delete_views_before(today).await?);
+        let articles = db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    slug: Some(&article.slug),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(3, articles[0].views_count);
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+CREATE TABLE comment_like
+(
+    comment_id integer NOT NULL REFERENCES article_comment (comment_id) ON DELETE CASCADE,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (comment_id, user_id)
+);
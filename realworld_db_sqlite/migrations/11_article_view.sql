@@ -0,0 +1,17 @@
+-- Who viewed which article on which day, so that a view is only counted once per viewer and day.
+-- Views of earlier days are purged, while their counts are kept in `article_view_count`.
+CREATE TABLE article_view
+(
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+    -- `user:<user id>` or `ip:<address>`
+    viewer text NOT NULL,
+    -- YYYY-MM-DD
+    day text NOT NULL,
+    PRIMARY KEY (article_id, viewer, day)
+);
+
+CREATE TABLE article_view_count
+(
+    article_id blob PRIMARY KEY REFERENCES article (article_id) ON DELETE CASCADE,
+    views_count integer NOT NULL
+);
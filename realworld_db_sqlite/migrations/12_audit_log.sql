@@ -0,0 +1,29 @@
+-- Security-relevant actions, for admins to look into.
+-- The user isn't a foreign key, so that entries outlive deleted users.
+CREATE TABLE audit_log
+(
+    audit_id integer PRIMARY KEY AUTOINCREMENT,
+    -- Who did it, when known
+    user_id blob,
+    -- 'login', 'failed_login', 'password_change', 'email_change', 'article_delete',
+    -- 'user_ban', 'user_unban' or 'comment_delete'
+    action text NOT NULL,
+    -- What it was done to, e.g. a slug, a username or the email address of a failed login
+    target text,
+    ip_address text,
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX audit_log_created_at ON audit_log (created_at);
+CREATE INDEX audit_log_user_id_created_at ON audit_log (user_id, created_at);
+
+-- The log is append-only
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,31 @@
+-- One session per sign-in, lasting as long as its refresh token keeps being exchanged
+CREATE TABLE session
+(
+    session_id blob PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    -- The User-Agent of the sign-in
+    device text,
+    expires_at text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_seen_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX session_user_id ON session (user_id);
+
+-- Refresh tokens from before sessions belong to none, so those users sign in again.
+-- The table is recreated, since a column can't be added with both NOT NULL and REFERENCES.
+DROP TABLE refresh_token;
+
+CREATE TABLE refresh_token
+(
+    token_hash text PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    session_id blob NOT NULL REFERENCES session (session_id) ON DELETE CASCADE,
+    expires_at text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX refresh_token_user_id ON refresh_token (user_id);
+CREATE INDEX refresh_token_session_id ON refresh_token (session_id);
@@ -0,0 +1,14 @@
+-- Archives of a user's data, compiled in the background when asked for
+CREATE TABLE data_export
+(
+    data_export_id blob PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    -- 'pending', 'running', 'ready' or 'failed'
+    status text NOT NULL DEFAULT 'pending',
+    -- Where the archive can be downloaded from, once ready
+    url text,
+    requested_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    finished_at text
+);
+
+CREATE INDEX data_export_user_id_requested_at ON data_export (user_id, requested_at);
@@ -0,0 +1,3 @@
+-- `NOCASE` only folds ASCII, letting e.g. `Ölaf` and `ölaf` coexist.
+-- `unicode_nocase` is registered by the application on every connection.
+CREATE UNIQUE INDEX user_username_unicode_nocase ON user (username COLLATE unicode_nocase);
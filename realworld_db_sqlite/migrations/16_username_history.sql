@@ -0,0 +1,9 @@
+-- Usernames that users have renamed themselves from, so that links to their old profiles keep working
+CREATE TABLE username_history
+(
+    username text COLLATE NOCASE NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    changed_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX username_history_username_changed_at ON username_history (username, changed_at);
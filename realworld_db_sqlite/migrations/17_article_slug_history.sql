@@ -0,0 +1,9 @@
+-- Slugs that articles had before their title was edited, so that links to them keep working
+CREATE TABLE article_slug_history
+(
+    slug text NOT NULL,
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+    changed_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX article_slug_history_slug_changed_at ON article_slug_history (slug, changed_at);
@@ -0,0 +1,8 @@
+-- Preferences of users who saved them. Keys missing from `settings` get their defaults when read.
+CREATE TABLE user_settings
+(
+    user_id blob PRIMARY KEY NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    -- JSON
+    settings text NOT NULL,
+    updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -0,0 +1,5 @@
+-- Unlisted articles are left out of every listing, and shared by a link with a random token.
+-- Public articles have no token.
+ALTER TABLE article ADD COLUMN share_token text;
+
+CREATE UNIQUE INDEX article_share_token ON article (share_token);
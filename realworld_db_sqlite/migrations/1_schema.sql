@@ -0,0 +1,113 @@
+-- The SQLite counterpart of the Postgres migrations, squashed into one.
+--
+-- UUIDs are 16 byte blobs and are generated by the application.
+-- Timestamps are RFC 3339 text in UTC with a fixed number of decimals, so that they sort correctly as text.
+
+CREATE TABLE user
+(
+    user_id blob PRIMARY KEY NOT NULL,
+    username text COLLATE NOCASE UNIQUE NOT NULL,
+    email text COLLATE NOCASE UNIQUE NOT NULL,
+    bio text NOT NULL DEFAULT '',
+    image text NULL,
+    password_hash text NOT NULL,
+    role text NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'moderator', 'admin')),
+    email_verified boolean NOT NULL DEFAULT false,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at text
+);
+
+CREATE TABLE follow
+(
+    followed_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    following_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+
+    CONSTRAINT user_cannot_follow_self CHECK (followed_user_id != following_user_id),
+    PRIMARY KEY (following_user_id, followed_user_id)
+);
+
+CREATE TABLE article
+(
+    article_id blob PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    slug text UNIQUE NOT NULL,
+    title text NOT NULL,
+    description text NOT NULL,
+    body text NOT NULL,
+    -- JSON array of strings
+    tag_list text NOT NULL DEFAULT '[]',
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE article_favorite
+(
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (article_id, user_id)
+);
+
+CREATE TABLE article_comment
+(
+    comment_id integer PRIMARY KEY AUTOINCREMENT,
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    body text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX article_comment_article_id_created_at ON article_comment (article_id, created_at);
+
+CREATE TABLE refresh_token
+(
+    token_hash text PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    expires_at text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE email_verification
+(
+    token_hash text PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    expires_at text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE password_reset
+(
+    token_hash text PRIMARY KEY NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    expires_at text NOT NULL,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX refresh_token_user_id ON refresh_token (user_id);
+CREATE INDEX email_verification_user_id ON email_verification (user_id);
+CREATE INDEX password_reset_user_id ON password_reset (user_id);
+
+CREATE TRIGGER user_updated_at AFTER UPDATE ON user FOR EACH ROW
+BEGIN
+    UPDATE user SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE user_id = NEW.user_id;
+END;
+
+CREATE TRIGGER article_updated_at AFTER UPDATE ON article FOR EACH ROW
+BEGIN
+    UPDATE article SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE article_id = NEW.article_id;
+END;
+
+CREATE TRIGGER article_comment_updated_at AFTER UPDATE ON article_comment FOR EACH ROW
+BEGIN
+    UPDATE article_comment SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE comment_id = NEW.comment_id;
+END;
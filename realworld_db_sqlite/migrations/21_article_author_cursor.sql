@@ -0,0 +1,2 @@
+-- Keyset pagination of feeds, newest first among the articles of each followed author
+CREATE INDEX article_user_created_at_slug ON article (user_id, created_at DESC, slug DESC);
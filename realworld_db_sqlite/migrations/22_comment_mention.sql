@@ -0,0 +1,12 @@
+-- The users mentioned with `@username` in a comment
+CREATE TABLE comment_mention
+(
+    comment_id integer NOT NULL REFERENCES article_comment (comment_id) ON DELETE CASCADE,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX comment_mention_user_id ON comment_mention (user_id);
+
+-- Notifications of mentions have the kind 'mentioned', and the article of the comment
@@ -0,0 +1,2 @@
+-- Keyset pagination of articles, newest first
+CREATE INDEX article_created_at_slug ON article (created_at DESC, slug DESC);
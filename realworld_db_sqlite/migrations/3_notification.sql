@@ -0,0 +1,23 @@
+CREATE TABLE notification
+(
+    notification_id integer PRIMARY KEY AUTOINCREMENT,
+    -- The user who gets notified
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    -- The user who did something
+    actor_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    -- 'followed', 'favorited' or 'commented'
+    kind text NOT NULL,
+    -- The article that was favorited or commented
+    article_id blob REFERENCES article (article_id) ON DELETE CASCADE,
+    read_at text,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX notification_user_id_created_at ON notification (user_id, created_at);
+
+CREATE TRIGGER notification_updated_at AFTER UPDATE ON notification FOR EACH ROW
+BEGIN
+    UPDATE notification SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE notification_id = NEW.notification_id;
+END;
@@ -0,0 +1,11 @@
+-- Failed logins, for locking out password guessing
+CREATE TABLE login_attempt
+(
+    login_attempt_id integer PRIMARY KEY AUTOINCREMENT,
+    email text COLLATE NOCASE NOT NULL,
+    ip_address text,
+    attempted_at text NOT NULL
+);
+
+CREATE INDEX login_attempt_email_attempted_at ON login_attempt (email, attempted_at);
+CREATE INDEX login_attempt_ip_address_attempted_at ON login_attempt (ip_address, attempted_at);
@@ -0,0 +1,13 @@
+-- Identities at external OAuth providers that users sign in with
+CREATE TABLE oauth_identity
+(
+    provider text NOT NULL,
+    subject text NOT NULL,
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+
+    PRIMARY KEY (provider, subject)
+);
+
+CREATE INDEX oauth_identity_user_id ON oauth_identity (user_id);
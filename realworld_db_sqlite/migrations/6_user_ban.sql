@@ -0,0 +1,2 @@
+-- Banned users can't sign in, and their tokens are rejected
+ALTER TABLE user ADD COLUMN banned_at text;
@@ -0,0 +1,21 @@
+-- Articles and comments reported by users, for moderators to look at
+CREATE TABLE report
+(
+    report_id integer PRIMARY KEY AUTOINCREMENT,
+    reporter_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+    -- Set when a comment on the article was reported, rather than the article itself
+    comment_id integer REFERENCES article_comment (comment_id) ON DELETE CASCADE,
+    reason text NOT NULL,
+    resolved_at text,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX report_created_at ON report (created_at);
+
+CREATE TRIGGER report_updated_at AFTER UPDATE ON report FOR EACH ROW
+BEGIN
+    UPDATE report SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE report_id = NEW.report_id;
+END;
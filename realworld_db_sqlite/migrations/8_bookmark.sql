@@ -0,0 +1,9 @@
+-- Articles saved for later. Unlike favorites, these are only visible to the user themselves.
+CREATE TABLE bookmark
+(
+    user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    article_id blob NOT NULL REFERENCES article (article_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (user_id, article_id)
+);
@@ -0,0 +1,10 @@
+-- Users whose articles and comments are hidden from the blocking user
+CREATE TABLE block
+(
+    blocking_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+    blocked_user_id blob NOT NULL REFERENCES user (user_id) ON DELETE CASCADE,
+
+    created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT user_cannot_block_self CHECK (blocking_user_id != blocked_user_id),
+    PRIMARY KEY (blocking_user_id, blocked_user_id)
+);
@@ -0,0 +1,1561 @@
+use crate::DbResultExt;
+use crate::GetDb;
+use crate::OnUniqueViolation;
+
+use realworld_domain::article::repo::*;
+use realworld_domain::article::{ArticleId, Slug};
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::timestamp::Timestamptz;
+use realworld_domain::user::repo::{Following, User};
+use realworld_domain::user::role::Role;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqliteArticleRepo;
+
+/// Columns of [ArticleRow], but the [CONTENT_COLUMNS]. `?1` is the current user.
+const ARTICLE_COLUMNS: &str = r#"
+    article.article_id,
+    article.slug,
+    article.title,
+    article.tag_list,
+    article.created_at,
+    article.updated_at,
+    EXISTS(
+        SELECT 1 FROM article_favorite WHERE article_id = article.article_id AND user_id = ?1
+    ) AS favorited,
+    (SELECT count(*) FROM article_favorite WHERE article_id = article.article_id) AS favorites_count,
+    COALESCE(
+        (SELECT views_count FROM article_view_count WHERE article_id = article.article_id),
+        0
+    ) AS views_count,
+    author.user_id AS author_id,
+    author.username AS author_username,
+    article.share_token
+"#;
+
+/// The description and body columns of [ArticleRow]
+const CONTENT_COLUMNS: &str = "article.description, article.body";
+
+/// [CONTENT_COLUMNS] of a summary, without the body, and with the description cut
+fn summary_content_columns() -> String {
+    format!(
+        r#"
+        CASE
+            WHEN length(article.description) > {SUMMARY_DESCRIPTION_LENGTH}
+            THEN substr(article.description, 1, {SUMMARY_DESCRIPTION_LENGTH} - 1) || '…'
+            ELSE article.description
+        END AS description,
+        '' AS body
+        "#
+    )
+}
+
+#[derive(sqlx::FromRow)]
+struct ArticleRow {
+    article_id: ArticleId,
+    slug: Slug,
+    title: String,
+    description: String,
+    body: String,
+    tag_list: Json<Vec<String>>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    favorited: bool,
+    favorites_count: i64,
+    views_count: i64,
+    author_id: Uuid,
+    author_username: String,
+    share_token: Option<String>,
+}
+
+impl From<ArticleRow> for Article {
+    fn from(row: ArticleRow) -> Self {
+        Article {
+            article_id: row.article_id,
+            slug: row.slug,
+            title: row.title,
+            description: row.description,
+            body: row.body,
+            tag_list: row.tag_list.0,
+            created_at: Timestamptz(row.created_at),
+            updated_at: Timestamptz(row.updated_at),
+            favorited: row.favorited,
+            favorites_count: row.favorites_count,
+            views_count: row.views_count,
+            author_id: row.author_id,
+            author_username: row.author_username,
+            share_token: row.share_token,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::article::repo::ArticleRepoImpl for SqliteArticleRepo {
+    pub async fn select_articles(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        filter: Filter<'_>,
+    ) -> RwResult<Vec<Article>> {
+        let content_columns = if filter.summary {
+            summary_content_columns()
+        } else {
+            CONTENT_COLUMNS.to_string()
+        };
+        let rows = sqlx::query_as::<_, ArticleRow>(&format!(
+            r#"
+            SELECT {ARTICLE_COLUMNS}, {content_columns}
+            FROM article
+            INNER JOIN user author USING (user_id)
+            WHERE (
+                ?2 IS NULL OR slug = ?2
+            ) AND (
+                ?14 IS NULL OR article.share_token = ?14
+            ) AND (
+                -- unlisted articles are only found by their slug or their token
+                article.share_token IS NULL OR ?2 IS NOT NULL OR ?14 IS NOT NULL
+            ) AND (
+                json_array_length(?3) = 0 OR EXISTS(
+                    SELECT 1 FROM json_each(article.tag_list) WHERE value IN (SELECT value FROM json_each(?3))
+                )
+            ) AND NOT EXISTS(
+                SELECT 1 FROM json_each(?11)
+                WHERE value NOT IN (SELECT value FROM json_each(article.tag_list))
+            ) AND NOT EXISTS(
+                SELECT 1 FROM json_each(article.tag_list) WHERE value IN (SELECT value FROM json_each(?12))
+            ) AND (
+                ?4 IS NULL OR author.username = ?4
+            ) AND (
+                ?5 IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM article_favorite
+                    INNER JOIN user favoriter USING (user_id)
+                    WHERE favoriter.username = ?5 AND article_id = article.article_id
+                )
+            ) AND (
+                ?6 IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM follow
+                    WHERE following_user_id = ?6 AND followed_user_id = author.user_id
+                )
+            ) AND (
+                ?15 IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM feed_entry
+                    WHERE user_id = ?15 AND article_id = article.article_id
+                )
+            ) AND (
+                -- blocked authors are left out of listings, but a single article can still be fetched
+                ?2 IS NOT NULL OR NOT EXISTS(
+                    SELECT 1 FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = author.user_id
+                )
+            ) AND (
+                ?13 IS NULL OR EXISTS(
+                    SELECT 1
+                    FROM bookmark
+                    WHERE user_id = ?13 AND article_id = article.article_id
+                )
+            ) AND (
+                -- normalized to the precision and format `created_at` is stored with
+                ?9 IS NULL OR (article.created_at, article.slug) < (strftime('%Y-%m-%dT%H:%M:%fZ', ?9), ?10)
+            )
+            ORDER BY article.created_at DESC, article.slug DESC
+            LIMIT ?7
+            OFFSET ?8
+            "#
+        ))
+        .bind(current_user.0)
+        .bind(filter.slug)
+        .bind(Json(filter.any_tag))
+        .bind(filter.author)
+        .bind(filter.favorited_by)
+        .bind(filter.followed_by.map(UserId::into_id))
+        .bind(filter.limit.unwrap_or(DEFAULT_LIMIT))
+        .bind(filter.offset.unwrap_or(0))
+        .bind(filter.after.map(|cursor| cursor.created_at.0))
+        .bind(filter.after.map(|cursor| cursor.slug.as_str()))
+        .bind(Json(filter.all_tags))
+        .bind(Json(filter.excluded_tags))
+        .bind(filter.bookmarked_by.map(UserId::into_id))
+        .bind(filter.share_token)
+        .bind(filter.in_feed_of.map(UserId::into_id))
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn fetch_article_id(deps: &impl GetDb, slug: &Slug) -> RwResult<ArticleId> {
+        sqlx::query_scalar("SELECT article_id FROM article WHERE slug = ?1")
+            .bind(slug)
+            .fetch_optional(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?
+            .ok_or(RwError::ArticleNotFound)
+    }
+
+    pub async fn find_article_slug(
+        deps: &impl GetDb,
+        article_id: ArticleId,
+    ) -> RwResult<Option<Slug>> {
+        sqlx::query_scalar("SELECT slug FROM article WHERE article_id = ?1")
+            .bind(article_id)
+            .fetch_optional(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()
+    }
+
+    pub async fn find_renamed_article_slug(
+        deps: &impl GetDb,
+        previous_slug: &Slug,
+    ) -> RwResult<Option<Slug>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT article.slug
+            FROM article_slug_history history
+            INNER JOIN article USING (article_id)
+            WHERE history.slug = ?1
+            ORDER BY history.changed_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(previous_slug)
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_article(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+        title: &str,
+        description: &str,
+        body: &str,
+        tag_list: &[String],
+        share_token: Option<&str>,
+    ) -> RwResult<Article> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+        let article_id = ArticleId(Uuid::new_v4());
+
+        sqlx::query(
+            r#"
+            INSERT INTO article (article_id, user_id, slug, title, description, body, tag_list, share_token)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(article_id)
+        .bind(user_id)
+        .bind(slug)
+        .bind(title)
+        .bind(description)
+        .bind(body)
+        .bind(Json(tag_list))
+        .bind(share_token)
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()
+        .on_unique_violation("article.slug", || {
+            RwError::DuplicateArticleSlug(slug.to_string())
+        })?;
+
+        let row = sqlx::query_as::<_, ArticleRow>(&format!(
+            r#"
+            SELECT {ARTICLE_COLUMNS}, {CONTENT_COLUMNS}
+            FROM article
+            INNER JOIN user author USING (user_id)
+            WHERE article_id = ?2
+            "#
+        ))
+        .bind(user_id)
+        .bind(article_id)
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(row.into())
+    }
+
+    pub async fn update_article(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+        up: ArticleUpdate<'_>,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let (article_id, author_id) = find_article_meta(&mut tx, slug).await?;
+        if author_id != user_id {
+            return Err(RwError::Forbidden);
+        }
+
+        if let Some(expected) = up.expected_updated_at {
+            let (updated_at,): (OffsetDateTime,) =
+                sqlx::query_as("SELECT updated_at FROM article WHERE article_id = ?1")
+                    .bind(article_id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .to_rw_err()?;
+            if updated_at != expected.0 {
+                return Err(RwError::PreconditionFailed);
+            }
+        }
+
+        if up.slug.is_some_and(|new_slug| new_slug != slug) {
+            sqlx::query("INSERT INTO article_slug_history (slug, article_id) VALUES (?1, ?2)")
+                .bind(slug)
+                .bind(article_id)
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE article
+            SET
+                slug = COALESCE(?1, slug),
+                title = COALESCE(?2, title),
+                description = COALESCE(?3, description),
+                body = COALESCE(?4, body),
+                tag_list = COALESCE(?5, tag_list),
+                share_token = CASE
+                    WHEN NOT ?6 THEN share_token
+                    WHEN ?7 IS NULL THEN NULL
+                    ELSE COALESCE(share_token, ?7)
+                END
+            WHERE article_id = ?8
+            "#,
+        )
+        .bind(up.slug)
+        .bind(up.title)
+        .bind(up.description)
+        .bind(up.body)
+        .bind(up.tag_list.map(Json))
+        .bind(up.share_token.is_some())
+        .bind(up.share_token.flatten())
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()
+        .on_unique_violation("article.slug", || {
+            RwError::DuplicateArticleSlug(up.slug.unwrap_or(slug).to_string())
+        })?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_article(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let (article_id, author_id) = find_article_meta(&mut tx, slug).await?;
+        if author_id != user_id {
+            return Err(RwError::Forbidden);
+        }
+
+        sqlx::query("DELETE FROM article WHERE article_id = ?1")
+            .bind(article_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_any_article(deps: &impl GetDb, slug: &Slug) -> RwResult<()> {
+        let result = sqlx::query("DELETE FROM article WHERE slug = ?1")
+            .bind(slug)
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::ArticleNotFound)
+        }
+    }
+
+    pub async fn insert_favorite(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let (article_id, _) = find_article_meta(&mut tx, slug).await?;
+
+        // if the article is already favorited, there's nothing to do
+        sqlx::query("INSERT OR IGNORE INTO article_favorite (article_id, user_id) VALUES (?1, ?2)")
+            .bind(article_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_favorite(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let (article_id, _) = find_article_meta(&mut tx, slug).await?;
+
+        sqlx::query("DELETE FROM article_favorite WHERE article_id = ?1 AND user_id = ?2")
+            .bind(article_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn set_favorites_bulk(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slugs: &[Slug],
+        favorited: bool,
+    ) -> RwResult<Vec<Slug>> {
+        // SQLite has no writes in `WITH`, so the existing articles are selected separately
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let statement = if favorited {
+            r#"
+            INSERT OR IGNORE INTO article_favorite (article_id, user_id)
+                SELECT article_id, ?2 FROM article
+                WHERE slug IN (SELECT value FROM json_each(?1))
+                AND (share_token IS NULL OR user_id = ?2)
+            "#
+        } else {
+            r#"
+            DELETE FROM article_favorite
+            WHERE article_id IN (
+                SELECT article_id FROM article
+                WHERE slug IN (SELECT value FROM json_each(?1))
+                AND (share_token IS NULL OR user_id = ?2)
+            )
+            AND user_id = ?2
+            "#
+        };
+        sqlx::query(statement)
+            .bind(Json(slugs))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        // Unlisted articles of others are not found by their slug
+        let found = sqlx::query_scalar(
+            r#"
+            SELECT slug FROM article
+            WHERE slug IN (SELECT value FROM json_each(?1))
+            AND (share_token IS NULL OR user_id = ?2)
+            "#,
+        )
+        .bind(Json(slugs))
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+        Ok(found)
+    }
+
+    pub async fn list_favoriting_users(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        slug: &Slug,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        let rows = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Role, bool)>(
+            r#"
+            SELECT
+                user.user_id,
+                username,
+                bio,
+                image,
+                role,
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = user.user_id AND following_user_id = ?2
+                )
+            FROM article
+            INNER JOIN article_favorite fav USING (article_id)
+            INNER JOIN user ON user.user_id = fav.user_id
+            WHERE slug = ?1
+            -- rowid breaks ties between favorites made in the same millisecond
+            ORDER BY fav.created_at DESC, fav.rowid DESC
+            LIMIT ?3
+            OFFSET ?4
+            "#,
+        )
+        .bind(slug)
+        .bind(current_user.0)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user_id, username, bio, image, role, following)| {
+                (
+                    User {
+                        user_id: UserId(user_id),
+                        username,
+                        bio,
+                        image,
+                        role,
+                    },
+                    Following(following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn select_trending_articles(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        since: OffsetDateTime,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let rows = sqlx::query_as::<_, ArticleRow>(&format!(
+            r#"
+            WITH activity AS (
+                SELECT article_id, count(*) AS score
+                FROM (
+                    SELECT article_id FROM article_favorite WHERE created_at >= strftime('%Y-%m-%dT%H:%M:%fZ', ?2)
+                    UNION ALL
+                    SELECT article_id FROM article_comment WHERE created_at >= strftime('%Y-%m-%dT%H:%M:%fZ', ?2)
+                )
+                GROUP BY article_id
+            )
+            SELECT {ARTICLE_COLUMNS}, {CONTENT_COLUMNS}
+            FROM activity
+            INNER JOIN article USING (article_id)
+            INNER JOIN user author ON author.user_id = article.user_id
+            WHERE article.share_token IS NULL AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = author.user_id
+            )
+            ORDER BY activity.score DESC, article.created_at DESC, article.slug DESC
+            LIMIT ?3
+            OFFSET ?4
+            "#
+        ))
+        .bind(current_user.0)
+        .bind(since)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn select_recommended_articles(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let rows = sqlx::query_as::<_, ArticleRow>(&format!(
+            r#"
+            WITH own_favorite AS (
+                SELECT article_id FROM article_favorite WHERE user_id = ?1
+            ),
+            -- users favoriting the same articles, and how many
+            similar_user AS (
+                SELECT user_id, count(*) AS overlap
+                FROM article_favorite
+                WHERE article_id IN (SELECT article_id FROM own_favorite) AND user_id <> ?1
+                GROUP BY user_id
+            ),
+            recommendation AS (
+                SELECT article_id, sum(similar_user.overlap) AS score
+                FROM article_favorite
+                INNER JOIN similar_user USING (user_id)
+                WHERE article_id NOT IN (SELECT article_id FROM own_favorite)
+                GROUP BY article_id
+            )
+            SELECT {ARTICLE_COLUMNS}, {CONTENT_COLUMNS}
+            FROM recommendation
+            INNER JOIN article USING (article_id)
+            INNER JOIN user author ON author.user_id = article.user_id
+            WHERE author.user_id <> ?1 AND article.share_token IS NULL AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = author.user_id
+            )
+            ORDER BY recommendation.score DESC, article.created_at DESC, article.slug DESC
+            LIMIT ?2
+            OFFSET ?3
+            "#
+        ))
+        .bind(user_id)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub fn stream_articles_by_author(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> BoxStream<'static, RwResult<Article>> {
+        // The stream outlives `deps`, so it gets its own handle to the pool
+        let sqlite_pool = deps.get_db().sqlite_pool.clone();
+
+        async_stream::try_stream! {
+            let query = format!(
+                r#"
+                SELECT {ARTICLE_COLUMNS}, {CONTENT_COLUMNS}
+                FROM article
+                INNER JOIN user author USING (user_id)
+                WHERE article.user_id = ?1
+                ORDER BY article.created_at, article.rowid
+                "#
+            );
+            let mut rows = sqlx::query_as::<_, ArticleRow>(&query)
+                .bind(user_id)
+                .fetch(&sqlite_pool);
+
+            while let Some(row) = rows.try_next().await.to_rw_err()? {
+                yield row.into();
+            }
+        }
+        .boxed()
+    }
+
+    pub fn stream_article_slugs(
+        deps: &impl GetDb,
+    ) -> BoxStream<'static, RwResult<(Slug, Timestamptz)>> {
+        let sqlite_pool = deps.get_db().sqlite_pool.clone();
+
+        async_stream::try_stream! {
+            let mut rows = sqlx::query_as::<_, (Slug, time::OffsetDateTime)>(
+                "SELECT slug, updated_at FROM article WHERE share_token IS NULL ORDER BY created_at, rowid",
+            )
+            .fetch(&sqlite_pool);
+
+            while let Some((slug, updated_at)) = rows.try_next().await.to_rw_err()? {
+                yield (slug, Timestamptz(updated_at));
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The id and author of an article
+async fn find_article_meta(
+    conn: &mut sqlx::SqliteConnection,
+    slug: &Slug,
+) -> RwResult<(ArticleId, Uuid)> {
+    sqlx::query_as("SELECT article_id, user_id FROM article WHERE slug = ?1")
+        .bind(slug)
+        .fetch_optional(conn)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_db;
+    use crate::user::tests as user_db_test;
+    use user_db_test::InsertTestUser;
+
+    use realworld_domain::iter_util::Single;
+    use realworld_domain::user::repo::UserRepo;
+
+    use assert_matches::*;
+
+    async fn select_single(
+        db: &impl ArticleRepo,
+        current_user: UserId<Option<Uuid>>,
+        filter: Filter<'_>,
+    ) -> Option<Article> {
+        db.select_articles(current_user, filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .single_or_none()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn article_lifecycle_should_work() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        let inserted_article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &["tag".to_string()],
+                None,
+            )
+            .await?;
+
+        assert_eq!(inserted_article.tag_list, &["tag".to_string()]);
+        assert_eq!(inserted_article.created_at.0, inserted_article.updated_at.0);
+        assert_eq!(inserted_article.author_username, user.username);
+        assert!(!inserted_article.favorited);
+
+        let fetched_article = select_single(
+            &db,
+            user.user_id.some(),
+            Filter {
+                any_tag: &["tag".to_string()],
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(Some(&inserted_article), fetched_article.as_ref());
+
+        db.update_article(
+            user.user_id,
+            &Slug::from("slug"),
+            ArticleUpdate {
+                slug: Some(&Slug::from("slug2")),
+                title: Some("title2"),
+                tag_list: Some(&["tag2".to_string()]),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let modified_article = select_single(
+            &db,
+            UserId(None),
+            Filter {
+                slug: Some(&Slug::from("slug2")),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(modified_article.title, "title2");
+        assert_eq!(modified_article.body, "body");
+        assert_eq!(modified_article.tag_list, &["tag2".to_string()]);
+
+        db.delete_article(user.user_id, &Slug::from("slug2"))
+            .await?;
+        assert_matches!(
+            db.fetch_article_id(&Slug::from("slug2")).await,
+            Err(RwError::ArticleNotFound)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renamed_article_should_be_found_by_previous_slugs() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        for (slug, new_slug) in [("slug", "slug2"), ("slug2", "slug3")] {
+            db.update_article(
+                user.user_id,
+                &Slug::from(slug),
+                ArticleUpdate {
+                    slug: Some(&Slug::from(new_slug)),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        }
+
+        let slug3 = Some(Slug::from("slug3"));
+        assert_eq!(slug3, db.find_article_slug(article.article_id).await?);
+        assert_eq!(
+            slug3,
+            db.find_renamed_article_slug(&Slug::from("slug")).await?
+        );
+        assert_eq!(
+            slug3,
+            db.find_renamed_article_slug(&Slug::from("slug2")).await?
+        );
+        assert_eq!(
+            None,
+            db.find_renamed_article_slug(&Slug::from("slug3")).await?
+        );
+
+        db.delete_article(user.user_id, &Slug::from("slug3"))
+            .await?;
+        assert_eq!(None, db.find_article_slug(article.article_id).await?);
+        assert_eq!(
+            None,
+            db.find_renamed_article_slug(&Slug::from("slug")).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn duplicate_slug_should_fail() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        assert_matches!(
+            db.insert_article(user.user_id, &Slug::from("slug"), "title", "desc", "body", &[], None)
+                .await,
+            Err(RwError::DuplicateArticleSlug(slug)) if slug == "slug"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unlisted_articles_should_only_be_found_by_slug_or_token() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        for (slug, share_token) in [("public", None), ("unlisted", Some("token"))] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                share_token,
+            )
+            .await?;
+        }
+
+        let listed = db
+            .select_articles(user.user_id.some(), Default::default())
+            .await?;
+        assert_eq!(
+            vec!["public"],
+            listed
+                .iter()
+                .map(|article| article.slug.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let unlisted_slug = Slug::from("unlisted");
+        let by_slug = select_single(
+            &db,
+            UserId(None),
+            Filter {
+                slug: Some(&unlisted_slug),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some("token"), by_slug.share_token.as_deref());
+
+        let by_token = select_single(
+            &db,
+            UserId(None),
+            Filter {
+                share_token: Some("token"),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(unlisted_slug, by_token.slug);
+
+        assert!(db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    share_token: Some("other"),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unlisted_article_should_keep_its_token_until_made_public() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let slug = Slug::from("slug");
+
+        db.insert_article(user.user_id, &slug, "title", "desc", "body", &[], None)
+            .await?;
+
+        for (share_token, expected) in [
+            (Some(Some("first")), Some("first")),
+            (Some(Some("second")), Some("first")),
+            (None, Some("first")),
+            (Some(None), None),
+            (Some(Some("second")), Some("second")),
+        ] {
+            db.update_article(
+                user.user_id,
+                &slug,
+                ArticleUpdate {
+                    share_token,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            let article = select_single(
+                &db,
+                UserId(None),
+                Filter {
+                    slug: Some(&slug),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(expected, article.share_token.as_deref());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn updating_article_changed_since_fetched_should_fail() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+
+        assert_matches!(
+            db.update_article(
+                user.user_id,
+                &Slug::from("slug"),
+                ArticleUpdate {
+                    body: Some("body2"),
+                    expected_updated_at: Some(&Timestamptz(OffsetDateTime::UNIX_EPOCH)),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::PreconditionFailed)
+        );
+        db.update_article(
+            user.user_id,
+            &Slug::from("slug"),
+            ArticleUpdate {
+                body: Some("body2"),
+                expected_updated_at: Some(&article.updated_at),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_filter_articles_by_tags() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        fn tags(tags: &[&str]) -> Vec<String> {
+            tags.iter().map(|tag| tag.to_string()).collect()
+        }
+
+        async fn slugs(db: &impl ArticleRepo, filter: Filter<'_>) -> Vec<String> {
+            let mut slugs: Vec<String> = db
+                .select_articles(UserId(None), filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect();
+            slugs.sort();
+            slugs
+        }
+
+        for (slug, tag_list) in [
+            ("a", tags(&["rust", "web"])),
+            ("b", tags(&["rust"])),
+            ("c", tags(&["web", "python"])),
+        ] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &tag_list,
+                None,
+            )
+            .await?;
+        }
+
+        let rust = tags(&["rust"]);
+        let web = tags(&["web"]);
+        let python = tags(&["python"]);
+        let rust_or_python = tags(&["rust", "python"]);
+        let rust_and_web = tags(&["rust", "web"]);
+
+        assert_eq!(tags(&["a", "b", "c"]), slugs(&db, Default::default()).await);
+        assert_eq!(
+            tags(&["a", "b", "c"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &rust_or_python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["c"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    all_tags: &rust_and_web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["b"]),
+            slugs(
+                &db,
+                Filter {
+                    excluded_tags: &web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["b"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &rust,
+                    excluded_tags: &web,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    any_tag: &web,
+                    all_tags: &rust,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+        assert_eq!(
+            tags(&["a"]),
+            slugs(
+                &db,
+                Filter {
+                    all_tags: &web,
+                    excluded_tags: &python,
+                    ..Default::default()
+                }
+            )
+            .await
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn favorites_should_be_counted_per_article() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        db.insert_article(
+            user.user_id,
+            &Slug::from("a"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("b"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        db.insert_favorite(other.user_id, &Slug::from("a")).await?;
+        db.insert_favorite(other.user_id, &Slug::from("a")).await?;
+
+        let favorited = select_single(
+            &db,
+            other.user_id.some(),
+            Filter {
+                favorited_by: Some(&other.username),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(favorited.slug, Slug::from("a"));
+        assert!(favorited.favorited);
+        assert_eq!(favorited.favorites_count, 1);
+
+        let not_favorited = select_single(
+            &db,
+            other.user_id.some(),
+            Filter {
+                slug: Some(&Slug::from("b")),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!not_favorited.favorited);
+
+        db.delete_favorite(other.user_id, &Slug::from("a")).await?;
+        assert_eq!(
+            None,
+            select_single(
+                &db,
+                UserId(None),
+                Filter {
+                    favorited_by: Some(&other.username),
+                    ..Default::default()
+                },
+            )
+            .await
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_list_favoriting_users() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (fan, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_favorite(author.user_id, &Slug::from("slug"))
+            .await?;
+        db.insert_favorite(fan.user_id, &Slug::from("slug")).await?;
+        db.insert_follow(author.user_id, &fan.username).await?;
+
+        let users = db
+            .list_favoriting_users(
+                author.user_id.some(),
+                &Slug::from("slug"),
+                Pagination::default(),
+            )
+            .await?;
+        assert_eq!(
+            vec![
+                (fan.username.as_str(), true),
+                (author.username.as_str(), false)
+            ],
+            users
+                .iter()
+                .map(|(user, Following(following))| (user.username.as_str(), *following))
+                .collect::<Vec<_>>()
+        );
+
+        let second_page = db
+            .list_favoriting_users(
+                UserId(None),
+                &Slug::from("slug"),
+                Pagination {
+                    limit: Some(1),
+                    offset: Some(1),
+                },
+            )
+            .await?;
+        assert_eq!(1, second_page.len());
+        assert_eq!(author.username, second_page[0].0.username);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_favorites_should_report_existing_slugs() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (fan, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        for slug in ["a", "b"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(fan.user_id, &Slug::from("a")).await?;
+
+        let slugs = |slugs: &[&str]| slugs.iter().map(|s| Slug::from(*s)).collect::<Vec<_>>();
+        let mut found = db
+            .set_favorites_bulk(fan.user_id, &slugs(&["a", "b", "unknown"]), true)
+            .await?;
+        found.sort();
+        assert_eq!(slugs(&["a", "b"]), found);
+        for slug in ["a", "b"] {
+            let users = db
+                .list_favoriting_users(UserId(None), &Slug::from(slug), Pagination::default())
+                .await?;
+            assert_eq!(1, users.len());
+        }
+
+        let mut found = db
+            .set_favorites_bulk(fan.user_id, &slugs(&["a", "b", "unknown"]), false)
+            .await?;
+        found.sort();
+        assert_eq!(slugs(&["a", "b"]), found);
+        assert!(db
+            .list_favoriting_users(UserId(None), &Slug::from("b"), Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn summaries_should_leave_out_body_and_cut_description() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let long_description = "é".repeat(300);
+        db.insert_article(
+            user.user_id,
+            &Slug::from("long"),
+            "title",
+            &long_description,
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("short"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        let (long_slug, short_slug) = (Slug::from("long"), Slug::from("short"));
+        let summaries = |slug| Filter {
+            slug: Some(slug),
+            summary: true,
+            ..Default::default()
+        };
+        let long = db
+            .select_articles(UserId(None), summaries(&long_slug))
+            .await?
+            .remove(0);
+        assert_eq!("", long.body);
+        assert_eq!(
+            SUMMARY_DESCRIPTION_LENGTH as usize,
+            long.description.chars().count()
+        );
+        assert!(long.description.ends_with("é…"));
+
+        let short = db
+            .select_articles(UserId(None), summaries(&short_slug))
+            .await?
+            .remove(0);
+        assert_eq!(
+            ("", "desc"),
+            (short.body.as_str(), short.description.as_str())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_continue_after_cursor() -> RwResult<()> {
+        use realworld_domain::article::cursor::ArticleCursor;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        for slug in ["a", "b", "c"] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+
+        async fn slugs(db: &impl ArticleRepo, filter: Filter<'_>) -> RwResult<Vec<String>> {
+            Ok(db
+                .select_articles(UserId(None), filter)
+                .await?
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect())
+        }
+
+        let first_page = db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    limit: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(
+            vec!["c", "b"],
+            first_page
+                .iter()
+                .map(|article| article.slug.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let cursor = ArticleCursor::from(first_page.last().unwrap());
+        assert_eq!(
+            vec!["a"],
+            slugs(
+                &db,
+                Filter {
+                    limit: Some(2),
+                    after: Some(&cursor),
+                    ..Default::default()
+                }
+            )
+            .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn trending_should_rank_by_recent_activity() -> RwResult<()> {
+        use realworld_domain::comment::repo::CommentRepo;
+
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(user_db_test::other_user()).await?;
+
+        for slug in ["quiet", "liked", "discussed"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(reader.user_id, &Slug::from("liked"))
+            .await?;
+        db.insert_favorite(author.user_id, &Slug::from("discussed"))
+            .await?;
+        db.insert_comment(reader.user_id, &Slug::from("discussed"), "first", &[])
+            .await?;
+        db.insert_comment(reader.user_id, &Slug::from("discussed"), "second", &[])
+            .await?;
+
+        let trending = db
+            .select_trending_articles(
+                reader.user_id.some(),
+                OffsetDateTime::now_utc() - time::Duration::hours(1),
+                Pagination::default(),
+            )
+            .await?;
+        assert_eq!(
+            vec![("discussed", false), ("liked", true)],
+            trending
+                .iter()
+                .map(|article| (article.slug.as_str(), article.favorited))
+                .collect::<Vec<_>>()
+        );
+
+        // A window starting in the future has no activity
+        assert!(db
+            .select_trending_articles(
+                UserId(None),
+                OffsetDateTime::now_utc() + time::Duration::hours(1),
+                Pagination::default(),
+            )
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recommended_should_be_favorited_by_users_with_common_favorites() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (me, _) = db.insert_test_user(user_db_test::named_user("me")).await?;
+        let (twin, _) = db
+            .insert_test_user(user_db_test::named_user("twin"))
+            .await?;
+        let (other, _) = db
+            .insert_test_user(user_db_test::named_user("other"))
+            .await?;
+        let (author, _) = db
+            .insert_test_user(user_db_test::named_user("author"))
+            .await?;
+
+        for slug in ["a", "b", "x", "y", "z"] {
+            db.insert_article(
+                author.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_article(
+            me.user_id,
+            &Slug::from("mine"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        for (user, slugs) in [
+            (&me, &["a", "b"][..]),
+            (&twin, &["a", "b", "x", "y", "mine"][..]),
+            (&other, &["a", "y", "z"][..]),
+        ] {
+            for slug in slugs {
+                db.insert_favorite(user.user_id, &Slug::from(*slug)).await?;
+            }
+        }
+
+        async fn slugs(
+            db: &impl ArticleRepo,
+            user_id: UserId,
+            pagination: Pagination,
+        ) -> RwResult<Vec<String>> {
+            Ok(db
+                .select_recommended_articles(user_id, pagination)
+                .await?
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect())
+        }
+
+        assert_eq!(
+            vec!["y", "x", "z"],
+            slugs(&db, me.user_id, Pagination::default()).await?
+        );
+        assert_eq!(
+            vec!["x"],
+            slugs(
+                &db,
+                me.user_id,
+                Pagination {
+                    limit: Some(1),
+                    offset: Some(1)
+                }
+            )
+            .await?
+        );
+        assert!(slugs(&db, author.user_id, Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
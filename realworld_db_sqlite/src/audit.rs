@@ -0,0 +1,162 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::audit::{AuditAction, AuditFilter, AuditRecord, NewAuditEntry};
+use realworld_domain::error::RwResult;
+
+use entrait::*;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqliteAuditLogRepo;
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    audit_id: i64,
+    user_id: Option<Uuid>,
+    username: Option<String>,
+    action: AuditAction,
+    target: Option<String>,
+    ip_address: Option<String>,
+    created_at: OffsetDateTime,
+}
+
+impl From<AuditRow> for AuditRecord {
+    fn from(row: AuditRow) -> Self {
+        AuditRecord {
+            audit_id: row.audit_id,
+            user_id: row.user_id,
+            username: row.username,
+            action: row.action,
+            target: row.target,
+            ip_address: row.ip_address,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::audit::AuditLogImpl for SqliteAuditLogRepo {
+    pub async fn record_audit(deps: &impl GetDb, entry: NewAuditEntry<'_>) -> RwResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (user_id, action, target, ip_address)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+        )
+        .bind(entry.user_id.0)
+        .bind(entry.action)
+        .bind(entry.target)
+        .bind(entry.ip_address.map(|ip_address| ip_address.to_string()))
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn select_audit_entries(
+        deps: &impl GetDb,
+        filter: AuditFilter,
+    ) -> RwResult<Vec<AuditRecord>> {
+        let rows = sqlx::query_as::<_, AuditRow>(
+            r#"
+            SELECT
+                audit_id,
+                audit_log.user_id,
+                username,
+                action,
+                target,
+                ip_address,
+                audit_log.created_at
+            FROM audit_log
+            LEFT JOIN user USING (user_id)
+            WHERE (?1 IS NULL OR audit_log.user_id = ?1)
+                AND (?2 IS NULL OR audit_log.created_at >= strftime('%Y-%m-%dT%H:%M:%fZ', ?2))
+                AND (?3 IS NULL OR audit_log.created_at < strftime('%Y-%m-%dT%H:%M:%fZ', ?3))
+            ORDER BY audit_log.created_at DESC, audit_id DESC
+            LIMIT ?4
+            OFFSET ?5
+            "#,
+        )
+        .bind(filter.user_id.map(|user_id| user_id.0))
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.pagination.limit())
+        .bind(filter.pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::audit::*;
+    use realworld_domain::error::*;
+    use realworld_domain::user::UserId;
+
+    #[tokio::test]
+    async fn audit_log_should_be_filtered_by_user_and_append_only() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        db.record_audit(NewAuditEntry {
+            user_id: user.user_id.some(),
+            action: AuditAction::Login,
+            target: None,
+            ip_address: None,
+        })
+        .await?;
+        db.record_audit(NewAuditEntry {
+            user_id: other.user_id.some(),
+            action: AuditAction::EmailChange,
+            target: Some("old@example.com"),
+            ip_address: None,
+        })
+        .await?;
+        db.record_audit(NewAuditEntry {
+            user_id: UserId(None),
+            action: AuditAction::FailedLogin,
+            target: Some("nobody@example.com"),
+            ip_address: None,
+        })
+        .await?;
+
+        assert_eq!(
+            3,
+            db.select_audit_entries(AuditFilter::default()).await?.len()
+        );
+
+        let by_user = db
+            .select_audit_entries(AuditFilter {
+                user_id: Some(other.user_id),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(1, by_user.len());
+        assert_eq!(AuditAction::EmailChange, by_user[0].action);
+        assert_eq!(Some(&other.username), by_user[0].username.as_ref());
+
+        let later = db
+            .select_audit_entries(AuditFilter {
+                since: Some(by_user[0].created_at + time::Duration::hours(1)),
+                ..Default::default()
+            })
+            .await?;
+        assert!(later.is_empty());
+
+        assert!(sqlx::query("DELETE FROM audit_log")
+            .execute(&db.sqlite_pool)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
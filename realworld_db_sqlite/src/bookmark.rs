@@ -0,0 +1,126 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::Slug;
+use realworld_domain::error::*;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use uuid::Uuid;
+
+pub struct SqliteBookmarkRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::article::repo::BookmarkRepoImpl for SqliteBookmarkRepo {
+    pub async fn insert_bookmark(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let article_id = find_article_id(&mut tx, slug).await?;
+
+        // if the article is already bookmarked, there's nothing to do
+        sqlx::query("INSERT OR IGNORE INTO bookmark (user_id, article_id) VALUES (?1, ?2)")
+            .bind(user_id)
+            .bind(article_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_bookmark(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let article_id = find_article_id(&mut tx, slug).await?;
+
+        sqlx::query("DELETE FROM bookmark WHERE user_id = ?1 AND article_id = ?2")
+            .bind(user_id)
+            .bind(article_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+}
+
+async fn find_article_id(conn: &mut sqlx::SqliteConnection, slug: &Slug) -> RwResult<Uuid> {
+    sqlx::query_scalar("SELECT article_id FROM article WHERE slug = ?1")
+        .bind(slug)
+        .fetch_optional(conn)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::{other_user, InsertTestUser};
+
+    use realworld_domain::article::repo::{ArticleRepo, BookmarkRepo, Filter};
+    use realworld_domain::article::Slug;
+    use realworld_domain::error::{RwError, RwResult};
+    use realworld_domain::user::UserId;
+
+    use assert_matches::*;
+
+    #[tokio::test]
+    async fn bookmarks_should_only_be_listed_for_their_user() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        for slug in ["a", "b"] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_bookmark(user.user_id, &Slug::from("a")).await?;
+        db.insert_bookmark(user.user_id, &Slug::from("a")).await?;
+
+        async fn bookmarked_slugs(db: &impl ArticleRepo, user_id: UserId) -> RwResult<Vec<String>> {
+            Ok(db
+                .select_articles(
+                    user_id.some(),
+                    Filter {
+                        bookmarked_by: Some(user_id),
+                        ..Default::default()
+                    },
+                )
+                .await?
+                .into_iter()
+                .map(|article| article.slug.0)
+                .collect())
+        }
+
+        assert_eq!(vec!["a"], bookmarked_slugs(&db, user.user_id).await?);
+        assert!(bookmarked_slugs(&db, other.user_id).await?.is_empty());
+
+        db.delete_bookmark(user.user_id, &Slug::from("a")).await?;
+        assert!(bookmarked_slugs(&db, user.user_id).await?.is_empty());
+
+        assert_matches!(
+            db.insert_bookmark(user.user_id, &Slug::from("unknown"))
+                .await,
+            Err(RwError::ArticleNotFound)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,597 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::{ArticleId, Slug};
+use realworld_domain::comment::repo::{ArticleComment, Comment, ListOptions, SortDirection};
+use realworld_domain::comment::CommentId;
+use realworld_domain::error::*;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqliteCommentRepo;
+
+/// Columns of [CommentRow]. `?1` is the current user.
+const COMMENT_COLUMNS: &str = r#"
+    comment.comment_id,
+    comment.created_at,
+    comment.updated_at,
+    comment.body,
+    author.user_id AS author_id,
+    author.username AS author_username,
+    (SELECT count(*) FROM comment_like WHERE comment_id = comment.comment_id) AS likes_count,
+    EXISTS(
+        SELECT 1 FROM comment_like WHERE comment_id = comment.comment_id AND user_id = ?1
+    ) AS liked,
+    (
+        SELECT json_group_array(username) FROM (
+            SELECT mentioned.username
+            FROM comment_mention
+            INNER JOIN user mentioned USING (user_id)
+            WHERE comment_mention.comment_id = comment.comment_id
+            ORDER BY mentioned.username
+        )
+    ) AS mentions
+"#;
+
+#[derive(sqlx::FromRow)]
+struct CommentRow {
+    comment_id: CommentId,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    body: String,
+    author_id: Uuid,
+    author_username: String,
+    likes_count: i64,
+    liked: bool,
+    mentions: Json<Vec<String>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ArticleCommentRow {
+    article_slug: Slug,
+    #[sqlx(flatten)]
+    comment: CommentRow,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Self {
+        Comment {
+            comment_id: row.comment_id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            body: row.body,
+            author_id: row.author_id,
+            author_username: row.author_username,
+            likes_count: row.likes_count,
+            liked: row.liked,
+            mentions: row.mentions.0,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::comment::repo::CommentRepoImpl for SqliteCommentRepo {
+    pub async fn list_comments(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        article_id: ArticleId,
+        options: ListOptions,
+    ) -> RwResult<Vec<Comment>> {
+        let rows = sqlx::query_as::<_, CommentRow>(&format!(
+            r#"
+            SELECT {COMMENT_COLUMNS}
+            FROM article_comment comment
+            INNER JOIN user author USING (user_id)
+            WHERE article_id = ?2 AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = author.user_id
+            )
+            ORDER BY
+                -- `comment_id` breaks ties between comments created at the same time
+                CASE WHEN ?3 THEN comment.created_at END DESC,
+                CASE WHEN ?3 THEN comment_id END DESC,
+                comment.created_at,
+                comment_id
+            LIMIT ?4
+            OFFSET ?5
+            "#
+        ))
+        .bind(current_user.0)
+        .bind(article_id)
+        .bind(options.direction == SortDirection::Desc)
+        // a negative limit means no limit
+        .bind(options.limit.unwrap_or(-1))
+        .bind(options.offset.unwrap_or(0))
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn count_comments(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        article_id: ArticleId,
+    ) -> RwResult<i64> {
+        sqlx::query_scalar(
+            r#"
+            SELECT count(*)
+            FROM article_comment comment
+            WHERE article_id = ?2 AND NOT EXISTS(
+                SELECT 1 FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = comment.user_id
+            )
+            "#,
+        )
+        .bind(current_user.0)
+        .bind(article_id)
+        .fetch_one(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn find_comment(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<Comment> {
+        let row = sqlx::query_as::<_, CommentRow>(&format!(
+            r#"
+            SELECT {COMMENT_COLUMNS}
+            FROM article_comment comment
+            INNER JOIN article USING (article_id)
+            INNER JOIN user author ON author.user_id = comment.user_id
+            WHERE comment_id = ?2 AND slug = ?3
+            "#
+        ))
+        .bind(current_user.0)
+        .bind(comment_id)
+        .bind(article_slug)
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        Ok(row.into())
+    }
+
+    pub async fn insert_comment(
+        deps: &impl GetDb,
+        current_user: UserId,
+        article_slug: &Slug,
+        body: &str,
+        mentions: &[String],
+    ) -> RwResult<Comment> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let comment_id: CommentId = sqlx::query_scalar(
+            r#"
+            INSERT INTO article_comment (article_id, user_id, body)
+                SELECT article_id, ?1, ?2
+                FROM article
+                WHERE slug = ?3
+            RETURNING comment_id
+            "#,
+        )
+        .bind(current_user.0)
+        .bind(body)
+        .bind(article_slug)
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        // Mentions of users that don't exist aren't inserted
+        sqlx::query(
+            r#"
+            INSERT INTO comment_mention (comment_id, user_id)
+                SELECT ?1, user_id
+                FROM user
+                WHERE username IN (SELECT value FROM json_each(?2))
+            "#,
+        )
+        .bind(comment_id)
+        .bind(Json(mentions))
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        let row = sqlx::query_as::<_, CommentRow>(&format!(
+            r#"
+            SELECT {COMMENT_COLUMNS}
+            FROM article_comment comment
+            INNER JOIN user author USING (user_id)
+            WHERE comment_id = ?2
+            "#
+        ))
+        .bind(current_user.0)
+        .bind(comment_id)
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(row.into())
+    }
+
+    pub async fn delete_comment(
+        deps: &impl GetDb,
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let author_id: Uuid = sqlx::query_scalar(
+            r#"
+            SELECT comment.user_id
+            FROM article_comment comment
+            INNER JOIN article USING (article_id)
+            WHERE comment_id = ?1 AND slug = ?2
+            "#,
+        )
+        .bind(comment_id)
+        .bind(article_slug)
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ArticleNotFound)?;
+
+        if author_id != current_user.0 {
+            return Err(RwError::Forbidden);
+        }
+
+        sqlx::query("DELETE FROM article_comment WHERE comment_id = ?1")
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn insert_like(
+        deps: &impl GetDb,
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        ensure_comment_exists(&mut tx, article_slug, comment_id).await?;
+
+        // if the comment is already liked, there's nothing to do
+        sqlx::query("INSERT OR IGNORE INTO comment_like (comment_id, user_id) VALUES (?1, ?2)")
+            .bind(comment_id)
+            .bind(current_user.0)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_like(
+        deps: &impl GetDb,
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        ensure_comment_exists(&mut tx, article_slug, comment_id).await?;
+
+        sqlx::query("DELETE FROM comment_like WHERE comment_id = ?1 AND user_id = ?2")
+            .bind(comment_id)
+            .bind(current_user.0)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_any_comment(
+        deps: &impl GetDb,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM article_comment
+            WHERE
+                comment_id = ?1
+            AND
+                article_id IN (SELECT article_id FROM article WHERE slug = ?2)
+            "#,
+        )
+        .bind(comment_id)
+        .bind(article_slug)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::ArticleNotFound)
+        }
+    }
+
+    pub fn stream_comments_by_author(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> BoxStream<'static, RwResult<ArticleComment>> {
+        // The stream outlives `deps`, so it gets its own handle to the pool
+        let sqlite_pool = deps.get_db().sqlite_pool.clone();
+
+        async_stream::try_stream! {
+            let query = format!(
+                r#"
+                SELECT article.slug AS article_slug, {COMMENT_COLUMNS}
+                FROM article_comment comment
+                INNER JOIN article USING (article_id)
+                INNER JOIN user author ON author.user_id = comment.user_id
+                WHERE comment.user_id = ?1
+                ORDER BY comment.created_at, comment_id
+                "#
+            );
+            let mut rows = sqlx::query_as::<_, ArticleCommentRow>(&query)
+                .bind(user_id)
+                .fetch(&sqlite_pool);
+
+            while let Some(row) = rows.try_next().await.to_rw_err()? {
+                yield ArticleComment {
+                    article_slug: row.article_slug,
+                    comment: row.comment.into(),
+                };
+            }
+        }
+        .boxed()
+    }
+}
+
+async fn ensure_comment_exists(
+    conn: &mut sqlx::SqliteConnection,
+    article_slug: &Slug,
+    comment_id: CommentId,
+) -> RwResult<()> {
+    sqlx::query_scalar::<_, CommentId>(
+        r#"
+        SELECT comment_id
+        FROM article_comment
+        INNER JOIN article USING (article_id)
+        WHERE comment_id = ?1 AND slug = ?2
+        "#,
+    )
+    .bind(comment_id)
+    .bind(article_slug)
+    .fetch_optional(conn)
+    .await
+    .to_rw_err()?
+    .ok_or(RwError::ArticleNotFound)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::comment::repo::CommentRepo;
+
+    #[tokio::test]
+    async fn comment_lifecycle() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        let article_id = db.fetch_article_id(&Slug::from("slug")).await?;
+
+        let first = db
+            .insert_comment(user.user_id, &Slug::from("slug"), "1", &[])
+            .await?;
+        let second = db
+            .insert_comment(user.user_id, &Slug::from("slug"), "2", &[])
+            .await?;
+
+        assert_eq!(
+            db.list_comments(user.user_id.some(), article_id, Default::default())
+                .await?,
+            [first.clone(), second.clone()]
+        );
+        assert_eq!(
+            db.list_comments(
+                UserId(None),
+                article_id,
+                ListOptions {
+                    limit: Some(1),
+                    direction: SortDirection::Desc,
+                    ..Default::default()
+                }
+            )
+            .await?,
+            std::slice::from_ref(&second)
+        );
+        assert_eq!(2, db.count_comments(UserId(None), article_id).await?);
+
+        assert_matches::assert_matches!(
+            db.delete_comment(
+                UserId(Uuid::new_v4()),
+                &Slug::from("slug"),
+                first.comment_id
+            )
+            .await,
+            Err(RwError::Forbidden)
+        );
+        db.delete_comment(user.user_id, &Slug::from("slug"), first.comment_id)
+            .await?;
+        db.delete_any_comment(&Slug::from("slug"), second.comment_id)
+            .await?;
+
+        assert_eq!(
+            db.list_comments(user.user_id.some(), article_id, Default::default())
+                .await?,
+            &[]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mentions_of_existing_users_should_be_stored() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        db.insert_test_user(crate::user::tests::named_user("bob"))
+            .await?;
+        db.insert_test_user(crate::user::tests::named_user("carol"))
+            .await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        let comment = db
+            .insert_comment(
+                user.user_id,
+                &Slug::from("slug"),
+                "@carol @nobody @bob",
+                &["carol".to_string(), "nobody".to_string(), "bob".to_string()],
+            )
+            .await?;
+        assert_eq!(vec!["bob", "carol"], comment.mentions);
+        assert_eq!(
+            db.find_comment(UserId(None), &Slug::from("slug"), comment.comment_id)
+                .await?
+                .mentions,
+            comment.mentions
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_comments_by_author_should_include_article_slug() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        db.insert_comment(user.user_id, &Slug::from("slug"), "1", &[])
+            .await?;
+        db.insert_comment(user.user_id, &Slug::from("slug"), "2", &[])
+            .await?;
+
+        let comments: Vec<_> = db
+            .stream_comments_by_author(user.user_id)
+            .map_ok(|comment| (comment.article_slug, comment.comment.body))
+            .try_collect()
+            .await?;
+        assert_eq!(
+            comments,
+            [
+                (Slug::from("slug"), "1".to_string()),
+                (Slug::from("slug"), "2".to_string())
+            ]
+        );
+
+        assert_eq!(
+            db.stream_comments_by_author(UserId(Uuid::new_v4()))
+                .count()
+                .await,
+            0
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn likes_should_be_counted_and_relative_to_current_user() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let (other_user, _) = db
+            .insert_test_user(crate::user::tests::other_user())
+            .await?;
+        db.insert_article(
+            user.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        let comment_id = db
+            .insert_comment(user.user_id, &Slug::from("slug"), "body", &[])
+            .await?
+            .comment_id;
+        db.insert_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        db.insert_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        db.insert_like(other_user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+
+        let comment = db
+            .find_comment(user.user_id.some(), &Slug::from("slug"), comment_id)
+            .await?;
+        assert_eq!((2, true), (comment.likes_count, comment.liked));
+
+        db.delete_like(user.user_id, &Slug::from("slug"), comment_id)
+            .await?;
+        let comment = db
+            .find_comment(user.user_id.some(), &Slug::from("slug"), comment_id)
+            .await?;
+        assert_eq!((1, false), (comment.likes_count, comment.liked));
+
+        assert_matches::assert_matches!(
+            db.insert_like(user.user_id, &Slug::from("other-slug"), comment_id)
+                .await,
+            Err(RwError::ArticleNotFound)
+        );
+        assert_matches::assert_matches!(
+            db.find_comment(
+                UserId(None),
+                &Slug::from("slug"),
+                CommentId(comment_id.0 + 1)
+            )
+            .await,
+            Err(RwError::ArticleNotFound)
+        );
+
+        Ok(())
+    }
+}
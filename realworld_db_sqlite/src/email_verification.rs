@@ -0,0 +1,96 @@
+use crate::DbResultExt;
+use crate::GetDb;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqliteEmailVerificationRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::EmailVerificationRepoImpl for SqliteEmailVerificationRepo {
+    pub async fn insert_email_verification(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        sqlx::query(
+            "INSERT INTO email_verification (user_id, token_hash, expires_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(user_id)
+        .bind(token_hash.as_ref())
+        .bind(expires_at)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn take_email_verification(
+        deps: &impl GetDb,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<UserId>> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let user_id = sqlx::query_as::<_, (Uuid, OffsetDateTime)>(
+            "DELETE FROM email_verification WHERE token_hash = ?1 RETURNING user_id, expires_at",
+        )
+        .bind(token_hash.as_ref())
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?
+        .filter(|(_, expires_at)| *expires_at > now)
+        .map(|(user_id, _)| user_id);
+
+        if let Some(user_id) = user_id {
+            sqlx::query("UPDATE user SET email_verified = true WHERE user_id = ?1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+        }
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(user_id.map(UserId))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::opaque_token::OpaqueToken;
+    use realworld_domain::user::repo::{EmailVerificationRepo, UserRepo};
+
+    #[tokio::test]
+    async fn taking_verification_token_should_verify_email() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+
+        let hash = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+        db.insert_email_verification(user.user_id, &hash, now + time::Duration::days(1))
+            .await?;
+
+        assert_eq!(
+            Some(user.user_id),
+            db.take_email_verification(&hash, now).await?
+        );
+        assert_eq!(None, db.take_email_verification(&hash, now).await?);
+
+        let (_, credentials) = db.find_user_credentials_by_id(user.user_id).await?.unwrap();
+        assert!(credentials.email_verified);
+        Ok(())
+    }
+}
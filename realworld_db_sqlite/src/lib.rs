@@ -0,0 +1,644 @@
+#![cfg_attr(feature = "use-associated-future", feature(type_alias_impl_trait))]
+
+//!
+//! SQLite implementations of the repositories in `realworld_domain`,
+//! for running the app without a Postgres server.
+//!
+//! Unlike `realworld_db`, queries are checked at runtime rather than at compile time,
+//! since the `sqlx` macros can only check against one database.
+//!
+
+use realworld_domain::error::{RwError, RwResult};
+
+use anyhow::Context;
+use entrait::entrait_export as entrait;
+use sqlx::error::ErrorKind;
+use sqlx::migrate::{Migrate, MigrationType, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub mod article;
+pub mod audit;
+pub mod ban;
+pub mod bookmark;
+pub mod comment;
+pub mod data_export;
+pub mod email_verification;
+pub mod feed;
+pub mod login_attempt;
+pub mod notification;
+pub mod password_reset;
+pub mod recommendation;
+pub mod refresh_token;
+pub mod report;
+pub mod session;
+pub mod settings;
+pub mod stats;
+pub mod user;
+pub mod view;
+
+#[derive(Clone)]
+pub struct Db {
+    pub sqlite_pool: SqlitePool,
+}
+
+///
+/// Sizing and timeouts of the connection pool.
+///
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// Connections that are kept open even when idle
+    pub min_connections: u32,
+    pub max_connections: u32,
+    /// How long to wait for a free connection before a query fails
+    pub acquire_timeout: Duration,
+    /// Idle connections above `min_connections` are closed after this long
+    pub idle_timeout: Option<Duration>,
+    /// Prepared statements cached per connection. 0 disables the cache.
+    pub statement_cache_capacity: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_connections: 0,
+            max_connections: 8,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            statement_cache_capacity: 100,
+        }
+    }
+}
+
+///
+/// Connections of the pool at one point in time.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PoolStatus {
+    /// Whether a connection could be acquired and used
+    pub healthy: bool,
+    /// Open connections, idle or in use
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub max_connections: u32,
+}
+
+///
+/// A migration of this build, and whether the database has run it.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+///
+/// How the schema of the database compares to the migrations of this build.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaStatus {
+    /// Every migration of this build, oldest first
+    pub migrations: Vec<MigrationStatus>,
+    /// Migrations the database has run that this build doesn't know, i.e. of a newer build
+    pub unknown_versions: Vec<i64>,
+}
+
+impl SchemaStatus {
+    fn new(migrator: &Migrator, applied_versions: &[i64]) -> Self {
+        let migrations: Vec<_> = migrator
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied_versions.contains(&migration.version),
+            })
+            .collect();
+        let unknown_versions = applied_versions
+            .iter()
+            .copied()
+            .filter(|version| !migrations.iter().any(|known| known.version == *version))
+            .collect();
+
+        Self {
+            migrations,
+            unknown_versions,
+        }
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus> {
+        self.migrations
+            .iter()
+            .filter(|migration| !migration.applied)
+    }
+
+    /// Fails unless the database has run exactly the migrations of this build
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if let Some(version) = self.unknown_versions.first() {
+            anyhow::bail!(
+                "the database has run migration {version}, which this build doesn't know; is the build outdated?"
+            );
+        }
+        if let Some(migration) = self.pending().next() {
+            anyhow::bail!(
+                "migration {} ({}) hasn't been run, see the `migrate` command",
+                migration.version,
+                migration.description
+            );
+        }
+        Ok(())
+    }
+}
+
+///
+/// Register the collations the schema uses, on every connection.
+///
+/// `NOCASE` only folds ASCII letters, so usernames are also unique by `unicode_nocase`,
+/// which folds all letters like Postgres' case-insensitive collation does.
+///
+fn with_collations(options: SqliteConnectOptions) -> SqliteConnectOptions {
+    options.collation("unicode_nocase", |a, b| {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    })
+}
+
+impl Db {
+    ///
+    /// Connect, and run the migrations that haven't been run yet if `migrate`.
+    /// Fails unless the database then has the schema this build expects.
+    ///
+    pub async fn init(url: &str, config: &PoolConfig, migrate: bool) -> anyhow::Result<Self> {
+        let db = Self::connect(url, config).await?;
+        if migrate {
+            db.migrate().await?;
+        }
+        db.schema_status().await?.verify()?;
+        Ok(db)
+    }
+
+    /// Connect to e.g. `sqlite://realworld.db`, creating the file if it doesn't exist.
+    pub async fn connect(url: &str, config: &PoolConfig) -> anyhow::Result<Self> {
+        let options =
+            with_collations(SqliteConnectOptions::from_str(url).context("invalid database_url")?)
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal)
+                .statement_cache_capacity(config.statement_cache_capacity);
+
+        let sqlite_pool = SqlitePoolOptions::new()
+            .min_connections(config.min_connections)
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect_with(options)
+            .await
+            .context("could not connect to database_url")?;
+
+        Ok(Db { sqlite_pool })
+    }
+
+    /// Run the migrations that haven't been run yet
+    pub async fn migrate(&self) -> anyhow::Result<()> {
+        sqlx::migrate!("./migrations")
+            .run(&self.sqlite_pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Which migrations of this build the database has run, creating sqlx' table of them if missing
+    pub async fn schema_status(&self) -> anyhow::Result<SchemaStatus> {
+        let mut connection = self.sqlite_pool.acquire().await?;
+        connection.ensure_migrations_table().await?;
+        let applied_versions: Vec<i64> = connection
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|applied| applied.version)
+            .collect();
+
+        Ok(SchemaStatus::new(
+            &sqlx::migrate!("./migrations"),
+            &applied_versions,
+        ))
+    }
+
+    ///
+    /// Revert the migrations newer than `target`, newest first.
+    ///
+    /// Only migrations with a `.down.sql` script can be reverted,
+    /// so this fails without reverting anything if any of them lacks one.
+    ///
+    pub async fn revert_migrations(&self, target: i64) -> anyhow::Result<()> {
+        let migrator = sqlx::migrate!("./migrations");
+
+        if let Some(migration) = migrator.iter().find(|migration| {
+            migration.version > target && migration.migration_type == MigrationType::Simple
+        }) {
+            anyhow::bail!(
+                "migration {} ({}) can't be reverted",
+                migration.version,
+                migration.description
+            );
+        }
+
+        migrator.undo(&self.sqlite_pool, target).await?;
+        Ok(())
+    }
+
+    /// Checks that a connection can be acquired within the acquire timeout, and that it responds
+    pub async fn pool_status(&self) -> PoolStatus {
+        let healthy = sqlx::query("SELECT 1")
+            .execute(&self.sqlite_pool)
+            .await
+            .is_ok();
+
+        PoolStatus {
+            healthy,
+            connections: self.sqlite_pool.size(),
+            idle_connections: self.sqlite_pool.num_idle() as u32,
+            max_connections: self.sqlite_pool.options().get_max_connections(),
+        }
+    }
+}
+
+#[entrait(pub GetDb)]
+fn get_db(db: &Db) -> &Db {
+    db
+}
+
+trait DbResultExt<T> {
+    fn to_rw_err(self) -> RwResult<T>;
+}
+
+impl<T> DbResultExt<T> for Result<T, sqlx::Error> {
+    fn to_rw_err(self) -> RwResult<T> {
+        self.map_err(|sqlx_error| match sqlx_error {
+            // The database itself, rather than the query, is the problem
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::WorkerCrashed => RwError::DatabaseUnavailable(sqlx_error.into()),
+            sqlx_error => RwError::Anyhow(sqlx_error.into()),
+        })
+    }
+}
+
+///
+/// A failure that may well not happen again if the operation is retried.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransientFailure {
+    /// The transaction conflicted with a concurrent one
+    Serialization,
+    /// The connection was lost during the operation
+    Connection,
+}
+
+impl TransientFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Serialization => "serialization",
+            Self::Connection => "connection",
+        }
+    }
+}
+
+/// Whether `error` is a transient failure of the database, and which one
+pub fn transient_failure(error: &RwError) -> Option<TransientFailure> {
+    let (RwError::DatabaseUnavailable(error) | RwError::Anyhow(error)) = error else {
+        return None;
+    };
+
+    match error.downcast_ref::<sqlx::Error>()? {
+        sqlx::Error::Io(_) => Some(TransientFailure::Connection),
+        // SQLITE_BUSY and SQLITE_LOCKED, including their extended codes:
+        // another connection held a lock that the statement needed
+        sqlx::Error::Database(dbe) => dbe
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .filter(|code| matches!(code & 0xff, 5 | 6))
+            .map(|_| TransientFailure::Serialization),
+        _ => None,
+    }
+}
+
+trait OnUniqueViolation<T> {
+    /// SQLite doesn't report constraint names,
+    /// so unique violations are recognized by their `table.column`.
+    fn on_unique_violation(self, column: &str, f: impl FnOnce() -> RwError) -> RwResult<T>;
+}
+
+impl<T> OnUniqueViolation<T> for RwResult<T> {
+    fn on_unique_violation(self, column: &str, map_err: impl FnOnce() -> RwError) -> RwResult<T> {
+        self.map_err(|e| match e {
+            RwError::Anyhow(error) => match error.downcast::<sqlx::Error>() {
+                Ok(sqlx::Error::Database(dbe))
+                    if dbe.kind() == ErrorKind::UniqueViolation
+                        && dbe.message().ends_with(column) =>
+                {
+                    map_err()
+                }
+                Ok(dbe) => RwError::Anyhow(dbe.into()),
+                Err(e) => RwError::Anyhow(e),
+            },
+            e => e,
+        })
+    }
+}
+
+///
+/// Delegates a repository trait of [Db] to its implementation in this crate,
+/// through a trait object with the `dyn-repos` feature.
+///
+macro_rules! delegate_repo {
+    ($($module:ident)::+, $delegate:ident, $repo_impl:ident, $repo:path) => {
+        #[cfg(not(feature = "dyn-repos"))]
+        impl $($module::)+$delegate<Self> for Db {
+            type Target = $repo;
+        }
+
+        #[cfg(feature = "dyn-repos")]
+        impl AsRef<dyn $($module::)+$repo_impl<Self> + Sync> for Db {
+            fn as_ref(&self) -> &(dyn $($module::)+$repo_impl<Self> + Sync) {
+                &$repo
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateUserRepo,
+    UserRepoImpl,
+    user::SqliteUserRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateRefreshTokenRepo,
+    RefreshTokenRepoImpl,
+    refresh_token::SqliteRefreshTokenRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateSessionRepo,
+    SessionRepoImpl,
+    session::SqliteSessionRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateEmailVerificationRepo,
+    EmailVerificationRepoImpl,
+    email_verification::SqliteEmailVerificationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateLoginAttemptRepo,
+    LoginAttemptRepoImpl,
+    login_attempt::SqliteLoginAttemptRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegatePasswordResetRepo,
+    PasswordResetRepoImpl,
+    password_reset::SqlitePasswordResetRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateArticleRepo,
+    ArticleRepoImpl,
+    article::SqliteArticleRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateBookmarkRepo,
+    BookmarkRepoImpl,
+    bookmark::SqliteBookmarkRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::article::feed,
+    DelegateFeedRepo,
+    FeedRepoImpl,
+    feed::SqliteFeedRepo
+);
+
+delegate_repo!(
+    realworld_domain::article::repo,
+    DelegateViewRepo,
+    ViewRepoImpl,
+    view::SqliteViewRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::comment::repo,
+    DelegateCommentRepo,
+    CommentRepoImpl,
+    comment::SqliteCommentRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::data_export,
+    DelegateDataExportRepo,
+    DataExportRepoImpl,
+    data_export::SqliteDataExportRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::notification::repo,
+    DelegateNotificationRepo,
+    NotificationRepoImpl,
+    notification::SqliteNotificationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::recommendation,
+    DelegateRecommendationRepo,
+    RecommendationRepoImpl,
+    recommendation::SqliteRecommendationRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::settings,
+    DelegateSettingsRepo,
+    SettingsRepoImpl,
+    settings::SqliteSettingsRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::stats,
+    DelegateStatsRepo,
+    StatsRepoImpl,
+    stats::SqliteStatsRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::user::repo,
+    DelegateBanRepo,
+    BanRepoImpl,
+    ban::SqliteBanRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::report::repo,
+    DelegateReportRepo,
+    ReportRepoImpl,
+    report::SqliteReportRepo
+);
+
+#[cfg(test)]
+delegate_repo!(
+    realworld_domain::audit,
+    DelegateAuditLog,
+    AuditLogImpl,
+    audit::SqliteAuditLogRepo
+);
+
+/// A migrated in-memory database of its own for the current test
+#[cfg(any(test, feature = "testing"))]
+pub async fn create_test_db() -> entrait::Impl<Db> {
+    // An in-memory database only lives as long as its connection
+    let options = SqliteConnectOptions::from_str("sqlite::memory:").unwrap();
+    let sqlite_pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(with_collations(options))
+        .await
+        .expect("failed to open in-memory database");
+
+    sqlx::migrate!("./migrations")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to migrate");
+
+    entrait::Impl::new(Db { sqlite_pool })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn schema_should_only_be_verified_with_the_migrations_of_this_build() {
+        let db = create_test_db().await;
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(None, status.pending().next());
+        status.verify().unwrap();
+
+        let latest = status.migrations.last().unwrap().version;
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+            .bind(latest)
+            .execute(&db.sqlite_pool)
+            .await
+            .unwrap();
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(
+            vec![latest],
+            status
+                .pending()
+                .map(|migration| migration.version)
+                .collect::<Vec<_>>()
+        );
+        assert!(status.verify().is_err());
+
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES ($1, 'future', true, x'', 0), (9999, 'from a newer build', true, x'', 0)",
+        )
+        .bind(latest)
+        .execute(&db.sqlite_pool)
+        .await
+        .unwrap();
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(vec![9999], status.unknown_versions);
+        assert!(status
+            .verify()
+            .unwrap_err()
+            .to_string()
+            .contains("doesn't know"));
+    }
+
+    #[tokio::test]
+    async fn migrations_without_down_script_should_not_be_reverted() {
+        let db = create_test_db().await;
+
+        let error = db.revert_migrations(0).await.unwrap_err();
+        assert!(error.to_string().contains("can't be reverted"));
+
+        let latest = sqlx::migrate!("./migrations")
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap();
+        db.revert_migrations(latest).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn busy_database_should_be_transient() {
+        use sqlx::{ConnectOptions, Connection};
+
+        let path = std::env::temp_dir().join(format!("busy-{}.db", uuid::Uuid::new_v4()));
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .busy_timeout(Duration::ZERO);
+        let mut holder = options.connect().await.unwrap();
+        let mut waiter = options.connect().await.unwrap();
+
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut holder)
+            .await
+            .unwrap();
+        let busy = sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut waiter)
+            .await
+            .to_rw_err()
+            .unwrap_err();
+
+        assert_eq!(
+            Some(TransientFailure::Serialization),
+            transient_failure(&busy)
+        );
+        assert_eq!(None, transient_failure(&RwError::ArticleNotFound));
+
+        holder.close().await.unwrap();
+        waiter.close().await.unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn pool_status_should_be_healthy() {
+        let db = create_test_db().await;
+        let status = db.pool_status().await;
+
+        assert!(status.healthy);
+        assert_eq!((1, 1), (status.connections, status.max_connections));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::DbResultExt;
+use crate::GetDb;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::email::Email;
+
+use entrait::*;
+use std::net::IpAddr;
+use time::OffsetDateTime;
+
+pub struct SqliteLoginAttemptRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::LoginAttemptRepoImpl for SqliteLoginAttemptRepo {
+    pub async fn insert_failed_login(
+        deps: &impl GetDb,
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        attempted_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        sqlx::query(
+            "INSERT INTO login_attempt (email, ip_address, attempted_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(email.as_ref())
+        .bind(ip_address.map(|ip_address| ip_address.to_string()))
+        .bind(attempted_at)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn count_failed_logins(
+        deps: &impl GetDb,
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        since: OffsetDateTime,
+    ) -> RwResult<i64> {
+        sqlx::query_scalar(
+            r#"
+            SELECT count(*)
+            FROM login_attempt
+            WHERE (email = ?1 OR ip_address = ?2) AND attempted_at > ?3
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(ip_address.map(|ip_address| ip_address.to_string()))
+        .bind(since)
+        .fetch_one(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()
+    }
+
+    pub async fn delete_failed_logins(deps: &impl GetDb, email: &Email) -> RwResult<()> {
+        sqlx::query("DELETE FROM login_attempt WHERE email = ?1")
+            .bind(email.as_ref())
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn delete_failed_logins_before(
+        deps: &impl GetDb,
+        before: OffsetDateTime,
+    ) -> RwResult<u64> {
+        let result = sqlx::query("DELETE FROM login_attempt WHERE attempted_at < ?1")
+            .bind(before)
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_db;
+
+    use realworld_domain::user::repo::LoginAttemptRepo;
+
+    #[tokio::test]
+    async fn failed_logins_should_count_by_email_or_ip_address() -> RwResult<()> {
+        let db = create_test_db().await;
+        let email: Email = "name@email.com".parse().unwrap();
+        let other_email: Email = "other@email.com".parse().unwrap();
+        let ip_address = Some(IpAddr::from([10, 0, 0, 1]));
+        let now = OffsetDateTime::now_utc();
+        let an_hour_ago = now - time::Duration::hours(1);
+
+        db.insert_failed_login(&email, None, an_hour_ago).await?;
+        db.insert_failed_login(&email, None, now).await?;
+        db.insert_failed_login(&other_email, ip_address, now)
+            .await?;
+
+        let since = now - time::Duration::minutes(15);
+        assert_eq!(1, db.count_failed_logins(&email, None, since).await?);
+        assert_eq!(2, db.count_failed_logins(&email, ip_address, since).await?);
+        assert_eq!(
+            3,
+            db.count_failed_logins(&email, ip_address, an_hour_ago - time::Duration::SECOND)
+                .await?
+        );
+
+        db.delete_failed_logins(&email).await?;
+        assert_eq!(1, db.count_failed_logins(&email, ip_address, since).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::Slug;
+use realworld_domain::error::*;
+use realworld_domain::notification::repo::Notification;
+use realworld_domain::notification::NotificationKind;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use time::OffsetDateTime;
+
+pub struct SqliteNotificationRepo;
+
+#[derive(sqlx::FromRow)]
+struct NotificationRow {
+    notification_id: i64,
+    kind: NotificationKind,
+    created_at: OffsetDateTime,
+    read_at: Option<OffsetDateTime>,
+    actor_username: String,
+    actor_bio: String,
+    actor_image: Option<String>,
+    following_actor: bool,
+    article_slug: Option<Slug>,
+}
+
+impl From<NotificationRow> for Notification {
+    fn from(row: NotificationRow) -> Self {
+        Notification {
+            notification_id: row.notification_id,
+            kind: row.kind,
+            created_at: row.created_at,
+            read_at: row.read_at,
+            actor_username: row.actor_username,
+            actor_bio: row.actor_bio,
+            actor_image: row.actor_image,
+            following_actor: row.following_actor,
+            article_slug: row.article_slug,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::notification::repo::NotificationRepoImpl for SqliteNotificationRepo {
+    pub async fn insert_follow_notification(
+        deps: &impl GetDb,
+        UserId(actor): UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO notification (user_id, actor_user_id, kind)
+                SELECT user_id, ?1, ?2
+                FROM user
+                WHERE username = ?3
+            "#,
+        )
+        .bind(actor)
+        .bind(NotificationKind::Followed)
+        .bind(username)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn insert_article_notification(
+        deps: &impl GetDb,
+        UserId(actor): UserId,
+        kind: NotificationKind,
+        article_slug: &Slug,
+    ) -> RwResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO notification (user_id, actor_user_id, kind, article_id)
+                SELECT user_id, ?1, ?2, article_id
+                FROM article
+                WHERE slug = ?3 AND user_id <> ?1
+            "#,
+        )
+        .bind(actor)
+        .bind(kind)
+        .bind(article_slug)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn insert_mention_notification(
+        deps: &impl GetDb,
+        UserId(actor): UserId,
+        username: &str,
+        article_slug: &Slug,
+    ) -> RwResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO notification (user_id, actor_user_id, kind, article_id)
+                SELECT mentioned.user_id, ?1, ?2, article.article_id
+                FROM user mentioned, article
+                WHERE mentioned.username = ?3 AND article.slug = ?4 AND mentioned.user_id <> ?1
+            "#,
+        )
+        .bind(actor)
+        .bind(NotificationKind::Mentioned)
+        .bind(username)
+        .bind(article_slug)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn list_notifications(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> RwResult<Vec<Notification>> {
+        let rows = sqlx::query_as::<_, NotificationRow>(
+            r#"
+            SELECT
+                notification_id,
+                kind,
+                notification.created_at,
+                read_at,
+                actor.username AS actor_username,
+                actor.bio AS actor_bio,
+                actor.image AS actor_image,
+                EXISTS(
+                    SELECT 1 FROM follow WHERE followed_user_id = actor.user_id AND following_user_id = ?1
+                ) AS following_actor,
+                article.slug AS article_slug
+            FROM notification
+            INNER JOIN user actor ON actor.user_id = notification.actor_user_id
+            LEFT JOIN article USING (article_id)
+            WHERE notification.user_id = ?1 AND NOT (?2 AND read_at IS NOT NULL)
+            ORDER BY notification.created_at DESC, notification_id DESC
+            LIMIT ?3
+            OFFSET ?4
+            "#,
+        )
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn mark_notification_read(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        notification_id: i64,
+    ) -> RwResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE notification
+            SET read_at = coalesce(read_at, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            WHERE notification_id = ?1 AND user_id = ?2
+            "#,
+        )
+        .bind(notification_id)
+        .bind(user_id)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        if result.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(RwError::NotificationNotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_db;
+    use crate::user::tests as user_db_test;
+    use user_db_test::InsertTestUser;
+
+    use realworld_domain::article::repo::ArticleRepo;
+    use realworld_domain::notification::repo::NotificationRepo;
+
+    use assert_matches::*;
+
+    #[tokio::test]
+    async fn notification_lifecycle() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        db.insert_follow_notification(reader.user_id, &author.username)
+            .await?;
+        db.insert_article_notification(
+            reader.user_id,
+            NotificationKind::Favorited,
+            &Slug::from("slug"),
+        )
+        .await?;
+        db.insert_article_notification(
+            author.user_id,
+            NotificationKind::Commented,
+            &Slug::from("slug"),
+        )
+        .await?;
+
+        let notifications = db.list_notifications(author.user_id, false, 10, 0).await?;
+        assert_eq!(
+            vec![
+                (NotificationKind::Favorited, Some("slug")),
+                (NotificationKind::Followed, None)
+            ],
+            notifications
+                .iter()
+                .map(|n| (n.kind, n.article_slug.as_ref().map(Slug::as_str)))
+                .collect::<Vec<_>>()
+        );
+
+        let read_id = notifications[0].notification_id;
+        assert_matches!(
+            db.mark_notification_read(reader.user_id, read_id).await,
+            Err(RwError::NotificationNotFound)
+        );
+        db.mark_notification_read(author.user_id, read_id).await?;
+
+        let unread = db.list_notifications(author.user_id, true, 10, 0).await?;
+        assert_eq!(1, unread.len());
+        assert_eq!(NotificationKind::Followed, unread[0].kind);
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn mentioned_user_should_be_notified() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (author, _) = db.insert_test_user(Default::default()).await?;
+        let (reader, _) = db.insert_test_user(user_db_test::other_user()).await?;
+        db.insert_article(
+            author.user_id,
+            &Slug::from("slug"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+
+        db.insert_mention_notification(author.user_id, &reader.username, &Slug::from("slug"))
+            .await?;
+        // Not notified about mentioning yourself, or users that don't exist
+        db.insert_mention_notification(author.user_id, &author.username, &Slug::from("slug"))
+            .await?;
+        db.insert_mention_notification(author.user_id, "nobody", &Slug::from("slug"))
+            .await?;
+
+        let notifications = db.list_notifications(reader.user_id, false, 10, 0).await?;
+        assert_eq!(
+            vec![(NotificationKind::Mentioned, Some("slug"))],
+            notifications
+                .iter()
+                .map(|n| (n.kind, n.article_slug.as_ref().map(Slug::as_str)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(author.username, notifications[0].actor_username);
+        assert!(db
+            .list_notifications(author.user_id, false, 10, 0)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::DbResultExt;
+use crate::GetDb;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqlitePasswordResetRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::PasswordResetRepoImpl for SqlitePasswordResetRepo {
+    pub async fn insert_password_reset(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        sqlx::query(
+            "INSERT INTO password_reset (user_id, token_hash, expires_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(user_id)
+        .bind(token_hash.as_ref())
+        .bind(expires_at)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn take_password_reset(
+        deps: &impl GetDb,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<UserId>> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let row = sqlx::query_as::<_, (Uuid, OffsetDateTime)>(
+            "DELETE FROM password_reset WHERE token_hash = ?1 RETURNING user_id, expires_at",
+        )
+        .bind(token_hash.as_ref())
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        if let Some((user_id, _)) = &row {
+            sqlx::query("DELETE FROM password_reset WHERE user_id = ?1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+        }
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(row
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(user_id, _)| UserId(user_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::opaque_token::OpaqueToken;
+    use realworld_domain::user::repo::PasswordResetRepo;
+
+    #[tokio::test]
+    async fn taking_reset_token_should_discard_other_tokens() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let first = OpaqueToken::generate().hash();
+        let second = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+
+        for hash in [&first, &second] {
+            db.insert_password_reset(user.user_id, hash, now + time::Duration::hours(1))
+                .await?;
+        }
+
+        assert_eq!(
+            Some(user.user_id),
+            db.take_password_reset(&second, now).await?
+        );
+        assert_eq!(None, db.take_password_reset(&first, now).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::DbResultExt;
+use crate::GetDb;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct SqliteRefreshTokenRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::RefreshTokenRepoImpl for SqliteRefreshTokenRepo {
+    pub async fn insert_refresh_token(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        session_id: Uuid,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_token (user_id, session_id, token_hash, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .bind(token_hash.as_ref())
+        .bind(expires_at)
+        .execute(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn take_refresh_token(
+        deps: &impl GetDb,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<(UserId, Uuid)>> {
+        // Expired tokens are deleted too, there's no use in keeping them around.
+        let row = sqlx::query_as::<_, (Uuid, Uuid, OffsetDateTime)>(
+            r#"
+            DELETE FROM refresh_token WHERE token_hash = ?1
+            RETURNING user_id, session_id, expires_at
+            "#,
+        )
+        .bind(token_hash.as_ref())
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(row
+            .filter(|(_, _, expires_at)| *expires_at > now)
+            .map(|(user_id, session_id, _)| (UserId(user_id), session_id)))
+    }
+
+    pub async fn delete_expired_refresh_tokens(
+        deps: &impl GetDb,
+        now: OffsetDateTime,
+    ) -> RwResult<u64> {
+        let result = sqlx::query("DELETE FROM refresh_token WHERE expires_at <= ?1")
+            .bind(now)
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::opaque_token::OpaqueToken;
+    use realworld_domain::user::repo::{RefreshTokenRepo, SessionRepo};
+
+    #[tokio::test]
+    async fn refresh_token_should_only_be_taken_once() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let hash = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+        let session_id = db
+            .insert_session(user.user_id, None, now + time::Duration::days(1))
+            .await?;
+
+        db.insert_refresh_token(
+            user.user_id,
+            session_id,
+            &hash,
+            now + time::Duration::days(1),
+        )
+        .await?;
+
+        assert_eq!(
+            Some((user.user_id, session_id)),
+            db.take_refresh_token(&hash, now).await?
+        );
+        assert_eq!(None, db.take_refresh_token(&hash, now).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_refresh_token_should_not_be_valid() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let hash = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+        let session_id = db
+            .insert_session(user.user_id, None, now + time::Duration::days(1))
+            .await?;
+
+        db.insert_refresh_token(
+            user.user_id,
+            session_id,
+            &hash,
+            now - time::Duration::seconds(1),
+        )
+        .await?;
+
+        assert_eq!(None, db.take_refresh_token(&hash, now).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_expired_refresh_tokens_should_be_purged() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let valid = OpaqueToken::generate().hash();
+        let now = time::OffsetDateTime::now_utc();
+        let session_id = db
+            .insert_session(user.user_id, None, now + time::Duration::days(1))
+            .await?;
+
+        db.insert_refresh_token(
+            user.user_id,
+            session_id,
+            &valid,
+            now + time::Duration::days(1),
+        )
+        .await?;
+        db.insert_refresh_token(
+            user.user_id,
+            session_id,
+            &OpaqueToken::generate().hash(),
+            now - time::Duration::days(1),
+        )
+        .await?;
+
+        assert_eq!(1, db.delete_expired_refresh_tokens(now).await?);
+        assert_eq!(
+            Some((user.user_id, session_id)),
+            db.take_refresh_token(&valid, now).await?
+        );
+        Ok(())
+    }
+}
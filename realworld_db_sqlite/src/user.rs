@@ -0,0 +1,1449 @@
+use crate::DbResultExt;
+use crate::GetDb;
+use crate::OnUniqueViolation;
+
+use realworld_domain::error::{RwError, RwResult};
+use realworld_domain::pagination::Pagination;
+use realworld_domain::user::email::Email;
+use realworld_domain::user::oauth::OAuthIdentity;
+use realworld_domain::user::password::PasswordHash;
+use realworld_domain::user::repo::*;
+use realworld_domain::user::role::Role;
+use realworld_domain::user::UserId;
+
+use entrait::*;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+pub struct SqliteUserRepo;
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    user_id: Uuid,
+    username: String,
+    email: String,
+    password_hash: String,
+    bio: String,
+    image: Option<String>,
+    role: Role,
+    email_verified: bool,
+}
+
+impl From<UserRow> for (User, Credentials) {
+    fn from(row: UserRow) -> Self {
+        (
+            User {
+                user_id: UserId(row.user_id),
+                username: row.username,
+                bio: row.bio,
+                image: row.image,
+                role: row.role,
+            },
+            Credentials {
+                email: Email::valid(row.email),
+                password_hash: row.password_hash.into(),
+                email_verified: row.email_verified,
+            },
+        )
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ListedUserRow {
+    #[sqlx(flatten)]
+    user: UserRow,
+    banned: bool,
+}
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::user::repo::UserRepoImpl for SqliteUserRepo {
+    pub async fn insert_user(
+        deps: &impl GetDb,
+        username: &str,
+        email: &Email,
+        password_hash: PasswordHash,
+    ) -> RwResult<(User, Credentials)> {
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            INSERT INTO user (user_id, username, email, password_hash) VALUES (?1, ?2, ?3, ?4)
+            RETURNING user_id, username, email, password_hash, bio, image, role, email_verified
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(username)
+        .bind(email.as_ref())
+        .bind(password_hash.0)
+        .fetch_one(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()
+        .on_unique_violation("user.username", || RwError::UsernameTaken)
+        .on_unique_violation("user.email", || RwError::EmailTaken)?;
+
+        Ok(row.into())
+    }
+
+    pub async fn insert_users(deps: &impl GetDb, users: &[ImportedUser]) -> RwResult<Vec<String>> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+        let mut inserted = Vec::with_capacity(users.len());
+
+        for user in users {
+            let username: Option<String> = sqlx::query_scalar(
+                r#"
+                INSERT INTO user (user_id, username, email, password_hash) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT DO NOTHING
+                RETURNING username
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(&user.username)
+            .bind(user.email.as_ref())
+            .bind(user.password_hash.as_ref())
+            .fetch_optional(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+            inserted.extend(username);
+        }
+
+        tx.commit().await.to_rw_err()?;
+        Ok(inserted)
+    }
+
+    pub async fn find_user_credentials_by_id(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+    ) -> RwResult<Option<(User, Credentials)>> {
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            SELECT user_id, username, email, password_hash, bio, image, role, email_verified
+            FROM user WHERE user_id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn find_user_credentials_by_email(
+        deps: &impl GetDb,
+        email: &Email,
+    ) -> RwResult<Option<(User, Credentials)>> {
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            SELECT user_id, username, email, password_hash, bio, image, role, email_verified
+            FROM user WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref())
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn find_or_create_by_oauth_identity(
+        deps: &impl GetDb,
+        identity: &OAuthIdentity,
+    ) -> RwResult<(User, Credentials, Created)> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let linked_user_id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT user_id FROM oauth_identity WHERE provider = ?1 AND subject = ?2",
+        )
+        .bind(&identity.provider)
+        .bind(&identity.subject)
+        .fetch_optional(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        let (user_id, created) = match linked_user_id {
+            Some(user_id) => (user_id, false),
+            None => {
+                let user_id_with_email: Option<Uuid> =
+                    sqlx::query_scalar("SELECT user_id FROM user WHERE email = ?1")
+                        .bind(identity.email.as_ref())
+                        .fetch_optional(&mut *tx)
+                        .await
+                        .to_rw_err()?;
+
+                let (user_id, created) = match user_id_with_email {
+                    Some(user_id) if identity.email_verified => (user_id, false),
+                    // Linking to an address the user might not own would let them take over the account
+                    Some(_) => return Err(RwError::EmailTaken),
+                    None => {
+                        let username_taken: bool = sqlx::query_scalar(
+                            "SELECT EXISTS (SELECT 1 FROM user WHERE username = ?1 COLLATE unicode_nocase)",
+                        )
+                        .bind(&identity.username)
+                        .fetch_one(&mut *tx)
+                        .await
+                        .to_rw_err()?;
+                        let username = if username_taken {
+                            format!(
+                                "{}-{}",
+                                identity.username,
+                                &Uuid::new_v4().simple().to_string()[..6]
+                            )
+                        } else {
+                            identity.username.clone()
+                        };
+
+                        let user_id = Uuid::new_v4();
+                        sqlx::query(
+                            // An empty password hash matches no password
+                            r#"
+                            INSERT INTO user (user_id, username, email, password_hash, email_verified)
+                            VALUES (?1, ?2, ?3, '', ?4)
+                            "#,
+                        )
+                        .bind(user_id)
+                        .bind(username)
+                        .bind(identity.email.as_ref())
+                        .bind(identity.email_verified)
+                        .execute(&mut *tx)
+                        .await
+                        .to_rw_err()
+                        .on_unique_violation("user.username", || RwError::UsernameTaken)
+                        .on_unique_violation("user.email", || RwError::EmailTaken)?;
+
+                        (user_id, true)
+                    }
+                };
+
+                sqlx::query(
+                    "INSERT INTO oauth_identity (provider, subject, user_id) VALUES (?1, ?2, ?3)",
+                )
+                .bind(&identity.provider)
+                .bind(&identity.subject)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .to_rw_err()?;
+
+                (user_id, created)
+            }
+        };
+
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            SELECT user_id, username, email, password_hash, bio, image, role, email_verified
+            FROM user WHERE user_id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()?;
+
+        let (user, credentials) = row.into();
+        Ok((user, credentials, Created(created)))
+    }
+
+    pub async fn find_user_by_username(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        PastUsernames(past_usernames): PastUsernames,
+    ) -> RwResult<Option<(User, Following, FollowStats)>> {
+        let row = sqlx::query_as::<
+            _,
+            (
+                Uuid,
+                String,
+                String,
+                Option<String>,
+                Role,
+                bool,
+                i64,
+                i64,
+                bool,
+            ),
+        >(
+            r#"
+            SELECT
+                user_id,
+                username,
+                bio,
+                image,
+                role,
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = user.user_id AND following_user_id = ?2
+                ),
+                (SELECT count(*) FROM follow WHERE followed_user_id = user.user_id),
+                (SELECT count(*) FROM follow WHERE following_user_id = user.user_id),
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = ?2 AND following_user_id = user.user_id
+                )
+            FROM user
+            WHERE user_id = COALESCE(
+                (SELECT user_id FROM user WHERE username = ?1),
+                (
+                    SELECT user_id FROM username_history
+                    WHERE ?3 AND username = ?1
+                    ORDER BY changed_at DESC
+                    LIMIT 1
+                )
+            )
+            "#,
+        )
+        .bind(username)
+        .bind(current_user.0)
+        .bind(past_usernames)
+        .fetch_optional(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(row.map(
+            |(
+                user_id,
+                username,
+                bio,
+                image,
+                role,
+                following,
+                followers_count,
+                following_count,
+                follows_you,
+            )| {
+                (
+                    User {
+                        user_id: UserId(user_id),
+                        username,
+                        bio,
+                        image,
+                        role,
+                    },
+                    Following(following),
+                    FollowStats {
+                        followers_count,
+                        following_count,
+                        follows_you,
+                    },
+                )
+            },
+        ))
+    }
+
+    pub async fn update_user(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        update: UserUpdate<'_>,
+    ) -> RwResult<(User, Credentials)> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        // Renaming only by case is no new name
+        sqlx::query(
+            r#"
+            INSERT INTO username_history (username, user_id)
+                SELECT username, user_id
+                FROM user
+                WHERE user_id = ?1 AND username <> ?2
+            "#,
+        )
+        .bind(current_user_id.0)
+        .bind(update.username)
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        let row = sqlx::query_as::<_, UserRow>(
+            r#"
+            UPDATE user SET
+                -- a new email address has to be verified again
+                email_verified = email_verified AND (?1 IS NULL OR ?1 = email),
+                email = COALESCE(?1, email),
+                username = COALESCE(?2, username),
+                password_hash = COALESCE(?3, password_hash),
+                bio = COALESCE(?4, bio),
+                image = COALESCE(?5, image)
+            WHERE user_id = ?6
+            RETURNING user_id, username, email, password_hash, bio, image, role, email_verified
+            "#,
+        )
+        .bind(update.email)
+        .bind(update.username)
+        .bind(update.password_hash.map(|hash| hash.0))
+        .bind(update.bio)
+        .bind(update.image)
+        .bind(current_user_id.0)
+        .fetch_one(&mut *tx)
+        .await
+        .to_rw_err()
+        .on_unique_violation("user.username", || RwError::UsernameTaken)
+        .on_unique_violation("user.email", || RwError::EmailTaken)?;
+
+        tx.commit().await.to_rw_err()?;
+
+        Ok(row.into())
+    }
+
+    pub async fn set_user_role(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        role: Role,
+    ) -> RwResult<()> {
+        sqlx::query("UPDATE user SET role = ?2 WHERE user_id = ?1")
+            .bind(user_id)
+            .bind(role)
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(())
+    }
+
+    pub async fn delete_user(
+        deps: &impl GetDb,
+        UserId(user_id): UserId,
+        mode: DeletionMode,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let result = match mode {
+            // Everything else references the user with `ON DELETE CASCADE`
+            DeletionMode::Delete => {
+                sqlx::query("DELETE FROM user WHERE user_id = ?1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+            }
+            DeletionMode::Anonymize => {
+                for statement in [
+                    "DELETE FROM article_favorite WHERE user_id = ?1",
+                    "DELETE FROM comment_like WHERE user_id = ?1",
+                    "DELETE FROM bookmark WHERE user_id = ?1",
+                    "DELETE FROM block WHERE blocking_user_id = ?1 OR blocked_user_id = ?1",
+                    "DELETE FROM follow WHERE followed_user_id = ?1 OR following_user_id = ?1",
+                    "DELETE FROM notification WHERE user_id = ?1 OR actor_user_id = ?1",
+                    // Refresh tokens go with their sessions
+                    "DELETE FROM session WHERE user_id = ?1",
+                    "DELETE FROM email_verification WHERE user_id = ?1",
+                    "DELETE FROM password_reset WHERE user_id = ?1",
+                    "DELETE FROM oauth_identity WHERE user_id = ?1",
+                    // Archives have the email address and everything else of the user in them
+                    "DELETE FROM data_export WHERE user_id = ?1",
+                    // Old profile links shouldn't lead to the anonymized user
+                    "DELETE FROM username_history WHERE user_id = ?1",
+                ] {
+                    sqlx::query(statement)
+                        .bind(user_id)
+                        .execute(&mut *tx)
+                        .await
+                        .to_rw_err()?;
+                }
+
+                sqlx::query(
+                    r#"
+                    UPDATE user
+                    SET
+                        username = ?2,
+                        email = ?3,
+                        bio = '',
+                        image = NULL,
+                        -- Not a valid hash, so no password matches it
+                        password_hash = '',
+                        role = 'user',
+                        email_verified = false
+                    WHERE user_id = ?1
+                    "#,
+                )
+                .bind(user_id)
+                .bind(format!("deleted-{}", user_id.simple()))
+                .bind(format!("{}@deleted.invalid", user_id.simple()))
+                .execute(&mut *tx)
+                .await
+            }
+        }
+        .to_rw_err()?;
+
+        if result.rows_affected() == 0 {
+            return Err(RwError::CurrentUserDoesNotExist);
+        }
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn insert_follow(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let followed_user_id = find_user_id(&mut tx, username).await?;
+        if followed_user_id == current_user_id.0 {
+            return Err(RwError::Forbidden);
+        }
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO follow (following_user_id, followed_user_id) VALUES (?1, ?2)",
+        )
+        .bind(current_user_id.0)
+        .bind(followed_user_id)
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_follow(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let followed_user_id = find_user_id(&mut tx, username).await?;
+
+        // Note: There is no error code for unfollowing where there was no following in the first place
+        sqlx::query("DELETE FROM follow WHERE following_user_id = ?1 AND followed_user_id = ?2")
+            .bind(current_user_id.0)
+            .bind(followed_user_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn insert_block(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let blocked_user_id = find_user_id(&mut tx, username).await?;
+        if blocked_user_id == current_user_id.0 {
+            return Err(RwError::Forbidden);
+        }
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO block (blocking_user_id, blocked_user_id) VALUES (?1, ?2)",
+        )
+        .bind(current_user_id.0)
+        .bind(blocked_user_id)
+        .execute(&mut *tx)
+        .await
+        .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn delete_block(
+        deps: &impl GetDb,
+        current_user_id: UserId,
+        username: &str,
+    ) -> RwResult<()> {
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+
+        let blocked_user_id = find_user_id(&mut tx, username).await?;
+
+        sqlx::query("DELETE FROM block WHERE blocking_user_id = ?1 AND blocked_user_id = ?2")
+            .bind(current_user_id.0)
+            .bind(blocked_user_id)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+
+        tx.commit().await.to_rw_err()
+    }
+
+    pub async fn list_followers(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        select_follows(
+            deps,
+            FollowSide::Followers,
+            current_user,
+            username,
+            pagination,
+        )
+        .await
+    }
+
+    pub async fn list_following(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        select_follows(
+            deps,
+            FollowSide::Following,
+            current_user,
+            username,
+            pagination,
+        )
+        .await
+    }
+
+    pub async fn search_users(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        query: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>> {
+        // `lower` only folds ASCII, like `NOCASE`
+        let rows = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Role, bool)>(
+            r#"
+            SELECT
+                user_id,
+                username,
+                bio,
+                image,
+                role,
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = user.user_id AND following_user_id = ?1
+                )
+            FROM user
+            WHERE banned_at IS NULL AND (
+                instr(lower(username), lower(?2)) > 0 OR instr(lower(bio), lower(?2)) > 0
+            )
+            ORDER BY instr(lower(username), lower(?2)) <> 1, username
+            LIMIT ?3
+            OFFSET ?4
+            "#,
+        )
+        .bind(current_user.0)
+        .bind(query)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user_id, username, bio, image, role, following)| {
+                (
+                    User {
+                        user_id: UserId(user_id),
+                        username,
+                        bio,
+                        image,
+                        role,
+                    },
+                    Following(following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn select_users_by_ids(
+        deps: &impl GetDb,
+        current_user: UserId<Option<Uuid>>,
+        user_ids: &[Uuid],
+    ) -> RwResult<Vec<(User, Following)>> {
+        // Ids are stored as blobs, and passed in as a JSON array of their hex encoding
+        let hex_ids: Vec<String> = user_ids
+            .iter()
+            .map(|user_id| user_id.simple().to_string().to_uppercase())
+            .collect();
+
+        let rows = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Role, bool)>(
+            r#"
+            SELECT
+                user_id,
+                username,
+                bio,
+                image,
+                role,
+                EXISTS(
+                    SELECT 1 FROM follow
+                    WHERE followed_user_id = user.user_id AND following_user_id = ?2
+                )
+            FROM user
+            WHERE hex(user_id) IN (SELECT value FROM json_each(?1))
+            "#,
+        )
+        .bind(Json(hex_ids))
+        .bind(current_user.0)
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user_id, username, bio, image, role, following)| {
+                (
+                    User {
+                        user_id: UserId(user_id),
+                        username,
+                        bio,
+                        image,
+                        role,
+                    },
+                    Following(following),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn list_users(
+        deps: &impl GetDb,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Credentials, Banned)>> {
+        let rows = sqlx::query_as::<_, ListedUserRow>(
+            r#"
+            SELECT
+                user_id, username, email, password_hash, bio, image, role, email_verified,
+                banned_at IS NOT NULL banned
+            FROM user
+            ORDER BY created_at, username
+            LIMIT ?1
+            OFFSET ?2
+            "#,
+        )
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&deps.get_db().sqlite_pool)
+        .await
+        .to_rw_err()?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let (user, credentials) = row.user.into();
+                (user, credentials, Banned(row.banned))
+            })
+            .collect())
+    }
+
+    pub fn stream_usernames(deps: &impl GetDb) -> BoxStream<'static, RwResult<String>> {
+        let sqlite_pool = deps.get_db().sqlite_pool.clone();
+
+        async_stream::try_stream! {
+            let mut usernames = sqlx::query_scalar::<_, String>(
+                "SELECT username FROM user WHERE banned_at IS NULL ORDER BY created_at, username",
+            )
+            .fetch(&sqlite_pool);
+
+            while let Some(username) = usernames.try_next().await.to_rw_err()? {
+                yield username;
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Which side of the `follow` table to list, seen from the user whose list it is
+enum FollowSide {
+    Followers,
+    Following,
+}
+
+async fn select_follows(
+    deps: &impl GetDb,
+    side: FollowSide,
+    current_user: UserId<Option<Uuid>>,
+    username: &str,
+    pagination: Pagination,
+) -> RwResult<Vec<(User, Following)>> {
+    let (own_column, listed_column) = match side {
+        FollowSide::Followers => ("followed_user_id", "following_user_id"),
+        FollowSide::Following => ("following_user_id", "followed_user_id"),
+    };
+
+    let rows = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Role, bool)>(&format!(
+        r#"
+        SELECT
+            listed.user_id,
+            listed.username,
+            listed.bio,
+            listed.image,
+            listed.role,
+            EXISTS(
+                SELECT 1 FROM follow
+                WHERE followed_user_id = listed.user_id AND following_user_id = ?2
+            )
+        FROM user
+        INNER JOIN follow ON follow.{own_column} = user.user_id
+        INNER JOIN user listed ON listed.user_id = follow.{listed_column}
+        WHERE user.username = ?1
+        -- rowid breaks ties between follows made in the same millisecond
+        ORDER BY follow.created_at DESC, follow.rowid DESC
+        LIMIT ?3
+        OFFSET ?4
+        "#
+    ))
+    .bind(username)
+    .bind(current_user.0)
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&deps.get_db().sqlite_pool)
+    .await
+    .to_rw_err()?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(user_id, username, bio, image, role, following)| {
+            (
+                User {
+                    user_id: UserId(user_id),
+                    username,
+                    bio,
+                    image,
+                    role,
+                },
+                Following(following),
+            )
+        })
+        .collect())
+}
+
+async fn find_user_id(conn: &mut sqlx::SqliteConnection, username: &str) -> RwResult<Uuid> {
+    sqlx::query_scalar("SELECT user_id FROM user WHERE username = ?1")
+        .bind(username)
+        .fetch_optional(conn)
+        .await
+        .to_rw_err()?
+        .ok_or(RwError::ProfileNotFound)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::create_test_db;
+
+    use realworld_domain::article::Slug;
+
+    use assert_matches::*;
+
+    pub struct TestNewUser {
+        username: &'static str,
+        email: &'static str,
+        password_hash: &'static str,
+    }
+
+    impl Default for TestNewUser {
+        fn default() -> Self {
+            Self {
+                username: "username",
+                email: "email",
+                password_hash: "hash",
+            }
+        }
+    }
+
+    pub fn other_user() -> TestNewUser {
+        TestNewUser {
+            username: "username2",
+            email: "email2",
+            password_hash: "hash2",
+        }
+    }
+
+    /// A user with the username as email address too
+    pub fn named_user(username: &'static str) -> TestNewUser {
+        TestNewUser {
+            username,
+            email: username,
+            password_hash: "hash",
+        }
+    }
+
+    #[entrait(pub InsertTestUser, unimock = false)]
+    pub async fn insert_test_user(
+        db: &impl realworld_domain::user::repo::UserRepo,
+        user: TestNewUser,
+    ) -> RwResult<(User, Credentials)> {
+        db.insert_user(
+            user.username,
+            &user.email.parse().unwrap(),
+            user.password_hash.into(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn should_insert_then_fetch_user() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (created_user, credentials) = db.insert_test_user(TestNewUser::default()).await?;
+
+        assert_eq!("username", created_user.username);
+        assert_eq!(Role::User, created_user.role);
+        assert_eq!("email", credentials.email.as_ref());
+        assert!(!credentials.email_verified);
+
+        let (fetched_user, fetched_credentials) = db
+            .find_user_credentials_by_id(created_user.user_id)
+            .await?
+            .unwrap();
+        assert_eq!(created_user, fetched_user);
+        assert_eq!(credentials, fetched_credentials);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inserting_users_should_skip_taken_ones() -> RwResult<()> {
+        let db = create_test_db().await;
+        db.insert_test_user(TestNewUser::default()).await?;
+
+        let imported = |username: &str, email: &str| ImportedUser {
+            username: username.to_string(),
+            email: email.parse().unwrap(),
+            password_hash: "hash".into(),
+        };
+        let inserted = db
+            .insert_users(&[
+                imported("username", "new_email"),
+                imported("new_username", "email"),
+                imported("imported", "imported_email"),
+                imported("IMPORTED", "other_imported_email"),
+            ])
+            .await?;
+
+        assert_eq!(vec!["imported".to_string()], inserted);
+        let (user, credentials) = db
+            .find_user_credentials_by_email(&"imported_email".parse().unwrap())
+            .await?
+            .unwrap();
+        assert_eq!("imported", user.username);
+        assert_eq!("hash", credentials.password_hash.as_ref());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_set_user_role() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+
+        db.set_user_role(user.user_id, Role::Admin).await?;
+
+        let (fetched_user, _) = db.find_user_credentials_by_id(user.user_id).await?.unwrap();
+        assert_eq!(Role::Admin, fetched_user.role);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn usernames_and_emails_should_be_unique_regardless_of_case() -> RwResult<()> {
+        let db = create_test_db().await;
+        db.insert_test_user(TestNewUser::default()).await?;
+
+        assert_matches!(
+            db.insert_test_user(TestNewUser {
+                username: "USERNAME",
+                ..other_user()
+            })
+            .await,
+            Err(RwError::UsernameTaken)
+        );
+        assert_matches!(
+            db.insert_test_user(TestNewUser {
+                email: "EMAIL",
+                ..other_user()
+            })
+            .await,
+            Err(RwError::EmailTaken)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn usernames_should_be_unique_regardless_of_case_beyond_ascii() -> RwResult<()> {
+        let db = create_test_db().await;
+        db.insert_test_user(TestNewUser {
+            username: "ölaf",
+            ..Default::default()
+        })
+        .await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        assert_matches!(
+            db.insert_test_user(TestNewUser {
+                username: "ÖLAF",
+                ..other_user()
+            })
+            .await,
+            Err(RwError::UsernameTaken)
+        );
+        assert_matches!(
+            db.update_user(
+                other.user_id,
+                UserUpdate {
+                    username: Some("Ölaf"),
+                    ..Default::default()
+                }
+            )
+            .await,
+            Err(RwError::UsernameTaken)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_update_user() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (created_user, _) = db.insert_test_user(TestNewUser::default()).await?;
+
+        let (updated_user, updated_credentials) = db
+            .update_user(
+                created_user.user_id,
+                UserUpdate {
+                    email: Some("newmail"),
+                    username: Some("newname"),
+                    password_hash: Some("newhash".into()),
+                    bio: Some("newbio"),
+                    image: Some("newimage"),
+                },
+            )
+            .await?;
+
+        assert_eq!(created_user.user_id, updated_user.user_id);
+        assert_eq!("newname", updated_user.username);
+        assert_eq!("newbio", updated_user.bio);
+        assert_eq!(Some("newimage"), updated_user.image.as_deref());
+
+        assert_eq!("newmail", updated_credentials.email.as_ref());
+        assert_eq!("newhash", updated_credentials.password_hash.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn past_usernames_should_only_resolve_when_asked_for() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let rename = |username| UserUpdate {
+            username: Some(username),
+            ..UserUpdate::default()
+        };
+        db.update_user(user.user_id, rename("Username")).await?;
+        db.update_user(user.user_id, rename("renamed")).await?;
+
+        assert!(db
+            .find_user_by_username(UserId(None), "username", PastUsernames(false))
+            .await?
+            .is_none());
+        let (found, _, _) = db
+            .find_user_by_username(UserId(None), "username", PastUsernames(true))
+            .await?
+            .unwrap();
+        assert_eq!(user.user_id, found.user_id);
+        assert_eq!("renamed", found.username);
+
+        // The current holder of a name wins over its past holders
+        let (other, _) = db
+            .insert_test_user(TestNewUser {
+                username: "username",
+                ..other_user()
+            })
+            .await?;
+        let (found, _, _) = db
+            .find_user_by_username(UserId(None), "username", PastUsernames(true))
+            .await?
+            .unwrap();
+        assert_eq!(other.user_id, found.user_id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_follow_and_unfollow() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (other, _) = db.insert_test_user(other_user()).await?;
+
+        db.insert_follow(user.user_id, &other.username).await?;
+        // following twice is not an error
+        db.insert_follow(user.user_id, &other.username).await?;
+
+        let (_, following, stats) = db
+            .find_user_by_username(user.user_id.some(), &other.username, PastUsernames(false))
+            .await?
+            .unwrap();
+        assert_eq!(Following(true), following);
+        assert_eq!(
+            FollowStats {
+                followers_count: 1,
+                following_count: 0,
+                follows_you: false
+            },
+            stats
+        );
+
+        let (_, _, stats) = db
+            .find_user_by_username(other.user_id.some(), &user.username, PastUsernames(false))
+            .await?
+            .unwrap();
+        assert_eq!(
+            FollowStats {
+                followers_count: 0,
+                following_count: 1,
+                follows_you: true
+            },
+            stats
+        );
+
+        db.delete_follow(user.user_id, &other.username).await?;
+
+        let (_, following, _) = db
+            .find_user_by_username(user.user_id.some(), &other.username, PastUsernames(false))
+            .await?
+            .unwrap();
+        assert_eq!(Following(false), following);
+
+        assert_matches!(
+            db.insert_follow(user.user_id, &user.username).await,
+            Err(RwError::Forbidden)
+        );
+        assert_matches!(
+            db.insert_follow(user.user_id, "nobody").await,
+            Err(RwError::ProfileNotFound)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_list_followers_and_following() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (user2, _) = db.insert_test_user(other_user()).await?;
+        let (user3, _) = db
+            .insert_test_user(TestNewUser {
+                username: "username3",
+                email: "email3",
+                password_hash: "hash3",
+            })
+            .await?;
+
+        db.insert_follow(user2.user_id, &user.username).await?;
+        db.insert_follow(user3.user_id, &user.username).await?;
+        db.insert_follow(user.user_id, &user3.username).await?;
+
+        fn usernames(users: Vec<(User, Following)>) -> Vec<(String, bool)> {
+            users
+                .into_iter()
+                .map(|(user, Following(following))| (user.username, following))
+                .collect()
+        }
+
+        assert_eq!(
+            vec![
+                ("username3".to_string(), true),
+                ("username2".to_string(), false)
+            ],
+            usernames(
+                db.list_followers(user.user_id.some(), "username", Pagination::default())
+                    .await?
+            )
+        );
+        assert_eq!(
+            vec![("username2".to_string(), false)],
+            usernames(
+                db.list_followers(
+                    UserId(None),
+                    "username",
+                    Pagination {
+                        limit: Some(1),
+                        offset: Some(1),
+                    }
+                )
+                .await?
+            )
+        );
+        assert_eq!(
+            vec![("username".to_string(), false)],
+            usernames(
+                db.list_following(UserId(None), "username3", Pagination::default())
+                    .await?
+            )
+        );
+
+        let mut selected = usernames(
+            db.select_users_by_ids(user2.user_id.some(), &[user.user_id.0, user3.user_id.0])
+                .await?,
+        );
+        selected.sort();
+        assert_eq!(
+            vec![
+                ("username".to_string(), true),
+                ("username3".to_string(), false)
+            ],
+            selected
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_search_users_by_username_and_bio() -> RwResult<()> {
+        use realworld_domain::user::repo::BanRepo;
+
+        let db = create_test_db().await;
+        let mut users = vec![];
+        for username in ["jake", "bigjake", "bob", "jakebanned", "alice"] {
+            let (user, _) = db.insert_test_user(named_user(username)).await?;
+            users.push(user);
+        }
+        let (jake, bob, banned) = (&users[0], &users[2], &users[3]);
+        db.update_user(
+            bob.user_id,
+            UserUpdate {
+                bio: Some("Knows Jake"),
+                ..Default::default()
+            },
+        )
+        .await?;
+        db.insert_follow(bob.user_id, &jake.username).await?;
+        db.set_user_banned(banned.user_id, Banned(true)).await?;
+
+        fn usernames(users: Vec<(User, Following)>) -> Vec<(String, bool)> {
+            users
+                .into_iter()
+                .map(|(user, Following(following))| (user.username, following))
+                .collect()
+        }
+
+        assert_eq!(
+            vec![
+                ("jake".to_string(), true),
+                ("bigjake".to_string(), false),
+                ("bob".to_string(), false)
+            ],
+            usernames(
+                db.search_users(bob.user_id.some(), "JAK", Pagination::default())
+                    .await?
+            )
+        );
+        assert_eq!(
+            vec![("bigjake".to_string(), false)],
+            usernames(
+                db.search_users(
+                    UserId(None),
+                    "jak",
+                    Pagination {
+                        limit: Some(1),
+                        offset: Some(1)
+                    }
+                )
+                .await?
+            )
+        );
+        assert!(db
+            .search_users(UserId(None), "%", Pagination::default())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_delete_or_anonymize_user() -> RwResult<()> {
+        use realworld_domain::article::repo::{ArticleRepo, Filter};
+        use realworld_domain::data_export::DataExportRepo;
+
+        let db = create_test_db().await;
+        let (deleted, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (anonymized, _) = db.insert_test_user(other_user()).await?;
+
+        for (user, slug) in [(&deleted, "deleted"), (&anonymized, "anonymized")] {
+            db.insert_article(
+                user.user_id,
+                &Slug::from(slug),
+                slug,
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        }
+        db.insert_favorite(anonymized.user_id, &Slug::from("deleted"))
+            .await?;
+        db.insert_follow(anonymized.user_id, &deleted.username)
+            .await?;
+        for user in [&deleted, &anonymized] {
+            db.insert_data_export(user.user_id).await?;
+        }
+
+        db.delete_user(deleted.user_id, DeletionMode::Delete)
+            .await?;
+        assert!(db
+            .find_user_credentials_by_id(deleted.user_id)
+            .await?
+            .is_none());
+        assert_matches!(
+            db.fetch_article_id(&Slug::from("deleted")).await,
+            Err(RwError::ArticleNotFound)
+        );
+        assert_eq!(None, db.find_latest_data_export(deleted.user_id).await?);
+
+        db.delete_user(anonymized.user_id, DeletionMode::Anonymize)
+            .await?;
+        let (user, credentials) = db
+            .find_user_credentials_by_id(anonymized.user_id)
+            .await?
+            .unwrap();
+        assert!(user.username.starts_with("deleted-"));
+        assert!(credentials.email.as_ref().ends_with("@deleted.invalid"));
+        assert_eq!(None, db.find_latest_data_export(anonymized.user_id).await?);
+        assert_eq!(
+            vec![user.username.clone()],
+            db.select_articles(
+                UserId(None),
+                Filter {
+                    slug: Some(&Slug::from("anonymized")),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .map(|article| article.author_username)
+            .collect::<Vec<_>>()
+        );
+
+        assert_matches!(
+            db.delete_user(deleted.user_id, DeletionMode::Delete).await,
+            Err(RwError::CurrentUserDoesNotExist)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_find_or_create_user_by_oauth_identity() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (existing, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let identity = |subject: &str, email: &str, email_verified| OAuthIdentity {
+            provider: "github".to_string(),
+            subject: subject.to_string(),
+            email: email.parse().unwrap(),
+            email_verified,
+            username: "username".to_string(),
+        };
+
+        let (created, credentials, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("1", "new", true))
+            .await?;
+        assert!(was_created);
+        assert!(created.username.starts_with("username-"));
+        assert!(credentials.email_verified);
+        assert_eq!("", credentials.password_hash.as_ref());
+
+        let (found, _, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("1", "changed", true))
+            .await?;
+        assert!(!was_created);
+        assert_eq!(created.user_id, found.user_id);
+
+        let (linked, _, Created(was_created)) = db
+            .find_or_create_by_oauth_identity(&identity("2", "email", true))
+            .await?;
+        assert!(!was_created);
+        assert_eq!(existing.user_id, linked.user_id);
+
+        assert_matches!(
+            db.find_or_create_by_oauth_identity(&identity("3", "email", false))
+                .await,
+            Err(RwError::EmailTaken)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocked_authors_should_be_hidden_from_listings() -> RwResult<()> {
+        use realworld_domain::article::repo::{ArticleRepo, Filter};
+        use realworld_domain::comment::repo::CommentRepo;
+
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(TestNewUser::default()).await?;
+        let (troll, _) = db.insert_test_user(other_user()).await?;
+
+        db.insert_article(
+            user.user_id,
+            &Slug::from("mine"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_article(
+            troll.user_id,
+            &Slug::from("theirs"),
+            "title",
+            "desc",
+            "body",
+            &[],
+            None,
+        )
+        .await?;
+        db.insert_comment(troll.user_id, &Slug::from("mine"), "first!", &[])
+            .await?;
+
+        db.insert_block(user.user_id, &troll.username).await?;
+        db.insert_block(user.user_id, &troll.username).await?;
+
+        let slugs = |articles: Vec<realworld_domain::article::repo::Article>| -> Vec<String> {
+            articles.into_iter().map(|article| article.slug.0).collect()
+        };
+        let article_id = db.fetch_article_id(&Slug::from("mine")).await?;
+
+        assert_eq!(
+            vec!["mine"],
+            slugs(
+                db.select_articles(user.user_id.some(), Filter::default())
+                    .await?
+            )
+        );
+        assert!(db
+            .list_comments(user.user_id.some(), article_id, Default::default())
+            .await?
+            .is_empty());
+        // The article itself can still be found
+        assert_eq!(
+            vec!["theirs"],
+            slugs(
+                db.select_articles(
+                    user.user_id.some(),
+                    Filter {
+                        slug: Some(&Slug::from("theirs")),
+                        ..Default::default()
+                    }
+                )
+                .await?
+            )
+        );
+        // Others still see everything
+        assert_eq!(
+            2,
+            db.select_articles(UserId(None), Filter::default())
+                .await?
+                .len()
+        );
+
+        assert_matches!(
+            db.insert_block(user.user_id, &user.username).await,
+            Err(RwError::Forbidden)
+        );
+
+        db.delete_block(user.user_id, &troll.username).await?;
+        assert_eq!(
+            1,
+            db.list_comments(user.user_id.some(), article_id, Default::default())
+                .await?
+                .len()
+        );
+
+        Ok(())
+    }
+}
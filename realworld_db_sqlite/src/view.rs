@@ -0,0 +1,136 @@
+use crate::{DbResultExt, GetDb};
+
+use realworld_domain::article::repo::ArticleView;
+use realworld_domain::error::RwResult;
+
+use entrait::*;
+
+pub struct SqliteViewRepo;
+
+#[cfg_attr(not(feature = "dyn-repos"), entrait)]
+#[cfg_attr(feature = "dyn-repos", entrait(ref), async_trait::async_trait)]
+impl realworld_domain::article::repo::ViewRepoImpl for SqliteViewRepo {
+    pub async fn insert_views(deps: &impl GetDb, views: &[ArticleView]) -> RwResult<u64> {
+        // The whole batch in one transaction
+        let mut tx = deps.get_db().sqlite_pool.begin().await.to_rw_err()?;
+        let mut count = 0;
+
+        for view in views {
+            // if the viewer has already viewed the article that day, it's ignored
+            let inserted = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO article_view (article_id, viewer, day)
+                SELECT article_id, ?2, ?3 FROM article WHERE slug = ?1
+                "#,
+            )
+            .bind(&view.slug)
+            .bind(view.viewer.to_string())
+            .bind(view.day)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?
+            .rows_affected();
+            if inserted == 0 {
+                continue;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO article_view_count (article_id, views_count)
+                SELECT article_id, 1 FROM article WHERE slug = ?1
+                ON CONFLICT (article_id) DO UPDATE SET views_count = views_count + 1
+                "#,
+            )
+            .bind(&view.slug)
+            .execute(&mut *tx)
+            .await
+            .to_rw_err()?;
+            count += inserted;
+        }
+
+        tx.commit().await.to_rw_err()?;
+        Ok(count)
+    }
+
+    pub async fn delete_views_before(deps: &impl GetDb, day: time::Date) -> RwResult<u64> {
+        let result = sqlx::query("DELETE FROM article_view WHERE day < ?1")
+            .bind(day)
+            .execute(&deps.get_db().sqlite_pool)
+            .await
+            .to_rw_err()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_db;
+    use crate::user::tests::InsertTestUser;
+
+    use realworld_domain::article::repo::{ArticleRepo, ArticleView, Filter, ViewRepo, Viewer};
+    use realworld_domain::article::Slug;
+    use realworld_domain::error::RwResult;
+    use realworld_domain::user::UserId;
+
+    use std::net::IpAddr;
+    use time::Duration;
+
+    #[tokio::test]
+    async fn views_should_count_once_per_viewer_and_day() -> RwResult<()> {
+        let db = create_test_db().await;
+        let (user, _) = db.insert_test_user(Default::default()).await?;
+        let article = db
+            .insert_article(
+                user.user_id,
+                &Slug::from("slug"),
+                "title",
+                "desc",
+                "body",
+                &[],
+                None,
+            )
+            .await?;
+        let today = time::OffsetDateTime::now_utc().date();
+        let view = |viewer, day| ArticleView {
+            slug: Slug::from("slug"),
+            viewer,
+            day,
+        };
+        let anonymous = Viewer::Ip(IpAddr::from([10, 0, 0, 1]));
+
+        assert_eq!(
+            2,
+            db.insert_views(&[
+                view(Viewer::User(user.user_id), today),
+                view(anonymous, today),
+                ArticleView {
+                    slug: Slug::from("deleted"),
+                    ..view(anonymous, today)
+                },
+            ])
+            .await?
+        );
+        assert_eq!(
+            1,
+            db.insert_views(&[
+                view(anonymous, today),
+                view(anonymous, today - Duration::days(1)),
+            ])
+            .await?
+        );
+
+        assert_eq!(1, db.delete_views_before(today).await?);
+        let articles = db
+            .select_articles(
+                UserId(None),
+                Filter {
+                    slug: Some(&article.slug),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(3, articles[0].views_count);
+        Ok(())
+    }
+}
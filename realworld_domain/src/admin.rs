@@ -0,0 +1,475 @@
+//!
+//! Moderation by admins: banning users, removing any article or comment, and looking into the audit log.
+//! Also tasks run from the command line, which aren't authorized by a token.
+//!
+
+use crate::article::feed_cache::FeedCache;
+use crate::article::repo::ArticleRepo;
+use crate::article::Slug;
+use crate::audit::{AuditAction, AuditEntry, AuditFilter, AuditLog, AuditLogQuery, NewAuditEntry};
+use crate::comment::repo::CommentRepo;
+use crate::comment::CommentId;
+use crate::error::{RwError, RwResult};
+use crate::event::{DomainEvents, Event};
+use crate::pagination::Pagination;
+use crate::user::auth::{AuthorizeRole, SignUserId, Token, DEFAULT_SESSION_LENGTH};
+use crate::user::email::Email;
+use crate::user::password::HashPassword;
+use crate::user::repo::{BanRepo, Banned, Credentials, PastUsernames, SessionRepo, User, UserRepo};
+use crate::user::role::Role;
+use crate::user::{NewUser, UserId};
+use crate::System;
+
+use entrait::entrait_export as entrait;
+
+/// A user as seen by admins
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUser {
+    pub username: String,
+    pub email: Email,
+    pub email_verified: bool,
+    pub bio: String,
+    pub image: Option<String>,
+    pub role: Role,
+    pub banned: bool,
+}
+
+impl From<(User, Credentials, Banned)> for AdminUser {
+    fn from((user, credentials, Banned(banned)): (User, Credentials, Banned)) -> Self {
+        Self {
+            username: user.username,
+            email: credentials.email,
+            email_verified: credentials.email_verified,
+            bio: user.bio,
+            image: user.image,
+            role: user.role,
+            banned,
+        }
+    }
+}
+
+///
+/// Settings of the app that can be changed while it's serving, like maintenance mode.
+///
+#[entrait(mock_api=ReloadSettingsMock)]
+pub trait ReloadSettings {
+    /// Read the settings again from where they came from
+    fn reload_settings(&self) -> RwResult<()>;
+}
+
+///
+/// Everything here requires the [Role::Admin] role.
+///
+#[entrait(pub Api, mock_api=mock)]
+pub mod api {
+    use super::*;
+
+    pub async fn list_users(
+        deps: &(impl AuthorizeRole + UserRepo),
+        token: Token,
+        pagination: Pagination,
+    ) -> RwResult<Vec<AdminUser>> {
+        deps.authorize_role(token, Role::Admin).await?;
+
+        Ok(deps
+            .list_users(pagination)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Admins can't be banned, so that there's always someone left to unban
+    pub async fn set_user_banned(
+        deps: &(impl AuthorizeRole + UserRepo + BanRepo + AuditLog),
+        token: Token,
+        username: &str,
+        banned: bool,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authorize_role(token, Role::Admin).await?;
+        let (user, _, _) = deps
+            .find_user_by_username(current_user_id.some(), username, PastUsernames(false))
+            .await?
+            .ok_or(RwError::ProfileNotFound)?;
+
+        if user.role >= Role::Admin {
+            return Err(RwError::Forbidden);
+        }
+
+        deps.set_user_banned(user.user_id, Banned(banned)).await?;
+        deps.record_audit(NewAuditEntry {
+            user_id: current_user_id.some(),
+            action: if banned {
+                AuditAction::UserBan
+            } else {
+                AuditAction::UserUnban
+            },
+            target: Some(username),
+            ip_address: None,
+        })
+        .await
+    }
+
+    pub async fn delete_article(
+        deps: &(impl AuthorizeRole + ArticleRepo + FeedCache + DomainEvents + AuditLog),
+        token: Token,
+        slug: &Slug,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authorize_role(token, Role::Admin).await?;
+        deps.delete_any_article(slug).await?;
+        deps.record_audit(NewAuditEntry {
+            user_id: current_user_id.some(),
+            action: AuditAction::ArticleDelete,
+            target: Some(slug.as_str()),
+            ip_address: None,
+        })
+        .await?;
+        deps.invalidate_all_feeds().await;
+        deps.publish(Event::ArticleDeleted {
+            user_id: current_user_id.into_id(),
+            slug: slug.clone(),
+        });
+        Ok(())
+    }
+
+    pub async fn delete_comment(
+        deps: &(impl AuthorizeRole + CommentRepo + DomainEvents + AuditLog),
+        token: Token,
+        slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authorize_role(token, Role::Admin).await?;
+        deps.delete_any_comment(slug, comment_id).await?;
+        deps.record_audit(NewAuditEntry {
+            user_id: current_user_id.some(),
+            action: AuditAction::CommentDelete,
+            target: Some(&format!("{slug}/{}", comment_id.0)),
+            ip_address: None,
+        })
+        .await?;
+        deps.publish(Event::CommentDeleted {
+            user_id: current_user_id.into_id(),
+            article_slug: slug.clone(),
+            comment_id,
+        });
+        Ok(())
+    }
+
+    /// Take changed settings into use without a restart, see [ReloadSettings]
+    pub async fn reload_config(
+        deps: &(impl AuthorizeRole + ReloadSettings),
+        token: Token,
+    ) -> RwResult<()> {
+        deps.authorize_role(token, Role::Admin).await?;
+        deps.reload_settings()
+    }
+
+    /// Newest first. Entries of a user are found by the user's current username.
+    pub async fn list_audit_log(
+        deps: &(impl AuthorizeRole + UserRepo + AuditLog),
+        token: Token,
+        query: AuditLogQuery,
+    ) -> RwResult<Vec<AuditEntry>> {
+        deps.authorize_role(token, Role::Admin).await?;
+
+        let user_id = match &query.username {
+            Some(username) => Some(
+                deps.find_user_by_username(UserId(None), username, PastUsernames(false))
+                    .await?
+                    .ok_or(RwError::ProfileNotFound)?
+                    .0
+                    .user_id,
+            ),
+            None => None,
+        };
+
+        Ok(deps
+            .select_audit_entries(AuditFilter {
+                user_id,
+                since: query.since.map(|since| since.0),
+                until: query.until.map(|until| until.0),
+                pagination: Pagination {
+                    limit: query.limit,
+                    offset: query.offset,
+                },
+            })
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+}
+
+///
+/// Create a user with a role, e.g. the first admin, whom no other admin could have promoted.
+///
+#[entrait(pub CreateUserWithRole, mock_api=CreateUserWithRoleMock)]
+async fn create_user_with_role(
+    deps: &(impl HashPassword + UserRepo),
+    new_user: NewUser,
+    role: Role,
+) -> RwResult<User> {
+    let email = new_user.email.parse()?;
+    let password_hash = deps.hash_password(new_user.password).await?;
+
+    let (user, _) = deps
+        .insert_user(&new_user.username, &email, password_hash)
+        .await?;
+    deps.set_user_role(user.user_id, role).await?;
+
+    Ok(User { role, ..user })
+}
+
+/// The device of the sessions of minted tokens
+const MINTED_TOKEN_DEVICE: &str = "minted by an admin";
+
+///
+/// An access token for any user, e.g. for trying out the API as that user.
+///
+/// The token gets a session of its own, without a refresh token,
+/// so that it shows up among the sessions of the user and can be revoked.
+///
+#[entrait(pub MintToken, mock_api=MintTokenMock)]
+async fn mint_token(
+    deps: &(impl System + UserRepo + SessionRepo + SignUserId),
+    user_id: UserId,
+) -> RwResult<String> {
+    let (user, _) = deps
+        .find_user_credentials_by_id(user_id)
+        .await?
+        .ok_or(RwError::ProfileNotFound)?;
+
+    let expires_at = deps.get_current_time() + DEFAULT_SESSION_LENGTH;
+    let session_id = deps
+        .insert_session(user.user_id, Some(MINTED_TOKEN_DEVICE), expires_at)
+        .await?;
+
+    Ok(deps.sign_user_id(user.user_id, user.role, session_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLogMock;
+    use crate::user::auth::authorize_role::AuthorizeRoleMock;
+    use crate::user::auth::SignUserIdMock;
+    use crate::user::password::HashPasswordMock;
+    use crate::user::repo::{BanRepoMock, FollowStats, Following, SessionRepoMock, UserRepoMock};
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn mock_admin() -> impl unimock::Clause {
+        AuthorizeRoleMock::authorize_role
+            .next_call(matching!(_, Role::Admin))
+            .returns(Ok(UserId(uuid::Uuid::new_v4())))
+    }
+
+    fn test_user(role: Role) -> User {
+        User {
+            user_id: UserId(uuid::Uuid::from_u128(1)),
+            username: "spammer".to_string(),
+            bio: "".to_string(),
+            image: None,
+            role,
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_should_ban_user() {
+        let deps = Unimock::new((
+            mock_admin(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(_, "spammer", _))
+                .returns(Ok(Some((
+                    test_user(Role::User),
+                    Following(false),
+                    FollowStats::default(),
+                )))),
+            BanRepoMock::set_user_banned
+                .next_call(matching!((UserId(id), Banned(true)) if id.as_u128() == 1))
+                .returns(Ok(())),
+            AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::UserBan,
+                    target: Some("spammer"),
+                    ..
+                }))
+                .returns(Ok(())),
+        ));
+
+        api::set_user_banned(&deps, Token::from_token("token"), "spammer", true)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn admin_should_not_be_banned() {
+        let deps = Unimock::new((
+            mock_admin(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(_, "spammer", _))
+                .returns(Ok(Some((
+                    test_user(Role::Admin),
+                    Following(false),
+                    FollowStats::default(),
+                )))),
+        ));
+
+        assert_matches!(
+            api::set_user_banned(&deps, Token::from_token("token"), "spammer", true).await,
+            Err(RwError::Forbidden)
+        );
+    }
+
+    #[tokio::test]
+    async fn moderator_should_not_list_users() {
+        let deps = Unimock::new(
+            AuthorizeRoleMock::authorize_role
+                .next_call(matching!(_, Role::Admin))
+                .returns(Err(RwError::Forbidden)),
+        );
+
+        assert_matches!(
+            api::list_users(&deps, Token::from_token("token"), Default::default()).await,
+            Err(RwError::Forbidden)
+        );
+    }
+
+    #[tokio::test]
+    async fn admin_should_reload_settings() {
+        let deps = Unimock::new((
+            mock_admin(),
+            ReloadSettingsMock::reload_settings
+                .next_call(matching!())
+                .returns(Ok(())),
+        ));
+
+        assert!(api::reload_config(&deps, Token::from_token("token"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn audit_log_should_be_filtered_by_user() {
+        let deps = Unimock::new((
+            mock_admin(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(UserId(None), "spammer", _))
+                .returns(Ok(Some((
+                    test_user(Role::User),
+                    Following(false),
+                    FollowStats::default(),
+                )))),
+            AuditLogMock::select_audit_entries
+                .next_call(matching! {
+                    (AuditFilter { user_id: Some(UserId(id)), since: None, .. }) if id.as_u128() == 1
+                })
+                .returns(Ok(vec![])),
+        ));
+
+        api::list_audit_log(
+            &deps,
+            Token::from_token("token"),
+            AuditLogQuery {
+                username: Some("spammer".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn audit_log_of_unknown_user_should_not_be_found() {
+        let deps = Unimock::new((
+            mock_admin(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(_, "nobody", _))
+                .returns(Ok(None)),
+        ));
+
+        assert_matches!(
+            api::list_audit_log(
+                &deps,
+                Token::from_token("token"),
+                AuditLogQuery {
+                    username: Some("nobody".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::ProfileNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn created_user_should_have_role() {
+        let deps = Unimock::new((
+            HashPasswordMock
+                .next_call(matching!("password"))
+                .returns(Ok("h4sh".into())),
+            UserRepoMock::insert_user
+                .next_call(matching!("admin", "admin@example.com", "h4sh"))
+                .answers(&|_, _, email, password_hash| {
+                    Ok((
+                        test_user(Role::User),
+                        Credentials {
+                            email: email.clone(),
+                            password_hash,
+                            email_verified: false,
+                        },
+                    ))
+                }),
+            UserRepoMock::set_user_role
+                .next_call(matching!((UserId(id), Role::Admin) if id.as_u128() == 1))
+                .returns(Ok(())),
+        ));
+
+        let user = create_user_with_role(
+            &deps,
+            NewUser {
+                username: "admin".to_string(),
+                email: "admin@example.com".to_string(),
+                password: "password".into(),
+            },
+            Role::Admin,
+        )
+        .await
+        .unwrap();
+        assert_eq!(Role::Admin, user.role);
+    }
+
+    #[tokio::test]
+    async fn token_should_be_minted_with_role_of_user() {
+        let deps = Unimock::new((
+            UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    Ok(Some((
+                        test_user(Role::Moderator),
+                        Credentials {
+                            email: "email".parse().unwrap(),
+                            password_hash: "hash".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            crate::test::mock_current_time(),
+            SessionRepoMock::insert_session
+                .next_call(matching!(_, Some(MINTED_TOKEN_DEVICE), _))
+                .returns(Ok(uuid::Uuid::nil())),
+            SignUserIdMock
+                .next_call(matching!(_, Role::Moderator, _))
+                .returns("token".to_string()),
+        ));
+
+        assert_eq!(
+            "token",
+            mint_token(&deps, UserId(uuid::Uuid::from_u128(1)))
+                .await
+                .unwrap()
+        );
+    }
+}
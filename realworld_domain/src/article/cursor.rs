@@ -0,0 +1,101 @@
+use super::{repo, Article, Slug};
+use crate::timestamp::Timestamptz;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Write;
+
+///
+/// Position in a list of articles, which is ordered newest first.
+///
+/// Listing articles `after` a cursor continues right after the article the cursor was made from,
+/// so pages stay stable while new articles are being posted.
+/// Clients see the cursor as an opaque string.
+///
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ArticleCursor {
+    pub created_at: Timestamptz,
+    /// Breaks ties between articles created at the same time
+    pub slug: Slug,
+}
+
+impl From<&repo::Article> for ArticleCursor {
+    fn from(article: &repo::Article) -> Self {
+        Self {
+            created_at: article.created_at.clone(),
+            slug: article.slug.clone(),
+        }
+    }
+}
+
+impl From<&Article> for ArticleCursor {
+    fn from(article: &Article) -> Self {
+        Self {
+            created_at: article.created_at.clone(),
+            slug: article.slug.clone(),
+        }
+    }
+}
+
+impl ArticleCursor {
+    fn encode(&self) -> String {
+        format!("{}:{}", self.created_at.0.unix_timestamp_nanos(), self.slug)
+            .bytes()
+            .fold(String::new(), |mut output, byte| {
+                let _ = write!(output, "{byte:02x}");
+                output
+            })
+    }
+
+    fn decode(encoded: &str) -> Option<Self> {
+        let bytes = (0..encoded.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(encoded.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let (nanos, slug) = std::str::from_utf8(&bytes).ok()?.split_once(':')?;
+
+        Some(Self {
+            created_at: Timestamptz(
+                time::OffsetDateTime::from_unix_timestamp_nanos(nanos.parse().ok()?).ok()?,
+            ),
+            slug: Slug(slug.to_string()),
+        })
+    }
+}
+
+impl Serialize for ArticleCursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl<'de> Deserialize<'de> for ArticleCursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        Self::decode(&encoded).ok_or_else(|| serde::de::Error::custom("invalid cursor"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_should_roundtrip_through_json() {
+        let cursor = ArticleCursor {
+            created_at: Timestamptz(
+                time::OffsetDateTime::from_unix_timestamp_nanos(1_565_000_000_123_456_000).unwrap(),
+            ),
+            slug: Slug("how-to-train-your-dragon".to_string()),
+        };
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert!(!json.contains("dragon"));
+        assert_eq!(cursor, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn malformed_cursor_should_not_deserialize() {
+        assert!(serde_json::from_str::<ArticleCursor>(r#""abc""#).is_err());
+        assert!(serde_json::from_str::<ArticleCursor>(r#""736c7567""#).is_err());
+    }
+}
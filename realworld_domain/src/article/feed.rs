@@ -0,0 +1,170 @@
+//!
+//! Materialized feeds: the articles in the feed of each user, written when articles are created
+//! and authors followed or unfollowed ("fan-out on write"), so that reading a feed doesn't join the
+//! follows of the reader.
+//!
+//! Used with `--materialized-feeds`. Entries are written by a background task after the event,
+//! so a feed may lag a moment behind. Feeds written while the switch was off are filled in
+//! with [backfill_feeds].
+//!
+
+use super::feed_cache::FeedCache;
+use super::Slug;
+use crate::error::RwResult;
+use crate::event::Event;
+use crate::user::UserId;
+use crate::GetConfig;
+
+use entrait::entrait_export as entrait;
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(FeedRepoImpl, delegate_by=DelegateFeedRepo, mock_api=FeedRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(FeedRepoImpl, delegate_by=ref, mock_api=FeedRepoMock),
+    async_trait::async_trait
+)]
+pub trait FeedRepo {
+    /// Add an article to the feeds of everyone following its author,
+    /// returning the number of feeds it was added to
+    async fn fan_out_article(&self, slug: &Slug) -> RwResult<u64>;
+
+    /// Add the articles of a followed author to the feed of the follower
+    async fn insert_author_into_feed(&self, follower: UserId, username: &str) -> RwResult<()>;
+
+    /// Remove the articles of an unfollowed author from the feed of the follower
+    async fn delete_author_from_feed(&self, follower: UserId, username: &str) -> RwResult<()>;
+
+    /// Write every feed again from the current follows, returning the number of entries
+    async fn rebuild_feeds(&self) -> RwResult<u64>;
+}
+
+///
+/// Update the feeds an event changes, when feeds are materialized.
+///
+/// The cached feeds were invalidated by the request that caused the event, but may have been
+/// cached again before the feed entries were written, so they're invalidated once more.
+///
+#[entrait(pub MaterializeFeed, mock_api=MaterializeFeedMock)]
+async fn materialize_feed(
+    deps: &(impl GetConfig + FeedRepo + FeedCache),
+    event: &Event,
+) -> RwResult<()> {
+    if !deps.materialized_feeds() {
+        return Ok(());
+    }
+
+    match event {
+        Event::ArticleCreated { slug, .. } => {
+            deps.fan_out_article(slug).await?;
+            deps.invalidate_all_feeds().await;
+        }
+        Event::UserFollowed {
+            follower_id,
+            username,
+            following,
+        } => {
+            let follower = UserId(*follower_id);
+            if *following {
+                deps.insert_author_into_feed(follower, username).await?;
+            } else {
+                deps.delete_author_from_feed(follower, username).await?;
+            }
+            deps.invalidate_feed(follower).await;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+///
+/// Write every feed from the current follows, e.g. before switching on `--materialized-feeds`.
+/// Returns the number of feed entries.
+///
+#[entrait(pub BackfillFeeds, mock_api=BackfillFeedsMock)]
+async fn backfill_feeds(deps: &(impl FeedRepo + FeedCache)) -> RwResult<u64> {
+    let entries = deps.rebuild_feeds().await?;
+    deps.invalidate_all_feeds().await;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::feed_cache::FeedCacheMock;
+    use crate::GetConfigMock;
+
+    use unimock::*;
+    use uuid::Uuid;
+
+    fn mock_materialized_feeds(materialized: bool) -> impl unimock::Clause {
+        GetConfigMock::materialized_feeds
+            .each_call(matching!())
+            .returns(materialized)
+    }
+
+    #[tokio::test]
+    async fn created_article_should_be_fanned_out() {
+        let deps = Unimock::new((
+            mock_materialized_feeds(true),
+            FeedRepoMock::fan_out_article
+                .next_call(matching!((slug) if slug.as_str() == "slug"))
+                .returns(Ok(3)),
+            FeedCacheMock::invalidate_all_feeds
+                .next_call(matching!())
+                .returns(()),
+        ));
+
+        materialize_feed(
+            &deps,
+            &Event::ArticleCreated {
+                author_id: Uuid::new_v4(),
+                slug: Slug::from("slug"),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unfollowing_should_remove_author_from_feed() {
+        let deps = Unimock::new((
+            mock_materialized_feeds(true),
+            FeedRepoMock::delete_author_from_feed
+                .next_call(matching!(_, "author"))
+                .returns(Ok(())),
+            FeedCacheMock::invalidate_feed
+                .next_call(matching!(_))
+                .returns(()),
+        ));
+
+        materialize_feed(
+            &deps,
+            &Event::UserFollowed {
+                follower_id: Uuid::new_v4(),
+                username: "author".to_string(),
+                following: false,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn feeds_should_not_be_written_when_not_materialized() {
+        let deps = Unimock::new(mock_materialized_feeds(false));
+
+        materialize_feed(
+            &deps,
+            &Event::UserFollowed {
+                follower_id: Uuid::new_v4(),
+                username: "author".to_string(),
+                following: true,
+            },
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+use super::Article;
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+
+/// Which part of a feed was requested
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
+pub struct FeedPage {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+///
+/// Cache of rendered feeds, keyed by the user the feed belongs to.
+///
+/// A cache is allowed to forget entries at any time,
+/// and failures are never reported to the caller: they are treated as misses.
+///
+#[entrait(mock_api=FeedCacheMock)]
+pub trait FeedCache {
+    async fn get_cached_feed(&self, user_id: UserId, page: FeedPage) -> Option<Vec<Article>>;
+
+    async fn cache_feed(&self, user_id: UserId, page: FeedPage, articles: Vec<Article>);
+
+    /// Forget every cached page of one user's feed
+    async fn invalidate_feed(&self, user_id: UserId);
+
+    /// Forget all cached feeds
+    async fn invalidate_all_feeds(&self);
+}
@@ -0,0 +1,26 @@
+//!
+//! Article bodies are written in Markdown, and can be fetched as HTML.
+//!
+
+use entrait::entrait_export as entrait;
+
+///
+/// Mockable Markdown renderer.
+///
+/// The HTML must be safe to embed in a page, i.e. raw HTML in the Markdown is never passed through.
+///
+#[entrait(mock_api=RenderMarkdownMock)]
+pub trait RenderMarkdown {
+    fn render_markdown(&self, markdown: &str) -> String;
+}
+
+/// The format of a fetched article body
+#[derive(serde::Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    /// As written by the author
+    #[default]
+    Markdown,
+    /// Rendered to sanitized HTML
+    Html,
+}
@@ -1,53 +1,207 @@
+pub mod cursor;
+pub mod feed;
+pub mod feed_cache;
+pub mod markdown;
+pub mod public_id;
+pub mod reading_time;
 pub mod repo;
+pub mod slugify;
+pub mod tag;
+pub mod views;
 
+use crate::audit::{AuditAction, AuditLog, NewAuditEntry};
 use crate::error::*;
+use crate::event::{DomainEvents, Event};
 use crate::iter_util::Single;
+use crate::sanitize::sanitize;
 use crate::timestamp::Timestamptz;
 use crate::user::auth::*;
-use crate::user::profile::Profile;
+use crate::user::opaque_token::OpaqueToken;
+use crate::user::profile::{Profile, ProfileLoader};
+use crate::user::repo::UserRepo;
 use crate::user::UserId;
-use repo::ArticleRepo;
+use crate::{GetConfig, System};
+use cursor::ArticleCursor;
+use feed_cache::{FeedCache, FeedPage};
+use markdown::{BodyFormat, RenderMarkdown};
+use repo::{ArticleRepo, ArticleView, BookmarkRepo, Viewer};
+use slugify::slugify;
+use views::RecordView;
 
 use entrait::entrait_export as entrait;
+use std::net::IpAddr;
 
+/// Stays the same when the slug changes. Exposed as a [public_id].
+#[derive(
+    sqlx::Type, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash,
+)]
+#[sqlx(transparent)]
+#[serde(transparent)]
+pub struct ArticleId(pub uuid::Uuid);
+
+/// Addresses an article in URLs. Derived from the title, so it changes when the title is edited.
+#[derive(
+    sqlx::Type,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Default,
+)]
+#[sqlx(transparent)]
+#[serde(transparent)]
+pub struct Slug(pub String);
+
+impl Slug {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Slug {
+    fn from(slug: &str) -> Self {
+        Self(slug.to_string())
+    }
+}
+
+impl AsRef<str> for Slug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Slug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+///
+/// An article as seen by a user, with its author and counts.
+///
+/// The API has its own representations of this, the serde derives are only for caching.
+///
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
 #[cfg_attr(test, derive(Debug))]
-#[serde(rename_all = "camelCase")]
 pub struct Article {
-    slug: String,
-    title: String,
-    description: String,
-    body: String,
-    tag_list: Vec<String>,
-    created_at: Timestamptz,
+    /// Stays the same when the title, and thereby the slug, changes
+    pub id: String,
+    pub slug: Slug,
+    pub title: String,
+    pub description: String,
+    /// Left out of summaries, like the counts that depend on it
+    pub body: Option<String>,
+    pub tag_list: Vec<String>,
+    pub created_at: Timestamptz,
     // Note: the Postman collection included with the spec assumes that this is never null.
     // We prefer to leave it unset unless the row has actually be updated.
-    updated_at: Timestamptz,
-    favorited: bool,
-    favorites_count: i64,
-    author: Profile,
+    pub updated_at: Timestamptz,
+    pub favorited: bool,
+    pub favorites_count: i64,
+    /// Views by different users and IP addresses, counted once per day
+    pub views_count: i64,
+    pub author: Profile,
+    pub word_count: Option<u32>,
+    pub reading_time_minutes: Option<u32>,
+    // Missing from articles cached before there were unlisted ones
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Only shown to the author
+    pub share_token: Option<String>,
+}
+
+/// Unlisted articles are left out of every listing, and are shared by a link with a random token
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    Unlisted,
+}
+
+/// A new token for sharing an unlisted article.
+/// The author is shown it again whenever they fetch the article, so unlike other opaque tokens it's stored as is.
+fn generate_share_token() -> String {
+    OpaqueToken::generate().as_ref().to_string()
 }
 
-impl From<repo::Article> for Article {
-    fn from(q: repo::Article) -> Self {
+impl Article {
+    /// The reading time is based on [GetConfig::words_per_minute]
+    fn from_db(
+        q: repo::Article,
+        author: Profile,
+        words_per_minute: u32,
+        current_user: UserId<Option<uuid::Uuid>>,
+    ) -> Self {
+        let word_count = reading_time::word_count(&q.body);
+        let visibility = match q.share_token {
+            Some(_) => Visibility::Unlisted,
+            None => Visibility::Public,
+        };
+        let share_token = q
+            .share_token
+            .filter(|_| current_user.0 == Some(q.author_id));
+
         Self {
+            id: public_id::encode(q.article_id),
             slug: q.slug,
             title: q.title,
             description: q.description,
-            body: q.body,
+            body: Some(q.body),
             tag_list: q.tag_list,
             created_at: q.created_at,
             updated_at: q.updated_at,
             favorited: q.favorited,
             favorites_count: q.favorites_count,
-            author: Profile {
-                username: q.author_username,
-                bio: q.author_bio,
-                image: q.author_image,
-                following: q.following_author,
-            },
+            views_count: q.views_count,
+            author,
+            word_count: Some(word_count),
+            reading_time_minutes: Some(reading_time::reading_time_minutes(
+                word_count,
+                words_per_minute,
+            )),
+            visibility,
+            share_token,
+        }
+    }
+
+    /// Without what summaries leave out, see [repo::Filter::summary]
+    fn into_summary(self) -> Self {
+        Self {
+            body: None,
+            word_count: None,
+            reading_time_minutes: None,
+            ..self
         }
     }
+
+    ///
+    /// Identifies this version of the article, for conditional requests.
+    ///
+    /// Editing the article changes `updated_at` and possibly the slug, and thereby the ETag.
+    ///
+    pub fn etag(&self) -> headers::ETag {
+        use sha2::{Digest, Sha256};
+        use std::fmt::Write;
+
+        let digest = Sha256::new()
+            .chain_update(self.updated_at.0.unix_timestamp_nanos().to_be_bytes())
+            .chain_update(self.slug.as_str().as_bytes())
+            .finalize();
+        let hex = digest[..16].iter().fold(String::new(), |mut output, byte| {
+            let _ = write!(output, "{byte:02x}");
+            output
+        });
+
+        format!("\"{hex}\"")
+            .parse()
+            .expect("quoted hex should be a valid ETag")
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -59,23 +213,79 @@ pub struct ArticleCreate {
     description: String,
     body: String,
     tag_list: Vec<String>,
+    #[serde(default)]
+    visibility: Visibility,
 }
 
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ArticleUpdate {
     title: Option<String>,
     description: Option<String>,
     body: Option<String>,
+    /// Replaces all the tags of the article
+    tag_list: Option<Vec<String>>,
+    /// An article that is already unlisted keeps its share token
+    visibility: Option<Visibility>,
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct FetchArticleQuery {
+    pub format: BodyFormat,
 }
 
 #[derive(serde::Deserialize, Default, Eq, PartialEq)]
 #[serde(default)]
 pub struct ListArticlesQuery {
+    /// Comma separated, articles with any of the tags
     tag: Option<String>,
+    /// Comma separated, articles with all of the tags
+    tag_all: Option<String>,
+    /// Comma separated, articles with none of the tags
+    exclude_tag: Option<String>,
     author: Option<String>,
     favorited: Option<String>,
     limit: Option<i64>,
     offset: Option<i64>,
+    /// The `next_cursor` of the previous page
+    after: Option<ArticleCursor>,
+    /// Leave out the bodies, and shorten the descriptions, to keep the listing small
+    summary: bool,
+}
+
+/// Most articles that [api::set_favorites] takes at once
+pub const MAX_FAVORITES_BATCH: usize = 100;
+
+/// Favorites to set at once, e.g. when a client syncs what was favorited while offline
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct FavoritesBatch {
+    pub slugs: Vec<Slug>,
+    pub favorited: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FavoriteStatus {
+    Favorited,
+    Unfavorited,
+    NotFound,
+}
+
+/// What happened to one article of a [FavoritesBatch]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct FavoriteResult {
+    pub slug: Slug,
+    pub status: FavoriteStatus,
+}
+
+/// One page of listed articles
+#[derive(Clone, Default)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ArticleList {
+    pub articles: Vec<Article>,
+    /// Where the next page starts, if there might be one
+    pub next_cursor: Option<ArticleCursor>,
 }
 
 #[derive(serde::Deserialize, Default)]
@@ -84,6 +294,121 @@ pub struct FeedArticlesQuery {
     // See comment on these fields in `ListArticlesQuery` above.
     limit: Option<i64>,
     offset: Option<i64>,
+    /// The `next_cursor` of the previous page.
+    /// Unlike an offset, deep pages are as quick to get as the first one.
+    after: Option<ArticleCursor>,
+}
+
+/// A full page might not be the last one, so it has a cursor to the next one
+fn feed_page(articles: Vec<Article>, limit: i64) -> ArticleList {
+    let next_cursor = if articles.len() as i64 >= limit {
+        articles.last().map(ArticleCursor::from)
+    } else {
+        None
+    };
+    ArticleList {
+        articles,
+        next_cursor,
+    }
+}
+
+/// Others only see an unlisted article through its share link, not by its slug
+fn is_hidden_from(article: &repo::Article, current_user_id: UserId<Option<uuid::Uuid>>) -> bool {
+    article.share_token.is_some() && current_user_id.0 != Some(article.author_id)
+}
+
+///
+/// The id of the article with the slug, for the routes nested under it.
+/// Like [api::fetch_article], an unlisted article of someone else is not found.
+///
+pub(crate) async fn fetch_visible_article_id(
+    deps: &impl ArticleRepo,
+    current_user_id: UserId<Option<uuid::Uuid>>,
+    slug: &Slug,
+) -> RwResult<ArticleId> {
+    deps.select_articles(
+        current_user_id,
+        repo::Filter {
+            slug: Some(slug),
+            summary: true,
+            ..Default::default()
+        },
+    )
+    .await?
+    .into_iter()
+    .single_or_none()?
+    .filter(|article| !is_hidden_from(article, current_user_id))
+    .map(|article| article.article_id)
+    .ok_or(RwError::ArticleNotFound)
+}
+
+/// Bodies are limited by [GetConfig::max_article_body_bytes], as posted
+fn check_body_size(deps: &impl GetConfig, body: &str) -> RwResult<()> {
+    let max_bytes = deps.max_article_body_bytes();
+    if body.len() > max_bytes {
+        return Err(RwError::ArticleBodyTooLarge { max_bytes });
+    }
+    Ok(())
+}
+
+/// Loads the profiles of all the authors with one query
+async fn load_authors(
+    deps: &(impl GetConfig + UserRepo),
+    current_user: UserId<Option<uuid::Uuid>>,
+    articles: Vec<repo::Article>,
+) -> RwResult<Vec<Article>> {
+    let mut profiles = ProfileLoader::new(current_user);
+    profiles
+        .load(
+            deps,
+            articles.iter().map(|article| UserId(article.author_id)),
+        )
+        .await?;
+
+    articles
+        .into_iter()
+        .map(|article| {
+            let author = profiles.get(UserId(article.author_id))?;
+            Ok(Article::from_db(
+                article,
+                author,
+                deps.words_per_minute(),
+                current_user,
+            ))
+        })
+        .collect()
+}
+
+/// Loads the author of an article being viewed, and counts the view as described for [api::fetch_article]
+async fn view_article(
+    deps: &(impl GetConfig + System + UserRepo + RenderMarkdown + RecordView),
+    current_user_id: UserId<Option<uuid::Uuid>>,
+    article: repo::Article,
+    query: FetchArticleQuery,
+    ip_address: Option<IpAddr>,
+) -> RwResult<Article> {
+    let mut article = load_authors(deps, current_user_id, vec![article])
+        .await?
+        .into_iter()
+        .single()?;
+
+    let viewer = match (current_user_id, ip_address) {
+        (UserId(Some(user_id)), _) => Some(Viewer::User(UserId(user_id))),
+        (UserId(None), Some(ip_address)) => Some(Viewer::Ip(ip_address)),
+        (UserId(None), None) => None,
+    };
+    if let Some(viewer) = viewer {
+        deps.record_view(ArticleView {
+            slug: article.slug.clone(),
+            viewer,
+            day: deps.get_current_time().date(),
+        });
+    }
+
+    if query.format == BodyFormat::Html {
+        article.body = article.body.map(|body| deps.render_markdown(&body));
+    }
+    Ok(article)
 }
 
 #[entrait(pub Api, mock_api=mock)]
@@ -91,185 +416,548 @@ pub mod api {
     use super::*;
 
     pub async fn list_articles(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo),
         token: Option<Token>,
         query: ListArticlesQuery,
-    ) -> RwResult<Vec<Article>> {
-        let current_user_id = deps.opt_authenticate(token)?;
-        deps.select_articles(
-            current_user_id,
-            repo::Filter {
-                slug: None,
-                tag: query.tag.as_deref(),
-                author: query.author.as_deref(),
-                favorited_by: query.favorited.as_deref(),
-                followed_by: None,
-                limit: query.limit,
-                offset: query.offset,
-            },
-        )
-        .await
-        .map(|articles| articles.into_iter().map(Into::into).collect())
+    ) -> RwResult<ArticleList> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        let page = deps.page_sizes().apply(repo::Pagination {
+            limit: query.limit,
+            offset: query.offset,
+        })?;
+        let limit = page.limit();
+        let any_tag = split_tags(query.tag.as_deref());
+        let all_tags = split_tags(query.tag_all.as_deref());
+        let excluded_tags = split_tags(query.exclude_tag.as_deref());
+        let articles = deps
+            .select_articles(
+                current_user_id,
+                repo::Filter {
+                    slug: None,
+                    share_token: None,
+                    any_tag: &any_tag,
+                    all_tags: &all_tags,
+                    excluded_tags: &excluded_tags,
+                    author: query.author.as_deref(),
+                    favorited_by: query.favorited.as_deref(),
+                    followed_by: None,
+                    in_feed_of: None,
+                    bookmarked_by: None,
+                    limit: Some(limit),
+                    offset: page.offset,
+                    after: query.after.as_ref(),
+                    summary: query.summary,
+                },
+            )
+            .await?;
+
+        // A short page is the last one
+        let next_cursor = if articles.len() as i64 >= limit {
+            articles.last().map(ArticleCursor::from)
+        } else {
+            None
+        };
+
+        let mut articles = load_authors(deps, current_user_id, articles).await?;
+        if query.summary {
+            articles = articles.into_iter().map(Article::into_summary).collect();
+        }
+        Ok(ArticleList {
+            articles,
+            next_cursor,
+        })
     }
 
     pub async fn feed_articles(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo + FeedCache),
         token: Token,
         query: FeedArticlesQuery,
-    ) -> RwResult<Vec<Article>> {
-        let current_user_id = deps.authenticate(token)?;
-        deps.select_articles(
-            current_user_id.some(),
-            repo::Filter {
-                slug: None,
-                tag: None,
-                author: None,
-                favorited_by: None,
-                followed_by: Some(current_user_id),
-                limit: query.limit,
-                offset: query.offset,
-            },
-        )
-        .await
-        .map(|articles| articles.into_iter().map(Into::into).collect())
+    ) -> RwResult<ArticleList> {
+        let current_user_id = deps.authenticate(token).await?;
+        let pagination = deps.page_sizes().apply(repo::Pagination {
+            limit: query.limit,
+            offset: query.offset,
+        })?;
+        let limit = pagination.limit();
+        // Pages after a cursor aren't cached, there are too many of them
+        let page = query.after.is_none().then_some(FeedPage {
+            limit: pagination.limit,
+            offset: pagination.offset,
+        });
+        if let Some(page) = page {
+            if let Some(articles) = deps.get_cached_feed(current_user_id, page).await {
+                return Ok(feed_page(articles, limit));
+            }
+        }
+
+        // The same articles either way, but a materialized feed doesn't join the follows
+        let (followed_by, in_feed_of) = if deps.materialized_feeds() {
+            (None, Some(current_user_id))
+        } else {
+            (Some(current_user_id), None)
+        };
+        let articles = deps
+            .select_articles(
+                current_user_id.some(),
+                repo::Filter {
+                    slug: None,
+                    author: None,
+                    favorited_by: None,
+                    followed_by,
+                    in_feed_of,
+                    limit: Some(limit),
+                    offset: pagination.offset,
+                    after: query.after.as_ref(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let articles = load_authors(deps, current_user_id.some(), articles).await?;
+
+        if let Some(page) = page {
+            deps.cache_feed(current_user_id, page, articles.clone())
+                .await;
+        }
+        Ok(feed_page(articles, limit))
     }
 
+    ///
+    /// Fetching an article counts as a view by the current user, or by `ip_address`
+    /// when anonymous. The view is counted later, and isn't part of the returned article.
+    ///
     pub async fn fetch_article(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate
+              + GetConfig
+              + System
+              + ArticleRepo
+              + UserRepo
+              + RenderMarkdown
+              + RecordView),
         token: Option<Token>,
-        slug: &str,
+        slug: &Slug,
+        query: FetchArticleQuery,
+        ip_address: Option<IpAddr>,
     ) -> RwResult<Article> {
-        let current_user_id = deps.opt_authenticate(token)?;
-        deps.select_articles(
-            current_user_id,
-            repo::Filter {
-                slug: Some(slug),
-                ..Default::default()
-            },
-        )
-        .await?
-        .into_iter()
-        .single_or_none()?
-        .map(Into::into)
-        .ok_or(RwError::ArticleNotFound)
+        let current_user_id = deps.opt_authenticate(token).await?;
+        let article = deps
+            .select_articles(
+                current_user_id,
+                repo::Filter {
+                    slug: Some(slug),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .single_or_none()?
+            .ok_or(RwError::ArticleNotFound)?;
+        if is_hidden_from(&article, current_user_id) {
+            return Err(RwError::ArticleNotFound);
+        }
+
+        view_article(deps, current_user_id, article, query, ip_address).await
+    }
+
+    ///
+    /// Fetch an unlisted article by the token of its share link, the only way anyone but its author sees it.
+    /// Counts as a view like [fetch_article].
+    ///
+    pub async fn fetch_shared_article(
+        deps: &(impl Authenticate
+              + GetConfig
+              + System
+              + ArticleRepo
+              + UserRepo
+              + RenderMarkdown
+              + RecordView),
+        token: Option<Token>,
+        share_token: &str,
+        query: FetchArticleQuery,
+        ip_address: Option<IpAddr>,
+    ) -> RwResult<Article> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        let article = deps
+            .select_articles(
+                current_user_id,
+                repo::Filter {
+                    share_token: Some(share_token),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .single_or_none()?
+            .ok_or(RwError::ArticleNotFound)?;
+
+        view_article(deps, current_user_id, article, query, ip_address).await
+    }
+
+    ///
+    /// Fetch an article by its [public_id], for clients holding a slug that may have changed.
+    ///
+    pub async fn fetch_article_by_id(
+        deps: &(impl Authenticate
+              + GetConfig
+              + System
+              + ArticleRepo
+              + UserRepo
+              + RenderMarkdown
+              + RecordView),
+        token: Option<Token>,
+        id: &str,
+        query: FetchArticleQuery,
+        ip_address: Option<IpAddr>,
+    ) -> RwResult<Article> {
+        let article_id = public_id::decode(id).ok_or(RwError::ArticleNotFound)?;
+        let slug = deps
+            .find_article_slug(article_id)
+            .await?
+            .ok_or(RwError::ArticleNotFound)?;
+
+        fetch_article(deps, token, &slug, query, ip_address).await
+    }
+
+    /// The current slug of an article that no longer has `previous_slug`, since its title was edited
+    pub async fn find_renamed_article(
+        deps: &impl ArticleRepo,
+        previous_slug: &Slug,
+    ) -> RwResult<Option<Slug>> {
+        deps.find_renamed_article_slug(previous_slug).await
     }
 
     pub async fn create_article(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo + FeedCache + DomainEvents),
         token: Token,
         article: ArticleCreate,
     ) -> RwResult<Article> {
-        let current_user_id = deps.authenticate(token)?;
-        let slug = slugify(&article.title);
-        deps.insert_article(
-            current_user_id,
-            &slug,
-            &article.title,
-            &article.description,
-            &article.body,
-            &article.tag_list,
-        )
-        .await
-        .map(Into::into)
+        let current_user_id = deps.authenticate(token).await?;
+        check_body_size(deps, &article.body)?;
+        let slug = slugify(deps.slug_strategy(), &article.title);
+        let tag_list = deps.tag_rules().normalize(&article.tag_list)?;
+        let body = sanitize(deps.sanitize_mode(), &article.body);
+        let share_token = (article.visibility == Visibility::Unlisted).then(generate_share_token);
+        let article = deps
+            .insert_article(
+                current_user_id,
+                &slug,
+                &article.title,
+                &article.description,
+                &body,
+                &tag_list,
+                share_token.as_deref(),
+            )
+            .await?;
+
+        // The article belongs in the feed of every follower of the author,
+        // and we don't know who they are here.
+        deps.invalidate_all_feeds().await;
+        deps.publish(Event::ArticleCreated {
+            author_id: current_user_id.into_id(),
+            slug: article.slug.clone(),
+        });
+        load_authors(deps, current_user_id.some(), vec![article])
+            .await?
+            .into_iter()
+            .single()
     }
 
+    ///
+    /// With `if_match`, the update only happens if the article still has one of the given ETags,
+    /// so that concurrent edits aren't silently lost.
+    ///
     pub async fn update_article(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo + FeedCache + DomainEvents),
         token: Token,
-        slug: &str,
+        slug: &Slug,
         article_update: ArticleUpdate,
+        if_match: Option<headers::IfMatch>,
     ) -> RwResult<Article> {
-        let current_user_id = deps.authenticate(token)?;
-        let new_slug = article_update.title.as_deref().map(slugify);
+        let current_user_id = deps.authenticate(token).await?;
+        if let Some(body) = &article_update.body {
+            check_body_size(deps, body)?;
+        }
+        let new_slug = article_update
+            .title
+            .as_deref()
+            .map(|title| slugify(deps.slug_strategy(), title));
+        let tag_list = article_update
+            .tag_list
+            .as_deref()
+            .map(|tag_list| deps.tag_rules().normalize(tag_list))
+            .transpose()?;
+        let body = article_update
+            .body
+            .as_deref()
+            .map(|body| sanitize(deps.sanitize_mode(), body));
+        let share_token = article_update
+            .visibility
+            .map(|visibility| match visibility {
+                Visibility::Public => None,
+                Visibility::Unlisted => Some(generate_share_token()),
+            });
+
+        let expected_updated_at = match if_match {
+            Some(if_match) => {
+                let current = get_single_article(deps, current_user_id, slug).await?;
+                if !if_match.precondition_passes(&current.etag()) {
+                    return Err(RwError::PreconditionFailed);
+                }
+                // The repo checks this again while updating, in case of a concurrent update
+                Some(current.updated_at)
+            }
+            None => None,
+        };
 
         deps.update_article(
             current_user_id,
             slug,
             repo::ArticleUpdate {
-                slug: new_slug.as_deref(),
+                slug: new_slug.as_ref(),
                 title: article_update.title.as_deref(),
                 description: article_update.description.as_deref(),
-                body: article_update.body.as_deref(),
+                body: body.as_deref(),
+                tag_list: tag_list.as_deref(),
+                share_token: share_token.as_ref().map(Option::as_deref),
+                expected_updated_at: expected_updated_at.as_ref(),
             },
         )
         .await?;
+        deps.invalidate_all_feeds().await;
 
-        get_single_article(deps, current_user_id, new_slug.as_deref().unwrap_or(slug)).await
+        let new_slug = new_slug.as_ref().unwrap_or(slug);
+        deps.publish(Event::ArticleUpdated {
+            user_id: current_user_id.into_id(),
+            previous_slug: slug.clone(),
+            slug: new_slug.clone(),
+        });
+        get_single_article(deps, current_user_id, new_slug).await
     }
 
     pub async fn delete_article(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl AuthorizeRole + ArticleRepo + FeedCache + DomainEvents + AuditLog),
         token: Token,
-        slug: &str,
+        slug: &Slug,
     ) -> RwResult<()> {
-        let current_user_id = deps.authenticate(token)?;
-        deps.delete_article(current_user_id, slug).await
+        let (current_user_id, role) = deps.authenticate_with_role(token).await?;
+        if role.can_moderate_articles() {
+            deps.delete_any_article(slug).await?;
+        } else {
+            deps.delete_article(current_user_id, slug).await?;
+        }
+        deps.record_audit(NewAuditEntry {
+            user_id: current_user_id.some(),
+            action: AuditAction::ArticleDelete,
+            target: Some(slug.as_str()),
+            ip_address: None,
+        })
+        .await?;
+        deps.invalidate_all_feeds().await;
+        deps.publish(Event::ArticleDeleted {
+            user_id: current_user_id.into_id(),
+            slug: slug.clone(),
+        });
+        Ok(())
     }
 
     pub async fn favorite_article(
-        deps: &(impl Authenticate + ArticleRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo + FeedCache + DomainEvents),
         token: Token,
-        slug: &str,
+        slug: &Slug,
         value: bool,
     ) -> RwResult<Article> {
-        let current_user_id = deps.authenticate(token)?;
+        let current_user_id = deps.authenticate(token).await?;
+        fetch_visible_article_id(deps, current_user_id.some(), slug).await?;
         if value {
             deps.insert_favorite(current_user_id, slug).await?;
         } else {
             deps.delete_favorite(current_user_id, slug).await?;
         }
+        // Favorite counts are part of every feed the article appears in
+        deps.invalidate_all_feeds().await;
+        deps.publish(Event::ArticleFavorited {
+            user_id: current_user_id.into_id(),
+            slug: slug.clone(),
+            favorited: value,
+        });
+        get_single_article(deps, current_user_id, slug).await
+    }
+
+    ///
+    /// Favorite or unfavorite several articles at once.
+    /// Unknown slugs don't fail the batch, but are reported as [FavoriteStatus::NotFound].
+    ///
+    pub async fn set_favorites(
+        deps: &(impl Authenticate + ArticleRepo + FeedCache + DomainEvents),
+        token: Token,
+        batch: FavoritesBatch,
+    ) -> RwResult<Vec<FavoriteResult>> {
+        if batch.slugs.len() > MAX_FAVORITES_BATCH {
+            return Err(RwError::BatchTooLarge {
+                field: "slugs",
+                max: MAX_FAVORITES_BATCH,
+            });
+        }
+        let current_user_id = deps.authenticate(token).await?;
+        let found = deps
+            .set_favorites_bulk(current_user_id, &batch.slugs, batch.favorited)
+            .await?;
+
+        if !found.is_empty() {
+            deps.invalidate_all_feeds().await;
+        }
+        for slug in &found {
+            deps.publish(Event::ArticleFavorited {
+                user_id: current_user_id.into_id(),
+                slug: slug.clone(),
+                favorited: batch.favorited,
+            });
+        }
+
+        Ok(batch
+            .slugs
+            .into_iter()
+            .map(|slug| {
+                let status = match (found.contains(&slug), batch.favorited) {
+                    (false, _) => FavoriteStatus::NotFound,
+                    (true, true) => FavoriteStatus::Favorited,
+                    (true, false) => FavoriteStatus::Unfavorited,
+                };
+                FavoriteResult { slug, status }
+            })
+            .collect())
+    }
+
+    /// Bookmarks are private, so unlike favorites they don't show up in any feed
+    pub async fn bookmark_article(
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + BookmarkRepo + UserRepo),
+        token: Token,
+        slug: &Slug,
+        value: bool,
+    ) -> RwResult<Article> {
+        let current_user_id = deps.authenticate(token).await?;
+        fetch_visible_article_id(deps, current_user_id.some(), slug).await?;
+        if value {
+            deps.insert_bookmark(current_user_id, slug).await?;
+        } else {
+            deps.delete_bookmark(current_user_id, slug).await?;
+        }
         get_single_article(deps, current_user_id, slug).await
     }
 
+    pub async fn list_bookmarked_articles(
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo),
+        token: Token,
+        pagination: repo::Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let current_user_id = deps.authenticate(token).await?;
+        let pagination = deps.page_sizes().apply(pagination)?;
+        let articles = deps
+            .select_articles(
+                current_user_id.some(),
+                repo::Filter {
+                    bookmarked_by: Some(current_user_id),
+                    limit: Some(pagination.limit()),
+                    offset: pagination.offset,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        load_authors(deps, current_user_id.some(), articles).await
+    }
+
+    /// Articles with the most favorites and comments within [GetConfig::trending_window]
+    pub async fn list_trending_articles(
+        deps: &(impl Authenticate + GetConfig + System + ArticleRepo + UserRepo),
+        token: Option<Token>,
+        pagination: repo::Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        let pagination = deps.page_sizes().apply(pagination)?;
+        let since = deps.get_current_time() - deps.trending_window();
+        let articles = deps
+            .select_trending_articles(current_user_id, since, pagination)
+            .await?;
+
+        load_authors(deps, current_user_id, articles).await
+    }
+
+    /// Articles favorited by users with favorites in common with the current user
+    pub async fn list_recommended_articles(
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + UserRepo),
+        token: Token,
+        pagination: repo::Pagination,
+    ) -> RwResult<Vec<Article>> {
+        let current_user_id = deps.authenticate(token).await?;
+        let pagination = deps.page_sizes().apply(pagination)?;
+        let articles = deps
+            .select_recommended_articles(current_user_id, pagination)
+            .await?;
+
+        load_authors(deps, current_user_id.some(), articles).await
+    }
+
+    /// Profiles of the users who favorited an article, most recent favorite first
+    pub async fn list_favoriters(
+        deps: &(impl Authenticate + ArticleRepo),
+        token: Option<Token>,
+        slug: &Slug,
+        pagination: repo::Pagination,
+    ) -> RwResult<Vec<Profile>> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        // Unknown articles are not found, rather than favorited by nobody
+        fetch_visible_article_id(deps, current_user_id, slug).await?;
+
+        Ok(deps
+            .list_favoriting_users(current_user_id, slug, pagination)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     async fn get_single_article(
-        deps: &impl ArticleRepo,
+        deps: &(impl GetConfig + ArticleRepo + UserRepo),
         current_user_id: UserId,
-        slug: &str,
+        slug: &Slug,
     ) -> RwResult<Article> {
-        deps.select_articles(
-            current_user_id.some(),
-            repo::Filter {
-                slug: Some(slug),
-                ..Default::default()
-            },
-        )
-        .await?
-        .into_iter()
-        .single()
-        .map(Into::into)
-    }
-
-    fn slugify(string: &str) -> String {
-        use itertools::Itertools;
-
-        const QUOTE_CHARS: &[char] = &['\'', '"'];
-
-        string
-            // Split on anything that isn't a word character or quotation mark.
-            // This has the effect of keeping contractions and possessives together.
-            .split(|c: char| !(QUOTE_CHARS.contains(&c) || c.is_alphanumeric()))
-            // If multiple non-word characters follow each other then we'll get empty substrings
-            // so we'll filter those out.
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                // Remove quotes from the substring.
-                //
-                // This allocation is probably avoidable with some more iterator hackery but
-                // at that point we'd be micro-optimizing. This function isn't called all that often.
-                let mut s = s.replace(QUOTE_CHARS, "");
-                // Make the substring lowercase (in-place operation)
-                s.make_ascii_lowercase();
-                s
-            })
-            .join("-")
+        let article = deps
+            .select_articles(
+                current_user_id.some(),
+                repo::Filter {
+                    slug: Some(slug),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .single()?;
+
+        load_authors(deps, current_user_id.some(), vec![article])
+            .await?
+            .into_iter()
+            .single()
+    }
+
+    fn split_tags(tags: Option<&str>) -> Vec<String> {
+        tags.into_iter()
+            .flat_map(|tags| tags.split(','))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::pagination::PageSizes;
     use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::auth::authorize_role::AuthorizeRoleMock;
+    use crate::user::repo::{Following, User, UserRepoMock};
+    use crate::user::role::Role;
 
-    use super::{repo::ArticleRepoMock, *};
+    use super::{feed_cache::FeedCacheMock, repo::ArticleRepoMock, *};
     use assert_matches::*;
     use unimock::*;
     use uuid::Uuid;
@@ -286,7 +974,8 @@ mod tests {
 
     fn test_db_article() -> repo::Article {
         repo::Article {
-            slug: "slug".to_string(),
+            article_id: ArticleId(uuid::Uuid::from_u128(1)),
+            slug: Slug::from("slug"),
             title: "title".to_string(),
             description: "desc".to_string(),
             body: "body".to_string(),
@@ -295,19 +984,72 @@ mod tests {
             updated_at: test_timestamp(),
             favorited: false,
             favorites_count: 0,
+            views_count: 0,
+            author_id: Uuid::from_u128(1),
             author_username: "author".to_string(),
-            author_bio: "bio".to_string(),
-            author_image: Some("image".to_string()),
-            following_author: false,
+            share_token: None,
+        }
+    }
+
+    fn test_author() -> Profile {
+        Profile {
+            username: "author".to_string(),
+            ..Default::default()
         }
     }
 
+    fn mock_load_authors() -> impl unimock::Clause {
+        UserRepoMock::select_users_by_ids
+            .each_call(matching!(_, _))
+            .answers(&|_, _, user_ids| {
+                Ok(user_ids
+                    .iter()
+                    .map(|user_id| {
+                        (
+                            User {
+                                user_id: UserId(*user_id),
+                                username: "author".to_string(),
+                                bio: "".to_string(),
+                                image: None,
+                                role: Default::default(),
+                            },
+                            Following(false),
+                        )
+                    })
+                    .collect())
+            })
+    }
+
     fn mock_authenticate() -> impl unimock::Clause {
         AuthenticateMock::authenticate
             .next_call(matching!(_))
             .returns(Ok(UserId(Uuid::new_v4())))
     }
 
+    fn mock_tag_rules() -> impl unimock::Clause {
+        crate::GetConfigMock::tag_rules
+            .each_call(matching!())
+            .returns(tag::TagRules::default())
+    }
+
+    fn mock_words_per_minute() -> impl unimock::Clause {
+        crate::GetConfigMock::words_per_minute
+            .each_call(matching!())
+            .returns(reading_time::DEFAULT_WORDS_PER_MINUTE)
+    }
+
+    fn mock_max_article_body_bytes() -> impl unimock::Clause {
+        crate::GetConfigMock::max_article_body_bytes
+            .each_call(matching!())
+            .returns(1024_usize)
+    }
+
+    fn mock_invalidate_all_feeds() -> impl unimock::Clause {
+        FeedCacheMock::invalidate_all_feeds
+            .next_call(matching!())
+            .returns(())
+    }
+
     fn mock_authenticate_anonymous() -> impl unimock::Clause {
         AuthenticateMock::opt_authenticate
             .next_call(matching!(None))
@@ -317,10 +1059,18 @@ mod tests {
     #[tokio::test]
     async fn create_article_should_slugify() {
         let deps = Unimock::new((
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            crate::test::mock_slug_strategy(),
+            crate::test::mock_publish_events(),
             mock_authenticate(),
+            mock_tag_rules(),
             ArticleRepoMock::insert_article
-                .next_call(matching!(UserId(_), "my-title", _, _, _, _))
+                .next_call(matching!(UserId(_), "my-title", _, _, _, _, None))
                 .returns(Ok(test_db_article())),
+            mock_invalidate_all_feeds(),
         ));
         api::create_article(
             &deps,
@@ -330,6 +1080,7 @@ mod tests {
                 description: "Desc".to_string(),
                 body: "Body".to_string(),
                 tag_list: vec!["tag".to_string()],
+                visibility: Visibility::Public,
             },
         )
         .await
@@ -337,62 +1088,1137 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn get_article_empty_result_should_produce_not_found_error() {
+    async fn create_article_should_slugify_with_configured_strategy() {
+        for (strategy, expected_slug) in [
+            (slugify::SlugStrategy::Transliterate, "privet-mir"),
+            (slugify::SlugStrategy::Unicode, "привет-мир"),
+        ] {
+            let deps = Unimock::new((
+                mock_max_article_body_bytes(),
+                mock_load_authors(),
+                mock_words_per_minute(),
+                crate::test::mock_sanitize_mode(),
+                crate::GetConfigMock::slug_strategy
+                    .each_call(matching!())
+                    .returns(strategy),
+                crate::test::mock_publish_events(),
+                mock_authenticate(),
+                mock_tag_rules(),
+                ArticleRepoMock::insert_article
+                    .next_call(matching!(_, _, _, _, _, _, _))
+                    .answers(&|_, _, slug, _, _, _, _, _| {
+                        Ok(repo::Article {
+                            slug: slug.clone(),
+                            ..test_db_article()
+                        })
+                    }),
+                mock_invalidate_all_feeds(),
+            ));
+            let article = api::create_article(
+                &deps,
+                Token::from_token("token"),
+                ArticleCreate {
+                    title: "Привет, мир".to_string(),
+                    description: "Desc".to_string(),
+                    body: "Body".to_string(),
+                    tag_list: vec![],
+                    visibility: Visibility::Public,
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(expected_slug, article.slug.as_str());
+        }
+    }
+
+    #[tokio::test]
+    async fn create_article_should_normalize_tags() {
         let deps = Unimock::new((
-            mock_authenticate_anonymous(),
-            ArticleRepoMock::select_articles
-                .next_call(matching!(
-                    UserId(None),
-                    repo::Filter {
-                        slug: Some("slug"),
-                        ..
-                    }
-                ))
-                .returns(Ok(vec![])),
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            crate::test::mock_slug_strategy(),
+            crate::test::mock_publish_events(),
+            mock_authenticate(),
+            mock_tag_rules(),
+            ArticleRepoMock::insert_article
+                .next_call(matching!((_, _, _, _, _, tag_list, _) if *tag_list == ["rust", "web"]))
+                .returns(Ok(test_db_article())),
+            mock_invalidate_all_feeds(),
         ));
-        assert_matches!(
-            api::fetch_article(&deps, Token::none(), "slug").await,
-            Err(RwError::ArticleNotFound)
-        );
+        api::create_article(
+            &deps,
+            Token::from_token("token"),
+            ArticleCreate {
+                title: "Title".to_string(),
+                description: "Desc".to_string(),
+                body: "Body".to_string(),
+                tag_list: vec![" Rust".to_string(), "web".to_string(), "rust".to_string()],
+                visibility: Visibility::Public,
+            },
+        )
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
-    async fn update_article_should_update_slug() {
+    async fn create_article_should_sanitize_body() {
         let deps = Unimock::new((
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            crate::test::mock_slug_strategy(),
+            crate::test::mock_publish_events(),
             mock_authenticate(),
-            ArticleRepoMock::update_article
-                .next_call(matching!(
-                    UserId(_),
-                    "slug",
-                    repo::ArticleUpdate {
-                        slug: Some("new-title"),
-                        title: Some("New Title"),
-                        description: Some("New desc"),
-                        body: Some("New body")
-                    }
-                ))
-                .returns(Ok(())),
-            ArticleRepoMock::select_articles
-                .next_call(matching!(
-                    UserId(Some(_)),
-                    repo::Filter {
-                        slug: Some("new-title"),
-                        ..
-                    }
-                ))
-                .returns(Ok(vec![test_db_article()])),
+            mock_tag_rules(),
+            ArticleRepoMock::insert_article
+                .next_call(matching!(_, _, _, _, "<b>Body</b> alert(1)", _, _))
+                .returns(Ok(test_db_article())),
+            mock_invalidate_all_feeds(),
         ));
-        api::update_article(
+        api::create_article(
             &deps,
             Token::from_token("token"),
-            "slug",
-            ArticleUpdate {
-                title: Some("New Title".to_string()),
-                description: Some("New desc".to_string()),
-                body: Some("New body".to_string()),
+            ArticleCreate {
+                title: "Title".to_string(),
+                description: "Desc".to_string(),
+                body: "<b onclick=\"x()\">Body</b> <script>alert(1)</script>".to_string(),
+                tag_list: vec![],
+                visibility: Visibility::Public,
             },
         )
         .await
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn create_article_with_too_large_body_should_fail() {
+        let deps = Unimock::new((mock_authenticate(), mock_max_article_body_bytes()));
+
+        assert_matches!(
+            api::create_article(
+                &deps,
+                Token::from_token("token"),
+                ArticleCreate {
+                    title: "title".to_string(),
+                    description: "desc".to_string(),
+                    body: "x".repeat(1025),
+                    tag_list: vec![],
+                    visibility: Visibility::Public,
+                },
+            )
+            .await,
+            Err(RwError::ArticleBodyTooLarge { max_bytes: 1024 })
+        );
+    }
+
+    #[tokio::test]
+    async fn get_article_empty_result_should_produce_not_found_error() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(None),
+                        repo::Filter {
+                            slug: Some(Slug(slug)),
+                            ..
+                        }
+                    ) if slug == "slug"
+                })
+                .returns(Ok(vec![])),
+        ));
+        assert_matches!(
+            api::fetch_article(
+                &deps,
+                Token::none(),
+                &Slug::from("slug"),
+                Default::default(),
+                None
+            )
+            .await,
+            Err(RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_article_as_html_should_render_body() {
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .answers(&|_, _, _| Ok(vec![test_db_article()])),
+            markdown::RenderMarkdownMock::render_markdown
+                .next_call(matching!(_))
+                .answers(&|_, body| format!("<p>{body}</p>")),
+        ));
+
+        let article = api::fetch_article(
+            &deps,
+            Token::none(),
+            &Slug::from("slug"),
+            FetchArticleQuery {
+                format: BodyFormat::Html,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            Some(format!("<p>{}</p>", test_db_article().body)),
+            article.body
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_article_should_record_view_by_ip_address_when_anonymous() {
+        let ip_address = IpAddr::from([10, 0, 0, 1]);
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            crate::test::mock_current_time(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .answers(&|_, _, _| Ok(vec![test_db_article()])),
+            views::RecordViewMock::record_view
+                .next_call(matching!(ArticleView {
+                    viewer: Viewer::Ip(_),
+                    ..
+                }))
+                .returns(()),
+        ));
+
+        api::fetch_article(
+            &deps,
+            Token::none(),
+            &Slug::from("slug"),
+            Default::default(),
+            Some(ip_address),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fetch_article_by_id_should_fetch_by_current_slug() {
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_words_per_minute(),
+            ArticleRepoMock::find_article_slug
+                .next_call(matching!((id) if *id == ArticleId(uuid::Uuid::from_u128(1))))
+                .returns(Ok(Some(Slug::from("new-slug")))),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        _,
+                        repo::Filter {
+                            slug: Some(Slug(slug)),
+                            ..
+                        }
+                    ) if slug == "new-slug"
+                })
+                .answers(&|_, _, _| Ok(vec![test_db_article()])),
+        ));
+
+        let id = public_id::encode(ArticleId(uuid::Uuid::from_u128(1)));
+        let article = api::fetch_article_by_id(&deps, Token::none(), &id, Default::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(id, article.id);
+
+        assert_matches!(
+            api::fetch_article_by_id(&deps, Token::none(), "invalid", Default::default(), None)
+                .await,
+            Err(RwError::ArticleNotFound)
+        );
+    }
+
+    fn test_unlisted_db_article() -> repo::Article {
+        repo::Article {
+            share_token: Some("share-token".to_string()),
+            ..test_db_article()
+        }
+    }
+
+    #[tokio::test]
+    async fn unlisted_article_should_not_be_fetched_by_slug_by_others() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .answers(&|_, _, _| Ok(vec![test_unlisted_db_article()])),
+        ));
+
+        assert_matches!(
+            api::fetch_article(
+                &deps,
+                Token::none(),
+                &Slug::from("slug"),
+                Default::default(),
+                None
+            )
+            .await,
+            Err(RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn shared_article_should_be_fetched_by_its_token_without_showing_it() {
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(None),
+                        repo::Filter {
+                            slug: None,
+                            share_token: Some("share-token"),
+                            ..
+                        }
+                    )
+                })
+                .answers(&|_, _, _| Ok(vec![test_unlisted_db_article()])),
+        ));
+
+        let article = api::fetch_shared_article(
+            &deps,
+            Token::none(),
+            "share-token",
+            Default::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(Visibility::Unlisted, article.visibility);
+        assert_eq!(None, article.share_token);
+    }
+
+    #[test]
+    fn share_token_should_only_be_shown_to_the_author() {
+        let author_id = UserId(Some(Uuid::from_u128(1)));
+        let other_id = UserId(Some(Uuid::from_u128(2)));
+        let from_db = |current_user| {
+            Article::from_db(test_unlisted_db_article(), test_author(), 200, current_user)
+        };
+
+        assert_eq!(
+            Some("share-token"),
+            from_db(author_id).share_token.as_deref()
+        );
+        assert_eq!(None, from_db(other_id).share_token);
+        assert_eq!(Visibility::Unlisted, from_db(other_id).visibility);
+    }
+
+    #[tokio::test]
+    async fn unlisted_article_should_be_created_with_a_share_token() {
+        let deps = Unimock::new((
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            crate::test::mock_slug_strategy(),
+            crate::test::mock_publish_events(),
+            mock_authenticate(),
+            mock_tag_rules(),
+            ArticleRepoMock::insert_article
+                .next_call(matching!((_, _, _, _, _, _, Some(token)) if token.len() == 64))
+                .returns(Ok(test_unlisted_db_article())),
+            mock_invalidate_all_feeds(),
+        ));
+        api::create_article(
+            &deps,
+            Token::from_token("token"),
+            ArticleCreate {
+                title: "Title".to_string(),
+                description: "Desc".to_string(),
+                body: "Body".to_string(),
+                tag_list: vec![],
+                visibility: Visibility::Unlisted,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_article_should_update_slug() {
+        let deps = Unimock::new((
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            crate::test::mock_slug_strategy(),
+            crate::test::mock_publish_events(),
+            mock_authenticate(),
+            ArticleRepoMock::update_article
+                .next_call(matching! {
+                    (
+                        UserId(_),
+                        "slug",
+                        repo::ArticleUpdate {
+                            slug: Some(Slug(slug)),
+                            title: Some("New Title"),
+                            description: Some("New desc"),
+                            body: Some("New body"),
+                            tag_list: None,
+                            share_token: None,
+                            expected_updated_at: None,
+                        }
+                    ) if slug == "new-title"
+                })
+                .returns(Ok(())),
+            mock_invalidate_all_feeds(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(Some(_)),
+                        repo::Filter {
+                            slug: Some(Slug(slug)),
+                            ..
+                        }
+                    ) if slug == "new-title"
+                })
+                .returns(Ok(vec![test_db_article()])),
+        ));
+        api::update_article(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            ArticleUpdate {
+                title: Some("New Title".to_string()),
+                description: Some("New desc".to_string()),
+                body: Some("New body".to_string()),
+                tag_list: None,
+                visibility: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_article_should_replace_tags() {
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_publish_events(),
+            mock_authenticate(),
+            mock_tag_rules(),
+            ArticleRepoMock::update_article
+                .next_call(matching!(
+                    (_, "slug", up) if up.tag_list == Some(&["dragons".to_string()][..])
+                        && up.title.is_none()
+                ))
+                .returns(Ok(())),
+            mock_invalidate_all_feeds(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .returns(Ok(vec![test_db_article()])),
+        ));
+        api::update_article(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            ArticleUpdate {
+                title: None,
+                description: None,
+                body: None,
+                tag_list: Some(vec!["Dragons ".to_string(), "dragons".to_string()]),
+                visibility: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn reading_time_should_be_computed_from_body() {
+        let article = Article::from_db(
+            repo::Article {
+                body: "word ".repeat(450),
+                ..test_db_article()
+            },
+            test_author(),
+            200,
+            UserId(None),
+        );
+
+        assert_eq!(Some(450), article.word_count);
+        assert_eq!(Some(3), article.reading_time_minutes);
+    }
+
+    #[test]
+    fn etag_should_change_when_article_is_updated() {
+        let article = Article::from_db(test_db_article(), test_author(), 200, UserId(None));
+        let mut updated = article.clone();
+        updated.updated_at = Timestamptz(time::OffsetDateTime::now_utc());
+
+        assert_eq!(
+            article.etag(),
+            Article::from_db(test_db_article(), test_author(), 200, UserId(None)).etag()
+        );
+        assert_ne!(article.etag(), updated.etag());
+    }
+
+    #[tokio::test]
+    async fn update_article_with_stale_etag_should_fail() {
+        let deps = Unimock::new((
+            mock_max_article_body_bytes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            crate::test::mock_sanitize_mode(),
+            mock_authenticate(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(Some(_)),
+                        repo::Filter {
+                            slug: Some(Slug(slug)),
+                            ..
+                        }
+                    ) if slug == "slug"
+                })
+                .returns(Ok(vec![test_db_article()])),
+        ));
+        let stale = headers::IfMatch::from("\"stale\"".parse::<headers::ETag>().unwrap());
+
+        assert_matches!(
+            api::update_article(
+                &deps,
+                Token::from_token("token"),
+                &Slug::from("slug"),
+                ArticleUpdate {
+                    title: None,
+                    description: None,
+                    body: Some("New body".to_string()),
+                    tag_list: None,
+                    visibility: None,
+                },
+                Some(stale),
+            )
+            .await,
+            Err(RwError::PreconditionFailed)
+        );
+    }
+
+    #[tokio::test]
+    async fn admin_should_delete_any_article() {
+        let deps = Unimock::new((
+            crate::test::mock_publish_events(),
+            AuthorizeRoleMock::authenticate_with_role
+                .next_call(matching!(_))
+                .returns(Ok((UserId(Uuid::new_v4()), Role::Admin))),
+            ArticleRepoMock::delete_any_article
+                .next_call(matching!("slug"))
+                .returns(Ok(())),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::ArticleDelete,
+                    target: Some("slug"),
+                    ..
+                }))
+                .returns(Ok(())),
+            mock_invalidate_all_feeds(),
+        ));
+
+        api::delete_article(&deps, Token::from_token("token"), &Slug::from("slug"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn user_should_only_delete_own_article() {
+        let deps = Unimock::new((
+            AuthorizeRoleMock::authenticate_with_role
+                .next_call(matching!(_))
+                .returns(Ok((UserId(Uuid::new_v4()), Role::Moderator))),
+            ArticleRepoMock::delete_article
+                .next_call(matching!(_, "slug"))
+                .returns(Err(RwError::Forbidden)),
+        ));
+
+        assert_matches!(
+            api::delete_article(&deps, Token::from_token("token"), &Slug::from("slug")).await,
+            Err(RwError::Forbidden)
+        );
+    }
+
+    #[tokio::test]
+    async fn favorites_batch_should_report_unknown_slugs_as_not_found() {
+        let deps = Unimock::new((
+            crate::test::mock_publish_events(),
+            mock_authenticate(),
+            ArticleRepoMock::set_favorites_bulk
+                .next_call(matching!(_, _, false))
+                .returns(Ok(vec![Slug::from("a")])),
+            mock_invalidate_all_feeds(),
+        ));
+
+        let results = api::set_favorites(
+            &deps,
+            Token::from_token("token"),
+            FavoritesBatch {
+                slugs: vec![Slug::from("a"), Slug::from("unknown")],
+                favorited: false,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![
+                ("a", FavoriteStatus::Unfavorited),
+                ("unknown", FavoriteStatus::NotFound)
+            ],
+            results
+                .iter()
+                .map(|result| (result.slug.as_str(), result.status))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_favorites_batch_should_be_rejected() {
+        let deps = Unimock::new(());
+
+        assert_matches!(
+            api::set_favorites(
+                &deps,
+                Token::from_token("token"),
+                FavoritesBatch {
+                    slugs: vec![Slug::from("slug"); MAX_FAVORITES_BATCH + 1],
+                    favorited: true,
+                },
+            )
+            .await,
+            Err(RwError::BatchTooLarge { field: "slugs", .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn feed_should_be_served_from_cache() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_authenticate(),
+            FeedCacheMock::get_cached_feed
+                .next_call(matching!(
+                    _,
+                    FeedPage {
+                        limit: Some(5),
+                        offset: None
+                    }
+                ))
+                .returns(Some(vec![Article::from_db(
+                    test_db_article(),
+                    test_author(),
+                    200,
+                    UserId(None),
+                )])),
+        ));
+
+        let list = api::feed_articles(
+            &deps,
+            Token::from_token("token"),
+            FeedArticlesQuery {
+                limit: Some(5),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, list.articles.len());
+        assert_eq!(None, list.next_cursor);
+    }
+
+    #[tokio::test]
+    async fn feed_should_be_cached_on_miss() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate(),
+            FeedCacheMock::get_cached_feed
+                .next_call(matching!(_, _))
+                .returns(None),
+            crate::GetConfigMock::materialized_feeds
+                .each_call(matching!())
+                .returns(false),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    UserId(Some(_)),
+                    repo::Filter {
+                        followed_by: Some(_),
+                        in_feed_of: None,
+                        ..
+                    }
+                ))
+                .returns(Ok(vec![test_db_article()])),
+            FeedCacheMock::cache_feed
+                .next_call(matching! {
+                    (_, FeedPage { limit: Some(repo::DEFAULT_LIMIT), offset: None }, articles) if articles.len() == 1
+                })
+                .returns(()),
+        ));
+
+        api::feed_articles(&deps, Token::from_token("token"), Default::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn feed_after_cursor_should_not_be_cached() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate(),
+            crate::GetConfigMock::materialized_feeds
+                .each_call(matching!())
+                .returns(false),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(Some(_)),
+                        repo::Filter {
+                            limit: Some(1),
+                            after: Some(ArticleCursor { slug, .. }),
+                            ..
+                        }
+                    ) if slug.as_str() == "previous"
+                })
+                .returns(Ok(vec![test_db_article()])),
+        ));
+
+        let list = api::feed_articles(
+            &deps,
+            Token::from_token("token"),
+            FeedArticlesQuery {
+                limit: Some(1),
+                after: Some(ArticleCursor {
+                    created_at: test_timestamp(),
+                    slug: Slug::from("previous"),
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            Some(ArticleCursor {
+                created_at: test_timestamp(),
+                slug: Slug::from("slug"),
+            }),
+            list.next_cursor
+        );
+    }
+
+    #[tokio::test]
+    async fn materialized_feed_should_not_join_follows() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate(),
+            FeedCacheMock::get_cached_feed
+                .next_call(matching!(_, _))
+                .returns(None),
+            crate::GetConfigMock::materialized_feeds
+                .each_call(matching!())
+                .returns(true),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    UserId(Some(_)),
+                    repo::Filter {
+                        followed_by: None,
+                        in_feed_of: Some(_),
+                        ..
+                    }
+                ))
+                .returns(Ok(vec![test_db_article()])),
+            FeedCacheMock::cache_feed
+                .next_call(matching!(_, _, _))
+                .returns(()),
+        ));
+
+        api::feed_articles(&deps, Token::from_token("token"), Default::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn summaries_should_be_selected_without_body() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, repo::Filter { summary: true, .. }))
+                .answers(&|_, _, _| {
+                    Ok(vec![repo::Article {
+                        body: String::new(),
+                        ..test_db_article()
+                    }])
+                }),
+        ));
+
+        let list = api::list_articles(
+            &deps,
+            None,
+            ListArticlesQuery {
+                summary: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let article = &list.articles[0];
+        assert_eq!("desc", article.description);
+        assert_eq!(None, article.body);
+        assert_eq!(None, article.word_count);
+    }
+
+    #[tokio::test]
+    async fn full_page_should_have_next_cursor() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        UserId(None),
+                        repo::Filter {
+                            limit: Some(1),
+                            after: Some(ArticleCursor { slug, .. }),
+                            ..
+                        }
+                    ) if slug.as_str() == "previous"
+                })
+                .returns(Ok(vec![test_db_article()])),
+        ));
+
+        let list = api::list_articles(
+            &deps,
+            None,
+            ListArticlesQuery {
+                limit: Some(1),
+                after: Some(ArticleCursor {
+                    created_at: test_timestamp(),
+                    slug: Slug::from("previous"),
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(1, list.articles.len());
+        assert_eq!(
+            Some(ArticleCursor {
+                created_at: test_timestamp(),
+                slug: Slug::from("slug"),
+            }),
+            list.next_cursor
+        );
+    }
+
+    #[tokio::test]
+    async fn short_page_should_be_the_last() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_words_per_minute(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    UserId(None),
+                    repo::Filter {
+                        limit: Some(repo::DEFAULT_LIMIT),
+                        after: None,
+                        ..
+                    }
+                ))
+                .returns(Ok(vec![test_db_article()])),
+        ));
+
+        let list = api::list_articles(&deps, None, Default::default())
+            .await
+            .unwrap();
+
+        assert_eq!(None, list.next_cursor);
+    }
+
+    #[tokio::test]
+    async fn oversized_limit_should_be_lowered_to_max() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            crate::GetConfigMock::page_sizes
+                .each_call(matching!())
+                .returns(PageSizes {
+                    default: 20,
+                    max: 50,
+                    max_offset: None,
+                }),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    _,
+                    repo::Filter {
+                        limit: Some(50),
+                        ..
+                    }
+                ))
+                .returns(Ok(vec![])),
+        ));
+
+        let query = ListArticlesQuery {
+            limit: Some(100_000),
+            ..Default::default()
+        };
+        api::list_articles(&deps, None, query).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn non_positive_limit_should_be_invalid() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            crate::test::mock_page_sizes(),
+        ));
+
+        let query = ListArticlesQuery {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert_matches!(
+            api::list_articles(&deps, None, query).await,
+            Err(RwError::InvalidPagination { field: "limit", .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn tag_filters_should_be_split_on_commas() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    (_, filter) if filter.any_tag == ["a", "b"]
+                        && filter.all_tags == ["c"]
+                        && filter.excluded_tags == ["d", "e"]
+                ))
+                .returns(Ok(vec![])),
+        ));
+        let query = serde_json::from_value(serde_json::json!({
+            "tag": "a,b",
+            "tag_all": "c,",
+            "exclude_tag": "d, e",
+        }))
+        .unwrap();
+
+        api::list_articles(&deps, None, query).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn favoriters_of_unknown_article_should_not_be_found() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .answers(&|_, _, _| Ok(vec![])),
+        ));
+
+        assert_matches!(
+            api::list_favoriters(&deps, None, &Slug::from("unknown"), Default::default()).await,
+            Err(RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn favoriters_should_be_listed_as_profiles() {
+        use crate::user::repo::{Following, User};
+
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            crate::test::mock_visible_article(),
+            ArticleRepoMock::list_favoriting_users
+                .next_call(matching!(
+                    UserId(None),
+                    "slug",
+                    repo::Pagination {
+                        limit: Some(5),
+                        offset: None
+                    }
+                ))
+                .answers(&|_, _, _, _| {
+                    Ok(vec![(
+                        User {
+                            user_id: UserId(Uuid::new_v4()),
+                            username: "fan".to_string(),
+                            bio: "bio".to_string(),
+                            image: None,
+                            role: Default::default(),
+                        },
+                        Following(true),
+                    )])
+                }),
+        ));
+
+        let profiles = api::list_favoriters(
+            &deps,
+            None,
+            &Slug::from("slug"),
+            repo::Pagination {
+                limit: Some(5),
+                offset: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(1, profiles.len());
+        assert_eq!("fan", profiles[0].username);
+        assert!(profiles[0].following);
+    }
+
+    #[tokio::test]
+    async fn trending_articles_should_be_since_trending_window() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            mock_authenticate_anonymous(),
+            crate::test::mock_current_time(),
+            mock_words_per_minute(),
+            crate::GetConfigMock::trending_window
+                .each_call(matching!())
+                .returns(time::Duration::hours(24)),
+            ArticleRepoMock::select_trending_articles
+                .next_call(matching!(
+                    (UserId(None), since, _) if since.unix_timestamp() == -24 * 3600
+                ))
+                .answers(&|_, _, _, _| Ok(vec![test_db_article()])),
+        ));
+
+        let articles = api::list_trending_articles(&deps, None, Default::default())
+            .await
+            .unwrap();
+
+        assert_eq!(1, articles.len());
+        assert_eq!("slug", articles[0].slug.as_str());
+    }
+
+    #[tokio::test]
+    async fn others_should_not_favorite_or_bookmark_unlisted_article() {
+        for bookmark in [false, true] {
+            let deps = Unimock::new((mock_authenticate(), crate::test::mock_unlisted_article()));
+            let slug = Slug::from("slug");
+            let token = Token::from_token("token");
+
+            let result = if bookmark {
+                api::bookmark_article(&deps, token, &slug, true).await
+            } else {
+                api::favorite_article(&deps, token, &slug, true).await
+            };
+            assert_matches!(result, Err(RwError::ArticleNotFound));
+        }
+    }
+
+    #[tokio::test]
+    async fn favoriters_of_unlisted_article_should_only_be_listed_for_its_author() {
+        let deps = Unimock::new((
+            mock_authenticate_anonymous(),
+            crate::test::mock_unlisted_article(),
+        ));
+        assert_matches!(
+            api::list_favoriters(&deps, None, &Slug::from("slug"), Default::default()).await,
+            Err(RwError::ArticleNotFound)
+        );
+
+        let deps = Unimock::new((
+            AuthenticateMock::opt_authenticate
+                .next_call(matching!(Some(_)))
+                .returns(Ok(UserId(Some(Uuid::from_u128(1))))),
+            crate::test::mock_unlisted_article(),
+            ArticleRepoMock::list_favoriting_users
+                .next_call(matching!(_, "slug", _))
+                .answers(&|_, _, _, _| Ok(vec![])),
+        ));
+        assert!(api::list_favoriters(
+            &deps,
+            Some(Token::from_token("token")),
+            &Slug::from("slug"),
+            Default::default()
+        )
+        .await
+        .unwrap()
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn unbookmark_should_delete_bookmark() {
+        let deps = Unimock::new((
+            mock_load_authors(),
+            mock_authenticate(),
+            mock_words_per_minute(),
+            crate::test::mock_visible_article(),
+            repo::BookmarkRepoMock::delete_bookmark
+                .next_call(matching!(_, "slug"))
+                .returns(Ok(())),
+            ArticleRepoMock::select_articles
+                .next_call(matching! {
+                    (
+                        _,
+                        repo::Filter {
+                            slug: Some(Slug(slug)),
+                            ..
+                        }
+                    ) if slug == "slug"
+                })
+                .answers(&|_, _, _| Ok(vec![test_db_article()])),
+        ));
+
+        let article = api::bookmark_article(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!("slug", article.slug.as_str());
+    }
+
+    #[tokio::test]
+    async fn bookmarked_articles_should_be_of_current_user() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            mock_load_authors(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::from_u128(1)))),
+            mock_words_per_minute(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    (_, repo::Filter { bookmarked_by: Some(UserId(id)), .. }) if id.as_u128() == 1
+                ))
+                .answers(&|_, _, _| Ok(vec![test_db_article()])),
+        ));
+
+        let articles =
+            api::list_bookmarked_articles(&deps, Token::from_token("token"), Default::default())
+                .await
+                .unwrap();
+        assert_eq!(1, articles.len());
+    }
 }
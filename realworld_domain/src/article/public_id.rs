@@ -0,0 +1,70 @@
+//!
+//! Public article ids, which unlike slugs don't change when the title is edited.
+//!
+//! The id is the article's UUID in base62, always 22 characters long.
+//!
+
+use super::ArticleId;
+
+const ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Enough base62 digits for any 128 bit number
+const LENGTH: usize = 22;
+
+pub fn encode(ArticleId(article_id): ArticleId) -> String {
+    let mut number = article_id.as_u128();
+    let mut digits = [b'0'; LENGTH];
+    for digit in digits.iter_mut().rev() {
+        *digit = ALPHABET[(number % 62) as usize];
+        number /= 62;
+    }
+
+    digits.into_iter().map(char::from).collect()
+}
+
+/// `None` when the id isn't a valid public id
+pub fn decode(public_id: &str) -> Option<ArticleId> {
+    if public_id.len() != LENGTH {
+        return None;
+    }
+
+    public_id
+        .bytes()
+        .try_fold(0_u128, |number, byte| {
+            let value = ALPHABET.iter().position(|digit| *digit == byte)?;
+            number.checked_mul(62)?.checked_add(value as u128)
+        })
+        .map(|number| ArticleId(uuid::Uuid::from_u128(number)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_ids_should_round_trip() {
+        for article_id in [
+            uuid::Uuid::nil(),
+            uuid::Uuid::max(),
+            uuid::Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef),
+        ]
+        .map(ArticleId)
+        {
+            let public_id = encode(article_id);
+            assert_eq!(LENGTH, public_id.len());
+            assert_eq!(Some(article_id), decode(&public_id));
+        }
+        assert_eq!(
+            "0000000000000000000001",
+            encode(ArticleId(uuid::Uuid::from_u128(1)))
+        );
+    }
+
+    #[test]
+    fn invalid_public_ids_should_not_decode() {
+        assert_eq!(None, decode("short"));
+        assert_eq!(None, decode("000000000000000000000-"));
+        // Larger than 128 bits
+        assert_eq!(None, decode("zzzzzzzzzzzzzzzzzzzzzz"));
+    }
+}
@@ -0,0 +1,76 @@
+//!
+//! How long an article is, for readers deciding whether to read it now.
+//!
+
+pub const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+
+///
+/// The number of words in `text`.
+///
+/// Han characters and kana count as one word each, since those scripts don't separate words with spaces.
+/// Punctuation within a word, like in `don't`, doesn't split it.
+///
+pub fn word_count(text: &str) -> u32 {
+    let mut count = 0;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                count += 1;
+                in_word = true;
+            }
+        } else if c.is_whitespace() {
+            in_word = false;
+        }
+    }
+
+    count
+}
+
+/// Whole minutes, rounded up, so that only empty texts take no time
+pub fn reading_time_minutes(word_count: u32, words_per_minute: u32) -> u32 {
+    word_count.div_ceil(words_per_minute.max(1))
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2a6df}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_should_be_separated_by_whitespace() {
+        assert_eq!(0, word_count(""));
+        assert_eq!(0, word_count(" — # "));
+        assert_eq!(4, word_count("Don't   panic,\nit's fine."));
+        assert_eq!(3, word_count("naïve café über"));
+        assert_eq!(2, word_count("Привет мир"));
+    }
+
+    #[test]
+    fn cjk_characters_should_be_words() {
+        assert_eq!(5, word_count("日本語です"));
+        assert_eq!(3, word_count("Hello, 世界!"));
+        assert_eq!(2, word_count("안녕하세요 세계"));
+    }
+
+    #[test]
+    fn reading_time_should_round_up() {
+        assert_eq!(0, reading_time_minutes(0, 200));
+        assert_eq!(1, reading_time_minutes(1, 200));
+        assert_eq!(1, reading_time_minutes(200, 200));
+        assert_eq!(2, reading_time_minutes(201, 200));
+        assert_eq!(5, reading_time_minutes(5, 0));
+    }
+}
@@ -1,48 +1,93 @@
-use super::UserId;
+use super::cursor::ArticleCursor;
+use super::{ArticleId, Slug, UserId};
+use crate::user::repo::{Following, User};
 use crate::{error::RwResult, timestamp::Timestamptz};
 
 use entrait::entrait_export as entrait;
+use futures::stream::BoxStream;
+use std::net::IpAddr;
 
 #[derive(Eq, PartialEq, Debug)]
 pub struct Article {
-    pub slug: String,
+    pub article_id: ArticleId,
+    pub slug: Slug,
     pub title: String,
+    /// Shortened in summaries, see [Filter::summary]
     pub description: String,
+    /// Empty in summaries
     pub body: String,
     pub tag_list: Vec<String>,
     pub created_at: Timestamptz,
     pub updated_at: Timestamptz,
     pub favorited: bool,
     pub favorites_count: i64,
+    pub views_count: i64,
+    /// The rest of the author's profile is loaded with a [crate::user::profile::ProfileLoader]
+    pub author_id: uuid::Uuid,
     pub author_username: String,
-    pub author_bio: String,
-    pub author_image: Option<String>,
-    // This was originally `author_following` to match other fields but that's kind of confusing.
-    // That made it sound like a flag showing if the author is following the current user
-    // but the intent is the other way round.
-    pub following_author: bool,
+    /// The token that shares an unlisted article, `None` if it's public
+    pub share_token: Option<String>,
 }
 
-#[derive(Default)]
+pub use crate::pagination::Pagination;
+/// Page size when a [Filter] has no `limit`
+pub use crate::pagination::DEFAULT_LIMIT;
+
+#[derive(Clone, Default)]
 pub struct Filter<'a> {
-    pub slug: Option<&'a str>,
-    pub tag: Option<&'a str>,
+    pub slug: Option<&'a Slug>,
+    /// The unlisted article shared with this token.
+    /// Otherwise unlisted articles are only found by their slug, and never listed.
+    pub share_token: Option<&'a str>,
+    /// Only articles with at least one of these tags, unless empty
+    pub any_tag: &'a [String],
+    /// Only articles with all of these tags
+    pub all_tags: &'a [String],
+    /// Only articles with none of these tags
+    pub excluded_tags: &'a [String],
     pub author: Option<&'a str>,
     pub favorited_by: Option<&'a str>,
     pub followed_by: Option<UserId>,
+    /// Only articles in the materialized feed of this user, see [super::feed]
+    pub in_feed_of: Option<UserId>,
+    /// Only articles bookmarked by this user
+    pub bookmarked_by: Option<UserId>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Only articles that come after this one, newest first
+    pub after: Option<&'a ArticleCursor>,
+    /// Select summaries, without the body, and with the description cut
+    /// to [SUMMARY_DESCRIPTION_LENGTH] characters
+    pub summary: bool,
 }
 
-#[derive(Default)]
+/// Characters of the description of a summary, including the `…` ending a cut one
+pub const SUMMARY_DESCRIPTION_LENGTH: i32 = 200;
+
+#[derive(Clone, Default)]
 pub struct ArticleUpdate<'a> {
-    pub slug: Option<&'a str>,
+    pub slug: Option<&'a Slug>,
     pub title: Option<&'a str>,
     pub description: Option<&'a str>,
     pub body: Option<&'a str>,
+    pub tag_list: Option<&'a [String]>,
+    /// `Some(Some(token))` unlists a public article, sharing it with `token`, while an unlisted one keeps its token.
+    /// `Some(None)` makes the article public.
+    pub share_token: Option<Option<&'a str>>,
+    /// Only update if the article hasn't been updated since this time.
+    /// Otherwise fails with [crate::error::RwError::PreconditionFailed].
+    pub expected_updated_at: Option<&'a Timestamptz>,
 }
 
-#[entrait(ArticleRepoImpl, delegate_by=DelegateArticleRepo, mock_api=ArticleRepoMock)]
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(ArticleRepoImpl, delegate_by=DelegateArticleRepo, mock_api=ArticleRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(ArticleRepoImpl, delegate_by=ref, mock_api=ArticleRepoMock),
+    async_trait::async_trait
+)]
 pub trait ArticleRepo {
     async fn select_articles(
         &self,
@@ -50,28 +95,144 @@ pub trait ArticleRepo {
         filter: Filter<'_>,
     ) -> RwResult<Vec<Article>>;
 
-    async fn fetch_article_id(&self, slug: &str) -> RwResult<uuid::Uuid>;
+    async fn fetch_article_id(&self, slug: &Slug) -> RwResult<ArticleId>;
+
+    /// The current slug of the article, if it exists
+    async fn find_article_slug(&self, article_id: ArticleId) -> RwResult<Option<Slug>>;
+
+    /// The current slug of the article that most recently had `previous_slug`.
+    /// Slugs are recorded when [ArticleRepo::update_article] changes them.
+    async fn find_renamed_article_slug(&self, previous_slug: &Slug) -> RwResult<Option<Slug>>;
 
+    #[allow(clippy::too_many_arguments)]
     async fn insert_article(
         &self,
         user_id: UserId,
-        slug: &str,
+        slug: &Slug,
         title: &str,
         description: &str,
         body: &str,
         tag_list: &[String],
+        share_token: Option<&str>,
     ) -> RwResult<Article>;
 
     async fn update_article(
         &self,
         user_id: UserId,
-        slug: &str,
+        slug: &Slug,
         up: ArticleUpdate<'_>,
     ) -> RwResult<()>;
 
-    async fn delete_article(&self, user_id: UserId, slug: &str) -> RwResult<()>;
+    async fn delete_article(&self, user_id: UserId, slug: &Slug) -> RwResult<()>;
+
+    /// Delete an article regardless of who wrote it
+    async fn delete_any_article(&self, slug: &Slug) -> RwResult<()>;
+
+    async fn insert_favorite(&self, user_id: UserId, slug: &Slug) -> RwResult<()>;
+
+    async fn delete_favorite(&self, user_id: UserId, slug: &Slug) -> RwResult<()>;
+
+    /// Favorite or unfavorite all the articles at once.
+    /// Returns the slugs of the articles that exist, the others are skipped.
+    async fn set_favorites_bulk(
+        &self,
+        user_id: UserId,
+        slugs: &[Slug],
+        favorited: bool,
+    ) -> RwResult<Vec<Slug>>;
+
+    /// The users who favorited an article, most recent favorite first.
+    /// `Following` is relative to the current user.
+    async fn list_favoriting_users(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        slug: &Slug,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>>;
+
+    /// Articles with the most favorites and comments since `since`, most active first.
+    /// Articles without any such activity are left out.
+    async fn select_trending_articles(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        since: time::OffsetDateTime,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>>;
+
+    /// Articles the user hasn't favorited, favorited by users who favorited the same articles as the user.
+    /// Ranked by how many favorites those users share with the user, summed over the users.
+    /// The user's own articles, and those of authors they blocked, are left out.
+    async fn select_recommended_articles(
+        &self,
+        current_user: UserId,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Article>>;
+
+    /// All articles by one author, oldest first, read lazily.
+    /// The current user is the author, so `favorited` is relative to them.
+    fn stream_articles_by_author(&self, author: UserId) -> BoxStream<'static, RwResult<Article>>;
+
+    /// The slug and last update of every article, oldest first, read lazily
+    fn stream_article_slugs(&self) -> BoxStream<'static, RwResult<(Slug, Timestamptz)>>;
+}
+
+/// Private "read later" bookmarks, as opposed to public favorites
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(BookmarkRepoImpl, delegate_by=DelegateBookmarkRepo, mock_api=BookmarkRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(BookmarkRepoImpl, delegate_by=ref, mock_api=BookmarkRepoMock),
+    async_trait::async_trait
+)]
+pub trait BookmarkRepo {
+    /// Bookmarking an article twice is not an error
+    async fn insert_bookmark(&self, user_id: UserId, slug: &Slug) -> RwResult<()>;
+
+    async fn delete_bookmark(&self, user_id: UserId, slug: &Slug) -> RwResult<()>;
+}
+
+/// Who viewed an article
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Viewer {
+    User(UserId),
+    /// Anonymous
+    Ip(IpAddr),
+}
+
+impl std::fmt::Display for Viewer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(UserId(user_id)) => write!(f, "user:{user_id}"),
+            Self::Ip(ip_address) => write!(f, "ip:{ip_address}"),
+        }
+    }
+}
+
+/// A view of an article, which counts once per viewer and day
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ArticleView {
+    pub slug: Slug,
+    pub viewer: Viewer,
+    pub day: time::Date,
+}
 
-    async fn insert_favorite(&self, user_id: UserId, slug: &str) -> RwResult<()>;
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(ViewRepoImpl, delegate_by=DelegateViewRepo, mock_api=ViewRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(ViewRepoImpl, delegate_by=ref, mock_api=ViewRepoMock),
+    async_trait::async_trait
+)]
+pub trait ViewRepo {
+    /// Count the views that haven't been counted already, returning how many were new.
+    /// Views of articles that no longer exist are ignored.
+    async fn insert_views(&self, views: &[ArticleView]) -> RwResult<u64>;
 
-    async fn delete_favorite(&self, user_id: UserId, slug: &str) -> RwResult<()>;
+    /// Forget who viewed articles before `day`, returning how many views were forgotten.
+    /// The counts are kept.
+    async fn delete_views_before(&self, day: time::Date) -> RwResult<u64>;
 }
@@ -0,0 +1,246 @@
+//!
+//! Slugs derived from the titles of articles, as configured by [SlugStrategy].
+//!
+//! Titles without a single letter or digit, e.g. only punctuation, would get an empty slug.
+//! They get a slug from a hash of the title instead, so that the same title gets the same slug.
+//! Slugs that would be taken for a route, like `feed` in `/articles/feed`, get a hash suffix the same way.
+//! Long titles are cut at a word to fit [MAX_SLUG_LENGTH].
+//!
+
+use super::Slug;
+
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+/// How the letters of a title end up in its slug
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SlugStrategy {
+    /// Spell letters beyond ASCII with ASCII letters, e.g. `Ærø` as `aero` and `Привет` as `privet`
+    #[default]
+    Transliterate,
+    /// Keep letters beyond ASCII, lowercased. Links percent-encode them.
+    Unicode,
+}
+
+/// Most characters of a slug
+pub const MAX_SLUG_LENGTH: usize = 100;
+
+/// Path segments after `/articles/` that are routes of their own, not slugs.
+/// Compared regardless of case.
+pub const RESERVED_SLUGS: &[&str] = &[
+    "bookmarked",
+    "favorites",
+    "feed",
+    "id",
+    "recommended",
+    "shared",
+    "trending",
+];
+
+/// Starts the slugs of titles without words, followed by a hash of the title
+const FALLBACK_PREFIX: &str = "article";
+
+/// Bytes of the hash in rewritten slugs, twice as many hex digits
+const HASH_BYTES: usize = 4;
+
+const QUOTE_CHARS: &[char] = &['\'', '"'];
+
+pub fn slugify(strategy: SlugStrategy, title: &str) -> Slug {
+    let slug = match strategy {
+        SlugStrategy::Transliterate => join_words(&deunicode::deunicode(title)),
+        SlugStrategy::Unicode => join_words(title),
+    };
+    if slug.is_empty() {
+        return Slug(with_hash(FALLBACK_PREFIX, title));
+    }
+    let slug = truncate(slug);
+    if is_reserved(&slug) {
+        return Slug(with_hash(&slug, title));
+    }
+    Slug(slug)
+}
+
+/// Whether the slug would be taken for a route, see [RESERVED_SLUGS]
+pub fn is_reserved(slug: &str) -> bool {
+    RESERVED_SLUGS
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(slug))
+}
+
+fn join_words(string: &str) -> String {
+    string
+        // Split on anything that isn't a word character or quotation mark.
+        // This has the effect of keeping contractions and possessives together.
+        .split(|c: char| !(QUOTE_CHARS.contains(&c) || c.is_alphanumeric()))
+        // If multiple non-word characters follow each other then we'll get empty substrings
+        // so we'll filter those out.
+        .filter(|s| !s.is_empty())
+        // Remove quotes from the substring, and make it lowercase
+        .map(|s| s.replace(QUOTE_CHARS, "").to_lowercase())
+        // A word of only quotes is empty now
+        .filter(|s| !s.is_empty())
+        .join("-")
+}
+
+/// Cut after the last whole word that fits [MAX_SLUG_LENGTH], or within a word longer than that
+fn truncate(slug: String) -> String {
+    let Some((cut, _)) = slug.char_indices().nth(MAX_SLUG_LENGTH) else {
+        return slug;
+    };
+    let head = &slug[..cut];
+    if slug[cut..].starts_with('-') {
+        return head.to_string();
+    }
+    match head.rfind('-') {
+        Some(last_dash) => head[..last_dash].to_string(),
+        None => head.to_string(),
+    }
+}
+
+/// `prefix` followed by a hash of the title
+fn with_hash(prefix: &str, title: &str) -> String {
+    use std::fmt::Write;
+
+    let digest = Sha256::digest(title.as_bytes());
+    digest[..HASH_BYTES]
+        .iter()
+        .fold(format!("{prefix}-"), |mut output, byte| {
+            let _ = write!(output, "{byte:02x}");
+            output
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transliterated(title: &str) -> String {
+        slugify(SlugStrategy::Transliterate, title).0
+    }
+
+    fn unicode(title: &str) -> String {
+        slugify(SlugStrategy::Unicode, title).0
+    }
+
+    #[test]
+    fn ascii_titles_should_be_the_same_for_both_strategies() {
+        for (title, slug) in [
+            ("My Title", "my-title"),
+            ("  Many   spaces  ", "many-spaces"),
+            ("Don't panic!", "dont-panic"),
+            ("The author's \"quoted\" words", "the-authors-quoted-words"),
+            ("Rust 2021: what's new?", "rust-2021-whats-new"),
+            ("a--b__c", "a-b-c"),
+        ] {
+            assert_eq!(slug, transliterated(title), "{title}");
+            assert_eq!(slug, unicode(title), "{title}");
+        }
+    }
+
+    #[test]
+    fn transliterated_slugs_should_be_ascii() {
+        for (title, slug) in [
+            ("Crème brûlée", "creme-brulee"),
+            ("Ærø og Østerbro", "aero-og-osterbro"),
+            ("Straße", "strasse"),
+            ("Привет, мир", "privet-mir"),
+            ("Καλημέρα", "kalemera"),
+            ("東京", "dong-jing"),
+            ("It’s typographic", "its-typographic"),
+        ] {
+            assert_eq!(slug, transliterated(title), "{title}");
+        }
+    }
+
+    #[test]
+    fn unicode_slugs_should_keep_letters_lowercased() {
+        assert_eq!("привет-мир", unicode("Привет, мир"));
+        assert_eq!("crème-brûlée", unicode("Crème Brûlée"));
+        assert_eq!("東京", unicode("東京"));
+    }
+
+    #[test]
+    fn titles_without_words_should_get_a_hashed_slug() {
+        for strategy in [SlugStrategy::Transliterate, SlugStrategy::Unicode] {
+            for title in ["", "!!!", "''", " - "] {
+                let slug = slugify(strategy, title).0;
+                let hash = slug.strip_prefix("article-").unwrap();
+
+                assert_eq!(8, hash.len(), "{title}");
+                assert!(hash.chars().all(|c| c.is_ascii_hexdigit()), "{title}");
+            }
+        }
+    }
+
+    #[test]
+    fn hashed_slugs_should_be_the_same_for_the_same_title() {
+        assert_eq!(transliterated("???"), transliterated("???"));
+        assert_ne!(transliterated("???"), transliterated("!!!"));
+        assert_eq!(transliterated("???"), unicode("???"));
+    }
+
+    #[test]
+    fn reserved_slugs_should_get_a_hash_suffix() {
+        for title in ["Feed", "feed", "FEED!", "Trending", "ID", "Shared"] {
+            for strategy in [SlugStrategy::Transliterate, SlugStrategy::Unicode] {
+                let slug = slugify(strategy, title).0;
+                let (word, hash) = slug.rsplit_once('-').unwrap();
+
+                assert!(is_reserved(word), "{title}");
+                assert_eq!(8, hash.len(), "{title}");
+                assert!(!is_reserved(&slug), "{title}");
+            }
+        }
+        // Only whole slugs are reserved
+        assert_eq!("my-feed", transliterated("My feed"));
+        assert_eq!("feed-me", transliterated("Feed me"));
+    }
+
+    #[test]
+    fn reserved_slugs_should_be_compared_regardless_of_case() {
+        assert!(is_reserved("feed"));
+        assert!(is_reserved("Feed"));
+        assert!(is_reserved("TRENDING"));
+        assert!(!is_reserved("feeds"));
+    }
+
+    #[test]
+    fn long_slugs_should_be_cut_at_a_word() {
+        let title = "word ".repeat(30);
+        let slug = transliterated(&title);
+
+        assert!(slug.len() <= MAX_SLUG_LENGTH);
+        assert!(slug.split('-').all(|word| word == "word"), "{slug}");
+        assert_eq!(20, slug.split('-').count());
+
+        let exact = format!("{}-{}", "a".repeat(49), "b".repeat(50));
+        assert_eq!(exact, transliterated(&format!("{exact} c")));
+    }
+
+    #[test]
+    fn long_words_should_be_cut_within_the_word() {
+        let slug = transliterated(&"x".repeat(150));
+        assert_eq!("x".repeat(MAX_SLUG_LENGTH), slug);
+
+        let slug = unicode(&"ø".repeat(150));
+        assert_eq!(MAX_SLUG_LENGTH, slug.chars().count());
+    }
+
+    #[test]
+    fn slugs_should_be_stable_when_slugified_again() {
+        let long_title = "word ".repeat(30);
+        for title in [
+            "Crème brûlée",
+            "Привет, мир",
+            "Don't panic!",
+            "!!!",
+            "Feed",
+            &long_title,
+        ] {
+            for strategy in [SlugStrategy::Transliterate, SlugStrategy::Unicode] {
+                let slug = slugify(strategy, title);
+                assert_eq!(slug, slugify(strategy, slug.as_str()), "{title}");
+            }
+        }
+    }
+}
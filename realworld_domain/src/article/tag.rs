@@ -0,0 +1,105 @@
+//!
+//! Tags are normalized before they're saved, so that e.g. `Rust` and ` rust ` end up as the same tag.
+//!
+
+use crate::error::{RwError, RwResult};
+
+pub const DEFAULT_MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Clone, Debug)]
+pub struct TagRules {
+    /// Longest allowed tag, in characters
+    max_length: usize,
+    /// Only these tags may be used, if any are given
+    allowed: Vec<String>,
+}
+
+impl Default for TagRules {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TAG_LENGTH, [])
+    }
+}
+
+impl TagRules {
+    pub fn new(max_length: usize, allowed: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            max_length,
+            allowed: allowed
+                .into_iter()
+                .map(|tag| normalize(&tag))
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+        }
+    }
+
+    ///
+    /// Trim and lowercase each tag, dropping empty and duplicate ones.
+    ///
+    /// Fails if a tag is too long, or isn't in the allow-list.
+    ///
+    pub fn normalize(&self, tags: &[String]) -> RwResult<Vec<String>> {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+
+        for tag in tags.iter().map(|tag| normalize(tag)) {
+            if tag.is_empty() || normalized.contains(&tag) {
+                continue;
+            }
+            if tag.chars().count() > self.max_length {
+                return Err(RwError::TagTooLong {
+                    tag,
+                    max_length: self.max_length,
+                });
+            }
+            if !self.allowed.is_empty() && !self.allowed.contains(&tag) {
+                return Err(RwError::TagNotAllowed(tag));
+            }
+            normalized.push(tag);
+        }
+
+        Ok(normalized)
+    }
+}
+
+fn normalize(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_matches::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn tags_should_be_trimmed_lowercased_and_deduplicated() {
+        assert_eq!(
+            tags(&["rust", "web dev"]),
+            TagRules::default()
+                .normalize(&tags(&[" Rust", "rust ", "", "  ", "Web Dev"]))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn too_long_tag_should_fail() {
+        assert_matches!(
+            TagRules::new(4, []).normalize(&tags(&["rust", "tokio"])),
+            Err(RwError::TagTooLong { tag, max_length: 4 }) if tag == "tokio"
+        );
+    }
+
+    #[test]
+    fn tag_outside_allow_list_should_fail() {
+        let rules = TagRules::new(DEFAULT_MAX_TAG_LENGTH, tags(&["Rust", "dragons"]));
+
+        assert_eq!(tags(&["rust"]), rules.normalize(&tags(&["RUST"])).unwrap());
+        assert_matches!(
+            rules.normalize(&tags(&["rust", "python"])),
+            Err(RwError::TagNotAllowed(tag)) if tag == "python"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use super::repo::ArticleView;
+
+use entrait::entrait_export as entrait;
+
+///
+/// Counts views of articles in batches, instead of writing to the database for every view.
+///
+/// Views that are recorded more than once before they are counted, count once.
+/// Views that are never counted, e.g. because of a restart, are lost.
+///
+#[entrait(mock_api=RecordViewMock)]
+pub trait RecordView {
+    fn record_view(&self, view: ArticleView);
+}
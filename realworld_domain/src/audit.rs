@@ -0,0 +1,128 @@
+//!
+//! The audit log: an append-only record of security-relevant actions, for admins to look into.
+//!
+//! Entries outlive the users they're about, so that the log stays complete.
+//!
+
+use crate::error::RwResult;
+use crate::pagination::Pagination;
+use crate::timestamp::Timestamptz;
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+use std::net::IpAddr;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(sqlx::Type, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[sqlx(type_name = "app.audit_action", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Logged in with email and password
+    Login,
+    /// Tried to log in with an unknown email address or a wrong password.
+    /// The target is the email address.
+    FailedLogin,
+    /// Changed password, or chose a new one after a password reset
+    PasswordChange,
+    /// Changed email address. The target is the previous address.
+    EmailChange,
+    /// Deleted an article, as its author or as a moderator. The target is the slug.
+    ArticleDelete,
+    /// An admin banned a user. The target is the username.
+    UserBan,
+    /// An admin unbanned a user. The target is the username.
+    UserUnban,
+    /// An admin deleted a comment. The target is `<slug>/<comment id>`.
+    CommentDelete,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewAuditEntry<'a> {
+    /// Who did it, when known
+    pub user_id: UserId<Option<Uuid>>,
+    pub action: AuditAction,
+    /// What it was done to
+    pub target: Option<&'a str>,
+    pub ip_address: Option<IpAddr>,
+}
+
+/// Which entries to list
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct AuditFilter {
+    pub user_id: Option<UserId>,
+    /// Inclusive
+    pub since: Option<OffsetDateTime>,
+    /// Exclusive
+    pub until: Option<OffsetDateTime>,
+    pub pagination: Pagination,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AuditRecord {
+    pub audit_id: i64,
+    pub user_id: Option<Uuid>,
+    /// `None` when the user is unknown or has been deleted
+    pub username: Option<String>,
+    pub action: AuditAction,
+    pub target: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(AuditLogImpl, delegate_by=DelegateAuditLog, mock_api=AuditLogMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(AuditLogImpl, delegate_by=ref, mock_api=AuditLogMock),
+    async_trait::async_trait
+)]
+pub trait AuditLog {
+    async fn record_audit(&self, entry: NewAuditEntry<'_>) -> RwResult<()>;
+
+    /// Newest first
+    async fn select_audit_entries(&self, filter: AuditFilter) -> RwResult<Vec<AuditRecord>>;
+}
+
+/// An entry of the audit log as seen by admins
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    id: i64,
+    username: Option<String>,
+    action: AuditAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ip_address: Option<String>,
+    created_at: Timestamptz,
+}
+
+impl From<AuditRecord> for AuditEntry {
+    fn from(db: AuditRecord) -> Self {
+        Self {
+            id: db.audit_id,
+            username: db.username,
+            action: db.action,
+            target: db.target,
+            ip_address: db.ip_address,
+            created_at: Timestamptz(db.created_at),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct AuditLogQuery {
+    /// Only entries of actions by this user
+    pub username: Option<String>,
+    /// Only entries from this time on
+    pub since: Option<Timestamptz>,
+    /// Only entries from before this time
+    pub until: Option<Timestamptz>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
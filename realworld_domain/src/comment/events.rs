@@ -0,0 +1,20 @@
+use super::Comment;
+use crate::article::Slug;
+
+use entrait::entrait_export as entrait;
+use futures::stream::BoxStream;
+
+///
+/// Live comments on articles, for clients that watch an article.
+///
+/// Delivery is best effort: comments published while nobody is watching are not kept,
+/// and a subscriber that falls behind may miss some.
+///
+#[entrait(mock_api=CommentEventsMock)]
+pub trait CommentEvents {
+    /// Tell everyone watching the article about a new comment
+    fn publish_comment(&self, article_slug: &Slug, comment: &Comment);
+
+    /// Comments posted on the article from now on
+    fn subscribe_comments(&self, article_slug: &Slug) -> BoxStream<'static, Comment>;
+}
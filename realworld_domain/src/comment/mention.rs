@@ -0,0 +1,73 @@
+//!
+//! `@username` mentions in comment bodies.
+//!
+//! A mention is an `@` at the start of the body or after a character that can't be part of a
+//! username, so that e-mail addresses aren't mentions, followed by the username.
+//! Mentions of users that don't exist are left as plain text.
+//!
+
+/// Mentions beyond this many in one comment are ignored, so that a comment can't notify everyone
+pub const MAX_MENTIONS: usize = 20;
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// The usernames mentioned in `body`, in order of their first mention
+pub fn parse_mentions(body: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = vec![];
+    let mut previous = None;
+
+    for (index, c) in body.char_indices() {
+        let mentions_here = c == '@' && !previous.is_some_and(is_username_char);
+        previous = Some(c);
+        if !mentions_here {
+            continue;
+        }
+
+        let rest = &body[index + 1..];
+        let end = rest.find(|c| !is_username_char(c)).unwrap_or(rest.len());
+        // A mention at the end of a sentence
+        let username = rest[..end].trim_end_matches('.');
+
+        if !username.is_empty() && !mentions.iter().any(|mention| mention == username) {
+            mentions.push(username.to_string());
+            if mentions.len() == MAX_MENTIONS {
+                break;
+            }
+        }
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_should_be_parsed_in_order() {
+        assert_eq!(
+            vec!["bob", "alice_1", "jo-el.k"],
+            parse_mentions("@bob, have you asked @alice_1? Or (@jo-el.k). Thanks @bob")
+        );
+    }
+
+    #[test]
+    fn mentions_need_a_username_and_no_word_before() {
+        assert!(parse_mentions("mail me at me@example.com, or @ me @. here").is_empty());
+        assert_eq!(vec!["bob"], parse_mentions("@@bob"));
+    }
+
+    #[test]
+    fn mentions_should_be_limited() {
+        let body = (0..MAX_MENTIONS + 5)
+            .map(|n| format!("@user{n}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mentions = parse_mentions(&body);
+        assert_eq!(MAX_MENTIONS, mentions.len());
+        assert_eq!("user0", mentions[0]);
+    }
+}
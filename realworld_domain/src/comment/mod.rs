@@ -1,80 +1,607 @@
+pub mod events;
+pub mod mention;
 pub mod repo;
 
 use crate::article::repo::ArticleRepo;
-use crate::error::RwResult;
+use crate::article::{fetch_visible_article_id, Slug};
+use crate::error::{RwError, RwResult};
+use crate::event::{DomainEvents, Event};
+use crate::iter_util::Single;
+use crate::pagination::Pagination;
+use crate::sanitize::sanitize;
 use crate::timestamp::Timestamptz;
 use crate::user::auth::Authenticate;
+use crate::user::auth::AuthorizeRole;
 use crate::user::auth::Token;
-use crate::user::profile::Profile;
+use crate::user::profile::{Profile, ProfileLoader};
+use crate::user::repo::UserRepo;
+use crate::user::UserId;
+use crate::GetConfig;
+use events::CommentEvents;
 use repo::CommentRepo;
 
 use entrait::entrait_export as entrait;
+use futures::stream::BoxStream;
+use uuid::Uuid;
 
-#[derive(serde::Serialize)]
+/// Comment bodies are limited to this many bytes, as posted
+pub const MAX_COMMENT_BODY_BYTES: usize = 16 * 1024;
+
+#[derive(
+    sqlx::Type, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash,
+)]
+#[sqlx(transparent)]
+#[serde(transparent)]
+pub struct CommentId(pub i64);
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Comment {
-    id: i64,
+    id: CommentId,
     created_at: Timestamptz,
     updated_at: Timestamptz,
     body: String,
     author: Profile,
+    likes_count: i64,
+    /// Whether the current user likes the comment
+    liked: bool,
+    #[serde(default)]
+    mentions: Vec<Mention>,
+}
+
+/// A user mentioned in the body of a comment with `@username`
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct Mention {
+    pub username: String,
 }
 
-impl From<repo::Comment> for Comment {
-    fn from(db: repo::Comment) -> Self {
+impl Comment {
+    fn from_db(db: repo::Comment, author: Profile) -> Self {
         Self {
             id: db.comment_id,
             created_at: Timestamptz(db.created_at),
             updated_at: Timestamptz(db.updated_at),
             body: db.body,
-            author: Profile {
-                username: db.author_username,
-                bio: db.author_bio,
-                image: db.author_image,
-                following: db.following_author,
-            },
+            author,
+            likes_count: db.likes_count,
+            liked: db.liked,
+            mentions: db
+                .mentions
+                .into_iter()
+                .map(|username| Mention { username })
+                .collect(),
         }
     }
 }
 
-#[entrait(pub Api)]
+/// Loads the profiles of all the authors with one query
+async fn load_authors(
+    deps: &impl UserRepo,
+    current_user: UserId<Option<Uuid>>,
+    comments: Vec<repo::Comment>,
+) -> RwResult<Vec<Comment>> {
+    let mut profiles = ProfileLoader::new(current_user);
+    profiles
+        .load(
+            deps,
+            comments.iter().map(|comment| UserId(comment.author_id)),
+        )
+        .await?;
+
+    comments
+        .into_iter()
+        .map(|comment| {
+            let author = profiles.get(UserId(comment.author_id))?;
+            Ok(Comment::from_db(comment, author))
+        })
+        .collect()
+}
+
+/// A page of the comments on an article
+pub struct CommentList {
+    pub comments: Vec<Comment>,
+    /// Comments on all pages
+    pub total_count: i64,
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct ListCommentsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    order: Option<repo::SortDirection>,
+}
+
+#[entrait(pub Api, mock_api=mock)]
 pub mod api {
     use super::*;
 
     pub async fn list_comments(
-        deps: &(impl Authenticate + ArticleRepo + CommentRepo),
+        deps: &(impl Authenticate + GetConfig + ArticleRepo + CommentRepo + UserRepo),
         token: Option<Token>,
-        slug: &str,
-    ) -> RwResult<Vec<Comment>> {
-        let current_user_id = deps.opt_authenticate(token)?;
-        let article_id = deps.fetch_article_id(slug).await?;
-        Ok(deps
-            .list_comments(current_user_id, article_id)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect())
+        slug: &Slug,
+        query: ListCommentsQuery,
+    ) -> RwResult<CommentList> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        let page = deps.page_sizes().apply(Pagination {
+            limit: query.limit,
+            offset: query.offset,
+        })?;
+        let article_id = fetch_visible_article_id(deps, current_user_id, slug).await?;
+        let comments = deps
+            .list_comments(
+                current_user_id,
+                article_id,
+                repo::ListOptions {
+                    limit: page.limit,
+                    offset: page.offset,
+                    direction: query.order.unwrap_or_default(),
+                },
+            )
+            .await?;
+        let total_count = deps.count_comments(current_user_id, article_id).await?;
+
+        Ok(CommentList {
+            comments: load_authors(deps, current_user_id, comments).await?,
+            total_count,
+        })
     }
 
     pub async fn add_comment(
-        deps: &(impl Authenticate + CommentRepo),
+        deps: &(impl Authenticate
+              + GetConfig
+              + ArticleRepo
+              + CommentRepo
+              + UserRepo
+              + CommentEvents
+              + DomainEvents),
         token: Token,
-        slug: &str,
+        slug: &Slug,
         body: &str,
     ) -> RwResult<Comment> {
-        let current_user_id = deps.authenticate(token)?;
-        deps.insert_comment(current_user_id, slug, body)
-            .await
-            .map(Into::into)
+        let current_user_id = deps.authenticate(token).await?;
+        if body.len() > MAX_COMMENT_BODY_BYTES {
+            return Err(RwError::TextTooLarge {
+                field: "body",
+                max_bytes: MAX_COMMENT_BODY_BYTES,
+            });
+        }
+        fetch_visible_article_id(deps, current_user_id.some(), slug).await?;
+        let body = sanitize(deps.sanitize_mode(), body);
+        let mentions = mention::parse_mentions(&body);
+        let comment = deps
+            .insert_comment(current_user_id, slug, &body, &mentions)
+            .await?;
+        let comment = load_authors(deps, current_user_id.some(), vec![comment])
+            .await?
+            .into_iter()
+            .single()?;
+
+        deps.publish_comment(slug, &comment);
+        deps.publish(Event::CommentAdded {
+            author_id: current_user_id.into_id(),
+            article_slug: slug.clone(),
+            comment_id: comment.id,
+        });
+        // Only the users that exist were stored as mentioned
+        for mention in &comment.mentions {
+            deps.publish(Event::UserMentioned {
+                author_id: current_user_id.into_id(),
+                username: mention.username.clone(),
+                article_slug: slug.clone(),
+                comment_id: comment.id,
+            });
+        }
+        Ok(comment)
+    }
+
+    pub async fn like_comment(
+        deps: &(impl Authenticate + ArticleRepo + CommentRepo + UserRepo),
+        token: Token,
+        slug: &Slug,
+        comment_id: CommentId,
+        value: bool,
+    ) -> RwResult<Comment> {
+        let current_user_id = deps.authenticate(token).await?;
+        fetch_visible_article_id(deps, current_user_id.some(), slug).await?;
+        if value {
+            deps.insert_like(current_user_id, slug, comment_id).await?;
+        } else {
+            deps.delete_like(current_user_id, slug, comment_id).await?;
+        }
+        let comment = deps
+            .find_comment(current_user_id.some(), slug, comment_id)
+            .await?;
+
+        load_authors(deps, current_user_id.some(), vec![comment])
+            .await?
+            .into_iter()
+            .single()
+    }
+
+    /// New comments on an article, as they are posted
+    pub async fn watch_comments(
+        deps: &(impl Authenticate + ArticleRepo + CommentEvents),
+        token: Option<Token>,
+        slug: &Slug,
+    ) -> RwResult<BoxStream<'static, Comment>> {
+        let current_user_id = deps.opt_authenticate(token).await?;
+        // Fails when the article doesn't exist
+        fetch_visible_article_id(deps, current_user_id, slug).await?;
+        Ok(deps.subscribe_comments(slug))
     }
 
     pub async fn delete_comment(
-        deps: &(impl Authenticate + CommentRepo),
+        deps: &(impl AuthorizeRole + CommentRepo + DomainEvents),
         token: Token,
-        slug: &str,
-        comment_id: i64,
+        slug: &Slug,
+        comment_id: CommentId,
     ) -> RwResult<()> {
-        let current_user_id = deps.authenticate(token)?;
-        deps.delete_comment(current_user_id, slug, comment_id).await
+        let (current_user_id, role) = deps.authenticate_with_role(token).await?;
+        if role.can_moderate_comments() {
+            deps.delete_any_comment(slug, comment_id).await?;
+        } else {
+            deps.delete_comment(current_user_id, slug, comment_id)
+                .await?;
+        }
+
+        deps.publish(Event::CommentDeleted {
+            user_id: current_user_id.into_id(),
+            article_slug: slug.clone(),
+            comment_id,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::repo::ArticleRepoMock;
+    use crate::event::DomainEventsMock;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::auth::authorize_role::AuthorizeRoleMock;
+    use crate::user::repo::{Following, User, UserRepoMock};
+    use crate::user::role::Role;
+
+    use unimock::*;
+
+    fn mock_load_author() -> impl unimock::Clause {
+        UserRepoMock::select_users_by_ids
+            .next_call(matching!((_, ids) if *ids == [uuid::Uuid::from_u128(1)]))
+            .returns(Ok(vec![(
+                User {
+                    user_id: UserId(uuid::Uuid::from_u128(1)),
+                    username: "author".to_string(),
+                    bio: "".to_string(),
+                    image: None,
+                    role: Default::default(),
+                },
+                Following(false),
+            )]))
+    }
+
+    #[tokio::test]
+    async fn list_comments_should_pass_query_to_repo() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            AuthenticateMock::opt_authenticate
+                .next_call(matching!(None))
+                .returns(Ok(UserId(None))),
+            crate::test::mock_visible_article(),
+            repo::CommentRepoMock::list_comments
+                .next_call(matching!(
+                    UserId(None),
+                    _,
+                    repo::ListOptions {
+                        limit: Some(10),
+                        offset: Some(5),
+                        direction: repo::SortDirection::Desc
+                    }
+                ))
+                .returns(Ok(vec![])),
+            repo::CommentRepoMock::count_comments
+                .next_call(matching!(UserId(None), _))
+                .returns(Ok(42)),
+        ));
+
+        let list = api::list_comments(
+            &deps,
+            None,
+            &Slug::from("slug"),
+            ListCommentsQuery {
+                limit: Some(10),
+                offset: Some(5),
+                order: Some(repo::SortDirection::Desc),
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(list.comments.is_empty());
+        assert_eq!(42, list.total_count);
+    }
+
+    #[tokio::test]
+    async fn moderator_should_delete_any_comment() {
+        let deps = Unimock::new((
+            crate::test::mock_publish_events(),
+            AuthorizeRoleMock::authenticate_with_role
+                .next_call(matching!(_))
+                .returns(Ok((UserId(uuid::Uuid::new_v4()), Role::Moderator))),
+            repo::CommentRepoMock::delete_any_comment
+                .next_call(matching!("slug", CommentId(42)))
+                .returns(Ok(())),
+        ));
+
+        api::delete_comment(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            CommentId(42),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn added_comment_should_be_published() {
+        let deps = Unimock::new((
+            crate::test::mock_publish_events(),
+            crate::test::mock_sanitize_mode(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::new_v4()))),
+            crate::test::mock_visible_article(),
+            repo::CommentRepoMock::insert_comment
+                .next_call(matching!(_, "slug", "body", _))
+                .returns(Ok(repo::Comment {
+                    comment_id: CommentId(1),
+                    created_at: time::OffsetDateTime::UNIX_EPOCH,
+                    updated_at: time::OffsetDateTime::UNIX_EPOCH,
+                    body: "body".to_string(),
+                    author_id: uuid::Uuid::from_u128(1),
+                    author_username: "author".to_string(),
+                    likes_count: 0,
+                    liked: false,
+                    mentions: vec![],
+                })),
+            mock_load_author(),
+            events::CommentEventsMock::publish_comment
+                .next_call(matching! {
+                    ("slug", comment) if comment.id == CommentId(1)
+                })
+                .returns(()),
+        ));
+
+        api::add_comment(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            "body",
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn too_large_comment_should_be_rejected_before_sanitizing() {
+        let deps = Unimock::new(
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::new_v4()))),
+        );
+
+        assert_matches::assert_matches!(
+            api::add_comment(
+                &deps,
+                Token::from_token("token"),
+                &Slug::from("slug"),
+                &"<".repeat(MAX_COMMENT_BODY_BYTES + 1),
+            )
+            .await
+            .err(),
+            Some(RwError::TextTooLarge { field: "body", .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn mentioned_users_that_exist_should_be_published() {
+        let deps = Unimock::new((
+            crate::test::mock_sanitize_mode(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::new_v4()))),
+            crate::test::mock_visible_article(),
+            repo::CommentRepoMock::insert_comment
+                .next_call(matching! {
+                    (_, "slug", "@bob, ask @nobody", mentions) if *mentions == ["bob", "nobody"]
+                })
+                .returns(Ok(repo::Comment {
+                    comment_id: CommentId(1),
+                    created_at: time::OffsetDateTime::UNIX_EPOCH,
+                    updated_at: time::OffsetDateTime::UNIX_EPOCH,
+                    body: "@bob, ask @nobody".to_string(),
+                    author_id: uuid::Uuid::from_u128(1),
+                    author_username: "author".to_string(),
+                    likes_count: 0,
+                    liked: false,
+                    mentions: vec!["bob".to_string()],
+                })),
+            mock_load_author(),
+            events::CommentEventsMock::publish_comment
+                .next_call(matching!(_, _))
+                .returns(()),
+            DomainEventsMock::publish
+                .next_call(matching!(Event::CommentAdded { .. }))
+                .returns(()),
+            DomainEventsMock::publish
+                .next_call(matching! {
+                    (Event::UserMentioned { username, comment_id: CommentId(1), .. }) if username == "bob"
+                })
+                .returns(()),
+        ));
+
+        let comment = api::add_comment(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            "@bob, ask @nobody",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            serde_json::json!([{ "username": "bob" }]),
+            serde_json::to_value(comment).unwrap()["mentions"]
+        );
+    }
+
+    #[tokio::test]
+    async fn like_should_respond_with_comment_relative_to_current_user() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::from_u128(1)))),
+            crate::test::mock_visible_article(),
+            repo::CommentRepoMock::insert_like
+                .next_call(matching!(_, "slug", CommentId(42)))
+                .returns(Ok(())),
+            repo::CommentRepoMock::find_comment
+                .next_call(
+                    matching!((UserId(Some(id)), "slug", CommentId(42)) if id.as_u128() == 1),
+                )
+                .returns(Ok(repo::Comment {
+                    comment_id: CommentId(42),
+                    created_at: time::OffsetDateTime::UNIX_EPOCH,
+                    updated_at: time::OffsetDateTime::UNIX_EPOCH,
+                    body: "body".to_string(),
+                    author_id: uuid::Uuid::from_u128(1),
+                    author_username: "author".to_string(),
+                    likes_count: 1,
+                    liked: true,
+                    mentions: vec![],
+                })),
+            mock_load_author(),
+        ));
+
+        let comment = api::like_comment(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            CommentId(42),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!((1, true), (comment.likes_count, comment.liked));
+    }
+
+    #[tokio::test]
+    async fn watching_missing_article_should_fail() {
+        let deps = Unimock::new((
+            AuthenticateMock::opt_authenticate
+                .next_call(matching!(None))
+                .returns(Ok(UserId(None))),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(_, _))
+                .answers(&|_, _, _| Ok(vec![])),
+        ));
+
+        assert_matches::assert_matches!(
+            api::watch_comments(&deps, None, &Slug::from("slug"))
+                .await
+                .err(),
+            Some(crate::error::RwError::ArticleNotFound)
+        );
+    }
+
+    /// Someone else than the author of [crate::test::mock_unlisted_article]
+    fn other_user_id() -> uuid::Uuid {
+        uuid::Uuid::from_u128(2)
+    }
+
+    #[tokio::test]
+    async fn comments_of_unlisted_article_should_not_be_listed_for_others() {
+        let deps = Unimock::new((
+            crate::test::mock_page_sizes(),
+            AuthenticateMock::opt_authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Some(other_user_id())))),
+            crate::test::mock_unlisted_article(),
+        ));
+
+        assert_matches::assert_matches!(
+            api::list_comments(
+                &deps,
+                Some(Token::from_token("token")),
+                &Slug::from("slug"),
+                Default::default()
+            )
+            .await
+            .err(),
+            Some(crate::error::RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn comments_of_unlisted_article_should_not_be_watched_by_others() {
+        let deps = Unimock::new((
+            AuthenticateMock::opt_authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Some(other_user_id())))),
+            crate::test::mock_unlisted_article(),
+        ));
+
+        assert_matches::assert_matches!(
+            api::watch_comments(&deps, Some(Token::from_token("token")), &Slug::from("slug"))
+                .await
+                .err(),
+            Some(crate::error::RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn others_should_not_comment_on_unlisted_article() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(other_user_id()))),
+            crate::test::mock_unlisted_article(),
+        ));
+
+        assert_matches::assert_matches!(
+            api::add_comment(
+                &deps,
+                Token::from_token("token"),
+                &Slug::from("slug"),
+                "body"
+            )
+            .await
+            .err(),
+            Some(crate::error::RwError::ArticleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn others_should_not_like_comments_on_unlisted_article() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(other_user_id()))),
+            crate::test::mock_unlisted_article(),
+        ));
+
+        assert_matches::assert_matches!(
+            api::like_comment(
+                &deps,
+                Token::from_token("token"),
+                &Slug::from("slug"),
+                CommentId(42),
+                true
+            )
+            .await
+            .err(),
+            Some(crate::error::RwError::ArticleNotFound)
+        );
     }
 }
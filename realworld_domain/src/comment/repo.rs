@@ -1,7 +1,10 @@
 use time::OffsetDateTime;
 
 use entrait::entrait_export as entrait;
+use futures::stream::BoxStream;
 
+use super::CommentId;
+use crate::article::{ArticleId, Slug};
 use crate::error::RwResult;
 use crate::user::UserId;
 
@@ -9,35 +12,115 @@ use uuid::Uuid;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Comment {
-    pub comment_id: i64,
+    pub comment_id: CommentId,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     pub body: String,
+    /// The rest of the author's profile is loaded with a [crate::user::profile::ProfileLoader]
+    pub author_id: Uuid,
     pub author_username: String,
-    pub author_bio: String,
-    pub author_image: Option<String>,
-    pub following_author: bool,
+    pub likes_count: i64,
+    /// Whether the current user likes the comment
+    pub liked: bool,
+    /// The usernames of the users mentioned in the body, ordered by username
+    pub mentions: Vec<String>,
 }
 
-#[entrait(CommentRepoImpl, delegate_by = DelegateCommentRepo)]
+/// A comment along with the article it was written on
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ArticleComment {
+    pub article_slug: Slug,
+    pub comment: Comment,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    /// Oldest first
+    #[default]
+    Asc,
+    /// Newest first
+    Desc,
+}
+
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct ListOptions {
+    /// `None` means no limit
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub direction: SortDirection,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(CommentRepoImpl, delegate_by=DelegateCommentRepo, mock_api=CommentRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(CommentRepoImpl, delegate_by=ref, mock_api=CommentRepoMock),
+    async_trait::async_trait
+)]
 pub trait CommentRepo {
     async fn list_comments(
         &self,
         current_user: UserId<Option<Uuid>>,
-        article_id: uuid::Uuid,
+        article_id: ArticleId,
+        options: ListOptions,
     ) -> RwResult<Vec<Comment>>;
 
+    /// How many comments `list_comments` lists without a limit or offset
+    async fn count_comments(
+        &self,
+        current_user: UserId<Option<Uuid>>,
+        article_id: ArticleId,
+    ) -> RwResult<i64>;
+
+    /// Fails with `ArticleNotFound` when there's no such comment on the article
+    async fn find_comment(
+        &self,
+        current_user: UserId<Option<Uuid>>,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<Comment>;
+
+    /// The `mentions` are stored for the usernames that exist, the others are ignored
     async fn insert_comment(
         &self,
         current_user: UserId,
-        article_slug: &str,
+        article_slug: &Slug,
         body: &str,
+        mentions: &[String],
     ) -> RwResult<Comment>;
 
     async fn delete_comment(
         &self,
         current_user: UserId,
-        article_slug: &str,
-        comment_id: i64,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()>;
+
+    /// Liking a comment twice is not an error.
+    /// Fails with `ArticleNotFound` when there's no such comment on the article.
+    async fn insert_like(
+        &self,
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId,
+    ) -> RwResult<()>;
+
+    async fn delete_like(
+        &self,
+        current_user: UserId,
+        article_slug: &Slug,
+        comment_id: CommentId,
     ) -> RwResult<()>;
+
+    /// Delete a comment regardless of who wrote it
+    async fn delete_any_comment(&self, article_slug: &Slug, comment_id: CommentId) -> RwResult<()>;
+
+    /// All comments by one author, on any article, oldest first, read lazily
+    fn stream_comments_by_author(
+        &self,
+        author: UserId,
+    ) -> BoxStream<'static, RwResult<ArticleComment>>;
 }
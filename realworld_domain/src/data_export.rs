@@ -0,0 +1,471 @@
+//!
+//! Data portability: a complete, machine-readable archive of what a user has put into the app,
+//! their profile, articles, comments, favorites and follows.
+//!
+//! Compiling it reads everything the user ever wrote, so it isn't done while the user waits.
+//! Asking for it queues an export, which the `compile_data_exports` job compiles in the background.
+//! The archive is kept with the export, and the user is emailed when it's ready.
+//! It has the user's email address in it, so it's only downloaded by the signed in user,
+//! and only while it's offered: the job purges archives older than that,
+//! and the exports of a deleted user go with the account.
+//!
+
+use crate::article::repo::ArticleRepo;
+use crate::comment::repo::CommentRepo;
+use crate::error::{RwError, RwResult};
+use crate::export::{ExportedArticle, ExportedComment};
+use crate::timestamp::Timestamptz;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::email::{Email, EmailMessage};
+use crate::user::repo::UserRepo;
+use crate::user::role::Role;
+use crate::user::UserId;
+use crate::{EmailSender, System};
+
+use entrait::entrait_export as entrait;
+use futures::TryStreamExt;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// How long a compiled archive is offered, before asking again compiles a new one
+const READY_LENGTH: Duration = Duration::days(7);
+
+/// How long an export may wait or run, before it's given up on and asking again queues a new one
+const PENDING_LENGTH: Duration = Duration::days(1);
+
+/// Exports compiled each time the job runs
+const EXPORTS_PER_RUN: i64 = 10;
+
+#[derive(sqlx::Type, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[sqlx(type_name = "app.data_export_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportStatus {
+    /// Waiting for the job to compile it
+    Pending,
+    /// Being compiled
+    Running,
+    /// The archive can be downloaded by its user
+    Ready,
+    /// Compiling it failed
+    Failed,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DataExportRecord {
+    pub data_export_id: Uuid,
+    pub user_id: Uuid,
+    pub status: DataExportStatus,
+    pub requested_at: OffsetDateTime,
+    pub finished_at: Option<OffsetDateTime>,
+}
+
+impl DataExportRecord {
+    /// Whether the export is still worth waiting for, or downloading
+    fn is_current(&self, now: OffsetDateTime) -> bool {
+        match self.status {
+            DataExportStatus::Pending | DataExportStatus::Running => {
+                self.requested_at + PENDING_LENGTH > now
+            }
+            DataExportStatus::Ready => self
+                .finished_at
+                .is_some_and(|finished_at| finished_at + READY_LENGTH > now),
+            DataExportStatus::Failed => false,
+        }
+    }
+}
+
+/// An export as seen by its user
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct DataExport {
+    pub status: DataExportStatus,
+    pub requested_at: Timestamptz,
+}
+
+impl From<DataExportRecord> for DataExport {
+    fn from(record: DataExportRecord) -> Self {
+        Self {
+            status: record.status,
+            requested_at: Timestamptz(record.requested_at),
+        }
+    }
+}
+
+/// The compiled archive
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataArchive {
+    pub profile: ArchivedProfile,
+    pub articles: Vec<ExportedArticle>,
+    pub comments: Vec<ExportedComment>,
+    /// Slugs of the articles the user favorited
+    pub favorites: Vec<String>,
+    /// Usernames of the users the user follows
+    pub following: Vec<String>,
+    pub exported_at: Timestamptz,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedProfile {
+    pub username: String,
+    pub email: Email,
+    pub bio: String,
+    pub image: Option<String>,
+    pub role: Role,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(DataExportRepoImpl, delegate_by=DelegateDataExportRepo, mock_api=DataExportRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(DataExportRepoImpl, delegate_by=ref, mock_api=DataExportRepoMock),
+    async_trait::async_trait
+)]
+pub trait DataExportRepo {
+    /// Queue an export of the user's data
+    async fn insert_data_export(&self, user_id: UserId) -> RwResult<DataExportRecord>;
+
+    /// The export the user asked for last, if any
+    async fn find_latest_data_export(&self, user_id: UserId) -> RwResult<Option<DataExportRecord>>;
+
+    /// Mark the oldest pending exports as running, at most `limit` of them, and return them.
+    /// Exports being claimed at the same time by another instance are skipped.
+    async fn claim_pending_data_exports(&self, limit: i64) -> RwResult<Vec<DataExportRecord>>;
+
+    /// Ready with the archive, or failed without one
+    async fn finish_data_export(
+        &self,
+        data_export_id: Uuid,
+        archive: Option<&[u8]>,
+    ) -> RwResult<()>;
+
+    /// The archive of a ready export, unless it's been purged
+    async fn find_data_export_archive(&self, data_export_id: Uuid) -> RwResult<Option<Vec<u8>>>;
+
+    /// Drop the archives of exports that finished before `finished_before`, returning how many
+    async fn purge_data_export_archives(&self, finished_before: OffsetDateTime) -> RwResult<u64>;
+
+    async fn select_favorited_slugs(&self, user_id: UserId) -> RwResult<Vec<String>>;
+
+    async fn select_followed_usernames(&self, user_id: UserId) -> RwResult<Vec<String>>;
+}
+
+///
+/// The current export of the current user, queueing a new one unless there's one
+/// being compiled or ready to download.
+///
+#[entrait(pub RequestDataExport, mock_api=RequestDataExportMock)]
+async fn request_data_export(
+    deps: &(impl System + Authenticate + DataExportRepo),
+    token: Token,
+) -> RwResult<DataExport> {
+    let current_user_id = deps.authenticate(token).await?;
+    let now = deps.get_current_time();
+
+    let export = match deps.find_latest_data_export(current_user_id).await? {
+        Some(export) if export.is_current(now) => export,
+        _ => deps.insert_data_export(current_user_id).await?,
+    };
+
+    Ok(export.into())
+}
+
+///
+/// The archive of the current user's export, as JSON, while it's offered for download.
+///
+#[entrait(pub DownloadDataExport, mock_api=DownloadDataExportMock)]
+async fn download_data_export(
+    deps: &(impl System + Authenticate + DataExportRepo),
+    token: Token,
+) -> RwResult<Vec<u8>> {
+    let current_user_id = deps.authenticate(token).await?;
+    let now = deps.get_current_time();
+
+    let export = deps
+        .find_latest_data_export(current_user_id)
+        .await?
+        .filter(|export| export.status == DataExportStatus::Ready && export.is_current(now))
+        .ok_or(RwError::DataExportNotFound)?;
+
+    deps.find_data_export_archive(export.data_export_id)
+        .await?
+        .ok_or(RwError::DataExportNotFound)
+}
+
+///
+/// Purge the archives no longer offered, then compile the oldest pending exports,
+/// returning how many archives were purged or became ready.
+/// An export that fails is logged and marked as failed, so the user can ask again.
+///
+#[entrait(pub CompileDataExports, mock_api=CompileDataExportsMock)]
+async fn compile_data_exports(
+    deps: &(impl System + DataExportRepo + UserRepo + ArticleRepo + CommentRepo + EmailSender),
+) -> RwResult<u64> {
+    let mut affected = deps
+        .purge_data_export_archives(deps.get_current_time() - READY_LENGTH)
+        .await?;
+    for export in deps.claim_pending_data_exports(EXPORTS_PER_RUN).await? {
+        match compile_data_export(deps, &export).await {
+            Ok(archive) => {
+                deps.finish_data_export(export.data_export_id, Some(archive.as_slice()))
+                    .await?;
+                affected += 1;
+            }
+            Err(error) => {
+                tracing::error!(data_export_id = %export.data_export_id, %error, "data export failed");
+                deps.finish_data_export(export.data_export_id, None).await?;
+            }
+        }
+    }
+
+    Ok(affected)
+}
+
+/// Compile the archive of the export and tell its user it's ready, returning the archive
+async fn compile_data_export(
+    deps: &(impl System + DataExportRepo + UserRepo + ArticleRepo + CommentRepo + EmailSender),
+    export: &DataExportRecord,
+) -> RwResult<Vec<u8>> {
+    let user_id = UserId(export.user_id);
+    let (user, credentials) = deps
+        .find_user_credentials_by_id(user_id)
+        .await?
+        .ok_or(RwError::CurrentUserDoesNotExist)?;
+
+    let archive = DataArchive {
+        articles: deps
+            .stream_articles_by_author(user_id)
+            .map_ok(Into::into)
+            .try_collect()
+            .await?,
+        comments: deps
+            .stream_comments_by_author(user_id)
+            .map_ok(Into::into)
+            .try_collect()
+            .await?,
+        favorites: deps.select_favorited_slugs(user_id).await?,
+        following: deps.select_followed_usernames(user_id).await?,
+        exported_at: Timestamptz(deps.get_current_time()),
+        profile: ArchivedProfile {
+            username: user.username,
+            email: credentials.email.clone(),
+            bio: user.bio,
+            image: user.image,
+            role: user.role,
+        },
+    };
+    let data = serde_json::to_vec_pretty(&archive).map_err(anyhow::Error::from)?;
+
+    deps.send_email(EmailMessage {
+        to: credentials.email,
+        subject: "Your data export is ready".to_string(),
+        body: format!(
+            "Your data can be downloaded from your account settings, while signed in.\n\n\
+             It can be downloaded for {} days.",
+            READY_LENGTH.whole_days()
+        ),
+    })
+    .await?;
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::repo::ArticleRepoMock;
+    use crate::comment::repo::CommentRepoMock;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::repo::{Credentials, User, UserRepoMock};
+    use crate::EmailSenderMock;
+
+    use assert_matches::*;
+    use futures::StreamExt;
+    use unimock::*;
+
+    fn test_export_id() -> Uuid {
+        Uuid::from_u128(2)
+    }
+
+    fn test_record(
+        status: DataExportStatus,
+        finished_at: Option<OffsetDateTime>,
+    ) -> DataExportRecord {
+        DataExportRecord {
+            data_export_id: test_export_id(),
+            user_id: Uuid::from_u128(1),
+            status,
+            requested_at: OffsetDateTime::UNIX_EPOCH,
+            finished_at,
+        }
+    }
+
+    #[test]
+    fn exports_should_be_current_for_a_while() {
+        let epoch = OffsetDateTime::UNIX_EPOCH;
+        let pending = test_record(DataExportStatus::Pending, None);
+        let ready = test_record(DataExportStatus::Ready, Some(epoch));
+
+        assert!(pending.is_current(epoch + Duration::hours(1)));
+        assert!(!pending.is_current(epoch + PENDING_LENGTH));
+        assert!(ready.is_current(epoch + Duration::days(6)));
+        assert!(!ready.is_current(epoch + READY_LENGTH));
+        assert!(!test_record(DataExportStatus::Failed, Some(epoch)).is_current(epoch));
+    }
+
+    #[tokio::test]
+    async fn ready_export_should_be_offered_again() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::from_u128(1)))),
+            DataExportRepoMock::find_latest_data_export
+                .next_call(matching!(_))
+                .returns(Ok(Some(test_record(
+                    DataExportStatus::Ready,
+                    Some(OffsetDateTime::UNIX_EPOCH),
+                )))),
+        ));
+
+        let export = request_data_export(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+
+        assert_eq!(DataExportStatus::Ready, export.status);
+    }
+
+    #[tokio::test]
+    async fn failed_export_should_be_queued_again() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::from_u128(1)))),
+            DataExportRepoMock::find_latest_data_export
+                .next_call(matching!(_))
+                .returns(Ok(Some(test_record(
+                    DataExportStatus::Failed,
+                    Some(OffsetDateTime::UNIX_EPOCH),
+                )))),
+            DataExportRepoMock::insert_data_export
+                .next_call(matching!(_))
+                .returns(Ok(test_record(DataExportStatus::Pending, None))),
+        ));
+
+        let export = request_data_export(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+
+        assert_eq!(DataExportStatus::Pending, export.status);
+    }
+
+    #[tokio::test]
+    async fn ready_export_should_be_downloaded_by_its_user() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::from_u128(1)))),
+            DataExportRepoMock::find_latest_data_export
+                .next_call(matching!((UserId(id)) if *id == Uuid::from_u128(1)))
+                .returns(Ok(Some(test_record(
+                    DataExportStatus::Ready,
+                    Some(OffsetDateTime::UNIX_EPOCH),
+                )))),
+            DataExportRepoMock::find_data_export_archive
+                .next_call(matching!((id) if *id == test_export_id()))
+                .returns(Ok(Some(b"{}".to_vec()))),
+        ));
+
+        let archive = download_data_export(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+
+        assert_eq!(b"{}".to_vec(), archive);
+    }
+
+    #[tokio::test]
+    async fn export_no_longer_offered_should_not_be_downloaded() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::from_u128(1)))),
+            DataExportRepoMock::find_latest_data_export
+                .next_call(matching!(_))
+                .returns(Ok(Some(test_record(
+                    DataExportStatus::Ready,
+                    Some(OffsetDateTime::UNIX_EPOCH - READY_LENGTH),
+                )))),
+        ));
+
+        assert_matches!(
+            download_data_export(&deps, Token::from_token("token")).await,
+            Err(RwError::DataExportNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn compiled_export_should_be_kept_and_emailed_after_purging() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            DataExportRepoMock::purge_data_export_archives
+                .next_call(
+                    matching!((before) if *before == OffsetDateTime::UNIX_EPOCH - READY_LENGTH),
+                )
+                .returns(Ok(1)),
+            DataExportRepoMock::claim_pending_data_exports
+                .next_call(matching!(_))
+                .returns(Ok(vec![test_record(DataExportStatus::Running, None)])),
+            UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, user_id| {
+                    Ok(Some((
+                        User {
+                            user_id,
+                            username: "username".to_string(),
+                            bio: "bio".to_string(),
+                            image: None,
+                            role: Default::default(),
+                        },
+                        Credentials {
+                            email: "user@example.com".parse().unwrap(),
+                            password_hash: "hash".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            ArticleRepoMock::stream_articles_by_author
+                .next_call(matching!(_))
+                .answers(&|_, _| futures::stream::empty().boxed()),
+            CommentRepoMock::stream_comments_by_author
+                .next_call(matching!(_))
+                .answers(&|_, _| futures::stream::empty().boxed()),
+            DataExportRepoMock::select_favorited_slugs
+                .next_call(matching!(_))
+                .returns(Ok(vec!["favorite".to_string()])),
+            DataExportRepoMock::select_followed_usernames
+                .next_call(matching!(_))
+                .returns(Ok(vec!["celeb".to_string()])),
+            EmailSenderMock::send_email
+                .next_call(matching! {
+                    (message) if message.to.as_ref() == "user@example.com"
+                        && !message.body.contains("http")
+                })
+                .returns(Ok(())),
+            DataExportRepoMock::finish_data_export
+                .next_call(matching! {
+                    (id, Some(archive)) if *id == test_export_id()
+                        && String::from_utf8_lossy(archive).contains("\"celeb\"")
+                })
+                .returns(Ok(())),
+        ));
+
+        assert_eq!(2, compile_data_exports(&deps).await.unwrap());
+    }
+}
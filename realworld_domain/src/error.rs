@@ -1,4 +1,4 @@
-use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::header::{CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE};
 use axum::http::StatusCode;
 use axum::http::{HeaderMap, HeaderValue};
 use axum::response::{IntoResponse, Response};
@@ -16,100 +16,629 @@ pub enum RwError {
     #[error("forbidden")]
     Forbidden,
 
+    #[error("user is banned")]
+    UserBanned,
+
     #[error("user does not exist")]
     CurrentUserDoesNotExist,
 
     #[error("email does not exist")]
     EmailDoesNotExist,
 
+    #[error("email is not verified")]
+    EmailNotVerified,
+
+    #[error("invalid or expired token")]
+    InvalidToken,
+
     #[error("username is taken")]
     UsernameTaken,
 
     #[error("email is taken")]
     EmailTaken,
 
+    #[error("username is reserved")]
+    UsernameReserved,
+
     #[error("user profile not found")]
     ProfileNotFound,
 
     #[error("article not found")]
     ArticleNotFound,
 
+    #[error("notification not found")]
+    NotificationNotFound,
+
+    #[error("session not found")]
+    SessionNotFound,
+
+    #[error("oauth provider not found")]
+    OAuthProviderNotFound,
+
+    #[error("report not found")]
+    ReportNotFound,
+
+    #[error("data export not found")]
+    DataExportNotFound,
+
+    #[error("a reason is required")]
+    ReportReasonMissing,
+
+    #[error("a search query is required")]
+    SearchQueryMissing,
+
+    /// A password hash of none of the algorithms of `PasswordAlgorithm`
+    #[error("the password hash has an unknown format")]
+    UnknownPasswordHash,
+
+    /// The current password given for changing it was wrong, or left out
+    #[error("the current password is wrong")]
+    WrongCurrentPassword,
+
+    /// A password in an update of the user, which doesn't confirm the current one
+    #[error("the password is changed with `PUT /api/user/password`")]
+    PasswordNotUpdatable,
+
     #[error("duplicate article slug: {0}")]
     DuplicateArticleSlug(String),
 
+    #[error("tag `{tag}` is longer than {max_length} characters")]
+    TagTooLong { tag: String, max_length: usize },
+
+    #[error("tag `{0}` is not allowed")]
+    TagNotAllowed(String),
+
+    /// A `limit` or `offset` of a listing out of range
+    #[error("`{field}` {problem}")]
+    InvalidPagination {
+        field: &'static str,
+        problem: &'static str,
+    },
+
+    /// An `offset` past [crate::pagination::PageSizes::max_offset]
+    #[error("`offset` can't be larger than {max}, page with `after` instead")]
+    OffsetTooLarge { max: i64 },
+
+    /// A user setting out of range
+    #[error("`{field}` {problem}")]
+    InvalidSetting {
+        field: &'static str,
+        problem: &'static str,
+    },
+
+    /// More items in one batch request than it can take
+    #[error("`{field}` can't have more than {max} items")]
+    BatchTooLarge { field: &'static str, max: usize },
+
+    /// A field asked for in `fields` that the listed items don't have
+    #[error("unknown field `{0}` in `fields`")]
+    UnknownField(String),
+
+    #[error("article has been changed since it was fetched")]
+    PreconditionFailed,
+
+    #[error("unsupported image type: {0}")]
+    UnsupportedImageType(String),
+
+    #[error("request body is larger than {max_bytes} bytes")]
+    PayloadTooLarge { max_bytes: usize },
+
+    #[error("article body is larger than {max_bytes} bytes")]
+    ArticleBodyTooLarge { max_bytes: usize },
+
+    /// Text other than an article body larger than it may be, like a comment or a bio
+    #[error("`{field}` is larger than {max_bytes} bytes")]
+    TextTooLarge {
+        field: &'static str,
+        max_bytes: usize,
+    },
+
+    #[error("too many failed login attempts, try again later")]
+    TooManyAttempts,
+
+    #[error("too many requests")]
+    TooManyRequests { retry_after: std::time::Duration },
+
+    /// Lost connection to the database, or no connection could be had in time
+    #[error("the database is unavailable")]
+    DatabaseUnavailable(#[source] anyhow::Error),
+
+    /// Shedding load, see `retry_after` for when to try again, if known
+    #[error("service unavailable, try again later")]
+    ServiceUnavailable {
+        retry_after: Option<std::time::Duration>,
+    },
+
     #[error("an internal server error occurred")]
     Anyhow(#[from] anyhow::Error),
 }
 
 impl RwError {
-    fn status_code(&self) -> StatusCode {
+    /// The stable code of the error, for clients to tell errors apart
+    pub fn code(&self) -> ErrorCode {
         match self {
-            Self::Unauthorized => StatusCode::UNAUTHORIZED,
-            Self::Forbidden => StatusCode::FORBIDDEN,
-            Self::CurrentUserDoesNotExist => StatusCode::NOT_FOUND,
-            Self::EmailDoesNotExist => StatusCode::UNPROCESSABLE_ENTITY,
-            Self::UsernameTaken => StatusCode::UNPROCESSABLE_ENTITY,
-            Self::EmailTaken => StatusCode::UNPROCESSABLE_ENTITY,
-            Self::ProfileNotFound => StatusCode::NOT_FOUND,
-            Self::ArticleNotFound => StatusCode::NOT_FOUND,
-            Self::DuplicateArticleSlug(_) => StatusCode::UNPROCESSABLE_ENTITY,
-            Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Unauthorized => ErrorCode::Unauthorized,
+            Self::Forbidden => ErrorCode::Forbidden,
+            Self::UserBanned => ErrorCode::UserBanned,
+            Self::CurrentUserDoesNotExist => ErrorCode::UserNotFound,
+            Self::EmailDoesNotExist => ErrorCode::EmailNotFound,
+            Self::EmailNotVerified => ErrorCode::EmailNotVerified,
+            Self::InvalidToken => ErrorCode::InvalidToken,
+            Self::UsernameTaken => ErrorCode::UsernameTaken,
+            Self::EmailTaken => ErrorCode::EmailTaken,
+            Self::UsernameReserved => ErrorCode::ValidationFailed,
+            Self::ProfileNotFound => ErrorCode::ProfileNotFound,
+            Self::ArticleNotFound => ErrorCode::ArticleNotFound,
+            Self::NotificationNotFound => ErrorCode::NotificationNotFound,
+            Self::SessionNotFound => ErrorCode::SessionNotFound,
+            Self::OAuthProviderNotFound => ErrorCode::OauthProviderNotFound,
+            Self::ReportNotFound => ErrorCode::ReportNotFound,
+            Self::DataExportNotFound => ErrorCode::DataExportNotFound,
+            Self::ReportReasonMissing => ErrorCode::ValidationFailed,
+            Self::SearchQueryMissing => ErrorCode::ValidationFailed,
+            Self::UnknownPasswordHash => ErrorCode::ValidationFailed,
+            Self::WrongCurrentPassword => ErrorCode::WrongCurrentPassword,
+            Self::PasswordNotUpdatable => ErrorCode::ValidationFailed,
+            Self::DuplicateArticleSlug(_) => ErrorCode::DuplicateArticleSlug,
+            Self::TagTooLong { .. } => ErrorCode::ValidationFailed,
+            Self::TagNotAllowed(_) => ErrorCode::ValidationFailed,
+            Self::InvalidPagination { .. } => ErrorCode::ValidationFailed,
+            Self::OffsetTooLarge { .. } => ErrorCode::ValidationFailed,
+            Self::InvalidSetting { .. } => ErrorCode::ValidationFailed,
+            Self::BatchTooLarge { .. } => ErrorCode::ValidationFailed,
+            Self::UnknownField(_) => ErrorCode::ValidationFailed,
+            Self::PreconditionFailed => ErrorCode::PreconditionFailed,
+            Self::UnsupportedImageType(_) => ErrorCode::UnsupportedImageType,
+            Self::PayloadTooLarge { .. } => ErrorCode::PayloadTooLarge,
+            Self::ArticleBodyTooLarge { .. } => ErrorCode::ValidationFailed,
+            Self::TextTooLarge { .. } => ErrorCode::ValidationFailed,
+            Self::TooManyAttempts => ErrorCode::TooManyLoginAttempts,
+            Self::TooManyRequests { .. } => ErrorCode::RateLimited,
+            Self::DatabaseUnavailable(_) => ErrorCode::DatabaseUnavailable,
+            Self::ServiceUnavailable { .. } => ErrorCode::ServiceUnavailable,
+            Self::Anyhow(_) => ErrorCode::InternalError,
         }
     }
-}
 
-impl axum::response::IntoResponse for RwError {
-    fn into_response(self) -> Response {
+    /// The HTTP status of the error, also the basis of its status in other protocols
+    pub fn status_code(&self) -> StatusCode {
+        self.code().status_code()
+    }
+
+    /// What was wrong with which field of the request, the `errors` of the RealWorld spec
+    fn field_errors(&self) -> FieldErrors {
         match self {
-            Self::Unauthorized => (
-                self.status_code(),
-                [(WWW_AUTHENTICATE, HeaderValue::from_static("Token"))]
-                    .into_iter()
-                    .collect::<HeaderMap>(),
-                self.to_string(),
-            )
-                .into_response(),
-            Self::Forbidden => (self.status_code(), ()).into_response(),
-            Self::CurrentUserDoesNotExist => (self.status_code(), ()).into_response(),
-            Self::EmailDoesNotExist => {
-                unprocessable_entity_with_errors([("email".into(), vec!["does not exist".into()])])
+            Self::UserBanned => field_error("user", "is banned"),
+            Self::EmailDoesNotExist => field_error("email", "does not exist"),
+            Self::EmailNotVerified => field_error("email", "is not verified"),
+            Self::InvalidToken => field_error("token", "is invalid or expired"),
+            Self::UsernameTaken => field_error("username", "username is taken"),
+            Self::EmailTaken => field_error("email", "email is taken"),
+            Self::UsernameReserved => field_error("username", "is reserved"),
+            Self::ReportReasonMissing => field_error("reason", "can't be blank"),
+            Self::SearchQueryMissing => field_error("query", "can't be blank"),
+            Self::UnknownPasswordHash => field_error("passwordHash", "has an unknown format"),
+            Self::WrongCurrentPassword => field_error("currentPassword", "is wrong"),
+            Self::PasswordNotUpdatable => {
+                field_error("password", "is changed with `PUT /api/user/password`")
+            }
+            Self::DuplicateArticleSlug(slug) => {
+                field_error("slug", format!("duplicate article slug: {slug}"))
+            }
+            Self::TagTooLong { .. } | Self::TagNotAllowed(_) => {
+                field_error("tagList", self.to_string())
+            }
+            Self::InvalidPagination { field, problem }
+            | Self::InvalidSetting { field, problem } => field_error(field, *problem),
+            Self::BatchTooLarge { field, max } => {
+                field_error(field, format!("can't have more than {max} items"))
+            }
+            Self::OffsetTooLarge { max } => field_error(
+                "offset",
+                format!(
+                    "can't be larger than {max}, page with `after` and the `nextCursor` of the previous page instead"
+                ),
+            ),
+            Self::UnknownField(name) => field_error("fields", format!("has no field `{name}`")),
+            Self::UnsupportedImageType(_) => {
+                field_error("image", "must be a PNG, JPEG, GIF or WebP image")
+            }
+            Self::PayloadTooLarge { max_bytes } | Self::ArticleBodyTooLarge { max_bytes } => {
+                field_error("body", format!("is larger than {max_bytes} bytes"))
+            }
+            Self::TextTooLarge { field, max_bytes } => {
+                field_error(field, format!("is larger than {max_bytes} bytes"))
             }
-            Self::UsernameTaken => unprocessable_entity_with_errors([(
-                "username".into(),
-                vec!["username is taken".into()],
-            )]),
-            Self::EmailTaken => {
-                unprocessable_entity_with_errors([("email".into(), vec!["email is taken".into()])])
+            _ => FieldErrors::new(),
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        match self {
+            Self::Unauthorized => [(WWW_AUTHENTICATE, HeaderValue::from_static("Token"))]
+                .into_iter()
+                .collect(),
+            Self::TooManyRequests { retry_after } => retry_after_header(Some(*retry_after)),
+            Self::ServiceUnavailable { retry_after } => retry_after_header(*retry_after),
+            _ => HeaderMap::new(),
+        }
+    }
+}
+
+///
+/// Stable, machine-readable error codes, given as `code` in error responses.
+///
+/// Several errors may share a code, like the validation errors, which tell what was wrong in `errors`.
+/// Codes are never renamed or removed, only added.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCode {
+    Unauthorized,
+    Forbidden,
+    UserBanned,
+    UserNotFound,
+    EmailNotFound,
+    EmailNotVerified,
+    InvalidToken,
+    UsernameTaken,
+    EmailTaken,
+    ProfileNotFound,
+    ArticleNotFound,
+    NotificationNotFound,
+    SessionNotFound,
+    OauthProviderNotFound,
+    ReportNotFound,
+    DataExportNotFound,
+    ValidationFailed,
+    WrongCurrentPassword,
+    DuplicateArticleSlug,
+    PreconditionFailed,
+    UnsupportedImageType,
+    PayloadTooLarge,
+    TooManyLoginAttempts,
+    RateLimited,
+    DatabaseUnavailable,
+    ServiceUnavailable,
+    InternalError,
+}
+
+impl serde::Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 27] = [
+        Self::Unauthorized,
+        Self::Forbidden,
+        Self::UserBanned,
+        Self::UserNotFound,
+        Self::EmailNotFound,
+        Self::EmailNotVerified,
+        Self::InvalidToken,
+        Self::UsernameTaken,
+        Self::EmailTaken,
+        Self::ProfileNotFound,
+        Self::ArticleNotFound,
+        Self::NotificationNotFound,
+        Self::SessionNotFound,
+        Self::OauthProviderNotFound,
+        Self::ReportNotFound,
+        Self::DataExportNotFound,
+        Self::ValidationFailed,
+        Self::WrongCurrentPassword,
+        Self::DuplicateArticleSlug,
+        Self::PreconditionFailed,
+        Self::UnsupportedImageType,
+        Self::PayloadTooLarge,
+        Self::TooManyLoginAttempts,
+        Self::RateLimited,
+        Self::DatabaseUnavailable,
+        Self::ServiceUnavailable,
+        Self::InternalError,
+    ];
+
+    pub fn status_code(self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden | Self::UserBanned | Self::EmailNotVerified => StatusCode::FORBIDDEN,
+            Self::UserNotFound
+            | Self::ProfileNotFound
+            | Self::ArticleNotFound
+            | Self::NotificationNotFound
+            | Self::SessionNotFound
+            | Self::OauthProviderNotFound
+            | Self::ReportNotFound
+            | Self::DataExportNotFound => StatusCode::NOT_FOUND,
+            Self::EmailNotFound
+            | Self::InvalidToken
+            | Self::UsernameTaken
+            | Self::EmailTaken
+            | Self::ValidationFailed
+            | Self::WrongCurrentPassword
+            | Self::DuplicateArticleSlug => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            Self::UnsupportedImageType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::TooManyLoginAttempts | Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::DatabaseUnavailable | Self::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::Forbidden => "FORBIDDEN",
+            Self::UserBanned => "USER_BANNED",
+            Self::UserNotFound => "USER_NOT_FOUND",
+            Self::EmailNotFound => "EMAIL_NOT_FOUND",
+            Self::EmailNotVerified => "EMAIL_NOT_VERIFIED",
+            Self::InvalidToken => "INVALID_TOKEN",
+            Self::UsernameTaken => "USERNAME_TAKEN",
+            Self::EmailTaken => "EMAIL_TAKEN",
+            Self::ProfileNotFound => "PROFILE_NOT_FOUND",
+            Self::ArticleNotFound => "ARTICLE_NOT_FOUND",
+            Self::NotificationNotFound => "NOTIFICATION_NOT_FOUND",
+            Self::SessionNotFound => "SESSION_NOT_FOUND",
+            Self::OauthProviderNotFound => "OAUTH_PROVIDER_NOT_FOUND",
+            Self::ReportNotFound => "REPORT_NOT_FOUND",
+            Self::DataExportNotFound => "DATA_EXPORT_NOT_FOUND",
+            Self::ValidationFailed => "VALIDATION_FAILED",
+            Self::WrongCurrentPassword => "WRONG_CURRENT_PASSWORD",
+            Self::DuplicateArticleSlug => "DUPLICATE_ARTICLE_SLUG",
+            Self::PreconditionFailed => "PRECONDITION_FAILED",
+            Self::UnsupportedImageType => "UNSUPPORTED_IMAGE_TYPE",
+            Self::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            Self::TooManyLoginAttempts => "TOO_MANY_LOGIN_ATTEMPTS",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::DatabaseUnavailable => "DATABASE_UNAVAILABLE",
+            Self::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            Self::InternalError => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Unauthorized => "A valid token is required",
+            Self::Forbidden => "The current user may not do this",
+            Self::UserBanned => "The current user is banned",
+            Self::UserNotFound => "The user of the token no longer exists",
+            Self::EmailNotFound => "No user has the email address",
+            Self::EmailNotVerified => "The email address must be verified before logging in",
+            Self::InvalidToken => {
+                "The email verification or password reset token is invalid or expired"
             }
-            Self::ProfileNotFound => (self.status_code(), ()).into_response(),
-            Self::ArticleNotFound => (self.status_code(), ()).into_response(),
-            Self::DuplicateArticleSlug(slug) => unprocessable_entity_with_errors([(
-                "slug".into(),
-                vec![format!("duplicate article slug: {slug}").into()],
-            )]),
-            Self::Anyhow(ref e) => {
+            Self::UsernameTaken => "Another user has the username",
+            Self::EmailTaken => "Another user has the email address",
+            Self::ProfileNotFound => "No user has the username",
+            Self::ArticleNotFound => "No article has the slug",
+            Self::NotificationNotFound => "The current user has no such notification",
+            Self::SessionNotFound => "The current user has no such session",
+            Self::OauthProviderNotFound => "No such OAuth provider is configured",
+            Self::ReportNotFound => "No such report",
+            Self::DataExportNotFound => "The current user has no data export ready to download",
+            Self::ValidationFailed => "A field of the request is invalid, as told by `errors`",
+            Self::WrongCurrentPassword => "The current password given is wrong",
+            Self::DuplicateArticleSlug => "Another article has the slug of the title",
+            Self::PreconditionFailed => "The article has been changed since it was fetched",
+            Self::UnsupportedImageType => "The image is not a PNG, JPEG, GIF or WebP image",
+            Self::PayloadTooLarge => "The request body is too large",
+            Self::TooManyLoginAttempts => "Logging in is locked after too many failed attempts",
+            Self::RateLimited => "Too many requests, try again after `Retry-After` seconds",
+            Self::DatabaseUnavailable => "The database can't be reached, try again later",
+            Self::ServiceUnavailable => "The service is overloaded, try again later",
+            Self::InternalError => "Something unexpected went wrong",
+        }
+    }
+}
+
+///
+/// How error responses are written
+///
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ErrorFormat {
+    /// The same JSON form for every error, e.g.
+    /// `{"code": "VALIDATION_FAILED", "message": "...", "errors": {"tagList": ["..."]}}`
+    #[default]
+    RealWorld,
+    /// `application/problem+json` of RFC 7807, with `code` and `errors` as extension members
+    Problem,
+}
+
+tokio::task_local! {
+    static ERROR_CONTEXT: ErrorContext;
+}
+
+struct ErrorContext {
+    format: ErrorFormat,
+    instance: String,
+}
+
+///
+/// Write the errors that `future` responds with in `format`, for the request at `instance`, its path.
+/// Outside of it errors are written in [ErrorFormat::RealWorld].
+///
+pub async fn with_error_format<F: std::future::Future>(
+    format: ErrorFormat,
+    instance: String,
+    future: F,
+) -> F::Output {
+    ERROR_CONTEXT
+        .scope(ErrorContext { format, instance }, future)
+        .await
+}
+
+impl IntoResponse for RwError {
+    fn into_response(self) -> Response {
+        match &self {
+            Self::DatabaseUnavailable(e) => tracing::error!("Database unavailable: {:?}", e),
+            Self::Anyhow(e) => {
                 // TODO: we probably want to use `tracing` instead
                 // so that this gets linked to the HTTP request by `TraceLayer`.
                 tracing::error!("Generic error: {:?}", e);
-                (self.status_code(), self.to_string()).into_response()
             }
+            _ => {}
         }
+
+        let code = self.code();
+        let mut headers = self.headers();
+        let problem = ERROR_CONTEXT
+            .try_with(|context| match context.format {
+                ErrorFormat::RealWorld => None,
+                ErrorFormat::Problem => Some(context.instance.clone()),
+            })
+            .ok()
+            .flatten();
+
+        let body = match problem {
+            None => serde_json::to_value(JsonError {
+                code,
+                message: self.to_string(),
+                errors: self.field_errors(),
+            }),
+            Some(instance) => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/problem+json"),
+                );
+                serde_json::to_value(ProblemJson {
+                    problem_type: format!("/api/errors#{}", code.as_str()),
+                    title: code.description(),
+                    status: code.status_code().as_u16(),
+                    detail: self.to_string(),
+                    instance,
+                    code,
+                    errors: self.field_errors(),
+                })
+            }
+        };
+
+        // The headers replace those of the body, like its content type
+        (
+            code.status_code(),
+            headers,
+            Json(body.expect("error responses serialize")),
+        )
+            .into_response()
     }
 }
 
+/// Empty without a duration
+fn retry_after_header(retry_after: Option<std::time::Duration>) -> HeaderMap {
+    retry_after
+        .map(|retry_after| {
+            (
+                RETRY_AFTER,
+                // Whole seconds, rounded up so that the client doesn't retry too early
+                HeaderValue::from(
+                    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0),
+                ),
+            )
+        })
+        .into_iter()
+        .collect()
+}
+
+type FieldErrors = HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>;
+
 #[derive(serde::Serialize)]
-struct JsonErrors {
-    errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
+struct JsonError {
+    code: ErrorCode,
+    message: String,
+    errors: FieldErrors,
+}
+
+/// RFC 7807. `type` links to the documentation of the code.
+#[derive(serde::Serialize)]
+struct ProblemJson {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    instance: String,
+    code: ErrorCode,
+    errors: FieldErrors,
+}
+
+fn field_error(field: &'static str, message: impl Into<Cow<'static, str>>) -> FieldErrors {
+    [(field.into(), vec![message.into()])].into()
 }
 
-fn unprocessable_entity_with_errors(
-    errors: impl Into<HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>>,
-) -> Response {
-    (
-        StatusCode::UNPROCESSABLE_ENTITY,
-        Json(JsonErrors {
-            errors: errors.into(),
-        }),
-    )
-        .into_response()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn error_response_should_have_code_message_and_field_errors() {
+        let response = RwError::TagNotAllowed("spam".to_string()).into_response();
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+        assert_eq!(
+            serde_json::json!({
+                "code": "VALIDATION_FAILED",
+                "message": "tag `spam` is not allowed",
+                "errors": { "tagList": ["tag `spam` is not allowed"] },
+            }),
+            json_body(response).await
+        );
+    }
+
+    #[tokio::test]
+    async fn unauthorized_response_should_keep_its_header() {
+        let response = RwError::Unauthorized.into_response();
+
+        assert_eq!("Token", response.headers()[WWW_AUTHENTICATE]);
+        assert_eq!(
+            serde_json::json!({
+                "code": "UNAUTHORIZED",
+                "message": "authentication required",
+                "errors": {},
+            }),
+            json_body(response).await
+        );
+    }
+
+    #[tokio::test]
+    async fn problem_response_should_have_rfc_7807_fields_and_field_errors() {
+        let response = with_error_format(ErrorFormat::Problem, "/api/reports".to_string(), async {
+            RwError::ReportReasonMissing.into_response()
+        })
+        .await;
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+        assert_eq!("application/problem+json", response.headers()[CONTENT_TYPE]);
+        assert_eq!(
+            serde_json::json!({
+                "type": "/api/errors#VALIDATION_FAILED",
+                "title": "A field of the request is invalid, as told by `errors`",
+                "status": 422,
+                "detail": "a reason is required",
+                "instance": "/api/reports",
+                "code": "VALIDATION_FAILED",
+                "errors": { "reason": ["can't be blank"] },
+            }),
+            json_body(response).await
+        );
+    }
+
+    #[test]
+    fn error_codes_should_be_unique() {
+        let codes: std::collections::HashSet<_> = ErrorCode::ALL
+            .iter()
+            .map(|code| serde_json::to_string(code).unwrap())
+            .collect();
+
+        assert_eq!(ErrorCode::ALL.len(), codes.len());
+        assert!(codes.contains("\"OAUTH_PROVIDER_NOT_FOUND\""));
+    }
 }
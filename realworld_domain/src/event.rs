@@ -0,0 +1,82 @@
+//!
+//! Things that happened in the domain, for whoever wants to react to them:
+//! notifications, webhooks, cache invalidation and the like.
+//!
+
+use crate::article::Slug;
+use crate::comment::CommentId;
+
+use entrait::entrait_export as entrait;
+use uuid::Uuid;
+
+#[derive(serde::Serialize, Clone, Debug, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    UserSignedUp {
+        user_id: Uuid,
+        username: String,
+    },
+    UserDeleted {
+        user_id: Uuid,
+    },
+    ArticleCreated {
+        author_id: Uuid,
+        slug: Slug,
+    },
+    ArticleUpdated {
+        user_id: Uuid,
+        previous_slug: Slug,
+        slug: Slug,
+    },
+    ArticleDeleted {
+        user_id: Uuid,
+        slug: Slug,
+    },
+    ArticleFavorited {
+        user_id: Uuid,
+        slug: Slug,
+        /// false when the favorite was removed
+        favorited: bool,
+    },
+    UserFollowed {
+        follower_id: Uuid,
+        username: String,
+        /// false when the user was unfollowed
+        following: bool,
+    },
+    CommentAdded {
+        author_id: Uuid,
+        article_slug: Slug,
+        comment_id: CommentId,
+    },
+    /// Published for each user mentioned in an added comment
+    UserMentioned {
+        author_id: Uuid,
+        username: String,
+        article_slug: Slug,
+        comment_id: CommentId,
+    },
+    CommentDeleted {
+        user_id: Uuid,
+        article_slug: Slug,
+        comment_id: CommentId,
+    },
+    ContentReported {
+        reporter_id: Uuid,
+        report_id: i64,
+        article_slug: Slug,
+        /// Set when a comment was reported, rather than the article
+        comment_id: Option<CommentId>,
+    },
+}
+
+///
+/// Mockable event publisher.
+///
+/// Events are published after the change has been stored,
+/// and publishing never fails the operation that caused it.
+///
+#[entrait(mock_api=DomainEventsMock)]
+pub trait DomainEvents {
+    fn publish(&self, event: Event);
+}
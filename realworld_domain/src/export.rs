@@ -0,0 +1,353 @@
+//!
+//! Portable dumps of articles and comments, so that users can take their content elsewhere.
+//!
+
+use crate::article::repo::{ArticleRepo, Filter};
+use crate::article::Slug;
+use crate::comment::repo::{ArticleComment, CommentRepo};
+use crate::error::{RwError, RwResult};
+use crate::iter_util::Single;
+use crate::timestamp::Timestamptz;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::repo::UserRepo;
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use std::fmt::Write;
+
+#[derive(serde::Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON document
+    #[default]
+    Json,
+    /// A zip archive of Markdown files
+    Markdown,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedArticle {
+    pub slug: Slug,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    pub author: String,
+    pub created_at: Timestamptz,
+    pub updated_at: Timestamptz,
+    pub favorites_count: i64,
+}
+
+impl From<crate::article::repo::Article> for ExportedArticle {
+    fn from(article: crate::article::repo::Article) -> Self {
+        Self {
+            slug: article.slug,
+            title: article.title,
+            description: article.description,
+            body: article.body,
+            tag_list: article.tag_list,
+            author: article.author_username,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+            favorites_count: article.favorites_count,
+        }
+    }
+}
+
+impl ExportedArticle {
+    /// The article as Markdown with YAML front matter, followed by its comments
+    pub fn to_markdown(&self, comments: &[ExportedComment]) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "---");
+        let _ = writeln!(markdown, "title: {}", yaml_value(&self.title));
+        let _ = writeln!(markdown, "slug: {}", yaml_value(&self.slug));
+        let _ = writeln!(markdown, "description: {}", yaml_value(&self.description));
+        let _ = writeln!(markdown, "author: {}", yaml_value(&self.author));
+        let _ = writeln!(markdown, "tags: {}", yaml_value(&self.tag_list));
+        let _ = writeln!(markdown, "createdAt: {}", self.created_at);
+        let _ = writeln!(markdown, "updatedAt: {}", self.updated_at);
+        let _ = writeln!(markdown, "favoritesCount: {}", self.favorites_count);
+        let _ = writeln!(markdown, "---\n");
+        let _ = writeln!(markdown, "{}", self.body.trim_end());
+
+        if !comments.is_empty() {
+            let _ = writeln!(markdown, "\n## Comments");
+            for comment in comments {
+                let _ = write!(
+                    markdown,
+                    "\n### {}, {}\n\n{}\n",
+                    comment.author,
+                    comment.created_at,
+                    comment.body.trim_end()
+                );
+            }
+        }
+
+        markdown
+    }
+}
+
+/// JSON strings are valid YAML, and take care of quoting
+fn yaml_value(value: &impl serde::Serialize) -> String {
+    serde_json::to_string(value).expect("strings should serialize")
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedComment {
+    pub article_slug: Slug,
+    pub author: String,
+    pub body: String,
+    pub created_at: Timestamptz,
+    pub updated_at: Timestamptz,
+}
+
+impl From<ArticleComment> for ExportedComment {
+    fn from(
+        ArticleComment {
+            article_slug,
+            comment,
+        }: ArticleComment,
+    ) -> Self {
+        Self {
+            article_slug,
+            author: comment.author_username,
+            body: comment.body,
+            created_at: Timestamptz(comment.created_at),
+            updated_at: Timestamptz(comment.updated_at),
+        }
+    }
+}
+
+impl ExportedComment {
+    /// A section for a list of comments written on different articles
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "## On {}, {}\n\n{}\n\n",
+            self.article_slug,
+            self.created_at,
+            self.body.trim_end()
+        )
+    }
+}
+
+/// One article with all its comments
+#[derive(serde::Serialize, Debug)]
+pub struct ArticleExport {
+    pub article: ExportedArticle,
+    pub comments: Vec<ExportedComment>,
+}
+
+///
+/// Everything a user has written. The content is read lazily,
+/// so that it can be sent to the client while it's being read.
+///
+pub struct UserExport {
+    pub username: String,
+    pub articles: BoxStream<'static, RwResult<ExportedArticle>>,
+    pub comments: BoxStream<'static, RwResult<ExportedComment>>,
+}
+
+#[entrait(pub ExportArticle, mock_api=ExportArticleMock)]
+async fn export_article(
+    deps: &(impl ArticleRepo + CommentRepo),
+    slug: &Slug,
+) -> RwResult<ArticleExport> {
+    let article = deps
+        .select_articles(
+            UserId(None),
+            Filter {
+                slug: Some(slug),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .single_or_none()?
+        // Unlisted articles are only seen through their share link
+        .filter(|article| article.share_token.is_none())
+        .ok_or(RwError::ArticleNotFound)?;
+
+    let article_id = deps.fetch_article_id(slug).await?;
+    let comments = deps
+        .list_comments(UserId(None), article_id, Default::default())
+        .await?;
+
+    Ok(ArticleExport {
+        comments: comments
+            .into_iter()
+            .map(|comment| {
+                ExportedComment::from(ArticleComment {
+                    article_slug: article.slug.clone(),
+                    comment,
+                })
+            })
+            .collect(),
+        article: article.into(),
+    })
+}
+
+#[entrait(pub ExportUser, mock_api=ExportUserMock)]
+async fn export_user(
+    deps: &(impl Authenticate + UserRepo + ArticleRepo + CommentRepo),
+    token: Token,
+) -> RwResult<UserExport> {
+    let current_user_id = deps.authenticate(token).await?;
+    let (user, _) = deps
+        .find_user_credentials_by_id(current_user_id)
+        .await?
+        .ok_or(RwError::Unauthorized)?;
+
+    Ok(UserExport {
+        username: user.username,
+        articles: deps
+            .stream_articles_by_author(current_user_id)
+            .map_ok(Into::into)
+            .boxed(),
+        comments: deps
+            .stream_comments_by_author(current_user_id)
+            .map_ok(Into::into)
+            .boxed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::repo::ArticleRepoMock;
+    use crate::comment::repo::{Comment, CommentRepoMock};
+    use crate::comment::CommentId;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::repo::{Credentials, User, UserRepoMock};
+
+    use unimock::*;
+
+    fn test_timestamp() -> time::OffsetDateTime {
+        time::OffsetDateTime::from_unix_timestamp(0).unwrap()
+    }
+
+    fn test_comment(body: &str) -> Comment {
+        Comment {
+            comment_id: CommentId(1),
+            created_at: test_timestamp(),
+            updated_at: test_timestamp(),
+            body: body.to_string(),
+            author_id: uuid::Uuid::from_u128(1),
+            author_username: "commenter".to_string(),
+            likes_count: 0,
+            liked: false,
+            mentions: vec![],
+        }
+    }
+
+    #[test]
+    fn article_markdown_should_have_front_matter_and_comments() {
+        let article = ExportedArticle {
+            slug: Slug::from("slug"),
+            title: "A \"quoted\" title".to_string(),
+            description: "desc".to_string(),
+            body: "# Body\n".to_string(),
+            tag_list: vec!["a".to_string(), "b".to_string()],
+            author: "author".to_string(),
+            created_at: Timestamptz(test_timestamp()),
+            updated_at: Timestamptz(test_timestamp()),
+            favorites_count: 2,
+        };
+        let comments = [ExportedComment::from(ArticleComment {
+            article_slug: Slug::from("slug"),
+            comment: test_comment("Nice"),
+        })];
+
+        assert_eq!(
+            article.to_markdown(&comments),
+            r#"---
+title: "A \"quoted\" title"
+slug: "slug"
+description: "desc"
+author: "author"
+tags: ["a","b"]
+createdAt: 1970-01-01T00:00:00Z
+updatedAt: 1970-01-01T00:00:00Z
+favoritesCount: 2
+---
+
+# Body
+
+## Comments
+
+### commenter, 1970-01-01T00:00:00Z
+
+Nice
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn export_user_should_stream_articles_and_comments_of_current_user() {
+        let user_id = UserId(uuid::Uuid::new_v4());
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(user_id)),
+            UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, user_id| {
+                    Ok(Some((
+                        User {
+                            user_id,
+                            username: "username".to_string(),
+                            bio: "".to_string(),
+                            image: None,
+                            role: Default::default(),
+                        },
+                        Credentials {
+                            email: "email".parse().unwrap(),
+                            password_hash: "hash".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            ArticleRepoMock::stream_articles_by_author
+                .next_call(matching!(_))
+                .answers(&|_, _| futures::stream::empty().boxed()),
+            CommentRepoMock::stream_comments_by_author
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    futures::stream::iter([
+                        Ok(ArticleComment {
+                            article_slug: Slug::from("a"),
+                            comment: test_comment("1"),
+                        }),
+                        Ok(ArticleComment {
+                            article_slug: Slug::from("b"),
+                            comment: test_comment("2"),
+                        }),
+                    ])
+                    .boxed()
+                }),
+        ));
+
+        let export = export_user(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+        assert_eq!("username", export.username);
+        assert_eq!(0, export.articles.count().await);
+
+        let slugs: Vec<_> = export
+            .comments
+            .map_ok(|comment| comment.article_slug.0)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(["a", "b"], slugs.as_slice());
+    }
+}
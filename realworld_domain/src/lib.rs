@@ -1,9 +1,27 @@
 use entrait::entrait_export as entrait;
+use error::RwResult;
+use user::email::EmailMessage;
 
+pub mod admin;
 pub mod article;
+pub mod audit;
 pub mod comment;
+pub mod data_export;
 pub mod error;
+pub mod event;
+pub mod export;
 pub mod iter_util;
+pub mod notification;
+pub mod pagination;
+pub mod recommendation;
+pub mod report;
+pub mod rss;
+pub mod sanitize;
+pub mod schedule;
+pub mod seed;
+pub mod settings;
+pub mod sitemap;
+pub mod stats;
 pub mod timestamp;
 pub mod user;
 
@@ -20,22 +38,89 @@ pub trait System {
 ///
 #[entrait(mock_api=GetConfigMock)]
 pub trait GetConfig {
-    fn get_jwt_signing_key(&self) -> &hmac::Hmac<sha2::Sha384>;
+    /// Whether users must verify their email address before they can log in
+    fn require_email_verification(&self) -> bool;
+
+    /// What happens to the content of users who delete their account
+    fn account_deletion_mode(&self) -> user::repo::DeletionMode;
+
+    /// Failed logins within [GetConfig::failed_login_window] before login is locked.
+    /// 0 means that login is never locked.
+    fn max_failed_logins(&self) -> u32;
+
+    fn failed_login_window(&self) -> time::Duration;
+
+    /// How tags of new and updated articles are normalized
+    fn tag_rules(&self) -> &article::tag::TagRules;
+
+    /// Reading speed that the reading time of articles is based on
+    fn words_per_minute(&self) -> u32;
+
+    /// How far back favorites and comments count towards trending articles
+    fn trending_window(&self) -> time::Duration;
+
+    /// Largest profile image that can be uploaded, in bytes
+    fn max_avatar_bytes(&self) -> usize;
+
+    /// Largest article body that can be posted, in bytes
+    fn max_article_body_bytes(&self) -> usize;
+
+    /// Public URL of the site, which links in feeds and the sitemap point to
+    fn site_url(&self) -> &str;
+
+    /// How article bodies, comments and bios are sanitized before they're saved
+    fn sanitize_mode(&self) -> sanitize::SanitizeMode;
+
+    /// How the slugs of articles are derived from their titles
+    fn slug_strategy(&self) -> article::slugify::SlugStrategy;
+
+    /// Usernames nobody can take, regardless of case
+    fn reserved_usernames(&self) -> &[String];
+
+    /// Default and max page sizes of article and comment listings
+    fn page_sizes(&self) -> pagination::PageSizes;
+
+    /// Whether feeds are read from the feed entries written on publish and follow,
+    /// see [article::feed], rather than from the follows at read time
+    fn materialized_feeds(&self) -> bool;
+
+    /// Whether plus tags are stripped from email addresses before they're saved or looked up
+    fn plus_addressing(&self) -> user::email::PlusAddressing;
+
+    /// The algorithm that new password hashes are made with, and older ones are rehashed with on login
+    fn password_algorithm(&self) -> user::password::PasswordAlgorithm;
+}
+
+///
+/// Mockable outgoing email abstraction
+///
+#[entrait(mock_api=EmailSenderMock)]
+pub trait EmailSender {
+    async fn send_email(&self, message: EmailMessage) -> RwResult<()>;
+}
+
+///
+/// Mockable metrics recorder
+///
+#[entrait(mock_api=RecordMetricsMock)]
+pub trait RecordMetrics {
+    /// A token was presented that could not be verified
+    fn record_auth_failure(&self);
 }
 
 pub mod test {
     use super::*;
     use unimock::*;
 
-    pub fn mock_jwt_signing_key() -> impl unimock::Clause {
-        use hmac::Mac;
+    pub fn mock_jwt_keys() -> impl unimock::Clause {
+        use user::jwt_keys::*;
 
-        GetConfigMock::get_jwt_signing_key
+        JwtKeyProviderMock::jwt_keys
             .each_call(matching!())
-            .returns(
-                hmac::Hmac::<sha2::Sha384>::new_from_slice("foobar".as_bytes())
-                    .expect("HMAC-SHA-384 can accept any key length"),
-            )
+            .returns(JwtKeys::new(
+                "test",
+                JwtKey::new(JwtAlgorithm::Hs384, "foobar".as_bytes()),
+            ))
     }
 
     pub fn mock_current_time() -> impl unimock::Clause {
@@ -45,6 +130,82 @@ pub mod test {
     }
 
     pub fn mock_system_and_config() -> impl unimock::Clause {
-        (mock_jwt_signing_key(), mock_current_time())
+        (mock_jwt_keys(), mock_current_time())
+    }
+
+    pub fn mock_sanitize_mode() -> impl unimock::Clause {
+        GetConfigMock::sanitize_mode
+            .each_call(matching!())
+            .returns(sanitize::SanitizeMode::default())
+    }
+
+    pub fn mock_slug_strategy() -> impl unimock::Clause {
+        GetConfigMock::slug_strategy
+            .each_call(matching!())
+            .returns(article::slugify::SlugStrategy::default())
+    }
+
+    pub fn mock_password_algorithm() -> impl unimock::Clause {
+        GetConfigMock::password_algorithm
+            .each_call(matching!())
+            .returns(user::password::PasswordAlgorithm::default())
+    }
+
+    pub fn mock_reserved_usernames() -> impl unimock::Clause {
+        GetConfigMock::reserved_usernames
+            .each_call(matching!())
+            .returns(vec!["admin".to_string()])
+    }
+
+    pub fn mock_page_sizes() -> impl unimock::Clause {
+        GetConfigMock::page_sizes
+            .each_call(matching!())
+            .returns(crate::pagination::PageSizes::default())
+    }
+
+    pub fn mock_plus_addressing() -> impl unimock::Clause {
+        GetConfigMock::plus_addressing
+            .each_call(matching!())
+            .returns(crate::user::email::PlusAddressing::default())
+    }
+
+    fn article_by_slug(share_token: Option<&str>) -> article::repo::Article {
+        article::repo::Article {
+            article_id: article::ArticleId(uuid::Uuid::from_u128(1)),
+            slug: article::Slug::from("slug"),
+            title: "title".to_string(),
+            description: "".to_string(),
+            body: "".to_string(),
+            tag_list: vec![],
+            created_at: timestamp::Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            updated_at: timestamp::Timestamptz(time::OffsetDateTime::UNIX_EPOCH),
+            favorited: false,
+            favorites_count: 0,
+            views_count: 0,
+            author_id: uuid::Uuid::from_u128(1),
+            author_username: "author".to_string(),
+            share_token: share_token.map(str::to_string),
+        }
+    }
+
+    /// The public article with the slug `slug`, as looked up by the routes nested under it
+    pub fn mock_visible_article() -> impl unimock::Clause {
+        article::repo::ArticleRepoMock::select_articles
+            .next_call(matching!(_, article::repo::Filter { slug: Some(_), .. }))
+            .answers(&|_, _, _| Ok(vec![article_by_slug(None)]))
+    }
+
+    /// An unlisted article with the slug `slug`, by the user with id 1
+    pub fn mock_unlisted_article() -> impl unimock::Clause {
+        article::repo::ArticleRepoMock::select_articles
+            .next_call(matching!(_, article::repo::Filter { slug: Some(_), .. }))
+            .answers(&|_, _, _| Ok(vec![article_by_slug(Some("share-token"))]))
+    }
+
+    /// Accept any number of published events
+    pub fn mock_publish_events() -> impl unimock::Clause {
+        event::DomainEventsMock::publish
+            .each_call(matching!(_))
+            .returns(())
     }
 }
@@ -0,0 +1,217 @@
+pub mod repo;
+
+use crate::article::Slug;
+use crate::error::RwResult;
+use crate::event::Event;
+use crate::timestamp::Timestamptz;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::profile::Profile;
+use crate::user::UserId;
+use repo::NotificationRepo;
+
+use entrait::entrait_export as entrait;
+
+/// What somebody did that the user gets notified about
+#[derive(sqlx::Type, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[sqlx(type_name = "app.notification_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    /// Started following the user
+    Followed,
+    /// Favorited one of the user's articles
+    Favorited,
+    /// Commented on one of the user's articles
+    Commented,
+    /// Mentioned the user in a comment
+    Mentioned,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    id: i64,
+    kind: NotificationKind,
+    created_at: Timestamptz,
+    read: bool,
+    actor: Profile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    article_slug: Option<Slug>,
+}
+
+impl From<repo::Notification> for Notification {
+    fn from(db: repo::Notification) -> Self {
+        Self {
+            id: db.notification_id,
+            kind: db.kind,
+            created_at: Timestamptz(db.created_at),
+            read: db.read_at.is_some(),
+            actor: Profile {
+                username: db.actor_username,
+                bio: db.actor_bio,
+                image: db.actor_image,
+                following: db.following_actor,
+                ..Default::default()
+            },
+            article_slug: db.article_slug,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ListNotificationsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    unread_only: bool,
+}
+
+#[entrait(pub Api, mock_api=mock)]
+pub mod api {
+    use super::*;
+
+    pub async fn list_notifications(
+        deps: &(impl Authenticate + NotificationRepo),
+        token: Token,
+        query: ListNotificationsQuery,
+    ) -> RwResult<Vec<Notification>> {
+        let current_user_id = deps.authenticate(token).await?;
+        Ok(deps
+            .list_notifications(
+                current_user_id,
+                query.unread_only,
+                query.limit.unwrap_or(20),
+                query.offset.unwrap_or(0),
+            )
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    pub async fn mark_notification_read(
+        deps: &(impl Authenticate + NotificationRepo),
+        token: Token,
+        notification_id: i64,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authenticate(token).await?;
+        deps.mark_notification_read(current_user_id, notification_id)
+            .await
+    }
+}
+
+///
+/// Create the notifications that follow from a domain event.
+///
+/// Undoing something, like unfollowing, doesn't retract the notification.
+///
+#[entrait(pub NotifyOnEvent, mock_api=NotifyOnEventMock)]
+async fn notify_on_event(deps: &impl NotificationRepo, event: &Event) -> RwResult<()> {
+    match event {
+        Event::UserFollowed {
+            follower_id,
+            username,
+            following: true,
+        } => {
+            deps.insert_follow_notification(UserId(*follower_id), username)
+                .await
+        }
+        Event::ArticleFavorited {
+            user_id,
+            slug,
+            favorited: true,
+        } => {
+            deps.insert_article_notification(UserId(*user_id), NotificationKind::Favorited, slug)
+                .await
+        }
+        Event::CommentAdded {
+            author_id,
+            article_slug,
+            ..
+        } => {
+            deps.insert_article_notification(
+                UserId(*author_id),
+                NotificationKind::Commented,
+                article_slug,
+            )
+            .await
+        }
+        Event::UserMentioned {
+            author_id,
+            username,
+            article_slug,
+            ..
+        } => {
+            deps.insert_mention_notification(UserId(*author_id), username, article_slug)
+                .await
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comment::CommentId;
+    use repo::NotificationRepoMock;
+
+    use unimock::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn comment_should_notify_article_author() {
+        let deps = Unimock::new(
+            NotificationRepoMock::insert_article_notification
+                .next_call(matching!(_, NotificationKind::Commented, "slug"))
+                .returns(Ok(())),
+        );
+
+        notify_on_event(
+            &deps,
+            &Event::CommentAdded {
+                author_id: Uuid::new_v4(),
+                article_slug: Slug::from("slug"),
+                comment_id: CommentId(1),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mention_should_notify_mentioned_user() {
+        let deps = Unimock::new(
+            NotificationRepoMock::insert_mention_notification
+                .next_call(matching!(_, "mentioned", "slug"))
+                .returns(Ok(())),
+        );
+
+        notify_on_event(
+            &deps,
+            &Event::UserMentioned {
+                author_id: Uuid::new_v4(),
+                username: "mentioned".to_string(),
+                article_slug: Slug::from("slug"),
+                comment_id: CommentId(1),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unfollowing_should_not_notify() {
+        let deps = Unimock::new(());
+
+        notify_on_event(
+            &deps,
+            &Event::UserFollowed {
+                follower_id: Uuid::new_v4(),
+                username: "username".to_string(),
+                following: false,
+            },
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use super::NotificationKind;
+use crate::article::Slug;
+use crate::error::RwResult;
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+use time::OffsetDateTime;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Notification {
+    pub notification_id: i64,
+    pub kind: NotificationKind,
+    pub created_at: OffsetDateTime,
+    pub read_at: Option<OffsetDateTime>,
+    pub actor_username: String,
+    pub actor_bio: String,
+    pub actor_image: Option<String>,
+    pub following_actor: bool,
+    /// The article that was favorited or commented, or that the user was mentioned on
+    pub article_slug: Option<Slug>,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(NotificationRepoImpl, delegate_by=DelegateNotificationRepo, mock_api=NotificationRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(NotificationRepoImpl, delegate_by=ref, mock_api=NotificationRepoMock),
+    async_trait::async_trait
+)]
+pub trait NotificationRepo {
+    /// Notify the user called `username` that `actor` started following them
+    async fn insert_follow_notification(&self, actor: UserId, username: &str) -> RwResult<()>;
+
+    /// Notify the author of an article that `actor` did something with it.
+    /// Nothing is inserted when the actor is the author.
+    async fn insert_article_notification(
+        &self,
+        actor: UserId,
+        kind: NotificationKind,
+        article_slug: &Slug,
+    ) -> RwResult<()>;
+
+    /// Notify the user called `username` that `actor` mentioned them in a comment on the article.
+    /// Nothing is inserted when the actor mentioned themselves.
+    async fn insert_mention_notification(
+        &self,
+        actor: UserId,
+        username: &str,
+        article_slug: &Slug,
+    ) -> RwResult<()>;
+
+    /// Newest first
+    async fn list_notifications(
+        &self,
+        user_id: UserId,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> RwResult<Vec<Notification>>;
+
+    /// Fails with `NotificationNotFound` unless the notification belongs to the user
+    async fn mark_notification_read(&self, user_id: UserId, notification_id: i64) -> RwResult<()>;
+}
@@ -0,0 +1,158 @@
+//!
+//! Limit and offset paging, for listings that don't need a cursor.
+//!
+
+use crate::error::{RwError, RwResult};
+
+/// Page size when no `limit` is given
+pub const DEFAULT_LIMIT: i64 = 20;
+
+#[derive(serde::Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct Pagination {
+    /// `None` means [DEFAULT_LIMIT]
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Pagination {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+/// The configured page sizes of article and comment listings
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PageSizes {
+    /// Page size when no `limit` is given
+    pub default: i64,
+    /// Larger limits are lowered to this
+    pub max: i64,
+    /// Larger offsets are rejected, since the database has to skip every row before them.
+    /// `None` allows any offset.
+    pub max_offset: Option<i64>,
+}
+
+impl Default for PageSizes {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_LIMIT,
+            max: 100,
+            max_offset: Some(10_000),
+        }
+    }
+}
+
+impl PageSizes {
+    ///
+    /// The pagination to query with, always having a limit, which is at most [PageSizes::max].
+    /// A limit that isn't positive, or a negative offset, is invalid.
+    /// An offset beyond [PageSizes::max_offset] is too, and should be paged to with a cursor instead.
+    ///
+    pub fn apply(&self, pagination: Pagination) -> RwResult<Pagination> {
+        if pagination.limit.is_some_and(|limit| limit <= 0) {
+            return Err(RwError::InvalidPagination {
+                field: "limit",
+                problem: "must be positive",
+            });
+        }
+        if pagination.offset.is_some_and(|offset| offset < 0) {
+            return Err(RwError::InvalidPagination {
+                field: "offset",
+                problem: "must not be negative",
+            });
+        }
+        if let Some(max) = self.max_offset {
+            if pagination.offset.is_some_and(|offset| offset > max) {
+                return Err(RwError::OffsetTooLarge { max });
+            }
+        }
+
+        Ok(Pagination {
+            limit: Some(pagination.limit.unwrap_or(self.default).min(self.max)),
+            offset: pagination.offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_matches::*;
+
+    fn pagination(limit: Option<i64>, offset: Option<i64>) -> Pagination {
+        Pagination { limit, offset }
+    }
+
+    #[test]
+    fn limits_should_be_defaulted_and_clamped() {
+        let sizes = PageSizes {
+            default: 10,
+            max: 50,
+            max_offset: None,
+        };
+
+        assert_eq!(Some(10), sizes.apply(pagination(None, None)).unwrap().limit);
+        assert_eq!(
+            Some(30),
+            sizes.apply(pagination(Some(30), None)).unwrap().limit
+        );
+        assert_eq!(
+            pagination(Some(50), Some(100)),
+            sizes.apply(pagination(Some(100_000), Some(100))).unwrap()
+        );
+    }
+
+    #[test]
+    fn non_positive_limits_and_negative_offsets_should_be_invalid() {
+        let sizes = PageSizes::default();
+
+        assert_matches!(
+            sizes.apply(pagination(Some(0), None)),
+            Err(RwError::InvalidPagination { field: "limit", .. })
+        );
+        assert_matches!(
+            sizes.apply(pagination(Some(-1), None)),
+            Err(RwError::InvalidPagination { field: "limit", .. })
+        );
+        assert_matches!(
+            sizes.apply(pagination(None, Some(-1))),
+            Err(RwError::InvalidPagination {
+                field: "offset",
+                ..
+            })
+        );
+        assert_eq!(
+            Some(0),
+            sizes.apply(pagination(None, Some(0))).unwrap().offset
+        );
+    }
+
+    #[test]
+    fn offsets_beyond_max_should_be_rejected() {
+        let sizes = PageSizes {
+            max_offset: Some(1000),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some(1000),
+            sizes.apply(pagination(None, Some(1000))).unwrap().offset
+        );
+        assert_matches!(
+            sizes.apply(pagination(None, Some(1001))),
+            Err(RwError::OffsetTooLarge { max: 1000 })
+        );
+        assert!(PageSizes {
+            max_offset: None,
+            ..sizes
+        }
+        .apply(pagination(None, Some(1_000_000)))
+        .is_ok());
+    }
+}
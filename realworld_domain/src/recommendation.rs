@@ -0,0 +1,131 @@
+//!
+//! Who to follow: authors close to those the user already follows, or whose articles they favorite.
+//!
+
+use crate::error::RwResult;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::profile::Profile;
+use crate::user::repo::{Following, User};
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+
+/// Profiles suggested at once
+pub const MAX_SUGGESTIONS: usize = 10;
+
+/// Candidates that are scored, out of those with the most signals
+const MAX_CANDIDATES: i64 = 100;
+
+/// What relates an author to the user they might be suggested to
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct Signals {
+    /// Users followed by the user, who follow the author
+    pub followed_by_followees: i64,
+    /// Articles of the author favorited by the user
+    pub favorited_articles: i64,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(RecommendationRepoImpl, delegate_by=DelegateRecommendationRepo, mock_api=RecommendationRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(RecommendationRepoImpl, delegate_by=ref, mock_api=RecommendationRepoMock),
+    async_trait::async_trait
+)]
+pub trait RecommendationRepo {
+    /// At most `limit` authors with any signals, most signals first.
+    /// Leaves out the user, and those the user follows or has blocked, and banned users.
+    async fn select_follow_candidates(
+        &self,
+        user_id: UserId,
+        limit: i64,
+    ) -> RwResult<Vec<(User, Signals)>>;
+}
+
+/// A follow of a followee weighs more than a favorite, since it's a choice of the whole author
+pub fn score(signals: &Signals) -> i64 {
+    2 * signals.followed_by_followees + signals.favorited_articles
+}
+
+#[entrait(pub SuggestProfiles, mock_api=SuggestProfilesMock)]
+async fn suggest_profiles(
+    deps: &(impl Authenticate + RecommendationRepo),
+    token: Token,
+) -> RwResult<Vec<Profile>> {
+    let current_user_id = deps.authenticate(token).await?;
+    let mut candidates = deps
+        .select_follow_candidates(current_user_id, MAX_CANDIDATES)
+        .await?;
+    candidates.sort_by(|(user_a, signals_a), (user_b, signals_b)| {
+        score(signals_b)
+            .cmp(&score(signals_a))
+            .then_with(|| user_a.username.cmp(&user_b.username))
+    });
+
+    Ok(candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(user, _)| Profile::from((user, Following(false))))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::auth::authenticate::AuthenticateMock;
+
+    use unimock::*;
+
+    fn candidate(
+        username: &str,
+        followed_by_followees: i64,
+        favorited_articles: i64,
+    ) -> (User, Signals) {
+        (
+            User {
+                user_id: UserId(uuid::Uuid::new_v4()),
+                username: username.to_string(),
+                bio: String::new(),
+                image: None,
+                role: Default::default(),
+            },
+            Signals {
+                followed_by_followees,
+                favorited_articles,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn suggestions_should_be_ranked_by_score() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::from_u128(1)))),
+            RecommendationRepoMock::select_follow_candidates
+                .next_call(matching!((_, limit) if *limit == MAX_CANDIDATES))
+                .answers(&|_, _, _| {
+                    Ok(vec![
+                        candidate("favorited", 0, 3),
+                        candidate("followed", 2, 0),
+                        candidate("both", 1, 1),
+                        candidate("also_both", 1, 1),
+                    ])
+                }),
+        ));
+
+        let suggestions = suggest_profiles(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+        assert_eq!(
+            vec!["followed", "also_both", "both", "favorited"],
+            suggestions
+                .iter()
+                .map(|profile| profile.username.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(suggestions.iter().all(|profile| !profile.following));
+    }
+}
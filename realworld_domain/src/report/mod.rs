@@ -0,0 +1,229 @@
+//!
+//! Users reporting articles and comments that moderators should take a look at.
+//!
+
+pub mod repo;
+
+use crate::article::Slug;
+use crate::comment::CommentId;
+use crate::error::{RwError, RwResult};
+use crate::event::{DomainEvents, Event};
+use crate::pagination::Pagination;
+use crate::timestamp::Timestamptz;
+use crate::user::auth::{Authenticate, AuthorizeRole, Token};
+use crate::user::role::Role;
+use crate::user::UserId;
+use repo::ReportRepo;
+
+use entrait::entrait_export as entrait;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct NewReport {
+    pub reason: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    id: i64,
+    /// Username of the user who reported
+    reporter: String,
+    article_slug: Slug,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment_id: Option<CommentId>,
+    reason: String,
+    created_at: Timestamptz,
+    resolved_at: Option<Timestamptz>,
+}
+
+impl From<repo::Report> for Report {
+    fn from(db: repo::Report) -> Self {
+        Self {
+            id: db.report_id,
+            reporter: db.reporter_username,
+            article_slug: db.article_slug,
+            comment_id: db.comment_id,
+            reason: db.reason,
+            created_at: Timestamptz(db.created_at),
+            resolved_at: db.resolved_at.map(Timestamptz),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct ListReportsQuery {
+    /// Only resolved or only unresolved reports, when given
+    resolved: Option<bool>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[entrait(pub Api, mock_api=mock)]
+pub mod api {
+    use super::*;
+
+    pub async fn report_article(
+        deps: &(impl Authenticate + ReportRepo + DomainEvents),
+        token: Token,
+        slug: &Slug,
+        report: NewReport,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authenticate(token).await?;
+        insert_report(deps, current_user_id, slug, None, &report.reason).await
+    }
+
+    pub async fn report_comment(
+        deps: &(impl Authenticate + ReportRepo + DomainEvents),
+        token: Token,
+        slug: &Slug,
+        comment_id: CommentId,
+        report: NewReport,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authenticate(token).await?;
+        insert_report(
+            deps,
+            current_user_id,
+            slug,
+            Some(comment_id),
+            &report.reason,
+        )
+        .await
+    }
+
+    /// Reports are handled by moderators
+    pub async fn list_reports(
+        deps: &(impl AuthorizeRole + ReportRepo),
+        token: Token,
+        query: ListReportsQuery,
+    ) -> RwResult<Vec<Report>> {
+        deps.authorize_role(token, Role::Moderator).await?;
+        Ok(deps
+            .list_reports(
+                query.resolved,
+                Pagination {
+                    limit: query.limit,
+                    offset: query.offset,
+                },
+            )
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    pub async fn resolve_report(
+        deps: &(impl AuthorizeRole + ReportRepo),
+        token: Token,
+        report_id: i64,
+    ) -> RwResult<()> {
+        deps.authorize_role(token, Role::Moderator).await?;
+        deps.resolve_report(report_id).await
+    }
+}
+
+async fn insert_report(
+    deps: &(impl ReportRepo + DomainEvents),
+    reporter: UserId,
+    slug: &Slug,
+    comment_id: Option<CommentId>,
+    reason: &str,
+) -> RwResult<()> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(RwError::ReportReasonMissing);
+    }
+
+    let report_id = deps
+        .insert_report(reporter, slug, comment_id, reason)
+        .await?;
+    deps.publish(Event::ContentReported {
+        reporter_id: reporter.into_id(),
+        report_id,
+        article_slug: slug.clone(),
+        comment_id,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::DomainEventsMock;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::auth::authorize_role::AuthorizeRoleMock;
+    use repo::ReportRepoMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn mock_authenticate() -> impl unimock::Clause {
+        AuthenticateMock::authenticate
+            .next_call(matching!(_))
+            .returns(Ok(UserId(uuid::Uuid::new_v4())))
+    }
+
+    fn new_report(reason: &str) -> NewReport {
+        NewReport {
+            reason: reason.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reported_comment_should_be_published() {
+        let deps = Unimock::new((
+            mock_authenticate(),
+            ReportRepoMock::insert_report
+                .next_call(matching!(_, "slug", Some(CommentId(2)), "spam"))
+                .returns(Ok(1)),
+            DomainEventsMock::publish
+                .next_call(matching!(Event::ContentReported {
+                    report_id: 1,
+                    comment_id: Some(CommentId(2)),
+                    ..
+                }))
+                .returns(()),
+        ));
+
+        api::report_comment(
+            &deps,
+            Token::from_token("token"),
+            &Slug::from("slug"),
+            CommentId(2),
+            new_report(" spam "),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn blank_reason_should_be_rejected() {
+        let deps = Unimock::new(mock_authenticate());
+
+        assert_matches!(
+            api::report_article(
+                &deps,
+                Token::from_token("token"),
+                &Slug::from("slug"),
+                new_report("  ")
+            )
+            .await,
+            Err(RwError::ReportReasonMissing)
+        );
+    }
+
+    #[tokio::test]
+    async fn user_should_not_list_reports() {
+        let deps = Unimock::new(
+            AuthorizeRoleMock::authorize_role
+                .next_call(matching!(_, Role::Moderator))
+                .returns(Err(RwError::Forbidden)),
+        );
+
+        assert_matches!(
+            api::list_reports(&deps, Token::from_token("token"), Default::default()).await,
+            Err(RwError::Forbidden)
+        );
+    }
+}
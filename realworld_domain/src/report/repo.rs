@@ -0,0 +1,51 @@
+use crate::article::Slug;
+use crate::comment::CommentId;
+use crate::error::RwResult;
+use crate::pagination::Pagination;
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+use time::OffsetDateTime;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Report {
+    pub report_id: i64,
+    pub reporter_username: String,
+    pub article_slug: Slug,
+    /// Set when a comment was reported, rather than the article
+    pub comment_id: Option<CommentId>,
+    pub reason: String,
+    pub created_at: OffsetDateTime,
+    pub resolved_at: Option<OffsetDateTime>,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(ReportRepoImpl, delegate_by=DelegateReportRepo, mock_api=ReportRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(ReportRepoImpl, delegate_by=ref, mock_api=ReportRepoMock),
+    async_trait::async_trait
+)]
+pub trait ReportRepo {
+    /// Report an article, or a comment on it, returning the id of the report.
+    /// Fails with `ArticleNotFound` when there's no such article or comment.
+    async fn insert_report(
+        &self,
+        reporter: UserId,
+        article_slug: &Slug,
+        comment_id: Option<CommentId>,
+        reason: &str,
+    ) -> RwResult<i64>;
+
+    /// Newest first. Only resolved or only unresolved reports when `resolved` is given.
+    async fn list_reports(
+        &self,
+        resolved: Option<bool>,
+        pagination: Pagination,
+    ) -> RwResult<Vec<Report>>;
+
+    /// Fails with `ReportNotFound` when there's no such report
+    async fn resolve_report(&self, report_id: i64) -> RwResult<()>;
+}
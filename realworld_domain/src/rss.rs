@@ -0,0 +1,294 @@
+//!
+//! RSS 2.0 feeds of the latest articles, for feed readers.
+//!
+//! Feed readers don't sign in, so the feeds are the same for everyone.
+//!
+
+use crate::article::repo::{Article, ArticleRepo, Filter, DEFAULT_LIMIT};
+use crate::error::{RwError, RwResult};
+use crate::user::repo::{PastUsernames, UserRepo};
+use crate::user::UserId;
+use crate::GetConfig;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use entrait::entrait_export as entrait;
+use std::fmt::Write;
+
+/// A rendered feed
+pub struct RssFeed(pub String);
+
+impl IntoResponse for RssFeed {
+    fn into_response(self) -> Response {
+        (
+            [(CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+            self.0,
+        )
+            .into_response()
+    }
+}
+
+struct Channel {
+    title: String,
+    description: String,
+    /// Where the feed's content can be seen on the site
+    link: String,
+    /// The feed itself
+    self_link: String,
+}
+
+#[entrait(pub GlobalRssFeed, mock_api=GlobalRssFeedMock)]
+async fn global_rss_feed(deps: &(impl GetConfig + ArticleRepo)) -> RwResult<RssFeed> {
+    let site_url = deps.site_url().trim_end_matches('/');
+    let articles = deps
+        .select_articles(UserId(None), Filter::default())
+        .await?;
+
+    Ok(render(
+        &Channel {
+            title: "Conduit".to_string(),
+            description: "The latest articles".to_string(),
+            link: site_url.to_string(),
+            self_link: format!("{site_url}/api/articles/feed.rss"),
+        },
+        site_url,
+        &articles,
+    ))
+}
+
+#[entrait(pub AuthorRssFeed, mock_api=AuthorRssFeedMock)]
+async fn author_rss_feed(
+    deps: &(impl GetConfig + ArticleRepo + UserRepo),
+    username: &str,
+) -> RwResult<RssFeed> {
+    let site_url = deps.site_url().trim_end_matches('/');
+    let (user, ..) = deps
+        .find_user_by_username(UserId(None), username, PastUsernames(false))
+        .await?
+        .ok_or(RwError::ProfileNotFound)?;
+    let articles = deps
+        .select_articles(
+            UserId(None),
+            Filter {
+                author: Some(&user.username),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    Ok(render(
+        &Channel {
+            title: format!("{} on Conduit", user.username),
+            description: if user.bio.is_empty() {
+                format!("The latest articles by {}", user.username)
+            } else {
+                user.bio.clone()
+            },
+            link: format!("{site_url}/profile/{}", user.username),
+            self_link: format!("{site_url}/api/profiles/{}/feed.rss", user.username),
+        },
+        site_url,
+        &articles,
+    ))
+}
+
+/// At most [DEFAULT_LIMIT] articles, newest first
+fn render(channel: &Channel, site_url: &str, articles: &[Article]) -> RssFeed {
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">"#
+    );
+    let _ = writeln!(xml, "<channel>");
+    let _ = writeln!(xml, "<title>{}</title>", escape(&channel.title));
+    let _ = writeln!(xml, "<link>{}</link>", escape(&channel.link));
+    let _ = writeln!(
+        xml,
+        "<description>{}</description>",
+        escape(&channel.description)
+    );
+    let _ = writeln!(
+        xml,
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape(&channel.self_link)
+    );
+    if let Some(last_updated) = articles.iter().map(|article| &article.updated_at.0).max() {
+        let _ = writeln!(
+            xml,
+            "<lastBuildDate>{}</lastBuildDate>",
+            crate::timestamp::Timestamptz(*last_updated).to_rfc2822()
+        );
+    }
+
+    for article in articles.iter().take(DEFAULT_LIMIT as usize) {
+        let _ = writeln!(xml, "<item>");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&article.title));
+        let _ = writeln!(
+            xml,
+            "<link>{}/article/{}</link>",
+            escape(site_url),
+            escape(article.slug.as_str())
+        );
+        // The link changes with the title, so the slug isn't claimed to be a permalink
+        let _ = writeln!(
+            xml,
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            escape(article.slug.as_str())
+        );
+        let _ = writeln!(
+            xml,
+            "<description>{}</description>",
+            escape(&article.description)
+        );
+        let _ = writeln!(
+            xml,
+            "<dc:creator>{}</dc:creator>",
+            escape(&article.author_username)
+        );
+        for tag in &article.tag_list {
+            let _ = writeln!(xml, "<category>{}</category>", escape(tag));
+        }
+        let _ = writeln!(
+            xml,
+            "<pubDate>{}</pubDate>",
+            article.created_at.to_rfc2822()
+        );
+        let _ = writeln!(xml, "</item>");
+    }
+
+    let _ = writeln!(xml, "</channel>");
+    let _ = writeln!(xml, "</rss>");
+    RssFeed(xml)
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::repo::ArticleRepoMock;
+    use crate::article::{ArticleId, Slug};
+    use crate::timestamp::Timestamptz;
+    use crate::user::repo::{FollowStats, Following, User, UserRepoMock};
+    use crate::user::role::Role;
+    use crate::GetConfigMock;
+
+    use unimock::*;
+
+    fn test_article(slug: &str, title: &str) -> Article {
+        Article {
+            article_id: ArticleId(uuid::Uuid::from_u128(1)),
+            slug: Slug::from(slug),
+            title: title.to_string(),
+            description: "Ever wonder how?".to_string(),
+            body: "It takes a Jacobian".to_string(),
+            tag_list: vec!["dragons".to_string()],
+            created_at: Timestamptz(time::OffsetDateTime::from_unix_timestamp(1570864850).unwrap()),
+            updated_at: Timestamptz(time::OffsetDateTime::from_unix_timestamp(1570864850).unwrap()),
+            favorited: false,
+            favorites_count: 0,
+            views_count: 0,
+            author_id: uuid::Uuid::from_u128(1),
+            author_username: "jake".to_string(),
+            share_token: None,
+        }
+    }
+
+    fn mock_site_url() -> impl unimock::Clause {
+        GetConfigMock::site_url
+            .each_call(matching!())
+            .returns("https://example.com/".to_string())
+    }
+
+    #[tokio::test]
+    async fn global_feed_should_have_an_item_per_article() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(UserId(None), _))
+                .answers(&|_, _, _| {
+                    Ok(vec![
+                        test_article("dragons", "How to train your dragon"),
+                        test_article("r-and-d", "R&D <notes>"),
+                    ])
+                }),
+        ));
+
+        let RssFeed(xml) = global_rss_feed(&deps).await.unwrap();
+
+        assert_eq!(2, xml.matches("<item>").count());
+        assert!(xml.contains("<link>https://example.com/article/dragons</link>"));
+        assert!(xml.contains(r#"<guid isPermaLink="false">dragons</guid>"#));
+        assert!(xml.contains("<title>R&amp;D &lt;notes&gt;</title>"));
+        assert!(xml.contains("<pubDate>Sat, 12 Oct 2019 07:20:50 +0000</pubDate>"));
+        assert!(xml.contains(
+            r#"<atom:link href="https://example.com/api/articles/feed.rss" rel="self" type="application/rss+xml"/>"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn author_feed_of_unknown_user_should_not_be_found() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(UserId(None), "nobody", _))
+                .returns(Ok(None)),
+        ));
+
+        assert!(matches!(
+            author_rss_feed(&deps, "nobody").await,
+            Err(RwError::ProfileNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn author_feed_should_only_have_articles_by_author() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            UserRepoMock::find_user_by_username
+                .next_call(matching!(UserId(None), "jake", _))
+                .answers(&|_, _, username, _| {
+                    Ok(Some((
+                        User {
+                            user_id: UserId(uuid::Uuid::from_u128(1)),
+                            username: username.to_string(),
+                            bio: "".to_string(),
+                            image: None,
+                            role: Role::User,
+                        },
+                        Following(false),
+                        FollowStats::default(),
+                    )))
+                }),
+            ArticleRepoMock::select_articles
+                .next_call(matching!(
+                    UserId(None),
+                    Filter {
+                        author: Some("jake"),
+                        ..
+                    }
+                ))
+                .answers(&|_, _, _| Ok(vec![test_article("dragons", "Dragons")])),
+        ));
+
+        let RssFeed(xml) = author_rss_feed(&deps, "jake").await.unwrap();
+
+        assert!(xml.contains("<title>jake on Conduit</title>"));
+        assert!(xml.contains("<link>https://example.com/profile/jake</link>"));
+        assert_eq!(1, xml.matches("<item>").count());
+    }
+}
@@ -0,0 +1,506 @@
+//!
+//! Sanitizing text that users write, so that it can't inject HTML or scripts into pages showing it.
+//!
+//! Article bodies, comments and bios are sanitized before they're saved, as configured by
+//! [SanitizeMode]. Sanitizing is idempotent: sanitizing sanitized text changes nothing,
+//! so text that is saved again, e.g. by an unchanged update, stays the same.
+//!
+//! In [SanitizeMode::Markdown], code spans and code blocks are kept as written,
+//! since Markdown shows them as text, like the `<T>` in `` `Vec<T>` ``.
+//!
+
+/// How text is sanitized
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SanitizeMode {
+    /// Remove all HTML tags and comments
+    Strip,
+    /// Escape HTML, so that it shows as text
+    Escape,
+    /// Keep Markdown and basic formatting tags without attributes, removing other tags,
+    /// and links with a scheme that can run scripts. Code is left alone.
+    #[default]
+    Markdown,
+}
+
+/// Formatting tags that are kept in [SanitizeMode::Markdown]
+const SAFE_TAGS: &[&str] = &[
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "em",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "strong",
+    "sub",
+    "sup",
+    "ul",
+];
+
+/// Link schemes that run scripts or embed content instead of linking
+const UNSAFE_SCHEMES: &[&str] = &["javascript", "vbscript", "data"];
+
+pub fn sanitize(mode: SanitizeMode, text: &str) -> String {
+    match mode {
+        SanitizeMode::Strip => until_unchanged(text, |text| filter_tags(text, |_| false)),
+        SanitizeMode::Escape => escape(text),
+        SanitizeMode::Markdown => until_unchanged(text, |text| {
+            outside_code(text, |text| {
+                neutralize_links(&filter_tags(text, |name| SAFE_TAGS.contains(&name)))
+            })
+        }),
+    }
+}
+
+/// Passes before giving up on text that keeps changing, see [until_unchanged]
+const MAX_PASSES: usize = 4;
+
+///
+/// Removing tags can change which parts of Markdown are code, so passes are repeated.
+/// A pass takes time linear in the text, and text normally settles after the second.
+/// Text still changing after [MAX_PASSES] is made to, and is escaped, which shows it as written.
+///
+fn until_unchanged(text: &str, pass: impl Fn(&str) -> String) -> String {
+    let mut text = pass(text);
+    for _ in 1..MAX_PASSES {
+        let next = pass(&text);
+        if next == text {
+            return text;
+        }
+        text = next;
+    }
+    neutralize_links(&escape(&text))
+}
+
+///
+/// Apply `pass` to the parts of Markdown `text` that aren't code spans or code blocks.
+///
+/// The code is found by a CommonMark parser, so that it's the code a renderer shows as text.
+/// Something like `` <a title="`">`` is a tag rather than the start of a code span.
+///
+fn outside_code(text: &str, pass: impl Fn(&str) -> String) -> String {
+    use pulldown_cmark::{Event, Parser, Tag};
+
+    let mut output = String::with_capacity(text.len());
+    let mut copied = 0;
+
+    for (event, range) in Parser::new(text).into_offset_iter() {
+        if !matches!(event, Event::Code(_) | Event::Start(Tag::CodeBlock(_))) {
+            continue;
+        }
+        if range.start < copied {
+            continue;
+        }
+        output.push_str(&pass(&text[copied..range.start]));
+        output.push_str(&text[range.clone()]);
+        copied = range.end;
+    }
+
+    output.push_str(&pass(&text[copied..]));
+    output
+}
+
+///
+/// Remove comments and the tags that aren't `keep`, and the attributes of those that are.
+/// A `<` that doesn't start a tag is left alone, like in `a < b`.
+/// An unterminated tag is removed along with the rest of the text.
+///
+/// Removing a tag can join a `<` before it with the text after it into a new tag, like in
+/// `<<b>script>`, so that `<` is taken back and looked at again. That way one scan removes
+/// every tag, and filtering the output again changes nothing.
+///
+fn filter_tags(text: &str, keep: impl Fn(&str) -> bool) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        // The text after the `<`, as long as the `<` may start a tag
+        let mut after = &rest[start + 1..];
+
+        loop {
+            let Some(removed) = filter_tag(after, &keep, &mut output) else {
+                output.push('<');
+                rest = after;
+                break;
+            };
+            rest = removed;
+            if rest.is_empty() || !output.ends_with('<') {
+                break;
+            }
+            output.pop();
+            after = rest;
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+///
+/// Remove the tag or comment after a `<`, pushing the tag to `output` if it's kept.
+/// Returns the text after it, or `None` when the `<` doesn't start one.
+///
+fn filter_tag<'t>(
+    after: &'t str,
+    keep: impl Fn(&str) -> bool,
+    output: &mut String,
+) -> Option<&'t str> {
+    if let Some(comment) = after.strip_prefix("!--") {
+        return Some(comment.find("-->").map_or("", |end| &comment[end + 3..]));
+    }
+
+    let starts_tag = after
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+    if !starts_tag {
+        return None;
+    }
+
+    let Some(end) = after.find('>') else {
+        return Some("");
+    };
+    let (closing, name) = match after[..end].strip_prefix('/') {
+        Some(name) => ("/", name),
+        None => ("", &after[..end]),
+    };
+    let name = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if keep(&name) {
+        output.push_str(&format!("<{closing}{name}>"));
+    }
+    Some(&after[end + 1..])
+}
+
+///
+/// Replace the targets of Markdown links and images having an unsafe scheme with `#`,
+/// both inline ones like `[text](target)` and reference definitions like `[id]: target`.
+///
+fn neutralize_links(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for line in text.split_inclusive('\n') {
+        if let Some(target_start) = reference_target_start(line) {
+            let target = &line[target_start..];
+            let target_end = target.find(char::is_whitespace).unwrap_or(target.len());
+            if is_unsafe_url(&target[..target_end]) {
+                output.push_str(&line[..target_start]);
+                output.push('#');
+                output.push_str(&target[target_end..]);
+                continue;
+            }
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find("](") {
+            let target = &rest[start + 2..];
+            let target_end = link_target_end(target);
+            output.push_str(&rest[..start + 2]);
+            if is_unsafe_url(&target[..target_end]) {
+                output.push('#');
+            } else {
+                output.push_str(&target[..target_end]);
+            }
+            rest = &target[target_end..];
+        }
+        output.push_str(rest);
+    }
+
+    output
+}
+
+/// Where the target of an inline link ends, at the `)` closing it. Targets may have balanced parentheses.
+fn link_target_end(target: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in target.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return i,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    target.len()
+}
+
+/// Where the target starts in a line like `[id]: target`
+fn reference_target_start(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    let label_end = trimmed.strip_prefix('[')?.find("]:")? + 1;
+    let after_label = &trimmed[label_end + 2..];
+    let target = after_label.trim_start();
+    if target.is_empty() {
+        return None;
+    }
+    Some(line.len() - target.len())
+}
+
+///
+/// Browsers ignore whitespace and control characters within the scheme, and its case.
+/// Markdown decodes entities in link targets, so any character may be written as one,
+/// like the `j` in `&#106;avascript:`.
+///
+fn is_unsafe_url(url: &str) -> bool {
+    let url: String = decode_entities(url.trim_start_matches('<'))
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    UNSAFE_SCHEMES.iter().any(|scheme| {
+        url.strip_prefix(scheme)
+            .is_some_and(|rest| rest.starts_with([':', '&']))
+    })
+}
+
+///
+/// Decode numeric entities like `&#106;` and `&#x6A;`, and the named ones for characters
+/// that matter in a scheme. Other named entities are left as they are.
+///
+fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let entity = &rest[start + 1..];
+        let decoded = entity
+            .find(';')
+            .filter(|_| starts_entity(entity))
+            .and_then(|end| Some((decode_entity(&entity[..end])?, end)));
+        match decoded {
+            Some((c, end)) => {
+                output.push(c);
+                rest = &entity[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = entity;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// The character of an entity named like `#106`, `#x6A` or `colon`
+fn decode_entity(name: &str) -> Option<char> {
+    let code = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        decimal.parse().ok()?
+    } else {
+        return match name {
+            "colon" => Some(':'),
+            "Tab" => Some('\t'),
+            "NewLine" => Some('\n'),
+            _ => None,
+        };
+    };
+    // Like an invalid code point, which Markdown decodes to the replacement character
+    Some(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+/// Escape the characters that are special in HTML, leaving alone the entities already escaped
+fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            '&' if !starts_entity(&text[i + 1..]) => output.push_str("&amp;"),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Whether the text after a `&` makes it an entity like `&amp;`, `&#39;` or `&#x27;`
+fn starts_entity(text: &str) -> bool {
+    let Some(end) = text.find(';') else {
+        return false;
+    };
+    let name = &text[..end];
+    let valid = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit())
+    } else {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    valid && name.len() <= 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    const MODES: [SanitizeMode; 3] = [
+        SanitizeMode::Strip,
+        SanitizeMode::Escape,
+        SanitizeMode::Markdown,
+    ];
+
+    #[test]
+    fn strip_should_remove_all_tags() {
+        assert_eq!(
+            "Hello alert(1) world",
+            sanitize(
+                SanitizeMode::Strip,
+                "<p>Hello <script>alert(1)</script> <!-- hidden -->world</p>"
+            )
+        );
+        assert_eq!("a < b", sanitize(SanitizeMode::Strip, "a < b"));
+        assert_eq!("", sanitize(SanitizeMode::Strip, "<<b>script>"));
+        assert_eq!("text ", sanitize(SanitizeMode::Strip, "text <img src=x"));
+    }
+
+    #[test]
+    fn nested_tags_should_be_removed_in_one_scan() {
+        let text = "<".repeat(30_000) + &"x>".repeat(30_000);
+        assert_eq!("", filter_tags(&text, |_| false));
+
+        for mode in [SanitizeMode::Strip, SanitizeMode::Markdown] {
+            assert_eq!("", sanitize(mode, &text), "{mode:?}");
+            assert_eq!("a << b", sanitize(mode, "a <<<x>x>< b"), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn escape_should_leave_entities_alone() {
+        assert_eq!(
+            "&lt;b&gt;&quot;Tom&quot; &amp; Jerry&lt;/b&gt; &amp;",
+            sanitize(SanitizeMode::Escape, r#"<b>"Tom" & Jerry</b> &amp;"#)
+        );
+        assert_eq!("&#39; &#x27;", sanitize(SanitizeMode::Escape, "' &#x27;"));
+    }
+
+    #[test]
+    fn markdown_should_keep_formatting_tags_without_attributes() {
+        assert_eq!(
+            "# Title\n<b>bold</b> *em* alert(1)",
+            sanitize(
+                SanitizeMode::Markdown,
+                "# Title\n<B onclick=\"x()\">bold</b> *em* <script>alert(1)</script>"
+            )
+        );
+        assert_eq!(
+            "",
+            sanitize(SanitizeMode::Markdown, "<javascript:alert(1)>")
+        );
+    }
+
+    #[test]
+    fn markdown_should_neutralize_unsafe_links() {
+        assert_eq!(
+            "[ok](https://example.com) [bad](#) [database](database.html) ![img](#)\n[id]: # \"title\"\n",
+            sanitize(
+                SanitizeMode::Markdown,
+                "[ok](https://example.com) [bad](Java\tScript&#58;alert(1)) [database](database.html) ![img](data:text/html,x)\n[id]: javascript:alert(1) \"title\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_should_keep_code_as_written() {
+        let text =
+            "Use `Vec<String>`, or:\n\n```rust\nlet v: Vec<u8> = vec![];\n```\n\n    <indented>\n";
+        assert_eq!(text, sanitize(SanitizeMode::Markdown, text));
+
+        assert_eq!(
+            "<b>x</b> `<script>` ",
+            sanitize(
+                SanitizeMode::Markdown,
+                "<b>x</b> `<script>` <img src=x onerror=alert(1)>"
+            )
+        );
+        // The tag comes first, so the backticks don't make a code span
+        assert_eq!(
+            "x\"> `",
+            sanitize(
+                SanitizeMode::Markdown,
+                "<img title=\"`\" onerror=alert(1)>x\"> `"
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_should_decode_entities_in_link_schemes() {
+        assert_eq!(
+            "[a](#) [b](#) [c](#) [d](&amp;)\n[id]: #\n",
+            sanitize(
+                SanitizeMode::Markdown,
+                "[a](&#106;avascript:alert(1)) [b](jav&#x61;script:alert(1)) [c](java&Tab;script&colon;x) [d](&amp;)\n[id]: &#x6A;avascript:alert(1)\n"
+            )
+        );
+    }
+
+    /// Render Markdown like a frontend would
+    fn render(markdown: &str) -> String {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(markdown));
+        html.to_ascii_lowercase()
+    }
+
+    proptest! {
+        #[test]
+        fn sanitizing_should_be_idempotent(text in r#"[<>/!a-z &;#:"'`\[\]()\-\n]{0,64}"#) {
+            for mode in MODES {
+                let once = sanitize(mode, &text);
+                prop_assert_eq!(&once, &sanitize(mode, &once), "{:?}", mode);
+            }
+        }
+
+        #[test]
+        fn filtering_tags_should_leave_none_to_filter(text in r#"[<>/!a-z -]{0,64}"#) {
+            let keep = |name: &str| SAFE_TAGS.contains(&name);
+            let once = filter_tags(&text, keep);
+            prop_assert_eq!(&once, &filter_tags(&once, keep));
+        }
+
+        #[test]
+        fn text_escaped_after_too_many_passes_should_stay_sanitized(
+            text in r#"[<>/!a-z &;#:"'`\[\]()\-\n]{0,64}"#
+        ) {
+            let escaped = neutralize_links(&escape(&text));
+            prop_assert_eq!(&escaped, &sanitize(SanitizeMode::Markdown, &escaped));
+        }
+
+        #[test]
+        fn sanitized_text_should_have_no_scripts(text in r#"[<>/a-z ="`]{0,64}|.*<script>.*"#) {
+            for mode in MODES {
+                prop_assert!(!render(&sanitize(mode, &text)).contains("<script"), "{:?}", mode);
+            }
+        }
+
+        #[test]
+        fn sanitized_links_should_not_run_scripts(
+            text in r#"\[a\]\(((j|&#106;|&#x6a;)(ava|&#x61;va)[\t ]?(script)(:|&#58;|&colon;)[a-z()]{0,8})\)[`<>]?"#
+        ) {
+            let html = render(&sanitize(SanitizeMode::Markdown, &text));
+            prop_assert!(!html.contains("href=\"javascript"), "{}", html);
+        }
+    }
+}
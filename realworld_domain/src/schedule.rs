@@ -0,0 +1,446 @@
+//!
+//! Recurring maintenance jobs, run on cron-like schedules.
+//!
+//! Schedules are in UTC, and have the five fields of cron: minute, hour, day of month,
+//! month and day of week, e.g. `0 3 * * *` for 03:00 every night.
+//! `@hourly`, `@daily` and `@weekly` are shorthands, and `never` disables a job.
+//!
+
+use crate::article::repo::ViewRepo;
+use crate::data_export::CompileDataExports;
+use crate::error::RwResult;
+use crate::user::repo::{LoginAttemptRepo, RefreshTokenRepo, SessionRepo};
+use crate::{GetConfig, System};
+
+use entrait::entrait_export as entrait;
+use time::{Date, Duration, OffsetDateTime, Time, UtcOffset};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Job {
+    /// Delete sessions and refresh tokens that have expired
+    PurgeRefreshTokens,
+    /// Delete failed logins too old to count towards locking a login
+    PurgeLoginAttempts,
+    /// Delete who viewed articles on earlier days, keeping the counts
+    PurgeArticleViews,
+    /// Compile the data exports users have asked for, and purge the archives no longer offered
+    CompileDataExports,
+}
+
+impl Job {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PurgeRefreshTokens => "purge_refresh_tokens",
+            Self::PurgeLoginAttempts => "purge_login_attempts",
+            Self::PurgeArticleViews => "purge_article_views",
+            Self::CompileDataExports => "compile_data_exports",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("invalid schedule: {0}")]
+pub struct ScheduleError(String);
+
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    source: String,
+    fields: Option<CronFields>,
+}
+
+#[derive(Clone, Debug)]
+struct CronFields {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+/// The values a field matches, as bits
+#[derive(Clone, Copy, Debug)]
+struct Field {
+    bits: u64,
+    any: bool,
+}
+
+impl Field {
+    fn parse(text: &str, min: u8, max: u8) -> Result<Self, ScheduleError> {
+        let invalid = || ScheduleError(format!("`{text}` is not within {min}-{max}"));
+        let number = |text: &str| -> Result<u8, ScheduleError> {
+            match text.parse() {
+                Ok(number) if (min..=max).contains(&number) => Ok(number),
+                _ => Err(invalid()),
+            }
+        };
+
+        let mut bits = 0;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse().map_err(|_| invalid())?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(invalid());
+            }
+            let (first, last) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((first, last)) => (number(first)?, number(last)?),
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            };
+            for value in (first..=last).step_by(step) {
+                bits |= 1 << value;
+            }
+        }
+
+        Ok(Self {
+            bits,
+            any: text == "*",
+        })
+    }
+
+    fn contains(&self, value: u8) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = ScheduleError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let cron = match source.trim() {
+            "never" => {
+                return Ok(Self {
+                    source: source.to_string(),
+                    fields: None,
+                })
+            }
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            cron => cron,
+        };
+        let [minutes, hours, days, months, weekdays] = cron
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| ScheduleError(format!("`{source}` does not have five fields")))?;
+
+        let mut weekdays = Field::parse(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays.contains(7) {
+            weekdays.bits |= 1;
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            fields: Some(CronFields {
+                minutes: Field::parse(minutes, 0, 59)?,
+                hours: Field::parse(hours, 0, 23)?,
+                days: Field::parse(days, 1, 31)?,
+                months: Field::parse(months, 1, 12)?,
+                weekdays,
+            }),
+        })
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl CronFields {
+    /// Like cron, a day matches either field when both are restricted
+    fn matches_day(&self, date: Date) -> bool {
+        let day = self.days.contains(date.day());
+        let weekday = self
+            .weekdays
+            .contains(date.weekday().number_days_from_sunday());
+
+        if self.days.any || self.weekdays.any {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
+
+impl Schedule {
+    /// The first time after `time` that the schedule is due, if within a few years
+    pub fn next_after(&self, time: OffsetDateTime) -> Option<OffsetDateTime> {
+        let fields = self.fields.as_ref()?;
+        let time = time.to_offset(UtcOffset::UTC);
+        let mut next = time.replace_time(Time::from_hms(time.hour(), time.minute(), 0).ok()?)
+            + Duration::minutes(1);
+        let give_up = next + Duration::days(5 * 366);
+
+        while next < give_up {
+            if !fields.months.contains(next.month() as u8) {
+                let (year, month) = match next.month() {
+                    time::Month::December => (next.year() + 1, time::Month::January),
+                    month => (next.year(), month.next()),
+                };
+                next = Date::from_calendar_date(year, month, 1)
+                    .ok()?
+                    .midnight()
+                    .assume_utc();
+            } else if !fields.matches_day(next.date()) {
+                next = next.replace_time(Time::MIDNIGHT) + Duration::days(1);
+            } else if !fields.hours.contains(next.hour()) {
+                next = next.replace_minute(0).ok()? + Duration::hours(1);
+            } else if !fields.minutes.contains(next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
+
+///
+/// Jobs, with the time each of them is next due.
+///
+pub struct Scheduler {
+    entries: Vec<(Job, Schedule, Option<OffsetDateTime>)>,
+}
+
+impl Scheduler {
+    pub fn new(jobs: impl IntoIterator<Item = (Job, Schedule)>, now: OffsetDateTime) -> Self {
+        Self {
+            entries: jobs
+                .into_iter()
+                .map(|(job, schedule)| {
+                    let next = schedule.next_after(now);
+                    (job, schedule, next)
+                })
+                .collect(),
+        }
+    }
+
+    /// The jobs that are due at `now`, which are then scheduled for their next time.
+    /// A job that was due several times since last asked is only due once.
+    pub fn take_due(&mut self, now: OffsetDateTime) -> Vec<Job> {
+        let mut due = vec![];
+        for (job, schedule, next) in &mut self.entries {
+            if next.is_some_and(|next| next <= now) {
+                due.push(*job);
+                *next = schedule.next_after(now);
+            }
+        }
+        due
+    }
+
+    /// When the next job is due, if ever
+    pub fn next_due(&self) -> Option<OffsetDateTime> {
+        self.entries.iter().filter_map(|(_, _, next)| *next).min()
+    }
+}
+
+/// Run one job, returning how many rows it affected
+#[entrait(pub RunJob, mock_api=RunJobMock)]
+async fn run_job(
+    deps: &(impl System
+          + GetConfig
+          + RefreshTokenRepo
+          + SessionRepo
+          + LoginAttemptRepo
+          + ViewRepo
+          + CompileDataExports),
+    job: Job,
+) -> RwResult<u64> {
+    let now = deps.get_current_time();
+    match job {
+        // Expired sessions take their refresh tokens with them
+        Job::PurgeRefreshTokens => Ok(deps.delete_expired_sessions(now).await?
+            + deps.delete_expired_refresh_tokens(now).await?),
+        Job::PurgeLoginAttempts => {
+            deps.delete_failed_logins_before(now - deps.failed_login_window())
+                .await
+        }
+        Job::PurgeArticleViews => deps.delete_views_before(now.date()).await,
+        Job::CompileDataExports => deps.compile_data_exports().await,
+    }
+}
+
+/// Run the jobs of the scheduler that are due, returning when to call again.
+/// A failed job is logged, and tried again at its next time.
+#[entrait(pub RunDueJobs, mock_api=RunDueJobsMock)]
+async fn run_due_jobs(
+    deps: &(impl System + RunJob),
+    scheduler: &mut Scheduler,
+) -> Option<OffsetDateTime> {
+    for job in scheduler.take_due(deps.get_current_time()) {
+        match deps.run_job(job).await {
+            Ok(affected) => tracing::info!(job = job.name(), affected, "ran scheduled job"),
+            Err(error) => tracing::error!(job = job.name(), %error, "scheduled job failed"),
+        }
+    }
+
+    scheduler.next_due()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RwError;
+    use crate::user::repo::LoginAttemptRepoMock;
+    use crate::{GetConfigMock, SystemMock};
+
+    use time::macros::datetime;
+    use unimock::*;
+
+    fn schedule(source: &str) -> Schedule {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn nightly_schedule_should_be_due_next_night() {
+        let nightly = schedule("0 3 * * *");
+
+        assert_eq!(
+            Some(datetime!(2024-05-02 03:00 UTC)),
+            nightly.next_after(datetime!(2024-05-01 03:00 UTC))
+        );
+        assert_eq!(
+            Some(datetime!(2024-05-01 03:00 UTC)),
+            nightly.next_after(datetime!(2024-05-01 02:59:30 UTC))
+        );
+        assert_eq!(
+            Some(datetime!(2025-01-01 03:00 UTC)),
+            nightly.next_after(datetime!(2024-12-31 04:00 UTC))
+        );
+    }
+
+    #[test]
+    fn schedule_should_support_lists_ranges_and_steps() {
+        assert_eq!(
+            Some(datetime!(2024-05-01 10:15 UTC)),
+            schedule("*/15 9-17 * * *").next_after(datetime!(2024-05-01 10:05 UTC))
+        );
+        assert_eq!(
+            Some(datetime!(2024-05-02 09:00 UTC)),
+            schedule("*/15 9-17 * * *").next_after(datetime!(2024-05-01 17:45 UTC))
+        );
+        // 2024-05-01 is a Wednesday
+        assert_eq!(
+            Some(datetime!(2024-05-05 00:00 UTC)),
+            schedule("@weekly").next_after(datetime!(2024-05-01 00:00 UTC))
+        );
+        assert_eq!(
+            Some(datetime!(2024-05-03 12:00 UTC)),
+            schedule("0 12 * * 1,5").next_after(datetime!(2024-05-01 00:00 UTC))
+        );
+        assert_eq!(
+            Some(datetime!(2028-02-29 00:00 UTC)),
+            schedule("0 0 29 2 *").next_after(datetime!(2024-03-01 00:00 UTC))
+        );
+    }
+
+    #[test]
+    fn invalid_schedules_should_be_rejected() {
+        for invalid in [
+            "0 3 * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{invalid}");
+        }
+        assert_eq!(
+            None,
+            schedule("never").next_after(datetime!(2024-05-01 00:00 UTC))
+        );
+    }
+
+    #[test]
+    fn scheduler_should_run_a_late_job_once() {
+        let mut scheduler = Scheduler::new(
+            [
+                (Job::PurgeRefreshTokens, schedule("@daily")),
+                (Job::PurgeLoginAttempts, schedule("@hourly")),
+            ],
+            datetime!(2024-05-01 12:30 UTC),
+        );
+
+        assert_eq!(Some(datetime!(2024-05-01 13:00 UTC)), scheduler.next_due());
+        assert!(scheduler
+            .take_due(datetime!(2024-05-01 12:59 UTC))
+            .is_empty());
+        assert_eq!(
+            vec![Job::PurgeLoginAttempts],
+            scheduler.take_due(datetime!(2024-05-01 15:10 UTC))
+        );
+        assert_eq!(Some(datetime!(2024-05-01 16:00 UTC)), scheduler.next_due());
+        assert_eq!(
+            vec![Job::PurgeRefreshTokens, Job::PurgeLoginAttempts],
+            scheduler.take_due(datetime!(2024-05-02 00:00 UTC))
+        );
+    }
+
+    #[tokio::test]
+    async fn due_jobs_should_run_at_current_time() {
+        let deps = Unimock::new((
+            SystemMock::get_current_time
+                .each_call(matching!())
+                .returns(datetime!(2024-05-01 03:00 UTC)),
+            RunJobMock
+                .next_call(matching!(Job::PurgeRefreshTokens))
+                .returns(Ok(3)),
+        ));
+        let mut scheduler = Scheduler::new(
+            [
+                (Job::PurgeRefreshTokens, schedule("0 3 * * *")),
+                (Job::PurgeLoginAttempts, schedule("never")),
+            ],
+            datetime!(2024-05-01 02:00 UTC),
+        );
+
+        assert_eq!(
+            Some(datetime!(2024-05-02 03:00 UTC)),
+            run_due_jobs(&deps, &mut scheduler).await
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_job_should_not_stop_the_others() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            RunJobMock
+                .next_call(matching!(Job::PurgeRefreshTokens))
+                .returns(Err(RwError::Unauthorized)),
+            RunJobMock
+                .next_call(matching!(Job::PurgeLoginAttempts))
+                .returns(Ok(0)),
+        ));
+        let mut scheduler = Scheduler::new(
+            [
+                (Job::PurgeRefreshTokens, schedule("@hourly")),
+                (Job::PurgeLoginAttempts, schedule("@hourly")),
+            ],
+            OffsetDateTime::UNIX_EPOCH - Duration::hours(1),
+        );
+
+        run_due_jobs(&deps, &mut scheduler).await;
+    }
+
+    #[tokio::test]
+    async fn login_attempts_should_be_purged_after_window() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            GetConfigMock::failed_login_window
+                .next_call(matching!())
+                .returns(Duration::minutes(15)),
+            LoginAttemptRepoMock::delete_failed_logins_before
+                .next_call(matching!((before) if *before == OffsetDateTime::UNIX_EPOCH - Duration::minutes(15)))
+                .returns(Ok(2)),
+        ));
+
+        assert_eq!(2, run_job(&deps, Job::PurgeLoginAttempts).await.unwrap());
+    }
+}
@@ -0,0 +1,315 @@
+//!
+//! Fills the database with made-up users, follows, articles, favorites and comments,
+//! for demos and for tests that need some content to work with.
+//!
+//! The content is generated from a random seed, and the same seed always gives the same content,
+//! so that tests can rely on e.g. which articles exist. Seeding twice fails,
+//! since the generated usernames are taken the second time.
+//!
+
+use crate::article::repo::ArticleRepo;
+use crate::article::Slug;
+use crate::comment::repo::CommentRepo;
+use crate::error::RwResult;
+use crate::user::password::{CleartextPassword, HashPassword};
+use crate::user::repo::UserRepo;
+
+use entrait::entrait_export as entrait;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+
+/// Password of every seeded user
+pub const PASSWORD: &str = "password";
+
+const WORDS: &[&str] = &[
+    "async", "borrow", "cargo", "closure", "crate", "dragon", "enum", "future", "generic",
+    "lifetime", "macro", "match", "module", "pattern", "pointer", "runtime", "slice", "stream",
+    "struct", "task", "trait", "type", "unsafe", "vector",
+];
+
+const TAGS: &[&str] = &[
+    "rust", "web", "database", "testing", "async", "tooling", "design", "dragons",
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct SeedOptions {
+    pub users: usize,
+    /// Each user follows this many other users, at most
+    pub follows_per_user: usize,
+    pub articles_per_user: usize,
+    /// Each user favorites this many articles, at most
+    pub favorites_per_user: usize,
+    pub comments_per_article: usize,
+    /// The same seed gives the same content
+    pub seed: u64,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        Self {
+            users: 10,
+            follows_per_user: 3,
+            articles_per_user: 3,
+            favorites_per_user: 5,
+            comments_per_article: 2,
+            seed: 0,
+        }
+    }
+}
+
+/// What to insert. Users and articles are referred to by their index.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SeedPlan {
+    pub users: Vec<SeedUser>,
+    /// Following user, followed user
+    pub follows: Vec<(usize, usize)>,
+    pub articles: Vec<SeedArticle>,
+    /// User, article
+    pub favorites: Vec<(usize, usize)>,
+    /// User, article, body
+    pub comments: Vec<(usize, usize, String)>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SeedUser {
+    pub username: String,
+    pub email: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SeedArticle {
+    pub author: usize,
+    pub slug: Slug,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+}
+
+/// Numbers of seeded rows
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Seeded {
+    pub users: usize,
+    pub follows: usize,
+    pub articles: usize,
+    pub favorites: usize,
+    pub comments: usize,
+}
+
+impl SeedPlan {
+    pub fn generate(options: SeedOptions) -> Self {
+        let mut rng = StdRng::seed_from_u64(options.seed);
+
+        let users: Vec<SeedUser> = (0..options.users)
+            .map(|user| SeedUser {
+                username: format!("seed-user-{user}"),
+                email: format!("seed-user-{user}@example.com"),
+            })
+            .collect();
+
+        let follows = (0..users.len())
+            .flat_map(|follower| {
+                (0..users.len())
+                    .filter(|followed| *followed != follower)
+                    .choose_multiple(&mut rng, options.follows_per_user)
+                    .into_iter()
+                    .map(move |followed| (follower, followed))
+            })
+            .collect();
+
+        let articles: Vec<SeedArticle> = (0..users.len())
+            .flat_map(|author| (0..options.articles_per_user).map(move |_| author))
+            .enumerate()
+            .map(|(index, author)| {
+                let title = capitalize(&words(&mut rng, 3..6));
+                let tag_count = rng.gen_range(1..4);
+                SeedArticle {
+                    author,
+                    // The index keeps slugs unique even if titles aren't
+                    slug: Slug(format!(
+                        "{}-{index}",
+                        title.to_lowercase().replace(' ', "-")
+                    )),
+                    description: capitalize(&words(&mut rng, 5..10)),
+                    body: (0..rng.gen_range(1..4))
+                        .map(|_| format!("{}.", capitalize(&words(&mut rng, 8..20))))
+                        .join("\n\n"),
+                    tag_list: TAGS
+                        .choose_multiple(&mut rng, tag_count)
+                        .map(|tag| tag.to_string())
+                        .collect(),
+                    title,
+                }
+            })
+            .collect();
+
+        let favorites = (0..users.len())
+            .flat_map(|user| {
+                (0..articles.len())
+                    .choose_multiple(&mut rng, options.favorites_per_user)
+                    .into_iter()
+                    .map(move |article| (user, article))
+            })
+            .collect();
+
+        let comments = (0..articles.len())
+            .flat_map(|article| (0..options.comments_per_article).map(move |_| article))
+            .map(|article| {
+                let user = rng.gen_range(0..users.len());
+                (
+                    user,
+                    article,
+                    format!("{}!", capitalize(&words(&mut rng, 3..12))),
+                )
+            })
+            .collect();
+
+        Self {
+            users,
+            follows,
+            articles,
+            favorites,
+            comments,
+        }
+    }
+}
+
+#[entrait(pub Seed, mock_api=SeedMock)]
+async fn seed(
+    deps: &(impl HashPassword + UserRepo + ArticleRepo + CommentRepo),
+    options: SeedOptions,
+) -> RwResult<Seeded> {
+    let plan = SeedPlan::generate(options);
+
+    // Hashing is slow on purpose, so every user gets the same hash
+    let password_hash = deps
+        .hash_password(CleartextPassword(PASSWORD.to_string()))
+        .await?;
+
+    let mut users = Vec::with_capacity(plan.users.len());
+    for user in &plan.users {
+        let (user, _) = deps
+            .insert_user(&user.username, &user.email.parse()?, password_hash.clone())
+            .await?;
+        users.push(user);
+    }
+
+    for (follower, followed) in &plan.follows {
+        deps.insert_follow(users[*follower].user_id, &users[*followed].username)
+            .await?;
+    }
+
+    for article in &plan.articles {
+        deps.insert_article(
+            users[article.author].user_id,
+            &article.slug,
+            &article.title,
+            &article.description,
+            &article.body,
+            &article.tag_list,
+            None,
+        )
+        .await?;
+    }
+
+    for (user, article) in &plan.favorites {
+        deps.insert_favorite(users[*user].user_id, &plan.articles[*article].slug)
+            .await?;
+    }
+
+    for (user, article, body) in &plan.comments {
+        deps.insert_comment(
+            users[*user].user_id,
+            &plan.articles[*article].slug,
+            body,
+            &[],
+        )
+        .await?;
+    }
+
+    Ok(Seeded {
+        users: plan.users.len(),
+        follows: plan.follows.len(),
+        articles: plan.articles.len(),
+        favorites: plan.favorites.len(),
+        comments: plan.comments.len(),
+    })
+}
+
+fn words(rng: &mut StdRng, count: std::ops::Range<usize>) -> String {
+    let count = rng.gen_range(count);
+    (0..count)
+        .map(|_| *WORDS.choose(rng).expect("there are words"))
+        .join(" ")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_should_give_same_plan() {
+        let options = SeedOptions {
+            seed: 42,
+            ..Default::default()
+        };
+
+        assert_eq!(SeedPlan::generate(options), SeedPlan::generate(options));
+        assert_ne!(
+            SeedPlan::generate(options),
+            SeedPlan::generate(SeedOptions {
+                seed: 43,
+                ..options
+            })
+        );
+    }
+
+    #[test]
+    fn plan_should_have_requested_amounts() {
+        let plan = SeedPlan::generate(SeedOptions {
+            users: 4,
+            follows_per_user: 2,
+            articles_per_user: 3,
+            favorites_per_user: 5,
+            comments_per_article: 2,
+            seed: 0,
+        });
+
+        assert_eq!(4, plan.users.len());
+        assert_eq!(8, plan.follows.len());
+        assert_eq!(12, plan.articles.len());
+        assert_eq!(20, plan.favorites.len());
+        assert_eq!(24, plan.comments.len());
+
+        assert!(plan
+            .follows
+            .iter()
+            .all(|(follower, followed)| follower != followed));
+        assert!(plan
+            .articles
+            .iter()
+            .map(|article| &article.slug)
+            .all_unique());
+    }
+
+    #[test]
+    fn follows_should_be_limited_by_number_of_other_users() {
+        let plan = SeedPlan::generate(SeedOptions {
+            users: 2,
+            follows_per_user: 5,
+            ..Default::default()
+        });
+
+        assert_eq!(vec![(0, 1), (1, 0)], plan.follows);
+    }
+}
@@ -0,0 +1,265 @@
+//!
+//! Preferences of a user, like which emails they want and how many items a page lists.
+//!
+//! The settings of a user are stored as one JSON document. Fields missing from it get their defaults
+//! when it's read, as do all of them for users who never saved their settings,
+//! so a new setting needs no backfill.
+//!
+
+use crate::error::{RwError, RwResult};
+use crate::pagination::DEFAULT_LIMIT;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::UserId;
+use crate::GetConfig;
+
+use entrait::entrait_export as entrait;
+
+/// Longest locale, like the longest language tags of BCP 47
+const MAX_LOCALE_LENGTH: usize = 35;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UserSettings {
+    pub email_notifications: EmailNotifications,
+    /// Language tag like `en` or `nb-NO`
+    pub locale: String,
+    /// Size of the pages the client lists articles and comments in
+    pub items_per_page: i64,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            email_notifications: EmailNotifications::default(),
+            locale: "en".to_string(),
+            items_per_page: DEFAULT_LIMIT,
+        }
+    }
+}
+
+/// Which notifications the user wants by email, all of them unless turned off
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EmailNotifications {
+    pub followed: bool,
+    pub favorited: bool,
+    pub commented: bool,
+}
+
+impl Default for EmailNotifications {
+    fn default() -> Self {
+        Self {
+            followed: true,
+            favorited: true,
+            commented: true,
+        }
+    }
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(SettingsRepoImpl, delegate_by=DelegateSettingsRepo, mock_api=SettingsRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(SettingsRepoImpl, delegate_by=ref, mock_api=SettingsRepoMock),
+    async_trait::async_trait
+)]
+pub trait SettingsRepo {
+    /// `None` if the user never saved their settings
+    async fn find_user_settings(&self, user_id: UserId) -> RwResult<Option<UserSettings>>;
+
+    async fn save_user_settings(&self, user_id: UserId, settings: &UserSettings) -> RwResult<()>;
+}
+
+#[entrait(pub FetchUserSettings, mock_api=FetchUserSettingsMock)]
+async fn fetch_user_settings(
+    deps: &(impl Authenticate + GetConfig + SettingsRepo),
+    token: Token,
+) -> RwResult<UserSettings> {
+    let current_user_id = deps.authenticate(token).await?;
+    let mut settings = deps
+        .find_user_settings(current_user_id)
+        .await?
+        .unwrap_or_default();
+    // The max page size may have been lowered since the settings were saved
+    settings.items_per_page = settings.items_per_page.min(deps.page_sizes().max);
+
+    Ok(settings)
+}
+
+/// Replaces all the settings, those left out get their defaults
+#[entrait(pub UpdateUserSettings, mock_api=UpdateUserSettingsMock)]
+async fn update_user_settings(
+    deps: &(impl Authenticate + GetConfig + SettingsRepo),
+    token: Token,
+    settings: UserSettings,
+) -> RwResult<UserSettings> {
+    let current_user_id = deps.authenticate(token).await?;
+    validate(&settings, deps.page_sizes().max)?;
+    deps.save_user_settings(current_user_id, &settings).await?;
+
+    Ok(settings)
+}
+
+fn validate(settings: &UserSettings, max_page_size: i64) -> RwResult<()> {
+    let locale = &settings.locale;
+    if locale.is_empty()
+        || locale.len() > MAX_LOCALE_LENGTH
+        || locale.starts_with('-')
+        || locale.ends_with('-')
+        || !locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(RwError::InvalidSetting {
+            field: "locale",
+            problem: "must be a language tag like en-US",
+        });
+    }
+    if settings.items_per_page <= 0 {
+        return Err(RwError::InvalidSetting {
+            field: "itemsPerPage",
+            problem: "must be positive",
+        });
+    }
+    if settings.items_per_page > max_page_size {
+        return Err(RwError::InvalidSetting {
+            field: "itemsPerPage",
+            problem: "is larger than the max page size",
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pagination::PageSizes;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::GetConfigMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn mock_authenticate() -> impl Clause {
+        AuthenticateMock::authenticate
+            .next_call(matching!(_))
+            .returns(Ok(UserId(uuid::Uuid::from_u128(1))))
+    }
+
+    fn mock_max_page_size(max: i64) -> impl Clause {
+        GetConfigMock::page_sizes
+            .each_call(matching!())
+            .returns(PageSizes {
+                default: DEFAULT_LIMIT,
+                max,
+                max_offset: None,
+            })
+    }
+
+    #[test]
+    fn missing_fields_should_get_defaults() {
+        let settings: UserSettings = serde_json::from_value(serde_json::json!({
+            "emailNotifications": { "followed": false },
+            "locale": "nb-NO"
+        }))
+        .unwrap();
+
+        assert_eq!(
+            UserSettings {
+                email_notifications: EmailNotifications {
+                    followed: false,
+                    ..Default::default()
+                },
+                locale: "nb-NO".to_string(),
+                items_per_page: DEFAULT_LIMIT,
+            },
+            settings
+        );
+    }
+
+    #[tokio::test]
+    async fn unsaved_settings_should_be_defaults() {
+        let deps = Unimock::new((
+            mock_authenticate(),
+            mock_max_page_size(100),
+            SettingsRepoMock::find_user_settings
+                .next_call(matching!(_))
+                .returns(Ok(None)),
+        ));
+
+        assert_eq!(
+            UserSettings::default(),
+            fetch_user_settings(&deps, Token::from_token("token"))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn items_per_page_should_be_at_most_max_page_size() {
+        let deps = Unimock::new((
+            mock_authenticate(),
+            mock_max_page_size(50),
+            SettingsRepoMock::find_user_settings
+                .next_call(matching!(_))
+                .returns(Ok(Some(UserSettings {
+                    items_per_page: 80,
+                    ..Default::default()
+                }))),
+        ));
+
+        let settings = fetch_user_settings(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+        assert_eq!(50, settings.items_per_page);
+    }
+
+    #[tokio::test]
+    async fn valid_settings_should_be_saved() {
+        let settings = UserSettings {
+            locale: "de".to_string(),
+            items_per_page: 10,
+            ..Default::default()
+        };
+        let deps = Unimock::new((
+            mock_authenticate(),
+            mock_max_page_size(100),
+            SettingsRepoMock::save_user_settings
+                .next_call(matching!((UserId(id), settings) if id.as_u128() == 1 && settings.locale == "de"))
+                .returns(Ok(())),
+        ));
+
+        assert_eq!(
+            settings.clone(),
+            update_user_settings(&deps, Token::from_token("token"), settings)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_settings_should_not_be_saved() {
+        for (locale, items_per_page, field) in [
+            ("", 10, "locale"),
+            ("en_US", 10, "locale"),
+            ("en-", 10, "locale"),
+            ("en", 0, "itemsPerPage"),
+            ("en", 101, "itemsPerPage"),
+        ] {
+            let deps = Unimock::new((mock_authenticate(), mock_max_page_size(100)));
+            let settings = UserSettings {
+                locale: locale.to_string(),
+                items_per_page,
+                ..Default::default()
+            };
+
+            assert_matches!(
+                update_user_settings(&deps, Token::from_token("token"), settings).await,
+                Err(RwError::InvalidSetting { field: f, .. }) if f == field
+            );
+        }
+    }
+}
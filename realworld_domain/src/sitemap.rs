@@ -0,0 +1,220 @@
+//!
+//! The sitemap, listing the pages of the site for search engines.
+//!
+//! The sitemap may list many articles and users, so it is written while it's being read from the
+//! database, in chunks of [CHUNK_URLS] urls, instead of being built in memory first.
+//!
+
+use crate::article::repo::ArticleRepo;
+use crate::error::RwResult;
+use crate::rss::escape;
+use crate::timestamp::Timestamptz;
+use crate::user::repo::UserRepo;
+use crate::GetConfig;
+
+use axum::body::Body;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use entrait::entrait_export as entrait;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use std::fmt::Write;
+
+/// The most urls a sitemap may have, by the sitemap protocol. Any more are left out.
+pub const MAX_URLS: usize = 50_000;
+
+/// Urls per chunk of the response
+pub const CHUNK_URLS: usize = 500;
+
+///
+/// The XML of a sitemap, in chunks.
+///
+/// A database error after the first chunk aborts the response instead of yielding an error status.
+///
+pub struct SitemapXml(pub BoxStream<'static, RwResult<String>>);
+
+impl IntoResponse for SitemapXml {
+    fn into_response(self) -> Response {
+        (
+            [(CONTENT_TYPE, "application/xml; charset=utf-8")],
+            Body::from_stream(self.0),
+        )
+            .into_response()
+    }
+}
+
+struct Url {
+    loc: String,
+    lastmod: Option<Timestamptz>,
+}
+
+/// The home page, then every article and every profile, oldest first
+#[entrait(pub Sitemap, mock_api=SitemapMock)]
+fn sitemap(deps: &(impl GetConfig + ArticleRepo + UserRepo)) -> SitemapXml {
+    let site_url = deps.site_url().trim_end_matches('/').to_string();
+
+    let home = Url {
+        loc: format!("{site_url}/"),
+        lastmod: None,
+    };
+    let articles = deps.stream_article_slugs().map_ok({
+        let site_url = site_url.clone();
+        move |(slug, updated_at)| Url {
+            loc: format!("{site_url}/article/{}", encode_path_segment(slug.as_str())),
+            lastmod: Some(updated_at),
+        }
+    });
+    let profiles = deps.stream_usernames().map_ok(move |username| Url {
+        loc: format!("{site_url}/profile/{}", encode_path_segment(&username)),
+        lastmod: None,
+    });
+
+    let urls = stream::iter([Ok(home)])
+        .chain(articles)
+        .chain(profiles)
+        .take(MAX_URLS)
+        .chunks(CHUNK_URLS)
+        .map(|chunk| {
+            let mut xml = String::new();
+            for url in chunk {
+                write_url(&mut xml, &url?);
+            }
+            Ok(xml)
+        });
+
+    SitemapXml(
+        stream::iter([Ok(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+            "\n"
+        )
+        .to_string())])
+        .chain(urls)
+        .chain(stream::iter([Ok("</urlset>\n".to_string())]))
+        .boxed(),
+    )
+}
+
+fn write_url(xml: &mut String, url: &Url) {
+    let _ = write!(xml, "<url><loc>{}</loc>", escape(&url.loc));
+    if let Some(lastmod) = &url.lastmod {
+        let _ = write!(xml, "<lastmod>{lastmod}</lastmod>");
+    }
+    let _ = writeln!(xml, "</url>");
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986
+fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article::repo::ArticleRepoMock;
+    use crate::article::Slug;
+    use crate::error::RwError;
+    use crate::user::repo::UserRepoMock;
+    use crate::GetConfigMock;
+
+    use unimock::*;
+
+    fn mock_site_url() -> impl unimock::Clause {
+        GetConfigMock::site_url
+            .each_call(matching!())
+            .returns("https://example.com/".to_string())
+    }
+
+    async fn collect(sitemap: SitemapXml) -> RwResult<String> {
+        sitemap.0.try_collect().await
+    }
+
+    #[tokio::test]
+    async fn sitemap_should_list_home_articles_and_profiles() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            ArticleRepoMock::stream_article_slugs
+                .next_call(matching!())
+                .answers(&|_| {
+                    stream::iter([Ok((
+                        Slug::from("dragons"),
+                        Timestamptz(time::macros::datetime!(2019-10-12 07:20:50 UTC)),
+                    ))])
+                    .boxed()
+                }),
+            UserRepoMock::stream_usernames
+                .next_call(matching!())
+                .answers(&|_| {
+                    stream::iter([Ok("jake".to_string()), Ok("Æsa & co".to_string())]).boxed()
+                }),
+        ));
+
+        let xml = collect(sitemap(&deps)).await.unwrap();
+
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert_eq!(4, xml.matches("<url>").count());
+        assert!(xml.contains("<url><loc>https://example.com/</loc></url>"));
+        assert!(xml.contains(
+            "<url><loc>https://example.com/article/dragons</loc><lastmod>2019-10-12T07:20:50Z</lastmod></url>"
+        ));
+        assert!(xml.contains("<loc>https://example.com/profile/jake</loc>"));
+        assert!(xml.contains("<loc>https://example.com/profile/%C3%86sa%20%26%20co</loc>"));
+        assert!(xml.ends_with("</urlset>\n"));
+    }
+
+    #[tokio::test]
+    async fn sitemap_should_be_cut_off_at_max_urls() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            ArticleRepoMock::stream_article_slugs
+                .next_call(matching!())
+                .answers(&|_| {
+                    stream::iter(0..MAX_URLS)
+                        .map(|n| {
+                            Ok((
+                                Slug(format!("article-{n}")),
+                                Timestamptz(time::macros::datetime!(2019-10-12 07:20:50 UTC)),
+                            ))
+                        })
+                        .boxed()
+                }),
+            UserRepoMock::stream_usernames
+                .next_call(matching!())
+                .answers(&|_| stream::iter([Ok("jake".to_string())]).boxed()),
+        ));
+
+        let xml = collect(sitemap(&deps)).await.unwrap();
+
+        assert_eq!(MAX_URLS, xml.matches("<url>").count());
+        assert!(!xml.contains("/profile/jake"));
+    }
+
+    #[tokio::test]
+    async fn database_error_should_end_sitemap() {
+        let deps = Unimock::new((
+            mock_site_url(),
+            ArticleRepoMock::stream_article_slugs
+                .next_call(matching!())
+                .answers(&|_| {
+                    stream::iter([Err(RwError::Anyhow(anyhow::anyhow!("connection lost")))]).boxed()
+                }),
+            UserRepoMock::stream_usernames
+                .next_call(matching!())
+                .answers(&|_| stream::iter([]).boxed()),
+        ));
+
+        assert!(collect(sitemap(&deps)).await.is_err());
+    }
+}
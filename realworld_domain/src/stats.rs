@@ -0,0 +1,87 @@
+//!
+//! Numbers for an author's dashboard.
+//!
+
+use crate::error::RwResult;
+use crate::user::auth::{Authenticate, Token};
+use crate::user::UserId;
+
+use entrait::entrait_export as entrait;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    /// Articles written by the user
+    pub articles_count: i64,
+    /// Favorites of all the user's articles
+    pub favorites_count: i64,
+    pub followers_count: i64,
+    /// Comments written by the user
+    pub comments_count: i64,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(StatsRepoImpl, delegate_by=DelegateStatsRepo, mock_api=StatsRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(StatsRepoImpl, delegate_by=ref, mock_api=StatsRepoMock),
+    async_trait::async_trait
+)]
+pub trait StatsRepo {
+    async fn select_user_stats(&self, user_id: UserId) -> RwResult<UserStats>;
+}
+
+#[entrait(pub FetchUserStats, mock_api=FetchUserStatsMock)]
+async fn fetch_user_stats(
+    deps: &(impl Authenticate + StatsRepo),
+    token: Token,
+) -> RwResult<UserStats> {
+    let current_user_id = deps.authenticate(token).await?;
+    deps.select_user_stats(current_user_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RwError;
+    use crate::user::auth::authenticate::AuthenticateMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    #[tokio::test]
+    async fn stats_should_be_of_current_user() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(uuid::Uuid::from_u128(1)))),
+            StatsRepoMock::select_user_stats
+                .next_call(matching!((UserId(id)) if id.as_u128() == 1))
+                .returns(Ok(UserStats {
+                    articles_count: 2,
+                    ..Default::default()
+                })),
+        ));
+
+        let stats = fetch_user_stats(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+        assert_eq!(2, stats.articles_count);
+    }
+
+    #[tokio::test]
+    async fn stats_should_require_authentication() {
+        let deps = Unimock::new(
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Err(RwError::Unauthorized)),
+        );
+
+        assert_matches!(
+            fetch_user_stats(&deps, Token::from_token("expired")).await,
+            Err(RwError::Unauthorized)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use time::format_description::well_known::Rfc3339;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
 use time::OffsetDateTime;
 
 #[derive(sqlx::Type, Clone, Eq, PartialEq, Debug)]
@@ -42,6 +42,17 @@ impl Serialize for Timestamptz {
     }
 }
 
+impl Timestamptz {
+    /// Like `Sat, 12 Oct 2019 07:20:50 +0000`, as used by RSS and email.
+    /// Falls back to RFC 3339 for times RFC 2822 can't express, before the year 1900.
+    pub fn to_rfc2822(&self) -> String {
+        self.0
+            .to_offset(time::UtcOffset::UTC)
+            .format(&Rfc2822)
+            .unwrap_or_else(|_| self.to_string())
+    }
+}
+
 impl<'de> Deserialize<'de> for Timestamptz {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -78,5 +89,6 @@ mod tests {
     fn timestamptz_display() {
         let ts = Timestamptz(OffsetDateTime::parse("2019-10-12T07:20:50.52Z", &Rfc3339).unwrap());
         assert_eq!("2019-10-12T07:20:50.52Z", format!("{}", ts));
+        assert_eq!("Sat, 12 Oct 2019 07:20:50 +0000", ts.to_rfc2822());
     }
 }
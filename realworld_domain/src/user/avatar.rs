@@ -0,0 +1,181 @@
+//!
+//! Profile images uploaded by the user, instead of linked to.
+//!
+
+use super::auth::{Authenticate, Token};
+use super::repo::{UserRepo, UserUpdate};
+use super::SignedUser;
+use crate::error::{RwError, RwResult};
+use crate::GetConfig;
+
+use entrait::entrait_export as entrait;
+
+///
+/// Mockable storage of uploaded files, such as the local disk or an S3 bucket.
+///
+#[entrait(mock_api=BlobStorageMock)]
+pub trait BlobStorage {
+    /// Store `data` under `key`, returning the public URL it can be fetched from
+    async fn put_blob(&self, key: &str, content_type: &str, data: Vec<u8>) -> RwResult<String>;
+}
+
+pub struct ImageUpload {
+    /// As declared by the client
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+///
+/// Store a new profile image and make it the image of the current user.
+///
+/// The image must be smaller than [GetConfig::max_avatar_bytes],
+/// and its content must match its declared content type.
+///
+#[entrait(pub UploadAvatar, mock_api=UploadAvatarMock)]
+async fn upload_avatar(
+    deps: &(impl Authenticate + GetConfig + BlobStorage + UserRepo),
+    token: Token,
+    upload: ImageUpload,
+) -> RwResult<SignedUser> {
+    let current_user_id = deps.authenticate(token.clone()).await?;
+
+    let max_bytes = deps.max_avatar_bytes();
+    if upload.data.len() > max_bytes {
+        return Err(RwError::PayloadTooLarge { max_bytes });
+    }
+    let extension = image_extension(&upload)?;
+
+    // A new key for every upload, so that cached old images aren't shown
+    let key = format!(
+        "avatars/{}/{}.{extension}",
+        current_user_id.into_id(),
+        uuid::Uuid::new_v4()
+    );
+    let url = deps
+        .put_blob(&key, &upload.content_type, upload.data)
+        .await?;
+
+    let (user, credentials) = deps
+        .update_user(
+            current_user_id,
+            UserUpdate {
+                image: Some(&url),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    Ok(user.with_token(&token, credentials.email))
+}
+
+/// The file extension of a supported image, checked against the magic bytes of the format
+fn image_extension(upload: &ImageUpload) -> RwResult<&'static str> {
+    let data = &upload.data;
+    let extension = match upload.content_type.as_str() {
+        "image/png" => data.starts_with(b"\x89PNG\r\n\x1a\n").then_some("png"),
+        "image/jpeg" => data.starts_with(b"\xff\xd8\xff").then_some("jpg"),
+        "image/gif" => data.starts_with(b"GIF8").then_some("gif"),
+        "image/webp" => {
+            (data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP")).then_some("webp")
+        }
+        _ => None,
+    };
+
+    extension.ok_or_else(|| RwError::UnsupportedImageType(upload.content_type.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::repo::{Credentials, User, UserRepoMock};
+    use crate::user::UserId;
+    use crate::GetConfigMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn mock_authenticate() -> impl unimock::Clause {
+        AuthenticateMock::authenticate
+            .next_call(matching!(_))
+            .returns(Ok(UserId(uuid::Uuid::from_u128(1))))
+    }
+
+    fn mock_max_bytes() -> impl unimock::Clause {
+        GetConfigMock::max_avatar_bytes
+            .next_call(matching!())
+            .returns(16_usize)
+    }
+
+    fn png(len: usize) -> ImageUpload {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.resize(len, 0);
+        ImageUpload {
+            content_type: "image/png".to_string(),
+            data,
+        }
+    }
+
+    #[tokio::test]
+    async fn uploaded_image_should_become_user_image() {
+        let deps = Unimock::new((
+            mock_authenticate(),
+            mock_max_bytes(),
+            BlobStorageMock::put_blob
+                .next_call(matching!((key, "image/png", _)
+                    if key.starts_with("avatars/00000000-0000-0000-0000-000000000001/")
+                        && key.ends_with(".png")))
+                .answers(&|_, key, _, _| Ok(format!("https://cdn/{key}"))),
+            UserRepoMock::update_user
+                .next_call(matching!(_, _))
+                .answers(&|_, _, update| {
+                    Ok((
+                        User {
+                            user_id: UserId(uuid::Uuid::from_u128(1)),
+                            username: "username".to_string(),
+                            bio: "".to_string(),
+                            image: update.image.map(ToString::to_string),
+                            role: Default::default(),
+                        },
+                        Credentials {
+                            email: "user@example.com".parse().unwrap(),
+                            password_hash: "".into(),
+                            email_verified: true,
+                        },
+                    ))
+                }),
+        ));
+
+        let signed_user = upload_avatar(&deps, Token::from_token("token"), png(16))
+            .await
+            .unwrap();
+        assert!(signed_user
+            .image
+            .unwrap()
+            .starts_with("https://cdn/avatars/"));
+    }
+
+    #[tokio::test]
+    async fn too_large_image_should_be_rejected() {
+        let deps = Unimock::new((mock_authenticate(), mock_max_bytes()));
+
+        assert_matches!(
+            upload_avatar(&deps, Token::from_token("token"), png(17)).await,
+            Err(RwError::PayloadTooLarge { max_bytes: 16 })
+        );
+    }
+
+    #[tokio::test]
+    async fn content_should_match_content_type() {
+        let deps = Unimock::new((mock_authenticate(), mock_max_bytes()));
+        let upload = ImageUpload {
+            content_type: "image/jpeg".to_string(),
+            ..png(16)
+        };
+
+        assert_matches!(
+            upload_avatar(&deps, Token::from_token("token"), upload).await,
+            Err(RwError::UnsupportedImageType(content_type)) if content_type == "image/jpeg"
+        );
+    }
+}
@@ -6,10 +6,36 @@ use crate::error::RwError;
 #[serde(transparent)]
 pub struct Email(String);
 
+/// How a tag after `+` in the local part of an address, as in `name+tag@example.com`, is treated
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PlusAddressing {
+    /// The tag is part of the address
+    #[default]
+    Keep,
+    /// The tag is removed, like Gmail ignores it, so that tagged addresses belong to the same user
+    Strip,
+}
+
 impl Email {
     pub fn valid(email: String) -> Self {
         Self(email)
     }
+
+    ///
+    /// The form that addresses are saved and compared in: with a lowercase domain,
+    /// and without a plus tag when [PlusAddressing::Strip].
+    ///
+    pub fn canonical(&self, plus_addressing: PlusAddressing) -> Self {
+        let Some((local, domain)) = self.0.rsplit_once('@') else {
+            return self.clone();
+        };
+        let local = match (plus_addressing, local.split_once('+')) {
+            (PlusAddressing::Strip, Some((untagged, _))) if !untagged.is_empty() => untagged,
+            _ => local,
+        };
+
+        Self(format!("{local}@{}", domain.to_lowercase()))
+    }
 }
 
 impl FromStr for Email {
@@ -26,3 +52,45 @@ impl AsRef<str> for Email {
         self.0.as_str()
     }
 }
+
+/// An outgoing email
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmailMessage {
+    pub to: Email,
+    pub subject: String,
+    pub body: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(email: &str, plus_addressing: PlusAddressing) -> String {
+        Email::valid(email.to_string()).canonical(plus_addressing).0
+    }
+
+    #[test]
+    fn canonical_email_should_have_lowercase_domain() {
+        assert_eq!(
+            "Name+Tag@example.com",
+            canonical("Name+Tag@Example.COM", PlusAddressing::Keep)
+        );
+        assert_eq!("no-domain", canonical("no-domain", PlusAddressing::Keep));
+    }
+
+    #[test]
+    fn canonical_email_should_strip_plus_tag_by_policy() {
+        assert_eq!(
+            "name@example.com",
+            canonical("name+tag+more@example.com", PlusAddressing::Strip)
+        );
+        assert_eq!(
+            "+tag@example.com",
+            canonical("+tag@example.com", PlusAddressing::Strip)
+        );
+        assert_eq!(
+            "name@example.com",
+            canonical("name@example.com", PlusAddressing::Strip)
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//!
+//! Importing users exported from other RealWorld backends, along with their password hashes,
+//! so that they log in with the passwords they had instead of having to reset them.
+//!
+//! Hashes of every [PasswordAlgorithm] are accepted, and are hashed again with the configured one
+//! as their users log in.
+//!
+
+use super::email::Email;
+use super::password::{PasswordAlgorithm, PasswordHash};
+use super::repo::{ImportedUser, UserRepo};
+use crate::error::{RwError, RwResult};
+use crate::GetConfig;
+
+use entrait::entrait_export as entrait;
+use std::collections::HashSet;
+
+/// A user as exported by another backend
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ExportedUser {
+    pub username: String,
+    pub email: String,
+    #[serde(alias = "passwordHash")]
+    pub password_hash: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Ordered by their position in the export
+    pub conflicts: Vec<ImportConflict>,
+}
+
+/// A user that wasn't imported, and why
+#[derive(Debug)]
+pub struct ImportConflict {
+    /// Position of the user in the export, from 0
+    pub index: usize,
+    pub username: String,
+    pub error: RwError,
+}
+
+///
+/// Insert the users `batch_size` at a time. Users whose username or email is taken,
+/// also by an earlier user of the same export, are reported as conflicts, like invalid ones.
+///
+#[entrait(pub ImportUsers, mock_api=ImportUsersMock)]
+async fn import_users(
+    deps: &(impl GetConfig + UserRepo),
+    users: Vec<ExportedUser>,
+    batch_size: usize,
+) -> RwResult<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut seen_usernames = HashSet::new();
+    let mut seen_emails = HashSet::new();
+
+    for (batch_index, batch) in users.chunks(batch_size.max(1)).enumerate() {
+        let offset = batch_index * batch_size.max(1);
+        let mut valid = vec![];
+
+        for (index, user) in batch.iter().enumerate() {
+            let result = validate(deps, user).and_then(|imported| {
+                if !seen_usernames.insert(imported.username.to_lowercase()) {
+                    Err(RwError::UsernameTaken)
+                } else if !seen_emails.insert(imported.email.as_ref().to_lowercase()) {
+                    Err(RwError::EmailTaken)
+                } else {
+                    Ok(imported)
+                }
+            });
+            match result {
+                Ok(imported) => valid.push((offset + index, imported)),
+                Err(error) => report.conflicts.push(ImportConflict {
+                    index: offset + index,
+                    username: user.username.clone(),
+                    error,
+                }),
+            }
+        }
+
+        let users: Vec<ImportedUser> = valid.iter().map(|(_, user)| user.clone()).collect();
+        let inserted: HashSet<String> = deps.insert_users(&users).await?.into_iter().collect();
+        report.imported += inserted.len();
+
+        for (index, user) in valid {
+            if inserted.contains(&user.username) {
+                continue;
+            }
+            let error = match deps.find_user_credentials_by_email(&user.email).await? {
+                Some(_) => RwError::EmailTaken,
+                None => RwError::UsernameTaken,
+            };
+            report.conflicts.push(ImportConflict {
+                index,
+                username: user.username,
+                error,
+            });
+        }
+    }
+
+    report.conflicts.sort_by_key(|conflict| conflict.index);
+    Ok(report)
+}
+
+/// The user as it would be signed up, with its email in canonical form
+fn validate(deps: &impl GetConfig, user: &ExportedUser) -> RwResult<ImportedUser> {
+    super::check_username(deps, &user.username)?;
+    let email = user
+        .email
+        .parse::<Email>()?
+        .canonical(deps.plus_addressing());
+    let password_hash = PasswordHash::from(user.password_hash.as_str());
+    if PasswordAlgorithm::of_hash(&password_hash).is_none() {
+        return Err(RwError::UnknownPasswordHash);
+    }
+
+    Ok(ImportedUser {
+        username: user.username.clone(),
+        email,
+        password_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::repo::{Credentials, User, UserRepoMock};
+    use crate::user::UserId;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    const BCRYPT_HASH: &str = "$2b$04$29xGewtac4oMV2RV7XSSnOb49ZVD1ISFs/CeElQ./YQh4Smc3y4pS";
+
+    fn exported(username: &str, email: &str) -> ExportedUser {
+        ExportedUser {
+            username: username.to_string(),
+            email: email.to_string(),
+            password_hash: BCRYPT_HASH.to_string(),
+        }
+    }
+
+    fn mock_config() -> impl unimock::Clause {
+        (
+            crate::test::mock_reserved_usernames(),
+            crate::test::mock_plus_addressing(),
+        )
+    }
+
+    #[tokio::test]
+    async fn users_should_be_inserted_in_batches() {
+        let deps = Unimock::new((
+            mock_config(),
+            UserRepoMock::insert_users
+                .next_call(matching!((users) if users.len() == 2 && users[0].username == "a"))
+                .answers(&|_, users| Ok(users.iter().map(|user| user.username.clone()).collect())),
+            UserRepoMock::insert_users
+                .next_call(matching!((users) if users.len() == 1 && users[0].username == "c"))
+                .answers(&|_, users| Ok(users.iter().map(|user| user.username.clone()).collect())),
+        ));
+
+        let report = import_users(
+            &deps,
+            vec![
+                exported("a", "a@example.com"),
+                exported("b", "b@example.com"),
+                exported("c", "c@example.com"),
+            ],
+            2,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(3, report.imported);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn taken_and_invalid_users_should_be_reported() {
+        let deps = Unimock::new((
+            mock_config(),
+            UserRepoMock::insert_users
+                .next_call(matching!((users) if users.len() == 3))
+                .returns(Ok(vec!["new".to_string()])),
+            UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("taken@example.com"))
+                .returns(Ok(None)),
+            UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("other@example.com"))
+                .answers(&|_, email| {
+                    Ok(Some((
+                        User {
+                            user_id: UserId(uuid::Uuid::from_u128(1)),
+                            username: "someone".to_string(),
+                            bio: "".to_string(),
+                            image: None,
+                            role: Default::default(),
+                        },
+                        Credentials {
+                            email: email.clone(),
+                            password_hash: BCRYPT_HASH.into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+        ));
+
+        let report = import_users(
+            &deps,
+            vec![
+                exported("new", "new@example.com"),
+                exported("taken", "taken@example.com"),
+                exported("NEW", "new2@example.com"),
+                exported("admin", "admin@example.com"),
+                exported("again", "New@example.com"),
+                ExportedUser {
+                    password_hash: "plaintext".to_string(),
+                    ..exported("plain", "plain@example.com")
+                },
+                exported("another", "other@example.com"),
+            ],
+            10,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(1, report.imported);
+        let conflicts: Vec<(usize, &str)> = report
+            .conflicts
+            .iter()
+            .map(|conflict| (conflict.index, conflict.username.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, "taken"),
+                (2, "NEW"),
+                (3, "admin"),
+                (4, "again"),
+                (5, "plain"),
+                (6, "another")
+            ],
+            conflicts
+        );
+        let errors: Vec<&RwError> = report.conflicts.iter().map(|c| &c.error).collect();
+        assert_matches!(errors[0], RwError::UsernameTaken);
+        assert_matches!(errors[1], RwError::UsernameTaken);
+        assert_matches!(errors[2], RwError::UsernameReserved);
+        assert_matches!(errors[3], RwError::EmailTaken);
+        assert_matches!(errors[4], RwError::UnknownPasswordHash);
+        assert_matches!(errors[5], RwError::EmailTaken);
+    }
+}
@@ -0,0 +1,186 @@
+//!
+//! Keys that access tokens are signed and verified with.
+//!
+//! Tokens carry the id of their signing key in the `kid` header,
+//! so that the signing key can be rotated while tokens signed by earlier keys stay valid.
+//!
+
+use entrait::entrait_export as entrait;
+use hmac::{Hmac, Mac};
+use jwt::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+use sha2::{Sha256, Sha384, Sha512};
+
+///
+/// Mockable source of JWT keys
+///
+#[entrait(mock_api=JwtKeyProviderMock)]
+pub trait JwtKeyProvider {
+    fn jwt_keys(&self) -> &JwtKeys;
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum JwtAlgorithm {
+    Hs256,
+    Hs384,
+    Hs512,
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "HS256" => Ok(Self::Hs256),
+            "HS384" => Ok(Self::Hs384),
+            "HS512" => Ok(Self::Hs512),
+            _ => Err(format!("Unsupported JWT algorithm: {s}")),
+        }
+    }
+}
+
+///
+/// A secret key of one of the supported HMAC algorithms.
+///
+#[derive(Clone)]
+pub enum JwtKey {
+    Hs256(Hmac<Sha256>),
+    Hs384(Hmac<Sha384>),
+    Hs512(Hmac<Sha512>),
+}
+
+impl JwtKey {
+    pub fn new(algorithm: JwtAlgorithm, secret: &[u8]) -> Self {
+        const ANY_LENGTH: &str = "HMAC can accept any key length";
+
+        match algorithm {
+            JwtAlgorithm::Hs256 => Self::Hs256(Hmac::new_from_slice(secret).expect(ANY_LENGTH)),
+            JwtAlgorithm::Hs384 => Self::Hs384(Hmac::new_from_slice(secret).expect(ANY_LENGTH)),
+            JwtAlgorithm::Hs512 => Self::Hs512(Hmac::new_from_slice(secret).expect(ANY_LENGTH)),
+        }
+    }
+}
+
+impl SigningAlgorithm for JwtKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        match self {
+            Self::Hs256(_) => AlgorithmType::Hs256,
+            Self::Hs384(_) => AlgorithmType::Hs384,
+            Self::Hs512(_) => AlgorithmType::Hs512,
+        }
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, jwt::Error> {
+        match self {
+            Self::Hs256(key) => key.sign(header, claims),
+            Self::Hs384(key) => key.sign(header, claims),
+            Self::Hs512(key) => key.sign(header, claims),
+        }
+    }
+}
+
+impl VerifyingAlgorithm for JwtKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        SigningAlgorithm::algorithm_type(self)
+    }
+
+    fn verify_bytes(
+        &self,
+        header: &str,
+        claims: &str,
+        signature: &[u8],
+    ) -> Result<bool, jwt::Error> {
+        match self {
+            Self::Hs256(key) => key.verify_bytes(header, claims, signature),
+            Self::Hs384(key) => key.verify_bytes(header, claims, signature),
+            Self::Hs512(key) => key.verify_bytes(header, claims, signature),
+        }
+    }
+}
+
+///
+/// The key that new tokens are signed with, along with earlier keys
+/// that tokens are still accepted from.
+///
+#[derive(Clone)]
+pub struct JwtKeys {
+    /// The signing key comes first
+    keys: Vec<(String, JwtKey)>,
+}
+
+impl JwtKeys {
+    pub fn new(signing_key_id: impl Into<String>, signing_key: JwtKey) -> Self {
+        Self {
+            keys: vec![(signing_key_id.into(), signing_key)],
+        }
+    }
+
+    /// Keep accepting tokens signed by a key that is no longer used for signing
+    pub fn with_verification_key(mut self, key_id: impl Into<String>, key: JwtKey) -> Self {
+        self.keys.push((key_id.into(), key));
+        self
+    }
+
+    pub fn signing_key(&self) -> (&str, &JwtKey) {
+        let (key_id, key) = &self.keys[0];
+        (key_id, key)
+    }
+
+    ///
+    /// The keys that a token with the given `kid` may have been signed by.
+    /// Tokens without a `kid` were signed before keys had ids, so every key is tried.
+    ///
+    pub fn verification_keys<'k>(
+        &'k self,
+        key_id: Option<&'k str>,
+    ) -> impl Iterator<Item = &'k JwtKey> {
+        self.keys
+            .iter()
+            .filter(move |(id, _)| key_id.is_none_or(|key_id| key_id == id))
+            .map(|(_, key)| key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_keys() -> JwtKeys {
+        JwtKeys::new("new", JwtKey::new(JwtAlgorithm::Hs512, b"new"))
+            .with_verification_key("old", JwtKey::new(JwtAlgorithm::Hs384, b"old"))
+    }
+
+    fn algorithms<'k>(keys: impl Iterator<Item = &'k JwtKey>) -> Vec<AlgorithmType> {
+        keys.map(SigningAlgorithm::algorithm_type).collect()
+    }
+
+    #[test]
+    fn should_sign_with_first_key() {
+        let keys = test_keys();
+        let (key_id, key) = keys.signing_key();
+
+        assert_eq!("new", key_id);
+        assert_eq!(AlgorithmType::Hs512, SigningAlgorithm::algorithm_type(key));
+    }
+
+    #[test]
+    fn verification_keys_should_be_selected_by_key_id() {
+        let keys = test_keys();
+
+        assert_eq!(
+            vec![AlgorithmType::Hs384],
+            algorithms(keys.verification_keys(Some("old")))
+        );
+        assert!(algorithms(keys.verification_keys(Some("unknown"))).is_empty());
+        assert_eq!(
+            vec![AlgorithmType::Hs512, AlgorithmType::Hs384],
+            algorithms(keys.verification_keys(None))
+        );
+    }
+
+    #[test]
+    fn algorithm_should_parse_case_insensitively() {
+        assert_eq!(Ok(JwtAlgorithm::Hs256), "hs256".parse());
+        assert_eq!(Ok(JwtAlgorithm::Hs512), "HS512".parse());
+        assert!("RS256".parse::<JwtAlgorithm>().is_err());
+    }
+}
@@ -1,19 +1,40 @@
 pub mod auth;
+pub mod avatar;
 pub mod email;
+pub mod import;
+pub mod jwt_keys;
+pub mod oauth;
+pub mod opaque_token;
 pub mod password;
+pub mod password_reset;
 pub mod profile;
 pub mod repo;
+pub mod revocation;
+pub mod role;
+pub mod session;
+pub mod verification;
 
 use auth::{Authenticate, Token};
 use email::Email;
-use password::CleartextPassword;
+use opaque_token::OpaqueToken;
+use password::{CleartextPassword, PasswordHash};
 
+use crate::article::feed_cache::FeedCache;
+use crate::audit::{AuditAction, AuditLog, NewAuditEntry};
 use crate::error::{RwError, RwResult};
+use crate::event::{DomainEvents, Event};
+use crate::pagination::Pagination;
+use crate::sanitize::sanitize;
+use crate::{GetConfig, System};
 
 use entrait::entrait_export as entrait;
+use std::net::IpAddr;
 use uuid::Uuid;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Bios are limited to this many bytes, as posted
+pub const MAX_BIO_BYTES: usize = 4 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct UserId<I = uuid::Uuid>(pub I);
 
 impl<I> UserId<I> {
@@ -33,6 +54,13 @@ pub struct SignedUser {
     pub username: String,
     pub bio: String,
     pub image: Option<String>,
+    /// Only issued when signing in, not when fetching or updating the current user.
+    #[serde(
+        rename = "refreshToken",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub refresh_token: Option<OpaqueToken>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -53,139 +81,596 @@ pub struct NewUser {
 pub struct UserUpdate {
     pub email: Option<String>,
     pub username: Option<String>,
+    /// Rejected, the password is changed by [ChangePassword], confirming the current one
     pub password: Option<CleartextPassword>,
     pub bio: Option<String>,
     pub image: Option<String>,
 }
 
+/// Confirmation of deleting the current user
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct DeleteUser {
+    pub password: CleartextPassword,
+}
+
+/// A new password for the current user, confirmed by the current one
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChange {
+    /// Left out by users signed up through OAuth, who set their first password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_password: Option<CleartextPassword>,
+    pub new_password: CleartextPassword,
+}
+
+/// Whether the username is one of [GetConfig::reserved_usernames], regardless of case
+fn is_reserved_username(deps: &impl GetConfig, username: &str) -> bool {
+    let username = username.to_lowercase();
+    deps.reserved_usernames()
+        .iter()
+        .any(|reserved| reserved.to_lowercase() == username)
+}
+
+fn check_username(deps: &impl GetConfig, username: &str) -> RwResult<()> {
+    if is_reserved_username(deps, username) {
+        Err(RwError::UsernameReserved)
+    } else {
+        Ok(())
+    }
+}
+
 #[entrait(pub Create, mock_api=CreateMock)]
 async fn create(
-    deps: &(impl password::HashPassword + repo::UserRepo + auth::SignUserId),
+    deps: &(impl GetConfig
+          + password::HashPassword
+          + repo::UserRepo
+          + verification::SendEmailVerification
+          + auth::SignUserId
+          + auth::SignRefreshToken
+          + DomainEvents),
     new_user: NewUser,
+    device: Option<String>,
 ) -> RwResult<SignedUser> {
-    let email = new_user.email.parse()?;
+    check_username(deps, &new_user.username)?;
+    let email = new_user
+        .email
+        .parse::<Email>()?
+        .canonical(deps.plus_addressing());
     let password_hash = deps.hash_password(new_user.password).await?;
 
     let (user, credentials) = deps
         .insert_user(&new_user.username, &email, password_hash)
         .await?;
+    deps.publish(Event::UserSignedUp {
+        user_id: user.user_id.into_id(),
+        username: user.username.clone(),
+    });
+
+    deps.send_email_verification(user.user_id, &credentials.email)
+        .await?;
 
-    Ok(user.sign(deps, credentials.email))
+    user.sign_in(deps, credentials.email, device.as_deref())
+        .await
 }
 
+///
+/// Log in with email and password.
+///
+/// After too many failed logins for one email address or from one IP address,
+/// logging in is locked until the oldest failures are outside the configured window.
+///
 #[entrait(pub Login)]
 async fn login(
-    deps: &(impl repo::UserRepo + password::VerifyPassword + auth::SignUserId),
+    deps: &(impl GetConfig
+          + System
+          + repo::UserRepo
+          + repo::LoginAttemptRepo
+          + password::VerifyPassword
+          + password::HashPassword
+          + auth::SignUserId
+          + auth::SignRefreshToken
+          + AuditLog),
     login_user: LoginUser,
+    ip_address: Option<IpAddr>,
+    device: Option<String>,
 ) -> RwResult<SignedUser> {
-    let (user, credentials) = deps
-        .find_user_credentials_by_email(&login_user.email)
+    let max_failed_logins = deps.max_failed_logins();
+    let now = deps.get_current_time();
+    // Failed logins with tagged addresses count towards the same lock
+    let email = login_user.email.canonical(deps.plus_addressing());
+
+    if max_failed_logins > 0 {
+        let failed_logins = deps
+            .count_failed_logins(&email, ip_address, now - deps.failed_login_window())
+            .await?;
+        if failed_logins >= i64::from(max_failed_logins) {
+            return Err(RwError::TooManyAttempts);
+        }
+    }
+
+    let (user, credentials) =
+        match verify_login(deps, &login_user.email, login_user.password.clone()).await {
+            Ok(verified) => verified,
+            Err(error @ (RwError::EmailDoesNotExist | RwError::Unauthorized)) => {
+                if max_failed_logins > 0 {
+                    deps.insert_failed_login(&email, ip_address, now).await?;
+                }
+                deps.record_audit(NewAuditEntry {
+                    user_id: UserId(None),
+                    action: AuditAction::FailedLogin,
+                    target: Some(email.as_ref()),
+                    ip_address,
+                })
+                .await?;
+                return Err(error);
+            }
+            Err(error) => return Err(error),
+        };
+
+    if max_failed_logins > 0 {
+        deps.delete_failed_logins(&email).await?;
+    }
+
+    if !credentials.email_verified && deps.require_email_verification() {
+        return Err(RwError::EmailNotVerified);
+    }
+
+    rehash_password(
+        deps,
+        user.user_id,
+        &credentials.password_hash,
+        login_user.password,
+    )
+    .await?;
+
+    deps.record_audit(NewAuditEntry {
+        user_id: user.user_id.some(),
+        action: AuditAction::Login,
+        target: None,
+        ip_address,
+    })
+    .await?;
+
+    user.sign_in(deps, credentials.email, device.as_deref())
+        .await
+}
+
+async fn verify_login(
+    deps: &(impl GetConfig + repo::UserRepo + password::VerifyPassword),
+    email: &Email,
+    password: CleartextPassword,
+) -> RwResult<(repo::User, repo::Credentials)> {
+    let (user, credentials) = find_credentials_by_email(deps, email)
         .await?
         .ok_or(RwError::EmailDoesNotExist)?;
 
-    deps.verify_password(login_user.password, credentials.password_hash)
+    deps.verify_password(password, credentials.password_hash.clone())
         .await?;
 
-    Ok(user.sign(deps, credentials.email))
+    Ok((user, credentials))
+}
+
+///
+/// Hash a verified password again with [GetConfig::password_algorithm], if its hash was made with another algorithm,
+/// so that hashes move to the configured algorithm as users log in.
+///
+async fn rehash_password(
+    deps: &(impl GetConfig + repo::UserRepo + password::HashPassword),
+    user_id: UserId,
+    password_hash: &PasswordHash,
+    password: CleartextPassword,
+) -> RwResult<()> {
+    let Some(algorithm) = password::PasswordAlgorithm::of_hash(password_hash) else {
+        return Ok(());
+    };
+    if algorithm == deps.password_algorithm() {
+        return Ok(());
+    }
+
+    let password_hash = deps.hash_password(password).await?;
+    deps.update_user(
+        user_id,
+        repo::UserUpdate {
+            password_hash: Some(password_hash),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+///
+/// Find the user owning the email address, saved either as given or in its canonical form.
+/// Addresses saved before [GetConfig::plus_addressing] changed are found as given.
+///
+async fn find_credentials_by_email(
+    deps: &(impl GetConfig + repo::UserRepo),
+    email: &Email,
+) -> RwResult<Option<(repo::User, repo::Credentials)>> {
+    if let Some(found) = deps.find_user_credentials_by_email(email).await? {
+        return Ok(Some(found));
+    }
+    let canonical = email.canonical(deps.plus_addressing());
+    if canonical == *email {
+        return Ok(None);
+    }
+    deps.find_user_credentials_by_email(&canonical).await
 }
 
 #[entrait(pub FetchCurrent, mock_api=FetchCurrentMock)]
 async fn fetch_current(
-    deps: &(impl Authenticate + repo::UserRepo + auth::SignUserId),
+    deps: &(impl Authenticate + repo::UserRepo),
     token: Token,
 ) -> RwResult<SignedUser> {
-    let current_user_id = deps.authenticate(token)?;
+    let current_user_id = deps.authenticate(token.clone()).await?;
     let (user, credentials) = deps
         .find_user_credentials_by_id(current_user_id)
         .await?
         .ok_or(RwError::CurrentUserDoesNotExist)?;
 
-    Ok(user.sign(deps, credentials.email))
+    Ok(user.with_token(&token, credentials.email))
 }
 
 #[entrait(pub Update)]
 async fn update(
-    deps: &(impl Authenticate + password::HashPassword + repo::UserRepo + auth::SignUserId),
+    deps: &(impl Authenticate
+          + GetConfig
+          + repo::UserRepo
+          + verification::SendEmailVerification
+          + AuditLog),
     token: Token,
     user_update: UserUpdate,
 ) -> RwResult<SignedUser> {
-    let current_user_id = deps.authenticate(token)?;
-    let password_hash = if let Some(password) = &user_update.password {
-        Some(deps.hash_password(password.clone()).await?)
+    let current_user_id = deps.authenticate(token.clone()).await?;
+    if user_update.password.is_some() {
+        return Err(RwError::PasswordNotUpdatable);
+    }
+    if let Some(username) = &user_update.username {
+        check_username(deps, username)?;
+    }
+    if user_update
+        .bio
+        .as_ref()
+        .is_some_and(|bio| bio.len() > MAX_BIO_BYTES)
+    {
+        return Err(RwError::TextTooLarge {
+            field: "bio",
+            max_bytes: MAX_BIO_BYTES,
+        });
+    }
+    let email = user_update
+        .email
+        .as_deref()
+        .map(str::parse::<Email>)
+        .transpose()?
+        .map(|email| email.canonical(deps.plus_addressing()));
+    // To tell whether the email address changes
+    let previous_email = if email.is_some() {
+        deps.find_user_credentials_by_id(current_user_id)
+            .await?
+            .map(|(_, credentials)| credentials.email)
     } else {
         None
     };
+    let bio = user_update
+        .bio
+        .as_deref()
+        .map(|bio| sanitize(deps.sanitize_mode(), bio));
 
     let (user, credentials) = deps
         .update_user(
             current_user_id,
             repo::UserUpdate {
                 username: user_update.username.as_deref(),
-                email: user_update.email.as_deref(),
-                password_hash,
-                bio: user_update.bio.as_deref(),
+                email: email.as_ref().map(AsRef::as_ref),
+                bio: bio.as_deref(),
                 image: user_update.image.as_deref(),
+                ..Default::default()
             },
         )
         .await?;
 
-    Ok(user.sign(deps, credentials.email))
+    if let Some(previous_email) = previous_email.filter(|email| *email != credentials.email) {
+        deps.record_audit(NewAuditEntry {
+            user_id: current_user_id.some(),
+            action: AuditAction::EmailChange,
+            target: Some(previous_email.as_ref()),
+            ip_address: None,
+        })
+        .await?;
+
+        // A changed email address has to be verified again
+        if !credentials.email_verified {
+            deps.send_email_verification(user.user_id, &credentials.email)
+                .await?;
+        }
+    }
+
+    Ok(user.with_token(&token, credentials.email))
+}
+
+///
+/// Change the password of the current user, after confirming the current one.
+/// Users signed up through OAuth have none, and set their first password without it.
+///
+/// All sessions of the user end, so that other devices have to sign in with the new password.
+/// The user is signed in again on a new session on this device.
+///
+#[entrait(pub ChangePassword, mock_api=ChangePasswordMock)]
+async fn change_password(
+    deps: &(impl Authenticate
+          + repo::UserRepo
+          + repo::SessionRepo
+          + password::VerifyPassword
+          + password::HashPassword
+          + auth::SignUserId
+          + auth::SignRefreshToken
+          + AuditLog),
+    token: Token,
+    change: PasswordChange,
+    device: Option<String>,
+) -> RwResult<SignedUser> {
+    let current_user_id = deps.authenticate(token).await?;
+    let (_, credentials) = deps
+        .find_user_credentials_by_id(current_user_id)
+        .await?
+        .ok_or(RwError::CurrentUserDoesNotExist)?;
+
+    if !credentials.password_hash.0.is_empty() {
+        let current_password = change
+            .current_password
+            .ok_or(RwError::WrongCurrentPassword)?;
+        deps.verify_password(current_password, credentials.password_hash)
+            .await
+            .map_err(|error| match error {
+                RwError::Unauthorized => RwError::WrongCurrentPassword,
+                error => error,
+            })?;
+    }
+
+    let password_hash = deps.hash_password(change.new_password).await?;
+    let (user, credentials) = deps
+        .update_user(
+            current_user_id,
+            repo::UserUpdate {
+                password_hash: Some(password_hash),
+                ..Default::default()
+            },
+        )
+        .await?;
+    deps.delete_user_sessions(current_user_id).await?;
+
+    deps.record_audit(NewAuditEntry {
+        user_id: current_user_id.some(),
+        action: AuditAction::PasswordChange,
+        target: None,
+        ip_address: None,
+    })
+    .await?;
+
+    user.sign_in(deps, credentials.email, device.as_deref())
+        .await
+}
+
+///
+/// Delete the current user, after confirming their password.
+///
+/// All sessions of the user end, so that no device stays signed in,
+/// and the access token used for deleting is revoked.
+///
+#[entrait(pub Delete, mock_api=DeleteMock)]
+async fn delete(
+    deps: &(impl Authenticate
+          + GetConfig
+          + repo::UserRepo
+          + repo::SessionRepo
+          + password::VerifyPassword
+          + auth::Logout
+          + FeedCache
+          + DomainEvents),
+    token: Token,
+    confirmation: DeleteUser,
+) -> RwResult<()> {
+    let current_user_id = deps.authenticate(token.clone()).await?;
+    let (_, credentials) = deps
+        .find_user_credentials_by_id(current_user_id)
+        .await?
+        .ok_or(RwError::CurrentUserDoesNotExist)?;
+
+    deps.verify_password(confirmation.password, credentials.password_hash)
+        .await?;
+
+    // While the token is still valid, since its session ends with the account
+    deps.logout(token).await?;
+
+    deps.delete_user(current_user_id, deps.account_deletion_mode())
+        .await?;
+    // An anonymized user is kept, and so would be its sessions
+    deps.delete_user_sessions(current_user_id).await?;
+    // The user's articles, favorites and follows may be part of anyone's feed
+    deps.invalidate_all_feeds().await;
+    deps.publish(Event::UserDeleted {
+        user_id: current_user_id.into_id(),
+    });
+
+    Ok(())
 }
 
 impl repo::User {
-    fn sign(self, deps: &impl auth::SignUserId, email: Email) -> SignedUser {
+    /// The user as seen with the access token of the current request
+    fn with_token(self, token: &Token, email: Email) -> SignedUser {
         SignedUser {
             email,
-            token: deps.sign_user_id(self.user_id),
+            token: token.token().to_string(),
             username: self.username,
             bio: self.bio,
             image: self.image,
+            refresh_token: None,
         }
     }
+
+    /// Sign in on a new session on the device
+    async fn sign_in(
+        self,
+        deps: &(impl auth::SignUserId + auth::SignRefreshToken),
+        email: Email,
+        device: Option<&str>,
+    ) -> RwResult<SignedUser> {
+        let (session_id, refresh_token) = deps.sign_refresh_token(self.user_id, device).await?;
+
+        Ok(SignedUser {
+            email,
+            token: deps.sign_user_id(self.user_id, self.role, session_id),
+            username: self.username,
+            bio: self.bio,
+            image: self.image,
+            refresh_token: Some(refresh_token),
+        })
+    }
 }
 
-#[entrait(pub FetchProfile)]
+#[entrait(pub FetchProfile, mock_api=FetchProfileMock)]
 async fn fetch_profile(
     deps: &(impl Authenticate + repo::UserRepo),
     token: Option<Token>,
     username: &str,
 ) -> RwResult<profile::Profile> {
-    let current_user_id = deps.opt_authenticate(token)?;
-    fetch_profile_inner(deps, current_user_id, username).await
+    let current_user_id = deps.opt_authenticate(token).await?;
+    fetch_profile_inner(deps, current_user_id, username, repo::PastUsernames(true)).await
 }
 
 #[entrait(pub Follow)]
 async fn follow(
-    deps: &(impl Authenticate + repo::UserRepo),
+    deps: &(impl Authenticate + repo::UserRepo + FeedCache + DomainEvents),
     token: Token,
     username: &str,
     value: bool,
 ) -> RwResult<profile::Profile> {
-    let current_user_id = deps.authenticate(token)?;
+    let current_user_id = deps.authenticate(token).await?;
     if value {
         deps.insert_follow(current_user_id, username).await?;
     } else {
         deps.delete_follow(current_user_id, username).await?;
     }
-    fetch_profile_inner(deps, current_user_id.some(), username).await
+    deps.invalidate_feed(current_user_id).await;
+    deps.publish(Event::UserFollowed {
+        follower_id: current_user_id.into_id(),
+        username: username.to_string(),
+        following: value,
+    });
+    fetch_profile_inner(
+        deps,
+        current_user_id.some(),
+        username,
+        repo::PastUsernames(false),
+    )
+    .await
+}
+
+/// Blocked users' articles and comments are left out of the current user's listings
+#[entrait(pub Block, mock_api=BlockMock)]
+async fn block(
+    deps: &(impl Authenticate + repo::UserRepo + FeedCache),
+    token: Token,
+    username: &str,
+    value: bool,
+) -> RwResult<profile::Profile> {
+    let current_user_id = deps.authenticate(token).await?;
+    if value {
+        deps.insert_block(current_user_id, username).await?;
+    } else {
+        deps.delete_block(current_user_id, username).await?;
+    }
+    deps.invalidate_feed(current_user_id).await;
+    fetch_profile_inner(
+        deps,
+        current_user_id.some(),
+        username,
+        repo::PastUsernames(false),
+    )
+    .await
 }
 
 async fn fetch_profile_inner(
     deps: &impl repo::UserRepo,
     current_user_id: UserId<Option<Uuid>>,
     username: &str,
+    past_usernames: repo::PastUsernames,
 ) -> RwResult<profile::Profile> {
-    let (user, following) = deps
-        .find_user_by_username(current_user_id, username)
+    deps.find_user_by_username(current_user_id, username, past_usernames)
         .await?
-        .ok_or(RwError::ProfileNotFound)?;
+        .map(Into::into)
+        .ok_or(RwError::ProfileNotFound)
+}
 
-    Ok(profile::Profile {
-        username: user.username,
-        bio: user.bio,
-        image: user.image,
-        following: following.0,
-    })
+#[entrait(pub ListFollowers, mock_api=ListFollowersMock)]
+async fn list_followers(
+    deps: &(impl Authenticate + repo::UserRepo),
+    token: Option<Token>,
+    username: &str,
+    pagination: Pagination,
+) -> RwResult<Vec<profile::Profile>> {
+    let current_user_id = deps.opt_authenticate(token).await?;
+    fetch_profile_inner(deps, current_user_id, username, repo::PastUsernames(false)).await?;
+
+    Ok(deps
+        .list_followers(current_user_id, username, pagination)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+#[entrait(pub ListFollowing, mock_api=ListFollowingMock)]
+async fn list_following(
+    deps: &(impl Authenticate + repo::UserRepo),
+    token: Option<Token>,
+    username: &str,
+    pagination: Pagination,
+) -> RwResult<Vec<profile::Profile>> {
+    let current_user_id = deps.opt_authenticate(token).await?;
+    fetch_profile_inner(deps, current_user_id, username, repo::PastUsernames(false)).await?;
+
+    Ok(deps
+        .list_following(current_user_id, username, pagination)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+#[derive(serde::Deserialize, Default, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct SearchProfilesQuery {
+    /// Searched for in usernames and bios
+    pub query: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[entrait(pub SearchProfiles, mock_api=SearchProfilesMock)]
+async fn search_profiles(
+    deps: &(impl Authenticate + GetConfig + repo::UserRepo),
+    token: Option<Token>,
+    query: SearchProfilesQuery,
+) -> RwResult<Vec<profile::Profile>> {
+    let current_user_id = deps.opt_authenticate(token).await?;
+    let search = query.query.trim();
+    if search.is_empty() {
+        return Err(RwError::SearchQueryMissing);
+    }
+    let pagination = deps.page_sizes().apply(Pagination {
+        limit: query.limit,
+        offset: query.offset,
+    })?;
+
+    Ok(deps
+        .search_users(current_user_id, search, pagination)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
 }
 
 #[cfg(test)]
@@ -203,6 +688,10 @@ mod tests {
         String::from("t3stt0k1")
     }
 
+    fn test_session_id() -> uuid::Uuid {
+        uuid::Uuid::parse_str("5c8d6a4e-2b4f-4d8e-9f1a-3e7b6c0d2a91").unwrap()
+    }
+
     fn test_user_id() -> UserId {
         UserId(uuid::Uuid::parse_str("20a626ba-c7d3-44c7-981a-e880f81c126f").unwrap())
     }
@@ -213,9 +702,26 @@ mod tests {
             username: "Name".into(),
             bio: "".to_string(),
             image: None,
+            role: role::Role::User,
         }
     }
 
+    fn mock_login_lockout(failed_logins: i64) -> impl unimock::Clause {
+        (
+            crate::test::mock_current_time(),
+            crate::test::mock_plus_addressing(),
+            crate::GetConfigMock::max_failed_logins
+                .each_call(matching!())
+                .returns(5_u32),
+            crate::GetConfigMock::failed_login_window
+                .each_call(matching!())
+                .returns(time::Duration::minutes(15)),
+            repo::LoginAttemptRepoMock::count_failed_logins
+                .next_call(matching!("name@email.com", _, _))
+                .returns(Ok(failed_logins)),
+        )
+    }
+
     pub fn mock_hash_password() -> impl unimock::Clause {
         HashPasswordMock
             .next_call(matching!(_))
@@ -225,6 +731,8 @@ mod tests {
     #[tokio::test]
     async fn test_create_user() {
         let deps = Unimock::new((
+            crate::test::mock_reserved_usernames(),
+            crate::test::mock_plus_addressing(),
             mock_hash_password(),
             repo::UserRepoMock::insert_user
                 .next_call(matching!("Name", "name@email.com", "h4sh"))
@@ -235,15 +743,28 @@ mod tests {
                             username: username.to_string(),
                             bio: "".to_string(),
                             image: None,
+                            role: role::Role::User,
                         },
                         repo::Credentials {
                             email: email.clone(),
                             password_hash,
+                            email_verified: false,
                         },
                     ))
                 }),
+            crate::event::DomainEventsMock::publish
+                .next_call(matching! {
+                    (Event::UserSignedUp { username, .. }) if username == "Name"
+                })
+                .returns(()),
+            verification::SendEmailVerificationMock
+                .next_call(matching!(_, "name@email.com"))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, _))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
             auth::SignUserIdMock
-                .next_call(matching!(_))
+                .next_call(matching!((_, _, session_id) if *session_id == test_session_id()))
                 .returns(test_token()),
         ));
 
@@ -254,16 +775,55 @@ mod tests {
                 email: "name@email.com".parse().unwrap(),
                 password: "password".into(),
             },
+            None,
         )
         .await
         .unwrap();
 
         assert_eq!(signed_user.token, test_token());
+        assert_eq!(signed_user.refresh_token, Some("r3fr3sh".into()));
+    }
+
+    #[tokio::test]
+    async fn reserved_username_should_be_rejected_regardless_of_case() {
+        let deps = Unimock::new((
+            crate::test::mock_reserved_usernames(),
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+        ));
+
+        assert_matches!(
+            create(
+                &deps,
+                NewUser {
+                    username: "Admin".into(),
+                    email: "name@email.com".into(),
+                    password: "password".into(),
+                },
+                None,
+            )
+            .await,
+            Err(RwError::UsernameReserved)
+        );
+        assert_matches!(
+            update(
+                &deps,
+                Token::from_token("token"),
+                UserUpdate {
+                    username: Some("ADMIN".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::UsernameReserved)
+        );
     }
 
     #[tokio::test]
     async fn test_login_ok() {
         let deps = Unimock::new((
+            mock_login_lockout(4),
             repo::UserRepoMock::find_user_credentials_by_email
                 .next_call(matching!("name@email.com"))
                 .answers(&|_, email| {
@@ -272,14 +832,28 @@ mod tests {
                         repo::Credentials {
                             email: email.clone(),
                             password_hash: "h4sh".into(),
+                            email_verified: true,
                         },
                     )))
                 }),
             password::VerifyPasswordMock
                 .next_call(matching!(_))
                 .returns(Ok(())),
+            repo::LoginAttemptRepoMock::delete_failed_logins
+                .next_call(matching!("name@email.com"))
+                .returns(Ok(())),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::Login,
+                    target: None,
+                    ..
+                }))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, _))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
             auth::SignUserIdMock
-                .next_call(matching!(_))
+                .next_call(matching!((_, _, session_id) if *session_id == test_session_id()))
                 .returns(test_token()),
         ));
 
@@ -289,6 +863,8 @@ mod tests {
                 email: "name@email.com".parse().unwrap(),
                 password: "password".into(),
             },
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -296,14 +872,107 @@ mod tests {
         assert_eq!(signed_user.token, test_token());
     }
 
+    /// Deps for logging in successfully with a password hashed as `password_hash`
+    fn mock_login_with_hash(password_hash: &'static str) -> impl unimock::Clause {
+        (
+            mock_login_lockout(0),
+            repo::UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("name@email.com"))
+                .answers_arc(Arc::new(move |_, email| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: email.clone(),
+                            password_hash: password_hash.into(),
+                            email_verified: true,
+                        },
+                    )))
+                })),
+            password::VerifyPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok(())),
+            repo::LoginAttemptRepoMock::delete_failed_logins
+                .next_call(matching!("name@email.com"))
+                .returns(Ok(())),
+            crate::GetConfigMock::password_algorithm
+                .each_call(matching!())
+                .returns(password::PasswordAlgorithm::Argon2id),
+        )
+    }
+
+    fn mock_signed_in() -> impl unimock::Clause {
+        (
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::Login,
+                    ..
+                }))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, _))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
+            auth::SignUserIdMock
+                .next_call(matching!((_, _, session_id) if *session_id == test_session_id()))
+                .returns(test_token()),
+        )
+    }
+
+    fn test_login_user() -> LoginUser {
+        LoginUser {
+            email: "name@email.com".parse().unwrap(),
+            password: "password".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn login_should_rehash_password_hashed_with_another_algorithm() {
+        let deps = Unimock::new((
+            mock_login_with_hash("$2b$04$29xGewtac4oMV2RV7XSSnOb49ZVD1ISFs/CeElQ./YQh4Smc3y4pS"),
+            HashPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok("$argon2id$n3w h4sh".into())),
+            repo::UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.password_hash == Some("$argon2id$n3w h4sh".into())
+                })
+                .answers(&|_, _, _| {
+                    Ok((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "$argon2id$n3w h4sh".into(),
+                            email_verified: true,
+                        },
+                    ))
+                }),
+            mock_signed_in(),
+        ));
+
+        let signed_user = login(&deps, test_login_user(), None, None).await.unwrap();
+        assert_eq!(signed_user.token, test_token());
+    }
+
+    #[tokio::test]
+    async fn login_should_keep_hash_of_configured_algorithm() {
+        let deps = Unimock::new((
+            mock_login_with_hash("$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"),
+            mock_signed_in(),
+        ));
+
+        let signed_user = login(&deps, test_login_user(), None, None).await.unwrap();
+        assert_eq!(signed_user.token, test_token());
+    }
+
     #[tokio::test]
     async fn integration_test_mismatched_password() {
-        let wrong_password_hash = ::entrait::Impl::new(())
-            .hash_password("wrong_password".into())
-            .await
-            .unwrap();
+        let wrong_password_hash =
+            ::entrait::Impl::new(Unimock::new(crate::test::mock_password_algorithm()))
+                .hash_password("wrong_password".into())
+                .await
+                .unwrap();
 
-        let deps = Unimock::new_partial(
+        let deps = Unimock::new_partial((
+            mock_login_lockout(0),
             repo::UserRepoMock::find_user_credentials_by_email
                 .next_call(matching!("name@email.com"))
                 .answers_arc(Arc::new(move |_, email| {
@@ -312,10 +981,22 @@ mod tests {
                         repo::Credentials {
                             email: email.clone(),
                             password_hash: wrong_password_hash.clone(),
+                            email_verified: true,
                         },
                     )))
                 })),
-        );
+            repo::LoginAttemptRepoMock::insert_failed_login
+                .next_call(matching!("name@email.com", Some(_), _))
+                .returns(Ok(())),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    user_id: UserId(None),
+                    action: AuditAction::FailedLogin,
+                    target: Some("name@email.com"),
+                    ip_address: Some(_),
+                }))
+                .returns(Ok(())),
+        ));
 
         let error = login(
             &deps,
@@ -323,10 +1004,603 @@ mod tests {
                 email: "name@email.com".parse().unwrap(),
                 password: "password".into(),
             },
+            Some(IpAddr::from([127, 0, 0, 1])),
+            None,
         )
         .await
         .expect_err("should error");
 
         assert_matches!(error, RwError::Unauthorized);
     }
+
+    #[tokio::test]
+    async fn login_should_require_verified_email_when_configured() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            crate::test::mock_plus_addressing(),
+            crate::GetConfigMock::max_failed_logins
+                .next_call(matching!())
+                .returns(0_u32),
+            repo::UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("name@email.com"))
+                .answers(&|_, email| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: email.clone(),
+                            password_hash: "h4sh".into(),
+                            email_verified: false,
+                        },
+                    )))
+                }),
+            password::VerifyPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok(())),
+            crate::GetConfigMock::require_email_verification
+                .next_call(matching!())
+                .returns(true),
+        ));
+
+        let error = login(
+            &deps,
+            LoginUser {
+                email: "name@email.com".parse().unwrap(),
+                password: "password".into(),
+            },
+            None,
+            None,
+        )
+        .await
+        .expect_err("should error");
+
+        assert_matches!(error, RwError::EmailNotVerified);
+    }
+
+    #[tokio::test]
+    async fn tagged_email_should_log_in_as_untagged_owner_when_stripped() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            crate::GetConfigMock::max_failed_logins
+                .next_call(matching!())
+                .returns(0_u32),
+            crate::GetConfigMock::plus_addressing
+                .each_call(matching!())
+                .returns(email::PlusAddressing::Strip),
+            repo::UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("name+tag@email.com"))
+                .returns(Ok(None)),
+            repo::UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("name@email.com"))
+                .answers(&|_, email| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: email.clone(),
+                            password_hash: "h4sh".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            password::VerifyPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok(())),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(_))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, _))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
+            auth::SignUserIdMock
+                .next_call(matching!((_, _, session_id) if *session_id == test_session_id()))
+                .returns(test_token()),
+        ));
+
+        let signed_user = login(
+            &deps,
+            LoginUser {
+                email: "name+tag@email.com".parse().unwrap(),
+                password: "password".into(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!("name@email.com", signed_user.email.as_ref());
+    }
+
+    #[tokio::test]
+    async fn login_should_be_locked_after_too_many_failures() {
+        let deps = Unimock::new(mock_login_lockout(5));
+
+        let error = login(
+            &deps,
+            LoginUser {
+                email: "name@email.com".parse().unwrap(),
+                password: "password".into(),
+            },
+            None,
+            None,
+        )
+        .await
+        .expect_err("should error");
+
+        assert_matches!(error, RwError::TooManyAttempts);
+    }
+
+    #[tokio::test]
+    async fn followers_of_unknown_user_should_not_be_found() {
+        let deps = Unimock::new((
+            auth::authenticate::AuthenticateMock::opt_authenticate
+                .next_call(matching!(None))
+                .returns(Ok(UserId(None))),
+            repo::UserRepoMock::find_user_by_username
+                .next_call(matching!(_, "unknown", _))
+                .returns(Ok(None)),
+        ));
+
+        assert_matches!(
+            list_followers(&deps, None, "unknown", Pagination::default()).await,
+            Err(RwError::ProfileNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn profile_search_should_trim_and_require_a_query() {
+        let deps = Unimock::new((
+            auth::authenticate::AuthenticateMock::opt_authenticate
+                .each_call(matching!(None))
+                .answers(&|_, _| Ok(UserId(None))),
+            crate::test::mock_page_sizes(),
+            repo::UserRepoMock::search_users
+                .next_call(matching!(
+                    _,
+                    "jake",
+                    Pagination {
+                        limit: Some(20),
+                        offset: None
+                    }
+                ))
+                .returns(Ok(vec![(test_repo_user(), repo::Following(false))])),
+        ));
+
+        let profiles = search_profiles(
+            &deps,
+            None,
+            SearchProfilesQuery {
+                query: " jake ".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, profiles.len());
+
+        assert_matches!(
+            search_profiles(
+                &deps,
+                None,
+                SearchProfilesQuery {
+                    query: "  ".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::SearchQueryMissing)
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_should_invalidate_own_feed() {
+        use crate::article::feed_cache::FeedCacheMock;
+
+        let deps = Unimock::new((
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::insert_block
+                .next_call(matching!(_, "troll"))
+                .returns(Ok(())),
+            FeedCacheMock::invalidate_feed
+                .next_call(matching!((UserId(id)) if *id == test_user_id().0))
+                .returns(()),
+            repo::UserRepoMock::find_user_by_username
+                .next_call(matching!(_, "troll", repo::PastUsernames(false)))
+                .answers(&|_, _, _, _| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Following(false),
+                        repo::FollowStats::default(),
+                    )))
+                }),
+        ));
+
+        block(&deps, Token::from_token("token"), "troll", true)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_email_should_be_audited() {
+        let deps = Unimock::new((
+            crate::test::mock_plus_addressing(),
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "old@email.com".parse().unwrap(),
+                            password_hash: "h4sh".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            repo::UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.email == Some("new@email.com")
+                })
+                .answers(&|_, _, _| {
+                    Ok((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "new@email.com".parse().unwrap(),
+                            password_hash: "h4sh".into(),
+                            email_verified: false,
+                        },
+                    ))
+                }),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::EmailChange,
+                    target: Some("old@email.com"),
+                    ..
+                }))
+                .returns(Ok(())),
+            verification::SendEmailVerificationMock
+                .next_call(matching!(_, "new@email.com"))
+                .returns(Ok(())),
+        ));
+
+        let signed_user = update(
+            &deps,
+            Token::from_token("token"),
+            UserUpdate {
+                email: Some("new@email.com".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!("new@email.com", signed_user.email.as_ref());
+        assert_eq!("token", signed_user.token);
+    }
+
+    #[tokio::test]
+    async fn unchanged_email_should_not_be_verified_again() {
+        let deps = Unimock::new((
+            crate::test::mock_sanitize_mode(),
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.email.is_none() && update.bio == Some("new bio")
+                })
+                .answers(&|_, _, _| {
+                    Ok((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "unverified@email.com".parse().unwrap(),
+                            password_hash: "h4sh".into(),
+                            email_verified: false,
+                        },
+                    ))
+                }),
+        ));
+
+        update(
+            &deps,
+            Token::from_token("token"),
+            UserUpdate {
+                bio: Some("new bio".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn password_should_not_be_updated_without_confirming_it() {
+        let deps = Unimock::new(
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+        );
+
+        assert_matches!(
+            update(
+                &deps,
+                Token::from_token("token"),
+                UserUpdate {
+                    password: Some("new password".into()),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::PasswordNotUpdatable)
+        );
+    }
+
+    #[tokio::test]
+    async fn too_large_bio_should_be_rejected_before_sanitizing() {
+        let deps = Unimock::new(
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+        );
+
+        assert_matches!(
+            update(
+                &deps,
+                Token::from_token("token"),
+                UserUpdate {
+                    bio: Some("<".repeat(MAX_BIO_BYTES + 1)),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(RwError::TextTooLarge { field: "bio", .. })
+        );
+    }
+
+    fn mock_password_confirmation(password_ok: bool) -> impl unimock::Clause {
+        (
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "h4sh".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            password::VerifyPasswordMock
+                .next_call(matching!(_, _))
+                .returns(if password_ok {
+                    Ok(())
+                } else {
+                    Err(RwError::Unauthorized)
+                }),
+        )
+    }
+
+    #[tokio::test]
+    async fn delete_should_use_configured_mode_and_end_sessions() {
+        let deps = Unimock::new((
+            crate::test::mock_publish_events(),
+            mock_password_confirmation(true),
+            auth::LogoutMock.next_call(matching!(_)).returns(Ok(())),
+            crate::GetConfigMock::account_deletion_mode
+                .next_call(matching!())
+                .returns(repo::DeletionMode::Anonymize),
+            repo::UserRepoMock::delete_user
+                .next_call(matching!(_, repo::DeletionMode::Anonymize))
+                .returns(Ok(())),
+            repo::SessionRepoMock::delete_user_sessions
+                .next_call(matching!(_))
+                .returns(Ok(2)),
+            crate::article::feed_cache::FeedCacheMock::invalidate_all_feeds
+                .next_call(matching!())
+                .returns(()),
+        ));
+
+        delete(
+            &deps,
+            Token::from_token("token"),
+            DeleteUser {
+                password: "password".into(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn delete_with_wrong_password_should_not_delete() {
+        let deps = Unimock::new(mock_password_confirmation(false));
+
+        assert_matches!(
+            delete(
+                &deps,
+                Token::from_token("token"),
+                DeleteUser {
+                    password: "wrong".into(),
+                },
+            )
+            .await,
+            Err(RwError::Unauthorized)
+        );
+    }
+
+    #[tokio::test]
+    async fn change_password_should_end_sessions_and_sign_in_again() {
+        let deps = Unimock::new((
+            mock_password_confirmation(true),
+            password::HashPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok("n3w h4sh".into())),
+            repo::UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.password_hash == Some("n3w h4sh".into())
+                })
+                .answers(&|_, _, _| {
+                    Ok((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "n3w h4sh".into(),
+                            email_verified: true,
+                        },
+                    ))
+                }),
+            repo::SessionRepoMock::delete_user_sessions
+                .next_call(matching!(_))
+                .returns(Ok(2)),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::PasswordChange,
+                    ..
+                }))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, Some("curl/8.0")))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
+            auth::SignUserIdMock
+                .next_call(matching!((_, _, session_id) if *session_id == test_session_id()))
+                .returns(test_token()),
+        ));
+
+        let signed_user = change_password(
+            &deps,
+            Token::from_token("token"),
+            PasswordChange {
+                current_password: Some("password".into()),
+                new_password: "new password".into(),
+            },
+            Some("curl/8.0".to_string()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(Some("r3fr3sh".into()), signed_user.refresh_token);
+    }
+
+    #[tokio::test]
+    async fn change_password_without_current_password_should_fail() {
+        let deps = Unimock::new((
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "h4sh".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+        ));
+
+        assert_matches!(
+            change_password(
+                &deps,
+                Token::from_token("token"),
+                PasswordChange {
+                    current_password: None,
+                    new_password: "new password".into(),
+                },
+                None,
+            )
+            .await,
+            Err(RwError::WrongCurrentPassword)
+        );
+    }
+
+    #[tokio::test]
+    async fn oauth_user_should_set_first_password_without_current_one() {
+        let deps = Unimock::new((
+            auth::authenticate::AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(test_user_id())),
+            repo::UserRepoMock::find_user_credentials_by_id
+                .next_call(matching!(_))
+                .answers(&|_, _| {
+                    Ok(Some((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            password::HashPasswordMock
+                .next_call(matching!(_))
+                .returns(Ok("n3w h4sh".into())),
+            repo::UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.password_hash == Some("n3w h4sh".into())
+                })
+                .answers(&|_, _, _| {
+                    Ok((
+                        test_repo_user(),
+                        repo::Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "n3w h4sh".into(),
+                            email_verified: true,
+                        },
+                    ))
+                }),
+            repo::SessionRepoMock::delete_user_sessions
+                .next_call(matching!(_))
+                .returns(Ok(1)),
+            crate::audit::AuditLogMock::record_audit
+                .next_call(matching!(_))
+                .returns(Ok(())),
+            auth::SignRefreshTokenMock
+                .next_call(matching!(_, None))
+                .returns(Ok((test_session_id(), OpaqueToken::from("r3fr3sh")))),
+            auth::SignUserIdMock
+                .next_call(matching!(_, _, _))
+                .returns(test_token()),
+        ));
+
+        change_password(
+            &deps,
+            Token::from_token("token"),
+            PasswordChange {
+                current_password: None,
+                new_password: "new password".into(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn change_password_with_wrong_current_password_should_fail() {
+        let deps = Unimock::new(mock_password_confirmation(false));
+
+        assert_matches!(
+            change_password(
+                &deps,
+                Token::from_token("token"),
+                PasswordChange {
+                    current_password: Some("wrong".into()),
+                    new_password: "new password".into(),
+                },
+                None,
+            )
+            .await,
+            Err(RwError::WrongCurrentPassword)
+        );
+    }
 }
@@ -0,0 +1,228 @@
+//!
+//! Signing in through an external OAuth provider, such as GitHub, instead of with a password.
+//!
+
+use super::email::Email;
+use super::opaque_token::OpaqueToken;
+use super::repo::{Created, UserRepo};
+use super::verification::SendEmailVerification;
+use super::{auth, SignedUser};
+use crate::error::{RwError, RwResult};
+use crate::event::{DomainEvents, Event};
+use crate::GetConfig;
+
+use entrait::entrait_export as entrait;
+
+/// A user as identified by an OAuth provider
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OAuthIdentity {
+    /// Name of the provider, e.g. `github`
+    pub provider: String,
+    /// The provider's stable id of the user
+    pub subject: String,
+    pub email: Email,
+    /// Whether the provider has verified that the user owns the email address
+    pub email_verified: bool,
+    /// Username for a new user, e.g. the user's login at the provider
+    pub username: String,
+}
+
+///
+/// Mockable OAuth providers, by name.
+/// Unknown or unconfigured providers fail with [RwError::OAuthProviderNotFound].
+///
+#[entrait(mock_api=OAuthProviderMock)]
+pub trait OAuthProvider {
+    /// The provider's sign-in page. It redirects back to the callback with a code and the `state`.
+    fn oauth_authorize_url(&self, provider: &str, state: &str) -> RwResult<String>;
+
+    /// Trade the code from the callback for the identity of the user
+    async fn fetch_oauth_identity(&self, provider: &str, code: &str) -> RwResult<OAuthIdentity>;
+}
+
+pub struct OAuthStart {
+    pub authorize_url: String,
+    /// Must be presented along with the code in the callback,
+    /// to prove that the sign-in was started by the same client
+    pub state: OpaqueToken,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OAuthCallback {
+    pub code: String,
+    pub state: String,
+}
+
+#[entrait(pub StartOAuth, mock_api=StartOAuthMock)]
+fn start_oauth(deps: &impl OAuthProvider, provider: &str) -> RwResult<OAuthStart> {
+    let state = OpaqueToken::generate();
+
+    Ok(OAuthStart {
+        authorize_url: deps.oauth_authorize_url(provider, state.as_ref())?,
+        state,
+    })
+}
+
+///
+/// Sign in with the code from the provider's callback, creating the user on first sign-in.
+///
+/// `expected_state` is the state of [start_oauth], as remembered by the client.
+///
+#[entrait(pub FinishOAuth, mock_api=FinishOAuthMock)]
+async fn finish_oauth(
+    deps: &(impl GetConfig
+          + OAuthProvider
+          + UserRepo
+          + SendEmailVerification
+          + auth::SignUserId
+          + auth::SignRefreshToken
+          + DomainEvents),
+    provider: &str,
+    callback: OAuthCallback,
+    expected_state: Option<String>,
+    device: Option<String>,
+) -> RwResult<SignedUser> {
+    if expected_state.as_deref() != Some(callback.state.as_str()) {
+        return Err(RwError::Unauthorized);
+    }
+
+    let mut identity = deps.fetch_oauth_identity(provider, &callback.code).await?;
+    identity.email = identity.email.canonical(deps.plus_addressing());
+    // Only used if the user is new, which like a taken username gets a suffix
+    if super::is_reserved_username(deps, &identity.username) {
+        identity.username = format!(
+            "{}-{}",
+            identity.username,
+            &uuid::Uuid::new_v4().simple().to_string()[..6]
+        );
+    }
+    let (user, credentials, Created(created)) =
+        deps.find_or_create_by_oauth_identity(&identity).await?;
+
+    if created {
+        deps.publish(Event::UserSignedUp {
+            user_id: user.user_id.into_id(),
+            username: user.username.clone(),
+        });
+
+        if !credentials.email_verified {
+            deps.send_email_verification(user.user_id, &credentials.email)
+                .await?;
+        }
+    }
+
+    if !credentials.email_verified && deps.require_email_verification() {
+        return Err(RwError::EmailNotVerified);
+    }
+
+    user.sign_in(deps, credentials.email, device.as_deref())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::auth::{SignRefreshTokenMock, SignUserIdMock};
+    use crate::user::repo::{Credentials, User, UserRepoMock};
+    use crate::user::UserId;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn test_identity() -> OAuthIdentity {
+        OAuthIdentity {
+            provider: "github".to_string(),
+            subject: "42".to_string(),
+            email: "octocat@example.com".parse().unwrap(),
+            email_verified: true,
+            username: "octocat".to_string(),
+        }
+    }
+
+    fn test_callback() -> OAuthCallback {
+        OAuthCallback {
+            code: "code".to_string(),
+            state: "state".to_string(),
+        }
+    }
+
+    #[test]
+    fn start_should_pass_state_to_provider() {
+        let deps = Unimock::new(
+            OAuthProviderMock::oauth_authorize_url
+                .next_call(matching!("github", _))
+                .answers(&|_, _, state| Ok(format!("https://github.com/login?state={state}"))),
+        );
+
+        let start = start_oauth(&deps, "github").unwrap();
+        assert_eq!(
+            format!("https://github.com/login?state={}", start.state.as_ref()),
+            start.authorize_url
+        );
+    }
+
+    #[tokio::test]
+    async fn first_sign_in_should_create_user() {
+        let deps = Unimock::new((
+            crate::test::mock_reserved_usernames(),
+            crate::test::mock_plus_addressing(),
+            OAuthProviderMock::fetch_oauth_identity
+                .next_call(matching!("github", "code"))
+                .returns(Ok(test_identity())),
+            UserRepoMock::find_or_create_by_oauth_identity
+                .next_call(matching!((identity) if identity.subject == "42"))
+                .answers(&|_, identity| {
+                    Ok((
+                        User {
+                            user_id: UserId(uuid::Uuid::new_v4()),
+                            username: identity.username.clone(),
+                            bio: "".to_string(),
+                            image: None,
+                            role: Default::default(),
+                        },
+                        Credentials {
+                            email: identity.email.clone(),
+                            password_hash: "".into(),
+                            email_verified: identity.email_verified,
+                        },
+                        Created(true),
+                    ))
+                }),
+            crate::event::DomainEventsMock::publish
+                .next_call(matching!((event) if matches!(event, Event::UserSignedUp { .. })))
+                .returns(()),
+            SignRefreshTokenMock
+                .next_call(matching!(_, _))
+                .returns(Ok((uuid::Uuid::nil(), OpaqueToken::from("refresh")))),
+            SignUserIdMock
+                .next_call(matching!(_, _, _))
+                .returns("token".to_string()),
+        ));
+
+        let signed_user =
+            finish_oauth(&deps, "github", test_callback(), Some("state".into()), None)
+                .await
+                .unwrap();
+
+        assert_eq!("octocat", signed_user.username);
+        assert_eq!("token", signed_user.token);
+        assert_eq!(
+            Some(OpaqueToken::from("refresh")),
+            signed_user.refresh_token
+        );
+    }
+
+    #[tokio::test]
+    async fn callback_with_other_state_should_be_unauthorized() {
+        let deps = Unimock::new(());
+
+        assert_matches!(
+            finish_oauth(&deps, "github", test_callback(), Some("other".into()), None).await,
+            Err(RwError::Unauthorized)
+        );
+        assert_matches!(
+            finish_oauth(&deps, "github", test_callback(), None, None).await,
+            Err(RwError::Unauthorized)
+        );
+    }
+}
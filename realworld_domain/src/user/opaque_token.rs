@@ -0,0 +1,80 @@
+use rand::RngCore;
+use sha2::Digest;
+
+const OPAQUE_TOKEN_BYTES: usize = 32;
+
+///
+/// A random secret handed out to a client, e.g. a refresh token.
+///
+/// Unlike a JWT, it carries no information by itself; it's only meaningful
+/// when looked up (by its hash) in a repository.
+///
+#[derive(Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(transparent)]
+pub struct OpaqueToken(String);
+
+impl OpaqueToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; OPAQUE_TOKEN_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(to_hex(&bytes))
+    }
+
+    /// The representation that gets persisted. The token itself should never be stored.
+    pub fn hash(&self) -> OpaqueTokenHash {
+        OpaqueTokenHash(to_hex(&sha2::Sha256::digest(self.0.as_bytes())))
+    }
+}
+
+impl<S: Into<String>> From<S> for OpaqueToken {
+    fn from(s: S) -> Self {
+        Self(s.into())
+    }
+}
+
+impl AsRef<str> for OpaqueToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpaqueTokenHash(pub String);
+
+impl AsRef<str> for OpaqueTokenHash {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut output, byte| {
+        let _ = write!(output, "{byte:02x}");
+        output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_tokens_should_be_unique() {
+        let a = OpaqueToken::generate();
+        let b = OpaqueToken::generate();
+
+        assert_eq!(a.as_ref().len(), OPAQUE_TOKEN_BYTES * 2);
+        assert_ne!(a, b);
+        assert_ne!(a.hash(), b.hash());
+    }
+
+    #[test]
+    fn hash_should_be_stable() {
+        assert_eq!(
+            OpaqueToken::from("token").hash().0,
+            "3c469e9d6c5875d37a43f353d4f88e61fcf812c66eee3457465a40b0da4153e0"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::{RwError, RwResult};
+use crate::GetConfig;
 
 use anyhow::Context;
 use argon2::password_hash::SaltString;
@@ -37,53 +38,130 @@ impl AsRef<str> for PasswordHash {
     }
 }
 
-#[entrait(pub HashPassword, no_deps, mock_api=HashPasswordMock)]
-async fn hash_password(password: CleartextPassword) -> RwResult<PasswordHash> {
-    // Argon2 hashing is designed to be computationally intensive,
-    // so we need to do this on a blocking thread.
-    tokio::task::spawn_blocking(move || -> RwResult<PasswordHash> {
+///
+/// Algorithms that password hashes can be made with.
+///
+/// New hashes are made with the one given by [GetConfig::password_algorithm],
+/// while hashes of every algorithm can be verified, e.g. those of users imported from another system.
+/// The algorithm of a hash is told by its format.
+///
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PasswordAlgorithm {
+    /// Argon2id in PHC format, `$argon2id$...`
+    #[default]
+    Argon2id,
+    /// bcrypt in modular crypt format, `$2b$...`
+    Bcrypt,
+    /// scrypt in PHC format, `$scrypt$...`
+    Scrypt,
+}
+
+impl PasswordAlgorithm {
+    /// The algorithm of a hash, if it has a known format
+    pub fn of_hash(password_hash: &PasswordHash) -> Option<Self> {
+        let hash = password_hash.as_ref();
+        if hash.starts_with("$argon2") {
+            Some(Self::Argon2id)
+        } else if ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+        {
+            Some(Self::Bcrypt)
+        } else if hash.starts_with("$scrypt$") {
+            Some(Self::Scrypt)
+        } else {
+            None
+        }
+    }
+
+    /// Blocking, the algorithms are designed to be computationally intensive
+    fn hash(self, password: &str) -> RwResult<PasswordHash> {
         let salt = SaltString::generate(rand::thread_rng());
-        Ok(
-            argon2::PasswordHash::generate(Argon2::default(), password.0, &salt)
-                .map_err(|e| anyhow::anyhow!("failed to generate password hash: {}", e))?
-                .to_string()
-                .into(),
-        )
-    })
-    .await
-    .context("panic when generating password hash")?
+        let hash = match self {
+            Self::Argon2id => argon2::PasswordHash::generate(Argon2::default(), password, &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| anyhow::anyhow!("failed to generate password hash: {}", e))?,
+            Self::Bcrypt => bcrypt::hash(password, bcrypt::DEFAULT_COST)
+                .map_err(|e| anyhow::anyhow!("failed to generate password hash: {}", e))?,
+            Self::Scrypt => argon2::PasswordHash::generate(scrypt::Scrypt, password, &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| anyhow::anyhow!("failed to generate password hash: {}", e))?,
+        };
+
+        Ok(hash.into())
+    }
+
+    /// Blocking like [Self::hash]. `Unauthorized` when the password doesn't match.
+    fn verify(self, password: &str, password_hash: &PasswordHash) -> RwResult<()> {
+        match self {
+            Self::Argon2id | Self::Scrypt => {
+                let hash = argon2::PasswordHash::new(password_hash.as_ref())
+                    .map_err(|e| anyhow::anyhow!("invalid password hash: {}", e))?;
+
+                hash.verify_password(&[&Argon2::default(), &scrypt::Scrypt], password)
+                    .map_err(|e| match e {
+                        argon2::password_hash::Error::Password => RwError::Unauthorized,
+                        _ => anyhow::anyhow!("failed to verify password hash: {}", e).into(),
+                    })
+            }
+            Self::Bcrypt => match bcrypt::verify(password, password_hash.as_ref()) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(RwError::Unauthorized),
+                Err(e) => Err(anyhow::anyhow!("failed to verify password hash: {}", e).into()),
+            },
+        }
+    }
+}
+
+#[entrait(pub HashPassword, mock_api=HashPasswordMock)]
+async fn hash_password(
+    deps: &impl GetConfig,
+    password: CleartextPassword,
+) -> RwResult<PasswordHash> {
+    let algorithm = deps.password_algorithm();
+
+    // Hashing is designed to be computationally intensive,
+    // so we need to do this on a blocking thread.
+    tokio::task::spawn_blocking(move || algorithm.hash(&password.0))
+        .await
+        .context("panic when generating password hash")?
 }
 
 #[entrait(pub VerifyPassword, no_deps, mock_api=VerifyPasswordMock)]
 async fn verify_password(password: CleartextPassword, password_hash: PasswordHash) -> RwResult<()> {
-    tokio::task::spawn_blocking(move || -> RwResult<()> {
-        use argon2::password_hash::PasswordHash;
-        let hash = PasswordHash::new(&password_hash.0)
-            .map_err(|e| anyhow::anyhow!("invalid password hash: {}", e))?;
-
-        hash.verify_password(&[&Argon2::default()], password.0)
-            .map_err(|e| match e {
-                argon2::password_hash::Error::Password => RwError::Unauthorized,
-                _ => anyhow::anyhow!("failed to verify password hash: {}", e).into(),
-            })
-    })
-    .await
-    .context("panic when verifying password hash")??;
-
-    Ok(())
+    // Users signed up through OAuth, and anonymized users, have no password
+    if password_hash.0.is_empty() {
+        return Err(RwError::Unauthorized);
+    }
+    let algorithm = PasswordAlgorithm::of_hash(&password_hash)
+        .ok_or_else(|| anyhow::anyhow!("invalid password hash: unknown format"))?;
+
+    tokio::task::spawn_blocking(move || algorithm.verify(&password.0, &password_hash))
+        .await
+        .context("panic when verifying password hash")?
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use assert_matches::*;
+    use unimock::*;
+
+    /// Made with a low cost, like hashes imported from elsewhere may have
+    const BCRYPT_HASH: &str = "$2b$04$29xGewtac4oMV2RV7XSSnOb49ZVD1ISFs/CeElQ./YQh4Smc3y4pS";
+    const SCRYPT_HASH: &str =
+        "$scrypt$ln=4,r=8,p=1$bGVnYWN5LXNhbHQtMTIzNA$u/tjXkxfFlojB6bDS/jgfL664h3dKxushHV7RM8hrN0";
 
     #[tokio::test]
     async fn password_hashing_should_work() {
         let password = CleartextPassword("v3rys3cr3t".to_string());
-        let app = entrait::Impl::new(());
+        let app = entrait::Impl::new(Unimock::new(crate::test::mock_password_algorithm()));
         let hash = app.hash_password(password.clone()).await.unwrap();
 
+        assert_eq!(
+            Some(PasswordAlgorithm::Argon2id),
+            PasswordAlgorithm::of_hash(&hash)
+        );
         assert!(app
             .verify_password(password.clone(), hash.clone())
             .await
@@ -99,5 +177,64 @@ mod tests {
                 .await,
             Err(RwError::Anyhow(_))
         );
+
+        assert_matches!(
+            app.verify_password(password, "".into()).await,
+            Err(RwError::Unauthorized)
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_algorithm_should_make_new_hashes() {
+        let password = CleartextPassword("v3rys3cr3t".to_string());
+        let app = entrait::Impl::new(Unimock::new(
+            crate::GetConfigMock::password_algorithm
+                .each_call(matching!())
+                .returns(PasswordAlgorithm::Bcrypt),
+        ));
+        let hash = app.hash_password(password.clone()).await.unwrap();
+
+        assert!(hash.0.starts_with("$2b$"));
+        assert!(app.verify_password(password, hash).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn hashes_of_every_algorithm_should_be_verified() {
+        let app = entrait::Impl::new(());
+
+        for hash in [BCRYPT_HASH, SCRYPT_HASH] {
+            assert!(
+                app.verify_password("v3rys3cr3t".into(), hash.into())
+                    .await
+                    .is_ok(),
+                "{hash}"
+            );
+            assert_matches!(
+                app.verify_password("wrong_password".into(), hash.into())
+                    .await,
+                Err(RwError::Unauthorized)
+            );
+        }
+    }
+
+    #[test]
+    fn algorithm_should_be_told_by_hash_format() {
+        for (hash, algorithm) in [
+            (
+                "$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA",
+                Some(PasswordAlgorithm::Argon2id),
+            ),
+            (BCRYPT_HASH, Some(PasswordAlgorithm::Bcrypt)),
+            ("$2y$10$abc", Some(PasswordAlgorithm::Bcrypt)),
+            (SCRYPT_HASH, Some(PasswordAlgorithm::Scrypt)),
+            ("$pbkdf2-sha256$i=1000$c2FsdA$aGFzaA", None),
+            ("invalid_hash_format", None),
+        ] {
+            assert_eq!(
+                algorithm,
+                PasswordAlgorithm::of_hash(&hash.into()),
+                "{hash}"
+            );
+        }
     }
 }
@@ -0,0 +1,248 @@
+use super::email::{Email, EmailMessage};
+use super::opaque_token::OpaqueToken;
+use super::password::{CleartextPassword, HashPassword};
+use super::repo::{PasswordResetRepo, SessionRepo, UserRepo, UserUpdate};
+use crate::audit::{AuditAction, AuditLog, NewAuditEntry};
+use crate::error::{RwError, RwResult};
+use crate::{EmailSender, GetConfig, System};
+
+use entrait::entrait_export as entrait;
+
+const PASSWORD_RESET_LENGTH: time::Duration = time::Duration::hours(1);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: Email,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordReset {
+    pub reset_token: OpaqueToken,
+    pub password: CleartextPassword,
+}
+
+///
+/// Mail a one-time password reset token to the user owning the email address.
+///
+/// Succeeds even if there is no such user, so that the endpoint
+/// can't be used to find out which addresses are registered.
+///
+#[entrait(pub RequestPasswordReset, mock_api=RequestPasswordResetMock)]
+async fn request_password_reset(
+    deps: &(impl GetConfig + System + UserRepo + PasswordResetRepo + EmailSender),
+    request: PasswordResetRequest,
+) -> RwResult<()> {
+    let Some((user, credentials)) = super::find_credentials_by_email(deps, &request.email).await?
+    else {
+        return Ok(());
+    };
+
+    let token = OpaqueToken::generate();
+    deps.insert_password_reset(
+        user.user_id,
+        &token.hash(),
+        deps.get_current_time() + PASSWORD_RESET_LENGTH,
+    )
+    .await?;
+
+    deps.send_email(EmailMessage {
+        to: credentials.email,
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Use this code to choose a new password: {}\n\
+            If you didn't ask for a password reset, you can ignore this email.",
+            token.as_ref()
+        ),
+    })
+    .await
+}
+
+///
+/// Choose a new password with a reset token. Every session of the user is ended,
+/// since whoever made the reset necessary may be signed in.
+///
+#[entrait(pub ResetPassword, mock_api=ResetPasswordMock)]
+async fn reset_password(
+    deps: &(impl System + PasswordResetRepo + UserRepo + SessionRepo + HashPassword + AuditLog),
+    reset: PasswordReset,
+) -> RwResult<()> {
+    let user_id = deps
+        .take_password_reset(&reset.reset_token.hash(), deps.get_current_time())
+        .await?
+        .ok_or(RwError::InvalidToken)?;
+
+    let password_hash = deps.hash_password(reset.password).await?;
+    deps.update_user(
+        user_id,
+        UserUpdate {
+            password_hash: Some(password_hash),
+            ..Default::default()
+        },
+    )
+    .await?;
+    deps.delete_user_sessions(user_id).await?;
+
+    deps.record_audit(NewAuditEntry {
+        user_id: user_id.some(),
+        action: AuditAction::PasswordChange,
+        target: None,
+        ip_address: None,
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLogMock;
+    use crate::user::password::HashPasswordMock;
+    use crate::user::repo::{
+        Credentials, PasswordResetRepoMock, SessionRepoMock, User, UserRepoMock,
+    };
+    use crate::user::role::Role;
+    use crate::user::UserId;
+    use crate::EmailSenderMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    fn test_user() -> User {
+        User {
+            user_id: UserId(uuid::Uuid::new_v4()),
+            username: "Name".to_string(),
+            bio: "".to_string(),
+            image: None,
+            role: Role::User,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_store_and_send_reset_token() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!("name@email.com"))
+                .answers(&|_, email| {
+                    Ok(Some((
+                        test_user(),
+                        Credentials {
+                            email: email.clone(),
+                            password_hash: "h4sh".into(),
+                            email_verified: true,
+                        },
+                    )))
+                }),
+            PasswordResetRepoMock::insert_password_reset
+                .next_call(matching!(_, _, _))
+                .returns(Ok(())),
+            EmailSenderMock::send_email
+                .next_call(matching! {
+                    (message) if message.to.as_ref() == "name@email.com"
+                })
+                .returns(Ok(())),
+        ));
+
+        request_password_reset(
+            &deps,
+            PasswordResetRequest {
+                email: "name@email.com".parse().unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_email_should_not_be_revealed() {
+        let deps = Unimock::new((
+            crate::test::mock_plus_addressing(),
+            UserRepoMock::find_user_credentials_by_email
+                .next_call(matching!(_))
+                .returns(Ok(None)),
+        ));
+
+        request_password_reset(
+            &deps,
+            PasswordResetRequest {
+                email: "unknown@email.com".parse().unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reset_should_update_password_hash_and_end_sessions() {
+        let user_id = UserId(uuid::Uuid::new_v4());
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            PasswordResetRepoMock::take_password_reset
+                .next_call(matching! {
+                    (hash, _) if *hash == &OpaqueToken::from("r3s3t").hash()
+                })
+                .returns(Ok(Some(user_id))),
+            HashPasswordMock
+                .next_call(matching!("new password"))
+                .returns(Ok("n3w h4sh".into())),
+            UserRepoMock::update_user
+                .next_call(matching! {
+                    (_, update) if update.password_hash == Some("n3w h4sh".into())
+                })
+                .answers(&|_, user_id, _| {
+                    Ok((
+                        User {
+                            user_id,
+                            ..test_user()
+                        },
+                        Credentials {
+                            email: "name@email.com".parse().unwrap(),
+                            password_hash: "n3w h4sh".into(),
+                            email_verified: true,
+                        },
+                    ))
+                }),
+            SessionRepoMock::delete_user_sessions
+                .next_call(matching!(_))
+                .returns(Ok(2)),
+            AuditLogMock::record_audit
+                .next_call(matching!(NewAuditEntry {
+                    action: AuditAction::PasswordChange,
+                    ..
+                }))
+                .returns(Ok(())),
+        ));
+
+        reset_password(
+            &deps,
+            PasswordReset {
+                reset_token: "r3s3t".into(),
+                password: "new password".into(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_reset_token_should_be_rejected() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            PasswordResetRepoMock::take_password_reset
+                .next_call(matching!(_, _))
+                .returns(Ok(None)),
+        ));
+
+        assert_matches!(
+            reset_password(
+                &deps,
+                PasswordReset {
+                    reset_token: "t0k3n".into(),
+                    password: "new password".into(),
+                }
+            )
+            .await,
+            Err(RwError::InvalidToken)
+        );
+    }
+}
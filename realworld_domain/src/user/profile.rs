@@ -1,7 +1,183 @@
-#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+use super::repo::{FollowStats, Following, User, UserRepo};
+use super::UserId;
+use crate::error::{RwError, RwResult};
+
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Profile {
     pub username: String,
     pub bio: String,
     pub image: Option<String>,
     pub following: bool,
+    // The fields below are only included when a profile is fetched by itself,
+    // not when it's embedded as the author of an article or comment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub followers_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub following_count: Option<i64>,
+    /// Whether this user follows the current user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follows_you: Option<bool>,
+}
+
+impl From<(User, Following)> for Profile {
+    fn from((user, following): (User, Following)) -> Self {
+        Self {
+            username: user.username,
+            bio: user.bio,
+            image: user.image,
+            following: following.0,
+            followers_count: None,
+            following_count: None,
+            follows_you: None,
+        }
+    }
+}
+
+impl From<(User, Following, FollowStats)> for Profile {
+    fn from((user, following, stats): (User, Following, FollowStats)) -> Self {
+        Self {
+            followers_count: Some(stats.followers_count),
+            following_count: Some(stats.following_count),
+            follows_you: Some(stats.follows_you),
+            ..Self::from((user, following))
+        }
+    }
+}
+
+///
+/// Loads the profiles of the authors of many articles or comments with one query,
+/// instead of one query per article or comment.
+///
+/// A loader lives for one request. Loaded profiles are kept, so that loading the same
+/// author again doesn't query again, but they're only valid for the current user of that request.
+///
+pub struct ProfileLoader {
+    current_user: UserId<Option<Uuid>>,
+    profiles: HashMap<Uuid, Profile>,
+}
+
+impl ProfileLoader {
+    pub fn new(current_user: UserId<Option<Uuid>>) -> Self {
+        Self {
+            current_user,
+            profiles: HashMap::new(),
+        }
+    }
+
+    /// Load the profiles that haven't been loaded yet, if any
+    pub async fn load(
+        &mut self,
+        deps: &impl UserRepo,
+        user_ids: impl IntoIterator<Item = UserId>,
+    ) -> RwResult<()> {
+        let mut missing: Vec<Uuid> = user_ids
+            .into_iter()
+            .map(UserId::into_id)
+            .filter(|user_id| !self.profiles.contains_key(user_id))
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for (user, following) in deps
+            .select_users_by_ids(self.current_user, &missing)
+            .await?
+        {
+            self.profiles
+                .insert(user.user_id.into_id(), (user, following).into());
+        }
+        Ok(())
+    }
+
+    /// A profile that was loaded before.
+    /// Fails with `ProfileNotFound` when the user was deleted in the meantime.
+    pub fn get(&self, user_id: UserId) -> RwResult<Profile> {
+        self.profiles
+            .get(&user_id.into_id())
+            .cloned()
+            .ok_or(RwError::ProfileNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::repo::UserRepoMock;
+
+    use unimock::*;
+
+    fn test_user() -> User {
+        User {
+            user_id: UserId(Uuid::from_u128(1)),
+            username: "name".to_string(),
+            bio: "bio".to_string(),
+            image: None,
+            role: Default::default(),
+        }
+    }
+
+    #[test]
+    fn follow_stats_should_only_be_serialized_when_known() {
+        let embedded = Profile::from((test_user(), Following(true)));
+        assert_eq!(
+            serde_json::json!({
+                "username": "name",
+                "bio": "bio",
+                "image": null,
+                "following": true
+            }),
+            serde_json::to_value(embedded).unwrap()
+        );
+
+        let fetched = Profile::from((
+            test_user(),
+            Following(false),
+            FollowStats {
+                followers_count: 2,
+                following_count: 3,
+                follows_you: true,
+            },
+        ));
+        assert_eq!(
+            serde_json::json!({
+                "username": "name",
+                "bio": "bio",
+                "image": null,
+                "following": false,
+                "followersCount": 2,
+                "followingCount": 3,
+                "followsYou": true
+            }),
+            serde_json::to_value(fetched).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn loader_should_only_query_profiles_not_yet_loaded() {
+        let author = UserId(Uuid::from_u128(1));
+        let commenter = UserId(Uuid::from_u128(2));
+        let deps = Unimock::new((
+            UserRepoMock::select_users_by_ids
+                .next_call(matching!((UserId(None), ids) if *ids == [Uuid::from_u128(1)]))
+                .returns(Ok(vec![(test_user(), Following(false))])),
+            UserRepoMock::select_users_by_ids
+                .next_call(matching!((UserId(None), ids) if *ids == [Uuid::from_u128(2)]))
+                .returns(Ok(vec![])),
+        ));
+        let mut loader = ProfileLoader::new(UserId(None));
+
+        loader.load(&deps, [author, author]).await.unwrap();
+        loader.load(&deps, [author, commenter]).await.unwrap();
+        loader.load(&deps, [author]).await.unwrap();
+
+        assert_eq!("name", loader.get(author).unwrap().username);
+        assert!(loader.get(commenter).is_err());
+    }
 }
@@ -1,8 +1,16 @@
 use entrait::entrait_export as entrait;
 
+use super::oauth::OAuthIdentity;
+use super::opaque_token::OpaqueTokenHash;
 use super::password::PasswordHash;
+use super::role::Role;
 use super::{Email, UserId};
 use crate::error::RwResult;
+use crate::pagination::Pagination;
+
+use futures::stream::BoxStream;
+use std::net::IpAddr;
+use time::OffsetDateTime;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct User {
@@ -10,17 +18,50 @@ pub struct User {
     pub username: String,
     pub bio: String,
     pub image: Option<String>,
+    pub role: Role,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Credentials {
     pub email: Email,
     pub password_hash: PasswordHash,
+    pub email_verified: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Following(pub bool);
 
+/// Whether a username nobody has anymore finds the user who last renamed themselves from it
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PastUsernames(pub bool);
+
+/// Whether a user was created, as opposed to found
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Created(pub bool);
+
+/// Whether a user has been banned by an admin
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Banned(pub bool);
+
+/// Follow relations of a user, for showing on their profile
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct FollowStats {
+    pub followers_count: i64,
+    pub following_count: i64,
+    /// Whether the user follows the current user
+    pub follows_you: bool,
+}
+
+/// What happens to the articles and comments of a deleted user
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub enum DeletionMode {
+    /// Delete them along with the user
+    #[default]
+    Delete,
+    /// Keep them, under a user that has been stripped of everything identifying
+    Anonymize,
+}
+
 #[derive(Clone, Default)]
 pub struct UserUpdate<'a> {
     pub email: Option<&'a str>,
@@ -30,7 +71,23 @@ pub struct UserUpdate<'a> {
     pub image: Option<&'a str>,
 }
 
-#[entrait(UserRepoImpl, delegate_by=DelegateUserRepo, mock_api=UserRepoMock)]
+/// A user inserted as is, with a password hash made elsewhere
+#[derive(Clone, Debug)]
+pub struct ImportedUser {
+    pub username: String,
+    pub email: Email,
+    pub password_hash: PasswordHash,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(UserRepoImpl, delegate_by=DelegateUserRepo, mock_api=UserRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(UserRepoImpl, delegate_by=ref, mock_api=UserRepoMock),
+    async_trait::async_trait
+)]
 pub trait UserRepo {
     async fn insert_user(
         &self,
@@ -39,6 +96,10 @@ pub trait UserRepo {
         password_hash: PasswordHash,
     ) -> RwResult<(User, Credentials)>;
 
+    /// Insert the users at once, skipping those whose username or email is taken.
+    /// The usernames of the users that were inserted.
+    async fn insert_users(&self, users: &[ImportedUser]) -> RwResult<Vec<String>>;
+
     async fn find_user_credentials_by_id(
         &self,
         user_id: UserId,
@@ -49,11 +110,25 @@ pub trait UserRepo {
         email: &Email,
     ) -> RwResult<Option<(User, Credentials)>>;
 
+    ///
+    /// The user linked to the OAuth identity.
+    ///
+    /// An identity seen for the first time is linked to the user with the same email address,
+    /// but only if the provider has verified the address. Otherwise a user without a password
+    /// is created, named after the identity, or with a suffix if that username is taken.
+    ///
+    async fn find_or_create_by_oauth_identity(
+        &self,
+        identity: &OAuthIdentity,
+    ) -> RwResult<(User, Credentials, Created)>;
+
+    /// The user with the username. A user's current username wins over past ones.
     async fn find_user_by_username(
         &self,
         current_user: UserId<Option<uuid::Uuid>>,
         username: &str,
-    ) -> RwResult<Option<(User, Following)>>;
+        past_usernames: PastUsernames,
+    ) -> RwResult<Option<(User, Following, FollowStats)>>;
 
     async fn update_user(
         &self,
@@ -61,6 +136,243 @@ pub trait UserRepo {
         update: UserUpdate<'_>,
     ) -> RwResult<(User, Credentials)>;
 
+    async fn set_user_role(&self, user_id: UserId, role: Role) -> RwResult<()>;
+
+    /// Delete the user along with their favorites, follows, tokens and notifications
+    async fn delete_user(&self, user_id: UserId, mode: DeletionMode) -> RwResult<()>;
+
     async fn insert_follow(&self, current_user_id: UserId, username: &str) -> RwResult<()>;
     async fn delete_follow(&self, current_user_id: UserId, username: &str) -> RwResult<()>;
+
+    /// Hide the articles and comments of `username` from the current user
+    async fn insert_block(&self, current_user_id: UserId, username: &str) -> RwResult<()>;
+    async fn delete_block(&self, current_user_id: UserId, username: &str) -> RwResult<()>;
+
+    /// The users following `username`, most recent follow first.
+    /// `Following` is relative to the current user.
+    async fn list_followers(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>>;
+
+    /// The users `username` follows, most recent follow first.
+    /// `Following` is relative to the current user.
+    async fn list_following(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        username: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>>;
+
+    /// Users that aren't banned, whose username or bio contains `query`, regardless of case.
+    /// Usernames starting with `query` come first. `Following` is relative to the current user.
+    async fn search_users(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        query: &str,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Following)>>;
+
+    /// The users with any of the ids, in no particular order.
+    /// `Following` is relative to the current user.
+    async fn select_users_by_ids(
+        &self,
+        current_user: UserId<Option<uuid::Uuid>>,
+        user_ids: &[uuid::Uuid],
+    ) -> RwResult<Vec<(User, Following)>>;
+
+    /// All users, oldest first
+    async fn list_users(
+        &self,
+        pagination: Pagination,
+    ) -> RwResult<Vec<(User, Credentials, Banned)>>;
+
+    /// The usernames of all users that aren't banned, oldest first, read lazily
+    fn stream_usernames(&self) -> BoxStream<'static, RwResult<String>>;
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(BanRepoImpl, delegate_by=DelegateBanRepo, mock_api=BanRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(BanRepoImpl, delegate_by=ref, mock_api=BanRepoMock),
+    async_trait::async_trait
+)]
+pub trait BanRepo {
+    async fn set_user_banned(&self, user_id: UserId, banned: Banned) -> RwResult<()>;
+
+    /// Unknown users aren't banned
+    async fn is_user_banned(&self, user_id: UserId) -> RwResult<bool>;
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(LoginAttemptRepoImpl, delegate_by=DelegateLoginAttemptRepo, mock_api=LoginAttemptRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(LoginAttemptRepoImpl, delegate_by=ref, mock_api=LoginAttemptRepoMock),
+    async_trait::async_trait
+)]
+pub trait LoginAttemptRepo {
+    async fn insert_failed_login(
+        &self,
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        attempted_at: OffsetDateTime,
+    ) -> RwResult<()>;
+
+    /// Failed logins after `since`, either for the email address or from the IP address
+    async fn count_failed_logins(
+        &self,
+        email: &Email,
+        ip_address: Option<IpAddr>,
+        since: OffsetDateTime,
+    ) -> RwResult<i64>;
+
+    /// Forget the failed logins for the email address, after a successful login
+    async fn delete_failed_logins(&self, email: &Email) -> RwResult<()>;
+
+    /// Forget the failed logins older than `before`, returning how many
+    async fn delete_failed_logins_before(&self, before: OffsetDateTime) -> RwResult<u64>;
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(RefreshTokenRepoImpl, delegate_by=DelegateRefreshTokenRepo, mock_api=RefreshTokenRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(RefreshTokenRepoImpl, delegate_by=ref, mock_api=RefreshTokenRepoMock),
+    async_trait::async_trait
+)]
+pub trait RefreshTokenRepo {
+    async fn insert_refresh_token(
+        &self,
+        user_id: UserId,
+        session_id: uuid::Uuid,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()>;
+
+    /// Remove the refresh token, returning its owner and session if it was still valid at `now`.
+    /// A refresh token can only be used once.
+    async fn take_refresh_token(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<(UserId, uuid::Uuid)>>;
+
+    /// Remove the refresh tokens that expired before `now`, returning how many
+    async fn delete_expired_refresh_tokens(&self, now: OffsetDateTime) -> RwResult<u64>;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Session {
+    pub session_id: uuid::Uuid,
+    /// The `User-Agent` of the sign-in
+    pub device: Option<String>,
+    pub created_at: OffsetDateTime,
+    /// When the session was started or last refreshed
+    pub last_seen_at: OffsetDateTime,
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(SessionRepoImpl, delegate_by=DelegateSessionRepo, mock_api=SessionRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(SessionRepoImpl, delegate_by=ref, mock_api=SessionRepoMock),
+    async_trait::async_trait
+)]
+pub trait SessionRepo {
+    /// Start a session lasting until `expires_at`, returning its id
+    async fn insert_session(
+        &self,
+        user_id: UserId,
+        device: Option<&str>,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<uuid::Uuid>;
+
+    /// Mark the session as seen now, and extend it until `expires_at`.
+    /// Returns false when the session no longer exists.
+    async fn touch_session(
+        &self,
+        session_id: uuid::Uuid,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<bool>;
+
+    /// Whether the session exists and is still valid at `now`
+    async fn session_exists(&self, session_id: uuid::Uuid, now: OffsetDateTime) -> RwResult<bool>;
+
+    /// The sessions of the user that are still valid at `now`, most recently seen first
+    async fn select_sessions(&self, user_id: UserId, now: OffsetDateTime)
+        -> RwResult<Vec<Session>>;
+
+    /// End the session, removing its refresh token.
+    /// Fails with `SessionNotFound` unless the session belongs to the user.
+    async fn delete_session(&self, user_id: UserId, session_id: uuid::Uuid) -> RwResult<()>;
+
+    /// End all sessions of the user, returning how many
+    async fn delete_user_sessions(&self, user_id: UserId) -> RwResult<u64>;
+
+    /// Remove the sessions that expired before `now`, returning how many
+    async fn delete_expired_sessions(&self, now: OffsetDateTime) -> RwResult<u64>;
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(EmailVerificationRepoImpl, delegate_by=DelegateEmailVerificationRepo, mock_api=EmailVerificationRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(EmailVerificationRepoImpl, delegate_by=ref, mock_api=EmailVerificationRepoMock),
+    async_trait::async_trait
+)]
+pub trait EmailVerificationRepo {
+    async fn insert_email_verification(
+        &self,
+        user_id: UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()>;
+
+    /// Consume the verification token, marking the email address of its owner as verified.
+    /// Returns the owner if the token was still valid at `now`.
+    async fn take_email_verification(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<UserId>>;
+}
+
+#[cfg_attr(
+    not(feature = "dyn-repos"),
+    entrait(PasswordResetRepoImpl, delegate_by=DelegatePasswordResetRepo, mock_api=PasswordResetRepoMock)
+)]
+#[cfg_attr(
+    feature = "dyn-repos",
+    entrait(PasswordResetRepoImpl, delegate_by=ref, mock_api=PasswordResetRepoMock),
+    async_trait::async_trait
+)]
+pub trait PasswordResetRepo {
+    async fn insert_password_reset(
+        &self,
+        user_id: UserId,
+        token_hash: &OpaqueTokenHash,
+        expires_at: OffsetDateTime,
+    ) -> RwResult<()>;
+
+    /// Consume the reset token, returning its owner if it was still valid at `now`.
+    /// Other pending reset tokens of the same user are discarded as well.
+    async fn take_password_reset(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        now: OffsetDateTime,
+    ) -> RwResult<Option<UserId>>;
 }
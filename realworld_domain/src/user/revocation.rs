@@ -0,0 +1,23 @@
+use super::opaque_token::OpaqueTokenHash;
+use crate::error::RwResult;
+
+use entrait::entrait_export as entrait;
+
+///
+/// Access tokens that were revoked before they expired, e.g. by logging out.
+///
+/// Tokens are identified by the hash of the whole token string,
+/// the same way refresh tokens are.
+///
+#[entrait(mock_api=TokenRevocationListMock)]
+pub trait TokenRevocationList {
+    /// Reject the token from now on. It only needs to be remembered until `expires_at`,
+    /// after which it's rejected anyway.
+    async fn revoke_token(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        expires_at: time::OffsetDateTime,
+    ) -> RwResult<()>;
+
+    async fn is_token_revoked(&self, token_hash: &OpaqueTokenHash) -> RwResult<bool>;
+}
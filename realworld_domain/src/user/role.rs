@@ -0,0 +1,70 @@
+///
+/// The role of a user. Roles are ordered, so that a role has
+/// all the privileges of the roles ranked below it.
+///
+#[derive(
+    sqlx::Type,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+)]
+#[sqlx(type_name = "app.user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn is_user(&self) -> bool {
+        *self == Self::User
+    }
+
+    /// Whether this role may delete articles written by other users
+    pub fn can_moderate_articles(self) -> bool {
+        self >= Self::Admin
+    }
+
+    /// Whether this role may delete comments written by other users
+    pub fn can_moderate_comments(self) -> bool {
+        self >= Self::Moderator
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "user" => Ok(Self::User),
+            "moderator" => Ok(Self::Moderator),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!("Unknown role: {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_should_be_ordered_by_privilege() {
+        assert!(Role::User < Role::Moderator);
+        assert!(Role::Moderator < Role::Admin);
+
+        assert!(!Role::User.can_moderate_comments());
+        assert!(Role::Moderator.can_moderate_comments());
+        assert!(!Role::Moderator.can_moderate_articles());
+        assert!(Role::Admin.can_moderate_articles());
+    }
+}
@@ -0,0 +1,122 @@
+//!
+//! Sessions: one per sign-in, lasting as long as its refresh token keeps being exchanged.
+//!
+//! Access tokens carry the id of their session. Revoking a session removes its refresh token,
+//! and the access token the device already has stops working along with it.
+//!
+
+use super::auth::{Authenticate, Token};
+use super::repo::{self, SessionRepo};
+use crate::error::RwResult;
+use crate::timestamp::Timestamptz;
+use crate::System;
+
+use entrait::entrait_export as entrait;
+use uuid::Uuid;
+
+/// A session as seen by its user
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    id: Uuid,
+    device: Option<String>,
+    created_at: Timestamptz,
+    last_seen_at: Timestamptz,
+}
+
+impl From<repo::Session> for Session {
+    fn from(db: repo::Session) -> Self {
+        Self {
+            id: db.session_id,
+            device: db.device,
+            created_at: Timestamptz(db.created_at),
+            last_seen_at: Timestamptz(db.last_seen_at),
+        }
+    }
+}
+
+#[entrait(pub Api, mock_api=mock)]
+pub mod api {
+    use super::*;
+
+    /// The active sessions of the current user, most recently seen first
+    pub async fn list_sessions(
+        deps: &(impl System + Authenticate + SessionRepo),
+        token: Token,
+    ) -> RwResult<Vec<Session>> {
+        let current_user_id = deps.authenticate(token).await?;
+        Ok(deps
+            .select_sessions(current_user_id, deps.get_current_time())
+            .await?
+            .into_iter()
+            .map(Session::from)
+            .collect())
+    }
+
+    /// Sign out the current user on the device of the session
+    pub async fn revoke_session(
+        deps: &(impl Authenticate + SessionRepo),
+        token: Token,
+        session_id: Uuid,
+    ) -> RwResult<()> {
+        let current_user_id = deps.authenticate(token).await?;
+        deps.delete_session(current_user_id, session_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RwError;
+    use crate::user::auth::authenticate::AuthenticateMock;
+    use crate::user::repo::SessionRepoMock;
+    use crate::user::UserId;
+
+    use unimock::*;
+
+    #[tokio::test]
+    async fn should_list_sessions_of_current_user() {
+        let user_id = UserId(Uuid::new_v4());
+        let session_id = Uuid::new_v4();
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(user_id)),
+            SessionRepoMock::select_sessions
+                .next_call(matching!(_, _))
+                .returns(Ok(vec![repo::Session {
+                    session_id,
+                    device: Some("curl/8.0".to_string()),
+                    created_at: time::OffsetDateTime::UNIX_EPOCH,
+                    last_seen_at: time::OffsetDateTime::UNIX_EPOCH,
+                }])),
+        ));
+
+        let sessions = api::list_sessions(&deps, Token::from_token("token"))
+            .await
+            .unwrap();
+
+        assert_eq!(1, sessions.len());
+        assert_eq!(session_id, sessions[0].id);
+        assert_eq!(Some("curl/8.0"), sessions[0].device.as_deref());
+    }
+
+    #[tokio::test]
+    async fn revoking_session_of_other_user_should_not_be_found() {
+        let deps = Unimock::new((
+            AuthenticateMock::authenticate
+                .next_call(matching!(_))
+                .returns(Ok(UserId(Uuid::new_v4()))),
+            SessionRepoMock::delete_session
+                .next_call(matching!(_, _))
+                .returns(Err(RwError::SessionNotFound)),
+        ));
+
+        assert_matches::assert_matches!(
+            api::revoke_session(&deps, Token::from_token("token"), Uuid::new_v4()).await,
+            Err(RwError::SessionNotFound)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::email::{Email, EmailMessage};
+use super::opaque_token::OpaqueToken;
+use super::repo::EmailVerificationRepo;
+use super::UserId;
+use crate::error::{RwError, RwResult};
+use crate::{EmailSender, System};
+
+use entrait::entrait_export as entrait;
+
+const EMAIL_VERIFICATION_LENGTH: time::Duration = time::Duration::days(2);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailVerification {
+    pub verification_token: OpaqueToken,
+}
+
+///
+/// Mail a verification token to the given address.
+///
+#[entrait(pub SendEmailVerification, mock_api=SendEmailVerificationMock)]
+async fn send_email_verification(
+    deps: &(impl System + EmailVerificationRepo + EmailSender),
+    user_id: UserId,
+    email: &Email,
+) -> RwResult<()> {
+    let token = OpaqueToken::generate();
+    deps.insert_email_verification(
+        user_id,
+        &token.hash(),
+        deps.get_current_time() + EMAIL_VERIFICATION_LENGTH,
+    )
+    .await?;
+
+    deps.send_email(EmailMessage {
+        to: email.clone(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Use this code to verify your email address: {}",
+            token.as_ref()
+        ),
+    })
+    .await
+}
+
+#[entrait(pub VerifyEmail, mock_api=VerifyEmailMock)]
+async fn verify_email(
+    deps: &(impl System + EmailVerificationRepo),
+    verification: EmailVerification,
+) -> RwResult<()> {
+    deps.take_email_verification(
+        &verification.verification_token.hash(),
+        deps.get_current_time(),
+    )
+    .await?
+    .ok_or(RwError::InvalidToken)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::repo::EmailVerificationRepoMock;
+    use crate::EmailSenderMock;
+
+    use assert_matches::*;
+    use unimock::*;
+
+    #[tokio::test]
+    async fn should_store_and_send_verification_token() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            EmailVerificationRepoMock::insert_email_verification
+                .next_call(matching!(_, _, _))
+                .returns(Ok(())),
+            EmailSenderMock::send_email
+                .next_call(matching! {
+                    (message) if message.to.as_ref() == "name@email.com"
+                })
+                .returns(Ok(())),
+        ));
+
+        send_email_verification(
+            &deps,
+            UserId(uuid::Uuid::new_v4()),
+            &"name@email.com".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_verification_token_should_be_rejected() {
+        let deps = Unimock::new((
+            crate::test::mock_current_time(),
+            EmailVerificationRepoMock::take_email_verification
+                .next_call(matching!(_, _))
+                .returns(Ok(None)),
+        ));
+
+        assert_matches!(
+            verify_email(
+                &deps,
+                EmailVerification {
+                    verification_token: "t0k3n".into()
+                }
+            )
+            .await,
+            Err(RwError::InvalidToken)
+        );
+    }
+}
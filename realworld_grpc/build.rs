@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_build::configure()
+        // The messages are converted to and from the domain's JSON types
+        .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+        .type_attribute(".", r#"#[serde(rename_all = "camelCase", default)]"#)
+        .compile_protos(&["proto/realworld.proto"], &["proto"])?;
+
+    Ok(())
+}
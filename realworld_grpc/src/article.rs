@@ -0,0 +1,222 @@
+use crate::auth::{opt_token, token};
+use crate::proto::{
+    self, ArticleList, ArticleRequest, CreateArticleRequest, Empty, ListArticlesRequest,
+};
+use crate::{convert, status};
+
+use realworld_domain::article::{self, FetchArticleQuery, Slug};
+
+use tonic::{Request, Response, Status};
+
+pub struct ArticleService<D>(pub D);
+
+impl From<article::Article> for proto::Article {
+    fn from(article: article::Article) -> Self {
+        Self {
+            slug: article.slug.0,
+            title: article.title,
+            description: article.description,
+            // Summaries have no body
+            body: article.body.unwrap_or_default(),
+            tag_list: article.tag_list,
+            created_at: article.created_at.to_string(),
+            updated_at: article.updated_at.to_string(),
+            favorited: article.favorited,
+            favorites_count: article.favorites_count,
+            views_count: article.views_count,
+            author: Some(proto::Profile {
+                username: article.author.username,
+                bio: article.author.bio,
+                image: article.author.image,
+                following: article.author.following,
+            }),
+            id: article.id,
+        }
+    }
+}
+
+impl<D: article::Api> ArticleService<D> {
+    async fn set_favorite(
+        &self,
+        request: Request<ArticleRequest>,
+        value: bool,
+    ) -> Result<Response<proto::Article>, Status> {
+        let token = token(&request)?;
+        let article = self
+            .0
+            .favorite_article(token, &Slug(request.get_ref().slug.clone()), value)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(article.into()))
+    }
+}
+
+#[tonic::async_trait]
+impl<D> proto::article_service_server::ArticleService for ArticleService<D>
+where
+    D: article::Api + Send + Sync + 'static,
+{
+    async fn list_articles(
+        &self,
+        request: Request<ListArticlesRequest>,
+    ) -> Result<Response<ArticleList>, Status> {
+        let token = opt_token(&request);
+        let query = convert(request.into_inner())?;
+        let list = self.0.list_articles(token, query).await.map_err(status)?;
+        Ok(Response::new(ArticleList {
+            articles: list
+                .articles
+                .into_iter()
+                .map(proto::Article::from)
+                .collect(),
+        }))
+    }
+
+    /// Counts as a view, like fetching the article over HTTP
+    async fn get_article(
+        &self,
+        request: Request<ArticleRequest>,
+    ) -> Result<Response<proto::Article>, Status> {
+        let ip_address = request.remote_addr().map(|addr| addr.ip());
+        let article = self
+            .0
+            .fetch_article(
+                opt_token(&request),
+                &Slug(request.get_ref().slug.clone()),
+                FetchArticleQuery::default(),
+                ip_address,
+            )
+            .await
+            .map_err(status)?;
+        Ok(Response::new(article.into()))
+    }
+
+    async fn create_article(
+        &self,
+        request: Request<CreateArticleRequest>,
+    ) -> Result<Response<proto::Article>, Status> {
+        let token = token(&request)?;
+        let article = self
+            .0
+            .create_article(token, convert(request.into_inner())?)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(article.into()))
+    }
+
+    async fn delete_article(
+        &self,
+        request: Request<ArticleRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let token = token(&request)?;
+        self.0
+            .delete_article(token, &Slug(request.get_ref().slug.clone()))
+            .await
+            .map_err(status)?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn favorite_article(
+        &self,
+        request: Request<ArticleRequest>,
+    ) -> Result<Response<proto::Article>, Status> {
+        self.set_favorite(request, true).await
+    }
+
+    async fn unfavorite_article(
+        &self,
+        request: Request<ArticleRequest>,
+    ) -> Result<Response<proto::Article>, Status> {
+        self.set_favorite(request, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::article_service_server::ArticleService as _;
+    use realworld_domain::article::ArticleList as DomainArticleList;
+
+    use unimock::*;
+
+    fn test_article() -> article::Article {
+        serde_json::from_value(serde_json::json!({
+            "id": "0000000000000000000001",
+            "slug": "how-to-train-your-dragon",
+            "title": "How to train your dragon",
+            "description": "Ever wonder how?",
+            "body": "It takes a Jacobian",
+            "tag_list": ["dragons", "training"],
+            "created_at": "2016-02-18T03:22:56.637Z",
+            "updated_at": "2016-02-18T03:48:35.824Z",
+            "favorited": false,
+            "favorites_count": 3,
+            "views_count": 7,
+            "author": {
+                "username": "jake",
+                "bio": "I work at statefarm",
+                "image": null,
+                "following": false
+            },
+            "word_count": 4,
+            "reading_time_minutes": 1
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn listed_articles_should_be_converted_to_messages() {
+        let service = ArticleService(Unimock::new(
+            article::api::mock::list_articles
+                .next_call(matching!(None, _))
+                .answers(&|_, _, _| {
+                    Ok(DomainArticleList {
+                        articles: vec![test_article()],
+                        next_cursor: None,
+                    })
+                }),
+        ));
+
+        let articles = service
+            .list_articles(Request::new(ListArticlesRequest::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .articles;
+
+        assert_eq!(
+            vec![proto::Article {
+                slug: "how-to-train-your-dragon".to_string(),
+                title: "How to train your dragon".to_string(),
+                description: "Ever wonder how?".to_string(),
+                body: "It takes a Jacobian".to_string(),
+                tag_list: vec!["dragons".to_string(), "training".to_string()],
+                created_at: "2016-02-18T03:22:56.637Z".to_string(),
+                updated_at: "2016-02-18T03:48:35.824Z".to_string(),
+                favorited: false,
+                favorites_count: 3,
+                views_count: 7,
+                author: Some(proto::Profile {
+                    username: "jake".to_string(),
+                    bio: "I work at statefarm".to_string(),
+                    image: None,
+                    following: false,
+                }),
+                id: "0000000000000000000001".to_string(),
+            }],
+            articles
+        );
+    }
+
+    #[tokio::test]
+    async fn creating_article_should_require_token() {
+        let service = ArticleService(Unimock::new(()));
+
+        let status = service
+            .create_article(Request::new(CreateArticleRequest::default()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(tonic::Code::Unauthenticated, status.code());
+    }
+}
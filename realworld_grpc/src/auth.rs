@@ -0,0 +1,62 @@
+use realworld_domain::error::RwError;
+use realworld_domain::user::auth::Token;
+
+use tonic::{Request, Status};
+
+///
+/// Interceptor taking the `Token <jwt>` of the `authorization` metadata, like the HTTP header.
+///
+/// The token is only verified by the domain functions that need it,
+/// the same way as for HTTP requests.
+///
+pub fn authenticate(mut request: Request<()>) -> Result<Request<()>, Status> {
+    if let Some(value) = request.metadata().get("authorization") {
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Token "))
+            .ok_or_else(|| Status::unauthenticated("expected `Token <jwt>` authorization"))?;
+        let token = Token::from_token(token);
+        request.extensions_mut().insert(token);
+    }
+    Ok(request)
+}
+
+/// The token of an authenticated request, if any
+pub(crate) fn opt_token<T>(request: &Request<T>) -> Option<Token> {
+    request.extensions().get::<Token>().cloned()
+}
+
+pub(crate) fn token<T>(request: &Request<T>) -> Result<Token, Status> {
+    opt_token(request).ok_or_else(|| crate::status(RwError::Unauthorized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_should_be_taken_from_metadata() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", "Token abc".parse().unwrap());
+
+        let request = authenticate(request).unwrap();
+
+        assert_eq!("abc", token(&request).unwrap().token());
+    }
+
+    #[test]
+    fn other_schemes_should_be_unauthenticated() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer abc".parse().unwrap());
+
+        assert_eq!(
+            tonic::Code::Unauthenticated,
+            authenticate(request).unwrap_err().code()
+        );
+    }
+}
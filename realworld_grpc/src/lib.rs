@@ -0,0 +1,110 @@
+//!
+//! The RealWorld API over gRPC, as described by `proto/realworld.proto`.
+//!
+//! The services call the same `realworld_domain` functions as the HTTP handlers of `realworld_app`,
+//! for any `D` implementing them. Messages are converted to and from the domain's types
+//! by way of their JSON form, so that the two APIs stay the same.
+//!
+
+// `tonic::Status` is large, but it's the error of every gRPC method anyway
+#![allow(clippy::result_large_err)]
+
+mod article;
+mod auth;
+mod profile;
+mod user;
+
+pub use article::ArticleService;
+pub use auth::authenticate;
+pub use profile::ProfileService;
+pub use user::UserService;
+
+use proto::article_service_server::ArticleServiceServer;
+use proto::profile_service_server::ProfileServiceServer;
+use proto::user_service_server::UserServiceServer;
+use realworld_domain::article::Api as ArticleApi;
+use realworld_domain::error::RwError;
+use realworld_domain::user::{Create, FetchCurrent, FetchProfile, Follow, Login};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::net::SocketAddr;
+use tonic::codegen::http::StatusCode;
+use tonic::transport::Server;
+use tonic::{Code, Status};
+
+pub mod proto {
+    tonic::include_proto!("realworld.v1");
+}
+
+/// Serves all the services on `addr`, until the server fails
+pub async fn serve<D>(deps: D, addr: SocketAddr) -> Result<(), tonic::transport::Error>
+where
+    D: ArticleApi
+        + Create
+        + Login
+        + FetchCurrent
+        + FetchProfile
+        + Follow
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    Server::builder()
+        .add_service(UserServiceServer::with_interceptor(
+            UserService(deps.clone()),
+            authenticate,
+        ))
+        .add_service(ProfileServiceServer::with_interceptor(
+            ProfileService(deps.clone()),
+            authenticate,
+        ))
+        .add_service(ArticleServiceServer::with_interceptor(
+            ArticleService(deps),
+            authenticate,
+        ))
+        .serve(addr)
+        .await
+}
+
+/// Converts between a domain type and a message with the same JSON form
+fn convert<T: Serialize, U: DeserializeOwned>(value: T) -> Result<U, Status> {
+    serde_json::to_value(value)
+        .and_then(serde_json::from_value)
+        .map_err(|error| Status::invalid_argument(error.to_string()))
+}
+
+/// The gRPC counterpart of the HTTP status of the error
+fn status(error: RwError) -> Status {
+    let code = match error.status_code() {
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::PRECONDITION_FAILED => Code::FailedPrecondition,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        status if status.is_client_error() => Code::InvalidArgument,
+        _ => {
+            tracing::error!("Generic error: {:?}", error);
+            Code::Internal
+        }
+    };
+    Status::new(code, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_should_have_grpc_codes() {
+        assert_eq!(Code::Unauthenticated, status(RwError::Unauthorized).code());
+        assert_eq!(Code::NotFound, status(RwError::ArticleNotFound).code());
+        assert_eq!(Code::InvalidArgument, status(RwError::UsernameTaken).code());
+        assert_eq!(
+            Code::Internal,
+            status(RwError::Anyhow(anyhow::anyhow!("oops"))).code()
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::auth::{opt_token, token};
+use crate::proto::{self, ProfileRequest};
+use crate::{convert, status};
+
+use realworld_domain::user::{FetchProfile, Follow};
+
+use tonic::{Request, Response, Status};
+
+pub struct ProfileService<D>(pub D);
+
+impl<D: Follow> ProfileService<D> {
+    async fn set_following(
+        &self,
+        request: Request<ProfileRequest>,
+        value: bool,
+    ) -> Result<Response<proto::Profile>, Status> {
+        let token = token(&request)?;
+        let profile = self
+            .0
+            .follow(token, &request.get_ref().username, value)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(convert(profile)?))
+    }
+}
+
+#[tonic::async_trait]
+impl<D> proto::profile_service_server::ProfileService for ProfileService<D>
+where
+    D: FetchProfile + Follow + Send + Sync + 'static,
+{
+    async fn get_profile(
+        &self,
+        request: Request<ProfileRequest>,
+    ) -> Result<Response<proto::Profile>, Status> {
+        let profile = self
+            .0
+            .fetch_profile(opt_token(&request), &request.get_ref().username)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(convert(profile)?))
+    }
+
+    async fn follow(
+        &self,
+        request: Request<ProfileRequest>,
+    ) -> Result<Response<proto::Profile>, Status> {
+        self.set_following(request, true).await
+    }
+
+    async fn unfollow(
+        &self,
+        request: Request<ProfileRequest>,
+    ) -> Result<Response<proto::Profile>, Status> {
+        self.set_following(request, false).await
+    }
+}
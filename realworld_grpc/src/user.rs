@@ -0,0 +1,58 @@
+use crate::auth::token;
+use crate::proto::{self, Empty, LoginRequest, RegisterRequest};
+use crate::{convert, status};
+
+use realworld_domain::user::{Create, FetchCurrent, Login};
+
+use tonic::{Request, Response, Status};
+
+pub struct UserService<D>(pub D);
+
+#[tonic::async_trait]
+impl<D> proto::user_service_server::UserService for UserService<D>
+where
+    D: Create + Login + FetchCurrent + Send + Sync + 'static,
+{
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<proto::User>, Status> {
+        let device = device(&request);
+        let new_user = convert(request.into_inner())?;
+        let user = self.0.create(new_user, device).await.map_err(status)?;
+        Ok(Response::new(convert(user)?))
+    }
+
+    async fn login(&self, request: Request<LoginRequest>) -> Result<Response<proto::User>, Status> {
+        let ip_address = request.remote_addr().map(|addr| addr.ip());
+        let device = device(&request);
+        let login_user = convert(request.into_inner())?;
+        let user = self
+            .0
+            .login(login_user, ip_address, device)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(convert(user)?))
+    }
+
+    async fn get_current_user(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<proto::User>, Status> {
+        let user = self
+            .0
+            .fetch_current(token(&request)?)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(convert(user)?))
+    }
+}
+
+/// The device signing in, as told by its `user-agent`
+fn device<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get("user-agent")
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(str::to_string)
+}
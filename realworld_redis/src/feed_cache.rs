@@ -0,0 +1,125 @@
+//!
+//! Each user's feed is a hash with one field per page, and expires after the feed TTL.
+//! Invalidating all feeds bumps a generation number that's part of every key,
+//! leaving the old keys to expire on their own.
+//!
+
+use crate::Redis;
+
+use realworld_domain::article::feed_cache::{FeedCache, FeedPage};
+use realworld_domain::article::Article;
+use realworld_domain::user::UserId;
+
+use redis::AsyncCommands;
+
+const GENERATION_KEY: &str = "realworld:feed:generation";
+
+fn feed_key(generation: u64, UserId(user_id): UserId) -> String {
+    format!("realworld:feed:{generation}:{user_id}")
+}
+
+fn page_field(page: FeedPage) -> String {
+    let format = |n: Option<i64>| n.map(|n| n.to_string()).unwrap_or_default();
+    format!("{}:{}", format(page.limit), format(page.offset))
+}
+
+impl Redis {
+    async fn current_feed_key(&self, user_id: UserId) -> anyhow::Result<String> {
+        let generation: Option<u64> = self.connection().get(GENERATION_KEY).await?;
+        Ok(feed_key(generation.unwrap_or_default(), user_id))
+    }
+
+    async fn get_feed(
+        &self,
+        user_id: UserId,
+        page: FeedPage,
+    ) -> anyhow::Result<Option<Vec<Article>>> {
+        let key = self.current_feed_key(user_id).await?;
+        let json: Option<String> = self.connection().hget(key, page_field(page)).await?;
+
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    async fn insert_feed(
+        &self,
+        user_id: UserId,
+        page: FeedPage,
+        articles: &[Article],
+    ) -> anyhow::Result<()> {
+        let key = self.current_feed_key(user_id).await?;
+        redis::pipe()
+            .atomic()
+            .hset(&key, page_field(page), serde_json::to_string(articles)?)
+            .expire(&key, self.feed_ttl.as_secs() as i64)
+            .query_async::<_, ()>(&mut self.connection())
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_feed(&self, user_id: UserId) -> anyhow::Result<()> {
+        let key = self.current_feed_key(user_id).await?;
+        self.connection().del::<_, ()>(key).await?;
+        Ok(())
+    }
+
+    async fn bump_feed_generation(&self) -> anyhow::Result<()> {
+        self.connection()
+            .incr::<_, _, ()>(GENERATION_KEY, 1)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Failures only make the feed slower, so they're logged and treated as misses.
+fn log_failure<T: Default>(error: anyhow::Error) -> T {
+    tracing::warn!("feed cache failure: {error:#}");
+    T::default()
+}
+
+impl FeedCache for Redis {
+    async fn get_cached_feed(&self, user_id: UserId, page: FeedPage) -> Option<Vec<Article>> {
+        self.get_feed(user_id, page)
+            .await
+            .unwrap_or_else(log_failure)
+    }
+
+    async fn cache_feed(&self, user_id: UserId, page: FeedPage, articles: Vec<Article>) {
+        self.insert_feed(user_id, page, &articles)
+            .await
+            .unwrap_or_else(log_failure)
+    }
+
+    async fn invalidate_feed(&self, user_id: UserId) {
+        self.delete_feed(user_id).await.unwrap_or_else(log_failure)
+    }
+
+    async fn invalidate_all_feeds(&self) {
+        self.bump_feed_generation()
+            .await
+            .unwrap_or_else(log_failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_should_have_distinct_fields() {
+        assert_eq!(":", page_field(FeedPage::default()));
+        assert_eq!(
+            "10:",
+            page_field(FeedPage {
+                limit: Some(10),
+                offset: None
+            })
+        );
+        assert_eq!(
+            ":10",
+            page_field(FeedPage {
+                limit: None,
+                offset: Some(10)
+            })
+        );
+    }
+}
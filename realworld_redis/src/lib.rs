@@ -0,0 +1,46 @@
+//!
+//! Redis implementations of the state that has to be shared between instances of the app:
+//! revoked tokens, cached feeds and rate limiter buckets.
+//!
+//! The app keeps this state in process unless it's configured with a `redis_url`.
+//!
+
+use anyhow::Context;
+use std::time::Duration;
+
+mod feed_cache;
+mod rate_limit;
+mod revocation;
+
+const DEFAULT_FEED_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+pub struct Redis {
+    connection: redis::aio::ConnectionManager,
+    feed_ttl: Duration,
+}
+
+impl Redis {
+    /// Connect to e.g. `redis://localhost:6379`.
+    /// The connection is re-established automatically if it's lost.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url).context("invalid redis_url")?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .context("could not connect to redis_url")?;
+
+        Ok(Self {
+            connection,
+            feed_ttl: DEFAULT_FEED_TTL,
+        })
+    }
+
+    /// How long cached feeds are kept
+    pub fn with_feed_ttl(self, feed_ttl: Duration) -> Self {
+        Self { feed_ttl, ..self }
+    }
+
+    fn connection(&self) -> redis::aio::ConnectionManager {
+        self.connection.clone()
+    }
+}
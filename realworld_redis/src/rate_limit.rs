@@ -0,0 +1,62 @@
+use crate::Redis;
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Token bucket, refilled according to the time of the Redis server
+/// so that the clocks of the app instances don't matter.
+///
+/// Returns the number of seconds to wait before retrying, or 0 when a token was taken.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local per_second = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local updated_at = tonumber(bucket[2]) or now
+
+tokens = math.min(burst, tokens + math.max(0, now - updated_at) * per_second)
+
+local retry_after = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    retry_after = (1 - tokens) / per_second
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(burst / per_second * 1000))
+
+-- numbers would be truncated to integers
+return tostring(retry_after)
+"#;
+
+static TAKE_TOKEN: OnceLock<redis::Script> = OnceLock::new();
+
+impl Redis {
+    ///
+    /// Take a token from the bucket of a client, shared between all instances of the app.
+    ///
+    /// Returns how long the client has to wait if the bucket is empty.
+    ///
+    pub async fn take_rate_limit_token(
+        &self,
+        client: &str,
+        per_second: f64,
+        burst: f64,
+    ) -> anyhow::Result<Option<Duration>> {
+        let retry_after: String = TAKE_TOKEN
+            .get_or_init(|| redis::Script::new(TAKE_TOKEN_SCRIPT))
+            .key(format!("realworld:rate_limit:{client}"))
+            .arg(per_second)
+            .arg(burst)
+            .invoke_async(&mut self.connection())
+            .await?;
+
+        let retry_after: f64 = retry_after.parse()?;
+        Ok((retry_after > 0.0).then(|| Duration::from_secs_f64(retry_after)))
+    }
+}
@@ -0,0 +1,41 @@
+use crate::Redis;
+
+use realworld_domain::error::RwResult;
+use realworld_domain::user::opaque_token::OpaqueTokenHash;
+use realworld_domain::user::revocation::TokenRevocationList;
+
+use redis::{AsyncCommands, SetExpiry, SetOptions};
+
+fn revoked_token_key(token_hash: &OpaqueTokenHash) -> String {
+    format!("realworld:revoked_token:{}", token_hash.as_ref())
+}
+
+impl TokenRevocationList for Redis {
+    async fn revoke_token(
+        &self,
+        token_hash: &OpaqueTokenHash,
+        expires_at: time::OffsetDateTime,
+    ) -> RwResult<()> {
+        let Ok(expires_at) = usize::try_from(expires_at.unix_timestamp()) else {
+            return Ok(());
+        };
+
+        self.connection()
+            .set_options::<_, _, ()>(
+                revoked_token_key(token_hash),
+                1,
+                SetOptions::default().with_expiration(SetExpiry::EXAT(expires_at)),
+            )
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn is_token_revoked(&self, token_hash: &OpaqueTokenHash) -> RwResult<bool> {
+        Ok(self
+            .connection()
+            .exists(revoked_token_key(token_hash))
+            .await
+            .map_err(anyhow::Error::from)?)
+    }
+}
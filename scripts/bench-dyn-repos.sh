@@ -0,0 +1,23 @@
+#!/bin/sh
+#
+# Compares the static and the `dyn-repos` wiring of the repositories:
+# the time of an incremental build of the app after a change in the domain,
+# and the latency of requests, see realworld_app/src/e2e/bench.rs.
+#
+# Needs the database of the tests, see docker-compose.yml.
+#
+set -e
+cd "$(dirname "$0")/.."
+
+for features in "" "dyn-repos"; do
+    echo "== ${features:-static}"
+    cargo build -q -p realworld-app --features "$features"
+
+    touch realworld_domain/src/lib.rs
+    start=$(date +%s%N)
+    cargo build -q -p realworld-app --features "$features"
+    end=$(date +%s%N)
+    echo "incremental build: $(((end - start) / 1000000)) ms"
+
+    cargo test -q --release -p realworld-app --features "$features" e2e::bench -- --ignored --nocapture
+done